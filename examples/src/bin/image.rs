@@ -49,14 +49,18 @@ fn main() {
         let caps = window.surface().get_capabilities(&physical).expect("failed to get surface capabilities");
 
         let dimensions = caps.current_extent.unwrap_or([1280, 1024]);
-        let present = caps.present_modes.iter().next().unwrap();
+        let present = caps.choose_present_mode(&[vulkano::swapchain::PresentMode::Mailbox]);
         let usage = caps.supported_usage_flags;
 
         vulkano::swapchain::Swapchain::new(&device, &window.surface(), 3,
-                                           vulkano::format::B8G8R8A8Srgb, dimensions, 1,
+                                           vulkano::format::B8G8R8A8Srgb,
+                                           vulkano::swapchain::ColorSpace::SrgbNonLinear,
+                                           dimensions, 1,
                                            &usage, &queue, vulkano::swapchain::SurfaceTransform::Identity,
                                            vulkano::swapchain::CompositeAlpha::Opaque,
-                                           present, true, None).expect("failed to create swapchain")
+                                           present, true,
+                                           vulkano::swapchain::FullScreenExclusive::Default,
+                                           None).expect("failed to create swapchain")
     };
 
 
@@ -142,7 +146,6 @@ fn main() {
                                                  vulkano::sampler::SamplerAddressMode::Repeat,
                                                  0.0, 1.0, 0.0, 0.0).unwrap();
 
-    let descriptor_pool = vulkano::descriptor::descriptor_set::DescriptorPool::new(&device);
     mod pipeline_layout {
         pipeline_layout!{
             set0: {
@@ -152,7 +155,7 @@ fn main() {
     }
 
     let pipeline_layout = pipeline_layout::CustomPipeline::new(&device).unwrap();
-    let set = pipeline_layout::set0::Set::new(&descriptor_pool, &pipeline_layout, &pipeline_layout::set0::Descriptors {
+    let set = pipeline_layout::set0::Set::new(&pipeline_layout, &pipeline_layout::set0::Descriptors {
         tex: (&sampler, &texture)
     });
 
@@ -209,7 +212,7 @@ fn main() {
     }).collect::<Vec<_>>();
 
     loop {
-        let image_num = swapchain.acquire_next_image(Duration::new(10, 0)).unwrap();
+        let (image_num, _) = swapchain.acquire_next_image(Duration::new(10, 0)).unwrap();
         vulkano::command_buffer::submit(&command_buffers[image_num], &queue).unwrap();
         swapchain.present(&queue, image_num).unwrap();
 
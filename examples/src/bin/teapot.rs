@@ -55,14 +55,19 @@ fn main() {
         let caps = window.surface().get_capabilities(&physical).expect("failed to get surface capabilities");
 
         let dimensions = caps.current_extent.unwrap_or([1280, 1024]);
-        let present = caps.present_modes.iter().next().unwrap();
         let usage = caps.supported_usage_flags;
-        let format = caps.supported_formats[0].0;
+        let (format, color_space, present) = window.surface()
+            .choose_format_and_present_mode(&physical, &[],
+                                            &[vulkano::swapchain::PresentMode::Mailbox])
+            .expect("failed to get surface capabilities");
 
-        vulkano::swapchain::Swapchain::new(&device, &window.surface(), 3, format, dimensions, 1,
+        vulkano::swapchain::Swapchain::new(&device, &window.surface(), 3, format, color_space,
+                                           dimensions, 1,
                                            &usage, &queue, vulkano::swapchain::SurfaceTransform::Identity,
                                            vulkano::swapchain::CompositeAlpha::Opaque,
-                                           present, true, None).expect("failed to create swapchain")
+                                           present, true,
+                                           vulkano::swapchain::FullScreenExclusive::Default,
+                                           None).expect("failed to create swapchain")
     };
 
 
@@ -124,8 +129,6 @@ fn main() {
         depth: (vulkano::format::D16Unorm, 1)
     }).unwrap();
 
-    let descriptor_pool = vulkano::descriptor::descriptor_set::DescriptorPool::new(&device);
-
     mod pipeline_layout {
         pipeline_layout!{
             set0: {
@@ -135,7 +138,7 @@ fn main() {
     }
 
     let pipeline_layout = pipeline_layout::CustomPipeline::new(&device).unwrap();
-    let set = pipeline_layout::set0::Set::new(&descriptor_pool, &pipeline_layout, &pipeline_layout::set0::Descriptors {
+    let set = pipeline_layout::set0::Set::new(&pipeline_layout, &pipeline_layout::set0::Descriptors {
         uniforms: &uniform_buffer
     });
 
@@ -203,7 +206,7 @@ fn main() {
             buffer_content.world = cgmath::Matrix4::from(rotation).into();
         }
 
-        let image_num = swapchain.acquire_next_image(Duration::from_millis(1)).unwrap();
+        let (image_num, _) = swapchain.acquire_next_image(Duration::from_millis(1)).unwrap();
         submissions.push(vulkano::command_buffer::submit(&command_buffers[image_num], &queue).unwrap());
         swapchain.present(&queue, image_num).unwrap();
 
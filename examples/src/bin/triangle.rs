@@ -52,6 +52,8 @@ use vulkano::pipeline::vertex::SingleBufferDefinition;
 use vulkano::pipeline::viewport::ViewportsState;
 use vulkano::pipeline::viewport::Viewport;
 use vulkano::pipeline::viewport::Scissor;
+use vulkano::swapchain::FullScreenExclusive;
+use vulkano::swapchain::PresentMode;
 use vulkano::swapchain::SurfaceTransform;
 use vulkano::swapchain::Swapchain;
 
@@ -166,22 +168,25 @@ fn main() {
         // by the dimensions of the swapchain, in which case we just use a default value.
         let dimensions = caps.current_extent.unwrap_or([1280, 1024]);
 
-        // The present mode determines the way the images will be presented on the screen. This
-        // includes things such as vsync and will affect the framerate of your application. We just
-        // use the first supported value, but you probably want to leave that choice to the user.
-        let present = caps.present_modes.iter().next().unwrap();
-
         // The alpha mode indicates how the alpha value of the final image will behave. For example
         // you can choose whether the window will be opaque or transparent.
         let alpha = caps.supported_composite_alpha.iter().next().unwrap();
 
-        // Choosing the internal format that the images will have.
-        let format = caps.supported_formats[0].0;
+        // Choosing the internal format that the images will have and the present mode, which
+        // determines the way the images will be presented on the screen. The present mode
+        // includes things such as vsync and will affect the framerate of your application. Both
+        // are passed by order of preference, and if none of them is supported a sRGB format and
+        // the `Fifo` mode are chosen. You probably want to leave the choice of the present mode
+        // to the user.
+        let (format, color_space, present) = window.surface()
+            .choose_format_and_present_mode(&physical, &[], &[PresentMode::Mailbox])
+            .expect("failed to get surface capabilities");
 
         // Please take a look at the docs for the meaning of the parameters we didn't mention.
-        Swapchain::new(&device, &window.surface(), 2, format, dimensions, 1,
+        Swapchain::new(&device, &window.surface(), 2, format, color_space, dimensions, 1,
                        &caps.supported_usage_flags, &queue, SurfaceTransform::Identity, alpha,
-                       present, true, None).expect("failed to create swapchain")
+                       present, true, FullScreenExclusive::Default, None)
+            .expect("failed to create swapchain")
     };
 
     // We now create a buffer that will store the shape of our triangle.
@@ -368,11 +373,13 @@ fn main() {
         // Before we can draw on the output, we have to *acquire* an image from the swapchain. If
         // no image is available (which happens if you submit draw commands too quickly), then the
         // function will block.
-        // This operation returns the index of the image that we are allowed to draw upon.
+        // This operation returns the index of the image that we are allowed to draw upon, and
+        // whether the swapchain is suboptimal and should be recreated. This example doesn't handle
+        // window resizes and ignores it.
         //
         // This function can block if no image is available. The parameter is a timeout after
         // which the function call will return an error.
-        let image_num = swapchain.acquire_next_image(Duration::new(1, 0)).unwrap();
+        let (image_num, _) = swapchain.acquire_next_image(Duration::new(1, 0)).unwrap();
 
         // In order to draw, we have to build a *command buffer*. The command buffer object holds
         // the list of commands that are going to be executed.
@@ -16,6 +16,7 @@ use std::ffi::CStr;
 use std::ffi::CString;
 use std::fmt;
 use std::os::raw::c_char;
+use std::os::raw::c_int;
 use std::os::raw::c_void;
 use std::os::raw::c_ulong;
 
@@ -55,6 +56,7 @@ pub type SwapchainKHR = u64;
 pub type DisplayKHR = u64;
 pub type DisplayModeKHR = u64;
 pub type DebugReportCallbackEXT = u64;
+pub type SamplerYcbcrConversionKHR = u64;
 
 pub const LOD_CLAMP_NONE: f32 = 1000.0;
 pub const REMAINING_MIP_LEVELS: u32 = 0xffffffff;
@@ -71,6 +73,7 @@ pub const MAX_MEMORY_TYPES: u32 = 32;
 pub const MAX_MEMORY_HEAPS: u32 = 16;
 pub const MAX_EXTENSION_NAME_SIZE: u32 = 256;
 pub const MAX_DESCRIPTION_SIZE: u32 = 256;
+pub const MAX_DEVICE_GROUP_SIZE_KHR: u32 = 32;
 
 pub type PipelineCacheHeaderVersion = u32;
 pub const PIPELINE_CACHE_HEADER_VERSION_ONE: u32 = 1;
@@ -93,12 +96,16 @@ pub const ERROR_FEATURE_NOT_PRESENT: u32 = -8i32 as u32;
 pub const ERROR_INCOMPATIBLE_DRIVER: u32 = -9i32 as u32;
 pub const ERROR_TOO_MANY_OBJECTS: u32 = -10i32 as u32;
 pub const ERROR_FORMAT_NOT_SUPPORTED: u32 = -11i32 as u32;
+pub const ERROR_FRAGMENTED_POOL: u32 = -12i32 as u32;
 pub const ERROR_SURFACE_LOST_KHR: u32 = -1000000000i32 as u32;
 pub const ERROR_NATIVE_WINDOW_IN_USE_KHR: u32 = -1000000001i32 as u32;
 pub const SUBOPTIMAL_KHR: u32 = 1000001003;
 pub const ERROR_OUT_OF_DATE_KHR: u32 = -1000001004i32 as u32;
 pub const ERROR_INCOMPATIBLE_DISPLAY_KHR: u32 = -1000003001i32 as u32;
 pub const ERROR_VALIDATION_FAILED_EXT: u32 = -1000011001i32 as u32;
+pub const ERROR_OUT_OF_POOL_MEMORY_KHR: u32 = -1000069000i32 as u32;
+pub const ERROR_INVALID_EXTERNAL_HANDLE_KHR: u32 = -1000072003i32 as u32;
+pub const ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT: u32 = -1000255000i32 as u32;
 
 pub type StructureType = u32;
 pub const STRUCTURE_TYPE_APPLICATION_INFO: u32 = 0;
@@ -162,6 +169,45 @@ pub const STRUCTURE_TYPE_MIR_SURFACE_CREATE_INFO_KHR: u32 = 1000007000;
 pub const STRUCTURE_TYPE_ANDROID_SURFACE_CREATE_INFO_KHR: u32 = 1000008000;
 pub const STRUCTURE_TYPE_WIN32_SURFACE_CREATE_INFO_KHR: u32 = 1000009000;
 pub const STRUCTURE_TYPE_DEBUG_REPORT_CREATE_INFO_EXT: u32 = 1000011000;
+pub const STRUCTURE_TYPE_DEBUG_MARKER_OBJECT_NAME_INFO_EXT: u32 = 1000022000;
+pub const STRUCTURE_TYPE_CONDITIONAL_RENDERING_BEGIN_INFO_EXT: u32 = 1000081002;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_CONDITIONAL_RENDERING_FEATURES_EXT: u32 = 1000081001;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_CONSERVATIVE_RASTERIZATION_PROPERTIES_EXT: u32 = 1000101000;
+pub const STRUCTURE_TYPE_PIPELINE_RASTERIZATION_CONSERVATIVE_STATE_CREATE_INFO_EXT: u32 = 1000101001;
+pub const STRUCTURE_TYPE_MEMORY_DEDICATED_REQUIREMENTS_KHR: u32 = 1000127000;
+pub const STRUCTURE_TYPE_MEMORY_DEDICATED_ALLOCATE_INFO_KHR: u32 = 1000127001;
+pub const STRUCTURE_TYPE_BUFFER_MEMORY_REQUIREMENTS_INFO_2_KHR: u32 = 1000146000;
+pub const STRUCTURE_TYPE_IMAGE_MEMORY_REQUIREMENTS_INFO_2_KHR: u32 = 1000146001;
+pub const STRUCTURE_TYPE_MEMORY_REQUIREMENTS_2_KHR: u32 = 1000146003;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_FEATURES_2_KHR: u32 = 1000059000;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_PROPERTIES_2_KHR: u32 = 1000059001;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_MEMORY_PROPERTIES_2_KHR: u32 = 1000059006;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_MEMORY_BUDGET_PROPERTIES_EXT: u32 = 1000237000;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_INDEX_TYPE_UINT8_FEATURES_EXT: u32 = 1000265000;
+pub const STRUCTURE_TYPE_EXTERNAL_MEMORY_BUFFER_CREATE_INFO_KHR: u32 = 1000072000;
+pub const STRUCTURE_TYPE_EXTERNAL_MEMORY_IMAGE_CREATE_INFO_KHR: u32 = 1000072001;
+pub const STRUCTURE_TYPE_EXPORT_MEMORY_ALLOCATE_INFO_KHR: u32 = 1000072002;
+pub const STRUCTURE_TYPE_IMPORT_MEMORY_WIN32_HANDLE_INFO_KHR: u32 = 1000073000;
+pub const STRUCTURE_TYPE_MEMORY_GET_WIN32_HANDLE_INFO_KHR: u32 = 1000073003;
+pub const STRUCTURE_TYPE_IMPORT_MEMORY_FD_INFO_KHR: u32 = 1000074000;
+pub const STRUCTURE_TYPE_MEMORY_GET_FD_INFO_KHR: u32 = 1000074002;
+pub const STRUCTURE_TYPE_SURFACE_FULL_SCREEN_EXCLUSIVE_INFO_EXT: u32 = 1000255000;
+pub const STRUCTURE_TYPE_SURFACE_FULL_SCREEN_EXCLUSIVE_WIN32_INFO_EXT: u32 = 1000255001;
+pub const STRUCTURE_TYPE_PRESENT_REGIONS_KHR: u32 = 1000084000;
+pub const STRUCTURE_TYPE_EXPORT_SEMAPHORE_CREATE_INFO_KHR: u32 = 1000077000;
+pub const STRUCTURE_TYPE_IMPORT_SEMAPHORE_FD_INFO_KHR: u32 = 1000079000;
+pub const STRUCTURE_TYPE_SEMAPHORE_GET_FD_INFO_KHR: u32 = 1000079001;
+pub const STRUCTURE_TYPE_EXPORT_FENCE_CREATE_INFO_KHR: u32 = 1000113000;
+pub const STRUCTURE_TYPE_IMPORT_FENCE_FD_INFO_KHR: u32 = 1000115000;
+pub const STRUCTURE_TYPE_FENCE_GET_FD_INFO_KHR: u32 = 1000115001;
+pub const STRUCTURE_TYPE_MEMORY_ALLOCATE_FLAGS_INFO_KHR: u32 = 1000060000;
+pub const STRUCTURE_TYPE_DEVICE_GROUP_COMMAND_BUFFER_BEGIN_INFO_KHR: u32 = 1000060004;
+pub const STRUCTURE_TYPE_DEVICE_GROUP_SUBMIT_INFO_KHR: u32 = 1000060005;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_GROUP_PROPERTIES_KHR: u32 = 1000070000;
+pub const STRUCTURE_TYPE_DEVICE_GROUP_DEVICE_CREATE_INFO_KHR: u32 = 1000070001;
+pub const STRUCTURE_TYPE_SAMPLER_YCBCR_CONVERSION_CREATE_INFO_KHR: u32 = 1000156000;
+pub const STRUCTURE_TYPE_SAMPLER_YCBCR_CONVERSION_INFO_KHR: u32 = 1000156001;
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_SAMPLER_YCBCR_CONVERSION_FEATURES_KHR: u32 = 1000156004;
 
 pub type SystemAllocationScope = u32;
 pub const SYSTEM_ALLOCATION_SCOPE_COMMAND: u32 = 0;
@@ -359,6 +405,20 @@ pub const FORMAT_ASTC_12x10_UNORM_BLOCK: u32 = 181;
 pub const FORMAT_ASTC_12x10_SRGB_BLOCK: u32 = 182;
 pub const FORMAT_ASTC_12x12_UNORM_BLOCK: u32 = 183;
 pub const FORMAT_ASTC_12x12_SRGB_BLOCK: u32 = 184;
+pub const FORMAT_G8B8G8R8_422_UNORM_KHR: u32 = 1000156000;
+pub const FORMAT_B8G8R8G8_422_UNORM_KHR: u32 = 1000156001;
+pub const FORMAT_G8_B8_R8_3PLANE_420_UNORM_KHR: u32 = 1000156002;
+pub const FORMAT_G8_B8R8_2PLANE_420_UNORM_KHR: u32 = 1000156003;
+pub const FORMAT_G8_B8_R8_3PLANE_422_UNORM_KHR: u32 = 1000156004;
+pub const FORMAT_G8_B8R8_2PLANE_422_UNORM_KHR: u32 = 1000156005;
+pub const FORMAT_G8_B8_R8_3PLANE_444_UNORM_KHR: u32 = 1000156006;
+pub const FORMAT_G16B16G16R16_422_UNORM_KHR: u32 = 1000156027;
+pub const FORMAT_B16G16R16G16_422_UNORM_KHR: u32 = 1000156028;
+pub const FORMAT_G16_B16_R16_3PLANE_420_UNORM_KHR: u32 = 1000156029;
+pub const FORMAT_G16_B16R16_2PLANE_420_UNORM_KHR: u32 = 1000156030;
+pub const FORMAT_G16_B16_R16_3PLANE_422_UNORM_KHR: u32 = 1000156031;
+pub const FORMAT_G16_B16R16_2PLANE_422_UNORM_KHR: u32 = 1000156032;
+pub const FORMAT_G16_B16_R16_3PLANE_444_UNORM_KHR: u32 = 1000156033;
 
 pub type ImageType = u32;
 pub const IMAGE_TYPE_1D: u32 = 0;
@@ -574,6 +634,7 @@ pub const COMMAND_BUFFER_LEVEL_SECONDARY: u32 = 1;
 pub type IndexType = u32;
 pub const INDEX_TYPE_UINT16: u32 = 0;
 pub const INDEX_TYPE_UINT32: u32 = 1;
+pub const INDEX_TYPE_UINT8_EXT: u32 = 1000265000;
 
 pub type SubpassContents = u32;
 pub const SUBPASS_CONTENTS_INLINE: u32 = 0;
@@ -672,6 +733,7 @@ pub const PIPELINE_STAGE_BOTTOM_OF_PIPE_BIT: u32 = 0x00002000;
 pub const PIPELINE_STAGE_HOST_BIT: u32 = 0x00004000;
 pub const PIPELINE_STAGE_ALL_GRAPHICS_BIT: u32 = 0x00008000;
 pub const PIPELINE_STAGE_ALL_COMMANDS_BIT: u32 = 0x00010000;
+pub const PIPELINE_STAGE_CONDITIONAL_RENDERING_BIT_EXT: u32 = 0x00040000;
 pub type PipelineStageFlags = Flags;
 pub type MemoryMapFlags = Flags;
 
@@ -681,6 +743,9 @@ pub const IMAGE_ASPECT_COLOR_BIT: u32 = 0x00000001;
 pub const IMAGE_ASPECT_DEPTH_BIT: u32 = 0x00000002;
 pub const IMAGE_ASPECT_STENCIL_BIT: u32 = 0x00000004;
 pub const IMAGE_ASPECT_METADATA_BIT: u32 = 0x00000008;
+pub const IMAGE_ASPECT_PLANE_0_BIT_KHR: u32 = 0x00000010;
+pub const IMAGE_ASPECT_PLANE_1_BIT_KHR: u32 = 0x00000020;
+pub const IMAGE_ASPECT_PLANE_2_BIT_KHR: u32 = 0x00000040;
 pub type ImageAspectFlags = Flags;
 
 
@@ -744,6 +809,7 @@ pub const BUFFER_USAGE_STORAGE_BUFFER_BIT: u32 = 0x00000020;
 pub const BUFFER_USAGE_INDEX_BUFFER_BIT: u32 = 0x00000040;
 pub const BUFFER_USAGE_VERTEX_BUFFER_BIT: u32 = 0x00000080;
 pub const BUFFER_USAGE_INDIRECT_BUFFER_BIT: u32 = 0x00000100;
+pub const BUFFER_USAGE_CONDITIONAL_RENDERING_BIT_EXT: u32 = 0x00000200;
 pub type BufferUsageFlags = Flags;
 pub type BufferViewCreateFlags = Flags;
 pub type ImageViewCreateFlags = Flags;
@@ -755,6 +821,7 @@ pub type PipelineCreateFlagBits = u32;
 pub const PIPELINE_CREATE_DISABLE_OPTIMIZATION_BIT: u32 = 0x00000001;
 pub const PIPELINE_CREATE_ALLOW_DERIVATIVES_BIT: u32 = 0x00000002;
 pub const PIPELINE_CREATE_DERIVATIVE_BIT: u32 = 0x00000004;
+pub const PIPELINE_CREATE_DISPATCH_BASE_KHR: u32 = 0x00000010;
 pub type PipelineCreateFlags = Flags;
 pub type PipelineShaderStageCreateFlags = Flags;
 
@@ -831,6 +898,7 @@ pub const ACCESS_HOST_READ_BIT: u32 = 0x00002000;
 pub const ACCESS_HOST_WRITE_BIT: u32 = 0x00004000;
 pub const ACCESS_MEMORY_READ_BIT: u32 = 0x00008000;
 pub const ACCESS_MEMORY_WRITE_BIT: u32 = 0x00010000;
+pub const ACCESS_CONDITIONAL_RENDERING_READ_BIT_EXT: u32 = 0x00100000;
 pub type AccessFlags = Flags;
 
 
@@ -861,6 +929,10 @@ pub type QueryControlFlagBits = u32;
 pub const QUERY_CONTROL_PRECISE_BIT: u32 = 0x00000001;
 pub type QueryControlFlags = Flags;
 
+pub type ConditionalRenderingFlagBitsEXT = u32;
+pub const CONDITIONAL_RENDERING_INVERTED_BIT_EXT: u32 = 0x00000001;
+pub type ConditionalRenderingFlagsEXT = Flags;
+
 
 pub type CommandBufferResetFlagBits = u32;
 pub const COMMAND_BUFFER_RESET_RELEASE_RESOURCES_BIT: u32 = 0x00000001;
@@ -887,6 +959,20 @@ pub type ColorSpaceKHR = u32;
 #[deprecated = "Renamed to COLOR_SPACE_SRGB_NONLINEAR_KHR"]
 pub const COLORSPACE_SRGB_NONLINEAR_KHR: u32 = 0;
 pub const COLOR_SPACE_SRGB_NONLINEAR_KHR: u32 = 0;
+pub const COLOR_SPACE_DISPLAY_P3_NONLINEAR_EXT: u32 = 1000104001;
+pub const COLOR_SPACE_EXTENDED_SRGB_LINEAR_EXT: u32 = 1000104002;
+pub const COLOR_SPACE_DISPLAY_P3_LINEAR_EXT: u32 = 1000104003;
+pub const COLOR_SPACE_DCI_P3_NONLINEAR_EXT: u32 = 1000104004;
+pub const COLOR_SPACE_BT709_LINEAR_EXT: u32 = 1000104005;
+pub const COLOR_SPACE_BT709_NONLINEAR_EXT: u32 = 1000104006;
+pub const COLOR_SPACE_BT2020_LINEAR_EXT: u32 = 1000104007;
+pub const COLOR_SPACE_HDR10_ST2084_EXT: u32 = 1000104008;
+pub const COLOR_SPACE_DOLBYVISION_EXT: u32 = 1000104009;
+pub const COLOR_SPACE_HDR10_HLG_EXT: u32 = 1000104010;
+pub const COLOR_SPACE_ADOBERGB_LINEAR_EXT: u32 = 1000104011;
+pub const COLOR_SPACE_ADOBERGB_NONLINEAR_EXT: u32 = 1000104012;
+pub const COLOR_SPACE_PASS_THROUGH_EXT: u32 = 1000104013;
+pub const COLOR_SPACE_EXTENDED_SRGB_NONLINEAR_EXT: u32 = 1000104014;
 
 pub type PresentModeKHR = u32;
 pub const PRESENT_MODE_IMMEDIATE_KHR: u32 = 0;
@@ -956,6 +1042,67 @@ pub const DEBUG_REPORT_ERROR_BIT_EXT: u32 = 0x00000008;
 pub const DEBUG_REPORT_DEBUG_BIT_EXT: u32 = 0x00000010;
 pub type DebugReportFlagsEXT = Flags;
 
+pub type ConservativeRasterizationModeEXT = u32;
+pub const CONSERVATIVE_RASTERIZATION_MODE_DISABLED_EXT: u32 = 0;
+pub const CONSERVATIVE_RASTERIZATION_MODE_OVERESTIMATE_EXT: u32 = 1;
+pub const CONSERVATIVE_RASTERIZATION_MODE_UNDERESTIMATE_EXT: u32 = 2;
+
+pub type PipelineRasterizationConservativeStateCreateFlagsEXT = Flags;
+
+pub type ExternalMemoryHandleTypeFlagBitsKHR = u32;
+pub const EXTERNAL_MEMORY_HANDLE_TYPE_OPAQUE_FD_BIT_KHR: u32 = 0x00000001;
+pub const EXTERNAL_MEMORY_HANDLE_TYPE_OPAQUE_WIN32_BIT_KHR: u32 = 0x00000002;
+pub const EXTERNAL_MEMORY_HANDLE_TYPE_OPAQUE_WIN32_KMT_BIT_KHR: u32 = 0x00000004;
+pub const EXTERNAL_MEMORY_HANDLE_TYPE_DMA_BUF_BIT_EXT: u32 = 0x00000200;
+pub type ExternalMemoryHandleTypeFlagsKHR = Flags;
+
+pub type ExternalSemaphoreHandleTypeFlagBitsKHR = u32;
+pub const EXTERNAL_SEMAPHORE_HANDLE_TYPE_OPAQUE_FD_BIT_KHR: u32 = 0x00000001;
+pub const EXTERNAL_SEMAPHORE_HANDLE_TYPE_OPAQUE_WIN32_BIT_KHR: u32 = 0x00000002;
+pub const EXTERNAL_SEMAPHORE_HANDLE_TYPE_OPAQUE_WIN32_KMT_BIT_KHR: u32 = 0x00000004;
+pub const EXTERNAL_SEMAPHORE_HANDLE_TYPE_D3D12_FENCE_BIT_KHR: u32 = 0x00000008;
+pub const EXTERNAL_SEMAPHORE_HANDLE_TYPE_SYNC_FD_BIT_KHR: u32 = 0x00000010;
+pub type ExternalSemaphoreHandleTypeFlagsKHR = Flags;
+
+pub type SemaphoreImportFlagBitsKHR = u32;
+pub const SEMAPHORE_IMPORT_TEMPORARY_BIT_KHR: u32 = 0x00000001;
+pub type SemaphoreImportFlagsKHR = Flags;
+
+pub type ExternalFenceHandleTypeFlagBitsKHR = u32;
+pub const EXTERNAL_FENCE_HANDLE_TYPE_OPAQUE_FD_BIT_KHR: u32 = 0x00000001;
+pub const EXTERNAL_FENCE_HANDLE_TYPE_OPAQUE_WIN32_BIT_KHR: u32 = 0x00000002;
+pub const EXTERNAL_FENCE_HANDLE_TYPE_OPAQUE_WIN32_KMT_BIT_KHR: u32 = 0x00000004;
+pub const EXTERNAL_FENCE_HANDLE_TYPE_SYNC_FD_BIT_KHR: u32 = 0x00000008;
+pub type ExternalFenceHandleTypeFlagsKHR = Flags;
+
+pub type FenceImportFlagBitsKHR = u32;
+pub const FENCE_IMPORT_TEMPORARY_BIT_KHR: u32 = 0x00000001;
+pub type FenceImportFlagsKHR = Flags;
+
+pub type MemoryAllocateFlagBitsKHR = u32;
+pub const MEMORY_ALLOCATE_DEVICE_MASK_BIT_KHR: u32 = 0x00000001;
+pub type MemoryAllocateFlagsKHR = Flags;
+
+pub type SamplerYcbcrModelConversionKHR = u32;
+pub const SAMPLER_YCBCR_MODEL_CONVERSION_RGB_IDENTITY_KHR: u32 = 0;
+pub const SAMPLER_YCBCR_MODEL_CONVERSION_YCBCR_IDENTITY_KHR: u32 = 1;
+pub const SAMPLER_YCBCR_MODEL_CONVERSION_YCBCR_709_KHR: u32 = 2;
+pub const SAMPLER_YCBCR_MODEL_CONVERSION_YCBCR_601_KHR: u32 = 3;
+pub const SAMPLER_YCBCR_MODEL_CONVERSION_YCBCR_2020_KHR: u32 = 4;
+
+pub type SamplerYcbcrRangeKHR = u32;
+pub const SAMPLER_YCBCR_RANGE_ITU_FULL_KHR: u32 = 0;
+pub const SAMPLER_YCBCR_RANGE_ITU_NARROW_KHR: u32 = 1;
+
+pub type ChromaLocationKHR = u32;
+pub const CHROMA_LOCATION_COSITED_EVEN_KHR: u32 = 0;
+pub const CHROMA_LOCATION_MIDPOINT_KHR: u32 = 1;
+
+pub type FullScreenExclusiveEXT = u32;
+pub const FULL_SCREEN_EXCLUSIVE_DEFAULT_EXT: u32 = 0;
+pub const FULL_SCREEN_EXCLUSIVE_ALLOWED_EXT: u32 = 1;
+pub const FULL_SCREEN_EXCLUSIVE_DISALLOWED_EXT: u32 = 2;
+pub const FULL_SCREEN_EXCLUSIVE_APPLICATION_CONTROLLED_EXT: u32 = 3;
 
 pub type PFN_vkAllocationFunction = extern "system" fn(*mut c_void, usize, usize, SystemAllocationScope) -> *mut c_void;
 pub type PFN_vkReallocationFunction = extern "system" fn(*mut c_void, *mut c_void, usize, usize, SystemAllocationScope) -> *mut c_void;
@@ -2368,6 +2515,340 @@ pub struct DebugReportCallbackCreateInfoEXT {
     pub pUserData: *mut c_void,
 }
 
+#[repr(C)]
+pub struct PipelineRasterizationConservativeStateCreateInfoEXT {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub flags: PipelineRasterizationConservativeStateCreateFlagsEXT,
+    pub conservativeRasterizationMode: ConservativeRasterizationModeEXT,
+    pub extraPrimitiveOverestimationSize: f32,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceConservativeRasterizationPropertiesEXT {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub primitiveOverestimationSize: f32,
+    pub maxExtraPrimitiveOverestimationSize: f32,
+    pub extraPrimitiveOverestimationSizeGranularity: f32,
+    pub primitiveUnderestimation: Bool32,
+    pub conservativePointAndLineRasterization: Bool32,
+    pub degenerateTrianglesRasterized: Bool32,
+    pub degenerateLinesRasterized: Bool32,
+    pub fullyCoveredFragmentShaderInputVariable: Bool32,
+    pub conservativeRasterizationPostDepthCoverage: Bool32,
+}
+
+#[repr(C)]
+pub struct BufferMemoryRequirementsInfo2KHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub buffer: Buffer,
+}
+
+#[repr(C)]
+pub struct ImageMemoryRequirementsInfo2KHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub image: Image,
+}
+
+#[repr(C)]
+pub struct MemoryRequirements2KHR {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub memoryRequirements: MemoryRequirements,
+}
+
+#[repr(C)]
+pub struct MemoryDedicatedRequirementsKHR {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub prefersDedicatedAllocation: Bool32,
+    pub requiresDedicatedAllocation: Bool32,
+}
+
+#[repr(C)]
+pub struct MemoryDedicatedAllocateInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub image: Image,
+    pub buffer: Buffer,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceFeatures2KHR {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub features: PhysicalDeviceFeatures,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceIndexTypeUint8FeaturesEXT {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub indexTypeUint8: Bool32,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceProperties2KHR {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub properties: PhysicalDeviceProperties,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceMemoryProperties2KHR {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub memoryProperties: PhysicalDeviceMemoryProperties,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceMemoryBudgetPropertiesEXT {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub heapBudget: [DeviceSize; MAX_MEMORY_HEAPS as usize],
+    pub heapUsage: [DeviceSize; MAX_MEMORY_HEAPS as usize],
+}
+
+#[repr(C)]
+pub struct ExternalMemoryBufferCreateInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub handleTypes: ExternalMemoryHandleTypeFlagsKHR,
+}
+
+#[repr(C)]
+pub struct ExternalMemoryImageCreateInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub handleTypes: ExternalMemoryHandleTypeFlagsKHR,
+}
+
+#[repr(C)]
+pub struct ExportMemoryAllocateInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub handleTypes: ExternalMemoryHandleTypeFlagsKHR,
+}
+
+#[repr(C)]
+pub struct ImportMemoryFdInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub handleType: ExternalMemoryHandleTypeFlagBitsKHR,
+    pub fd: c_int,
+}
+
+#[repr(C)]
+pub struct MemoryGetFdInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub memory: DeviceMemory,
+    pub handleType: ExternalMemoryHandleTypeFlagBitsKHR,
+}
+
+#[repr(C)]
+pub struct ImportMemoryWin32HandleInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub handleType: ExternalMemoryHandleTypeFlagBitsKHR,
+    pub handle: *mut c_void,
+    pub name: *const u16,
+}
+
+#[repr(C)]
+pub struct MemoryGetWin32HandleInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub memory: DeviceMemory,
+    pub handleType: ExternalMemoryHandleTypeFlagBitsKHR,
+}
+
+#[repr(C)]
+pub struct SurfaceFullScreenExclusiveInfoEXT {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub fullScreenExclusive: FullScreenExclusiveEXT,
+}
+
+#[repr(C)]
+pub struct SurfaceFullScreenExclusiveWin32InfoEXT {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub hmonitor: *mut c_void,
+}
+
+#[repr(C)]
+pub struct RectLayerKHR {
+    pub offset: Offset2D,
+    pub extent: Extent2D,
+    pub layer: u32,
+}
+
+#[repr(C)]
+pub struct PresentRegionKHR {
+    pub rectangleCount: u32,
+    pub pRectangles: *const RectLayerKHR,
+}
+
+#[repr(C)]
+pub struct PresentRegionsKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub swapchainCount: u32,
+    pub pRegions: *const PresentRegionKHR,
+}
+
+#[repr(C)]
+pub struct ExportSemaphoreCreateInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub handleTypes: ExternalSemaphoreHandleTypeFlagsKHR,
+}
+
+#[repr(C)]
+pub struct ImportSemaphoreFdInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub semaphore: Semaphore,
+    pub flags: SemaphoreImportFlagsKHR,
+    pub handleType: ExternalSemaphoreHandleTypeFlagBitsKHR,
+    pub fd: c_int,
+}
+
+#[repr(C)]
+pub struct SemaphoreGetFdInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub semaphore: Semaphore,
+    pub handleType: ExternalSemaphoreHandleTypeFlagBitsKHR,
+}
+
+#[repr(C)]
+pub struct ExportFenceCreateInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub handleTypes: ExternalFenceHandleTypeFlagsKHR,
+}
+
+#[repr(C)]
+pub struct ImportFenceFdInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub fence: Fence,
+    pub flags: FenceImportFlagsKHR,
+    pub handleType: ExternalFenceHandleTypeFlagBitsKHR,
+    pub fd: c_int,
+}
+
+#[repr(C)]
+pub struct FenceGetFdInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub fence: Fence,
+    pub handleType: ExternalFenceHandleTypeFlagBitsKHR,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceGroupPropertiesKHR {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub physicalDeviceCount: u32,
+    pub physicalDevices: [PhysicalDevice; MAX_DEVICE_GROUP_SIZE_KHR as usize],
+    pub subsetAllocation: Bool32,
+}
+
+#[repr(C)]
+pub struct DeviceGroupDeviceCreateInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub physicalDeviceCount: u32,
+    pub pPhysicalDevices: *const PhysicalDevice,
+}
+
+#[repr(C)]
+pub struct MemoryAllocateFlagsInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub flags: MemoryAllocateFlagsKHR,
+    pub deviceMask: u32,
+}
+
+#[repr(C)]
+pub struct DeviceGroupCommandBufferBeginInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub deviceMask: u32,
+}
+
+#[repr(C)]
+pub struct DeviceGroupSubmitInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub waitSemaphoreCount: u32,
+    pub pWaitSemaphoreDeviceIndices: *const u32,
+    pub commandBufferCount: u32,
+    pub pCommandBufferDeviceMasks: *const u32,
+    pub signalSemaphoreCount: u32,
+    pub pSignalSemaphoreDeviceIndices: *const u32,
+}
+
+#[repr(C)]
+pub struct SamplerYcbcrConversionCreateInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub format: Format,
+    pub ycbcrModel: SamplerYcbcrModelConversionKHR,
+    pub ycbcrRange: SamplerYcbcrRangeKHR,
+    pub components: ComponentMapping,
+    pub xChromaOffset: ChromaLocationKHR,
+    pub yChromaOffset: ChromaLocationKHR,
+    pub chromaFilter: Filter,
+    pub forceExplicitReconstruction: Bool32,
+}
+
+#[repr(C)]
+pub struct DebugMarkerObjectNameInfoEXT {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub objectType: DebugReportObjectTypeEXT,
+    pub object: u64,
+    pub pObjectName: *const c_char,
+}
+
+#[repr(C)]
+pub struct ConditionalRenderingBeginInfoEXT {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub buffer: Buffer,
+    pub offset: DeviceSize,
+    pub flags: ConditionalRenderingFlagsEXT,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceConditionalRenderingFeaturesEXT {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub conditionalRendering: Bool32,
+    pub inheritedConditionalRendering: Bool32,
+}
+
+#[repr(C)]
+pub struct SamplerYcbcrConversionInfoKHR {
+    pub sType: StructureType,
+    pub pNext: *const c_void,
+    pub conversion: SamplerYcbcrConversionKHR,
+}
+
+#[repr(C)]
+pub struct PhysicalDeviceSamplerYcbcrConversionFeaturesKHR {
+    pub sType: StructureType,
+    pub pNext: *mut c_void,
+    pub samplerYcbcrConversion: Bool32,
+}
+
 macro_rules! ptrs {
     ($struct_name:ident, { $($name:ident => ($($param_n:ident: $param_ty:ty),*) -> $ret:ty,)+ }) => (
         pub struct $struct_name {
@@ -2463,6 +2944,10 @@ ptrs!(InstancePointers, {
     CreateDebugReportCallbackEXT => (instance: Instance, pCreateInfo: *const DebugReportCallbackCreateInfoEXT, pAllocator: *const AllocationCallbacks, pCallback: *mut DebugReportCallbackEXT) -> Result,
     DestroyDebugReportCallbackEXT => (instance: Instance, callback: DebugReportCallbackEXT, pAllocator: *const AllocationCallbacks) -> (),
     DebugReportMessageEXT => (instance: Instance, flags: DebugReportFlagsEXT, objectType: DebugReportObjectTypeEXT, object: u64, location: usize, messageCode: i32, pLayerPrefix: *const c_char, pMessage: *const c_char) -> (),
+    GetPhysicalDeviceFeatures2KHR => (physicalDevice: PhysicalDevice, pFeatures: *mut PhysicalDeviceFeatures2KHR) -> (),
+    GetPhysicalDeviceProperties2KHR => (physicalDevice: PhysicalDevice, pProperties: *mut PhysicalDeviceProperties2KHR) -> (),
+    GetPhysicalDeviceMemoryProperties2KHR => (physicalDevice: PhysicalDevice, pMemoryProperties: *mut PhysicalDeviceMemoryProperties2KHR) -> (),
+    EnumeratePhysicalDeviceGroupsKHR => (instance: Instance, pPhysicalDeviceGroupCount: *mut u32, pPhysicalDeviceGroupProperties: *mut PhysicalDeviceGroupPropertiesKHR) -> Result,
 });
 
 ptrs!(DevicePointers, {
@@ -2592,4 +3077,23 @@ ptrs!(DevicePointers, {
     AcquireNextImageKHR => (device: Device, swapchain: SwapchainKHR, timeout: u64, semaphore: Semaphore, fence: Fence, pImageIndex: *mut u32) -> Result,
     QueuePresentKHR => (queue: Queue, pPresentInfo: *const PresentInfoKHR) -> Result,
     CreateSharedSwapchainsKHR => (device: Device, swapchainCount: u32, pCreateInfos: *const SwapchainCreateInfoKHR, pAllocator: *const AllocationCallbacks, pSwapchains: *mut SwapchainKHR) -> Result,
+    GetBufferMemoryRequirements2KHR => (device: Device, pInfo: *const BufferMemoryRequirementsInfo2KHR, pMemoryRequirements: *mut MemoryRequirements2KHR) -> (),
+    GetImageMemoryRequirements2KHR => (device: Device, pInfo: *const ImageMemoryRequirementsInfo2KHR, pMemoryRequirements: *mut MemoryRequirements2KHR) -> (),
+    GetMemoryFdKHR => (device: Device, pGetFdInfo: *const MemoryGetFdInfoKHR, pFd: *mut c_int) -> Result,
+    GetMemoryWin32HandleKHR => (device: Device, pGetWin32HandleInfo: *const MemoryGetWin32HandleInfoKHR, pHandle: *mut *mut c_void) -> Result,
+    AcquireFullScreenExclusiveModeEXT => (device: Device, swapchain: SwapchainKHR) -> Result,
+    ReleaseFullScreenExclusiveModeEXT => (device: Device, swapchain: SwapchainKHR) -> Result,
+    ImportSemaphoreFdKHR => (device: Device, pImportSemaphoreFdInfo: *const ImportSemaphoreFdInfoKHR) -> Result,
+    GetSemaphoreFdKHR => (device: Device, pGetFdInfo: *const SemaphoreGetFdInfoKHR, pFd: *mut c_int) -> Result,
+    ImportFenceFdKHR => (device: Device, pImportFenceFdInfo: *const ImportFenceFdInfoKHR) -> Result,
+    GetFenceFdKHR => (device: Device, pGetFdInfo: *const FenceGetFdInfoKHR, pFd: *mut c_int) -> Result,
+    CmdSetDeviceMaskKHR => (commandBuffer: CommandBuffer, deviceMask: u32) -> (),
+    CmdDispatchBaseKHR => (commandBuffer: CommandBuffer, baseGroupX: u32, baseGroupY: u32, baseGroupZ: u32, groupCountX: u32, groupCountY: u32, groupCountZ: u32) -> (),
+    CreateSamplerYcbcrConversionKHR => (device: Device, pCreateInfo: *const SamplerYcbcrConversionCreateInfoKHR, pAllocator: *const AllocationCallbacks, pYcbcrConversion: *mut SamplerYcbcrConversionKHR) -> Result,
+    DestroySamplerYcbcrConversionKHR => (device: Device, ycbcrConversion: SamplerYcbcrConversionKHR, pAllocator: *const AllocationCallbacks) -> (),
+    DebugMarkerSetObjectNameEXT => (device: Device, pNameInfo: *const DebugMarkerObjectNameInfoEXT) -> Result,
+    CmdBeginConditionalRenderingEXT => (commandBuffer: CommandBuffer, pConditionalRenderingBegin: *const ConditionalRenderingBeginInfoEXT) -> (),
+    CmdEndConditionalRenderingEXT => (commandBuffer: CommandBuffer) -> (),
+    CmdDrawIndirectCountKHR => (commandBuffer: CommandBuffer, buffer: Buffer, offset: DeviceSize, countBuffer: Buffer, countBufferOffset: DeviceSize, maxDrawCount: u32, stride: u32) -> (),
+    CmdDrawIndexedIndirectCountKHR => (commandBuffer: CommandBuffer, buffer: Buffer, offset: DeviceSize, countBuffer: Buffer, countBufferOffset: DeviceSize, maxDrawCount: u32, stride: u32) -> (),
 });
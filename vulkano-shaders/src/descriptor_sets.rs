@@ -11,8 +11,9 @@ use std::collections::HashSet;
 
 use enums;
 use parse;
+use Error;
 
-pub fn write_descriptor_sets(doc: &parse::Spirv) -> String {
+pub fn write_descriptor_sets(doc: &parse::Spirv) -> Result<String, Error> {
     // TODO: not implemented correctly
 
     // Finding all the descriptors.
@@ -21,6 +22,7 @@ pub fn write_descriptor_sets(doc: &parse::Spirv) -> String {
         set: u32,
         binding: u32,
         desc_ty: String,
+        array_count: u64,
         readonly: bool,
     }
 
@@ -49,10 +51,15 @@ pub fn write_descriptor_sets(doc: &parse::Spirv) -> String {
         }).next().expect(&format!("Uniform `{}` is missing a binding", name));
 
         // Find informations about the kind of binding for this descriptor.
-        let (desc_ty, readonly) = descriptor_infos(doc, pointed_ty, false).expect(&format!("Couldn't find relevant type for uniform `{}` (type {}, maybe unimplemented)", name, pointed_ty));
+        let (desc_ty, readonly, array_count) = match descriptor_infos(doc, pointed_ty, false) {
+            Some(Ok(infos)) => infos,
+            Some(Err(())) => return Err(Error::NonConstantArrayLength(name)),
+            None => panic!("Couldn't find relevant type for uniform `{}` (type {}, maybe unimplemented)", name, pointed_ty),
+        };
 
         descriptors.push(Descriptor {
             desc_ty: desc_ty,
+            array_count: array_count,
             set: descriptor_set,
             binding: binding,
             readonly: readonly,
@@ -74,10 +81,11 @@ pub fn write_descriptor_sets(doc: &parse::Spirv) -> String {
                                    format!("DescriptorDesc {{
                                                 binding: {binding},
                                                 ty: {desc_ty},
-                                                array_count: 1,
+                                                array_count: {array_count},
                                                 stages: stages.clone(),
                                                 readonly: {readonly},
                                             }}", binding = d.binding, desc_ty = d.desc_ty,
+                                                 array_count = d.array_count,
                                                  readonly = if d.readonly { "true" } else { "false" })
                                })
                                .collect::<Vec<_>>();
@@ -110,7 +118,7 @@ pub fn write_descriptor_sets(doc: &parse::Spirv) -> String {
 
         "#, layouts = (0 .. max_set).map(|n| format!("set{}_layout(self.0)", n)).collect::<Vec<_>>().join(",")));
 
-    output
+    Ok(output)
 }
 
 /// Assumes that `variable` is a variable with a `TypePointer` and returns the id of the pointed
@@ -135,12 +143,15 @@ fn pointer_variable_ty(doc: &parse::Spirv, variable: u32) -> u32 {
     }).next().unwrap()
 }
 
-/// Returns a `DescriptorDescTy` constructor and a bool indicating whether the descriptor is
-/// read-only.
+/// Returns a `DescriptorDescTy` constructor, a bool indicating whether the descriptor is
+/// read-only, and the number of array elements of the binding.
+///
+/// Returns `Some(Err(()))` if the length of an array isn't a constant, for example because it is
+/// a specialization constant.
 ///
 /// See also section 14.5.2 of the Vulkan specs: Descriptor Set Interface
 fn descriptor_infos(doc: &parse::Spirv, pointed_ty: u32, force_combined_image_sampled: bool)
-                    -> Option<(String, bool)>
+                    -> Option<Result<(String, bool, u64), ()>>
 {
     doc.instructions.iter().filter_map(|i| {
         match i {
@@ -173,7 +184,7 @@ fn descriptor_infos(doc: &parse::Spirv, pointed_ty: u32, force_combined_image_sa
                     storage: {}
                 }})", if is_ssbo { "true" } else { "false "});
 
-                Some((desc, true))
+                Some(Ok((desc, true, 1)))
             },
 
             &parse::Instruction::TypeImage { result_id, ref dim, arrayed, ms, sampled,
@@ -203,7 +214,7 @@ fn descriptor_infos(doc: &parse::Spirv, pointed_ty: u32, force_combined_image_sa
                                             array_layers: {}
                                         }}", ms, arrayed);
 
-                    Some((desc, true))
+                    Some(Ok((desc, true, 1)))
 
                 } else if let &enums::Dim::DimBuffer = dim {
                     // We are a texel buffer.
@@ -212,7 +223,8 @@ fn descriptor_infos(doc: &parse::Spirv, pointed_ty: u32, force_combined_image_sa
                         format: None,       // TODO: specify format if known
                     }}", !sampled);
 
-                    Some((desc, true))
+                    // Only storage texel buffers can be written to.
+                    Some(Ok((desc, sampled, 1)))
 
                 } else {
                     // We are a sampled or storage image.
@@ -236,7 +248,7 @@ fn descriptor_infos(doc: &parse::Spirv, pointed_ty: u32, force_combined_image_sa
                         array_layers: {},
                     }})", ty, sampled, dim, ms, arrayed);
 
-                    Some((desc, true))
+                    Some(Ok((desc, true, 1)))
                 }
             },
 
@@ -246,9 +258,36 @@ fn descriptor_infos(doc: &parse::Spirv, pointed_ty: u32, force_combined_image_sa
                 descriptor_infos(doc, image_type_id, true)
             },
 
+            &parse::Instruction::TypeArray { result_id, type_id, length_id }
+                                                                if result_id == pointed_ty =>
+            {
+                let (desc, readonly, count) = match descriptor_infos(doc, type_id, false) {
+                    Some(Ok(infos)) => infos,
+                    other => return other,
+                };
+
+                let len = doc.instructions.iter().filter_map(|e| {
+                    match e {
+                        &parse::Instruction::Constant { result_id, ref data, .. }
+                                                                if result_id == length_id =>
+                        {
+                            Some(data.clone())
+                        },
+                        _ => None
+                    }
+                }).next();
+                let len = match len {
+                    Some(len) => len,
+                    None => return Some(Err(())),
+                };
+                let len = len.iter().rev().fold(0u64, |a, &b| (a << 32) | b as u64);
+
+                Some(Ok((desc, readonly, count * len)))
+            },
+
             &parse::Instruction::TypeSampler { result_id } if result_id == pointed_ty => {
                 let desc = format!("DescriptorDescTy::Sampler");
-                Some((desc, true))
+                Some(Ok((desc, true, 1)))
             },
 
             _ => None,      // TODO: other types
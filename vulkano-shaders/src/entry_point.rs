@@ -10,15 +10,18 @@
 use enums;
 use parse;
 
+use Error;
 use is_builtin;
 use name_from_id;
 use location_decoration;
 use format_from_id;
 
-pub fn write_entry_point(doc: &parse::Spirv, instruction: &parse::Instruction) -> (String, String) {
-    let (execution, ep_name, interface) = match instruction {
-        &parse::Instruction::EntryPoint { ref execution, ref name, ref interface, .. } => {
-            (execution, name, interface)
+pub fn write_entry_point(doc: &parse::Spirv, instruction: &parse::Instruction)
+                         -> Result<(String, String), Error>
+{
+    let (execution, id, ep_name, interface) = match instruction {
+        &parse::Instruction::EntryPoint { ref execution, id, ref name, ref interface } => {
+            (execution, id, name, interface)
         },
         _ => unreachable!()
     };
@@ -70,8 +73,14 @@ pub fn write_entry_point(doc: &parse::Spirv, instruction: &parse::Instruction) -
         },
 
         enums::ExecutionModel::ExecutionModelGLCompute => {
+            let local_size = match local_size(doc, id) {
+                Some(s) => s,
+                None => return Err(Error::MissingLocalSize(ep_name.clone())),
+            };
+
             (format!("::vulkano::pipeline::shader::ComputeShaderEntryPoint<(), Layout>"),
-             format!("compute_shader_entry_point(::std::ffi::CStr::from_ptr(NAME.as_ptr() as *const _), Layout(ShaderStages {{ compute: true, .. ShaderStages::none() }}))"))
+             format!("compute_shader_entry_point(::std::ffi::CStr::from_ptr(NAME.as_ptr() as *const _), [{}, {}, {}], Layout(ShaderStages {{ compute: true, .. ShaderStages::none() }}))",
+                     local_size[0], local_size[1], local_size[2]))
         },
 
         enums::ExecutionModel::ExecutionModelKernel => panic!("Kernels are not supported"),
@@ -93,7 +102,26 @@ pub fn write_entry_point(doc: &parse::Spirv, instruction: &parse::Instruction) -
                                          .collect::<Vec<String>>().join(", "),
                 f_call = f_call);
 
-    (interface_structs, entry_point)
+    Ok((interface_structs, entry_point))
+}
+
+/// Returns the `LocalSize` execution mode of the given entry point, if any.
+fn local_size(doc: &parse::Spirv, entry_point_id: u32) -> Option<[u32; 3]> {
+    for instruction in doc.instructions.iter() {
+        match instruction {
+            &parse::Instruction::ExecutionMode { target_id,
+                                                 mode: enums::ExecutionMode::ExecutionModeLocalSize,
+                                                 ref optional_literals }
+                                               if target_id == entry_point_id =>
+            {
+                // The parser guarantees that `LocalSize` has three literals.
+                return optional_literals.get(0 .. 3).map(|s| [s[0], s[1], s[2]]);
+            },
+            _ => ()
+        }
+    }
+
+    None
 }
 
 fn write_interface_structs(doc: &parse::Spirv, capitalized_ep_name: &str, interface: &[u32],
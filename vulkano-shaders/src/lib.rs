@@ -8,6 +8,7 @@
 // according to those terms.
 
 extern crate glsl_to_spirv;
+extern crate vulkano;
 
 use std::env;
 use std::fs;
@@ -17,13 +18,14 @@ use std::io::Read;
 use std::io::Write;
 use std::path::Path;
 
-pub use parse::ParseError;
+pub use vulkano::pipeline::spirv::SpirvParseError as ParseError;
 pub use glsl_to_spirv::ShaderType;
 
+use vulkano::pipeline::spirv::enums;
+use vulkano::pipeline::spirv::parse;
+
 mod descriptor_sets;
 mod entry_point;
-mod enums;
-mod parse;
 mod structs;
 
 pub fn build_glsl_shaders<'a, I>(shaders: I)
@@ -163,7 +165,8 @@ impl {name} {{
         let mut outside_impl = String::new();
         for instruction in doc.instructions.iter() {
             if let &parse::Instruction::EntryPoint { .. } = instruction {
-                let (outside, entry_point) = entry_point::write_entry_point(&doc, instruction);
+                let (outside, entry_point) = try!(entry_point::write_entry_point(&doc,
+                                                                                 instruction));
                 output.push_str(&entry_point);
                 outside_impl.push_str(&outside);
             }
@@ -182,7 +185,7 @@ impl {name} {{
         output.push_str("}");
 
         // descriptor sets
-        output.push_str(&descriptor_sets::write_descriptor_sets(&doc));
+        output.push_str(&try!(descriptor_sets::write_descriptor_sets(&doc)));
     }
 
     Ok(output)
@@ -192,6 +195,11 @@ impl {name} {{
 pub enum Error {
     IoError(IoError),
     ParseError(ParseError),
+    /// The compute shader entry point with this name doesn't have a `LocalSize` execution mode.
+    MissingLocalSize(String),
+    /// The length of the array of descriptors with this name isn't a constant, for example
+    /// because it is a specialization constant.
+    NonConstantArrayLength(String),
 }
 
 impl From<IoError> for Error {
@@ -437,3 +445,55 @@ fn capability_name(cap: &enums::Capability) -> Option<&'static str> {
         enums::Capability::CapabilityMultiViewport => Some("multi_viewport"),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use parse;
+    use reflect;
+    use Error;
+
+    #[test]
+    fn test() {
+        let data = include_bytes!("../tests/frag.spv");
+        println!("{:#?}", parse::parse_spirv(data).unwrap());
+    }
+
+    #[test]
+    fn missing_local_size() {
+        // A `GLCompute` entry point named "main" without any execution mode.
+        let words = [0x07230203, 0x00010000, 0, 2, 0,
+                     (2 << 16) | 17, 1,
+                     (3 << 16) | 14, 0, 1,
+                     (5 << 16) | 15, 5, 1, 0x6e69616d, 0];
+        let data = words.iter().flat_map(|&w: &u32| {
+            vec![w as u8, (w >> 8) as u8, (w >> 16) as u8, (w >> 24) as u8]
+        }).collect::<Vec<u8>>();
+
+        match reflect("Shader", &data[..]) {
+            Err(Error::MissingLocalSize(ref name)) => assert_eq!(name, "main"),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn spec_constant_array_length() {
+        // A uniform array of samplers whose length is a specialization constant.
+        let words = [0x07230203, 0x00010000, 0, 7, 0,
+                     (4 << 16) | 71, 5, 34, 0,
+                     (4 << 16) | 71, 5, 33, 0,
+                     (2 << 16) | 26, 1,
+                     (4 << 16) | 21, 2, 32, 0,
+                     (4 << 16) | 50, 2, 3, 4,
+                     (4 << 16) | 28, 4, 1, 3,
+                     (4 << 16) | 32, 6, 0, 4,
+                     (4 << 16) | 59, 6, 5, 0];
+        let data = words.iter().flat_map(|&w: &u32| {
+            vec![w as u8, (w >> 8) as u8, (w >> 16) as u8, (w >> 24) as u8]
+        }).collect::<Vec<u8>>();
+
+        match reflect("Shader", &data[..]) {
+            Err(Error::NonConstantArrayLength(_)) => (),
+            _ => panic!()
+        }
+    }
+}
@@ -46,23 +46,29 @@ use buffer::traits::TypedBuffer;
 use buffer::traits::PipelineBarrierRequest;
 use buffer::traits::PipelineMemoryBarrierRequest;
 use command_buffer::Submission;
+use command_buffer::pool::CommandPool;
+use command_buffer::sys::UnsafeCommandBufferBuilder;
 use device::Device;
 use device::Queue;
 use instance::QueueFamily;
 use memory::Content;
 use memory::CpuAccess as MemCpuAccess;
+use memory::DedicatedAlloc;
+use memory::DedicatedAllocHint;
+use memory::DeviceMemoryAllocError;
+use memory::MemoryUsage;
 use memory::pool::AllocLayout;
+use memory::pool::DedicatedAllocError;
 use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
 use memory::pool::StdMemoryPool;
+use memory::pool::alloc_for_resource;
 use sync::FenceWaitError;
 use sync::Sharing;
 use sync::Fence;
 use sync::AccessFlagBits;
 use sync::PipelineStages;
 
-use OomError;
-
 /// Buffer whose content is accessible by the CPU.
 #[derive(Debug)]
 pub struct CpuAccessibleBuffer<T: ?Sized, A = Arc<StdMemoryPool>> where A: MemoryPool {
@@ -94,7 +100,7 @@ impl<T> CpuAccessibleBuffer<T> {
     #[deprecated]
     #[inline]
     pub fn new<'a, I>(device: &Arc<Device>, usage: &Usage, queue_families: I)
-                      -> Result<Arc<CpuAccessibleBuffer<T>>, OomError>
+                      -> Result<Arc<CpuAccessibleBuffer<T>>, DeviceMemoryAllocError>
         where I: IntoIterator<Item = QueueFamily<'a>>
     {
         unsafe {
@@ -104,7 +110,7 @@ impl<T> CpuAccessibleBuffer<T> {
 
     /// Builds a new buffer with some data in it. Only allowed for sized data.
     pub fn from_data<'a, I>(device: &Arc<Device>, usage: &Usage, queue_families: I, data: T)
-                            -> Result<Arc<CpuAccessibleBuffer<T>>, OomError>
+                            -> Result<Arc<CpuAccessibleBuffer<T>>, DeviceMemoryAllocError>
         where I: IntoIterator<Item = QueueFamily<'a>>,
               T: Content + 'static,
     {
@@ -129,7 +135,8 @@ impl<T> CpuAccessibleBuffer<T> {
     /// Builds a new uninitialized buffer. Only allowed for sized data.
     #[inline]
     pub unsafe fn uninitialized<'a, I>(device: &Arc<Device>, usage: &Usage, queue_families: I)
-                                       -> Result<Arc<CpuAccessibleBuffer<T>>, OomError>
+                                       -> Result<Arc<CpuAccessibleBuffer<T>>,
+                                                 DeviceMemoryAllocError>
         where I: IntoIterator<Item = QueueFamily<'a>>
     {
         CpuAccessibleBuffer::raw(device, mem::size_of::<T>(), usage, queue_families)
@@ -140,7 +147,7 @@ impl<T> CpuAccessibleBuffer<[T]> {
     /// Builds a new buffer that contains an array `T`. The initial data comes from an iterator
     /// that produces that list of Ts.
     pub fn from_iter<'a, I, Q>(device: &Arc<Device>, usage: &Usage, queue_families: Q, data: I)
-                               -> Result<Arc<CpuAccessibleBuffer<[T]>>, OomError>
+                               -> Result<Arc<CpuAccessibleBuffer<[T]>>, DeviceMemoryAllocError>
         where I: ExactSizeIterator<Item = T>,
               T: Content + 'static,
               Q: IntoIterator<Item = QueueFamily<'a>>
@@ -171,7 +178,7 @@ impl<T> CpuAccessibleBuffer<[T]> {
     #[inline]
     #[deprecated]
     pub fn array<'a, I>(device: &Arc<Device>, len: usize, usage: &Usage, queue_families: I)
-                      -> Result<Arc<CpuAccessibleBuffer<[T]>>, OomError>
+                      -> Result<Arc<CpuAccessibleBuffer<[T]>>, DeviceMemoryAllocError>
         where I: IntoIterator<Item = QueueFamily<'a>>
     {
         unsafe {
@@ -183,7 +190,8 @@ impl<T> CpuAccessibleBuffer<[T]> {
     #[inline]
     pub unsafe fn uninitialized_array<'a, I>(device: &Arc<Device>, len: usize, usage: &Usage,
                                              queue_families: I)
-                                             -> Result<Arc<CpuAccessibleBuffer<[T]>>, OomError>
+                                             -> Result<Arc<CpuAccessibleBuffer<[T]>>,
+                                                       DeviceMemoryAllocError>
         where I: IntoIterator<Item = QueueFamily<'a>>
     {
         CpuAccessibleBuffer::raw(device, len * mem::size_of::<T>(), usage, queue_families)
@@ -197,8 +205,102 @@ impl<T: ?Sized> CpuAccessibleBuffer<T> {
     ///
     /// You must ensure that the size that you pass is correct for `T`.
     ///
+    #[inline]
     pub unsafe fn raw<'a, I>(device: &Arc<Device>, size: usize, usage: &Usage, queue_families: I)
-                             -> Result<Arc<CpuAccessibleBuffer<T>>, OomError>
+                             -> Result<Arc<CpuAccessibleBuffer<T>>, DeviceMemoryAllocError>
+        where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        CpuAccessibleBuffer::raw_with_pool(device, size, usage, queue_families,
+                                           &Device::standard_pool(device))
+    }
+
+    /// Moves the buffer to a fuller block of memory of its pool, in order to reduce
+    /// fragmentation.
+    ///
+    /// Returns `None` if the buffer can't be moved. Otherwise, records in `cb` a copy of the
+    /// content of this buffer to a new buffer, and returns the new buffer. See
+    /// `StdMemoryPool::defragment_buffers` for more information.
+    ///
+    /// # Safety
+    ///
+    /// - Must be called outside of a render pass.
+    /// - This buffer must not be written by the device while `cb` executes, and must not be used
+    ///   anymore once `cb` has finished executing.
+    /// - The new buffer must not be used before `cb` has finished executing.
+    /// - If this buffer was created with only one queue family, `cb` must belong to it.
+    ///
+    pub unsafe fn defragment<P>(&self, cb: &mut UnsafeCommandBufferBuilder<P>)
+                                -> Result<Option<Arc<CpuAccessibleBuffer<T>>>,
+                                          DeviceMemoryAllocError>
+        where P: CommandPool
+    {
+        let pool = self.memory.pool();
+        let mut moved = try!(pool.defragment_buffers(cb, Some((&self.inner, &self.memory))));
+
+        let moved = match moved.pop() {
+            Some(moved) => moved,
+            None => return Ok(None),
+        };
+
+        Ok(Some(Arc::new(CpuAccessibleBuffer {
+            inner: moved.buffer,
+            memory: moved.memory,
+            queue_families: self.queue_families.clone(),
+            latest_submission: RwLock::new(LatestSubmission {
+                read_submissions: Mutex::new(vec![]),
+                write_submission: None,
+            }),
+            marker: PhantomData,
+        })))
+    }
+}
+
+impl<T: ?Sized, A> CpuAccessibleBuffer<T, A> where A: MemoryPool {
+    /// Same as `raw`, but allocates the memory of the buffer from `pool` instead of the standard
+    /// memory pool of the device.
+    ///
+    /// # Safety
+    ///
+    /// - You must ensure that the size that you pass is correct for `T`.
+    /// - The pool must return allocations that are mapped when they are in a host-visible memory
+    ///   type.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the buffer must be bound to a dedicated allocation and `pool` doesn't support
+    ///   dedicated allocations.
+    ///
+    #[inline]
+    pub unsafe fn raw_with_pool<'a, I>(device: &Arc<Device>, size: usize, usage: &Usage,
+                                       queue_families: I, pool: &A)
+                                       -> Result<Arc<CpuAccessibleBuffer<T, A>>,
+                                                 DeviceMemoryAllocError>
+        where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        CpuAccessibleBuffer::raw_with_pool_and_hint(device, size, usage, queue_families, pool,
+                                                    DedicatedAllocHint::Auto)
+    }
+
+    /// Same as `raw_with_pool`, but `dedicated` controls whether the buffer gets a memory
+    /// allocation of its own.
+    ///
+    /// # Safety
+    ///
+    /// - You must ensure that the size that you pass is correct for `T`.
+    /// - The pool must return allocations that are mapped when they are in a host-visible memory
+    ///   type.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the buffer must be bound to a dedicated allocation, because the
+    ///   implementation requires it or because `dedicated` is `DedicatedAllocHint::Always`, and
+    ///   `pool` doesn't support dedicated allocations.
+    ///
+    pub unsafe fn raw_with_pool_and_hint<'a, I>(device: &Arc<Device>, size: usize,
+                                                usage: &Usage, queue_families: I, pool: &A,
+                                                dedicated: DedicatedAllocHint)
+                                                -> Result<Arc<CpuAccessibleBuffer<T, A>>,
+                                                          DeviceMemoryAllocError>
         where I: IntoIterator<Item = QueueFamily<'a>>
     {
         let queue_families = queue_families.into_iter().map(|f| f.id())
@@ -213,19 +315,31 @@ impl<T: ?Sized> CpuAccessibleBuffer<T> {
 
             match UnsafeBuffer::new(device, size, &usage, sharing, SparseLevel::none()) {
                 Ok(b) => b,
-                Err(BufferCreationError::OomError(err)) => return Err(err),
+                Err(BufferCreationError::OomError(err)) => return Err(err.into()),
                 Err(_) => unreachable!()        // We don't use sparse binding, therefore the other
                                                 // errors can't happen
             }
         };
 
-        let mem_ty = device.physical_device().memory_types()
-                           .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
-                           .filter(|t| t.is_host_visible())
-                           .next().unwrap();    // Vk specs guarantee that this can't fail
+        // Vk specs guarantee that there's always a host-visible memory type. We don't use
+        // `MemoryUsage::Upload`, as it could waste the scarce memory that is both host-visible
+        // and device-local on large staging resources.
+        let mem_ty = MemoryUsage::Staging.choose(device.physical_device(),
+                                                 mem_reqs.memory_type_bits).unwrap();
 
-        let mem = try!(MemoryPool::alloc(&Device::standard_pool(device), mem_ty,
-                                         mem_reqs.size, mem_reqs.alignment, AllocLayout::Linear));
+        let mem = match alloc_for_resource(pool, mem_ty, &mem_reqs, AllocLayout::Linear,
+                                           dedicated, DedicatedAlloc::Buffer(&buffer))
+        {
+            Ok(mem) => mem,
+            Err(DedicatedAllocError::OomError(err)) => return Err(err.into()),
+            Err(DedicatedAllocError::TooManyObjects) => {
+                return Err(DeviceMemoryAllocError::TooManyObjects)
+            },
+            Err(DedicatedAllocError::Unsupported) => {
+                panic!("the buffer requires a dedicated allocation, but the pool doesn't \
+                        support them")
+            },
+        };
         debug_assert!((mem.offset() % mem_reqs.alignment) == 0);
         debug_assert!(mem.mapped_memory().is_some());
         try!(buffer.bind_memory(mem.memory(), mem.offset()));
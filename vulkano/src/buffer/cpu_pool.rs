@@ -0,0 +1,492 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Ring of buffers whose content is accessible to the CPU.
+//!
+//! The `CpuBufferPool` is designed for data that is uploaded once and read by the GPU during a
+//! single frame, like per-frame uniforms or dynamically-generated vertices. Instead of creating
+//! a new `CpuAccessibleBuffer` every frame, you ask the pool for a new sub-buffer that contains
+//! your data.
+//!
+//! Internally the pool holds a list of chunks of the same size, which are sub-allocated from
+//! large blocks of host-visible memory. When a sub-buffer is destroyed, which happens once it is
+//! no longer used by any command buffer and therefore once the GPU has finished reading it, its
+//! chunk is recycled. When no chunk is available, a new memory block is allocated with as many
+//! chunks as the pool already has, which doubles the total number of chunks.
+
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::Range;
+use std::ptr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use smallvec::SmallVec;
+
+use buffer::sys::BufferCreationError;
+use buffer::sys::SparseLevel;
+use buffer::sys::UnsafeBuffer;
+use buffer::sys::Usage;
+use buffer::traits::AccessRange;
+use buffer::traits::Buffer;
+use buffer::traits::GpuAccessResult;
+use buffer::traits::TypedBuffer;
+use command_buffer::Submission;
+use device::Device;
+use instance::QueueFamily;
+use memory::Content;
+use memory::DeviceMemoryAllocError;
+use memory::MemoryUsage;
+use memory::pool::AllocLayout;
+use memory::pool::MemoryPool;
+use memory::pool::MemoryPoolAlloc;
+use memory::pool::StdMemoryPool;
+use sync::Sharing;
+
+// Number of chunks of the first memory block allocated by a pool.
+const INITIAL_CHUNKS: usize = 4;
+
+/// Pool of buffers whose content is accessible by the CPU, meant for data that changes every
+/// frame.
+///
+/// See the documentation of the `cpu_pool` module.
+pub struct CpuBufferPool<T: ?Sized, A = Arc<StdMemoryPool>> where A: MemoryPool {
+    device: Arc<Device>,
+
+    // Memory pool from which the memory blocks are allocated.
+    pool: A,
+
+    // All the chunks that have been created so far.
+    chunks: Mutex<Vec<Arc<Chunk<A>>>>,
+
+    // Size in bytes of the content of a chunk.
+    one_size: usize,
+
+    // Usage of all the chunks.
+    usage: Usage,
+
+    // Queue families allowed to access the chunks.
+    queue_families: SmallVec<[u32; 4]>,
+
+    // Necessary to make it compile.
+    marker: PhantomData<Box<T>>,
+}
+
+// A chunk of memory that can hold one sub-buffer.
+struct Chunk<A> where A: MemoryPool {
+    inner: UnsafeBuffer,
+
+    // The memory block the chunk belongs to. Shared between all the chunks of the block.
+    block: Arc<A::Alloc>,
+
+    // Offset of the chunk within `block`.
+    offset: usize,
+
+    // True if a `CpuBufferPoolSubbuffer` currently uses this chunk.
+    in_use: AtomicBool,
+}
+
+impl<T> CpuBufferPool<T> {
+    /// Builds a new pool whose sub-buffers contain a `T`.
+    #[inline]
+    pub fn new<'a, I>(device: &Arc<Device>, usage: &Usage, queue_families: I)
+                      -> CpuBufferPool<T>
+        where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        unsafe {
+            CpuBufferPool::raw(device, mem::size_of::<T>(), usage, queue_families)
+        }
+    }
+}
+
+impl<T, A> CpuBufferPool<T, A> where A: MemoryPool {
+    /// Returns a sub-buffer that contains `data`.
+    ///
+    /// Reuses a chunk that is no longer in use if possible, or allocates a new memory block
+    /// otherwise.
+    pub fn next(&self, data: T) -> Result<Arc<CpuBufferPoolSubbuffer<T, A>>, DeviceMemoryAllocError>
+        where T: Content + 'static
+    {
+        unsafe {
+            let chunk = try!(self.next_chunk());
+
+            {
+                let range = chunk.memory_range(self.one_size);
+                let mut mapping = chunk.block.mapped_memory().unwrap().read_write::<T>(range);
+                ptr::write::<T>(&mut *mapping, data);
+            }
+
+            Ok(CpuBufferPoolSubbuffer::new(chunk, &self.queue_families))
+        }
+    }
+}
+
+impl<T> CpuBufferPool<[T]> {
+    /// Builds a new pool whose sub-buffers contain exactly `len` elements of type `T`.
+    #[inline]
+    pub fn array<'a, I>(device: &Arc<Device>, len: usize, usage: &Usage, queue_families: I)
+                        -> CpuBufferPool<[T]>
+        where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        unsafe {
+            CpuBufferPool::raw(device, len * mem::size_of::<T>(), usage, queue_families)
+        }
+    }
+}
+
+impl<T, A> CpuBufferPool<[T], A> where A: MemoryPool {
+    /// Returns the number of elements of each sub-buffer.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.one_size / mem::size_of::<T>()
+    }
+
+    /// Returns a sub-buffer whose elements are produced by `data`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the length of the iterator is not the same as the length of the sub-buffers
+    ///   of this pool.
+    ///
+    pub fn chunk<I>(&self, data: I) -> Result<Arc<CpuBufferPoolSubbuffer<[T], A>>,
+                                              DeviceMemoryAllocError>
+        where I: ExactSizeIterator<Item = T>,
+              T: Content + 'static
+    {
+        assert_eq!(data.len(), self.len());
+
+        unsafe {
+            let chunk = try!(self.next_chunk());
+
+            {
+                let range = chunk.memory_range(self.one_size);
+                let mut mapping = chunk.block.mapped_memory().unwrap().read_write::<[T]>(range);
+
+                for (i, o) in data.zip(mapping.iter_mut()) {
+                    ptr::write(o, i);
+                }
+            }
+
+            Ok(CpuBufferPoolSubbuffer::new(chunk, &self.queue_families))
+        }
+    }
+}
+
+impl<T: ?Sized> CpuBufferPool<T> {
+    /// Builds a new pool without checking the size.
+    ///
+    /// # Safety
+    ///
+    /// You must ensure that the size that you pass is correct for `T`.
+    ///
+    #[inline]
+    pub unsafe fn raw<'a, I>(device: &Arc<Device>, one_size: usize, usage: &Usage,
+                             queue_families: I) -> CpuBufferPool<T>
+        where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        CpuBufferPool::raw_with_pool(device, one_size, usage, queue_families,
+                                     &Device::standard_pool(device))
+    }
+}
+
+impl<T: ?Sized, A> CpuBufferPool<T, A> where A: MemoryPool {
+    /// Same as `raw`, but the memory blocks of the pool are allocated from `pool` instead of the
+    /// standard memory pool of the device. The pool keeps a clone of `pool`.
+    ///
+    /// # Safety
+    ///
+    /// - You must ensure that the size that you pass is correct for `T`.
+    /// - The pool must return allocations that are mapped when they are in a host-visible memory
+    ///   type.
+    ///
+    pub unsafe fn raw_with_pool<'a, I>(device: &Arc<Device>, one_size: usize, usage: &Usage,
+                                       queue_families: I, pool: &A) -> CpuBufferPool<T, A>
+        where I: IntoIterator<Item = QueueFamily<'a>>, A: Clone
+    {
+        let queue_families = queue_families.into_iter().map(|f| f.id())
+                                           .collect::<SmallVec<[u32; 4]>>();
+
+        CpuBufferPool {
+            device: device.clone(),
+            pool: pool.clone(),
+            chunks: Mutex::new(Vec::new()),
+            one_size: one_size,
+            usage: *usage,
+            queue_families: queue_families,
+            marker: PhantomData,
+        }
+    }
+
+    // Finds a chunk that isn't in use and marks it as used. Allocates a new memory block if
+    // none is available.
+    unsafe fn next_chunk(&self) -> Result<Arc<Chunk<A>>, DeviceMemoryAllocError> {
+        let mut chunks = self.chunks.lock().unwrap();
+
+        for chunk in chunks.iter() {
+            if !chunk.in_use.swap(true, Ordering::Acquire) {
+                return Ok(chunk.clone());
+            }
+        }
+
+        // No chunk is available. Doubling the total number of chunks.
+        let num = if chunks.is_empty() { INITIAL_CHUNKS } else { chunks.len() };
+        let new_chunks = try!(self.alloc_block(num));
+        chunks.extend(new_chunks.into_iter());
+
+        let chunk = chunks.last().unwrap().clone();
+        chunk.in_use.store(true, Ordering::Release);
+        Ok(chunk)
+    }
+
+    // Allocates a new memory block that contains `num` chunks.
+    unsafe fn alloc_block(&self, num: usize) -> Result<Vec<Arc<Chunk<A>>>, DeviceMemoryAllocError>
+    {
+        let mut buffers = Vec::with_capacity(num);
+        let mut mem_reqs = None;
+
+        for _ in 0 .. num {
+            let sharing = if self.queue_families.len() >= 2 {
+                Sharing::Concurrent(self.queue_families.iter().cloned())
+            } else {
+                Sharing::Exclusive
+            };
+
+            match UnsafeBuffer::new(&self.device, self.one_size, &self.usage, sharing,
+                                    SparseLevel::none())
+            {
+                Ok((buffer, reqs)) => {
+                    buffers.push(buffer);
+                    mem_reqs = Some(reqs);
+                },
+                Err(BufferCreationError::OomError(err)) => return Err(err.into()),
+                Err(_) => unreachable!()        // We don't use sparse binding, therefore the other
+                                                // errors can't happen
+            }
+        }
+
+        let mem_reqs = mem_reqs.unwrap();
+        let stride = (mem_reqs.size + mem_reqs.alignment - 1) / mem_reqs.alignment *
+                     mem_reqs.alignment;
+
+        // Vk specs guarantee that there's always a host-visible memory type.
+        let mem_ty = MemoryUsage::Upload.choose(self.device.physical_device(),
+                                                mem_reqs.memory_type_bits).unwrap();
+
+        let block = try!(MemoryPool::alloc(&self.pool, mem_ty, stride * num, mem_reqs.alignment,
+                                           AllocLayout::Linear));
+        debug_assert!((block.offset() % mem_reqs.alignment) == 0);
+        debug_assert!(block.mapped_memory().is_some());
+        let block = Arc::new(block);
+
+        let mut chunks = Vec::with_capacity(num);
+        for (i, buffer) in buffers.into_iter().enumerate() {
+            try!(buffer.bind_memory(block.memory(), block.offset() + i * stride));
+
+            chunks.push(Arc::new(Chunk {
+                inner: buffer,
+                block: block.clone(),
+                offset: i * stride,
+                in_use: AtomicBool::new(false),
+            }));
+        }
+
+        Ok(chunks)
+    }
+
+    /// Returns the device used to create this pool.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    /// Returns the number of chunks that have been allocated by this pool, whether they are in
+    /// use or not.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.chunks.lock().unwrap().len()
+    }
+
+    /// Returns the queue families the sub-buffers can be used on.
+    // TODO: use a custom iterator
+    #[inline]
+    pub fn queue_families(&self) -> Vec<QueueFamily> {
+        self.queue_families.iter().map(|&num| {
+            self.device().physical_device().queue_family_by_id(num).unwrap()
+        }).collect()
+    }
+}
+
+impl<A> Chunk<A> where A: MemoryPool {
+    // Returns the range of the mapped memory that contains the chunk.
+    #[inline]
+    fn memory_range(&self, size: usize) -> Range<usize> {
+        let start = self.block.offset() + self.offset;
+        start .. start + size
+    }
+}
+
+/// A sub-buffer allocated from a `CpuBufferPool`.
+///
+/// The chunk of the pool that this sub-buffer uses is recycled when the sub-buffer is destroyed.
+/// Since command buffers keep the buffers they use alive until their execution is finished, this
+/// can't happen while the GPU is still reading it.
+pub struct CpuBufferPoolSubbuffer<T: ?Sized, A = Arc<StdMemoryPool>> where A: MemoryPool {
+    chunk: Arc<Chunk<A>>,
+
+    // Queue families allowed to access this sub-buffer.
+    queue_families: SmallVec<[u32; 4]>,
+
+    // Necessary to make it compile.
+    marker: PhantomData<Box<T>>,
+}
+
+impl<T: ?Sized, A> CpuBufferPoolSubbuffer<T, A> where A: MemoryPool {
+    #[inline]
+    fn new(chunk: Arc<Chunk<A>>, queue_families: &[u32]) -> Arc<CpuBufferPoolSubbuffer<T, A>> {
+        Arc::new(CpuBufferPoolSubbuffer {
+            chunk: chunk,
+            queue_families: queue_families.iter().cloned().collect(),
+            marker: PhantomData,
+        })
+    }
+
+    /// Returns the device used to create this sub-buffer.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        self.chunk.inner.device()
+    }
+}
+
+unsafe impl<T: ?Sized, A> Buffer for CpuBufferPoolSubbuffer<T, A>
+    where T: 'static + Send + Sync, A: MemoryPool
+{
+    #[inline]
+    fn inner(&self) -> &UnsafeBuffer {
+        &self.chunk.inner
+    }
+
+    #[inline]
+    fn blocks(&self, _: Range<usize>) -> Vec<usize> {
+        vec![0]
+    }
+
+    #[inline]
+    fn block_memory_range(&self, _: usize) -> Range<usize> {
+        0 .. self.size()
+    }
+
+    fn needs_fence(&self, _: bool, _: Range<usize>) -> Option<bool> {
+        Some(false)
+    }
+
+    #[inline]
+    fn host_accesses(&self, _: usize) -> bool {
+        true
+    }
+
+    unsafe fn gpu_access(&self, _: &mut Iterator<Item = AccessRange>,
+                         submission: &Arc<Submission>) -> GpuAccessResult
+    {
+        let queue_id = submission.queue().family().id();
+        if self.queue_families.iter().find(|&&id| id == queue_id).is_none() {
+            panic!("Trying to submit to family {} a buffer suitable for families {:?}",
+                   queue_id, self.queue_families);
+        }
+
+        // The content is only written by the CPU before the sub-buffer is handed out, so there
+        // is nothing to wait for.
+        GpuAccessResult {
+            dependencies: vec![],
+            additional_wait_semaphore: None,
+            additional_signal_semaphore: None,
+        }
+    }
+}
+
+unsafe impl<T: ?Sized, A> TypedBuffer for CpuBufferPoolSubbuffer<T, A>
+    where T: 'static + Send + Sync, A: MemoryPool
+{
+    type Content = T;
+}
+
+impl<T: ?Sized, A> Drop for CpuBufferPoolSubbuffer<T, A> where A: MemoryPool {
+    #[inline]
+    fn drop(&mut self) {
+        self.chunk.in_use.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use buffer::BufferUsage;
+    use buffer::CpuBufferPool;
+    use device::Device;
+    use instance::MemoryType;
+    use memory::DeviceMemoryAllocError;
+    use memory::pool::AllocLayout;
+    use memory::pool::MemoryPool;
+    use memory::pool::StdMemoryPool;
+    use memory::pool::StdMemoryPoolAlloc;
+
+    // Memory pool that forwards to the standard pool and counts the allocations.
+    #[derive(Clone)]
+    struct CountingPool {
+        inner: Arc<StdMemoryPool>,
+        count: Arc<AtomicUsize>,
+    }
+
+    unsafe impl MemoryPool for CountingPool {
+        type Alloc = StdMemoryPoolAlloc;
+
+        fn alloc(&self, ty: MemoryType, size: usize, alignment: usize, layout: AllocLayout)
+                 -> Result<StdMemoryPoolAlloc, DeviceMemoryAllocError>
+        {
+            self.count.fetch_add(1, Ordering::SeqCst);
+            self.inner.alloc(ty, size, alignment, layout)
+        }
+    }
+
+    #[test]
+    fn recycles_chunks() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let pool = CpuBufferPool::<u32>::new(&device, &BufferUsage::all(), Some(queue.family()));
+        assert_eq!(pool.capacity(), 0);
+
+        let first = (0 .. 6).map(|i| pool.next(i).unwrap()).collect::<Vec<_>>();
+        assert_eq!(pool.capacity(), 8);
+
+        drop(first);
+        let _second = (0 .. 8).map(|i| pool.next(i).unwrap()).collect::<Vec<_>>();
+        assert_eq!(pool.capacity(), 8);
+    }
+
+    #[test]
+    fn custom_memory_pool() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let memory_pool = CountingPool {
+            inner: Device::standard_pool(&device),
+            count: Arc::new(AtomicUsize::new(0)),
+        };
+
+        let pool = unsafe {
+            CpuBufferPool::<u32, _>::raw_with_pool(&device, 4, &BufferUsage::all(),
+                                                   Some(queue.family()), &memory_pool)
+        };
+
+        let _sub = pool.next(12).unwrap();
+        assert_eq!(memory_pool.count.load(Ordering::SeqCst), 1);
+    }
+}
@@ -30,16 +30,22 @@ use buffer::traits::Buffer;
 use buffer::traits::GpuAccessResult;
 use buffer::traits::TypedBuffer;
 use command_buffer::Submission;
+use command_buffer::pool::CommandPool;
+use command_buffer::sys::UnsafeCommandBufferBuilder;
 use device::Device;
 use instance::QueueFamily;
+use memory::DedicatedAlloc;
+use memory::DedicatedAllocHint;
+use memory::DeviceMemoryAllocError;
+use memory::MemoryUsage;
 use memory::pool::AllocLayout;
+use memory::pool::DedicatedAllocError;
 use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
 use memory::pool::StdMemoryPool;
+use memory::pool::alloc_for_resource;
 use sync::Sharing;
 
-use OomError;
-
 /// Buffer whose content is accessible by the CPU.
 #[derive(Debug)]
 pub struct DeviceLocalBuffer<T: ?Sized, A = Arc<StdMemoryPool>> where A: MemoryPool {
@@ -70,7 +76,7 @@ impl<T> DeviceLocalBuffer<T> {
     /// Builds a new buffer. Only allowed for sized data.
     #[inline]
     pub fn new<'a, I>(device: &Arc<Device>, usage: &Usage, queue_families: I)
-                      -> Result<Arc<DeviceLocalBuffer<T>>, OomError>
+                      -> Result<Arc<DeviceLocalBuffer<T>>, DeviceMemoryAllocError>
         where I: IntoIterator<Item = QueueFamily<'a>>
     {
         unsafe {
@@ -83,7 +89,7 @@ impl<T> DeviceLocalBuffer<[T]> {
     /// Builds a new buffer. Can be used for arrays.
     #[inline]
     pub fn array<'a, I>(device: &Arc<Device>, len: usize, usage: &Usage, queue_families: I)
-                      -> Result<Arc<DeviceLocalBuffer<[T]>>, OomError>
+                      -> Result<Arc<DeviceLocalBuffer<[T]>>, DeviceMemoryAllocError>
         where I: IntoIterator<Item = QueueFamily<'a>>
     {
         unsafe {
@@ -99,8 +105,97 @@ impl<T: ?Sized> DeviceLocalBuffer<T> {
     ///
     /// You must ensure that the size that you pass is correct for `T`.
     ///
+    #[inline]
     pub unsafe fn raw<'a, I>(device: &Arc<Device>, size: usize, usage: &Usage, queue_families: I)
-                             -> Result<Arc<DeviceLocalBuffer<T>>, OomError>
+                             -> Result<Arc<DeviceLocalBuffer<T>>, DeviceMemoryAllocError>
+        where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        DeviceLocalBuffer::raw_with_pool(device, size, usage, queue_families,
+                                         &Device::standard_pool(device))
+    }
+
+    /// Moves the buffer to a fuller block of memory of its pool, in order to reduce
+    /// fragmentation.
+    ///
+    /// Returns `None` if the buffer can't be moved. Otherwise, records in `cb` a copy of the
+    /// content of this buffer to a new buffer, and returns the new buffer. See
+    /// `StdMemoryPool::defragment_buffers` for more information.
+    ///
+    /// # Safety
+    ///
+    /// - Must be called outside of a render pass.
+    /// - This buffer must not be written by the device while `cb` executes, and must not be used
+    ///   anymore once `cb` has finished executing.
+    /// - The new buffer must not be used before `cb` has finished executing.
+    /// - If this buffer was created with only one queue family, `cb` must belong to it.
+    ///
+    pub unsafe fn defragment<P>(&self, cb: &mut UnsafeCommandBufferBuilder<P>)
+                                -> Result<Option<Arc<DeviceLocalBuffer<T>>>, DeviceMemoryAllocError>
+        where P: CommandPool
+    {
+        let pool = self.memory.pool();
+        let mut moved = try!(pool.defragment_buffers(cb, Some((&self.inner, &self.memory))));
+
+        let moved = match moved.pop() {
+            Some(moved) => moved,
+            None => return Ok(None),
+        };
+
+        Ok(Some(Arc::new(DeviceLocalBuffer {
+            inner: moved.buffer,
+            memory: moved.memory,
+            queue_families: self.queue_families.clone(),
+            latest_submission: Mutex::new(LatestSubmission {
+                read_submissions: SmallVec::new(),
+                write_submission: None,
+            }),
+            marker: PhantomData,
+        })))
+    }
+}
+
+impl<T: ?Sized, A> DeviceLocalBuffer<T, A> where A: MemoryPool {
+    /// Same as `raw`, but allocates the memory of the buffer from `pool` instead of the standard
+    /// memory pool of the device.
+    ///
+    /// # Safety
+    ///
+    /// You must ensure that the size that you pass is correct for `T`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the buffer must be bound to a dedicated allocation and `pool` doesn't support
+    ///   dedicated allocations.
+    ///
+    #[inline]
+    pub unsafe fn raw_with_pool<'a, I>(device: &Arc<Device>, size: usize, usage: &Usage,
+                                       queue_families: I, pool: &A)
+                                       -> Result<Arc<DeviceLocalBuffer<T, A>>,
+                                                 DeviceMemoryAllocError>
+        where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        DeviceLocalBuffer::raw_with_pool_and_hint(device, size, usage, queue_families, pool,
+                                                  DedicatedAllocHint::Auto)
+    }
+
+    /// Same as `raw_with_pool`, but `dedicated` controls whether the buffer gets a memory
+    /// allocation of its own.
+    ///
+    /// # Safety
+    ///
+    /// You must ensure that the size that you pass is correct for `T`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the buffer must be bound to a dedicated allocation, because the
+    ///   implementation requires it or because `dedicated` is `DedicatedAllocHint::Always`, and
+    ///   `pool` doesn't support dedicated allocations.
+    ///
+    pub unsafe fn raw_with_pool_and_hint<'a, I>(device: &Arc<Device>, size: usize,
+                                                usage: &Usage, queue_families: I, pool: &A,
+                                                dedicated: DedicatedAllocHint)
+                                                -> Result<Arc<DeviceLocalBuffer<T, A>>,
+                                                          DeviceMemoryAllocError>
         where I: IntoIterator<Item = QueueFamily<'a>>
     {
         let queue_families = queue_families.into_iter().map(|f| f.id())
@@ -115,23 +210,28 @@ impl<T: ?Sized> DeviceLocalBuffer<T> {
 
             match UnsafeBuffer::new(device, size, &usage, sharing, SparseLevel::none()) {
                 Ok(b) => b,
-                Err(BufferCreationError::OomError(err)) => return Err(err),
+                Err(BufferCreationError::OomError(err)) => return Err(err.into()),
                 Err(_) => unreachable!()        // We don't use sparse binding, therefore the other
                                                 // errors can't happen
             }
         };
 
-        let mem_ty = {
-            let device_local = device.physical_device().memory_types()
-                                     .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
-                                     .filter(|t| t.is_device_local());
-            let any = device.physical_device().memory_types()
-                            .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0);
-            device_local.chain(any).next().unwrap()
+        let mem_ty = MemoryUsage::DeviceLocal.choose(device.physical_device(),
+                                                     mem_reqs.memory_type_bits).unwrap();
+
+        let mem = match alloc_for_resource(pool, mem_ty, &mem_reqs, AllocLayout::Linear,
+                                           dedicated, DedicatedAlloc::Buffer(&buffer))
+        {
+            Ok(mem) => mem,
+            Err(DedicatedAllocError::OomError(err)) => return Err(err.into()),
+            Err(DedicatedAllocError::TooManyObjects) => {
+                return Err(DeviceMemoryAllocError::TooManyObjects)
+            },
+            Err(DedicatedAllocError::Unsupported) => {
+                panic!("the buffer requires a dedicated allocation, but the pool doesn't \
+                        support them")
+            },
         };
-
-        let mem = try!(MemoryPool::alloc(&Device::standard_pool(device), mem_ty,
-                                         mem_reqs.size, mem_reqs.alignment, AllocLayout::Linear));
         debug_assert!((mem.offset() % mem_reqs.alignment) == 0);
         try!(buffer.bind_memory(mem.memory(), mem.offset()));
 
@@ -17,6 +17,9 @@
 //! 
 //! The buffer will be stored in device-local memory if possible
 //!
+//! The `from_data` and `from_iter` constructors take care of uploading the initial content of the
+//! buffer through a staging buffer, which is the most common way to fill an immutable buffer.
+//!
 
 use std::marker::PhantomData;
 use std::mem;
@@ -28,6 +31,7 @@ use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use smallvec::SmallVec;
 
+use buffer::cpu_access::CpuAccessibleBuffer;
 use buffer::sys::BufferCreationError;
 use buffer::sys::SparseLevel;
 use buffer::sys::UnsafeBuffer;
@@ -36,17 +40,28 @@ use buffer::traits::AccessRange;
 use buffer::traits::Buffer;
 use buffer::traits::GpuAccessResult;
 use buffer::traits::TypedBuffer;
+use command_buffer::PrimaryCommandBufferBuilder;
 use command_buffer::Submission;
+use command_buffer::pool::CommandPool;
+use command_buffer::sys::UnsafeCommandBufferBuilder;
+use command_buffer::submit;
 use device::Device;
+use device::Queue;
+use device::QueueError;
 use instance::QueueFamily;
+use memory::Content;
+use memory::DedicatedAlloc;
+use memory::DedicatedAllocHint;
+use memory::DeviceMemoryAllocError;
+use memory::MemoryUsage;
 use memory::pool::AllocLayout;
+use memory::pool::DedicatedAllocError;
 use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
 use memory::pool::StdMemoryPool;
+use memory::pool::alloc_for_resource;
 use sync::Sharing;
 
-use OomError;
-
 /// Buffer that is written once then read for as long as it is alive.
 pub struct ImmutableBuffer<T: ?Sized, A = Arc<StdMemoryPool>> where A: MemoryPool {
     // Inner content.
@@ -68,26 +83,145 @@ impl<T> ImmutableBuffer<T> {
     /// Builds a new buffer. Only allowed for sized data.
     #[inline]
     pub fn new<'a, I>(device: &Arc<Device>, usage: &Usage, queue_families: I)
-                      -> Result<Arc<ImmutableBuffer<T>>, OomError>
+                      -> Result<Arc<ImmutableBuffer<T>>, DeviceMemoryAllocError>
         where I: IntoIterator<Item = QueueFamily<'a>>
     {
         unsafe {
             ImmutableBuffer::raw(device, mem::size_of::<T>(), usage, queue_families)
         }
     }
+
+    /// Builds a new buffer whose content is `data`.
+    ///
+    /// The data is written to a host-visible staging buffer, then copied to the new buffer by a
+    /// command buffer that is submitted to `queue`. The `transfer_dest` usage is automatically
+    /// added to `usage`, and the family of `queue` is automatically added to `queue_families`.
+    ///
+    /// Returns the buffer and the submission of the copy. Command buffers that later use the
+    /// buffer automatically wait for the copy to be finished.
+    pub fn from_data<'a, I>(data: T, usage: &Usage, queue_families: I, queue: &'a Arc<Queue>)
+                            -> Result<(Arc<ImmutableBuffer<T>>, Arc<Submission>), QueueError>
+        where I: IntoIterator<Item = QueueFamily<'a>>, T: Content + 'static + Send + Sync
+    {
+        let source = try!(CpuAccessibleBuffer::from_data(queue.device(), &Usage::transfer_source(),
+                                                         Some(queue.family()), data));
+        let families = upload_queue_families(queue_families, queue);
+        let buffer = try!(ImmutableBuffer::new(queue.device(), &upload_usage(usage), families));
+        let submission = try!(upload(&source, &buffer, queue));
+        Ok((buffer, submission))
+    }
+
+    /// Builds a new buffer whose content is `data`, and performs the copy on a dedicated
+    /// transfer queue.
+    ///
+    /// The buffer is exclusively owned by the family of `queue`. The staging copy is submitted
+    /// to `transfer_queue` and releases the ownership of the buffer, then a command buffer that
+    /// acquires the ownership is submitted to `queue`. This command buffer automatically waits
+    /// for the copy with a semaphore, so that `queue` doesn't execute the copy itself.
+    ///
+    /// If both queues belong to the same family, this is the same as calling `from_data` with
+    /// `transfer_queue`.
+    ///
+    /// Returns the buffer and the submission of the acquire operation, which keeps the copy
+    /// alive.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the two queues don't belong to the same device.
+    ///
+    pub fn from_data_with_transfer_queue(data: T, usage: &Usage, transfer_queue: &Arc<Queue>,
+                                         queue: &Arc<Queue>)
+                                         -> Result<(Arc<ImmutableBuffer<T>>, Arc<Submission>),
+                                                   QueueError>
+        where T: Content + 'static + Send + Sync
+    {
+        assert_eq!(&**transfer_queue.device() as *const Device,
+                   &**queue.device() as *const Device);
+
+        if transfer_queue.family().id() == queue.family().id() {
+            return ImmutableBuffer::from_data(data, usage, None, transfer_queue);
+        }
+
+        let source = try!(CpuAccessibleBuffer::from_data(queue.device(), &Usage::transfer_source(),
+                                                         Some(transfer_queue.family()), data));
+        let buffer = unsafe {
+            try!(ImmutableBuffer::raw_impl(queue.device(), mem::size_of::<T>(),
+                                           &upload_usage(usage),
+                                           transfer_queue_families(transfer_queue, queue), false,
+                                           &Device::standard_pool(queue.device()),
+                                           DedicatedAllocHint::Auto))
+        };
+        let submission = try!(upload_with_transfer_queue(&source, &buffer, transfer_queue, queue));
+        Ok((buffer, submission))
+    }
 }
 
 impl<T> ImmutableBuffer<[T]> {
     /// Builds a new buffer. Can be used for arrays.
     #[inline]
     pub fn array<'a, I>(device: &Arc<Device>, len: usize, usage: &Usage, queue_families: I)
-                      -> Result<Arc<ImmutableBuffer<[T]>>, OomError>
+                      -> Result<Arc<ImmutableBuffer<[T]>>, DeviceMemoryAllocError>
         where I: IntoIterator<Item = QueueFamily<'a>>
     {
         unsafe {
             ImmutableBuffer::raw(device, len * mem::size_of::<T>(), usage, queue_families)
         }
     }
+
+    /// Builds a new buffer whose content is produced by an iterator.
+    ///
+    /// Same as `from_data`, but for arrays.
+    pub fn from_iter<'a, D, I>(data: D, usage: &Usage, queue_families: I, queue: &'a Arc<Queue>)
+                               -> Result<(Arc<ImmutableBuffer<[T]>>, Arc<Submission>),
+                                         QueueError>
+        where D: ExactSizeIterator<Item = T>, I: IntoIterator<Item = QueueFamily<'a>>,
+              T: Content + 'static + Send + Sync
+    {
+        let len = data.len();
+        let source = try!(CpuAccessibleBuffer::from_iter(queue.device(), &Usage::transfer_source(),
+                                                         Some(queue.family()), data));
+        let families = upload_queue_families(queue_families, queue);
+        let buffer = try!(ImmutableBuffer::array(queue.device(), len, &upload_usage(usage),
+                                                 families));
+        let submission = try!(upload(&source, &buffer, queue));
+        Ok((buffer, submission))
+    }
+
+    /// Builds a new buffer whose content is produced by an iterator, and performs the copy on a
+    /// dedicated transfer queue.
+    ///
+    /// Same as `from_data_with_transfer_queue`, but for arrays.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the two queues don't belong to the same device.
+    ///
+    pub fn from_iter_with_transfer_queue<D>(data: D, usage: &Usage, transfer_queue: &Arc<Queue>,
+                                            queue: &Arc<Queue>)
+                                            -> Result<(Arc<ImmutableBuffer<[T]>>,
+                                                       Arc<Submission>), QueueError>
+        where D: ExactSizeIterator<Item = T>, T: Content + 'static + Send + Sync
+    {
+        assert_eq!(&**transfer_queue.device() as *const Device,
+                   &**queue.device() as *const Device);
+
+        if transfer_queue.family().id() == queue.family().id() {
+            return ImmutableBuffer::from_iter(data, usage, None, transfer_queue);
+        }
+
+        let len = data.len();
+        let source = try!(CpuAccessibleBuffer::from_iter(queue.device(), &Usage::transfer_source(),
+                                                         Some(transfer_queue.family()), data));
+        let buffer = unsafe {
+            try!(ImmutableBuffer::raw_impl(queue.device(), len * mem::size_of::<T>(),
+                                           &upload_usage(usage),
+                                           transfer_queue_families(transfer_queue, queue), false,
+                                           &Device::standard_pool(queue.device()),
+                                           DedicatedAllocHint::Auto))
+        };
+        let submission = try!(upload_with_transfer_queue(&source, &buffer, transfer_queue, queue));
+        Ok((buffer, submission))
+    }
 }
 
 impl<T: ?Sized> ImmutableBuffer<T> {
@@ -97,15 +231,113 @@ impl<T: ?Sized> ImmutableBuffer<T> {
     ///
     /// You must ensure that the size that you pass is correct for `T`.
     ///
+    #[inline]
     pub unsafe fn raw<'a, I>(device: &Arc<Device>, size: usize, usage: &Usage, queue_families: I)
-                             -> Result<Arc<ImmutableBuffer<T>>, OomError>
+                             -> Result<Arc<ImmutableBuffer<T>>, DeviceMemoryAllocError>
+        where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        ImmutableBuffer::raw_with_pool(device, size, usage, queue_families,
+                                       &Device::standard_pool(device))
+    }
+
+    /// Moves the buffer to a fuller block of memory of its pool, in order to reduce
+    /// fragmentation.
+    ///
+    /// Returns `None` if the buffer can't be moved. Otherwise, records in `cb` a copy of the
+    /// content of this buffer to a new buffer, and returns the new buffer. See
+    /// `StdMemoryPool::defragment_buffers` for more information.
+    ///
+    /// # Safety
+    ///
+    /// - Must be called outside of a render pass.
+    /// - This buffer must not be written by the device while `cb` executes, and must not be used
+    ///   anymore once `cb` has finished executing.
+    /// - The new buffer must not be used before `cb` has finished executing.
+    /// - If this buffer was created with only one queue family, `cb` must belong to it.
+    ///
+    pub unsafe fn defragment<P>(&self, cb: &mut UnsafeCommandBufferBuilder<P>)
+                                -> Result<Option<Arc<ImmutableBuffer<T>>>, DeviceMemoryAllocError>
+        where P: CommandPool
+    {
+        let pool = self.memory.pool();
+        let mut moved = try!(pool.defragment_buffers(cb, Some((&self.inner, &self.memory))));
+
+        let moved = match moved.pop() {
+            Some(moved) => moved,
+            None => return Ok(None),
+        };
+
+        Ok(Some(Arc::new(ImmutableBuffer {
+            inner: moved.buffer,
+            memory: moved.memory,
+            queue_families: self.queue_families.clone(),
+            latest_write_submission: Mutex::new(None),
+            started_reading: AtomicBool::new(self.started_reading.load(Ordering::Acquire)),
+            marker: PhantomData,
+        })))
+    }
+}
+
+impl<T: ?Sized, A> ImmutableBuffer<T, A> where A: MemoryPool {
+    /// Same as `raw`, but allocates the memory of the buffer from `pool` instead of the standard
+    /// memory pool of the device.
+    ///
+    /// # Safety
+    ///
+    /// You must ensure that the size that you pass is correct for `T`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the buffer must be bound to a dedicated allocation and `pool` doesn't support
+    ///   dedicated allocations.
+    ///
+    #[inline]
+    pub unsafe fn raw_with_pool<'a, I>(device: &Arc<Device>, size: usize, usage: &Usage,
+                                       queue_families: I, pool: &A)
+                                       -> Result<Arc<ImmutableBuffer<T, A>>, DeviceMemoryAllocError>
+        where I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        ImmutableBuffer::raw_with_pool_and_hint(device, size, usage, queue_families, pool,
+                                                DedicatedAllocHint::Auto)
+    }
+
+    /// Same as `raw_with_pool`, but `dedicated` controls whether the buffer gets a memory
+    /// allocation of its own.
+    ///
+    /// # Safety
+    ///
+    /// You must ensure that the size that you pass is correct for `T`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the buffer must be bound to a dedicated allocation, because the
+    ///   implementation requires it or because `dedicated` is `DedicatedAllocHint::Always`, and
+    ///   `pool` doesn't support dedicated allocations.
+    ///
+    pub unsafe fn raw_with_pool_and_hint<'a, I>(device: &Arc<Device>, size: usize,
+                                                usage: &Usage, queue_families: I, pool: &A,
+                                                dedicated: DedicatedAllocHint)
+                                                -> Result<Arc<ImmutableBuffer<T, A>>,
+                                                          DeviceMemoryAllocError>
         where I: IntoIterator<Item = QueueFamily<'a>>
     {
         let queue_families = queue_families.into_iter().map(|f| f.id())
                                            .collect::<SmallVec<[u32; 4]>>();
+        let concurrent = queue_families.len() >= 2;
+        ImmutableBuffer::raw_impl(device, size, usage, queue_families, concurrent, pool,
+                                  dedicated)
+    }
 
+    // Implementation of `raw_with_pool_and_hint`. If `concurrent` is false, the buffer uses the
+    // exclusive sharing mode even if it can be used by multiple queue families, in which case
+    // ownership of the buffer must be transferred between the families.
+    unsafe fn raw_impl(device: &Arc<Device>, size: usize, usage: &Usage,
+                       queue_families: SmallVec<[u32; 4]>, concurrent: bool, pool: &A,
+                       dedicated: DedicatedAllocHint)
+                       -> Result<Arc<ImmutableBuffer<T, A>>, DeviceMemoryAllocError>
+    {
         let (buffer, mem_reqs) = {
-            let sharing = if queue_families.len() >= 2 {
+            let sharing = if concurrent {
                 Sharing::Concurrent(queue_families.iter().cloned())
             } else {
                 Sharing::Exclusive
@@ -113,23 +345,28 @@ impl<T: ?Sized> ImmutableBuffer<T> {
 
             match UnsafeBuffer::new(device, size, &usage, sharing, SparseLevel::none()) {
                 Ok(b) => b,
-                Err(BufferCreationError::OomError(err)) => return Err(err),
+                Err(BufferCreationError::OomError(err)) => return Err(err.into()),
                 Err(_) => unreachable!()        // We don't use sparse binding, therefore the other
                                                 // errors can't happen
             }
         };
 
-        let mem_ty = {
-            let device_local = device.physical_device().memory_types()
-                                     .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
-                                     .filter(|t| t.is_device_local());
-            let any = device.physical_device().memory_types()
-                            .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0);
-            device_local.chain(any).next().unwrap()
-        };
+        let mem_ty = MemoryUsage::DeviceLocal.choose(device.physical_device(),
+                                                     mem_reqs.memory_type_bits).unwrap();
 
-        let mem = try!(MemoryPool::alloc(&Device::standard_pool(device), mem_ty,
-                                         mem_reqs.size, mem_reqs.alignment, AllocLayout::Linear));
+        let mem = match alloc_for_resource(pool, mem_ty, &mem_reqs, AllocLayout::Linear,
+                                           dedicated, DedicatedAlloc::Buffer(&buffer))
+        {
+            Ok(mem) => mem,
+            Err(DedicatedAllocError::OomError(err)) => return Err(err.into()),
+            Err(DedicatedAllocError::TooManyObjects) => {
+                return Err(DeviceMemoryAllocError::TooManyObjects)
+            },
+            Err(DedicatedAllocError::Unsupported) => {
+                panic!("the buffer requires a dedicated allocation, but the pool doesn't \
+                        support them")
+            },
+        };
         debug_assert!((mem.offset() % mem_reqs.alignment) == 0);
         try!(buffer.bind_memory(mem.memory(), mem.offset()));
 
@@ -144,6 +381,85 @@ impl<T: ?Sized> ImmutableBuffer<T> {
     }
 }
 
+// Returns the usage of a buffer that is filled by `upload`.
+#[inline]
+fn upload_usage(usage: &Usage) -> Usage {
+    Usage {
+        transfer_dest: true,
+        .. *usage
+    }
+}
+
+// Returns the queue families of a buffer that is filled by `upload`, which must include the family
+// of the queue that performs the copy.
+fn upload_queue_families<'a, I>(queue_families: I, queue: &'a Arc<Queue>)
+                                 -> SmallVec<[QueueFamily<'a>; 4]>
+    where I: IntoIterator<Item = QueueFamily<'a>>
+{
+    let mut families: SmallVec<[QueueFamily<'a>; 4]> = SmallVec::new();
+    for family in queue_families.into_iter().chain(Some(queue.family())) {
+        if families.iter().find(|f| f.id() == family.id()).is_none() {
+            families.push(family);
+        }
+    }
+    families
+}
+
+// Returns the queue families of a buffer that is filled by `upload_with_transfer_queue`.
+#[inline]
+fn transfer_queue_families(transfer_queue: &Arc<Queue>, queue: &Arc<Queue>)
+                           -> SmallVec<[u32; 4]>
+{
+    let mut families = SmallVec::new();
+    families.push(queue.family().id());
+    families.push(transfer_queue.family().id());
+    families
+}
+
+// Copies the content of the staging buffer `source` to `destination` on `transfer_queue`, then
+// transfers the ownership of `destination` to the family of `queue`.
+//
+// Returns the submission of the acquire operation on `queue`, which keeps the submission of the
+// copy alive.
+fn upload_with_transfer_queue<T: ?Sized>(source: &Arc<CpuAccessibleBuffer<T>>,
+                                         destination: &Arc<ImmutableBuffer<T>>,
+                                         transfer_queue: &Arc<Queue>, queue: &Arc<Queue>)
+                                         -> Result<Arc<Submission>, QueueError>
+    where T: 'static + Send + Sync
+{
+    let device = queue.device();
+    let (transfer_family, family) = (transfer_queue.family(), queue.family());
+
+    let (release, acquire) = unsafe {
+        let release = PrimaryCommandBufferBuilder::new(device, transfer_family)
+                          .copy_buffer(source, destination)
+                          .transfer_buffer_ownership(destination, transfer_family, family)
+                          .build();
+        let acquire = PrimaryCommandBufferBuilder::new(device, family)
+                          .transfer_buffer_ownership(destination, transfer_family, family)
+                          .build();
+        (release, acquire)
+    };
+
+    // The acquire depends on the release through the write tracking of the buffer, and therefore
+    // waits upon it with a semaphore.
+    let release_submission = try!(submit(&release, transfer_queue));
+    let acquire_submission = try!(submit(&acquire, queue));
+    acquire_submission.keep_alive(release_submission);
+    Ok(acquire_submission)
+}
+
+// Copies the content of the staging buffer `source` to `destination` on the given queue.
+fn upload<T: ?Sized>(source: &Arc<CpuAccessibleBuffer<T>>, destination: &Arc<ImmutableBuffer<T>>,
+                     queue: &Arc<Queue>) -> Result<Arc<Submission>, QueueError>
+    where T: 'static + Send + Sync
+{
+    let cb = PrimaryCommandBufferBuilder::new(queue.device(), queue.family())
+                                         .copy_buffer(source, destination)
+                                         .build();
+    submit(&cb, queue)
+}
+
 impl<T: ?Sized, A> ImmutableBuffer<T, A> where A: MemoryPool {
     /// Returns the device used to create this buffer.
     #[inline]
@@ -242,3 +558,39 @@ unsafe impl<T: ?Sized, A> TypedBuffer for ImmutableBuffer<T, A>
 {
     type Content = T;
 }
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+    use std::time::Duration;
+
+    use buffer::Buffer;
+    use buffer::BufferUsage;
+    use buffer::ImmutableBuffer;
+    use buffer::TypedBuffer;
+
+    #[test]
+    fn from_iter() {
+        let (_, queue) = gfx_dev_and_queue!();
+
+        let usage = BufferUsage { vertex_buffer: true, .. BufferUsage::none() };
+        let (buffer, submission) = ImmutableBuffer::from_iter(0 .. 64u32, &usage, iter::empty(),
+                                                              &queue).unwrap();
+
+        assert_eq!(buffer.len(), 64);
+        assert_eq!(buffer.queue_families().len(), 1);
+        submission.wait(Duration::from_secs(5)).unwrap();
+    }
+
+    #[test]
+    fn from_data_same_family() {
+        let (_, queue) = gfx_dev_and_queue!();
+
+        let usage = BufferUsage { uniform_buffer: true, .. BufferUsage::none() };
+        let (buffer, submission) =
+            ImmutableBuffer::from_data_with_transfer_queue(12u32, &usage, &queue, &queue).unwrap();
+
+        assert_eq!(buffer.size(), 4);
+        submission.wait(Duration::from_secs(5)).unwrap();
+    }
+}
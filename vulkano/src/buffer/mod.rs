@@ -19,6 +19,8 @@
 //!
 //! - `CpuAccessBuffer` designates a buffer located in RAM and whose content can be directly
 //!   written by your application.
+//! - `CpuBufferPool` is a ring of buffers located in RAM, from which you can obtain short-lived
+//!   sub-buffers that contain your data.
 //! - `DeviceLocalBuffer` designates a buffer located in video memory and whose content can't be
 //!   written by your application. Accessing this buffer from the GPU is usually faster than the
 //!   `CpuAccessBuffer`.
@@ -27,12 +29,15 @@
 //!   CPU because we don't need to keep track of the reads and writes.
 //!
 //! If you have data that is modified at every single frame, you are encouraged to use a
-//! `CpuAccessibleBuffer`. If you have data that is very rarely modified, you are encouraged to
-//! use an `ImmutableBuffer` or a `DeviceLocalBuffer` instead.
+//! `CpuBufferPool` or a `CpuAccessibleBuffer`. If you have data that is very rarely modified, you
+//! are encouraged to use an `ImmutableBuffer` or a `DeviceLocalBuffer` instead.
 //!
 //! If you just want to get started, you can use the `CpuAccessibleBuffer` everywhere, as it is
 //! the most flexible type of buffer.
 //!
+//! In order to read the content of a buffer that isn't accessible by the CPU, for example the
+//! results of a compute shader, see the `readback` module.
+//!
 //! # Buffers usage
 //!
 //! When you create a buffer object, you have to specify its *usage*. In other words, you have to
@@ -64,6 +69,7 @@ use std::ops::Range;
 use std::sync::Arc;
 
 pub use self::cpu_access::CpuAccessibleBuffer;
+pub use self::cpu_pool::CpuBufferPool;
 pub use self::device_local::DeviceLocalBuffer;
 pub use self::immutable::ImmutableBuffer;
 pub use self::sys::BufferCreationError;
@@ -73,10 +79,13 @@ pub use self::traits::TypedBuffer;
 pub use self::view::BufferView;
 
 pub mod cpu_access;
+pub mod cpu_pool;
 pub mod device_local;
 pub mod immutable;
+pub mod readback;
 pub mod sys;
 pub mod traits;
+pub mod upload;
 pub mod view;
 
 /// A subpart of a buffer.
@@ -103,7 +112,6 @@ pub mod view;
 /// let _slice = BufferSlice::from(&buffer).slice(12 .. 14).unwrap();
 /// ```
 ///
-#[derive(Clone)]
 pub struct BufferSlice<'a, T: ?Sized, B: 'a> {
     marker: PhantomData<T>,
     resource: &'a Arc<B>,
@@ -111,6 +119,22 @@ pub struct BufferSlice<'a, T: ?Sized, B: 'a> {
     size: usize,
 }
 
+// We need to implement `Clone` manually, otherwise the derive adds a `Clone` requirement on `T`
+// and `B`.
+impl<'a, T: ?Sized, B: 'a> Clone for BufferSlice<'a, T, B> {
+    #[inline]
+    fn clone(&self) -> BufferSlice<'a, T, B> {
+        BufferSlice {
+            marker: PhantomData,
+            resource: self.resource,
+            offset: self.offset,
+            size: self.size,
+        }
+    }
+}
+
+impl<'a, T: ?Sized, B: 'a> Copy for BufferSlice<'a, T, B> {}
+
 impl<'a, T: ?Sized, B: 'a> BufferSlice<'a, T, B> {
     /// Returns the buffer that this slice belongs to.
     pub fn buffer(&self) -> &'a Arc<B> {
@@ -168,6 +192,29 @@ impl<'a, T: ?Sized, B: 'a> BufferSlice<'a, T, B> {
 }
 
 impl<'a, T, B: 'a> BufferSlice<'a, [T], B> {
+    /// Reinterprets the slice as a slice of elements of another type. The offset and the size in
+    /// bytes are kept.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the size isn't a multiple of the size of `R`.
+    ///
+    /// # Safety
+    ///
+    /// - The content of the slice must be valid for `R`.
+    ///
+    #[inline]
+    pub unsafe fn reinterpret<R>(self) -> BufferSlice<'a, [R], B> {
+        assert_eq!(self.size % mem::size_of::<R>(), 0);
+
+        BufferSlice {
+            marker: PhantomData,
+            resource: self.resource,
+            offset: self.offset,
+            size: self.size,
+        }
+    }
+
     /// Returns the number of elements in this slice.
     #[inline]
     pub fn len(&self) -> usize {
@@ -0,0 +1,159 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Reading back the content of a buffer that isn't accessible by the CPU.
+//!
+//! Buffers in device-local memory, like the results of a compute shader, can't be read directly
+//! by the CPU. A `ReadBack` copies the content of such a buffer to a host-visible buffer, and
+//! lets you poll for the completion of the copy so that the content can be read without blocking.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! use vulkano::buffer::DeviceLocalBuffer;
+//! use vulkano::buffer::readback::ReadBack;
+//!
+//! # let queue: Arc<vulkano::device::Queue> = unsafe { std::mem::uninitialized() };
+//! # let results: Arc<DeviceLocalBuffer<[u32]>> = unsafe { std::mem::uninitialized() };
+//! let readback = ReadBack::new(&results, &queue).unwrap();
+//!
+//! // Later, for example during the next frame.
+//! if let Some(content) = readback.try_read() {
+//!     println!("first result: {}", content[0]);
+//! }
+//! ```
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use buffer::cpu_access::CpuAccessibleBuffer;
+use buffer::cpu_access::ReadLock;
+use buffer::sys::Usage;
+use buffer::traits::Buffer;
+use buffer::traits::TypedBuffer;
+use command_buffer::PrimaryCommandBufferBuilder;
+use command_buffer::Submission;
+use command_buffer::submit;
+use device::Queue;
+use device::QueueError;
+use memory::Content;
+use sync::FenceWaitError;
+
+/// Copy of the content of a buffer to a host-visible buffer that is in progress.
+pub struct ReadBack<T: ?Sized> {
+    // Host-visible buffer that receives the content.
+    destination: Arc<CpuAccessibleBuffer<T>>,
+    // Submission of the copy.
+    submission: Arc<Submission>,
+}
+
+impl<T: ?Sized> ReadBack<T> where T: Content + 'static + Send + Sync {
+    /// Submits to `queue` a command buffer that copies the content of `source` to a new
+    /// host-visible buffer.
+    ///
+    /// The copy is automatically ordered after the commands that were previously submitted and
+    /// that write to `source`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `source` wasn't created with the `transfer_source` usage.
+    /// - Panics if `source` and `queue` don't belong to the same device.
+    ///
+    pub fn new<B>(source: &Arc<B>, queue: &Arc<Queue>) -> Result<ReadBack<T>, QueueError>
+        where B: TypedBuffer<Content = T> + 'static
+    {
+        let usage = Usage {
+            transfer_dest: true,
+            .. Usage::none()
+        };
+
+        let destination = unsafe {
+            try!(CpuAccessibleBuffer::raw(queue.device(), source.size(), &usage,
+                                          Some(queue.family())))
+        };
+
+        let cb = PrimaryCommandBufferBuilder::new(queue.device(), queue.family())
+                                             .copy_buffer(source, &destination)
+                                             .build();
+        let submission = try!(submit(&cb, queue));
+
+        Ok(ReadBack {
+            destination: destination,
+            submission: submission,
+        })
+    }
+
+    /// Returns true if the copy is finished, in which case reading the content doesn't block.
+    #[inline]
+    pub fn ready(&self) -> bool {
+        self.submission.finished()
+    }
+
+    /// Waits until the copy is finished or until `timeout` has elapsed.
+    #[inline]
+    pub fn wait(&self, timeout: Duration) -> Result<(), FenceWaitError> {
+        self.submission.wait(timeout)
+    }
+
+    /// Locks the host-visible buffer in order to read the content that was copied, blocking
+    /// until the copy is finished or until `timeout` has elapsed.
+    #[inline]
+    pub fn read(&self, timeout: Duration) -> Result<ReadLock<T>, FenceWaitError> {
+        self.destination.read(timeout)
+    }
+
+    /// Locks the host-visible buffer in order to read the content that was copied.
+    ///
+    /// Returns `None` without blocking if the copy is not finished yet.
+    #[inline]
+    pub fn try_read(&self) -> Option<ReadLock<T>> {
+        if !self.ready() {
+            return None;
+        }
+
+        self.destination.read(Duration::new(0, 0)).ok()
+    }
+
+    /// Returns the host-visible buffer that receives the content.
+    #[inline]
+    pub fn buffer(&self) -> &Arc<CpuAccessibleBuffer<T>> {
+        &self.destination
+    }
+
+    /// Returns the submission of the copy.
+    #[inline]
+    pub fn submission(&self) -> &Arc<Submission> {
+        &self.submission
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+    use std::time::Duration;
+
+    use buffer::BufferUsage;
+    use buffer::ImmutableBuffer;
+    use buffer::readback::ReadBack;
+
+    #[test]
+    fn read_back() {
+        let (_, queue) = gfx_dev_and_queue!();
+
+        let usage = BufferUsage { transfer_source: true, .. BufferUsage::none() };
+        let (source, _) = ImmutableBuffer::from_iter(0 .. 16u32, &usage, iter::empty(),
+                                                     &queue).unwrap();
+
+        let readback = ReadBack::new(&source, &queue).unwrap();
+        let content = readback.read(Duration::from_secs(5)).unwrap();
+        assert!(readback.ready());
+        assert_eq!(content[5], 5);
+    }
+}
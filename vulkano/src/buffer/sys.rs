@@ -32,13 +32,17 @@ use std::sync::Arc;
 use smallvec::SmallVec;
 
 use device::Device;
+use instance::debug::ObjectType;
+use instrumentation::ResourceKind;
 use memory::DeviceMemory;
+use memory::ExternalMemoryHandleType;
 use memory::MemoryRequirements;
 use sync::Sharing;
 
 use check_errors;
 use Error;
 use OomError;
+use VulkanError;
 use VulkanObject;
 use VulkanPointers;
 use vk;
@@ -50,6 +54,10 @@ pub struct UnsafeBuffer {
     device: Arc<Device>,
     size: usize,
     usage: vk::BufferUsageFlags,
+    sparse: SparseLevel,
+    memory_requirements: MemoryRequirements,
+    // Queue families the buffer is shared between. Empty if the sharing mode is exclusive.
+    concurrent_queue_families: SmallVec<[u32; 4]>,
 }
 
 impl UnsafeBuffer {
@@ -62,13 +70,54 @@ impl UnsafeBuffer {
     /// Panics if `sparse.sparse` is false and `sparse.sparse_residency` or
     /// `sparse.sparse_aliased` is true.
     ///
+    #[inline]
     pub unsafe fn new<'a, I>(device: &Arc<Device>, size: usize, usage: &Usage, sharing: Sharing<I>,
                              sparse: SparseLevel)
                              -> Result<(UnsafeBuffer, MemoryRequirements), BufferCreationError>
         where I: Iterator<Item = u32>
+    {
+        UnsafeBuffer::new_impl(device, size, usage, sharing, sparse, None)
+    }
+
+    /// Same as `new`, but the buffer can be bound to memory that is shared with another API or
+    /// another process through a handle of the given type.
+    ///
+    /// See `DeviceMemory::alloc_exportable`, `DeviceMemory::import_fd` and
+    /// `DeviceMemory::import_win32_handle`.
+    ///
+    /// # Panic
+    ///
+    /// Same as `new`.
+    ///
+    #[inline]
+    pub unsafe fn new_external<'a, I>(device: &Arc<Device>, size: usize, usage: &Usage,
+                                      sharing: Sharing<I>, sparse: SparseLevel,
+                                      handle_type: ExternalMemoryHandleType)
+                                      -> Result<(UnsafeBuffer, MemoryRequirements),
+                                                BufferCreationError>
+        where I: Iterator<Item = u32>
+    {
+        UnsafeBuffer::new_impl(device, size, usage, sharing, sparse, Some(handle_type))
+    }
+
+    unsafe fn new_impl<'a, I>(device: &Arc<Device>, size: usize, usage: &Usage,
+                              sharing: Sharing<I>, sparse: SparseLevel,
+                              external: Option<ExternalMemoryHandleType>)
+                              -> Result<(UnsafeBuffer, MemoryRequirements), BufferCreationError>
+        where I: Iterator<Item = u32>
     {
         let vk = device.pointers();
 
+        if let Some(handle_type) = external {
+            if !handle_type.is_supported_by(device.loaded_extensions()) {
+                return Err(BufferCreationError::ExternalMemoryExtensionNotEnabled);
+            }
+        }
+
+        if usage.conditional_rendering && !device.loaded_extensions().ext_conditional_rendering {
+            return Err(BufferCreationError::ConditionalRenderingExtensionNotEnabled);
+        }
+
         let usage_bits = usage.to_usage_bits();
 
         // Checking sparse features.
@@ -86,15 +135,26 @@ impl UnsafeBuffer {
             return Err(BufferCreationError::SparseResidencyAliasedFeatureNotEnabled);
         }
 
+        let (sh_mode, sh_indices) = match sharing {
+            Sharing::Exclusive => (vk::SHARING_MODE_EXCLUSIVE, SmallVec::<[u32; 4]>::new()),
+            Sharing::Concurrent(ids) => (vk::SHARING_MODE_CONCURRENT, ids.collect()),
+        };
+
         let buffer = {
-            let (sh_mode, sh_indices) = match sharing {
-                Sharing::Exclusive => (vk::SHARING_MODE_EXCLUSIVE, SmallVec::<[u32; 8]>::new()),
-                Sharing::Concurrent(ids) => (vk::SHARING_MODE_CONCURRENT, ids.collect()),
-            };
+
+            let external_infos = external.map(|ty| {
+                vk::ExternalMemoryBufferCreateInfoKHR {
+                    sType: vk::STRUCTURE_TYPE_EXTERNAL_MEMORY_BUFFER_CREATE_INFO_KHR,
+                    pNext: ptr::null(),
+                    handleTypes: ty.to_bits(),
+                }
+            });
 
             let infos = vk::BufferCreateInfo {
                 sType: vk::STRUCTURE_TYPE_BUFFER_CREATE_INFO,
-                pNext: ptr::null(),
+                pNext: external_infos.as_ref()
+                                     .map(|i| i as *const vk::ExternalMemoryBufferCreateInfoKHR)
+                                     .unwrap_or(ptr::null()) as *const _,
                 flags: sparse.to_flags(),
                 size: size as u64,
                 usage: usage_bits,
@@ -112,12 +172,9 @@ impl UnsafeBuffer {
         let mem_reqs = {
             #[inline] fn align(val: usize, al: usize) -> usize { al * (1 + (val - 1) / al) }
 
-            let mut output: vk::MemoryRequirements = mem::uninitialized();
-            vk.GetBufferMemoryRequirements(device.internal_object(), buffer, &mut output);
-            debug_assert!(output.size >= size as u64);
-            debug_assert!(output.memoryTypeBits != 0);
-
-            let mut output: MemoryRequirements = output.into();
+            let mut output = buffer_memory_requirements(device, buffer);
+            debug_assert!(output.size >= size);
+            debug_assert!(output.memory_type_bits != 0);
 
             // We have to manually enforce some additional requirements for some buffer types.
             let limits = device.physical_device().limits();
@@ -144,8 +201,12 @@ impl UnsafeBuffer {
             device: device.clone(),
             size: size as usize,
             usage: usage_bits,
+            sparse: sparse,
+            memory_requirements: mem_reqs,
+            concurrent_queue_families: sh_indices,
         };
 
+        device.resource_tracker().created(ResourceKind::Buffer, buffer);
         Ok((obj, mem_reqs))
     }
 
@@ -189,12 +250,46 @@ impl UnsafeBuffer {
         &self.device
     }
 
+    /// Gives a name to the buffer, that debugging tools and validation layers can display.
+    ///
+    /// Does nothing if the `VK_EXT_debug_marker` extension isn't enabled on the device.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `name` contains a nul character.
+    ///
+    #[inline]
+    pub fn set_object_name(&self, name: &str) -> Result<(), OomError> {
+        unsafe {
+            self.device.set_object_name_raw(ObjectType::Buffer, self.buffer as u64, name)
+        }
+    }
+
     /// Returns the size of the buffer in bytes.
     #[inline]
     pub fn size(&self) -> usize {
         self.size
     }
 
+    /// Returns the sparse level the buffer was created with.
+    #[inline]
+    pub fn sparse_level(&self) -> SparseLevel {
+        self.sparse
+    }
+
+    /// Returns the memory requirements of the buffer, as returned by `new`.
+    #[inline]
+    pub fn memory_requirements(&self) -> MemoryRequirements {
+        self.memory_requirements
+    }
+
+    /// Returns the queue families the buffer was created to be shared between, or an empty
+    /// slice if it was created with an exclusive sharing mode.
+    #[inline]
+    pub fn concurrent_queue_families(&self) -> &[u32] {
+        &self.concurrent_queue_families
+    }
+
     #[inline]
     pub fn usage_transfer_src(&self) -> bool {
         (self.usage & vk::BUFFER_USAGE_TRANSFER_SRC_BIT) != 0
@@ -239,6 +334,11 @@ impl UnsafeBuffer {
     pub fn usage_indirect_buffer(&self) -> bool {
         (self.usage & vk::BUFFER_USAGE_INDIRECT_BUFFER_BIT) != 0
     }
+
+    #[inline]
+    pub fn usage_conditional_rendering(&self) -> bool {
+        (self.usage & vk::BUFFER_USAGE_CONDITIONAL_RENDERING_BIT_EXT) != 0
+    }
 }
 
 unsafe impl VulkanObject for UnsafeBuffer {
@@ -250,12 +350,52 @@ unsafe impl VulkanObject for UnsafeBuffer {
     }
 }
 
+// Queries the memory requirements of a buffer. If the `khr_dedicated_allocation` extension is
+// enabled, also queries whether the buffer should be bound to a dedicated allocation.
+unsafe fn buffer_memory_requirements(device: &Device, buffer: vk::Buffer) -> MemoryRequirements {
+    let vk = device.pointers();
+
+    let extensions = device.loaded_extensions();
+    if !extensions.khr_get_memory_requirements2 || !extensions.khr_dedicated_allocation {
+        let mut output: vk::MemoryRequirements = mem::uninitialized();
+        vk.GetBufferMemoryRequirements(device.internal_object(), buffer, &mut output);
+        return output.into();
+    }
+
+    let infos = vk::BufferMemoryRequirementsInfo2KHR {
+        sType: vk::STRUCTURE_TYPE_BUFFER_MEMORY_REQUIREMENTS_INFO_2_KHR,
+        pNext: ptr::null(),
+        buffer: buffer,
+    };
+
+    let mut dedicated = vk::MemoryDedicatedRequirementsKHR {
+        sType: vk::STRUCTURE_TYPE_MEMORY_DEDICATED_REQUIREMENTS_KHR,
+        pNext: ptr::null_mut(),
+        prefersDedicatedAllocation: vk::FALSE,
+        requiresDedicatedAllocation: vk::FALSE,
+    };
+
+    let mut output = vk::MemoryRequirements2KHR {
+        sType: vk::STRUCTURE_TYPE_MEMORY_REQUIREMENTS_2_KHR,
+        pNext: &mut dedicated as *mut vk::MemoryDedicatedRequirementsKHR as *mut _,
+        memoryRequirements: mem::uninitialized(),
+    };
+
+    vk.GetBufferMemoryRequirements2KHR(device.internal_object(), &infos, &mut output);
+
+    let mut reqs: MemoryRequirements = output.memoryRequirements.into();
+    reqs.prefer_dedicated = dedicated.prefersDedicatedAllocation != vk::FALSE;
+    reqs.require_dedicated = dedicated.requiresDedicatedAllocation != vk::FALSE;
+    reqs
+}
+
 impl Drop for UnsafeBuffer {
     #[inline]
     fn drop(&mut self) {
         unsafe {
             let vk = self.device.pointers();
             vk.DestroyBuffer(self.device.internal_object(), self.buffer, ptr::null());
+            self.device.resource_tracker().destroyed(ResourceKind::Buffer, self.buffer);
         }
     }
 }
@@ -285,6 +425,16 @@ impl SparseLevel {
         if self.sparse_aliased { result |= vk::BUFFER_CREATE_SPARSE_ALIASED_BIT; }
         result
     }
+
+    #[inline]
+    #[doc(hidden)]
+    pub fn to_image_flags(&self) -> vk::ImageCreateFlagBits {       // TODO: hacky
+        let mut result = 0;
+        if self.sparse { result |= vk::IMAGE_CREATE_SPARSE_BINDING_BIT; }
+        if self.sparse_residency { result |= vk::IMAGE_CREATE_SPARSE_RESIDENCY_BIT; }
+        if self.sparse_aliased { result |= vk::IMAGE_CREATE_SPARSE_ALIASED_BIT; }
+        result
+    }
 }
 
 /// Describes how a buffer is going to be used. This is **not** an optimization.
@@ -304,6 +454,9 @@ pub struct Usage {
     pub index_buffer: bool,
     pub vertex_buffer: bool,
     pub indirect_buffer: bool,
+    /// The buffer can contain the predicate of conditional rendering. Requires the
+    /// `ext_conditional_rendering` extension.
+    pub conditional_rendering: bool,
 }
 
 impl Usage {
@@ -320,10 +473,12 @@ impl Usage {
             index_buffer: false,
             vertex_buffer: false,
             indirect_buffer: false,
+            conditional_rendering: false,
         }
     }
 
-    /// Builds a `Usage` with all values set to true. Can be used for quick prototyping.
+    /// Builds a `Usage` with all values set to true, except `conditional_rendering` which
+    /// requires an extension. Can be used for quick prototyping.
     #[inline]
     pub fn all() -> Usage {
         Usage {
@@ -336,6 +491,7 @@ impl Usage {
             index_buffer: true,
             vertex_buffer: true,
             indirect_buffer: true,
+            conditional_rendering: false,
         }
     }
 
@@ -447,6 +603,9 @@ impl Usage {
         if self.index_buffer { result |= vk::BUFFER_USAGE_INDEX_BUFFER_BIT; }
         if self.vertex_buffer { result |= vk::BUFFER_USAGE_VERTEX_BUFFER_BIT; }
         if self.indirect_buffer { result |= vk::BUFFER_USAGE_INDIRECT_BUFFER_BIT; }
+        if self.conditional_rendering {
+            result |= vk::BUFFER_USAGE_CONDITIONAL_RENDERING_BIT_EXT;
+        }
         result
     }
 }
@@ -462,6 +621,12 @@ pub enum BufferCreationError {
     SparseResidencyBufferFeatureNotEnabled,
     /// Sparse aliasing was requested but the corresponding feature wasn't enabled.
     SparseResidencyAliasedFeatureNotEnabled,
+    /// External memory was requested but the extensions required by the handle type weren't
+    /// enabled.
+    ExternalMemoryExtensionNotEnabled,
+    /// The `conditional_rendering` usage was requested but the `ext_conditional_rendering`
+    /// extension wasn't enabled.
+    ConditionalRenderingExtensionNotEnabled,
 }
 
 impl error::Error for BufferCreationError {
@@ -478,6 +643,13 @@ impl error::Error for BufferCreationError {
             BufferCreationError::SparseResidencyAliasedFeatureNotEnabled => {
                 "sparse aliasing was requested but the corresponding feature wasn't enabled"
             },
+            BufferCreationError::ExternalMemoryExtensionNotEnabled => {
+                "external memory was requested but the corresponding extensions weren't enabled"
+            },
+            BufferCreationError::ConditionalRenderingExtensionNotEnabled => {
+                "the conditional rendering usage was requested but the corresponding extension \
+                 wasn't enabled"
+            },
         }
     }
 
@@ -497,6 +669,16 @@ impl fmt::Display for BufferCreationError {
     }
 }
 
+impl VulkanError for BufferCreationError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            BufferCreationError::OomError(ref err) => err.error_code(),
+            _ => None
+        }
+    }
+}
+
 impl From<OomError> for BufferCreationError {
     #[inline]
     fn from(err: OomError) -> BufferCreationError {
@@ -540,6 +722,20 @@ mod tests {
         assert_eq!(&**buf.device() as *const Device, &*device as *const Device);
     }
 
+    #[test]
+    fn set_object_name() {
+        let (device, _) = gfx_dev_and_queue!();
+        let (buf, _) = unsafe {
+            UnsafeBuffer::new(&device, 128, &Usage::all(), Sharing::Exclusive::<Empty<_>>,
+                              SparseLevel::none())
+        }.unwrap();
+
+        // Without `VK_EXT_debug_marker` this is a no-op that always succeeds.
+        assert!(!device.loaded_extensions().ext_debug_marker);
+        buf.set_object_name("vertex buffer").unwrap();
+        device.set_object_name("main device").unwrap();
+    }
+
     #[test]
     #[should_panic = "Can't enable sparse residency without enabling sparse binding as well"]
     fn panic_wrong_sparse_residency() {
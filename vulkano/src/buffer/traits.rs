@@ -50,7 +50,11 @@ pub unsafe trait Buffer: 'static + Send + Sync {
     /// Returns the range of bytes of the buffer slice used by a block.
     fn block_memory_range(&self, block: usize) -> Range<usize>;
 
+    /// Called when a command buffer that uses this buffer is being submitted. Returns the
+    /// submissions that must finish before the command buffer executes.
     ///
+    /// Buffers don't have a layout, therefore the only state that needs to be tracked across
+    /// submissions is the list of the latest submissions that read or write each block.
     ///
     /// If the host is still accessing the buffer, this function implementation should block
     /// until it is no longer the case.
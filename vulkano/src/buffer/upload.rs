@@ -0,0 +1,384 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Uploading the content of many immutable buffers and images at once.
+//!
+//! `ImmutableBuffer::from_iter` and `ImmutableImage::from_iter` allocate a staging buffer and
+//! submit a command buffer for each resource, which is slow when loading hundreds of small
+//! meshes and textures. An `UploadBatcher` instead writes the content of all the resources to
+//! the same staging buffer, and copies them with a single command buffer. Since the copies
+//! don't depend on each other, all the pipeline barriers of the command buffer are grouped
+//! together.
+//!
+//! The resources are returned immediately, but their content is only uploaded when `submit` is
+//! called. Command buffers that use the resources automatically wait for the copy to be
+//! finished, but they must not be submitted before the batch.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let mut batcher = UploadBatcher::new(&queue);
+//!
+//! let meshes = models.iter().map(|model| {
+//!     batcher.buffer_from_iter(model.vertices.iter().cloned(), &BufferUsage::vertex_buffer(),
+//!                              Some(queue.family())).unwrap()
+//! }).collect::<Vec<_>>();
+//!
+//! let textures = models.iter().map(|model| {
+//!     batcher.image_from_slice(&model.texels, model.dimensions, Format::R8G8B8A8Srgb).unwrap()
+//! }).collect::<Vec<_>>();
+//!
+//! batcher.submit().unwrap();
+//! ```
+
+use std::mem;
+use std::slice;
+use std::sync::Arc;
+use std::time::Duration;
+
+use buffer::BufferSlice;
+use buffer::cpu_access::CpuAccessibleBuffer;
+use buffer::immutable::ImmutableBuffer;
+use buffer::sys::Usage;
+use command_buffer::PrimaryCommandBufferBuilder;
+use command_buffer::Submission;
+use command_buffer::submit;
+use device::Device;
+use device::Queue;
+use device::QueueError;
+use format::FormatDesc;
+use format::FormatTy;
+use image::Dimensions;
+use image::ImageCreationError;
+use image::immutable::ImmutableImage;
+use instance::QueueFamily;
+use memory::Content;
+use memory::DeviceMemoryAllocError;
+
+// Alignment in bytes of the data of each resource within the staging buffer.
+const MIN_ALIGNMENT: usize = 16;
+
+// Records the copy of a region of the staging buffer to a resource.
+type RecordCopy = Box<Fn(PrimaryCommandBufferBuilder, &Arc<CpuAccessibleBuffer<[u8]>>)
+                   -> PrimaryCommandBufferBuilder + Send + Sync>;
+
+/// Accumulates the content of immutable buffers and images, and uploads all of them with a
+/// single staging buffer and a single command buffer.
+///
+/// See [the documentation of the module](index.html) for more info.
+pub struct UploadBatcher {
+    queue: Arc<Queue>,
+    // Content of the staging buffer.
+    data: Vec<u8>,
+    // Copies of each resource, in the order in which they have been added.
+    copies: Vec<RecordCopy>,
+}
+
+impl UploadBatcher {
+    /// Builds a new batcher whose uploads are submitted to `queue`.
+    #[inline]
+    pub fn new(queue: &Arc<Queue>) -> UploadBatcher {
+        UploadBatcher {
+            queue: queue.clone(),
+            data: Vec::new(),
+            copies: Vec::new(),
+        }
+    }
+
+    /// Returns the device of the resources.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        self.queue.device()
+    }
+
+    /// Returns the queue the uploads are submitted to.
+    #[inline]
+    pub fn queue(&self) -> &Arc<Queue> {
+        &self.queue
+    }
+
+    /// Returns the number of resources whose content hasn't been uploaded yet.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.copies.len()
+    }
+
+    /// Returns true if there is nothing to upload.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.copies.is_empty()
+    }
+
+    /// Returns the size in bytes of the staging buffer that the next call to `submit` allocates.
+    #[inline]
+    pub fn staging_size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Builds a new buffer whose content is `data`, and adds the upload of this content to the
+    /// batch.
+    ///
+    /// The `transfer_dest` usage is added to `usage`, and the family of the queue of the batcher
+    /// is added to `queue_families`.
+    pub fn buffer_from_data<'a, T, I>(&mut self, data: T, usage: &Usage, queue_families: I)
+                                      -> Result<Arc<ImmutableBuffer<T>>, DeviceMemoryAllocError>
+        where T: Content + 'static + Send + Sync, I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        let buffer = {
+            let families = upload_queue_families(queue_families, &self.queue);
+            let physical = self.queue.device().physical_device();
+            let families = families.into_iter().map(|id| physical.queue_family_by_id(id).unwrap());
+            unsafe {
+                try!(ImmutableBuffer::raw(self.queue.device(), mem::size_of::<T>(),
+                                          &upload_usage(usage), families))
+            }
+        };
+
+        let offset = self.push(Some(data), mem::align_of::<T>());
+        let size = mem::size_of::<T>();
+        let destination = buffer.clone();
+        self.copies.push(Box::new(move |cb, staging| {
+            let source = BufferSlice::from(staging).slice(offset .. offset + size).unwrap();
+            let source = unsafe { source.reinterpret::<T>() }.index(0).unwrap();
+            cb.copy_buffer_slice(source, &destination)
+        }));
+
+        Ok(buffer)
+    }
+
+    /// Builds a new buffer whose content is produced by an iterator, and adds the upload of this
+    /// content to the batch.
+    ///
+    /// Same as `buffer_from_data`, but for arrays.
+    pub fn buffer_from_iter<'a, T, D, I>(&mut self, data: D, usage: &Usage, queue_families: I)
+                                         -> Result<Arc<ImmutableBuffer<[T]>>,
+                                                   DeviceMemoryAllocError>
+        where D: ExactSizeIterator<Item = T>, I: IntoIterator<Item = QueueFamily<'a>>,
+              T: Content + 'static + Send + Sync
+    {
+        let len = data.len();
+        let buffer = {
+            let families = upload_queue_families(queue_families, &self.queue);
+            let physical = self.queue.device().physical_device();
+            let families = families.into_iter().map(|id| physical.queue_family_by_id(id).unwrap());
+            try!(ImmutableBuffer::array(self.queue.device(), len, &upload_usage(usage), families))
+        };
+
+        let offset = self.push(data, mem::align_of::<T>());
+        let size = len * mem::size_of::<T>();
+        let destination = buffer.clone();
+        self.copies.push(Box::new(move |cb, staging| {
+            let source = BufferSlice::from(staging).slice(offset .. offset + size).unwrap();
+            cb.copy_buffer_slice(unsafe { source.reinterpret::<T>() }, &destination)
+        }));
+
+        Ok(buffer)
+    }
+
+    /// Builds a new immutable image whose content is produced by an iterator, and adds the
+    /// upload of this content to the batch.
+    ///
+    /// The iterator must produce the texels of each array layer one after the other, row by row,
+    /// or the blocks of each layer if the format is compressed. Only the first mipmap level is
+    /// filled.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the iterator doesn't produce enough data for the whole image.
+    /// - Panics if the format is a combined depth-stencil format. The depth and stencil aspects
+    ///   of such images are laid out separately in buffers and can't be uploaded in one copy.
+    ///
+    pub fn image_from_iter<F, P, I>(&mut self, data: I, dimensions: Dimensions, format: F)
+                                    -> Result<Arc<ImmutableImage<F>>, ImageCreationError>
+        where F: FormatDesc + 'static + Send + Sync, I: ExactSizeIterator<Item = P>,
+              P: Content + 'static + Send + Sync
+    {
+        let raw_format = format.format();
+        assert!(raw_format.ty() != FormatTy::DepthStencil,
+                "Images with the combined depth-stencil format {:?} can't be uploaded", raw_format);
+
+        let image = try!(ImmutableImage::new(self.queue.device(), dimensions, format,
+                                             Some(self.queue.family())));
+
+        let layers = 0 .. dimensions.array_layers_with_cube();
+        let extent = dimensions.to_image_dimensions().mipmap_extent(0);
+        let size = data.len() * mem::size_of::<P>();
+        if let Some(required) = raw_format.packed_size(extent) {
+            assert!(size >= required * (layers.end - layers.start) as usize,
+                    "Not enough data for the whole image");
+        }
+
+        // The offset of a copy to an image must be a multiple of the size of a texel or block.
+        let alignment = match raw_format.block_size() {
+            Some(block_size) => lcm(mem::align_of::<P>(), block_size),
+            None => mem::align_of::<P>(),
+        };
+
+        let offset = self.push(data, alignment);
+        let destination = image.clone();
+        self.copies.push(Box::new(move |cb, staging| {
+            let source = BufferSlice::from(staging).slice(offset .. offset + size).unwrap();
+            cb.copy_buffer_to_color_image(source, &destination, 0, layers.clone(), [0, 0, 0],
+                                          extent)
+        }));
+
+        Ok(image)
+    }
+
+    /// Builds a new immutable image whose content is a slice of texels or blocks, and adds the
+    /// upload of this content to the batch.
+    ///
+    /// Same as `image_from_iter`, but for data that is already in memory.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `data` isn't large enough for the whole image.
+    /// - Panics if the format is a combined depth-stencil format.
+    ///
+    #[inline]
+    pub fn image_from_slice<F, P>(&mut self, data: &[P], dimensions: Dimensions, format: F)
+                                  -> Result<Arc<ImmutableImage<F>>, ImageCreationError>
+        where F: FormatDesc + 'static + Send + Sync, P: Content + Copy + 'static + Send + Sync
+    {
+        self.image_from_iter(data.iter().cloned(), dimensions, format)
+    }
+
+    /// Uploads the content of all the resources that have been added since the last call.
+    ///
+    /// Allocates one staging buffer that holds the content of all the resources, and submits to
+    /// the queue of the batcher one command buffer that copies it to the resources. Returns
+    /// `None` if there is nothing to upload.
+    ///
+    /// The returned submission keeps the staging buffer alive. The batcher can be reused
+    /// afterwards. If an error is returned, nothing is removed from the batch and `submit` can
+    /// be called again.
+    pub fn submit(&mut self) -> Result<Option<Arc<Submission>>, QueueError> {
+        if self.copies.is_empty() {
+            return Ok(None);
+        }
+
+        let device = self.queue.device().clone();
+        let staging = unsafe {
+            try!(CpuAccessibleBuffer::uninitialized_array(&device, self.data.len(),
+                                                          &Usage::transfer_source(),
+                                                          Some(self.queue.family())))
+        };
+
+        {
+            // The buffer has just been created, therefore it's not in use by the GPU.
+            let mut mapping = staging.write(Duration::new(0, 0)).unwrap();
+            mapping.copy_from_slice(&self.data);
+        }
+
+        let mut cb = PrimaryCommandBufferBuilder::new(&device, self.queue.family());
+        for copy in self.copies.iter() {
+            cb = copy(cb, &staging);
+        }
+
+        let submission = try!(submit(&cb.build(), &self.queue));
+        self.copies.clear();
+        self.data.clear();
+        Ok(Some(submission))
+    }
+
+    // Appends the elements produced by `data` to the staging data, starting at an offset that
+    // is a multiple of both `alignment` and `MIN_ALIGNMENT`. Returns that offset.
+    fn push<T, I>(&mut self, data: I, alignment: usize) -> usize
+        where I: IntoIterator<Item = T>
+    {
+        let alignment = lcm(alignment, MIN_ALIGNMENT);
+        let offset = (self.data.len() + alignment - 1) / alignment * alignment;
+        self.data.resize(offset, 0);
+
+        for element in data {
+            unsafe {
+                let bytes = slice::from_raw_parts(&element as *const T as *const u8,
+                                                  mem::size_of::<T>());
+                self.data.extend_from_slice(bytes);
+            }
+
+            // The element now belongs to the staging data.
+            mem::forget(element);
+        }
+
+        offset
+    }
+}
+
+// Returns the usage of a buffer that is filled by the batcher.
+#[inline]
+fn upload_usage(usage: &Usage) -> Usage {
+    Usage {
+        transfer_dest: true,
+        .. *usage
+    }
+}
+
+// Returns the ids of the queue families of a buffer that is filled by the batcher, which must
+// include the family of the queue that performs the copy.
+fn upload_queue_families<'a, I>(queue_families: I, queue: &Arc<Queue>) -> Vec<u32>
+    where I: IntoIterator<Item = QueueFamily<'a>>
+{
+    let mut families = Vec::new();
+    for id in queue_families.into_iter().map(|f| f.id()).chain(Some(queue.family().id())) {
+        if !families.contains(&id) {
+            families.push(id);
+        }
+    }
+    families
+}
+
+// Returns the least common multiple of two non-zero numbers.
+fn lcm(a: usize, b: usize) -> usize {
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        let r = x % y;
+        x = y;
+        y = r;
+    }
+    a / x * b
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use buffer::BufferUsage;
+    use buffer::upload::UploadBatcher;
+    use buffer::upload::lcm;
+    use format::Format;
+    use image::Dimensions;
+
+    #[test]
+    fn lcm_of_alignments() {
+        assert_eq!(lcm(16, 4), 16);
+        assert_eq!(lcm(16, 12), 48);
+        assert_eq!(lcm(1, 16), 16);
+    }
+
+    #[test]
+    fn batch_buffers_and_images() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let mut batcher = UploadBatcher::new(&queue);
+        assert!(batcher.submit().unwrap().is_none());
+
+        let _a = batcher.buffer_from_data(12u32, &BufferUsage::all(), None).unwrap();
+        let _b = batcher.buffer_from_iter(0 .. 3u8, &BufferUsage::all(), None).unwrap();
+        let _c = batcher.image_from_slice(&[0u8; 4 * 4 * 4], Dimensions::Dim2d { width: 4,
+                                                                                height: 4 },
+                                          Format::R8G8B8A8Unorm).unwrap();
+        assert_eq!(batcher.len(), 3);
+        assert_eq!(batcher.staging_size(), 32 + 64);
+
+        let submission = batcher.submit().unwrap().unwrap();
+        submission.wait(Duration::from_secs(5)).unwrap();
+        assert!(batcher.is_empty());
+        assert_eq!(batcher.staging_size(), 0);
+        let _ = device;
+    }
+}
@@ -45,11 +45,13 @@ use std::sync::Arc;
 
 use buffer::Buffer;
 use buffer::BufferSlice;
+use format::Format;
 use format::FormatDesc;
 use format::StrongStorage;
 
 use Error;
 use OomError;
+use VulkanError;
 use VulkanObject;
 use VulkanPointers;
 use check_errors;
@@ -60,12 +62,19 @@ use vk;
 pub struct BufferView<F, B> where B: Buffer {
     view: vk::BufferView,
     buffer: Arc<B>,
+    format: Format,
+    offset: usize,
+    size: usize,
     marker: PhantomData<F>,
     atomic_accesses: bool,
 }
 
 impl<F, B> BufferView<F, B> where B: Buffer {
     /// Builds a new buffer view.
+    ///
+    /// The buffer must have been created with the `uniform_texel_buffer` or the
+    /// `storage_texel_buffer` usage, and the format must support the corresponding usages. The
+    /// offset of the slice must be a multiple of the `min_texel_buffer_offset_alignment` limit.
     #[inline]
     pub fn new<'a, S>(buffer: S, format: F)
                       -> Result<Arc<BufferView<F, B>>, BufferViewCreationError>
@@ -77,6 +86,9 @@ impl<F, B> BufferView<F, B> where B: Buffer {
     }
 
     /// Builds a new buffer view without checking that the format is correct.
+    ///
+    /// The usage, the alignment and the format support are still checked. In addition, the size
+    /// of the slice must be a multiple of the size of a texel of the format.
     pub unsafe fn unchecked<'a, S, T: ?Sized>(buffer: S, format: F)
                                               -> Result<Arc<BufferView<F, B>>,
                                                         BufferViewCreationError>
@@ -86,7 +98,12 @@ impl<F, B> BufferView<F, B> where B: Buffer {
         let device = buffer.resource.inner().device();
         let format = format.format();
 
-        // TODO: check minTexelBufferOffsetAlignment
+        {
+            let align = device.physical_device().limits().min_texel_buffer_offset_alignment();
+            if buffer.offset() as u64 % align != 0 {
+                return Err(BufferViewCreationError::WrongBufferAlignment);
+            }
+        }
 
         if !buffer.buffer().inner().usage_uniform_texel_buffer() &&
            !buffer.buffer().inner().usage_storage_texel_buffer()
@@ -102,8 +119,17 @@ impl<F, B> BufferView<F, B> where B: Buffer {
             output.bufferFeatures
         };
 
+        let texel_size = match format.size() {
+            Some(s) => s,
+            None => return Err(BufferViewCreationError::UnsupportedFormat),
+        };
+
+        if buffer.size() % texel_size != 0 {
+            return Err(BufferViewCreationError::WrongBufferSize);
+        }
+
         {
-            let nb = buffer.size() / format.size().expect("Can't use a compressed format for buffer views");
+            let nb = buffer.size() / texel_size;
             let l = buffer.buffer().inner().device().physical_device().limits().max_texel_buffer_elements();
             if nb > l as usize {
                 return Err(BufferViewCreationError::MaxTexelBufferElementsExceeded);
@@ -143,6 +169,9 @@ impl<F, B> BufferView<F, B> where B: Buffer {
         Ok(Arc::new(BufferView {
             view: view,
             buffer: buffer.resource.clone(),
+            format: format,
+            offset: buffer.offset(),
+            size: buffer.size(),
             marker: PhantomData,
             atomic_accesses: (format_props &
                               vk::FORMAT_FEATURE_STORAGE_TEXEL_BUFFER_ATOMIC_BIT) != 0,
@@ -155,6 +184,24 @@ impl<F, B> BufferView<F, B> where B: Buffer {
         &self.buffer
     }
 
+    /// Returns the format of the texels of this view.
+    #[inline]
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Returns the offset in bytes of the view within the buffer.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the size in bytes of the view.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
     /// Returns true if the buffer view can be used as a uniform texel buffer.
     #[inline]
     pub fn uniform_texel_buffer(&self) -> bool {
@@ -204,6 +251,13 @@ pub enum BufferViewCreationError {
     /// `uniform_texel_buffer` usages.
     WrongBufferUsage,
 
+    /// The offset of the slice within the buffer is not a multiple of the
+    /// `min_texel_buffer_offset_alignment` limit.
+    WrongBufferAlignment,
+
+    /// The size of the slice is not a multiple of the size of a texel of the format.
+    WrongBufferSize,
+
     /// The requested format is not supported for this usage.
     UnsupportedFormat,
 
@@ -218,6 +272,10 @@ impl error::Error for BufferViewCreationError {
             BufferViewCreationError::OomError(_) => "out of memory when creating buffer view",
             BufferViewCreationError::WrongBufferUsage => "the buffer is missing correct usage \
                                                           flags",
+            BufferViewCreationError::WrongBufferAlignment => "the offset of the view is not \
+                                                              properly aligned",
+            BufferViewCreationError::WrongBufferSize => "the size of the view is not a multiple \
+                                                         of the size of a texel",
             BufferViewCreationError::UnsupportedFormat => "the requested format is not supported \
                                                            for this usage",
             BufferViewCreationError::MaxTexelBufferElementsExceeded => {
@@ -242,6 +300,16 @@ impl fmt::Display for BufferViewCreationError {
     }
 }
 
+impl VulkanError for BufferViewCreationError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            BufferViewCreationError::OomError(ref err) => err.error_code(),
+            _ => None
+        }
+    }
+}
+
 impl From<OomError> for BufferViewCreationError {
     #[inline]
     fn from(err: OomError) -> BufferViewCreationError {
@@ -349,4 +417,22 @@ mod tests {
             _ => panic!()
         }
     }
+
+    #[test]
+    fn wrong_size() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let usage = Usage {
+            uniform_texel_buffer: true,
+            .. Usage::none()
+        };
+
+        let buffer = ImmutableBuffer::<[u8]>::array(&device, 130, &usage,
+                                                    Some(queue.family())).unwrap();
+
+        match unsafe { BufferView::unchecked(&buffer, format::R8G8B8A8Unorm) } {
+            Err(BufferViewCreationError::WrongBufferSize) => (),
+            _ => panic!()
+        }
+    }
 }
@@ -0,0 +1,628 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+use buffer::Buffer;
+use buffer::BufferSlice;
+use buffer::TypedBuffer;
+use command_buffer::DrawParams;
+use command_buffer::DynamicState;
+use command_buffer::PrimaryCommandBuffer;
+use command_buffer::PrimaryCommandBufferBuilder;
+use command_buffer::PrimaryCommandBufferBuilderInlineDraw;
+use command_buffer::pool::CommandPool;
+use command_buffer::pool::StandardCommandPool;
+use descriptor::descriptor_set::DescriptorSetsCollection;
+use descriptor::PipelineLayout;
+use device::Device;
+use framebuffer::Framebuffer;
+use framebuffer::RenderPass;
+use framebuffer::RenderPassDesc;
+use framebuffer::RenderPassClearValues;
+use image::traits::ImageClearValue;
+use image::traits::ImageContent;
+use instance::QueueFamily;
+use pipeline::ComputePipeline;
+use pipeline::GraphicsPipeline;
+use pipeline::input_assembly::Index;
+use pipeline::vertex::Source as VertexSource;
+use query::QueryPool;
+use sync::PipelineStages;
+
+use Error;
+use OomError;
+use VulkanError;
+
+/// Builds a primary command buffer with a single type, whatever the commands that are recorded.
+///
+/// Contrary to `PrimaryCommandBufferBuilder`, whose type changes when a render pass is begun or
+/// ended, this builder checks at runtime that each command is allowed where it is recorded, and
+/// returns an error instead of recording it otherwise. This makes it possible to record commands
+/// in a loop or in helper functions without caring about the exact type of the builder.
+///
+/// The commands are recorded through a mutable reference. When a command returns an error,
+/// nothing is recorded and the builder can still be used.
+///
+/// Pipeline barriers and layout transitions are inserted automatically, the same way as with the
+/// other builders.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut builder = AutoCommandBufferBuilder::new(&device, queue.family());
+/// builder.update_buffer(&uniform_buffer, &uniforms).unwrap();
+/// builder.begin_render_pass(&framebuffer, render_pass::ClearValues {
+///     color: [0.0, 0.0, 1.0, 1.0]
+/// }).unwrap();
+/// builder.draw(&pipeline, &vertex_buffer, &DynamicState::none(), (), &()).unwrap();
+/// builder.end_render_pass().unwrap();
+/// let command_buffer = builder.build().unwrap();
+/// ```
+pub struct AutoCommandBufferBuilder<P = Arc<StandardCommandPool>> where P: CommandPool {
+    // Always `Some`, except while a command is being recorded.
+    state: Option<State<P>>,
+}
+
+enum State<P> where P: CommandPool {
+    // Outside of a render pass.
+    Outside(PrimaryCommandBufferBuilder<P>),
+    // Inside of a render pass whose subpasses are recorded inline.
+    Inside {
+        builder: PrimaryCommandBufferBuilderInlineDraw<P>,
+        subpass: u32,
+        num_subpasses: u32,
+    },
+}
+
+impl AutoCommandBufferBuilder<Arc<StandardCommandPool>> {
+    /// Builds a new primary command buffer and start recording commands in it.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device or host ran out of memory.
+    /// - Panics if the device and queue family do not belong to the same physical device.
+    ///
+    #[inline]
+    pub fn new(device: &Arc<Device>, queue_family: QueueFamily)
+               -> AutoCommandBufferBuilder<Arc<StandardCommandPool>>
+    {
+        AutoCommandBufferBuilder::raw(Device::standard_command_pool(device, queue_family)).unwrap()
+    }
+
+    /// Starts recording commands again in a command buffer that has been built, instead of
+    /// allocating a new one. The previous content of the command buffer is discarded.
+    ///
+    /// See `PrimaryCommandBufferBuilder::reuse` for more info.
+    #[inline]
+    pub fn reuse(command_buffer: PrimaryCommandBuffer<Arc<StandardCommandPool>>)
+                 -> Result<AutoCommandBufferBuilder<Arc<StandardCommandPool>>, OomError>
+    {
+        let builder = try!(PrimaryCommandBufferBuilder::reuse(command_buffer));
+        Ok(AutoCommandBufferBuilder {
+            state: Some(State::Outside(builder)),
+        })
+    }
+}
+
+impl<P> AutoCommandBufferBuilder<P> where P: CommandPool {
+    /// See the docs of new().
+    #[inline]
+    pub fn raw(pool: P) -> Result<AutoCommandBufferBuilder<P>, OomError> {
+        Ok(AutoCommandBufferBuilder {
+            state: Some(State::Outside(try!(PrimaryCommandBufferBuilder::raw(pool)))),
+        })
+    }
+
+    /// Returns true if a render pass has been begun and not ended.
+    #[inline]
+    pub fn is_inside_render_pass(&self) -> bool {
+        match self.state {
+            Some(State::Outside(_)) => false,
+            Some(State::Inside { .. }) => true,
+            None => unreachable!(),
+        }
+    }
+
+    // Records a command that is only allowed outside of a render pass.
+    fn outside<F>(&mut self, f: F) -> Result<(), AutoCommandBufferBuilderError>
+        where F: FnOnce(PrimaryCommandBufferBuilder<P>) -> PrimaryCommandBufferBuilder<P>
+    {
+        if self.is_inside_render_pass() {
+            return Err(AutoCommandBufferBuilderError::ForbiddenInsideRenderPass);
+        }
+
+        match self.state.take() {
+            Some(State::Outside(builder)) => self.state = Some(State::Outside(f(builder))),
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    // Records a command that is only allowed inside of a render pass.
+    fn inside<F>(&mut self, f: F) -> Result<(), AutoCommandBufferBuilderError>
+        where F: FnOnce(PrimaryCommandBufferBuilderInlineDraw<P>)
+                        -> PrimaryCommandBufferBuilderInlineDraw<P>
+    {
+        if !self.is_inside_render_pass() {
+            return Err(AutoCommandBufferBuilderError::ForbiddenOutsideRenderPass);
+        }
+
+        match self.state.take() {
+            Some(State::Inside { builder, subpass, num_subpasses }) => {
+                self.state = Some(State::Inside {
+                    builder: f(builder),
+                    subpass: subpass,
+                    num_subpasses: num_subpasses,
+                });
+            },
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    /// Writes data to a buffer. See `PrimaryCommandBufferBuilder::update_buffer`.
+    ///
+    /// Only allowed outside of a render pass.
+    #[inline]
+    pub fn update_buffer<'a, B, T, Bb>(&mut self, buffer: B, data: &T)
+                                       -> Result<(), AutoCommandBufferBuilderError>
+        where B: Into<BufferSlice<'a, T, Bb>>, Bb: Buffer + 'static,
+              T: Clone + 'static + Send + Sync
+    {
+        self.outside(|b| b.update_buffer(buffer, data))
+    }
+
+    /// Fills a buffer with data. See `PrimaryCommandBufferBuilder::fill_buffer`.
+    ///
+    /// Only allowed outside of a render pass.
+    ///
+    /// # Safety
+    ///
+    /// - Type safety is not enforced by the API.
+    ///
+    #[inline]
+    pub unsafe fn fill_buffer<B>(&mut self, buffer: &Arc<B>, offset: usize, size: usize,
+                                 data: u32) -> Result<(), AutoCommandBufferBuilderError>
+        where B: Buffer + 'static
+    {
+        self.outside(|b| b.fill_buffer(buffer, offset, size, data))
+    }
+
+    /// Copies the content of a buffer to another buffer.
+    ///
+    /// Only allowed outside of a render pass.
+    #[inline]
+    pub fn copy_buffer<T: ?Sized + 'static, Bs, Bd>(&mut self, source: &Arc<Bs>,
+                                                    destination: &Arc<Bd>)
+                                                    -> Result<(), AutoCommandBufferBuilderError>
+        where Bs: TypedBuffer<Content = T> + 'static, Bd: TypedBuffer<Content = T> + 'static
+    {
+        self.outside(|b| b.copy_buffer(source, destination))
+    }
+
+    /// Copies from a buffer to a color image.
+    /// See `PrimaryCommandBufferBuilder::copy_buffer_to_color_image`.
+    ///
+    /// Only allowed outside of a render pass.
+    #[inline]
+    pub fn copy_buffer_to_color_image<'a, Pi, S, Img, Sb>(&mut self, source: S,
+                                                         destination: &Arc<Img>,
+                                                         mip_level: u32,
+                                                         array_layers_range: Range<u32>,
+                                                         offset: [u32; 3], extent: [u32; 3])
+                                                         -> Result<(),
+                                                                   AutoCommandBufferBuilderError>
+        where S: Into<BufferSlice<'a, [Pi], Sb>>, Sb: Buffer + 'static,
+              Img: ImageContent<Pi> + 'static
+    {
+        self.outside(|b| {
+            b.copy_buffer_to_color_image(source, destination, mip_level, array_layers_range,
+                                         offset, extent)
+        })
+    }
+
+    /// Copies from a color image to a buffer.
+    /// See `PrimaryCommandBufferBuilder::copy_color_image_to_buffer`.
+    ///
+    /// Only allowed outside of a render pass.
+    #[inline]
+    pub fn copy_color_image_to_buffer<'a, Pi, S, Img, Sb>(&mut self, dest: S, source: &Arc<Img>,
+                                                         mip_level: u32,
+                                                         array_layers_range: Range<u32>,
+                                                         offset: [u32; 3], extent: [u32; 3])
+                                                         -> Result<(),
+                                                                   AutoCommandBufferBuilderError>
+        where S: Into<BufferSlice<'a, [Pi], Sb>>, Sb: Buffer + 'static,
+              Img: ImageContent<Pi> + 'static
+    {
+        self.outside(|b| {
+            b.copy_color_image_to_buffer(dest, source, mip_level, array_layers_range, offset,
+                                         extent)
+        })
+    }
+
+    /// Clears a color image. See `PrimaryCommandBufferBuilder::clear_color_image`.
+    ///
+    /// Only allowed outside of a render pass.
+    #[inline]
+    pub fn clear_color_image<I, V>(&mut self, image: &Arc<I>, color: V)
+                                   -> Result<(), AutoCommandBufferBuilderError>
+        where I: ImageClearValue<V> + 'static
+    {
+        self.outside(|b| b.clear_color_image(image, color))
+    }
+
+    /// Executes a compute pipeline.
+    ///
+    /// Only allowed outside of a render pass.
+    #[inline]
+    pub fn dispatch<Pl, L, Pc>(&mut self, pipeline: &Arc<ComputePipeline<Pl>>, sets: L,
+                               dimensions: [u32; 3], push_constants: &Pc)
+                               -> Result<(), AutoCommandBufferBuilderError>
+        where L: DescriptorSetsCollection + Send + Sync,
+              Pl: 'static + PipelineLayout + Send + Sync,
+              Pc: 'static + Clone + Send + Sync
+    {
+        self.outside(|b| b.dispatch(pipeline, sets, dimensions, push_constants))
+    }
+
+    /// Begins a render pass on `framebuffer`. The commands of its first subpass are then
+    /// recorded inline.
+    ///
+    /// Only allowed outside of a render pass.
+    pub fn begin_render_pass<F, C>(&mut self, framebuffer: &Arc<Framebuffer<F>>,
+                                   clear_values: C) -> Result<(), AutoCommandBufferBuilderError>
+        where F: RenderPass + RenderPassDesc + RenderPassClearValues<C> + 'static
+    {
+        if self.is_inside_render_pass() {
+            return Err(AutoCommandBufferBuilderError::ForbiddenInsideRenderPass);
+        }
+
+        match self.state.take() {
+            Some(State::Outside(builder)) => {
+                let num_subpasses = framebuffer.render_pass().num_subpasses();
+                let builder = builder.draw_inline(framebuffer.render_pass(), framebuffer,
+                                                  clear_values);
+
+                self.state = Some(State::Inside {
+                    builder: builder,
+                    subpass: 0,
+                    num_subpasses: num_subpasses,
+                });
+            },
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    /// Switches to the next subpass of the current render pass.
+    pub fn next_subpass(&mut self) -> Result<(), AutoCommandBufferBuilderError> {
+        match self.state {
+            Some(State::Outside(_)) => {
+                return Err(AutoCommandBufferBuilderError::ForbiddenOutsideRenderPass);
+            },
+            Some(State::Inside { subpass, num_subpasses, .. }) => {
+                if subpass + 1 >= num_subpasses {
+                    return Err(AutoCommandBufferBuilderError::NoSubpassesRemaining);
+                }
+            },
+            None => unreachable!(),
+        }
+
+        match self.state.take() {
+            Some(State::Inside { builder, subpass, num_subpasses }) => {
+                self.state = Some(State::Inside {
+                    builder: builder.next_subpass_inline(),
+                    subpass: subpass + 1,
+                    num_subpasses: num_subpasses,
+                });
+            },
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    /// Ends the current render pass. All of its subpasses must have been recorded.
+    pub fn end_render_pass(&mut self) -> Result<(), AutoCommandBufferBuilderError> {
+        match self.state {
+            Some(State::Outside(_)) => {
+                return Err(AutoCommandBufferBuilderError::ForbiddenOutsideRenderPass);
+            },
+            Some(State::Inside { subpass, num_subpasses, .. }) => {
+                if subpass + 1 != num_subpasses {
+                    return Err(AutoCommandBufferBuilderError::SubpassesRemaining);
+                }
+            },
+            None => unreachable!(),
+        }
+
+        match self.state.take() {
+            Some(State::Inside { builder, .. }) => {
+                self.state = Some(State::Outside(builder.draw_end()));
+            },
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    /// Draws with a graphics pipeline.
+    ///
+    /// Only allowed inside of a render pass.
+    #[inline]
+    pub fn draw<V, L, Pv, Pl, Rp, Pc>(&mut self, pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
+                                      vertices: V, dynamic: &DynamicState, sets: L,
+                                      push_constants: &Pc)
+                                      -> Result<(), AutoCommandBufferBuilderError>
+        where Pv: VertexSource<V> + 'static, Pl: PipelineLayout + 'static + Send + Sync,
+              Rp: 'static + Send + Sync, L: DescriptorSetsCollection + Send + Sync,
+              Pc: 'static + Clone + Send + Sync
+    {
+        self.inside(|b| b.draw(pipeline, vertices, dynamic, sets, push_constants))
+    }
+
+    /// Draws with a graphics pipeline, using the range of vertices and instances of `params`.
+    /// See `PrimaryCommandBufferBuilderInlineDraw::draw_with_params`.
+    ///
+    /// Only allowed inside of a render pass.
+    #[inline]
+    pub fn draw_with_params<V, L, Pv, Pl, Rp, Pc>(&mut self,
+                                                  pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
+                                                  vertices: V, params: &DrawParams,
+                                                  dynamic: &DynamicState, sets: L,
+                                                  push_constants: &Pc)
+                                                  -> Result<(), AutoCommandBufferBuilderError>
+        where Pv: VertexSource<V> + 'static, Pl: PipelineLayout + 'static + Send + Sync,
+              Rp: 'static + Send + Sync, L: DescriptorSetsCollection + Send + Sync,
+              Pc: 'static + Clone + Send + Sync
+    {
+        self.inside(|b| b.draw_with_params(pipeline, vertices, params, dynamic, sets,
+                                           push_constants))
+    }
+
+    /// Draws with a graphics pipeline and an index buffer.
+    /// See `PrimaryCommandBufferBuilderInlineDraw::draw_indexed`.
+    ///
+    /// Only allowed inside of a render pass.
+    #[inline]
+    pub fn draw_indexed<'a, V, L, Pv, Pl, Rp, I, Ib, Ibb, Pc>(&mut self,
+                                              pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
+                                              vertices: V, indices: Ib, dynamic: &DynamicState,
+                                              sets: L, push_constants: &Pc)
+                                              -> Result<(), AutoCommandBufferBuilderError>
+        where Pv: 'static + VertexSource<V> + Send + Sync,
+              Pl: 'static + PipelineLayout + Send + Sync, Rp: 'static + Send + Sync,
+              Ib: Into<BufferSlice<'a, [I], Ibb>>, I: 'static + Index,
+              Ibb: Buffer + 'static + Send + Sync,
+              L: DescriptorSetsCollection + Send + Sync, Pc: 'static + Clone + Send + Sync
+    {
+        self.inside(|b| b.draw_indexed(pipeline, vertices, indices, dynamic, sets, push_constants))
+    }
+
+    /// Draws with a graphics pipeline and an index buffer, using the range of indices and
+    /// instances and the vertex offset of `params`.
+    /// See `PrimaryCommandBufferBuilderInlineDraw::draw_indexed_with_params`.
+    ///
+    /// Only allowed inside of a render pass.
+    #[inline]
+    pub fn draw_indexed_with_params<'a, V, L, Pv, Pl, Rp, I, Ib, Ibb, Pc>(&mut self,
+                                              pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
+                                              vertices: V, indices: Ib, params: &DrawParams,
+                                              dynamic: &DynamicState, sets: L, push_constants: &Pc)
+                                              -> Result<(), AutoCommandBufferBuilderError>
+        where Pv: 'static + VertexSource<V> + Send + Sync,
+              Pl: 'static + PipelineLayout + Send + Sync, Rp: 'static + Send + Sync,
+              Ib: Into<BufferSlice<'a, [I], Ibb>>, I: 'static + Index,
+              Ibb: Buffer + 'static + Send + Sync,
+              L: DescriptorSetsCollection + Send + Sync, Pc: 'static + Clone + Send + Sync
+    {
+        self.inside(|b| b.draw_indexed_with_params(pipeline, vertices, indices, params, dynamic,
+                                                   sets, push_constants))
+    }
+
+    /// Resets the queries of `pool` in the range `slots`.
+    /// See `PrimaryCommandBufferBuilder::reset_query_pool`.
+    ///
+    /// Only allowed outside of a render pass.
+    #[inline]
+    pub fn reset_query_pool<Q>(&mut self, pool: &Arc<Q>, slots: Range<u32>)
+                               -> Result<(), AutoCommandBufferBuilderError>
+        where Q: QueryPool
+    {
+        self.outside(|b| b.reset_query_pool(pool, slots))
+    }
+
+    /// Writes a timestamp in the given slot of `pool`.
+    /// See `PrimaryCommandBufferBuilder::write_timestamp`.
+    ///
+    /// Allowed both inside and outside of a render pass.
+    ///
+    /// # Safety
+    ///
+    /// - The slot must have been reset.
+    ///
+    pub unsafe fn write_timestamp<Q>(&mut self, pool: &Arc<Q>, stage: PipelineStages, slot: u32)
+                                     -> Result<(), AutoCommandBufferBuilderError>
+        where Q: QueryPool
+    {
+        let state = match self.state.take() {
+            Some(State::Outside(builder)) => {
+                State::Outside(builder.write_timestamp(pool, stage, slot))
+            },
+            Some(State::Inside { builder, subpass, num_subpasses }) => {
+                State::Inside {
+                    builder: builder.write_timestamp(pool, stage, slot),
+                    subpass: subpass,
+                    num_subpasses: num_subpasses,
+                }
+            },
+            None => unreachable!(),
+        };
+
+        self.state = Some(state);
+        Ok(())
+    }
+
+    /// Finishes recording commands and builds the command buffer, which can then be submitted.
+    ///
+    /// Returns an error if a render pass has been begun and not ended. Contrary to the other
+    /// methods, this consumes the builder even if an error is returned. Use
+    /// `is_inside_render_pass` beforehand to keep the builder in that situation.
+    pub fn build(self) -> Result<Arc<PrimaryCommandBuffer<P>>, AutoCommandBufferBuilderError> {
+        match self.state {
+            Some(State::Outside(builder)) => Ok(Arc::new(try!(builder.build_raw()))),
+            Some(State::Inside { .. }) => Err(AutoCommandBufferBuilderError::RenderPassNotEnded),
+            None => unreachable!(),
+        }
+    }
+}
+
+/// Error that can happen when recording a command with an `AutoCommandBufferBuilder`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AutoCommandBufferBuilderError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The command can't be recorded inside of a render pass.
+    ForbiddenInsideRenderPass,
+    /// The command can only be recorded inside of a render pass.
+    ForbiddenOutsideRenderPass,
+    /// Tried to switch to the next subpass while at the last subpass of the render pass.
+    NoSubpassesRemaining,
+    /// Tried to end the render pass before recording all of its subpasses.
+    SubpassesRemaining,
+    /// Tried to build the command buffer while a render pass hasn't been ended.
+    RenderPassNotEnded,
+}
+
+impl error::Error for AutoCommandBufferBuilderError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            AutoCommandBufferBuilderError::OomError(_) => "not enough memory available",
+            AutoCommandBufferBuilderError::ForbiddenInsideRenderPass => {
+                "the command can't be recorded inside of a render pass"
+            },
+            AutoCommandBufferBuilderError::ForbiddenOutsideRenderPass => {
+                "the command can only be recorded inside of a render pass"
+            },
+            AutoCommandBufferBuilderError::NoSubpassesRemaining => {
+                "tried to switch to the next subpass while at the last subpass"
+            },
+            AutoCommandBufferBuilderError::SubpassesRemaining => {
+                "tried to end the render pass before recording all of its subpasses"
+            },
+            AutoCommandBufferBuilderError::RenderPassNotEnded => {
+                "tried to build the command buffer while a render pass hasn't been ended"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            AutoCommandBufferBuilderError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for AutoCommandBufferBuilderError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for AutoCommandBufferBuilderError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            AutoCommandBufferBuilderError::OomError(ref err) => err.error_code(),
+            _ => None
+        }
+    }
+}
+
+impl From<OomError> for AutoCommandBufferBuilderError {
+    #[inline]
+    fn from(err: OomError) -> AutoCommandBufferBuilderError {
+        AutoCommandBufferBuilderError::OomError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::AutoCommandBufferBuilderError;
+    use framebuffer::EmptySinglePassRenderPass;
+    use framebuffer::Framebuffer;
+
+    #[test]
+    fn render_pass_state() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let render_pass = EmptySinglePassRenderPass::new(&device);
+        let framebuffer = Framebuffer::new(&render_pass, [32, 32, 1], ()).unwrap();
+
+        let mut builder = AutoCommandBufferBuilder::new(&device, queue.family());
+        assert!(!builder.is_inside_render_pass());
+        match builder.end_render_pass() {
+            Err(AutoCommandBufferBuilderError::ForbiddenOutsideRenderPass) => (),
+            _ => panic!()
+        };
+
+        builder.begin_render_pass(&framebuffer, ()).unwrap();
+        assert!(builder.is_inside_render_pass());
+        match builder.next_subpass() {
+            Err(AutoCommandBufferBuilderError::NoSubpassesRemaining) => (),
+            _ => panic!()
+        };
+        match builder.begin_render_pass(&framebuffer, ()) {
+            Err(AutoCommandBufferBuilderError::ForbiddenInsideRenderPass) => (),
+            _ => panic!()
+        };
+
+        // The builder can still be used after an error.
+        assert!(builder.is_inside_render_pass());
+        builder.end_render_pass().unwrap();
+        builder.build().unwrap();
+
+        let mut builder = AutoCommandBufferBuilder::new(&device, queue.family());
+        builder.begin_render_pass(&framebuffer, ()).unwrap();
+        match builder.build() {
+            Err(AutoCommandBufferBuilderError::RenderPassNotEnded) => (),
+            _ => panic!()
+        };
+    }
+
+    #[test]
+    fn reuse() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let render_pass = EmptySinglePassRenderPass::new(&device);
+        let framebuffer = Framebuffer::new(&render_pass, [32, 32, 1], ()).unwrap();
+
+        let builder = AutoCommandBufferBuilder::new(&device, queue.family());
+        let command_buffer = Arc::try_unwrap(builder.build().unwrap()).ok().unwrap();
+
+        let mut builder = AutoCommandBufferBuilder::reuse(command_buffer).unwrap();
+        builder.begin_render_pass(&framebuffer, ()).unwrap();
+        builder.end_render_pass().unwrap();
+        builder.build().unwrap();
+    }
+}
@@ -0,0 +1,366 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+
+use command_buffer::PrimaryCommandBuffer;
+use command_buffer::Submission;
+use command_buffer::pool::CommandPool;
+use command_buffer::submit_with_semaphores;
+use device::Device;
+use device::Queue;
+use device::QueueError;
+use sync::PipelineStages;
+use sync::Semaphore;
+
+use Error;
+use OomError;
+use VulkanError;
+
+/// Builds a group of command buffer submissions to multiple queues, with dependencies between
+/// them.
+///
+/// Each node of the graph is a command buffer and the queue it is submitted to. Each dependency
+/// between two nodes becomes a semaphore that is signaled by the first submission and waited upon
+/// by the second one at the given pipeline stages. This makes it possible to express work such as
+/// asynchronous compute running alongside graphics without creating semaphores manually.
+///
+/// The graph is validated before anything is submitted. The submissions are then performed in
+/// an order that respects the dependencies.
+///
+/// # Example
+///
+/// ```no_run
+/// use vulkano::command_buffer::SubmitGraph;
+/// use vulkano::sync::PipelineStages;
+///
+/// # let compute_queue: std::sync::Arc<vulkano::device::Queue> = unsafe { std::mem::uninitialized() };
+/// # let graphics_queue: std::sync::Arc<vulkano::device::Queue> = unsafe { std::mem::uninitialized() };
+/// # let simulation: std::sync::Arc<vulkano::command_buffer::PrimaryCommandBuffer> = unsafe { std::mem::uninitialized() };
+/// # let draw: std::sync::Arc<vulkano::command_buffer::PrimaryCommandBuffer> = unsafe { std::mem::uninitialized() };
+/// let mut graph = SubmitGraph::new();
+/// let simulation = graph.add(&compute_queue, &simulation);
+/// let draw = graph.add(&graphics_queue, &draw);
+///
+/// // The vertex input of the draw reads the results of the simulation.
+/// graph.add_dependency(simulation, draw,
+///                      PipelineStages { vertex_input: true, .. PipelineStages::none() });
+///
+/// let submissions = graph.submit().unwrap();
+/// ```
+pub struct SubmitGraph {
+    nodes: Vec<Node>,
+    dependencies: Vec<Dependency>,
+}
+
+struct Node {
+    queue: Arc<Queue>,
+    submit: Box<Fn(&Arc<Queue>, &[(Arc<Semaphore>, PipelineStages)], &[Arc<Semaphore>])
+                   -> Result<Arc<Submission>, QueueError>>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Dependency {
+    before: SubmitNode,
+    after: SubmitNode,
+    stages: PipelineStages,
+}
+
+/// Identifies a node of a `SubmitGraph`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SubmitNode(usize);
+
+impl SubmitGraph {
+    /// Builds an empty graph.
+    #[inline]
+    pub fn new() -> SubmitGraph {
+        SubmitGraph {
+            nodes: Vec::new(),
+            dependencies: Vec::new(),
+        }
+    }
+
+    /// Adds a command buffer that will be submitted to `queue`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the queue doesn't belong to the same device as the queues of the other nodes.
+    ///
+    pub fn add<P>(&mut self, queue: &Arc<Queue>, command_buffer: &Arc<PrimaryCommandBuffer<P>>)
+                  -> SubmitNode
+        where P: CommandPool + 'static,
+              P::Finished: Send + Sync + 'static
+    {
+        if let Some(first) = self.nodes.first() {
+            assert_eq!(&**first.queue.device() as *const Device,
+                       &**queue.device() as *const Device);
+        }
+
+        let command_buffer = command_buffer.clone();
+
+        self.nodes.push(Node {
+            queue: queue.clone(),
+            // `submit` submits the nodes in dependency order, with one new semaphore per
+            // dependency.
+            submit: Box::new(move |queue, wait, signal| unsafe {
+                submit_with_semaphores(&command_buffer, queue, wait, signal)
+            }),
+        });
+
+        SubmitNode(self.nodes.len() - 1)
+    }
+
+    /// Declares that the stages `stages` of the submission of `after` must wait until the
+    /// submission of `before` has finished.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the nodes doesn't belong to this graph.
+    ///
+    pub fn add_dependency(&mut self, before: SubmitNode, after: SubmitNode,
+                          stages: PipelineStages)
+    {
+        assert!(before.0 < self.nodes.len());
+        assert!(after.0 < self.nodes.len());
+
+        self.dependencies.push(Dependency {
+            before: before,
+            after: after,
+            stages: stages,
+        });
+    }
+
+    /// Returns the number of command buffers in the graph.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Checks that the graph can be submitted.
+    ///
+    /// This is automatically called by `submit`.
+    pub fn validate(&self) -> Result<(), SubmitGraphError> {
+        try!(self.submission_order());
+        Ok(())
+    }
+
+    /// Submits all the command buffers of the graph.
+    ///
+    /// Returns the submissions in the same order as the nodes were added.
+    ///
+    /// If an error happens while submitting, the command buffers that have already been
+    /// submitted still execute.
+    pub fn submit(self) -> Result<Vec<Arc<Submission>>, SubmitGraphError> {
+        let order = try!(self.submission_order());
+
+        // Creating one semaphore per dependency.
+        let semaphores = {
+            let mut list = Vec::with_capacity(self.dependencies.len());
+            for dependency in self.dependencies.iter() {
+                let device = self.nodes[dependency.before.0].queue.device();
+                list.push(Arc::new(try!(Semaphore::raw(device.clone()))));
+            }
+            list
+        };
+
+        let mut submissions = self.nodes.iter().map(|_| None).collect::<Vec<_>>();
+
+        for node_id in order {
+            let wait = self.dependencies.iter().zip(semaphores.iter())
+                           .filter(|&(d, _)| d.after.0 == node_id)
+                           .map(|(d, s)| (s.clone(), d.stages))
+                           .collect::<Vec<_>>();
+            let signal = self.dependencies.iter().zip(semaphores.iter())
+                             .filter(|&(d, _)| d.before.0 == node_id)
+                             .map(|(_, s)| s.clone())
+                             .collect::<Vec<_>>();
+
+            let node = &self.nodes[node_id];
+            submissions[node_id] = Some(try!((node.submit)(&node.queue, &wait, &signal)));
+        }
+
+        Ok(submissions.into_iter().map(|s| s.unwrap()).collect())
+    }
+
+    // Checks the graph and returns the order in which the nodes must be submitted, so that each
+    // submission that signals a semaphore is performed before the submission that waits upon it.
+    fn submission_order(&self) -> Result<Vec<usize>, SubmitGraphError> {
+        for dependency in self.dependencies.iter() {
+            if dependency.before == dependency.after {
+                return Err(SubmitGraphError::CyclicDependency);
+            }
+
+            let raw_stages: u32 = dependency.stages.into();
+            if raw_stages == 0 {
+                return Err(SubmitGraphError::NoWaitStage);
+            }
+
+            let family = self.nodes[dependency.after.0].queue.family();
+            if !dependency.stages.supported_by_queue_family(family) {
+                return Err(SubmitGraphError::UnsupportedWaitStage);
+            }
+        }
+
+        // Kahn's algorithm. Nodes that don't depend on each other keep the order in which they
+        // were added.
+        let mut remaining_deps = self.nodes.iter().enumerate().map(|(id, _)| {
+            self.dependencies.iter().filter(|d| d.after.0 == id).count()
+        }).collect::<Vec<_>>();
+        let mut submitted = vec![false; self.nodes.len()];
+        let mut order = Vec::with_capacity(self.nodes.len());
+
+        while order.len() < self.nodes.len() {
+            let next = match (0 .. self.nodes.len()).find(|&id| {
+                !submitted[id] && remaining_deps[id] == 0
+            }) {
+                Some(id) => id,
+                None => return Err(SubmitGraphError::CyclicDependency),
+            };
+
+            submitted[next] = true;
+            order.push(next);
+
+            for dependency in self.dependencies.iter().filter(|d| d.before.0 == next) {
+                remaining_deps[dependency.after.0] -= 1;
+            }
+        }
+
+        Ok(order)
+    }
+}
+
+/// Error that can happen when validating or submitting a `SubmitGraph`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubmitGraphError {
+    /// Error while creating the semaphores or submitting a command buffer.
+    QueueError(QueueError),
+    /// The dependencies between the nodes contain a cycle.
+    CyclicDependency,
+    /// A dependency doesn't have any pipeline stage to wait at.
+    NoWaitStage,
+    /// A dependency waits at a pipeline stage that the queue of the node can't execute.
+    UnsupportedWaitStage,
+}
+
+impl error::Error for SubmitGraphError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            SubmitGraphError::QueueError(_) => "error while submitting a command buffer",
+            SubmitGraphError::CyclicDependency => {
+                "the dependencies between the submissions contain a cycle"
+            },
+            SubmitGraphError::NoWaitStage => "a dependency doesn't have any pipeline stage",
+            SubmitGraphError::UnsupportedWaitStage => {
+                "a dependency waits at a pipeline stage that the queue doesn't support"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            SubmitGraphError::QueueError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for SubmitGraphError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for SubmitGraphError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            SubmitGraphError::QueueError(ref err) => err.error_code(),
+            _ => None
+        }
+    }
+}
+
+impl From<OomError> for SubmitGraphError {
+    #[inline]
+    fn from(err: OomError) -> SubmitGraphError {
+        SubmitGraphError::QueueError(QueueError::OomError(err))
+    }
+}
+
+impl From<QueueError> for SubmitGraphError {
+    #[inline]
+    fn from(err: QueueError) -> SubmitGraphError {
+        SubmitGraphError::QueueError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use command_buffer::PrimaryCommandBufferBuilder;
+    use command_buffer::SubmitGraph;
+    use command_buffer::SubmitGraphError;
+    use sync::PipelineStages;
+
+    #[test]
+    fn chain() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let mut graph = SubmitGraph::new();
+        let c = graph.add(&queue, &PrimaryCommandBufferBuilder::new(&device, queue.family())
+                                                                .build());
+        let a = graph.add(&queue, &PrimaryCommandBufferBuilder::new(&device, queue.family())
+                                                                .build());
+        let b = graph.add(&queue, &PrimaryCommandBufferBuilder::new(&device, queue.family())
+                                                                .build());
+
+        let stages = PipelineStages { all_commands: true, .. PipelineStages::none() };
+        graph.add_dependency(a, b, stages);
+        graph.add_dependency(b, c, stages);
+
+        let submissions = graph.submit().unwrap();
+        assert_eq!(submissions.len(), 3);
+        submissions[0].wait(Duration::from_secs(10)).unwrap();
+    }
+
+    #[test]
+    fn cycle() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let mut graph = SubmitGraph::new();
+        let cb = PrimaryCommandBufferBuilder::new(&device, queue.family()).build();
+        let a = graph.add(&queue, &cb);
+        let b = graph.add(&queue, &cb);
+
+        let stages = PipelineStages { all_commands: true, .. PipelineStages::none() };
+        graph.add_dependency(a, b, stages);
+        graph.add_dependency(b, a, stages);
+
+        assert_eq!(graph.validate(), Err(SubmitGraphError::CyclicDependency));
+    }
+
+    #[test]
+    fn no_wait_stage() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let mut graph = SubmitGraph::new();
+        let cb = PrimaryCommandBufferBuilder::new(&device, queue.family()).build();
+        let a = graph.add(&queue, &cb);
+        let b = graph.add(&queue, &cb);
+        graph.add_dependency(a, b, PipelineStages::none());
+
+        assert_eq!(graph.validate(), Err(SubmitGraphError::NoWaitStage));
+    }
+}
@@ -26,34 +26,51 @@ use buffer::Buffer;
 use buffer::BufferSlice;
 use buffer::TypedBuffer;
 use buffer::traits::AccessRange as BufferAccessRange;
+use command_buffer::DrawIndexedIndirectCommand;
 use command_buffer::DrawIndirectCommand;
+use command_buffer::DrawParams;
 use command_buffer::DynamicState;
+use command_buffer::DynamicStencilValue;
+use command_buffer::OcclusionPredicates;
 use command_buffer::pool::CommandPool;
 use command_buffer::pool::CommandPoolFinished;
 use command_buffer::pool::StandardCommandPool;
 use descriptor::descriptor_set::DescriptorSetsCollection;
 use descriptor::PipelineLayout;
+use descriptor::pipeline_layout::PipelineLayoutPushConstantsCompatible;
+use descriptor::pipeline_layout::push_constants::push_constants_updates;
 use device::Queue;
 use format::ClearValue;
 use format::FormatTy;
-use format::PossibleFloatFormatDesc;
 use framebuffer::RenderPass;
+use framebuffer::RenderPassDesc;
 use framebuffer::Framebuffer;
 use framebuffer::Subpass;
+use framebuffer::UnsafeRenderPass;
 use image::Image;
+use image::ImageAspect;
 use image::sys::Layout as ImageLayout;
 use image::traits::ImageClearValue;
 use image::traits::ImageContent;
 use image::traits::AccessRange as ImageAccessRange;
 use pipeline::ComputePipeline;
 use pipeline::GraphicsPipeline;
+use pipeline::depth_stencil::check_depth_bounds;
 use pipeline::input_assembly::Index;
+use pipeline::vertex::InputRate;
 use pipeline::vertex::Source as VertexSource;
+use query::QueryPool;
+use query::QueryResultFlags;
+use query::QueryType;
 use sync::Fence;
 use sync::FenceWaitError;
+use sync::PipelineStages;
 use sync::Semaphore;
 
+use device::BufferAccessChecks;
 use device::Device;
+use device::DeviceExtensions;
+use device::QueueError;
 use OomError;
 use SynchronizedVulkanObject;
 use VulkanObject;
@@ -118,6 +135,14 @@ pub struct InnerCommandBufferBuilder<P> where P: CommandPool {
     render_pass_staging_required_buffer_accesses: HashMap<(BufferKey, usize), InternalBufferBlockAccess, BuildHasherDefault<FnvHasher>>,
     render_pass_staging_required_image_accesses: HashMap<(ImageKey, (u32, u32)), InternalImageBlockAccess, BuildHasherDefault<FnvHasher>>,
 
+    // For each subpass of the current render pass, the images that are used as input attachments
+    // and their layout during the subpass. Empty if we're not inside a render pass, or if the
+    // render pass is unknown because this is a secondary command buffer.
+    render_pass_input_attachments: Vec<Vec<(Arc<Image>, ImageLayout)>>,
+
+    // Index of the current subpass within the current render pass.
+    current_subpass: usize,
+
     // List of resources that must be kept alive because they are used by this command buffer.
     keep_alive: Vec<Arc<KeepAlive>>,
 
@@ -129,6 +154,9 @@ pub struct InnerCommandBufferBuilder<P> where P: CommandPool {
 
     // Current state of the dynamic state within the command buffer. Includes all staging commands.
     current_dynamic_state: DynamicState,
+
+    // Pools and slots of the queries that were started as precise by this command buffer.
+    precise_queries: Vec<(vk::QueryPool, u32)>,
 }
 
 impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
@@ -137,12 +165,46 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
                   secondary_cont_fb: Option<&Arc<Framebuffer<R>>>)
                   -> Result<InnerCommandBufferBuilder<P>, OomError>
         where R: RenderPass + 'static + Send + Sync
+    {
+        let cmd = try!(pool.alloc(secondary, 1)).next().unwrap().internal_object();
+        InnerCommandBufferBuilder::begin(pool, cmd, secondary, secondary_cont, secondary_cont_fb)
+    }
+
+    /// Creates a new builder for a primary command buffer that records its commands in the
+    /// Vulkan command buffer of `cb`, instead of allocating a new one. The previous content of
+    /// `cb` is discarded.
+    ///
+    /// # Safety
+    ///
+    /// - `cb` must be a primary command buffer that was built from `pool`.
+    /// - `cb` must not be in use by the GPU anymore.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `pool` can't reset command buffers individually.
+    ///
+    pub unsafe fn reuse(pool: P, cb: InnerCommandBuffer<P>)
+                        -> Result<InnerCommandBufferBuilder<P>, OomError>
+    {
+        assert!(pool.can_reset_invidual_command_buffers(),
+                "The pool can't reset command buffers individually");
+        debug_assert!(!cb.is_secondary);
+
+        // Beginning the command buffer implicitly resets it.
+        let cmd = cb.into_raw();
+        InnerCommandBufferBuilder::begin::<UnsafeRenderPass>(pool, cmd, false, None, None)
+    }
+
+    // Starts recording commands in `cmd`, which was allocated from `pool`.
+    fn begin<R>(pool: P, cmd: vk::CommandBuffer, secondary: bool,
+                secondary_cont: Option<Subpass<R>>,
+                secondary_cont_fb: Option<&Arc<Framebuffer<R>>>)
+                -> Result<InnerCommandBufferBuilder<P>, OomError>
+        where R: RenderPass + 'static + Send + Sync
     {
         let device = pool.device().clone();
         let vk = device.pointers();
 
-        let cmd = try!(pool.alloc(secondary, 1)).next().unwrap().internal_object();
-
         let mut keep_alive = Vec::new();
 
         unsafe {
@@ -199,10 +261,13 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
             render_pass_staging_commands: Vec::new(),
             render_pass_staging_required_buffer_accesses: HashMap::with_hasher(BuildHasherDefault::<FnvHasher>::default()),
             render_pass_staging_required_image_accesses: HashMap::with_hasher(BuildHasherDefault::<FnvHasher>::default()),
+            render_pass_input_attachments: Vec::new(),
+            current_subpass: 0,
             keep_alive: keep_alive,
             current_graphics_pipeline: None,
             current_compute_pipeline: None,
             current_dynamic_state: DynamicState::none(),
+            precise_queries: Vec::new(),
         })
     }
 
@@ -415,6 +480,230 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
         self
     }
 
+    /// Adds a pipeline barrier that transfers the ownership of a buffer from the queue family
+    /// `source` to the queue family `destination`.
+    ///
+    /// If the command buffer is created for `source`, the barrier is the release half of the
+    /// transfer and makes the transfer writes done earlier available. If it is created for
+    /// `destination`, the barrier is the acquire half of the transfer.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `source` and `destination` are equal.
+    /// - Panics if the queue family of the command buffer is neither `source` nor `destination`.
+    ///
+    /// # Safety
+    ///
+    /// - The buffer must have been created with the exclusive sharing mode.
+    /// - Each release must be matched by an acquire with the same parameters, submitted after
+    ///   the release has been executed.
+    /// - Care must be taken to respect the rules about secondary command buffers.
+    ///
+    pub unsafe fn transfer_buffer_ownership<B>(mut self, buffer: &Arc<B>, source: u32,
+                                               destination: u32) -> InnerCommandBufferBuilder<P>
+        where B: Buffer + 'static
+    {
+        debug_assert!(self.render_pass_staging_commands.is_empty());
+
+        let family = self.pool.as_ref().unwrap().queue_family().id();
+        assert!(source != destination);
+        assert!(family == source || family == destination);
+        let release = family == source;
+
+        let (stages, accesses) = if release {
+            (vk::PIPELINE_STAGE_TRANSFER_BIT, vk::ACCESS_TRANSFER_WRITE_BIT)
+        } else {
+            (vk::PIPELINE_STAGE_ALL_COMMANDS_BIT, vk::ACCESS_MEMORY_READ_BIT)
+        };
+
+        self.add_buffer_resource_outside(buffer.clone() as Arc<_>, true, 0 .. buffer.size(),
+                                         stages, accesses);
+
+        {
+            let buffer_size = buffer.size() as vk::DeviceSize;
+            let buffer = buffer.inner().internal_object();
+
+            self.staging_commands.push(Box::new(move |vk, cmd| {
+                let (src_access, dst_access) = if release { (accesses, 0) } else { (0, accesses) };
+
+                let barrier = vk::BufferMemoryBarrier {
+                    sType: vk::STRUCTURE_TYPE_BUFFER_MEMORY_BARRIER,
+                    pNext: ptr::null(),
+                    srcAccessMask: src_access,
+                    dstAccessMask: dst_access,
+                    srcQueueFamilyIndex: source,
+                    dstQueueFamilyIndex: destination,
+                    buffer: buffer,
+                    offset: 0,
+                    size: buffer_size,
+                };
+
+                let (src_stages, dst_stages) = if release {
+                    (stages, vk::PIPELINE_STAGE_BOTTOM_OF_PIPE_BIT)
+                } else {
+                    (vk::PIPELINE_STAGE_TOP_OF_PIPE_BIT, stages)
+                };
+
+                vk.CmdPipelineBarrier(cmd, src_stages, dst_stages, 0, 0, ptr::null(),
+                                      1, &barrier, 0, ptr::null());
+            }));
+        }
+
+        self
+    }
+
+    /// Adds a pipeline barrier that transfers the ownership of a whole image from the queue family
+    /// `source` to the queue family `destination`.
+    ///
+    /// The image is put in `layout` before the transfer and stays in this layout. The release
+    /// and the acquire must use the same layout, which should be the layout that the image has
+    /// at the end of a command buffer. Otherwise the image would have to be transitioned after
+    /// it has been released.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `source` and `destination` are equal.
+    /// - Panics if the queue family of the command buffer is neither `source` nor `destination`.
+    ///
+    /// # Safety
+    ///
+    /// - The image must have been created with the exclusive sharing mode.
+    /// - Each release must be matched by an acquire with the same parameters, submitted after
+    ///   the release has been executed.
+    /// - Care must be taken to respect the rules about secondary command buffers.
+    ///
+    pub unsafe fn transfer_image_ownership<I>(mut self, image: &Arc<I>, source: u32,
+                                              destination: u32, layout: ImageLayout)
+                                              -> InnerCommandBufferBuilder<P>
+        where I: Image + 'static
+    {
+        debug_assert!(self.render_pass_staging_commands.is_empty());
+
+        let family = self.pool.as_ref().unwrap().queue_family().id();
+        assert!(source != destination);
+        assert!(family == source || family == destination);
+        let release = family == source;
+
+        let (stages, accesses) = if release {
+            (vk::PIPELINE_STAGE_TRANSFER_BIT, vk::ACCESS_TRANSFER_WRITE_BIT)
+        } else {
+            (vk::PIPELINE_STAGE_ALL_COMMANDS_BIT, vk::ACCESS_MEMORY_READ_BIT)
+        };
+
+        let mipmap_levels = image.inner().mipmap_levels();
+        let array_layers = image.dimensions().array_layers();
+
+        self.add_image_resource_outside(image.clone() as Arc<_>, 0 .. mipmap_levels,
+                                        0 .. array_layers, true, layout, stages, accesses);
+
+        {
+            let aspect_mask = match image.format().ty() {
+                FormatTy::Float | FormatTy::Uint | FormatTy::Sint | FormatTy::Compressed => {
+                    vk::IMAGE_ASPECT_COLOR_BIT
+                },
+                FormatTy::Depth => vk::IMAGE_ASPECT_DEPTH_BIT,
+                FormatTy::Stencil => vk::IMAGE_ASPECT_STENCIL_BIT,
+                FormatTy::DepthStencil => vk::IMAGE_ASPECT_DEPTH_BIT | vk::IMAGE_ASPECT_STENCIL_BIT,
+            };
+
+            let image = image.inner().internal_object();
+
+            self.staging_commands.push(Box::new(move |vk, cmd| {
+                let (src_access, dst_access) = if release { (accesses, 0) } else { (0, accesses) };
+
+                let barrier = vk::ImageMemoryBarrier {
+                    sType: vk::STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER,
+                    pNext: ptr::null(),
+                    srcAccessMask: src_access,
+                    dstAccessMask: dst_access,
+                    oldLayout: layout as u32,
+                    newLayout: layout as u32,
+                    srcQueueFamilyIndex: source,
+                    dstQueueFamilyIndex: destination,
+                    image: image,
+                    subresourceRange: vk::ImageSubresourceRange {
+                        aspectMask: aspect_mask,
+                        baseMipLevel: 0,
+                        levelCount: mipmap_levels,
+                        baseArrayLayer: 0,
+                        layerCount: array_layers,
+                    },
+                };
+
+                let (src_stages, dst_stages) = if release {
+                    (stages, vk::PIPELINE_STAGE_BOTTOM_OF_PIPE_BIT)
+                } else {
+                    (vk::PIPELINE_STAGE_TOP_OF_PIPE_BIT, stages)
+                };
+
+                vk.CmdPipelineBarrier(cmd, src_stages, dst_stages, 0, 0, ptr::null(),
+                                      0, ptr::null(), 1, &barrier);
+            }));
+        }
+
+        self
+    }
+
+    /// Copies data between slices of buffers.
+    ///
+    /// There is no restriction for the type of queue that can perform this.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the buffers don't belong to the same device.
+    /// - Panics if one of the buffers wasn't created with the right usage.
+    /// - Panics if the slices don't have the same size.
+    ///
+    /// # Safety
+    ///
+    /// - The slices must not overlap if they belong to the same buffer.
+    /// - Care must be taken to respect the rules about secondary command buffers.
+    ///
+    pub unsafe fn copy_buffer_slice<'a, 'b, T: ?Sized, Bs, Bd, S, D>(mut self, source: S,
+                                                                     destination: D)
+                                                                     -> InnerCommandBufferBuilder<P>
+        where S: Into<BufferSlice<'a, T, Bs>>, D: Into<BufferSlice<'b, T, Bd>>,
+              Bs: Buffer + 'static, Bd: Buffer + 'static
+    {
+        debug_assert!(self.render_pass_staging_commands.is_empty());
+
+        let source = source.into();
+        let destination = destination.into();
+
+        assert_eq!(&**source.buffer().inner().device() as *const _,
+                   &**destination.buffer().inner().device() as *const _);
+        assert!(source.buffer().inner().usage_transfer_src());
+        assert!(destination.buffer().inner().usage_transfer_dest());
+        assert_eq!(source.size(), destination.size());
+
+        self.add_buffer_resource_outside(source.buffer().clone() as Arc<_>, false,
+                                         source.offset() .. source.offset() + source.size(),
+                                         vk::PIPELINE_STAGE_TRANSFER_BIT,
+                                         vk::ACCESS_TRANSFER_READ_BIT);
+        self.add_buffer_resource_outside(destination.buffer().clone() as Arc<_>, true,
+                                         destination.offset() ..
+                                         destination.offset() + destination.size(),
+                                         vk::PIPELINE_STAGE_TRANSFER_BIT,
+                                         vk::ACCESS_TRANSFER_WRITE_BIT);
+
+        {
+            let copy = vk::BufferCopy {
+                srcOffset: source.offset() as vk::DeviceSize,
+                dstOffset: destination.offset() as vk::DeviceSize,
+                size: source.size() as vk::DeviceSize,
+            };
+
+            let source = source.buffer().inner().internal_object();
+            let destination = destination.buffer().inner().internal_object();
+
+            self.staging_commands.push(Box::new(move |vk, cmd| {
+                vk.CmdCopyBuffer(cmd, source, destination, 1, &copy);
+            }));
+        }
+
+        self
+    }
+
     /// Copies data between buffers.
     ///
     /// There is no restriction for the type of queue that can perform this.
@@ -429,7 +718,6 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
     /// - Type safety is not enforced by the API.
     /// - Care must be taken to respect the rules about secondary command buffers.
     ///
-    // TODO: doesn't support slices
     pub unsafe fn copy_buffer<T: ?Sized + 'static, Bs, Bd>(mut self, source: &Arc<Bs>,
                                                            destination: &Arc<Bd>)
                                                            -> InnerCommandBufferBuilder<P>
@@ -471,19 +759,30 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
     ///
     /// Note that compressed formats are not supported.
     ///
+    /// # Panic
+    ///
+    /// - Panics if the image doesn't have a float, integer or unsigned integer format.
+    /// - Panics if the clear value doesn't match the format of the image.
+    ///
     /// # Safety
     ///
     /// - Care must be taken to respect the rules about secondary command buffers.
     ///
     pub unsafe fn clear_color_image<'a, I, V>(mut self, image: &Arc<I>, color: V)
                                               -> InnerCommandBufferBuilder<P>
-        where I: ImageClearValue<V> + 'static   // FIXME: should accept uint and int images too
+        where I: ImageClearValue<V> + 'static
     {
         debug_assert!(self.render_pass_staging_commands.is_empty());
 
-        assert!(image.format().is_float()); // FIXME: should accept uint and int images too
+        let format = image.format();
+        match format.ty() {
+            FormatTy::Float | FormatTy::Uint | FormatTy::Sint => (),
+            _ => panic!("Can't clear an image of format {:?} as a color image", format),
+        }
 
         let color = image.decode(color).unwrap(); /* FIXME: error */
+        assert!(color.is_compatible_with(format), "The clear value {:?} doesn't match the \
+                                                   format {:?}", color, format);
 
         {
             let image = image.inner().internal_object();
@@ -493,7 +792,7 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
                     ClearValue::Float(data) => vk::ClearColorValue::float32(data),
                     ClearValue::Int(data) => vk::ClearColorValue::int32(data),
                     ClearValue::Uint(data) => vk::ClearColorValue::uint32(data),
-                    _ => unreachable!()   // PossibleFloatFormatDesc has been improperly implemented
+                    _ => unreachable!()   // checked by `is_compatible_with` above
                 };
 
                 let range = vk::ImageSubresourceRange {
@@ -516,16 +815,67 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
     ///
     /// This operation can be performed by any kind of queue.
     ///
+    /// The third component of `offset` and `extent` designates the range of depth slices to
+    /// write if the image is three-dimensional, and must be respectively 0 and 1 otherwise.
+    ///
+    /// If the image has a compressed format, the buffer must contain the compressed blocks
+    /// tightly packed. See `Format::packed_size`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the region is out of range of the image.
+    /// - Panics if the image has a compressed format and the region isn't aligned to blocks.
+    /// - Panics if the buffer is too small for the region.
+    ///
     /// # Safety
     ///
     /// - Care must be taken to respect the rules about secondary command buffers.
     ///
-    pub unsafe fn copy_buffer_to_color_image<'a, Pi, S, Sb, Img>(mut self, source: S, image: &Arc<Img>,
+    pub unsafe fn copy_buffer_to_color_image<'a, Pi, S, Sb, Img>(self, source: S, image: &Arc<Img>,
                                                                 mip_level: u32, array_layers_range: Range<u32>,
                                                                 offset: [u32; 3], extent: [u32; 3])
                                                              -> InnerCommandBufferBuilder<P>
         where S: Into<BufferSlice<'a, [Pi], Sb>>, Img: ImageContent<Pi> + Image + 'static,
               Sb: Buffer + 'static
+    {
+        self.copy_buffer_to_image_aspect(source, image, ImageAspect::Color, mip_level,
+                                         array_layers_range, offset, extent)
+    }
+
+    /// Same as `copy_buffer_to_color_image`, but copies to one plane of an image with a
+    /// multi-planar format.
+    ///
+    /// `offset` and `extent` are in texels of the plane, whose dimensions are given by
+    /// `Format::plane_extent`, and the buffer must contain data of the format of the plane.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `plane` is out of range of the planes of the format of the image.
+    /// - Same panic reasons as `copy_buffer_to_color_image`.
+    ///
+    /// # Safety
+    ///
+    /// - Care must be taken to respect the rules about secondary command buffers.
+    ///
+    pub unsafe fn copy_buffer_to_image_plane<'a, Pi, S, Sb, Img>(self, source: S, image: &Arc<Img>,
+                                                                plane: u32, mip_level: u32,
+                                                                array_layers_range: Range<u32>,
+                                                                offset: [u32; 3], extent: [u32; 3])
+                                                             -> InnerCommandBufferBuilder<P>
+        where S: Into<BufferSlice<'a, [Pi], Sb>>, Img: ImageContent<Pi> + Image + 'static,
+              Sb: Buffer + 'static
+    {
+        self.copy_buffer_to_image_aspect(source, image, plane_aspect(plane), mip_level,
+                                         array_layers_range, offset, extent)
+    }
+
+    unsafe fn copy_buffer_to_image_aspect<'a, Pi, S, Sb, Img>(mut self, source: S, image: &Arc<Img>,
+                                                             aspect: ImageAspect, mip_level: u32,
+                                                             array_layers_range: Range<u32>,
+                                                             offset: [u32; 3], extent: [u32; 3])
+                                                          -> InnerCommandBufferBuilder<P>
+        where S: Into<BufferSlice<'a, [Pi], Sb>>, Img: ImageContent<Pi> + Image + 'static,
+              Sb: Buffer + 'static
     {
         // FIXME: check the parameters
 
@@ -533,7 +883,14 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
 
         //assert!(image.format().is_float_or_compressed());
 
+        check_image_copy_region(&**image, aspect, mip_level, &array_layers_range, offset,
+                                extent);
+
         let source = source.into();
+        if let Some(size) = image_copy_buffer_size(&**image, aspect, &array_layers_range,
+                                                       extent) {
+            assert!(source.size() >= size);
+        }
         self.add_buffer_resource_outside(source.buffer().clone() as Arc<_>, false,
                                          source.offset() .. source.offset() + source.size(),
                                          vk::PIPELINE_STAGE_TRANSFER_BIT,
@@ -555,7 +912,7 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
                     bufferRowLength: 0,
                     bufferImageHeight: 0,
                     imageSubresource: vk::ImageSubresourceLayers {
-                        aspectMask: vk::IMAGE_ASPECT_COLOR_BIT,
+                        aspectMask: aspect.to_vk_bits(),
                         mipLevel: mip_level,
                         baseArrayLayer: array_layers_range.start,
                         layerCount: array_layers_range.end - array_layers_range.start,
@@ -585,16 +942,64 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
     ///
     /// This operation can be performed by any kind of queue.
     ///
+    /// The third component of `offset` and `extent` designates the range of depth slices to
+    /// read if the image is three-dimensional, and must be respectively 0 and 1 otherwise.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the region is out of range of the image.
+    /// - Panics if the image has a compressed format and the region isn't aligned to blocks.
+    /// - Panics if the buffer is too small for the region.
+    ///
     /// # Safety
     ///
     /// - Care must be taken to respect the rules about secondary command buffers.
     ///
-    pub unsafe fn copy_color_image_to_buffer<'a, Pi, S, Sb, Img>(mut self, dest: S, image: &Arc<Img>,
+    pub unsafe fn copy_color_image_to_buffer<'a, Pi, S, Sb, Img>(self, dest: S, image: &Arc<Img>,
                                                                 mip_level: u32, array_layers_range: Range<u32>,
                                                                 offset: [u32; 3], extent: [u32; 3])
                                                              -> InnerCommandBufferBuilder<P>
         where S: Into<BufferSlice<'a, [Pi], Sb>>, Img: ImageContent<Pi> + Image + 'static,
               Sb: Buffer + 'static
+    {
+        self.copy_image_aspect_to_buffer(dest, image, ImageAspect::Color, mip_level,
+                                         array_layers_range, offset, extent)
+    }
+
+    /// Same as `copy_color_image_to_buffer`, but copies from one plane of an image with a
+    /// multi-planar format.
+    ///
+    /// `offset` and `extent` are in texels of the plane, whose dimensions are given by
+    /// `Format::plane_extent`, and the buffer receives data of the format of the plane.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `plane` is out of range of the planes of the format of the image.
+    /// - Same panic reasons as `copy_color_image_to_buffer`.
+    ///
+    /// # Safety
+    ///
+    /// - Care must be taken to respect the rules about secondary command buffers.
+    ///
+    pub unsafe fn copy_image_plane_to_buffer<'a, Pi, S, Sb, Img>(self, dest: S, image: &Arc<Img>,
+                                                                plane: u32, mip_level: u32,
+                                                                array_layers_range: Range<u32>,
+                                                                offset: [u32; 3], extent: [u32; 3])
+                                                             -> InnerCommandBufferBuilder<P>
+        where S: Into<BufferSlice<'a, [Pi], Sb>>, Img: ImageContent<Pi> + Image + 'static,
+              Sb: Buffer + 'static
+    {
+        self.copy_image_aspect_to_buffer(dest, image, plane_aspect(plane), mip_level,
+                                         array_layers_range, offset, extent)
+    }
+
+    unsafe fn copy_image_aspect_to_buffer<'a, Pi, S, Sb, Img>(mut self, dest: S, image: &Arc<Img>,
+                                                             aspect: ImageAspect, mip_level: u32,
+                                                             array_layers_range: Range<u32>,
+                                                             offset: [u32; 3], extent: [u32; 3])
+                                                          -> InnerCommandBufferBuilder<P>
+        where S: Into<BufferSlice<'a, [Pi], Sb>>, Img: ImageContent<Pi> + Image + 'static,
+              Sb: Buffer + 'static
     {
         // FIXME: check the parameters
 
@@ -602,7 +1007,14 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
 
         //assert!(image.format().is_float_or_compressed());
 
+        check_image_copy_region(&**image, aspect, mip_level, &array_layers_range, offset,
+                                extent);
+
         let dest = dest.into();
+        if let Some(size) = image_copy_buffer_size(&**image, aspect, &array_layers_range,
+                                                       extent) {
+            assert!(dest.size() >= size);
+        }
         self.add_buffer_resource_outside(dest.buffer().clone() as Arc<_>, true,
                                          dest.offset() .. dest.offset() + dest.size(),
                                          vk::PIPELINE_STAGE_TRANSFER_BIT,
@@ -624,7 +1036,7 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
                     bufferRowLength: 0,
                     bufferImageHeight: 0,
                     imageSubresource: vk::ImageSubresourceLayers {
-                        aspectMask: vk::IMAGE_ASPECT_COLOR_BIT,
+                        aspectMask: aspect.to_vk_bits(),
                         mipLevel: mip_level,
                         baseArrayLayer: array_layers_range.start,
                         layerCount: array_layers_range.end - array_layers_range.start,
@@ -748,7 +1160,7 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
     /// Calls `vkCmdDraw`.
     // FIXME: push constants
     pub unsafe fn draw<V, Pv, Pl, L, Rp, Pc>(mut self, pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
-                             vertices: V, dynamic: &DynamicState,
+                             vertices: V, params: &DrawParams, dynamic: &DynamicState,
                              sets: L, push_constants: &Pc) -> InnerCommandBufferBuilder<P>
         where Pv: 'static + VertexSource<V>, L: DescriptorSetsCollection + Send + Sync,
               Pl: 'static + PipelineLayout + Send + Sync, Rp: 'static + Send + Sync,
@@ -760,27 +1172,44 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
 
         let vertices = pipeline.vertex_definition().decode(vertices);
 
-        let offsets = (0 .. vertices.0.len()).map(|_| 0).collect::<SmallVec<[_; 8]>>();
-        let ids = vertices.0.map(|b| {
+        let mut offsets = SmallVec::<[_; 8]>::new();
+        let mut sizes = SmallVec::<[_; 8]>::new();
+        let ids = vertices.0.map(|(b, off)| {
             assert!(b.inner().usage_vertex_buffer());
-            self.add_buffer_resource_inside(b.clone(), false, 0 .. b.size(),
+            assert!(off <= b.size());
+            self.add_buffer_resource_inside(b.clone(), false, off .. b.size(),
                                             vk::PIPELINE_STAGE_VERTEX_INPUT_BIT,
                                             vk::ACCESS_VERTEX_ATTRIBUTE_READ_BIT);
+            offsets.push(off as vk::DeviceSize);
+            sizes.push(b.size() - off);
             b.inner().internal_object()
         }).collect::<SmallVec<[_; 8]>>();
 
+        let (num_vertices, num_instances) = {
+            if self.device.buffer_access_checks() == BufferAccessChecks::Relaxed {
+                relaxed_vertices_instances(pipeline.vertex_bindings(), &sizes,
+                                           (vertices.1, vertices.2))
+            } else {
+                (vertices.1, vertices.2)
+            }
+        };
+
+        let (first_vertex, num_vertices) = draw_range(params.first_vertex, params.vertex_count,
+                                                      num_vertices, "vertices");
+        let (first_instance, num_instances) = draw_range(params.first_instance,
+                                                         params.instance_count, num_instances,
+                                                         "instances");
+
         {
             let mut ids = Some(ids);
             let mut offsets = Some(offsets);
-            let num_vertices = vertices.1 as u32;
-            let num_instances = vertices.2 as u32;
 
             self.render_pass_staging_commands.push(Box::new(move |vk, cmd| {
                 let ids = ids.take().unwrap();
                 let offsets = offsets.take().unwrap();
 
                 vk.CmdBindVertexBuffers(cmd, 0, ids.len() as u32, ids.as_ptr(), offsets.as_ptr());
-                vk.CmdDraw(cmd, num_vertices, num_instances, 0, 0);  // FIXME: params
+                vk.CmdDraw(cmd, num_vertices, num_instances, first_vertex, first_instance);
             }));
         }
 
@@ -790,7 +1219,8 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
     /// Calls `vkCmdDrawIndexed`.
     // FIXME: push constants
     pub unsafe fn draw_indexed<'a, V, Pv, Pl, Rp, L, I, Ib, Ibb, Pc>(mut self, pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
-                                                          vertices: V, indices: Ib, dynamic: &DynamicState,
+                                                          vertices: V, indices: Ib, params: &DrawParams,
+                                                          dynamic: &DynamicState,
                                                           sets: L, push_constants: &Pc) -> InnerCommandBufferBuilder<P>
         where L: DescriptorSetsCollection + Send + Sync,
               Pv: 'static + VertexSource<V>,
@@ -807,30 +1237,59 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
 
         let vertices = pipeline.vertex_definition().decode(vertices);
 
-        let offsets = (0 .. vertices.0.len()).map(|_| 0).collect::<SmallVec<[_; 8]>>();
-        let ids = vertices.0.map(|b| {
+        let mut offsets = SmallVec::<[_; 8]>::new();
+        let mut sizes = SmallVec::<[_; 8]>::new();
+        let ids = vertices.0.map(|(b, off)| {
             assert!(b.inner().usage_vertex_buffer());
-            self.add_buffer_resource_inside(b.clone(), false, 0 .. b.size(),
+            assert!(off <= b.size());
+            self.add_buffer_resource_inside(b.clone(), false, off .. b.size(),
                                             vk::PIPELINE_STAGE_VERTEX_INPUT_BIT,
                                             vk::ACCESS_VERTEX_ATTRIBUTE_READ_BIT);
+            offsets.push(off as vk::DeviceSize);
+            sizes.push(b.size() - off);
             b.inner().internal_object()
         }).collect::<SmallVec<[_; 8]>>();
 
         assert!(indices.buffer().inner().usage_index_buffer());
+        assert!(I::ty().supported_by_device(&self.device));
+        assert_eq!(indices.offset() % I::ty().size(), 0);
+
+        // Without `robustBufferAccess`, we can't know whether the indices are in range of the
+        // vertex buffers unless all the possible values are.
+        if self.device.buffer_access_checks().strict_checks(self.device.enabled_features()) {
+            assert!(params.vertex_offset >= 0,
+                    "The vertex offset can't be negative without robust buffer access");
+            assert!(vertices.1 as u64 > I::ty().max_value() as u64 + params.vertex_offset as u64,
+                    "The vertex buffers are too small for all the possible values of the indices");
+        }
+
+        let num_instances = {
+            if self.device.buffer_access_checks() == BufferAccessChecks::Relaxed {
+                relaxed_vertices_instances(pipeline.vertex_bindings(), &sizes,
+                                           (vertices.1, vertices.2)).1
+            } else {
+                vertices.2
+            }
+        };
 
         self.add_buffer_resource_inside(indices.buffer().clone() as Arc<_>, false,
                                         indices.offset() .. indices.offset() + indices.size(),
                                         vk::PIPELINE_STAGE_VERTEX_INPUT_BIT,
                                         vk::ACCESS_INDEX_READ_BIT);
 
+        let (first_index, num_indices) = draw_range(params.first_index, params.index_count,
+                                                    indices.len(), "indices");
+        let (first_instance, num_instances) = draw_range(params.first_instance,
+                                                         params.instance_count, num_instances,
+                                                         "instances");
+
         {
             let mut ids = Some(ids);
             let mut offsets = Some(offsets);
             let indices_offset = indices.offset() as u64;
-            let indices_len = indices.len() as u32;
             let indices_ty = I::ty() as u32;
             let indices = indices.buffer().inner().internal_object();
-            let num_instances = vertices.2 as u32;
+            let vertex_offset = params.vertex_offset;
 
             self.render_pass_staging_commands.push(Box::new(move |vk, cmd| {
                 let ids = ids.take().unwrap();
@@ -838,7 +1297,8 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
 
                 vk.CmdBindIndexBuffer(cmd, indices, indices_offset, indices_ty);
                 vk.CmdBindVertexBuffers(cmd, 0, ids.len() as u32, ids.as_ptr(), offsets.as_ptr());
-                vk.CmdDrawIndexed(cmd, indices_len, num_instances, 0, 0, 0);  // FIXME: params
+                vk.CmdDrawIndexed(cmd, num_indices, num_instances, first_index, vertex_offset,
+                                  first_instance);
             }));
         }
 
@@ -847,7 +1307,8 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
 
     /// Calls `vkCmdDrawIndirect`.
     // FIXME: push constants
-    pub unsafe fn draw_indirect<I, V, Pv, Pl, L, Rp, Pc>(mut self, buffer: &Arc<I>, pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
+    pub unsafe fn draw_indirect<I, V, Pv, Pl, L, Rp, Pc>(mut self, buffer: &Arc<I>, commands: Range<usize>,
+                             pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
                              vertices: V, dynamic: &DynamicState,
                              sets: L, push_constants: &Pc) -> InnerCommandBufferBuilder<P>
         where Pv: 'static + VertexSource<V>, L: DescriptorSetsCollection + Send + Sync,
@@ -856,20 +1317,32 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
     {
         // FIXME: add buffers to the resources
 
+        // Without `robustBufferAccess`, nothing guarantees that the commands of the indirect
+        // buffer stay in range of the vertex buffers.
+        assert!(!self.device.buffer_access_checks().strict_checks(self.device.enabled_features()),
+                "Indirect draws require the robust_buffer_access feature with \
+                 BufferAccessChecks::Strict");
+
         self.bind_gfx_pipeline_state(pipeline, dynamic, sets, push_constants);
 
         let vertices = pipeline.vertex_definition().decode(vertices);
 
-        let offsets = (0 .. vertices.0.len()).map(|_| 0).collect::<SmallVec<[_; 8]>>();
-        let ids = vertices.0.map(|b| {
+        let mut offsets = SmallVec::<[_; 8]>::new();
+        let ids = vertices.0.map(|(b, off)| {
             assert!(b.inner().usage_vertex_buffer());
-            self.add_buffer_resource_inside(b.clone(), false, 0 .. b.size(),
+            assert!(off <= b.size());
+            self.add_buffer_resource_inside(b.clone(), false, off .. b.size(),
                                             vk::PIPELINE_STAGE_VERTEX_INPUT_BIT,
                                             vk::ACCESS_VERTEX_ATTRIBUTE_READ_BIT);
+            offsets.push(off as vk::DeviceSize);
             b.inner().internal_object()
         }).collect::<SmallVec<[_; 8]>>();
 
-        self.add_buffer_resource_inside(buffer.clone(), false, 0 .. buffer.size(),
+        assert!(commands.start <= commands.end && commands.end <= buffer.len());
+        let stride = mem::size_of::<DrawIndirectCommand>();
+        let range = commands.start * stride .. commands.end * stride;
+
+        self.add_buffer_resource_inside(buffer.clone(), false, range.clone(),
                                         vk::PIPELINE_STAGE_DRAW_INDIRECT_BIT,
                                         vk::ACCESS_INDIRECT_COMMAND_READ_BIT);
 
@@ -877,55 +1350,224 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
             let mut ids = Some(ids);
             let mut offsets = Some(offsets);
             let buffer_internal = buffer.inner().internal_object();
-            let buffer_draw_count = buffer.len() as u32;
-            let buffer_size = buffer.size() as u32;
+            let draw_count = (commands.end - commands.start) as u32;
 
             self.render_pass_staging_commands.push(Box::new(move |vk, cmd| {
                 let ids = ids.take().unwrap();
                 let offsets = offsets.take().unwrap();
 
                 vk.CmdBindVertexBuffers(cmd, 0, ids.len() as u32, ids.as_ptr(), offsets.as_ptr());
-                vk.CmdDrawIndirect(cmd, buffer_internal, 0, buffer_draw_count,
-                                   mem::size_of::<DrawIndirectCommand>() as u32);
+                vk.CmdDrawIndirect(cmd, buffer_internal, range.start as vk::DeviceSize,
+                                   draw_count, stride as u32);
             }));
         }
 
         self
     }
 
-    fn bind_compute_pipeline_state<Pl, L, Pc>(&mut self, pipeline: &Arc<ComputePipeline<Pl>>, sets: L,
-                                          push_constants: &Pc)
-        where L: DescriptorSetsCollection,
-              Pl: 'static + PipelineLayout + Send + Sync,
-              Pc: 'static + Clone + Send + Sync
+    /// Calls `vkCmdDrawIndirectCountKHR`.
+    ///
+    /// The number of commands to read from `buffer` is the 32-bit value at `count_offset` in
+    /// `count_buffer`, clamped to `max_draw_count`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the `khr_draw_indirect_count` extension isn't enabled.
+    /// - Panics if the device uses `BufferAccessChecks::Strict` without the
+    ///   `robust_buffer_access` feature.
+    /// - Panics if `max_draw_count` is larger than the number of commands of `buffer`.
+    /// - Panics if the count isn't in `count_buffer` or if `count_offset` isn't a multiple of 4.
+    /// - Panics if the buffers weren't created with the `indirect_buffer` usage.
+    ///
+    pub unsafe fn draw_indirect_count<I, C, V, Pv, Pl, L, Rp, Pc>(mut self, buffer: &Arc<I>,
+                             count_buffer: &Arc<C>, count_offset: usize, max_draw_count: u32,
+                             pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
+                             vertices: V, dynamic: &DynamicState,
+                             sets: L, push_constants: &Pc) -> InnerCommandBufferBuilder<P>
+        where Pv: 'static + VertexSource<V>, L: DescriptorSetsCollection + Send + Sync,
+              Pl: 'static + PipelineLayout + Send + Sync, Rp: 'static + Send + Sync, Pc: 'static + Clone + Send + Sync,
+              I: 'static + TypedBuffer<Content = [DrawIndirectCommand]>, C: Buffer + 'static
     {
-        unsafe {
-            //assert!(sets.is_compatible_with(pipeline.layout()));
-
-            if self.current_compute_pipeline != Some(pipeline.internal_object()) {
-                self.keep_alive.push(pipeline.clone());
-                let pipeline = pipeline.internal_object();
-                self.staging_commands.push(Box::new(move |vk, cmd| {
-                    vk.CmdBindPipeline(cmd, vk::PIPELINE_BIND_POINT_COMPUTE,
-                                       pipeline);
-                }));
-                self.current_compute_pipeline = Some(pipeline);
-            }
+        check_indirect_count(&self.device, max_draw_count, buffer.len());
+        assert!(buffer.inner().usage_indirect_buffer());
 
-            let descriptor_sets = DescriptorSetsCollection::list(&sets).collect::<SmallVec<[_; 32]>>();
+        self.bind_gfx_pipeline_state(pipeline, dynamic, sets, push_constants);
 
-            for set in descriptor_sets.iter() {
-                for &(ref img, block, layout) in set.inner().images_list().iter() {
-                    self.add_image_resource_outside(img.clone(), 0 .. 1 /* FIXME */, 0 .. 1 /* FIXME */,
-                                                   false, layout, vk::PIPELINE_STAGE_ALL_COMMANDS_BIT /* FIXME */,
-                                                   vk::ACCESS_SHADER_READ_BIT | vk::ACCESS_UNIFORM_READ_BIT /* TODO */);
-                }
-                for buffer in set.inner().buffers_list().iter() {
-                    self.add_buffer_resource_outside(buffer.clone(), false, 0 .. buffer.size() /* TODO */,
-                                                    vk::PIPELINE_STAGE_ALL_COMMANDS_BIT /* FIXME */,
-                                                    vk::ACCESS_SHADER_READ_BIT | vk::ACCESS_UNIFORM_READ_BIT /* TODO */);
-                }
-            }
+        let vertices = pipeline.vertex_definition().decode(vertices);
+
+        let mut offsets = SmallVec::<[_; 8]>::new();
+        let ids = vertices.0.map(|(b, off)| {
+            assert!(b.inner().usage_vertex_buffer());
+            assert!(off <= b.size());
+            self.add_buffer_resource_inside(b.clone(), false, off .. b.size(),
+                                            vk::PIPELINE_STAGE_VERTEX_INPUT_BIT,
+                                            vk::ACCESS_VERTEX_ATTRIBUTE_READ_BIT);
+            offsets.push(off as vk::DeviceSize);
+            b.inner().internal_object()
+        }).collect::<SmallVec<[_; 8]>>();
+
+        let stride = mem::size_of::<DrawIndirectCommand>();
+        self.add_buffer_resource_inside(buffer.clone(), false,
+                                        0 .. max_draw_count as usize * stride,
+                                        vk::PIPELINE_STAGE_DRAW_INDIRECT_BIT,
+                                        vk::ACCESS_INDIRECT_COMMAND_READ_BIT);
+        self.add_draw_count_resource(count_buffer, count_offset);
+
+        {
+            let mut ids = Some(ids);
+            let mut offsets = Some(offsets);
+            let buffer_internal = buffer.inner().internal_object();
+            let count_internal = count_buffer.inner().internal_object();
+
+            self.render_pass_staging_commands.push(Box::new(move |vk, cmd| {
+                let ids = ids.take().unwrap();
+                let offsets = offsets.take().unwrap();
+
+                vk.CmdBindVertexBuffers(cmd, 0, ids.len() as u32, ids.as_ptr(), offsets.as_ptr());
+                vk.CmdDrawIndirectCountKHR(cmd, buffer_internal, 0, count_internal,
+                                           count_offset as vk::DeviceSize, max_draw_count,
+                                           stride as u32);
+            }));
+        }
+
+        self
+    }
+
+    /// Calls `vkCmdDrawIndexedIndirectCountKHR`.
+    ///
+    /// The number of commands to read from `buffer` is the 32-bit value at `count_offset` in
+    /// `count_buffer`, clamped to `max_draw_count`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics for the same reasons as `draw_indirect_count`.
+    /// - Panics if the buffer of `indices` wasn't created with the `index_buffer` usage.
+    /// - Panics if the indices are `u8` and the `ext_index_type_uint8` extension isn't enabled.
+    ///
+    pub unsafe fn draw_indexed_indirect_count<'a, Ic, C, V, Pv, Pl, L, Rp, I, Ib, Ibb, Pc>(mut self,
+                             buffer: &Arc<Ic>, count_buffer: &Arc<C>, count_offset: usize,
+                             max_draw_count: u32, pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
+                             vertices: V, indices: Ib, dynamic: &DynamicState,
+                             sets: L, push_constants: &Pc) -> InnerCommandBufferBuilder<P>
+        where Pv: 'static + VertexSource<V>, L: DescriptorSetsCollection + Send + Sync,
+              Pl: 'static + PipelineLayout + Send + Sync, Rp: 'static + Send + Sync, Pc: 'static + Clone + Send + Sync,
+              Ib: Into<BufferSlice<'a, [I], Ibb>>, I: 'static + Index, Ibb: Buffer + 'static,
+              Ic: 'static + TypedBuffer<Content = [DrawIndexedIndirectCommand]>, C: Buffer + 'static
+    {
+        check_indirect_count(&self.device, max_draw_count, buffer.len());
+        assert!(buffer.inner().usage_indirect_buffer());
+
+        self.bind_gfx_pipeline_state(pipeline, dynamic, sets, push_constants);
+
+        let indices = indices.into();
+        let vertices = pipeline.vertex_definition().decode(vertices);
+
+        let mut offsets = SmallVec::<[_; 8]>::new();
+        let ids = vertices.0.map(|(b, off)| {
+            assert!(b.inner().usage_vertex_buffer());
+            assert!(off <= b.size());
+            self.add_buffer_resource_inside(b.clone(), false, off .. b.size(),
+                                            vk::PIPELINE_STAGE_VERTEX_INPUT_BIT,
+                                            vk::ACCESS_VERTEX_ATTRIBUTE_READ_BIT);
+            offsets.push(off as vk::DeviceSize);
+            b.inner().internal_object()
+        }).collect::<SmallVec<[_; 8]>>();
+
+        assert!(indices.buffer().inner().usage_index_buffer());
+        assert!(I::ty().supported_by_device(&self.device));
+        assert_eq!(indices.offset() % I::ty().size(), 0);
+
+        self.add_buffer_resource_inside(indices.buffer().clone() as Arc<_>, false,
+                                        indices.offset() .. indices.offset() + indices.size(),
+                                        vk::PIPELINE_STAGE_VERTEX_INPUT_BIT,
+                                        vk::ACCESS_INDEX_READ_BIT);
+
+        let stride = mem::size_of::<DrawIndexedIndirectCommand>();
+        self.add_buffer_resource_inside(buffer.clone(), false,
+                                        0 .. max_draw_count as usize * stride,
+                                        vk::PIPELINE_STAGE_DRAW_INDIRECT_BIT,
+                                        vk::ACCESS_INDIRECT_COMMAND_READ_BIT);
+        self.add_draw_count_resource(count_buffer, count_offset);
+
+        {
+            let mut ids = Some(ids);
+            let mut offsets = Some(offsets);
+            let indices_offset = indices.offset() as u64;
+            let indices_ty = I::ty() as u32;
+            let indices = indices.buffer().inner().internal_object();
+            let buffer_internal = buffer.inner().internal_object();
+            let count_internal = count_buffer.inner().internal_object();
+
+            self.render_pass_staging_commands.push(Box::new(move |vk, cmd| {
+                let ids = ids.take().unwrap();
+                let offsets = offsets.take().unwrap();
+
+                vk.CmdBindIndexBuffer(cmd, indices, indices_offset, indices_ty);
+                vk.CmdBindVertexBuffers(cmd, 0, ids.len() as u32, ids.as_ptr(), offsets.as_ptr());
+                vk.CmdDrawIndexedIndirectCountKHR(cmd, buffer_internal, 0, count_internal,
+                                                  count_offset as vk::DeviceSize, max_draw_count,
+                                                  stride as u32);
+            }));
+        }
+
+        self
+    }
+
+    // Checks the buffer that contains the number of draws of an indirect draw and adds it to the
+    // resources of the render pass.
+    fn add_draw_count_resource<C>(&mut self, count_buffer: &Arc<C>, count_offset: usize)
+        where C: Buffer + 'static
+    {
+        assert!(count_offset % 4 == 0);
+        assert!(count_offset + 4 <= count_buffer.size());
+        assert!(count_buffer.inner().usage_indirect_buffer());
+
+        self.add_buffer_resource_inside(count_buffer.clone() as Arc<_>, false,
+                                        count_offset .. count_offset + 4,
+                                        vk::PIPELINE_STAGE_DRAW_INDIRECT_BIT,
+                                        vk::ACCESS_INDIRECT_COMMAND_READ_BIT);
+    }
+
+    fn bind_compute_pipeline_state<Pl, L, Pc>(&mut self, pipeline: &Arc<ComputePipeline<Pl>>, sets: L,
+                                          push_constants: &Pc)
+        where L: DescriptorSetsCollection,
+              Pl: 'static + PipelineLayout + Send + Sync,
+              Pc: 'static + Clone + Send + Sync
+    {
+        unsafe {
+            //assert!(sets.is_compatible_with(pipeline.layout()));
+
+            if self.current_compute_pipeline != Some(pipeline.internal_object()) {
+                self.keep_alive.push(pipeline.clone());
+                let pipeline = pipeline.internal_object();
+                self.staging_commands.push(Box::new(move |vk, cmd| {
+                    vk.CmdBindPipeline(cmd, vk::PIPELINE_BIND_POINT_COMPUTE,
+                                       pipeline);
+                }));
+                self.current_compute_pipeline = Some(pipeline);
+            }
+
+            sets.check_dynamic_offsets(&self.device);
+            let dynamic_offsets = sets.dynamic_offsets().iter().cloned().collect::<SmallVec<[u32; 16]>>();
+            let descriptor_sets = DescriptorSetsCollection::list(&sets).collect::<SmallVec<[_; 32]>>();
+
+            for set in descriptor_sets.iter() {
+                for &(ref img, block, layout) in set.inner().images_list().iter() {
+                    self.add_image_resource_outside(img.clone(), 0 .. 1 /* FIXME */, 0 .. 1 /* FIXME */,
+                                                   false, layout, vk::PIPELINE_STAGE_ALL_COMMANDS_BIT /* FIXME */,
+                                                   vk::ACCESS_SHADER_READ_BIT | vk::ACCESS_UNIFORM_READ_BIT /* TODO */);
+                }
+                for &(ref img, block, layout) in set.inner().storage_images_list().iter() {
+                    self.add_image_resource_outside(img.clone(), 0 .. 1 /* FIXME */, 0 .. 1 /* FIXME */,
+                                                   true, layout, vk::PIPELINE_STAGE_ALL_COMMANDS_BIT /* FIXME */,
+                                                   vk::ACCESS_SHADER_READ_BIT | vk::ACCESS_SHADER_WRITE_BIT);
+                }
+                for buffer in set.inner().buffers_list().iter() {
+                    self.add_buffer_resource_outside(buffer.clone(), false, 0 .. buffer.size() /* TODO */,
+                                                    vk::PIPELINE_STAGE_ALL_COMMANDS_BIT /* FIXME */,
+                                                    vk::ACCESS_SHADER_READ_BIT | vk::ACCESS_UNIFORM_READ_BIT /* TODO */);
+                }
+            }
 
             for d in descriptor_sets.iter() { self.keep_alive.push(mem::transmute(d.clone()) /* FIXME: */); }
             let mut descriptor_sets = Some(descriptor_sets.into_iter().map(|set| set.inner().internal_object()).collect::<SmallVec<[_; 32]>>());
@@ -937,19 +1579,28 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
                     let descriptor_sets = descriptor_sets.take().unwrap();
                     vk.CmdBindDescriptorSets(cmd, vk::PIPELINE_BIND_POINT_COMPUTE,
                                              pipeline, 0, descriptor_sets.len() as u32,
-                                             descriptor_sets.as_ptr(), 0, ptr::null());   // FIXME: dynamic offsets
+                                             descriptor_sets.as_ptr(), dynamic_offsets.len() as u32,
+                                             dynamic_offsets.as_ptr());
                 }));
             }
 
             if mem::size_of_val(push_constants) >= 1 {
-                let pipeline = PipelineLayout::inner(&**pipeline.layout()).internal_object();
+                assert!(PipelineLayoutPushConstantsCompatible::is_compatible(&**pipeline.layout(),
+                                                                             push_constants),
+                        "The push constants are not in the push constant ranges of the layout");
+                let layout = PipelineLayout::inner(&**pipeline.layout());
                 let size = mem::size_of_val(push_constants);
+                let updates = push_constants_updates(layout.push_constant_ranges(), size);
+                let pipeline = layout.internal_object();
                 let push_constants = push_constants.clone();
                 assert!((size % 4) == 0);
 
                 self.staging_commands.push(Box::new(move |vk, cmd| {
-                    vk.CmdPushConstants(cmd, pipeline, 0x7fffffff, 0, size as u32,
-                                        &push_constants as *const Pc as *const _);
+                    let data = &push_constants as *const Pc as *const u8;
+                    for &(offset, size, stages) in updates.iter() {
+                        vk.CmdPushConstants(cmd, pipeline, stages.into(), offset as u32,
+                                            size as u32, data.offset(offset as isize) as *const _);
+                    }
                 }));
             }
         }
@@ -974,7 +1625,11 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
 
             if let Some(line_width) = dynamic.line_width {
                 assert!(pipeline.has_dynamic_line_width());
-                // TODO: check limits
+                if line_width != 1.0 {
+                    assert!(self.device.enabled_features().wide_lines);
+                    let range = self.device.physical_device().limits().line_width_range();
+                    assert!(line_width >= range[0] && line_width <= range[1]);
+                }
                 if self.current_dynamic_state.line_width != Some(line_width) {
                     self.render_pass_staging_commands.push(Box::new(move |vk, cmd| {
                         vk.CmdSetLineWidth(cmd, line_width);
@@ -1014,13 +1669,76 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
                 assert!(!pipeline.has_dynamic_scissors());
             }
 
+            if let Some((min, max)) = dynamic.depth_bounds {
+                assert!(pipeline.has_dynamic_depth_bounds());
+                if let Err(err) = check_depth_bounds(min, max) {
+                    panic!("invalid dynamic depth bounds: {}", err);
+                }
+                if self.current_dynamic_state.depth_bounds != Some((min, max)) {
+                    self.render_pass_staging_commands.push(Box::new(move |vk, cmd| {
+                        vk.CmdSetDepthBounds(cmd, min, max);
+                    }));
+                    self.current_dynamic_state.depth_bounds = Some((min, max));
+                }
+            } else {
+                assert!(!pipeline.has_dynamic_depth_bounds());
+            }
+
+            if let Some(value) = dynamic.stencil_compare_mask {
+                assert!(pipeline.has_dynamic_stencil_compare_mask());
+                if self.current_dynamic_state.stencil_compare_mask != Some(value) {
+                    self.render_pass_staging_commands.push(Box::new(move |vk, cmd| {
+                        set_stencil_value(value, |face, v| vk.CmdSetStencilCompareMask(cmd, face, v));
+                    }));
+                    self.current_dynamic_state.stencil_compare_mask = Some(value);
+                }
+            } else {
+                assert!(!pipeline.has_dynamic_stencil_compare_mask());
+            }
+
+            if let Some(value) = dynamic.stencil_write_mask {
+                assert!(pipeline.has_dynamic_stencil_write_mask());
+                if self.current_dynamic_state.stencil_write_mask != Some(value) {
+                    self.render_pass_staging_commands.push(Box::new(move |vk, cmd| {
+                        set_stencil_value(value, |face, v| vk.CmdSetStencilWriteMask(cmd, face, v));
+                    }));
+                    self.current_dynamic_state.stencil_write_mask = Some(value);
+                }
+            } else {
+                assert!(!pipeline.has_dynamic_stencil_write_mask());
+            }
+
+            if let Some(value) = dynamic.stencil_reference {
+                assert!(pipeline.has_dynamic_stencil_reference());
+                if self.current_dynamic_state.stencil_reference != Some(value) {
+                    self.render_pass_staging_commands.push(Box::new(move |vk, cmd| {
+                        set_stencil_value(value, |face, v| vk.CmdSetStencilReference(cmd, face, v));
+                    }));
+                    self.current_dynamic_state.stencil_reference = Some(value);
+                }
+            } else {
+                assert!(!pipeline.has_dynamic_stencil_reference());
+            }
+
+            sets.check_dynamic_offsets(&self.device);
+            let dynamic_offsets = sets.dynamic_offsets().iter().cloned().collect::<SmallVec<[u32; 16]>>();
             let descriptor_sets = DescriptorSetsCollection::list(&sets).collect::<SmallVec<[_; 32]>>();
             for set in descriptor_sets.iter() {
+                // Input attachments are framebuffer attachments, therefore their layout is
+                // handled by the render pass and they are already part of the resources.
+                for &(ref img, layout) in set.inner().input_attachments_list().iter() {
+                    self.check_input_attachment(img, layout);
+                }
                 for &(ref img, block, layout) in set.inner().images_list().iter() {
                     self.add_image_resource_inside(img.clone(), 0 .. 1 /* FIXME */, 0 .. 1 /* FIXME */,
                                                    false, layout, layout, vk::PIPELINE_STAGE_ALL_COMMANDS_BIT /* FIXME */,
                                                    vk::ACCESS_SHADER_READ_BIT | vk::ACCESS_UNIFORM_READ_BIT /* TODO */);
                 }
+                for &(ref img, block, layout) in set.inner().storage_images_list().iter() {
+                    self.add_image_resource_inside(img.clone(), 0 .. 1 /* FIXME */, 0 .. 1 /* FIXME */,
+                                                   true, layout, layout, vk::PIPELINE_STAGE_ALL_COMMANDS_BIT /* FIXME */,
+                                                   vk::ACCESS_SHADER_READ_BIT | vk::ACCESS_SHADER_WRITE_BIT);
+                }
                 for buffer in set.inner().buffers_list().iter() {
                     self.add_buffer_resource_inside(buffer.clone(), false, 0 .. buffer.size() /* TODO */,
                                                     vk::PIPELINE_STAGE_ALL_COMMANDS_BIT /* FIXME */,
@@ -1031,167 +1749,621 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
             let mut descriptor_sets = Some(descriptor_sets.into_iter().map(|set| set.inner().internal_object()).collect::<SmallVec<[_; 32]>>());
 
             if mem::size_of_val(push_constants) >= 1 {
-                let pipeline = PipelineLayout::inner(&**pipeline.layout()).internal_object();
+                assert!(PipelineLayoutPushConstantsCompatible::is_compatible(&**pipeline.layout(),
+                                                                             push_constants),
+                        "The push constants are not in the push constant ranges of the layout");
+                let layout = PipelineLayout::inner(&**pipeline.layout());
                 let size = mem::size_of_val(push_constants);
+                let updates = push_constants_updates(layout.push_constant_ranges(), size);
+                let pipeline = layout.internal_object();
                 let push_constants = push_constants.clone();
                 assert!((size % 4) == 0);
 
                 self.render_pass_staging_commands.push(Box::new(move |vk, cmd| {
-                    vk.CmdPushConstants(cmd, pipeline, 0x7fffffff, 0, size as u32,
-                                        &push_constants as *const Pc as *const _);
+                    let data = &push_constants as *const Pc as *const u8;
+                    for &(offset, size, stages) in updates.iter() {
+                        vk.CmdPushConstants(cmd, pipeline, stages.into(), offset as u32,
+                                            size as u32, data.offset(offset as isize) as *const _);
+                    }
                 }));
             }
 
-            // FIXME: input attachments of descriptor sets have to be checked against input
-            //        attachments of the render pass
+            // TODO: shouldn't rebind everything every time
+            if !descriptor_sets.as_ref().unwrap().is_empty() {
+                let pipeline = PipelineLayout::inner(&**pipeline.layout()).internal_object();
+                self.render_pass_staging_commands.push(Box::new(move |vk, cmd| {
+                    let descriptor_sets = descriptor_sets.take().unwrap();
+                    vk.CmdBindDescriptorSets(cmd, vk::PIPELINE_BIND_POINT_GRAPHICS, pipeline,
+                                             0, descriptor_sets.len() as u32,
+                                             descriptor_sets.as_ptr(), dynamic_offsets.len() as u32,
+                                             dynamic_offsets.as_ptr());
+                }));
+            }
+        }
+    }
+
+    /// Calls `vkCmdBeginRenderPass`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the framebuffer is not compatible with the renderpass.
+    /// - Panics if a clear value doesn't match the format of its attachment.
+    ///
+    /// # Safety
+    ///
+    /// - Care must be taken to respect the rules about secondary command buffers.
+    ///
+    #[inline]
+    pub unsafe fn begin_renderpass<R, F>(mut self, render_pass: &Arc<R>,
+                                         framebuffer: &Arc<Framebuffer<F>>,
+                                         secondary_cmd_buffers: bool,
+                                         clear_values: &[ClearValue]) -> InnerCommandBufferBuilder<P>
+        where R: RenderPass + 'static, F: RenderPass + RenderPassDesc + 'static
+    {
+        debug_assert!(self.render_pass_staging_commands.is_empty());
+        debug_assert!(self.render_pass_staging_required_buffer_accesses.is_empty());
+        debug_assert!(self.render_pass_staging_required_image_accesses.is_empty());
+
+        assert!(framebuffer.is_compatible_with(render_pass));
+
+        // Integer attachments would otherwise silently reinterpret the bits of the clear value.
+        let attachments = framebuffer.render_pass().attachments();
+        for (num, (attachment, value)) in attachments.zip(clear_values).enumerate() {
+            if *value == ClearValue::None {
+                continue;
+            }
+
+            assert!(value.is_compatible_with(attachment.format), "The clear value {:?} doesn't \
+                    match the format {:?} of attachment {}", value, attachment.format, num);
+        }
+
+        self.keep_alive.push(framebuffer.clone() as Arc<_>);
+        self.keep_alive.push(render_pass.clone() as Arc<_>);
+
+        let clear_values = clear_values.iter().map(|value| {
+            match *value {
+                ClearValue::None => vk::ClearValue::color({
+                    vk::ClearColorValue::float32([0.0, 0.0, 0.0, 0.0])
+                }),
+                ClearValue::Float(data) => vk::ClearValue::color(vk::ClearColorValue::float32(data)),
+                ClearValue::Int(data) => vk::ClearValue::color(vk::ClearColorValue::int32(data)),
+                ClearValue::Uint(data) => vk::ClearValue::color(vk::ClearColorValue::uint32(data)),
+                ClearValue::Depth(d) => vk::ClearValue::depth_stencil({
+                    vk::ClearDepthStencilValue { depth: d, stencil: 0 }
+                }),
+                ClearValue::Stencil(s) => vk::ClearValue::depth_stencil({
+                    vk::ClearDepthStencilValue { depth: 0.0, stencil: s }
+                }),
+                ClearValue::DepthStencil((d, s)) => vk::ClearValue::depth_stencil({
+                    vk::ClearDepthStencilValue { depth: d, stencil: s }
+                }),
+            }
+        }).collect::<SmallVec<[_; 16]>>();
+
+        for &(ref attachment, ref image, initial_layout, final_layout) in framebuffer.attachments() {
+            self.keep_alive.push(mem::transmute(attachment.clone()) /* FIXME: */);
+
+            let stages = vk::PIPELINE_STAGE_FRAGMENT_SHADER_BIT |
+                         vk::PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT |
+                         vk::PIPELINE_STAGE_EARLY_FRAGMENT_TESTS_BIT |
+                         vk::PIPELINE_STAGE_LATE_FRAGMENT_TESTS_BIT; // FIXME:
+
+            let accesses = vk::ACCESS_COLOR_ATTACHMENT_READ_BIT |
+                           vk::ACCESS_COLOR_ATTACHMENT_WRITE_BIT |
+                           vk::ACCESS_DEPTH_STENCIL_ATTACHMENT_READ_BIT |
+                           vk::ACCESS_DEPTH_STENCIL_ATTACHMENT_WRITE_BIT |
+                           vk::ACCESS_INPUT_ATTACHMENT_READ_BIT;       // FIXME:
+
+            // Only the subresources covered by the view are used by the render pass.
+            let view = attachment.inner();
+            self.add_image_resource_inside(image.clone(), view.mipmap_levels(),
+                                           view.array_layers(), true,
+                                           initial_layout, final_layout, stages, accesses);
+        }
+
+        self.render_pass_input_attachments = framebuffer.render_pass().passes().map(|pass| {
+            pass.input_attachments.iter().map(|&(num, layout)| {
+                (framebuffer.attachments()[num].1.clone(), layout)
+            }).collect()
+        }).collect();
+        self.current_subpass = 0;
+
+        {
+            let mut clear_values = Some(clear_values);
+            let render_pass = render_pass.inner().internal_object();
+            let (fw, fh) = (framebuffer.width(), framebuffer.height());
+            let framebuffer = framebuffer.internal_object();
+
+            self.render_pass_staging_commands.push(Box::new(move |vk, cmd| {
+                let clear_values = clear_values.take().unwrap();
+
+                let infos = vk::RenderPassBeginInfo {
+                    sType: vk::STRUCTURE_TYPE_RENDER_PASS_BEGIN_INFO,
+                    pNext: ptr::null(),
+                    renderPass: render_pass,
+                    framebuffer: framebuffer,
+                    renderArea: vk::Rect2D {                // TODO: let user customize
+                        offset: vk::Offset2D { x: 0, y: 0 },
+                        extent: vk::Extent2D {
+                            width: fw,
+                            height: fh,
+                        },
+                    },
+                    clearValueCount: clear_values.len() as u32,
+                    pClearValues: clear_values.as_ptr(),
+                };
+
+                let content = if secondary_cmd_buffers {
+                    vk::SUBPASS_CONTENTS_SECONDARY_COMMAND_BUFFERS
+                } else {
+                    vk::SUBPASS_CONTENTS_INLINE
+                };
+
+                vk.CmdBeginRenderPass(cmd, &infos, content);
+            }));
+        }
+
+        self
+    }
+
+    #[inline]
+    pub unsafe fn next_subpass(mut self, secondary_cmd_buffers: bool) -> InnerCommandBufferBuilder<P> {
+        debug_assert!(!self.render_pass_staging_commands.is_empty());
+
+        let content = if secondary_cmd_buffers {
+            vk::SUBPASS_CONTENTS_SECONDARY_COMMAND_BUFFERS
+        } else {
+            vk::SUBPASS_CONTENTS_INLINE
+        };
+
+        self.render_pass_staging_commands.push(Box::new(move |vk, cmd| {
+            vk.CmdNextSubpass(cmd, content);
+        }));
+
+        self.current_subpass += 1;
+        self
+    }
+
+    /// Ends the current render pass by calling `vkCmdEndRenderPass`.
+    ///
+    /// # Safety
+    ///
+    /// Assumes that you're inside a render pass and that all subpasses have been processed.
+    ///
+    #[inline]
+    pub unsafe fn end_renderpass(mut self) -> InnerCommandBufferBuilder<P> {
+        debug_assert!(!self.render_pass_staging_commands.is_empty());
+        self.flush_render_pass();
+        self.staging_commands.push(Box::new(move |vk, cmd| {
+            vk.CmdEndRenderPass(cmd);
+        }));
+        self.render_pass_input_attachments.clear();
+        self
+    }
+
+    /// Calls `vkCmdResetQueryPool`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `slots` is out of range of the pool.
+    ///
+    /// # Safety
+    ///
+    /// - Care must be taken to respect the rules about secondary command buffers.
+    ///
+    pub unsafe fn reset_query_pool<Q>(mut self, pool: &Arc<Q>, slots: Range<u32>)
+                                      -> InnerCommandBufferBuilder<P>
+        where Q: QueryPool
+    {
+        debug_assert!(self.render_pass_staging_commands.is_empty());
+
+        assert!(slots.start <= slots.end);
+        assert!(slots.end <= pool.inner().num_slots());
+
+        self.keep_alive.push(pool.clone() as Arc<_>);
+
+        {
+            let pool = pool.inner().internal_object();
+            self.staging_commands.push(Box::new(move |vk, cmd| {
+                vk.CmdResetQueryPool(cmd, pool, slots.start, slots.end - slots.start);
+            }));
+        }
+
+        self
+    }
+
+    /// Calls `vkCmdBeginQuery`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `slot` is out of range of the pool.
+    /// - Panics if `precise` is true and the pool is not an occlusion pool, or if the
+    ///   `occlusion_query_precise` feature isn't enabled.
+    /// - Panics if the queue family doesn't support graphics or compute operations.
+    ///
+    /// # Safety
+    ///
+    /// - The slot must have been reset and must not be active.
+    /// - Care must be taken to respect the rules about secondary command buffers.
+    ///
+    pub unsafe fn begin_query<Q>(mut self, pool: &Arc<Q>, slot: u32, precise: bool)
+                                 -> InnerCommandBufferBuilder<P>
+        where Q: QueryPool
+    {
+        assert!(slot < pool.inner().num_slots());
+
+        if precise {
+            match pool.inner().ty() {
+                QueryType::Occlusion => (),
+                _ => panic!("Precise queries are only allowed for occlusion queries")
+            }
+
+            assert!(self.device.enabled_features().occlusion_query_precise);
+            self.precise_queries.push((pool.inner().internal_object(), slot));
+        }
+
+        {
+            let queue_family = self.pool.as_ref().unwrap().queue_family();
+            assert!(queue_family.supports_graphics() || queue_family.supports_compute());
+        }
+
+        self.keep_alive.push(pool.clone() as Arc<_>);
+
+        let flags = if precise { vk::QUERY_CONTROL_PRECISE_BIT } else { 0 };
+        let pool = pool.inner().internal_object();
+        let command = Box::new(move |vk: &vk::DevicePointers, cmd| {
+            vk.CmdBeginQuery(cmd, pool, slot, flags);
+        });
+
+        if self.is_secondary_graphics || !self.render_pass_staging_commands.is_empty() {
+            self.render_pass_staging_commands.push(command);
+        } else {
+            self.staging_commands.push(command);
+        }
+
+        self
+    }
+
+    /// Calls `vkCmdEndQuery`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `slot` is out of range of the pool.
+    ///
+    /// # Safety
+    ///
+    /// - The query must be active and must have been started in the same subpass.
+    /// - Care must be taken to respect the rules about secondary command buffers.
+    ///
+    pub unsafe fn end_query<Q>(mut self, pool: &Arc<Q>, slot: u32)
+                               -> InnerCommandBufferBuilder<P>
+        where Q: QueryPool
+    {
+        assert!(slot < pool.inner().num_slots());
+
+        let pool = pool.inner().internal_object();
+        let command = Box::new(move |vk: &vk::DevicePointers, cmd| {
+            vk.CmdEndQuery(cmd, pool, slot);
+        });
+
+        if self.is_secondary_graphics || !self.render_pass_staging_commands.is_empty() {
+            self.render_pass_staging_commands.push(command);
+        } else {
+            self.staging_commands.push(command);
+        }
+
+        self
+    }
+
+    /// Calls `vkCmdWriteTimestamp`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the pool is not a timestamp pool or if `slot` is out of range of the pool.
+    /// - Panics if `stage` doesn't contain exactly one stage.
+    /// - Panics if the queue family doesn't support timestamps.
+    ///
+    /// # Safety
+    ///
+    /// - The slot must have been reset.
+    /// - Care must be taken to respect the rules about secondary command buffers.
+    ///
+    pub unsafe fn write_timestamp<Q>(mut self, pool: &Arc<Q>, stage: PipelineStages, slot: u32)
+                                     -> InnerCommandBufferBuilder<P>
+        where Q: QueryPool
+    {
+        match pool.inner().ty() {
+            QueryType::Timestamp => (),
+            _ => panic!("Timestamps can only be written in a timestamp query pool")
+        }
+
+        assert!(slot < pool.inner().num_slots());
+        assert!(self.pool.as_ref().unwrap().queue_family().supports_timestamps());
+
+        let stage: vk::PipelineStageFlagBits = stage.into();
+        assert_eq!(stage.count_ones(), 1);
+
+        self.keep_alive.push(pool.clone() as Arc<_>);
+
+        let pool = pool.inner().internal_object();
+        let command = Box::new(move |vk: &vk::DevicePointers, cmd| {
+            vk.CmdWriteTimestamp(cmd, stage, pool, slot);
+        });
+
+        if self.is_secondary_graphics || !self.render_pass_staging_commands.is_empty() {
+            self.render_pass_staging_commands.push(command);
+        } else {
+            self.staging_commands.push(command);
+        }
+
+        self
+    }
+
+    /// Calls `vkCmdCopyQueryPoolResults`.
+    ///
+    /// The results are written as 64-bit values starting at `offset`. If `with_availability` is
+    /// true, the availability of each query is written after its values.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `slots` is out of range of the pool.
+    /// - Panics if the results don't fit in the buffer or if `offset` is not a multiple of 8.
+    /// - Panics if the buffer wasn't created with the right usage.
+    /// - Panics if partial results are requested for timestamp queries.
+    ///
+    /// # Safety
+    ///
+    /// - Care must be taken to respect the rules about secondary command buffers.
+    ///
+    pub unsafe fn copy_query_pool_results<Q, B>(mut self, pool: &Arc<Q>, slots: Range<u32>,
+                                                buffer: &Arc<B>, offset: usize,
+                                                flags: QueryResultFlags, with_availability: bool)
+                                                -> InnerCommandBufferBuilder<P>
+        where Q: QueryPool, B: Buffer + 'static
+    {
+        debug_assert!(self.render_pass_staging_commands.is_empty());
+
+        assert!(slots.start <= slots.end);
+        assert!(slots.end <= pool.inner().num_slots());
+        assert!(offset % 8 == 0);
+        assert!(buffer.inner().usage_transfer_dest());
+
+        if flags.partial {
+            match pool.inner().ty() {
+                QueryType::Timestamp => panic!("Partial results are not allowed for timestamps"),
+                _ => ()
+            }
+        }
+
+        let values = pool.inner().ty().result_len() + if with_availability { 1 } else { 0 };
+        let stride = values * 8;
+        let size = (slots.end - slots.start) as usize * stride;
+        assert!(offset + size <= buffer.size());
+
+        self.keep_alive.push(pool.clone() as Arc<_>);
+        self.add_buffer_resource_outside(buffer.clone() as Arc<_>, true, offset .. offset + size,
+                                         vk::PIPELINE_STAGE_TRANSFER_BIT,
+                                         vk::ACCESS_TRANSFER_WRITE_BIT);
 
-            // TODO: shouldn't rebind everything every time
-            if !descriptor_sets.as_ref().unwrap().is_empty() {
-                let pipeline = PipelineLayout::inner(&**pipeline.layout()).internal_object();
-                self.render_pass_staging_commands.push(Box::new(move |vk, cmd| {
-                    let descriptor_sets = descriptor_sets.take().unwrap();
-                    vk.CmdBindDescriptorSets(cmd, vk::PIPELINE_BIND_POINT_GRAPHICS, pipeline,
-                                             0, descriptor_sets.len() as u32,
-                                             descriptor_sets.as_ptr(), 0, ptr::null());   // FIXME: dynamic offsets
-                }));
+        {
+            let pool = pool.inner().internal_object();
+            let buffer = buffer.clone();
+            let mut vk_flags: vk::QueryResultFlags = flags.into();
+            vk_flags |= vk::QUERY_RESULT_64_BIT;
+            if with_availability {
+                vk_flags |= vk::QUERY_RESULT_WITH_AVAILABILITY_BIT;
             }
+
+            self.staging_commands.push(Box::new(move |vk, cmd| {
+                vk.CmdCopyQueryPoolResults(cmd, pool, slots.start, slots.end - slots.start,
+                                           buffer.inner().internal_object(),
+                                           offset as vk::DeviceSize, stride as vk::DeviceSize,
+                                           vk_flags);
+            }));
         }
+
+        self
     }
 
-    /// Calls `vkCmdBeginRenderPass`.
+    /// Calls `vkCmdCopyQueryPoolResults` to write the result of an occlusion query as a 32-bit
+    /// value at `offset`, waiting for the query to be available.
     ///
     /// # Panic
     ///
-    /// - Panics if the framebuffer is not compatible with the renderpass.
+    /// - Panics if the pool is not an occlusion pool or if `slot` is out of range of the pool.
+    /// - Panics if the value doesn't fit in the buffer or if `offset` is not a multiple of 4.
+    /// - Panics if the buffer wasn't created with the `transfer_dest` usage.
     ///
     /// # Safety
     ///
     /// - Care must be taken to respect the rules about secondary command buffers.
     ///
-    #[inline]
-    pub unsafe fn begin_renderpass<R, F>(mut self, render_pass: &Arc<R>,
-                                         framebuffer: &Arc<Framebuffer<F>>,
-                                         secondary_cmd_buffers: bool,
-                                         clear_values: &[ClearValue]) -> InnerCommandBufferBuilder<P>
-        where R: RenderPass + 'static, F: RenderPass + 'static
+    pub unsafe fn write_occlusion_result<Q, B>(mut self, pool: &Arc<Q>, slot: u32,
+                                               buffer: &Arc<B>, offset: usize)
+                                               -> InnerCommandBufferBuilder<P>
+        where Q: QueryPool, B: Buffer + 'static
     {
         debug_assert!(self.render_pass_staging_commands.is_empty());
-        debug_assert!(self.render_pass_staging_required_buffer_accesses.is_empty());
-        debug_assert!(self.render_pass_staging_required_image_accesses.is_empty());
-
-        assert!(framebuffer.is_compatible_with(render_pass));
-
-        self.keep_alive.push(framebuffer.clone() as Arc<_>);
-        self.keep_alive.push(render_pass.clone() as Arc<_>);
 
-        let clear_values = clear_values.iter().map(|value| {
-            match *value {
-                ClearValue::None => vk::ClearValue::color({
-                    vk::ClearColorValue::float32([0.0, 0.0, 0.0, 0.0])
-                }),
-                ClearValue::Float(data) => vk::ClearValue::color(vk::ClearColorValue::float32(data)),
-                ClearValue::Int(data) => vk::ClearValue::color(vk::ClearColorValue::int32(data)),
-                ClearValue::Uint(data) => vk::ClearValue::color(vk::ClearColorValue::uint32(data)),
-                ClearValue::Depth(d) => vk::ClearValue::depth_stencil({
-                    vk::ClearDepthStencilValue { depth: d, stencil: 0 }
-                }),
-                ClearValue::Stencil(s) => vk::ClearValue::depth_stencil({
-                    vk::ClearDepthStencilValue { depth: 0.0, stencil: s }
-                }),
-                ClearValue::DepthStencil((d, s)) => vk::ClearValue::depth_stencil({
-                    vk::ClearDepthStencilValue { depth: d, stencil: s }
-                }),
-            }
-        }).collect::<SmallVec<[_; 16]>>();
+        match pool.inner().ty() {
+            QueryType::Occlusion => (),
+            _ => panic!("Only the results of occlusion queries can be used as predicates")
+        }
 
-        for &(ref attachment, ref image, initial_layout, final_layout) in framebuffer.attachments() {
-            self.keep_alive.push(mem::transmute(attachment.clone()) /* FIXME: */);
+        assert!(slot < pool.inner().num_slots());
+        assert!(offset % 4 == 0);
+        assert!(offset + 4 <= buffer.size());
+        assert!(buffer.inner().usage_transfer_dest());
 
-            let stages = vk::PIPELINE_STAGE_FRAGMENT_SHADER_BIT |
-                         vk::PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT |
-                         vk::PIPELINE_STAGE_EARLY_FRAGMENT_TESTS_BIT |
-                         vk::PIPELINE_STAGE_LATE_FRAGMENT_TESTS_BIT; // FIXME:
+        self.keep_alive.push(pool.clone() as Arc<_>);
+        self.add_buffer_resource_outside(buffer.clone() as Arc<_>, true, offset .. offset + 4,
+                                         vk::PIPELINE_STAGE_TRANSFER_BIT,
+                                         vk::ACCESS_TRANSFER_WRITE_BIT);
 
-            let accesses = vk::ACCESS_COLOR_ATTACHMENT_READ_BIT |
-                           vk::ACCESS_COLOR_ATTACHMENT_WRITE_BIT |
-                           vk::ACCESS_DEPTH_STENCIL_ATTACHMENT_READ_BIT |
-                           vk::ACCESS_DEPTH_STENCIL_ATTACHMENT_WRITE_BIT |
-                           vk::ACCESS_INPUT_ATTACHMENT_READ_BIT;       // FIXME:
+        {
+            let pool = pool.inner().internal_object();
+            let buffer = buffer.clone();
 
-            // FIXME: parameters
-            self.add_image_resource_inside(image.clone(), 0 .. 1, 0 .. 1, true,
-                                           initial_layout, final_layout, stages, accesses);
+            self.staging_commands.push(Box::new(move |vk, cmd| {
+                vk.CmdCopyQueryPoolResults(cmd, pool, slot, 1, buffer.inner().internal_object(),
+                                           offset as vk::DeviceSize, 4,
+                                           vk::QUERY_RESULT_WAIT_BIT);
+            }));
         }
 
-        {
-            let mut clear_values = Some(clear_values);
-            let render_pass = render_pass.inner().internal_object();
-            let (fw, fh) = (framebuffer.width(), framebuffer.height());
-            let framebuffer = framebuffer.internal_object();
+        self
+    }
 
-            self.render_pass_staging_commands.push(Box::new(move |vk, cmd| {
-                let clear_values = clear_values.take().unwrap();
+    /// Calls `vkCmdBeginConditionalRenderingEXT`. The draw commands that follow are skipped if
+    /// the 32-bit value at `offset` is zero, or non-zero if `inverted` is true.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the `ext_conditional_rendering` extension isn't enabled.
+    /// - Panics if the value isn't in the buffer or if `offset` is not a multiple of 4.
+    /// - Panics if the buffer wasn't created with the `conditional_rendering` usage.
+    ///
+    /// # Safety
+    ///
+    /// - Conditional rendering must not be active, and must be ended in the same subpass if
+    ///   started inside of a render pass.
+    /// - Care must be taken to respect the rules about secondary command buffers.
+    ///
+    pub unsafe fn begin_conditional_rendering<B>(mut self, buffer: &Arc<B>, offset: usize,
+                                                 inverted: bool) -> InnerCommandBufferBuilder<P>
+        where B: Buffer + 'static
+    {
+        assert!(self.device.loaded_extensions().ext_conditional_rendering);
+        assert!(offset % 4 == 0);
+        assert!(offset + 4 <= buffer.size());
+        assert!(buffer.inner().usage_conditional_rendering());
+
+        let inside = self.is_secondary_graphics || !self.render_pass_staging_commands.is_empty();
+        if inside {
+            self.add_buffer_resource_inside(buffer.clone() as Arc<_>, false, offset .. offset + 4,
+                                            vk::PIPELINE_STAGE_CONDITIONAL_RENDERING_BIT_EXT,
+                                            vk::ACCESS_CONDITIONAL_RENDERING_READ_BIT_EXT);
+        } else {
+            self.add_buffer_resource_outside(buffer.clone() as Arc<_>, false, offset .. offset + 4,
+                                             vk::PIPELINE_STAGE_CONDITIONAL_RENDERING_BIT_EXT,
+                                             vk::ACCESS_CONDITIONAL_RENDERING_READ_BIT_EXT);
+        }
 
-                let infos = vk::RenderPassBeginInfo {
-                    sType: vk::STRUCTURE_TYPE_RENDER_PASS_BEGIN_INFO,
-                    pNext: ptr::null(),
-                    renderPass: render_pass,
-                    framebuffer: framebuffer,
-                    renderArea: vk::Rect2D {                // TODO: let user customize
-                        offset: vk::Offset2D { x: 0, y: 0 },
-                        extent: vk::Extent2D {
-                            width: fw,
-                            height: fh,
-                        },
-                    },
-                    clearValueCount: clear_values.len() as u32,
-                    pClearValues: clear_values.as_ptr(),
-                };
+        let buffer = buffer.clone();
+        let flags = if inverted { vk::CONDITIONAL_RENDERING_INVERTED_BIT_EXT } else { 0 };
+        let command = Box::new(move |vk: &vk::DevicePointers, cmd| {
+            let infos = vk::ConditionalRenderingBeginInfoEXT {
+                sType: vk::STRUCTURE_TYPE_CONDITIONAL_RENDERING_BEGIN_INFO_EXT,
+                pNext: ptr::null(),
+                buffer: buffer.inner().internal_object(),
+                offset: offset as vk::DeviceSize,
+                flags: flags,
+            };
 
-                let content = if secondary_cmd_buffers {
-                    vk::SUBPASS_CONTENTS_SECONDARY_COMMAND_BUFFERS
-                } else {
-                    vk::SUBPASS_CONTENTS_INLINE
-                };
+            vk.CmdBeginConditionalRenderingEXT(cmd, &infos);
+        });
 
-                vk.CmdBeginRenderPass(cmd, &infos, content);
-            }));
+        if inside {
+            self.render_pass_staging_commands.push(command);
+        } else {
+            self.staging_commands.push(command);
         }
 
         self
     }
 
-    #[inline]
-    pub unsafe fn next_subpass(mut self, secondary_cmd_buffers: bool) -> InnerCommandBufferBuilder<P> {
-        debug_assert!(!self.render_pass_staging_commands.is_empty());
+    /// Calls `vkCmdEndConditionalRenderingEXT`.
+    ///
+    /// # Safety
+    ///
+    /// - Conditional rendering must be active and must have been started in the same subpass.
+    /// - Care must be taken to respect the rules about secondary command buffers.
+    ///
+    pub unsafe fn end_conditional_rendering(mut self) -> InnerCommandBufferBuilder<P> {
+        let command = Box::new(move |vk: &vk::DevicePointers, cmd| {
+            vk.CmdEndConditionalRenderingEXT(cmd);
+        });
 
-        let content = if secondary_cmd_buffers {
-            vk::SUBPASS_CONTENTS_SECONDARY_COMMAND_BUFFERS
+        if self.is_secondary_graphics || !self.render_pass_staging_commands.is_empty() {
+            self.render_pass_staging_commands.push(command);
         } else {
-            vk::SUBPASS_CONTENTS_INLINE
-        };
-
-        self.render_pass_staging_commands.push(Box::new(move |vk, cmd| {
-            vk.CmdNextSubpass(cmd, content);
-        }));
+            self.staging_commands.push(command);
+        }
 
         self
     }
 
-    /// Ends the current render pass by calling `vkCmdEndRenderPass`.
+    /// Writes the result of an occlusion query to the predicate of the given index.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `index` is out of range of the predicates.
+    /// - Panics if the predicates are emulated and the query was started as precise by this
+    ///   command buffer.
+    /// - Panics for the same reasons as `write_occlusion_result`.
     ///
     /// # Safety
     ///
-    /// Assumes that you're inside a render pass and that all subpasses have been processed.
+    /// - Care must be taken to respect the rules about secondary command buffers.
+    /// - If the predicates are emulated, the query must not be precise.
     ///
-    #[inline]
-    pub unsafe fn end_renderpass(mut self) -> InnerCommandBufferBuilder<P> {
-        debug_assert!(!self.render_pass_staging_commands.is_empty());
-        self.flush_render_pass();
-        self.staging_commands.push(Box::new(move |vk, cmd| {
-            vk.CmdEndRenderPass(cmd);
-        }));
-        self
+    pub unsafe fn write_predicate<Q>(self, predicates: &Arc<OcclusionPredicates>, index: usize,
+                                     pool: &Arc<Q>, slot: u32) -> InnerCommandBufferBuilder<P>
+        where Q: QueryPool
+    {
+        assert!(index < predicates.len());
+
+        if !predicates.is_native() {
+            let query = (pool.inner().internal_object(), slot);
+            assert!(!self.precise_queries.contains(&query),
+                    "Emulated predicates can't be written with precise occlusion queries");
+        }
+
+        // The result is written to the `instance_count` of the command.
+        let offset = index * mem::size_of::<DrawIndirectCommand>() + 4;
+        self.write_occlusion_result(pool, slot, predicates.buffer(), offset)
+    }
+
+    /// Draws the vertices of the predicate of the given index if the predicate is true, either
+    /// with conditional rendering or with an indirect draw.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `index` is out of range of the predicates.
+    /// - Panics for the same reasons as `draw` if the predicates are native, or as
+    ///   `draw_indirect` if they are emulated.
+    ///
+    /// # Safety
+    ///
+    /// - Care must be taken to respect the rules about secondary command buffers.
+    ///
+    pub unsafe fn draw_predicated<V, Pv, Pl, L, Rp, Pc>(self, pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
+                             vertices: V, predicates: &Arc<OcclusionPredicates>, index: usize,
+                             dynamic: &DynamicState, sets: L, push_constants: &Pc)
+                             -> InnerCommandBufferBuilder<P>
+        where Pv: 'static + VertexSource<V>, L: DescriptorSetsCollection + Send + Sync,
+              Pl: 'static + PipelineLayout + Send + Sync, Rp: 'static + Send + Sync,
+              Pc: 'static + Clone + Send + Sync
+    {
+        assert!(index < predicates.len());
+
+        if !predicates.is_native() {
+            return self.draw_indirect(predicates.buffer(), index .. index + 1, pipeline,
+                                      vertices, dynamic, sets, push_constants);
+        }
+
+        let range = predicates.vertices(index);
+        let params = DrawParams {
+            first_vertex: range.start,
+            vertex_count: Some(range.end - range.start),
+            instance_count: Some(1),
+            .. DrawParams::default()
+        };
+
+        let offset = index * mem::size_of::<DrawIndirectCommand>() + 4;
+        self.begin_conditional_rendering(predicates.buffer(), offset, false)
+            .draw(pipeline, vertices, &params, dynamic, sets, push_constants)
+            .end_conditional_rendering()
     }
 
     /// Adds a buffer resource to the list of resources used by this command buffer.
@@ -1307,6 +2479,35 @@ impl<P> InnerCommandBufferBuilder<P> where P: CommandPool {
         }
     }
 
+    /// Checks that an image used as an input attachment by a descriptor set is an input
+    /// attachment of the current subpass, and that the layout expected by the descriptor matches
+    /// the layout of the attachment during the subpass.
+    ///
+    /// Does nothing if the render pass is unknown, which is the case for secondary command
+    /// buffers.
+    fn check_input_attachment(&self, image: &Arc<Image>, layout: ImageLayout) {
+        let attachments = match self.render_pass_input_attachments.get(self.current_subpass) {
+            Some(a) => a,
+            None => return,
+        };
+
+        let expected = attachments.iter().find(|&&(ref a, _)| {
+            &**a as *const Image as *const () == &**image as *const Image as *const ()
+        });
+
+        match expected {
+            Some(&(_, expected_layout)) => {
+                assert!(expected_layout == layout, "The layout of an input attachment of a \
+                                                    descriptor set doesn't match the layout of \
+                                                    the attachment in the current subpass");
+            },
+            None => {
+                panic!("An image used as an input attachment in a descriptor set is not an \
+                        input attachment of the current subpass");
+            },
+        }
+    }
+
     /// Adds an image resource to the list of resources used by this command buffer.
     fn add_image_resource_inside(&mut self, image: Arc<Image>, mipmap_levels_range: Range<u32>,
                                  array_layers_range: Range<u32>, write: bool,
@@ -1753,16 +2954,22 @@ pub struct InnerCommandBuffer<P = Arc<StandardCommandPool>> where P: CommandPool
 ///
 /// Queues are not thread-safe, therefore we need to get a `&mut`.
 ///
+/// In addition to the semaphores that are determined automatically, the submission waits upon
+/// `wait_semaphores` at the given stages and signals `signal_semaphores` when it has finished.
+///
 /// # Panic
 ///
 /// - Panics if the queue doesn't belong to the device this command buffer was created with.
 /// - Panics if the queue doesn't belong to the family the pool was created with.
 ///
 pub fn submit<P>(me: &InnerCommandBuffer<P>, me_arc: Arc<KeepAlive>,
-                 queue: &Arc<Queue>) -> Result<Arc<Submission>, OomError>   // TODO: wrong error type
+                 queue: &Arc<Queue>, wait_semaphores: &[(Arc<Semaphore>, PipelineStages)],
+                 signal_semaphores: &[Arc<Semaphore>])
+                 -> Result<Arc<Submission>, QueueError>
     where P: CommandPool
 {
     debug_assert!(!me.is_secondary);
+    try!(queue.device().check_lost());
 
     // TODO: see comment of GLOBAL_MUTEX
     let _global_lock = GLOBAL_MUTEX.lock().unwrap();
@@ -1799,6 +3006,17 @@ pub fn submit<P>(me: &InnerCommandBuffer<P>, me_arc: Arc<KeepAlive>,
         keep_alive_semaphores.push(signalled);
     }
 
+    // Semaphores requested by the caller.
+    for &(ref semaphore, stages) in wait_semaphores.iter() {
+        pre_semaphores_ids.push(semaphore.internal_object());
+        pre_semaphores_stages.push(stages.into());
+        keep_alive_semaphores.push(semaphore.clone());
+    }
+    for semaphore in signal_semaphores.iter() {
+        post_semaphores_ids.push(semaphore.internal_object());
+        keep_alive_semaphores.push(semaphore.clone());
+    }
+
     // Creating additional semaphores, one for each queue transition.
     let queue_transitions_hint: u32 = 2;        // TODO: get as function parameter
     // TODO: use a pool
@@ -2009,10 +3227,13 @@ pub fn submit<P>(me: &InnerCommandBuffer<P>, me_arc: Arc<KeepAlive>,
             }
 
             let fence = fence.internal_object();
-            try!(check_errors(vk.QueueSubmit(*queue.internal_object_guard(), infos.len() as u32,
-                                             infos.as_ptr(), fence)));
+            try!(queue.device().check_errors(vk.QueueSubmit(*queue.internal_object_guard(),
+                                                            infos.len() as u32, infos.as_ptr(),
+                                                            fence)));
         }
 
+        queue.record_submission(submission.fence.clone());
+
         // Don't forget to add all the semaphores in the list of semaphores that must be kept alive.
         {
             let mut ka_sem = submission.keep_alive_semaphores.lock().unwrap();
@@ -2024,9 +3245,28 @@ pub fn submit<P>(me: &InnerCommandBuffer<P>, me_arc: Arc<KeepAlive>,
     Ok(submission)
 }
 
+impl<P> InnerCommandBuffer<P> where P: CommandPool {
+    /// Returns the pool that the command buffer was built from.
+    #[inline]
+    pub fn pool(&self) -> &P::Finished {
+        &self.pool
+    }
+
+    // Destroys the command buffer without giving its Vulkan command buffer back to the pool.
+    #[inline]
+    fn into_raw(mut self) -> vk::CommandBuffer {
+        mem::replace(&mut self.cmd, 0)
+    }
+}
+
 impl<P> Drop for InnerCommandBuffer<P> where P: CommandPool {
     #[inline]
     fn drop(&mut self) {
+        // The Vulkan command buffer has been taken by `into_raw`.
+        if self.cmd == 0 {
+            return;
+        }
+
         unsafe {
             self.pool.free(self.is_secondary, Some(self.cmd.into()).into_iter());
         }
@@ -2106,6 +3346,20 @@ impl Submission {
     pub fn queue(&self) -> &Arc<Queue> {
         &self.queue
     }
+
+    /// Returns the fence that is signaled when the GPU has finished executing this submission.
+    #[inline]
+    pub fn fence(&self) -> &Arc<Fence> {
+        &self.fence
+    }
+
+    /// Keeps an object alive until this submission has been destroyed.
+    // TODO: hacky
+    #[doc(hidden)]
+    #[inline]
+    pub fn keep_alive(&self, object: Arc<KeepAlive>) {
+        self.keep_alive_cb.lock().unwrap().push(object);
+    }
 }
 
 impl Drop for Submission {
@@ -2114,7 +3368,7 @@ impl Drop for Submission {
         let timeout = Duration::new(u64::MAX / 1_000_000_000, (u64::MAX % 1_000_000_000) as u32);
         match self.fence.wait(timeout) {
             Ok(_) => (),
-            Err(FenceWaitError::DeviceLostError) => (),
+            Err(FenceWaitError::DeviceLost(_)) => (),
             Err(FenceWaitError::Timeout) => panic!(),       // The driver has some sort of problem.
             Err(FenceWaitError::OomError(_)) => panic!(),   // What else to do here?
         }
@@ -2197,6 +3451,132 @@ struct InternalImageBlockAccess {
     new_layout: ImageLayout,
 }
 
+/// Calls `f` once with `STENCIL_FRONT_AND_BACK` if both values are equal, or once per face
+/// otherwise.
+#[inline]
+fn set_stencil_value<F>(value: DynamicStencilValue, mut f: F)
+    where F: FnMut(vk::StencilFaceFlags, u32)
+{
+    if value.front == value.back {
+        f(vk::STENCIL_FRONT_AND_BACK, value.front);
+    } else {
+        f(vk::STENCIL_FACE_FRONT_BIT, value.front);
+        f(vk::STENCIL_FACE_BACK_BIT, value.back);
+    }
+}
+
+/// Panics if the region of a buffer-image copy is out of range of the image.
+///
+/// For three-dimensional images, the third component of `offset` and `extent` selects depth
+/// slices. Other images only have one depth slice. Images with a multi-planar format must be
+/// copied one plane at a time, and the region is then in texels of the plane.
+fn check_image_copy_region<I>(image: &I, aspect: ImageAspect, mip_level: u32,
+                              array_layers: &Range<u32>, offset: [u32; 3], extent: [u32; 3])
+    where I: ?Sized + Image
+{
+    let dimensions = image.dimensions();
+    let format = image.format();
+
+    assert!(mip_level < image.inner().mipmap_levels());
+    assert!(array_layers.start < array_layers.end);
+    assert!(array_layers.end <= dimensions.array_layers());
+
+    let plane = match aspect.plane() {
+        Some(plane) => {
+            assert!(plane < format.num_planes() && format.num_planes() > 1,
+                    "The format {:?} doesn't have a plane {}", format, plane);
+            plane
+        },
+        None => {
+            assert!(format.num_planes() == 1,
+                    "Images of the multi-planar format {:?} must be copied plane by plane",
+                    format);
+            0
+        },
+    };
+
+    let mip_extent = format.plane_extent(plane, dimensions.mipmap_extent(mip_level));
+    for i in 0 .. 3 {
+        assert!(extent[i] != 0);
+        assert!(offset[i] as u64 + extent[i] as u64 <= mip_extent[i] as u64);
+    }
+
+    // With compressed formats, the region must cover whole blocks, except at the edges of the
+    // mipmap level whose dimensions may not be a multiple of the block dimensions.
+    let block = aspect.format(format).block_dimensions();
+    for i in 0 .. 2 {
+        assert!(offset[i] % block[i] == 0);
+        assert!(extent[i] % block[i] == 0 || offset[i] + extent[i] == mip_extent[i]);
+    }
+}
+
+// Returns the minimum size in bytes of the buffer that is copied from or to a region of an image.
+fn image_copy_buffer_size<I>(image: &I, aspect: ImageAspect, array_layers: &Range<u32>,
+                             extent: [u32; 3]) -> Option<usize>
+    where I: ?Sized + Image
+{
+    let num_layers = (array_layers.end - array_layers.start) as usize;
+    aspect.format(image.format()).packed_size(extent).map(|s| s * num_layers)
+}
+
+// Returns the first element and the number of elements used by a draw command, given the first
+// element and the optional number of elements of `DrawParams` and the number of elements that
+// are available.
+fn draw_range(first: u32, count: Option<u32>, available: usize, what: &str) -> (u32, u32) {
+    assert!(first as usize <= available, "The first of the {} of the draw is out of range", what);
+    let count = count.unwrap_or((available - first as usize) as u32);
+    assert!(first as usize + count as usize <= available,
+            "The draw uses more {} than available", what);
+    (first, count)
+}
+
+// Checks the parameters of `draw_indirect_count` and `draw_indexed_indirect_count` that don't
+// depend on the type of the commands. `num_commands` is the number of commands in the buffer.
+fn check_indirect_count(device: &Device, max_draw_count: u32, num_commands: usize) {
+    let strict = device.buffer_access_checks().strict_checks(device.enabled_features());
+    check_indirect_count_raw(device.loaded_extensions(), strict, max_draw_count, num_commands);
+}
+
+fn check_indirect_count_raw(extensions: &DeviceExtensions, strict_checks: bool,
+                            max_draw_count: u32, num_commands: usize)
+{
+    assert!(extensions.khr_draw_indirect_count,
+            "The khr_draw_indirect_count extension must be enabled");
+    assert!(!strict_checks,
+            "Indirect draws require the robust_buffer_access feature with \
+             BufferAccessChecks::Strict");
+    assert!(max_draw_count as usize <= num_commands,
+            "The maximum number of draws is larger than the number of commands of the buffer");
+}
+
+// Returns the aspect corresponding to a plane of a multi-planar image.
+fn plane_aspect(plane: u32) -> ImageAspect {
+    match plane {
+        0 => ImageAspect::Plane0,
+        1 => ImageAspect::Plane1,
+        2 => ImageAspect::Plane2,
+        _ => panic!("Formats have at most 3 planes"),
+    }
+}
+
+// Returns the number of vertices and instances of a draw when reads past the end of the vertex
+// buffers are allowed. Uses the largest buffer of each input rate instead of the smallest one.
+// `sizes` contains the number of bytes of each buffer after its offset, and `default` is
+// returned for an input rate that has no buffer.
+fn relaxed_vertices_instances(bindings: &[(usize, InputRate)], sizes: &[usize],
+                              default: (usize, usize)) -> (usize, usize)
+{
+    let count = |rate| {
+        bindings.iter().zip(sizes.iter())
+                .filter(|&(&(stride, r), _)| r == rate && stride != 0)
+                .map(|(&(stride, _), &size)| size / stride)
+                .max()
+    };
+
+    (count(InputRate::Vertex).unwrap_or(default.0),
+     count(InputRate::Instance).unwrap_or(default.1))
+}
+
 /// Builds an `InnerCommandBuffer` whose only purpose is to transition an image between two
 /// layouts.
 fn transition_cb<P>(pool: P, image: Arc<Image>, block: (u32, u32),
@@ -2234,7 +3614,9 @@ fn transition_cb<P>(pool: P, image: Arc<Image>, block: (u32, u32),
         let barrier = vk::ImageMemoryBarrier {
             sType: vk::STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER,
             pNext: ptr::null(),
-            srcAccessMask: 0,      // TODO: ?
+            // The content of an image in the undefined layout doesn't need to be made available.
+            srcAccessMask: if old_layout == ImageLayout::Undefined { 0 }
+                           else { vk::ACCESS_MEMORY_WRITE_BIT },
             dstAccessMask: 0x0001ffff,      // TODO: ?
             oldLayout: old_layout as u32,
             newLayout: new_layout as u32,
@@ -2270,3 +3652,71 @@ fn transition_cb<P>(pool: P, image: Arc<Image>, block: (u32, u32),
         keep_alive: Vec::new(),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use command_buffer::pool::StandardCommandPool;
+    use device::DeviceExtensions;
+    use framebuffer::UnsafeRenderPass;
+    use super::check_indirect_count_raw;
+    use super::draw_range;
+    use super::InnerCommandBufferBuilder;
+
+    #[test]
+    fn draw_ranges() {
+        assert_eq!(draw_range(0, None, 12, "vertices"), (0, 12));
+        assert_eq!(draw_range(4, None, 12, "vertices"), (4, 8));
+        assert_eq!(draw_range(4, Some(6), 12, "vertices"), (4, 6));
+        assert_eq!(draw_range(12, None, 12, "vertices"), (12, 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn draw_range_too_large() {
+        draw_range(4, Some(9), 12, "indices");
+    }
+
+    #[test]
+    fn indirect_count() {
+        let extensions = DeviceExtensions {
+            khr_draw_indirect_count: true,
+            .. DeviceExtensions::none()
+        };
+
+        check_indirect_count_raw(&extensions, false, 0, 0);
+        check_indirect_count_raw(&extensions, false, 4, 4);
+    }
+
+    #[test]
+    #[should_panic = "The khr_draw_indirect_count extension must be enabled"]
+    fn indirect_count_missing_extension() {
+        check_indirect_count_raw(&DeviceExtensions::none(), false, 1, 4);
+    }
+
+    #[test]
+    #[should_panic = "The maximum number of draws is larger than the number of commands"]
+    fn indirect_count_too_many_draws() {
+        let extensions = DeviceExtensions {
+            khr_draw_indirect_count: true,
+            .. DeviceExtensions::none()
+        };
+
+        check_indirect_count_raw(&extensions, false, 5, 4);
+    }
+
+    #[test]
+    fn reuse_command_buffer() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let pool = Arc::new(StandardCommandPool::new(&device, queue.family()));
+
+        let builder = InnerCommandBufferBuilder::new::<UnsafeRenderPass>(pool.clone(), false,
+                                                                         None, None).unwrap();
+        let cb = builder.build().unwrap();
+        let raw = cb.cmd;
+
+        let builder = unsafe { InnerCommandBufferBuilder::reuse(pool.clone(), cb).unwrap() };
+        assert_eq!(builder.cmd, Some(raw));
+        assert_eq!(builder.build().unwrap().cmd, raw);
+    }
+}
@@ -41,8 +41,15 @@
 // API has several different command buffer wrappers, but they all use the same internal
 // struct. The restrictions are enforced only in the public types.
 
+pub use self::auto::AutoCommandBufferBuilder;
+pub use self::auto::AutoCommandBufferBuilderError;
+pub use self::graph::SubmitGraph;
+pub use self::graph::SubmitGraphError;
+pub use self::graph::SubmitNode;
 pub use self::inner::Submission;
+pub use self::predicate::OcclusionPredicates;
 pub use self::outer::submit;
+pub use self::outer::submit_with_semaphores;
 pub use self::outer::PrimaryCommandBufferBuilder;
 pub use self::outer::PrimaryCommandBufferBuilderInlineDraw;
 pub use self::outer::PrimaryCommandBufferBuilderSecondaryDraw;
@@ -54,11 +61,16 @@ pub use self::outer::SecondaryComputeCommandBuffer;
 pub use self::submit::CommandBuffer;
 pub use self::submit::Submit;
 
+use pipeline::depth_stencil::DepthBoundsError;
+use pipeline::depth_stencil::check_depth_bounds;
 use pipeline::viewport::Viewport;
 use pipeline::viewport::Scissor;
 
+mod auto;
+mod graph;
 mod inner;
 mod outer;
+mod predicate;
 
 pub mod pool;
 pub mod std;
@@ -84,12 +96,55 @@ pub struct DrawIndexedIndirectCommand {
     pub first_instance: u32,
 }
 
+/// Range of vertices, indices and instances used by a draw command.
+///
+/// This makes it possible to store the geometry of several objects in the same vertex and
+/// index buffers, and to draw each object separately. The default value draws all the vertices,
+/// indices and instances.
+///
+/// None of these parameters requires a device feature. For indirect draws, a non-zero
+/// `first_instance` in the indirect buffer requires the `draw_indirect_first_instance` feature.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct DrawParams {
+    /// Index of the first vertex to draw. Only used by non-indexed draws.
+    pub first_vertex: u32,
+    /// Number of vertices to draw, or `None` to draw all the vertices starting at
+    /// `first_vertex`. Only used by non-indexed draws.
+    pub vertex_count: Option<u32>,
+    /// Position of the first index to use within the index buffer. Only used by indexed draws.
+    pub first_index: u32,
+    /// Number of indices to use, or `None` to use all the indices starting at `first_index`.
+    /// Only used by indexed draws.
+    pub index_count: Option<u32>,
+    /// Value added to each index before reading the vertex buffers. Only used by indexed draws.
+    pub vertex_offset: i32,
+    /// Index of the first instance to draw.
+    pub first_instance: u32,
+    /// Number of instances to draw, or `None` to draw all the instances starting at
+    /// `first_instance`.
+    pub instance_count: Option<u32>,
+}
+
 /// The dynamic state to use for a draw command.
 #[derive(Debug, Clone)]
 pub struct DynamicState {
+    /// Width of lines. Must be `Some` if and only if the pipeline was created with a
+    /// `line_width` of `None`.
     pub line_width: Option<f32>,
     pub viewports: Option<Vec<Viewport>>,
     pub scissors: Option<Vec<Scissor>>,
+    /// Minimum and maximum depth bounds. Must be `Some` if and only if the pipeline was created
+    /// with `DepthBounds::Dynamic`. Use `set_depth_bounds` to check the values when setting them.
+    pub depth_bounds: Option<(f32, f32)>,
+    /// Stencil compare masks. Must be `Some` if and only if the pipeline was created with a
+    /// `compare_mask` of `None`.
+    pub stencil_compare_mask: Option<DynamicStencilValue>,
+    /// Stencil write masks. Must be `Some` if and only if the pipeline was created with a
+    /// `write_mask` of `None`.
+    pub stencil_write_mask: Option<DynamicStencilValue>,
+    /// Stencil references. Must be `Some` if and only if the pipeline was created with a
+    /// `reference` of `None`.
+    pub stencil_reference: Option<DynamicStencilValue>,
 }
 
 impl DynamicState {
@@ -99,8 +154,22 @@ impl DynamicState {
             line_width: None,
             viewports: None,
             scissors: None,
+            depth_bounds: None,
+            stencil_compare_mask: None,
+            stencil_write_mask: None,
+            stencil_reference: None,
         }
     }
+
+    /// Sets the dynamic depth bounds after checking that they are valid.
+    ///
+    /// Both values must be between 0.0 and 1.0, and `min` must not be greater than `max`.
+    #[inline]
+    pub fn set_depth_bounds(&mut self, min: f32, max: f32) -> Result<(), DepthBoundsError> {
+        try!(check_depth_bounds(min, max));
+        self.depth_bounds = Some((min, max));
+        Ok(())
+    }
 }
 
 impl Default for DynamicState {
@@ -109,3 +178,12 @@ impl Default for DynamicState {
         DynamicState::none()
     }
 }
+
+/// Value of a dynamic stencil state for the front and back faces.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DynamicStencilValue {
+    /// Value for points, lines and triangles whose front is facing the user.
+    pub front: u32,
+    /// Value for triangles whose back is facing the user.
+    pub back: u32,
+}
@@ -14,8 +14,11 @@ use smallvec::SmallVec;
 use buffer::Buffer;
 use buffer::BufferSlice;
 use buffer::TypedBuffer;
+use command_buffer::DrawIndexedIndirectCommand;
 use command_buffer::DrawIndirectCommand;
+use command_buffer::DrawParams;
 use command_buffer::DynamicState;
+use command_buffer::OcclusionPredicates;
 use command_buffer::inner::InnerCommandBufferBuilder;
 use command_buffer::inner::InnerCommandBuffer;
 use command_buffer::inner::Submission;
@@ -26,6 +29,7 @@ use descriptor::descriptor_set::DescriptorSetsCollection;
 use descriptor::PipelineLayout;
 use device::Device;
 use device::Queue;
+use device::QueueError;
 use framebuffer::Framebuffer;
 use framebuffer::UnsafeRenderPass;
 use framebuffer::RenderPassCompatible;
@@ -33,6 +37,7 @@ use framebuffer::RenderPass;
 use framebuffer::RenderPassDesc;
 use framebuffer::RenderPassClearValues;
 use framebuffer::Subpass;
+use image::sys::Layout as ImageLayout;
 use image::traits::Image;
 use image::traits::ImageClearValue;
 use image::traits::ImageContent;
@@ -41,6 +46,10 @@ use pipeline::ComputePipeline;
 use pipeline::GraphicsPipeline;
 use pipeline::input_assembly::Index;
 use pipeline::vertex::Source as VertexSource;
+use query::QueryPool;
+use query::QueryResultFlags;
+use sync::PipelineStages;
+use sync::Semaphore;
 
 use OomError;
 
@@ -75,6 +84,30 @@ impl PrimaryCommandBufferBuilder<Arc<StandardCommandPool>> {
     {
         PrimaryCommandBufferBuilder::raw(Device::standard_command_pool(device, queue_family)).unwrap()
     }
+
+    /// Starts recording commands again in the Vulkan command buffer of `command_buffer`, instead
+    /// of allocating a new one. The previous content of the command buffer is discarded.
+    ///
+    /// The command buffer must be owned, which guarantees that the GPU has finished executing
+    /// it. Use `Arc::try_unwrap` to get it back once its submissions have been destroyed.
+    ///
+    /// If the command buffer was built on another thread, it is given back to its pool and a new
+    /// command buffer is allocated instead.
+    pub fn reuse(command_buffer: PrimaryCommandBuffer<Arc<StandardCommandPool>>)
+                 -> Result<PrimaryCommandBufferBuilder<Arc<StandardCommandPool>>, OomError>
+    {
+        let pool = command_buffer.inner.pool().pool().clone();
+
+        if !command_buffer.inner.pool().is_current_thread() {
+            drop(command_buffer);
+            return PrimaryCommandBufferBuilder::raw(pool);
+        }
+
+        // Safe because the command buffer was built from this pool on this thread, and it can't
+        // be in use by the GPU since submissions keep it alive.
+        let inner = try!(unsafe { InnerCommandBufferBuilder::reuse(pool, command_buffer.inner) });
+        Ok(PrimaryCommandBufferBuilder { inner: inner })
+    }
 }
 
 impl<P> PrimaryCommandBufferBuilder<P> where P: CommandPool {
@@ -135,6 +168,62 @@ impl<P> PrimaryCommandBufferBuilder<P> where P: CommandPool {
         }
     }
 
+    /// Adds a pipeline barrier that transfers the ownership of a buffer from the queue family
+    /// `source` to the queue family `destination`.
+    ///
+    /// A transfer is made of two halves: a release recorded in a command buffer of `source`, and
+    /// an acquire recorded in a command buffer of `destination`. Which half is recorded depends on
+    /// the queue family of this command buffer.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `source` and `destination` are the same family.
+    /// - Panics if the queue family of the command buffer is neither `source` nor `destination`.
+    ///
+    /// # Safety
+    ///
+    /// - The buffer must have been created with the exclusive sharing mode.
+    /// - Each release must be matched by an acquire with the same parameters, submitted after
+    ///   the release has been executed.
+    ///
+    pub unsafe fn transfer_buffer_ownership<B>(self, buffer: &Arc<B>, source: QueueFamily,
+                                               destination: QueueFamily)
+                                               -> PrimaryCommandBufferBuilder<P>
+        where B: Buffer + 'static
+    {
+        PrimaryCommandBufferBuilder {
+            inner: self.inner.transfer_buffer_ownership(buffer, source.id(), destination.id())
+        }
+    }
+
+    /// Adds a pipeline barrier that transfers the ownership of a whole image from the queue
+    /// family `source` to the queue family `destination`.
+    ///
+    /// Same as `transfer_buffer_ownership`, but for images. The image is put in `layout` and
+    /// stays in this layout, which must be the same for the release and the acquire.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `source` and `destination` are the same family.
+    /// - Panics if the queue family of the command buffer is neither `source` nor `destination`.
+    ///
+    /// # Safety
+    ///
+    /// - The image must have been created with the exclusive sharing mode.
+    /// - Each release must be matched by an acquire with the same parameters, submitted after
+    ///   the release has been executed.
+    ///
+    pub unsafe fn transfer_image_ownership<I>(self, image: &Arc<I>, source: QueueFamily,
+                                              destination: QueueFamily, layout: ImageLayout)
+                                              -> PrimaryCommandBufferBuilder<P>
+        where I: Image + 'static
+    {
+        PrimaryCommandBufferBuilder {
+            inner: self.inner.transfer_image_ownership(image, source.id(), destination.id(),
+                                                       layout)
+        }
+    }
+
     pub fn copy_buffer<T: ?Sized + 'static, Bs, Bd>(self, source: &Arc<Bs>, destination: &Arc<Bd>)
                                                     -> PrimaryCommandBufferBuilder<P>
         where Bs: TypedBuffer<Content = T> + 'static, Bd: TypedBuffer<Content = T> + 'static
@@ -146,6 +235,36 @@ impl<P> PrimaryCommandBufferBuilder<P> where P: CommandPool {
         }
     }
 
+    /// Copies data from a slice of a buffer to a slice of another buffer.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the slices don't have the same size.
+    /// - Panics if the slices belong to the same buffer and overlap.
+    ///
+    pub fn copy_buffer_slice<'a, 'b, T: ?Sized, Bs, Bd, S, D>(self, source: S, destination: D)
+                                                              -> PrimaryCommandBufferBuilder<P>
+        where S: Into<BufferSlice<'a, T, Bs>>, D: Into<BufferSlice<'b, T, Bd>>,
+              Bs: Buffer + 'static, Bd: Buffer + 'static
+    {
+        let source = source.into();
+        let destination = destination.into();
+
+        if &**source.buffer() as *const Bs as *const () ==
+           &**destination.buffer() as *const Bd as *const ()
+        {
+            assert!(source.offset() + source.size() <= destination.offset() ||
+                    destination.offset() + destination.size() <= source.offset(),
+                    "The source and destination slices overlap");
+        }
+
+        unsafe {
+            PrimaryCommandBufferBuilder {
+                inner: self.inner.copy_buffer_slice(source, destination),
+            }
+        }
+    }
+
     pub fn copy_buffer_to_color_image<'a, Pi, S, Img, Sb>(self, source: S, destination: &Arc<Img>, mip_level: u32, array_layers_range: Range<u32>,
                                                          offset: [u32; 3], extent: [u32; 3])
                                                     -> PrimaryCommandBufferBuilder<P>
@@ -174,6 +293,46 @@ impl<P> PrimaryCommandBufferBuilder<P> where P: CommandPool {
         }
     }
 
+    /// Copies from a buffer to one plane of an image with a multi-planar format.
+    ///
+    /// See `Format::plane_format` and `Format::plane_extent` for the format and the dimensions
+    /// of the plane.
+    pub fn copy_buffer_to_image_plane<'a, Pi, S, Img, Sb>(self, source: S, destination: &Arc<Img>,
+                                                         plane: u32, mip_level: u32,
+                                                         array_layers_range: Range<u32>,
+                                                         offset: [u32; 3], extent: [u32; 3])
+                                                    -> PrimaryCommandBufferBuilder<P>
+        where S: Into<BufferSlice<'a, [Pi], Sb>>, Sb: Buffer + 'static,
+              Img: ImageContent<Pi> + 'static
+    {
+        unsafe {
+            PrimaryCommandBufferBuilder {
+                inner: self.inner.copy_buffer_to_image_plane(source, destination, plane, mip_level,
+                                                             array_layers_range, offset, extent),
+            }
+        }
+    }
+
+    /// Copies from one plane of an image with a multi-planar format to a buffer.
+    ///
+    /// See `Format::plane_format` and `Format::plane_extent` for the format and the dimensions
+    /// of the plane.
+    pub fn copy_image_plane_to_buffer<'a, Pi, S, Img, Sb>(self, dest: S, source: &Arc<Img>,
+                                                         plane: u32, mip_level: u32,
+                                                         array_layers_range: Range<u32>,
+                                                         offset: [u32; 3], extent: [u32; 3])
+                                                    -> PrimaryCommandBufferBuilder<P>
+        where S: Into<BufferSlice<'a, [Pi], Sb>>, Sb: Buffer + 'static,
+              Img: ImageContent<Pi> + 'static
+    {
+        unsafe {
+            PrimaryCommandBufferBuilder {
+                inner: self.inner.copy_image_plane_to_buffer(dest, source, plane, mip_level,
+                                                             array_layers_range, offset, extent),
+            }
+        }
+    }
+
     pub fn blit<Si, Di>(self, source: &Arc<Si>, source_mip_level: u32,
                         source_array_layers: Range<u32>, src_coords: [Range<i32>; 3],
                         destination: &Arc<Di>, dest_mip_level: u32,
@@ -231,6 +390,167 @@ impl<P> PrimaryCommandBufferBuilder<P> where P: CommandPool {
         }
     }
 
+    /// Executes a compute pipeline once for each of `elements` elements.
+    ///
+    /// The number of working groups is computed from the local size of the pipeline. See
+    /// `ComputePipeline::group_counts_for_elements` for more info.
+    #[inline]
+    pub fn dispatch_for_elements<Pl, L, Pc>(self, pipeline: &Arc<ComputePipeline<Pl>>, sets: L,
+                                            elements: u32, push_constants: &Pc)
+                                            -> PrimaryCommandBufferBuilder<P>
+        where L: DescriptorSetsCollection + Send + Sync,
+              Pl: 'static + PipelineLayout + Send + Sync,
+              Pc: 'static + Clone + Send + Sync
+    {
+        let dimensions = pipeline.group_counts_for_elements(elements);
+        self.dispatch(pipeline, sets, dimensions, push_constants)
+    }
+
+    /// Resets the queries of `pool` in the range `slots`.
+    ///
+    /// A query must be reset before it can be used again with `begin_query`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `slots` is out of range of the pool.
+    ///
+    #[inline]
+    pub fn reset_query_pool<Q>(self, pool: &Arc<Q>, slots: Range<u32>)
+                               -> PrimaryCommandBufferBuilder<P>
+        where Q: QueryPool
+    {
+        unsafe {
+            PrimaryCommandBufferBuilder {
+                inner: self.inner.reset_query_pool(pool, slots)
+            }
+        }
+    }
+
+    /// Starts the query of `pool` at the given slot.
+    ///
+    /// The query will track the commands until `end_query` is called. If `precise` is true, an
+    /// occlusion query will return the exact number of samples that passed instead of any
+    /// non-zero value.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `slot` is out of range of the pool.
+    /// - Panics if `precise` is true and the pool is not an occlusion pool, or if the
+    ///   `occlusion_query_precise` feature isn't enabled.
+    /// - Panics if the queue family doesn't support graphics or compute operations.
+    ///
+    /// # Safety
+    ///
+    /// - The slot must have been reset and must not be active.
+    ///
+    #[inline]
+    pub unsafe fn begin_query<Q>(self, pool: &Arc<Q>, slot: u32, precise: bool)
+                                 -> PrimaryCommandBufferBuilder<P>
+        where Q: QueryPool
+    {
+        PrimaryCommandBufferBuilder {
+            inner: self.inner.begin_query(pool, slot, precise)
+        }
+    }
+
+    /// Ends the query of `pool` at the given slot.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `slot` is out of range of the pool.
+    ///
+    /// # Safety
+    ///
+    /// - The query must have been started with `begin_query` outside of a render pass.
+    ///
+    #[inline]
+    pub unsafe fn end_query<Q>(self, pool: &Arc<Q>, slot: u32) -> PrimaryCommandBufferBuilder<P>
+        where Q: QueryPool
+    {
+        PrimaryCommandBufferBuilder {
+            inner: self.inner.end_query(pool, slot)
+        }
+    }
+
+    /// Writes in the given slot of `pool` the moment when all the previous commands have
+    /// reached `stage`.
+    ///
+    /// The value is a number of ticks. Use `TimestampQueryPool::ticks_to_nanoseconds` to convert
+    /// it to nanoseconds.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the pool is not a timestamp pool or if `slot` is out of range of the pool.
+    /// - Panics if `stage` doesn't contain exactly one stage.
+    /// - Panics if the queue family doesn't support timestamps.
+    ///
+    /// # Safety
+    ///
+    /// - The slot must have been reset.
+    ///
+    #[inline]
+    pub unsafe fn write_timestamp<Q>(self, pool: &Arc<Q>, stage: PipelineStages, slot: u32)
+                                     -> PrimaryCommandBufferBuilder<P>
+        where Q: QueryPool
+    {
+        PrimaryCommandBufferBuilder {
+            inner: self.inner.write_timestamp(pool, stage, slot)
+        }
+    }
+
+    /// Copies the results of the queries of `pool` in the range `slots` into a buffer.
+    ///
+    /// The results are written as 64-bit values starting at `offset`. If `with_availability` is
+    /// true, a non-zero value is written after the values of each query whose result was
+    /// available. This can be used to let the GPU itself decide what to draw, for example with
+    /// indirect draw commands.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `slots` is out of range of the pool.
+    /// - Panics if the results don't fit in the buffer or if `offset` is not a multiple of 8.
+    /// - Panics if the buffer wasn't created with the right usage.
+    /// - Panics if partial results are requested for timestamp queries.
+    ///
+    #[inline]
+    pub fn copy_query_pool_results<Q, B>(self, pool: &Arc<Q>, slots: Range<u32>,
+                                         buffer: &Arc<B>, offset: usize, flags: QueryResultFlags,
+                                         with_availability: bool)
+                                         -> PrimaryCommandBufferBuilder<P>
+        where Q: QueryPool, B: Buffer + 'static
+    {
+        unsafe {
+            PrimaryCommandBufferBuilder {
+                inner: self.inner.copy_query_pool_results(pool, slots, buffer, offset, flags,
+                                                          with_availability)
+            }
+        }
+    }
+
+    /// Writes the result of the occlusion query of `pool` at the given slot to the predicate
+    /// of the given index, waiting on the GPU for the result to be available.
+    ///
+    /// The predicate is then used by `draw_predicated`. See `OcclusionPredicates`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the pool is not an occlusion pool or if `slot` is out of range of the pool.
+    /// - Panics if `index` is out of range of the predicates.
+    /// - Panics if the predicates are emulated and the query was started as precise by this
+    ///   command buffer.
+    ///
+    #[inline]
+    pub fn write_predicate<Q>(self, predicates: &Arc<OcclusionPredicates>, index: usize,
+                              pool: &Arc<Q>, slot: u32) -> PrimaryCommandBufferBuilder<P>
+        where Q: QueryPool
+    {
+        unsafe {
+            PrimaryCommandBufferBuilder {
+                inner: self.inner.write_predicate(predicates, index, pool, slot)
+            }
+        }
+    }
+
     /// Start drawing on a framebuffer.
     //
     /// This function returns an object that can be used to submit draw commands on the first
@@ -329,17 +649,35 @@ pub struct PrimaryCommandBufferBuilderInlineDraw<P = Arc<StandardCommandPool>>
 impl<P> PrimaryCommandBufferBuilderInlineDraw<P> where P: CommandPool {
     /// Calls `vkCmdDraw`.
     // FIXME: push constants
+    #[inline]
     pub fn draw<V, L, Pv, Pl, Rp, Pc>(self, pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
                               vertices: V, dynamic: &DynamicState, sets: L, push_constants: &Pc)
                               -> PrimaryCommandBufferBuilderInlineDraw<P>
         where Pv: VertexSource<V> + 'static, Pl: PipelineLayout + 'static + Send + Sync, Rp: 'static + Send + Sync,
               L: DescriptorSetsCollection + Send + Sync, Pc: 'static + Clone + Send + Sync
+    {
+        self.draw_with_params(pipeline, vertices, &DrawParams::default(), dynamic, sets,
+                              push_constants)
+    }
+
+    /// Calls `vkCmdDraw` with the range of vertices and instances of `params`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the range of vertices or instances of `params` is out of range of the vertex
+    ///   buffers.
+    ///
+    pub fn draw_with_params<V, L, Pv, Pl, Rp, Pc>(self, pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
+                              vertices: V, params: &DrawParams, dynamic: &DynamicState, sets: L,
+                              push_constants: &Pc) -> PrimaryCommandBufferBuilderInlineDraw<P>
+        where Pv: VertexSource<V> + 'static, Pl: PipelineLayout + 'static + Send + Sync, Rp: 'static + Send + Sync,
+              L: DescriptorSetsCollection + Send + Sync, Pc: 'static + Clone + Send + Sync
     {
         // FIXME: check subpass
 
         unsafe {
             PrimaryCommandBufferBuilderInlineDraw {
-                inner: self.inner.draw(pipeline, vertices, dynamic, sets, push_constants),
+                inner: self.inner.draw(pipeline, vertices, params, dynamic, sets, push_constants),
                 num_subpasses: self.num_subpasses,
                 current_subpass: self.current_subpass,
             }
@@ -347,24 +685,227 @@ impl<P> PrimaryCommandBufferBuilderInlineDraw<P> where P: CommandPool {
     }
 
     /// Calls `vkCmdDrawIndexed`.
+    ///
+    /// The type of the indices is determined by the type of the elements of `indices`. See the
+    /// `Index` trait.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the buffer of `indices` wasn't created with the `index_buffer` usage.
+    /// - Panics if the indices are `u8` and the `ext_index_type_uint8` extension isn't enabled.
+    /// - Panics if the device uses `BufferAccessChecks::Strict` without the
+    ///   `robust_buffer_access` feature and the vertex buffers don't contain enough vertices for
+    ///   all the possible values of the indices.
+    ///
+    #[inline]
     pub fn draw_indexed<'a, V, L, Pv, Pl, Rp, I, Ib, Ibb, Pc>(self, pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
                                               vertices: V, indices: Ib, dynamic: &DynamicState,
                                               sets: L, push_constants: &Pc) -> PrimaryCommandBufferBuilderInlineDraw<P>
         where Pv: 'static + VertexSource<V> + Send + Sync, Pl: 'static + PipelineLayout + Send + Sync, Rp: 'static + Send + Sync,
               Ib: Into<BufferSlice<'a, [I], Ibb>>, I: 'static + Index, Ibb: Buffer + 'static + Send + Sync,
               L: DescriptorSetsCollection + Send + Sync, Pc: 'static + Clone + Send + Sync
+    {
+        self.draw_indexed_with_params(pipeline, vertices, indices, &DrawParams::default(),
+                                      dynamic, sets, push_constants)
+    }
+
+    /// Calls `vkCmdDrawIndexed` with the range of indices and instances and the vertex offset
+    /// of `params`.
+    ///
+    /// This makes it possible to draw an object whose indices and vertices are stored in a part
+    /// of larger buffers shared with other objects.
+    ///
+    /// # Panic
+    ///
+    /// - Panics for the same reasons as `draw_indexed`. When the checks are strict,
+    ///   `params.vertex_offset` is added to all the possible values of the indices.
+    /// - Panics if the range of indices or instances of `params` is out of range of the index or
+    ///   vertex buffers.
+    /// - Panics if the device uses `BufferAccessChecks::Strict` without the
+    ///   `robust_buffer_access` feature and `params.vertex_offset` is negative.
+    ///
+    pub fn draw_indexed_with_params<'a, V, L, Pv, Pl, Rp, I, Ib, Ibb, Pc>(self, pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
+                                              vertices: V, indices: Ib, params: &DrawParams,
+                                              dynamic: &DynamicState, sets: L, push_constants: &Pc)
+                                              -> PrimaryCommandBufferBuilderInlineDraw<P>
+        where Pv: 'static + VertexSource<V> + Send + Sync, Pl: 'static + PipelineLayout + Send + Sync, Rp: 'static + Send + Sync,
+              Ib: Into<BufferSlice<'a, [I], Ibb>>, I: 'static + Index, Ibb: Buffer + 'static + Send + Sync,
+              L: DescriptorSetsCollection + Send + Sync, Pc: 'static + Clone + Send + Sync
+    {
+        // FIXME: check subpass
+
+        unsafe {
+            PrimaryCommandBufferBuilderInlineDraw {
+                inner: self.inner.draw_indexed(pipeline, vertices, indices, params, dynamic, sets,
+                                               push_constants),
+                num_subpasses: self.num_subpasses,
+                current_subpass: self.current_subpass,
+            }
+        }
+    }
+
+    /// Draws the vertices of the predicate of the given index, if the predicate is true.
+    ///
+    /// See `OcclusionPredicates`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `index` is out of range of the predicates.
+    /// - Panics if the predicates are emulated and the device uses `BufferAccessChecks::Strict`
+    ///   without the `robust_buffer_access` feature.
+    ///
+    pub fn draw_predicated<V, L, Pv, Pl, Rp, Pc>(self, pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
+                              vertices: V, predicates: &Arc<OcclusionPredicates>, index: usize,
+                              dynamic: &DynamicState, sets: L, push_constants: &Pc)
+                              -> PrimaryCommandBufferBuilderInlineDraw<P>
+        where Pv: VertexSource<V> + 'static, Pl: PipelineLayout + 'static + Send + Sync, Rp: 'static + Send + Sync,
+              L: DescriptorSetsCollection + Send + Sync, Pc: 'static + Clone + Send + Sync
     {
         // FIXME: check subpass
 
         unsafe {
             PrimaryCommandBufferBuilderInlineDraw {
-                inner: self.inner.draw_indexed(pipeline, vertices, indices, dynamic, sets, push_constants),
+                inner: self.inner.draw_predicated(pipeline, vertices, predicates, index, dynamic,
+                                                  sets, push_constants),
                 num_subpasses: self.num_subpasses,
                 current_subpass: self.current_subpass,
             }
         }
     }
 
+    /// Calls `vkCmdDrawIndirectCountKHR`.
+    ///
+    /// Reads draw commands from `buffer`. Their number is read by the GPU from the 32-bit value
+    /// at `count_offset` in `count_buffer`, and is clamped to `max_draw_count`. This makes it
+    /// possible for a compute shader to write both the commands and their number.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the `khr_draw_indirect_count` extension isn't enabled.
+    /// - Panics if the device uses `BufferAccessChecks::Strict` without the
+    ///   `robust_buffer_access` feature.
+    /// - Panics if `max_draw_count` is larger than the number of commands of `buffer`.
+    /// - Panics if the count isn't in `count_buffer` or if `count_offset` isn't a multiple of 4.
+    /// - Panics if the buffers weren't created with the `indirect_buffer` usage.
+    ///
+    pub fn draw_indirect_count<I, C, V, L, Pv, Pl, Rp, Pc>(self, buffer: &Arc<I>,
+                              count_buffer: &Arc<C>, count_offset: usize, max_draw_count: u32,
+                              pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>, vertices: V,
+                              dynamic: &DynamicState, sets: L, push_constants: &Pc)
+                              -> PrimaryCommandBufferBuilderInlineDraw<P>
+        where Pv: 'static + VertexSource<V>, L: DescriptorSetsCollection + Send + Sync,
+              Pl: 'static + PipelineLayout + Send + Sync, Rp: 'static + Send + Sync,
+              Pc: 'static + Clone + Send + Sync,
+              I: 'static + TypedBuffer<Content = [DrawIndirectCommand]>, C: Buffer + 'static
+    {
+        // FIXME: check subpass
+
+        unsafe {
+            PrimaryCommandBufferBuilderInlineDraw {
+                inner: self.inner.draw_indirect_count(buffer, count_buffer, count_offset,
+                                                      max_draw_count, pipeline, vertices, dynamic,
+                                                      sets, push_constants),
+                num_subpasses: self.num_subpasses,
+                current_subpass: self.current_subpass,
+            }
+        }
+    }
+
+    /// Calls `vkCmdDrawIndexedIndirectCountKHR`.
+    ///
+    /// Same as `draw_indirect_count`, but with indexed draw commands and the indices of
+    /// `indices`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics for the same reasons as `draw_indirect_count`.
+    /// - Panics if the buffer of `indices` wasn't created with the `index_buffer` usage.
+    /// - Panics if the indices are `u8` and the `ext_index_type_uint8` extension isn't enabled.
+    ///
+    pub fn draw_indexed_indirect_count<'a, Ic, C, V, L, Pv, Pl, Rp, I, Ib, Ibb, Pc>(self,
+                              buffer: &Arc<Ic>, count_buffer: &Arc<C>, count_offset: usize,
+                              max_draw_count: u32, pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
+                              vertices: V, indices: Ib, dynamic: &DynamicState, sets: L,
+                              push_constants: &Pc) -> PrimaryCommandBufferBuilderInlineDraw<P>
+        where Pv: 'static + VertexSource<V>, L: DescriptorSetsCollection + Send + Sync,
+              Pl: 'static + PipelineLayout + Send + Sync, Rp: 'static + Send + Sync,
+              Pc: 'static + Clone + Send + Sync,
+              Ib: Into<BufferSlice<'a, [I], Ibb>>, I: 'static + Index, Ibb: Buffer + 'static,
+              Ic: 'static + TypedBuffer<Content = [DrawIndexedIndirectCommand]>,
+              C: Buffer + 'static
+    {
+        // FIXME: check subpass
+
+        unsafe {
+            PrimaryCommandBufferBuilderInlineDraw {
+                inner: self.inner.draw_indexed_indirect_count(buffer, count_buffer, count_offset,
+                                                              max_draw_count, pipeline, vertices,
+                                                              indices, dynamic, sets,
+                                                              push_constants),
+                num_subpasses: self.num_subpasses,
+                current_subpass: self.current_subpass,
+            }
+        }
+    }
+
+    /// Starts the query of `pool` at the given slot.
+    ///
+    /// See the documentation of `begin_query` on `PrimaryCommandBufferBuilder`.
+    ///
+    /// # Safety
+    ///
+    /// - The slot must have been reset and must not be active.
+    ///
+    #[inline]
+    pub unsafe fn begin_query<Q>(self, pool: &Arc<Q>, slot: u32, precise: bool)
+                                 -> PrimaryCommandBufferBuilderInlineDraw<P>
+        where Q: QueryPool
+    {
+        PrimaryCommandBufferBuilderInlineDraw {
+            inner: self.inner.begin_query(pool, slot, precise),
+            num_subpasses: self.num_subpasses,
+            current_subpass: self.current_subpass,
+        }
+    }
+
+    /// Writes a timestamp in the given slot of `pool`.
+    ///
+    /// See the documentation of `write_timestamp` on `PrimaryCommandBufferBuilder`.
+    ///
+    /// # Safety
+    ///
+    /// - The slot must have been reset.
+    ///
+    #[inline]
+    pub unsafe fn write_timestamp<Q>(self, pool: &Arc<Q>, stage: PipelineStages, slot: u32)
+                                     -> PrimaryCommandBufferBuilderInlineDraw<P>
+        where Q: QueryPool
+    {
+        PrimaryCommandBufferBuilderInlineDraw {
+            inner: self.inner.write_timestamp(pool, stage, slot),
+            num_subpasses: self.num_subpasses,
+            current_subpass: self.current_subpass,
+        }
+    }
+
+    /// Ends the query of `pool` at the given slot.
+    ///
+    /// # Safety
+    ///
+    /// - The query must have been started with `begin_query` in the same subpass.
+    ///
+    #[inline]
+    pub unsafe fn end_query<Q>(self, pool: &Arc<Q>, slot: u32)
+                               -> PrimaryCommandBufferBuilderInlineDraw<P>
+        where Q: QueryPool
+    {
+        PrimaryCommandBufferBuilderInlineDraw {
+            inner: self.inner.end_query(pool, slot),
+            num_subpasses: self.num_subpasses,
+            current_subpass: self.current_subpass,
+        }
+    }
+
     /// Switches to the next subpass of the current renderpass.
     ///
     /// This function is similar to `draw_inline` on the builder.
@@ -537,6 +1078,8 @@ pub struct PrimaryCommandBuffer<P = Arc<StandardCommandPool>> where P: CommandPo
 ///
 /// Fences and semaphores are automatically handled.
 ///
+/// Returns `QueueError::DeviceLost` without submitting anything if the device has been lost.
+///
 /// # Panic
 ///
 /// - Panics if the queue doesn't belong to the device this command buffer was created with.
@@ -544,11 +1087,40 @@ pub struct PrimaryCommandBuffer<P = Arc<StandardCommandPool>> where P: CommandPo
 ///
 #[inline]
 pub fn submit<P>(cmd: &Arc<PrimaryCommandBuffer<P>>, queue: &Arc<Queue>)
-                 -> Result<Arc<Submission>, OomError>
+                 -> Result<Arc<Submission>, QueueError>
+    where P: CommandPool + 'static,
+          P::Finished: Send + Sync + 'static
+{
+    inner_submit(&cmd.inner, cmd.clone() as Arc<_>, queue, &[], &[])
+}
+
+/// Same as `submit`, but the submission also waits upon `wait_semaphores` at the given stages
+/// and signals `signal_semaphores` once it has finished.
+///
+/// This is the function that the `GpuFuture` trait and `SubmitGraph` use to chain operations.
+/// Prefer them when possible, as they manage the semaphores for you.
+///
+/// # Safety
+///
+/// - Each semaphore of `wait_semaphores` must have a signal operation that has been submitted
+///   before, and must not be waited upon by any other operation.
+/// - The semaphores of `signal_semaphores` must be unsignaled and must not have any pending
+///   signal operation.
+///
+/// # Panic
+///
+/// - Panics if the queue doesn't belong to the device this command buffer was created with.
+/// - Panics if the queue doesn't belong to the family the pool was created with.
+///
+#[inline]
+pub unsafe fn submit_with_semaphores<P>(cmd: &Arc<PrimaryCommandBuffer<P>>, queue: &Arc<Queue>,
+                                        wait_semaphores: &[(Arc<Semaphore>, PipelineStages)],
+                                        signal_semaphores: &[Arc<Semaphore>])
+                                        -> Result<Arc<Submission>, QueueError>
     where P: CommandPool + 'static,
           P::Finished: Send + Sync + 'static
-{       // TODO: wrong error type
-    inner_submit(&cmd.inner, cmd.clone() as Arc<_>, queue)
+{
+    inner_submit(&cmd.inner, cmd.clone() as Arc<_>, queue, wait_semaphores, signal_semaphores)
 }
 
 /// A prototype of a secondary compute command buffer.
@@ -605,19 +1177,38 @@ impl<R, P> SecondaryGraphicsCommandBufferBuilder<R, P>
 
     /// Calls `vkCmdDraw`.
     // FIXME: push constants
+    #[inline]
     pub fn draw<V, L, Pv, Pl, Rp, Pc>(self, pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
                               vertices: V, dynamic: &DynamicState, sets: L, push_constants: &Pc)
                               -> SecondaryGraphicsCommandBufferBuilder<R, P>
         where Pv: VertexSource<V> + 'static, Pl: PipelineLayout + 'static + Send + Sync,
               Rp: RenderPass + RenderPassDesc + 'static + Send + Sync, L: DescriptorSetsCollection + Send + Sync,
               R: RenderPassCompatible<Rp>, Pc: 'static + Clone + Send + Sync
+    {
+        self.draw_with_params(pipeline, vertices, &DrawParams::default(), dynamic, sets,
+                              push_constants)
+    }
+
+    /// Calls `vkCmdDraw` with the range of vertices and instances of `params`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the range of vertices or instances of `params` is out of range of the vertex
+    ///   buffers.
+    ///
+    pub fn draw_with_params<V, L, Pv, Pl, Rp, Pc>(self, pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
+                              vertices: V, params: &DrawParams, dynamic: &DynamicState, sets: L,
+                              push_constants: &Pc) -> SecondaryGraphicsCommandBufferBuilder<R, P>
+        where Pv: VertexSource<V> + 'static, Pl: PipelineLayout + 'static + Send + Sync,
+              Rp: RenderPass + RenderPassDesc + 'static + Send + Sync, L: DescriptorSetsCollection + Send + Sync,
+              R: RenderPassCompatible<Rp>, Pc: 'static + Clone + Send + Sync
     {
         assert!(self.render_pass.is_compatible_with(pipeline.subpass().render_pass()));
         assert_eq!(self.render_pass_subpass, pipeline.subpass().index());
 
         unsafe {
             SecondaryGraphicsCommandBufferBuilder {
-                inner: self.inner.draw(pipeline, vertices, dynamic, sets, push_constants),
+                inner: self.inner.draw(pipeline, vertices, params, dynamic, sets, push_constants),
                 render_pass: self.render_pass,
                 render_pass_subpass: self.render_pass_subpass,
                 framebuffer: self.framebuffer,
@@ -626,6 +1217,19 @@ impl<R, P> SecondaryGraphicsCommandBufferBuilder<R, P>
     }
 
     /// Calls `vkCmdDrawIndexed`.
+    ///
+    /// The type of the indices is determined by the type of the elements of `indices`. See the
+    /// `Index` trait.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the buffer of `indices` wasn't created with the `index_buffer` usage.
+    /// - Panics if the indices are `u8` and the `ext_index_type_uint8` extension isn't enabled.
+    /// - Panics if the device uses `BufferAccessChecks::Strict` without the
+    ///   `robust_buffer_access` feature and the vertex buffers don't contain enough vertices for
+    ///   all the possible values of the indices.
+    ///
+    #[inline]
     pub fn draw_indexed<'a, V, L, Pv, Pl, Rp, I, Ib, Ibb, Pc>(self, pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
                                               vertices: V, indices: Ib, dynamic: &DynamicState,
                                               sets: L, push_constants: &Pc) -> SecondaryGraphicsCommandBufferBuilder<R, P>
@@ -633,13 +1237,153 @@ impl<R, P> SecondaryGraphicsCommandBufferBuilder<R, P>
               Rp: RenderPass + RenderPassDesc + 'static + Send + Sync,
               Ib: Into<BufferSlice<'a, [I], Ibb>>, I: 'static + Index, Ibb: Buffer + 'static,
               L: DescriptorSetsCollection + Send + Sync, Pc: 'static + Clone + Send + Sync
+    {
+        self.draw_indexed_with_params(pipeline, vertices, indices, &DrawParams::default(),
+                                      dynamic, sets, push_constants)
+    }
+
+    /// Calls `vkCmdDrawIndexed` with the range of indices and instances and the vertex offset
+    /// of `params`.
+    ///
+    /// This makes it possible to draw an object whose indices and vertices are stored in a part
+    /// of larger buffers shared with other objects.
+    ///
+    /// # Panic
+    ///
+    /// - Panics for the same reasons as `draw_indexed`. When the checks are strict,
+    ///   `params.vertex_offset` is added to all the possible values of the indices.
+    /// - Panics if the range of indices or instances of `params` is out of range of the index or
+    ///   vertex buffers.
+    /// - Panics if the device uses `BufferAccessChecks::Strict` without the
+    ///   `robust_buffer_access` feature and `params.vertex_offset` is negative.
+    ///
+    pub fn draw_indexed_with_params<'a, V, L, Pv, Pl, Rp, I, Ib, Ibb, Pc>(self, pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
+                                              vertices: V, indices: Ib, params: &DrawParams,
+                                              dynamic: &DynamicState, sets: L, push_constants: &Pc)
+                                              -> SecondaryGraphicsCommandBufferBuilder<R, P>
+        where Pv: 'static + VertexSource<V>, Pl: 'static + PipelineLayout + Send + Sync,
+              Rp: RenderPass + RenderPassDesc + 'static + Send + Sync,
+              Ib: Into<BufferSlice<'a, [I], Ibb>>, I: 'static + Index, Ibb: Buffer + 'static,
+              L: DescriptorSetsCollection + Send + Sync, Pc: 'static + Clone + Send + Sync
     {
         assert!(self.render_pass.is_compatible_with(pipeline.subpass().render_pass()));
         assert_eq!(self.render_pass_subpass, pipeline.subpass().index());
 
         unsafe {
             SecondaryGraphicsCommandBufferBuilder {
-                inner: self.inner.draw_indexed(pipeline, vertices, indices, dynamic, sets, push_constants),
+                inner: self.inner.draw_indexed(pipeline, vertices, indices, params, dynamic, sets,
+                                               push_constants),
+                render_pass: self.render_pass,
+                render_pass_subpass: self.render_pass_subpass,
+                framebuffer: self.framebuffer,
+            }
+        }
+    }
+
+    /// Draws the vertices of the predicate of the given index, if the predicate is true.
+    ///
+    /// See `OcclusionPredicates`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `index` is out of range of the predicates.
+    /// - Panics if the predicates are emulated and the device uses `BufferAccessChecks::Strict`
+    ///   without the `robust_buffer_access` feature.
+    ///
+    pub fn draw_predicated<V, L, Pv, Pl, Rp, Pc>(self, pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
+                              vertices: V, predicates: &Arc<OcclusionPredicates>, index: usize,
+                              dynamic: &DynamicState, sets: L, push_constants: &Pc)
+                              -> SecondaryGraphicsCommandBufferBuilder<R, P>
+        where Pv: VertexSource<V> + 'static, Pl: PipelineLayout + 'static + Send + Sync,
+              Rp: RenderPass + RenderPassDesc + 'static + Send + Sync, L: DescriptorSetsCollection + Send + Sync,
+              R: RenderPassCompatible<Rp>, Pc: 'static + Clone + Send + Sync
+    {
+        assert!(self.render_pass.is_compatible_with(pipeline.subpass().render_pass()));
+        assert_eq!(self.render_pass_subpass, pipeline.subpass().index());
+
+        unsafe {
+            SecondaryGraphicsCommandBufferBuilder {
+                inner: self.inner.draw_predicated(pipeline, vertices, predicates, index, dynamic,
+                                                  sets, push_constants),
+                render_pass: self.render_pass,
+                render_pass_subpass: self.render_pass_subpass,
+                framebuffer: self.framebuffer,
+            }
+        }
+    }
+
+    /// Calls `vkCmdDrawIndirectCountKHR`.
+    ///
+    /// Reads draw commands from `buffer`. Their number is read by the GPU from the 32-bit value
+    /// at `count_offset` in `count_buffer`, and is clamped to `max_draw_count`. This makes it
+    /// possible for a compute shader to write both the commands and their number.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the `khr_draw_indirect_count` extension isn't enabled.
+    /// - Panics if the device uses `BufferAccessChecks::Strict` without the
+    ///   `robust_buffer_access` feature.
+    /// - Panics if `max_draw_count` is larger than the number of commands of `buffer`.
+    /// - Panics if the count isn't in `count_buffer` or if `count_offset` isn't a multiple of 4.
+    /// - Panics if the buffers weren't created with the `indirect_buffer` usage.
+    ///
+    pub fn draw_indirect_count<I, C, V, L, Pv, Pl, Rp, Pc>(self, buffer: &Arc<I>,
+                              count_buffer: &Arc<C>, count_offset: usize, max_draw_count: u32,
+                              pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>, vertices: V,
+                              dynamic: &DynamicState, sets: L, push_constants: &Pc)
+                              -> SecondaryGraphicsCommandBufferBuilder<R, P>
+        where Pv: 'static + VertexSource<V>, L: DescriptorSetsCollection + Send + Sync,
+              Pl: 'static + PipelineLayout + Send + Sync, Rp: RenderPass + RenderPassDesc + 'static + Send + Sync,
+              R: RenderPassCompatible<Rp>, Pc: 'static + Clone + Send + Sync,
+              I: 'static + TypedBuffer<Content = [DrawIndirectCommand]>, C: Buffer + 'static
+    {
+        assert!(self.render_pass.is_compatible_with(pipeline.subpass().render_pass()));
+        assert_eq!(self.render_pass_subpass, pipeline.subpass().index());
+
+        unsafe {
+            SecondaryGraphicsCommandBufferBuilder {
+                inner: self.inner.draw_indirect_count(buffer, count_buffer, count_offset,
+                                                      max_draw_count, pipeline, vertices, dynamic,
+                                                      sets, push_constants),
+                render_pass: self.render_pass,
+                render_pass_subpass: self.render_pass_subpass,
+                framebuffer: self.framebuffer,
+            }
+        }
+    }
+
+    /// Calls `vkCmdDrawIndexedIndirectCountKHR`.
+    ///
+    /// Same as `draw_indirect_count`, but with indexed draw commands and the indices of
+    /// `indices`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics for the same reasons as `draw_indirect_count`.
+    /// - Panics if the buffer of `indices` wasn't created with the `index_buffer` usage.
+    /// - Panics if the indices are `u8` and the `ext_index_type_uint8` extension isn't enabled.
+    ///
+    pub fn draw_indexed_indirect_count<'a, Ic, C, V, L, Pv, Pl, Rp, I, Ib, Ibb, Pc>(self,
+                              buffer: &Arc<Ic>, count_buffer: &Arc<C>, count_offset: usize,
+                              max_draw_count: u32, pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
+                              vertices: V, indices: Ib, dynamic: &DynamicState, sets: L,
+                              push_constants: &Pc) -> SecondaryGraphicsCommandBufferBuilder<R, P>
+        where Pv: 'static + VertexSource<V>, L: DescriptorSetsCollection + Send + Sync,
+              Pl: 'static + PipelineLayout + Send + Sync, Rp: RenderPass + RenderPassDesc + 'static + Send + Sync,
+              R: RenderPassCompatible<Rp>, Pc: 'static + Clone + Send + Sync,
+              Ib: Into<BufferSlice<'a, [I], Ibb>>, I: 'static + Index, Ibb: Buffer + 'static,
+              Ic: 'static + TypedBuffer<Content = [DrawIndexedIndirectCommand]>,
+              C: Buffer + 'static
+    {
+        assert!(self.render_pass.is_compatible_with(pipeline.subpass().render_pass()));
+        assert_eq!(self.render_pass_subpass, pipeline.subpass().index());
+
+        unsafe {
+            SecondaryGraphicsCommandBufferBuilder {
+                inner: self.inner.draw_indexed_indirect_count(buffer, count_buffer, count_offset,
+                                                              max_draw_count, pipeline, vertices,
+                                                              indices, dynamic, sets,
+                                                              push_constants),
                 render_pass: self.render_pass,
                 render_pass_subpass: self.render_pass_subpass,
                 framebuffer: self.framebuffer,
@@ -648,6 +1392,12 @@ impl<R, P> SecondaryGraphicsCommandBufferBuilder<R, P>
     }
 
     /// Calls `vkCmdDrawIndirect`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device uses `BufferAccessChecks::Strict` without the
+    ///   `robust_buffer_access` feature.
+    ///
     pub fn draw_indirect<I, V, Pv, Pl, L, Rp, Pc>(self, buffer: &Arc<I>, pipeline: &Arc<GraphicsPipeline<Pv, Pl, Rp>>,
                              vertices: V, dynamic: &DynamicState,
                              sets: L, push_constants: &Pc) -> SecondaryGraphicsCommandBufferBuilder<R, P>
@@ -661,7 +1411,7 @@ impl<R, P> SecondaryGraphicsCommandBufferBuilder<R, P>
 
         unsafe {
             SecondaryGraphicsCommandBufferBuilder {
-                inner: self.inner.draw_indirect(buffer, pipeline, vertices, dynamic, sets, push_constants),
+                inner: self.inner.draw_indirect(buffer, 0 .. buffer.len(), pipeline, vertices, dynamic, sets, push_constants),
                 render_pass: self.render_pass,
                 render_pass_subpass: self.render_pass_subpass,
                 framebuffer: self.framebuffer,
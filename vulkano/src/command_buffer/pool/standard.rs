@@ -39,6 +39,12 @@ fn curr_thread_id() -> usize { THREAD_ID.with(|data| &**data as *const u8 as usi
 ///
 /// Will use one Vulkan pool per thread in order to avoid locking. Will try to reuse command
 /// buffers. Locking is required only when allocating/freeing command buffers.
+///
+/// When a command buffer is destroyed, its Vulkan command buffer isn't freed but kept for the
+/// next command buffer that is built on the same thread. Since the Vulkan pools are created with
+/// the ability to reset command buffers individually, it is implicitly reset when it starts
+/// being recorded again. Rebuilding command buffers every frame therefore doesn't allocate and
+/// free Vulkan command buffers once the pool has enough of them.
 pub struct StandardCommandPool {
     // The device.
     device: Arc<Device>,
@@ -182,12 +188,29 @@ pub struct StandardCommandPoolFinished {
     thread_id: usize,
 }
 
+impl StandardCommandPoolFinished {
+    /// Returns the pool that the command buffer was built from.
+    #[inline]
+    pub fn pool(&self) -> &Arc<StandardCommandPool> {
+        &self.pool
+    }
+
+    /// Returns true if the command buffer was built on the current thread, in which case its
+    /// Vulkan command buffer belongs to the Vulkan pool of this thread.
+    #[inline]
+    pub fn is_current_thread(&self) -> bool {
+        self.thread_id == curr_thread_id()
+    }
+}
+
 unsafe impl CommandPoolFinished for StandardCommandPoolFinished {
     unsafe fn free<I>(&self, secondary: bool, command_buffers: I)
         where I: Iterator<Item = AllocatedCommandBuffer>
     {
+        // The command buffer may be destroyed in a different thread from the one it was built
+        // in. It must be given back to the pool it was allocated from.
         let mut per_thread = self.pool.per_thread.lock().unwrap();
-        let mut per_thread = per_thread.get_mut(&curr_thread_id()).unwrap();
+        let mut per_thread = per_thread.get_mut(&self.thread_id).unwrap();
 
         if secondary {
             for cb in command_buffers {
@@ -214,3 +237,35 @@ unsafe impl CommandPoolFinished for StandardCommandPoolFinished {
 // See `StandardCommandPool` for comments about this.
 unsafe impl Send for StandardCommandPoolFinished {}
 unsafe impl Sync for StandardCommandPoolFinished {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+    use command_buffer::pool::CommandPool;
+    use command_buffer::pool::CommandPoolFinished;
+    use command_buffer::pool::StandardCommandPool;
+    use VulkanObject;
+
+    #[test]
+    fn reuse_command_buffers() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let pool = Arc::new(StandardCommandPool::new(&device, queue.family()));
+
+        let cb = pool.alloc(false, 1).unwrap().next().unwrap();
+        let raw = cb.internal_object();
+        unsafe { pool.free(false, Some(cb).into_iter()); }
+
+        let cb = pool.alloc(false, 1).unwrap().next().unwrap();
+        assert_eq!(cb.internal_object(), raw);
+
+        // Freeing from another thread gives the command buffer back to the original thread.
+        let finished = pool.clone().finish();
+        thread::spawn(move || unsafe {
+            finished.free(false, Some(cb).into_iter());
+        }).join().unwrap();
+
+        let cb = pool.alloc(false, 1).unwrap().next().unwrap();
+        assert_eq!(cb.internal_object(), raw);
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use buffer::BufferUsage;
+use buffer::CpuAccessibleBuffer;
+use command_buffer::DrawIndirectCommand;
+use device::Device;
+use instance::QueueFamily;
+use memory::DeviceMemoryAllocError;
+
+/// Predicates that skip draw commands on the GPU depending on the result of occlusion queries,
+/// without reading the results back on the CPU.
+///
+/// Each predicate is associated to a range of vertices to draw. The result of an occlusion query
+/// is written to a predicate with `write_predicate`, and `draw_predicated` then draws the
+/// vertices of the predicate only if some samples passed the query. A typical use is to draw
+/// the bounding boxes of objects with occlusion queries, and to draw the objects themselves
+/// only if their bounding box is visible.
+///
+/// If the `ext_conditional_rendering` extension is enabled on the device, the predicates use
+/// conditional rendering. Otherwise they are emulated by writing the result of the query to the
+/// instance count of an indirect draw command. Native predicates always draw a single instance,
+/// but emulated ones draw as many instances as the value of the result. For this reason, the
+/// results of precise queries can't be written to emulated predicates. Occlusion queries that
+/// aren't precise usually return 0 or 1, but the implementation is allowed to return larger
+/// values, in which case the vertices are drawn several times. When using emulated predicates,
+/// the draws must produce the same image if they are repeated, which excludes blending and
+/// stencil operations that aren't idempotent as well as writes to storage resources, and the
+/// shaders must not depend on the instance index.
+///
+/// Before a result has been written, the predicates are true.
+///
+/// # Example
+///
+/// ```ignore
+/// let predicates = OcclusionPredicates::new(&device, Some(queue.family()),
+///                                           vec![0 .. 36, 36 .. 1200]).unwrap();
+///
+/// let cb = PrimaryCommandBufferBuilder::new(&device, queue.family())
+///     .reset_query_pool(&pool, 0 .. 1)
+///     .draw_inline(&render_pass, &framebuffer, clear_values);
+/// let cb = unsafe { cb.begin_query(&pool, 0, false) }
+///     .draw(&bounding_box_pipeline, &boxes, &dynamic, (), &());
+/// let cb = unsafe { cb.end_query(&pool, 0) }
+///     .draw_end()
+///     .write_predicate(&predicates, 1, &pool, 0)
+///     .draw_inline(&render_pass, &framebuffer, clear_values)
+///     .draw_predicated(&pipeline, &vertices, &predicates, 1, &dynamic, (), &())
+///     .draw_end()
+///     .build();
+/// ```
+pub struct OcclusionPredicates {
+    // One command per predicate, whose instance count is the value of the predicate.
+    buffer: Arc<CpuAccessibleBuffer<[DrawIndirectCommand]>>,
+    // Range of vertices of each predicate.
+    draws: Vec<Range<u32>>,
+    // True if the predicates use the `ext_conditional_rendering` extension.
+    native: bool,
+}
+
+impl OcclusionPredicates {
+    /// Builds predicates for the given ranges of vertices.
+    ///
+    /// Uses conditional rendering if the `ext_conditional_rendering` extension is enabled on
+    /// the device, and emulates it otherwise.
+    #[inline]
+    pub fn new<'a, I, Q>(device: &Arc<Device>, queue_families: Q, draws: I)
+                         -> Result<Arc<OcclusionPredicates>, DeviceMemoryAllocError>
+        where I: IntoIterator<Item = Range<u32>>,
+              Q: IntoIterator<Item = QueueFamily<'a>>
+    {
+        let native = device.loaded_extensions().ext_conditional_rendering;
+        OcclusionPredicates::new_impl(device, queue_families, draws, native)
+    }
+
+    /// Same as `new`, but always emulates conditional rendering with indirect draws.
+    #[inline]
+    pub fn emulated<'a, I, Q>(device: &Arc<Device>, queue_families: Q, draws: I)
+                              -> Result<Arc<OcclusionPredicates>, DeviceMemoryAllocError>
+        where I: IntoIterator<Item = Range<u32>>,
+              Q: IntoIterator<Item = QueueFamily<'a>>
+    {
+        OcclusionPredicates::new_impl(device, queue_families, draws, false)
+    }
+
+    fn new_impl<'a, I, Q>(device: &Arc<Device>, queue_families: Q, draws: I, native: bool)
+                          -> Result<Arc<OcclusionPredicates>, DeviceMemoryAllocError>
+        where I: IntoIterator<Item = Range<u32>>,
+              Q: IntoIterator<Item = QueueFamily<'a>>
+    {
+        let draws = draws.into_iter().collect::<Vec<_>>();
+        let commands = draws.iter().map(|range| {
+            assert!(range.start <= range.end);
+            DrawIndirectCommand {
+                vertex_count: range.end - range.start,
+                instance_count: 1,
+                first_vertex: range.start,
+                first_instance: 0,
+            }
+        });
+
+        let usage = BufferUsage {
+            transfer_dest: true,
+            indirect_buffer: true,
+            conditional_rendering: native,
+            .. BufferUsage::none()
+        };
+
+        let buffer = try!(CpuAccessibleBuffer::from_iter(device, &usage, queue_families,
+                                                         commands));
+
+        Ok(Arc::new(OcclusionPredicates {
+            buffer: buffer,
+            draws: draws,
+            native: native,
+        }))
+    }
+
+    /// Returns the number of predicates.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.draws.len()
+    }
+
+    /// Returns true if the predicates use the `ext_conditional_rendering` extension, and false
+    /// if they are emulated.
+    #[inline]
+    pub fn is_native(&self) -> bool {
+        self.native
+    }
+
+    /// Returns the buffer that contains one draw command per predicate. The results of the
+    /// queries are written to the `instance_count` of the commands.
+    #[inline]
+    pub fn buffer(&self) -> &Arc<CpuAccessibleBuffer<[DrawIndirectCommand]>> {
+        &self.buffer
+    }
+
+    /// Returns the range of vertices drawn by the predicate of the given index.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `index` is out of range.
+    ///
+    #[inline]
+    pub fn vertices(&self, index: usize) -> Range<u32> {
+        self.draws[index].clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use command_buffer::OcclusionPredicates;
+    use command_buffer::PrimaryCommandBufferBuilder;
+    use query::OcclusionQueriesPool;
+
+    #[test]
+    fn emulated_predicates() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let predicates = OcclusionPredicates::emulated(&device, Some(queue.family()),
+                                                       vec![0 .. 36, 36 .. 100]).unwrap();
+        assert!(!predicates.is_native());
+        assert_eq!(predicates.len(), 2);
+        assert_eq!(predicates.vertices(1), 36 .. 100);
+
+        {
+            let commands = predicates.buffer().read(Duration::from_secs(0)).unwrap();
+            assert_eq!(commands[1].vertex_count, 64);
+            assert_eq!(commands[1].instance_count, 1);
+        }
+
+        let pool = OcclusionQueriesPool::new(&device, 1);
+        let _ = PrimaryCommandBufferBuilder::new(&device, queue.family())
+                    .reset_query_pool(&pool, 0 .. 1)
+                    .write_predicate(&predicates, 1, &pool, 0)
+                    .build();
+    }
+}
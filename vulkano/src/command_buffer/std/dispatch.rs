@@ -62,6 +62,8 @@ impl<'a, L, Pl, S, Pc> DispatchCommand<'a, L, Pl, S, Pc>
     pub fn new(mut previous: L, pipeline: Arc<ComputePipeline<Pl>>, sets: S, dimensions: [u32; 3],
                push_constants: &'a Pc) -> DispatchCommand<'a, L, Pl, S, Pc>
     {
+        sets.check_dynamic_offsets(pipeline.device());
+
         let (sets_state, barrier_loc, barrier) = unsafe {
             sets.extract_states_and_transition(&mut previous)
         };
@@ -160,7 +162,8 @@ unsafe impl<'a, L, Pl, S, Pc> StdCommandsList for DispatchCommand<'a, L, Pl, S,
 
             let sets: SmallVec<[_; 8]> = my_sets.list().collect();      // TODO: ideally shouldn't collect, but there are lifetime problems
             cb.bind_descriptor_sets(false, &**my_pipeline.layout(), 0,
-                                    sets.iter().map(|s| s.inner()), iter::empty());         // TODO: dynamic ranges, and don't bind if not necessary
+                                    sets.iter().map(|s| s.inner()),
+                                    my_sets.dynamic_offsets().iter().cloned());         // TODO: don't bind if not necessary
             cb.push_constants(&**my_pipeline.layout(), ShaderStages::all(), 0,        // TODO: stages
                               &my_push_constants);
             cb.dispatch(my_dimensions[0], my_dimensions[1], my_dimensions[2]);
@@ -54,7 +54,7 @@ pub struct DrawCommand<'a, L, Pv, Pl, Prp, S, Pc>
     // The push constants.   TODO: use Cow
     push_constants: &'a Pc,
     // FIXME: strong typing and state transitions
-    vertex_buffers: SmallVec<[Arc<Buffer>; 4]>,
+    vertex_buffers: SmallVec<[(Arc<Buffer>, usize); 4]>,
     // Actual type of draw.
     inner: DrawInner,
 }
@@ -88,6 +88,8 @@ impl<'a, L, Pv, Pl, Prp, S, Pc> DrawCommand<'a, L, Pv, Pl, Prp, S, Pc>
                       -> DrawCommand<'a, L, Pv, Pl, Prp, S, Pc>
         where Pv: Source<V>
     {
+        sets.check_dynamic_offsets(PipelineLayout::inner(&**pipeline.layout()).device());
+
         let (sets_state, barrier_loc, barrier) = unsafe {
             sets.extract_states_and_transition(&mut previous)
         };
@@ -199,11 +201,12 @@ unsafe impl<'a, L, Pv, Pl, Prp, S, Pc> StdCommandsList for DrawCommand<'a, L, Pv
 
             let sets: SmallVec<[_; 8]> = my_sets.list().collect();      // TODO: ideally shouldn't collect, but there are lifetime problems
             cb.bind_descriptor_sets(true, &**my_pipeline.layout(), 0,
-                                    sets.iter().map(|s| s.inner()), iter::empty());         // TODO: dynamic ranges, and don't bind if not necessary
+                                    sets.iter().map(|s| s.inner()),
+                                    my_sets.dynamic_offsets().iter().cloned());         // TODO: don't bind if not necessary
             cb.push_constants(&**my_pipeline.layout(), ShaderStages::all(), 0,        // TODO: stages
                               &my_push_constants);
 
-            cb.bind_vertex_buffers(0, my_vertex_buffers.iter().map(|buf| (buf.inner(), 0)));
+            cb.bind_vertex_buffers(0, my_vertex_buffers.iter().map(|&(ref buf, off)| (buf.inner(), off)));
 
             match my_inner {
                 DrawInner::Regular { vertex_count, instance_count,
@@ -300,7 +303,7 @@ pub struct DrawCommandCb<L, Pv, Pl, Prp, S>
     // State of the descriptor sets.
     sets_state: S::Finished,
     // FIXME: strong typing and state transitions
-    vertex_buffers: SmallVec<[Arc<Buffer>; 4]>,
+    vertex_buffers: SmallVec<[(Arc<Buffer>, usize); 4]>,
 }
 
 unsafe impl<L, Pv, Pl, Prp, S> CommandBuffer for DrawCommandCb<L, Pv, Pl, Prp, S>
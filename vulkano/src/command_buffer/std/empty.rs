@@ -10,6 +10,8 @@
 use std::iter;
 use std::iter::Empty;
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
 
 use buffer::traits::TrackedBuffer;
 use command_buffer::pool::CommandPool;
@@ -116,6 +118,7 @@ unsafe impl<P> StdCommandsList for PrimaryCbBuilder<P> where P: CommandPool {
         
         PrimaryCb {
             cb: cb.build().unwrap(),        // TODO: handle error
+            fences: Mutex::new(Vec::new()),
         }
     }
 }
@@ -140,6 +143,10 @@ unsafe impl<P> OutsideRenderPass for PrimaryCbBuilder<P> where P: CommandPool {}
 
 pub struct PrimaryCb<P = Arc<StandardCommandPool>> where P: CommandPool {
     cb: UnsafeCommandBuffer<P>,
+    // Fences of the submissions that may still be executing. The command buffer can be borrowed
+    // by a `Submission`, so it waits for them when it is destroyed in case that `Submission` was
+    // leaked.
+    fences: Mutex<Vec<Arc<Fence>>>,
 }
 
 unsafe impl<P> CommandBuffer for PrimaryCb<P> where P: CommandPool {
@@ -152,7 +159,7 @@ unsafe impl<P> CommandBuffer for PrimaryCb<P> where P: CommandPool {
         &self.cb
     }
 
-    unsafe fn on_submit<F>(&self, queue: &Arc<Queue>, fence: F)
+    unsafe fn on_submit<F>(&self, queue: &Arc<Queue>, mut fence: F)
                            -> SubmitInfo<Self::SemaphoresWaitIterator,
                                          Self::SemaphoresSignalIterator>
         where F: FnMut() -> Arc<Fence>
@@ -161,6 +168,12 @@ unsafe impl<P> CommandBuffer for PrimaryCb<P> where P: CommandPool {
         //       flag is mandatory, so there's no safety issue. However it will need to be handled
         //       before allowing other flags to be used.
 
+        {
+            let mut fences = self.fences.lock().unwrap();
+            fences.retain(|f| !f.ready().unwrap_or(false));
+            fences.push(fence());
+        }
+
         SubmitInfo {
             semaphores_wait: iter::empty(),
             semaphores_signal: iter::empty(),
@@ -170,6 +183,15 @@ unsafe impl<P> CommandBuffer for PrimaryCb<P> where P: CommandPool {
     }
 }
 
+impl<P> Drop for PrimaryCb<P> where P: CommandPool {
+    fn drop(&mut self) {
+        for fence in self.fences.lock().unwrap().iter() {
+            // Errors are ignored, as there's nothing more we can do if the device has been lost.
+            let _ = fence.wait(Duration::from_secs(10));
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use command_buffer::std::PrimaryCbBuilder;
@@ -181,4 +203,12 @@ mod tests {
         let (device, queue) = gfx_dev_and_queue!();
         let _ = PrimaryCbBuilder::new(&device, queue.family()).build().submit(&queue);
     }
+
+    #[test]
+    fn submit_borrowed_twice() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let cb = PrimaryCbBuilder::new(&device, queue.family()).build();
+        let _first = (&cb).submit(&queue).unwrap();
+        let _second = (&cb).submit(&queue).unwrap();
+    }
 }
@@ -96,6 +96,21 @@ pub unsafe trait StdCommandsList: ResourcesStates {
         dispatch::DispatchCommand::new(self, pipeline, sets, dimensions, push_constants)
     }
 
+    /// Adds a command that executes a compute shader once for each of `elements` elements.
+    ///
+    /// The number of working groups is computed from the local size of the pipeline. See
+    /// `ComputePipeline::group_counts_for_elements` for more info.
+    #[inline]
+    fn dispatch_for_elements<'a, Pl, S, Pc>(self, pipeline: Arc<ComputePipeline<Pl>>, sets: S,
+                                            elements: u32, push_constants: &'a Pc)
+                                            -> dispatch::DispatchCommand<'a, Self, Pl, S, Pc>
+        where Self: Sized + StdCommandsList + OutsideRenderPass, Pl: PipelineLayout,
+              S: TrackedDescriptorSetsCollection, Pc: 'a
+    {
+        let dimensions = pipeline.group_counts_for_elements(elements);
+        dispatch::DispatchCommand::new(self, pipeline, sets, dimensions, push_constants)
+    }
+
     /// Adds a command that starts a render pass.
     ///
     /// If `secondary` is true, then you will only be able to add secondary command buffers while
@@ -20,13 +20,14 @@ use command_buffer::sys::UnsafeCommandBufferBuilder;
 use command_buffer::sys::UnsafeCommandBuffer;
 use device::Device;
 use device::Queue;
+use device::QueueError;
 use framebuffer::EmptySinglePassRenderPass;
 use framebuffer::Framebuffer as OldFramebuffer;
 use sync::Fence;
+use sync::FenceWaitError;
 use sync::PipelineStages;
 use sync::Semaphore;
 
-use check_errors;
 use vk;
 use VulkanObject;
 use VulkanPointers;
@@ -40,9 +41,14 @@ pub unsafe trait CommandBuffer {
     /// multiple command buffers at once instead.
     ///
     /// This is a simple shortcut for creating a `Submit` object.
-    // TODO: remove 'static
+    ///
+    /// Command buffers are also implemented on references. Submitting `&command_buffer` borrows
+    /// it for as long as the `Submission` exists instead of moving it, which lets you submit the
+    /// same command buffer multiple times without rebuilding its list of resources.
     #[inline]
-    fn submit(self, queue: &Arc<Queue>) -> Submission where Self: Sized + 'static {
+    fn submit<'a>(self, queue: &Arc<Queue>) -> Result<Submission<'a>, QueueError>
+        where Self: Sized + 'a
+    {
         Submit::new().add(self).submit(queue)
     }
 
@@ -88,6 +94,26 @@ pub unsafe trait CommandBuffer {
         where F: FnMut() -> Arc<Fence>;
 }
 
+unsafe impl<'b, C> CommandBuffer for &'b C where C: CommandBuffer {
+    type Pool = C::Pool;
+    type SemaphoresWaitIterator = C::SemaphoresWaitIterator;
+    type SemaphoresSignalIterator = C::SemaphoresSignalIterator;
+
+    #[inline]
+    fn inner(&self) -> &UnsafeCommandBuffer<Self::Pool> {
+        (**self).inner()
+    }
+
+    #[inline]
+    unsafe fn on_submit<F>(&self, queue: &Arc<Queue>, fence: F)
+                           -> SubmitInfo<Self::SemaphoresWaitIterator,
+                                         Self::SemaphoresSignalIterator>
+        where F: FnMut() -> Arc<Fence>
+    {
+        (**self).on_submit(queue, fence)
+    }
+}
+
 /// Information about how the submitting function should synchronize the submission.
 pub struct SubmitInfo<Swi, Ssi> {
     /// List of semaphores to wait upon before the command buffer starts execution.
@@ -112,18 +138,19 @@ pub struct SubmitInfo<Swi, Ssi> {
 /// in a long-living container such as a `Vec`. From time to time, you can clean the obsolete
 /// objects by checking whether `destroying_would_block()` returns false. For example, if you use
 /// a `Vec` you can do `vec.retain(|s| s.destroying_would_block())`.
-// TODO: docs
-// # Leak safety
-//
-// The `Submission` object can hold borrows of command buffers. In order for it to be safe to leak
-// a `Submission`, the borrowed object themselves must be protected by a fence.
+///
+/// # Leak safety
+///
+/// The `Submission` object can hold borrows of command buffers. Leaking a `Submission` is still
+/// safe, because the safety requirements of `CommandBuffer::on_submit` force a command buffer to
+/// wait for the fences of its submissions before it is destroyed.
 #[must_use]
-pub struct Submission {
+pub struct Submission<'a> {
     fence: Arc<Fence>,      // TODO: make optional
-    keep_alive: SmallVec<[Arc<KeepAlive>; 4]>,
+    keep_alive: SmallVec<[Arc<KeepAlive + 'a>; 4]>,
 }
 
-impl Submission {
+impl<'a> Submission<'a> {
     /// Returns `true` if destroying this `Submission` object would block the CPU for some time.
     #[inline]
     pub fn destroying_would_block(&self) -> bool {
@@ -137,9 +164,14 @@ impl Submission {
     }
 }
 
-impl Drop for Submission {
+impl<'a> Drop for Submission<'a> {
     fn drop(&mut self) {
-        self.fence.wait(Duration::from_secs(10)).unwrap();      // TODO: handle some errors
+        match self.fence.wait(Duration::from_secs(10)) {
+            Ok(_) => (),
+            // A lost device doesn't use the resources anymore, so they can be released.
+            Err(FenceWaitError::DeviceLost(_)) => (),
+            Err(err) => panic!("{}", err),      // TODO: handle some errors
+        }
     }
 }
 
@@ -159,20 +191,29 @@ impl Submit<()> {
     }
 }
 
-impl<L> Submit<L> where L: SubmitList {
+impl<L> Submit<L> {
     /// Adds a command buffer to submit to the list.
     ///
     /// In the Vulkan API, a submission is divided into batches that each contain one or more
     /// command buffers. Vulkano will automatically determine which command buffers can be grouped
     /// into the same batch.
-    // TODO: remove 'static
+    ///
+    /// Adding a reference to a command buffer instead of the command buffer itself makes the
+    /// `Submission` borrow it.
     #[inline]
-    pub fn add<C>(self, command_buffer: C) -> Submit<(C, L)> where C: CommandBuffer + 'static {
+    pub fn add<C>(self, command_buffer: C) -> Submit<(C, L)> where C: CommandBuffer {
         Submit { list: (command_buffer, self.list) }
     }
 
     /// Submits the list of command buffers.
-    pub fn submit(self, queue: &Arc<Queue>) -> Submission {
+    ///
+    /// Returns `QueueError::DeviceLost` without submitting anything if the device has already
+    /// been lost.
+    pub fn submit<'a>(self, queue: &Arc<Queue>) -> Result<Submission<'a>, QueueError>
+        where L: SubmitList<'a>
+    {
+        try!(queue.device().check_lost());
+
         let SubmitListOpaque { fence, wait_semaphores, wait_stages, command_buffers,
                                signal_semaphores, mut submits, keep_alive }
                              = self.list.infos(queue);
@@ -213,39 +254,44 @@ impl<L> Submit<L> where L: SubmitList {
         }
 
         unsafe {
-            let vk = queue.device().pointers();
+            let device = queue.device();
+            let vk = device.pointers();
             let queue = queue.internal_object_guard();
             //let fence = fence.as_ref().map(|f| f.internal_object()).unwrap_or(0);
             let fence = fence.internal_object();
-            check_errors(vk.QueueSubmit(*queue, submits.len() as u32, submits.as_ptr(),
-                                        fence)).unwrap();        // TODO: handle errors (trickier than it looks)
+            // If the submission fails, the resources in `keep_alive` are released immediately
+            // since the queue never uses them.
+            try!(device.check_errors(vk.QueueSubmit(*queue, submits.len() as u32,
+                                                    submits.as_ptr(), fence)));
         }
 
-        Submission {
+        queue.record_submission(fence.clone());
+
+        Ok(Submission {
             keep_alive: keep_alive,
             fence: fence,
-        }
+        })
     }
 }
 
 /* TODO: All that stuff below is undocumented */
 
-pub struct SubmitListOpaque {
+pub struct SubmitListOpaque<'a> {
     fence: Option<Arc<Fence>>,
     wait_semaphores: SmallVec<[vk::Semaphore; 16]>,
     wait_stages: SmallVec<[vk::PipelineStageFlags; 16]>,
     command_buffers: SmallVec<[vk::CommandBuffer; 16]>,
     signal_semaphores: SmallVec<[vk::Semaphore; 16]>,
     submits: SmallVec<[vk::SubmitInfo; 8]>,
-    keep_alive: SmallVec<[Arc<KeepAlive>; 4]>,
+    keep_alive: SmallVec<[Arc<KeepAlive + 'a>; 4]>,
 }
 
-pub unsafe trait SubmitList {
-    fn infos(self, queue: &Arc<Queue>) -> SubmitListOpaque;
+pub unsafe trait SubmitList<'a> {
+    fn infos(self, queue: &Arc<Queue>) -> SubmitListOpaque<'a>;
 }
 
-unsafe impl SubmitList for () {
-    fn infos(self, queue: &Arc<Queue>) -> SubmitListOpaque {
+unsafe impl<'a> SubmitList<'a> for () {
+    fn infos(self, queue: &Arc<Queue>) -> SubmitListOpaque<'a> {
         SubmitListOpaque {
             fence: None,
             wait_semaphores: SmallVec::new(),
@@ -258,9 +304,10 @@ unsafe impl SubmitList for () {
     }
 }
 
-// TODO: remove 'static
-unsafe impl<C, R> SubmitList for (C, R) where C: CommandBuffer + 'static, R: SubmitList {
-    fn infos(self, queue: &Arc<Queue>) -> SubmitListOpaque {
+unsafe impl<'a, C, R> SubmitList<'a> for (C, R)
+    where C: CommandBuffer + 'a, R: SubmitList<'a>
+{
+    fn infos(self, queue: &Arc<Queue>) -> SubmitListOpaque<'a> {
         // TODO: attempt to group multiple submits into one when possible
 
         let (current, rest) = self;
@@ -387,6 +434,6 @@ mod tests {
         let cb = UnsafeCommandBufferBuilder::new(pool, kind, Flags::OneTimeSubmit).unwrap();
         let cb = Basic { inner: cb.build().unwrap() };
 
-        let _s = cb.submit(&queue);
+        let _s = cb.submit(&queue).unwrap();
     }
 }
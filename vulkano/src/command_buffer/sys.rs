@@ -33,6 +33,7 @@ use std::ops::Range;
 use std::ptr;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::thread;
 use std::u32;
 use smallvec::SmallVec;
 
@@ -54,13 +55,18 @@ use framebuffer::Subpass;
 use framebuffer::UnsafeRenderPass;
 use framebuffer::traits::Framebuffer;
 use image::Image;
+use image::ImageAspect;
 use image::sys::Layout;
 use image::sys::UnsafeImage;
 use image::traits::PipelineBarrierRequest as ImagePipelineBarrierRequest;
+use instance::QueueFamily;
 use pipeline::ComputePipeline;
 use pipeline::GraphicsPipeline;
 use pipeline::input_assembly::IndexType;
+use query::QueryResultFlags;
+use query::UnsafeQueryPool;
 use sync::AccessFlagBits;
+use sync::Event;
 use sync::PipelineStages;
 
 use OomError;
@@ -664,7 +670,12 @@ impl<P> UnsafeCommandBufferBuilder<P> where P: CommandPool {
     /// Adds a pipeline barrier to the command buffer.
     ///
     /// This function itself is not unsafe, but creating a pipeline barrier builder is.
-    #[inline]
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the barrier contains one half of a queue family ownership transfer that must
+    ///   be recorded in a command buffer of another queue family.
+    ///
     pub fn pipeline_barrier(&mut self, barrier: PipelineBarrierBuilder) {
         // If barrier is empty, don't do anything.
         if barrier.src_stage_mask == 0 || barrier.dst_stage_mask == 0 {
@@ -675,6 +686,8 @@ impl<P> UnsafeCommandBufferBuilder<P> where P: CommandPool {
             return;
         }
 
+        self.check_barrier_queue_family(&barrier);
+
         let vk = self.device.pointers();
         let cmd = self.cmd.clone().take().unwrap();
 
@@ -689,6 +702,99 @@ impl<P> UnsafeCommandBufferBuilder<P> where P: CommandPool {
         }
     }
 
+    // Checks that the queue family ownership transfers of a barrier can be recorded in this
+    // command buffer.
+    fn check_barrier_queue_family(&self, barrier: &PipelineBarrierBuilder) {
+        if let Some(family) = barrier.queue_family {
+            let pool_family = self.pool.as_ref().unwrap().queue_family().id();
+            assert_eq!(family, pool_family, "The pipeline barrier contains a queue family \
+                                             ownership transfer of another queue family");
+        }
+    }
+
+    /// Sets an event once all the `stages` of the previous commands have finished.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the event was not created with the same device as this command buffer.
+    ///
+    /// # Safety
+    ///
+    /// - Must be called outside of a render pass.
+    /// - The event must be kept alive until the command buffer has finished executing.
+    ///
+    #[inline]
+    pub unsafe fn set_event(&mut self, event: &Event, stages: PipelineStages) {
+        assert_eq!(event.device().internal_object(), self.device.internal_object());
+
+        let vk = self.device.pointers();
+        let cmd = self.cmd.clone().take().unwrap();
+        vk.CmdSetEvent(cmd, event.internal_object(), stages.into());
+    }
+
+    /// Resets an event once all the `stages` of the previous commands have finished.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the event was not created with the same device as this command buffer.
+    ///
+    /// # Safety
+    ///
+    /// - Must be called outside of a render pass.
+    /// - The event must be kept alive until the command buffer has finished executing.
+    ///
+    #[inline]
+    pub unsafe fn reset_event(&mut self, event: &Event, stages: PipelineStages) {
+        assert_eq!(event.device().internal_object(), self.device.internal_object());
+
+        let vk = self.device.pointers();
+        let cmd = self.cmd.clone().take().unwrap();
+        vk.CmdResetEvent(cmd, event.internal_object(), stages.into());
+    }
+
+    /// Waits until all the events are signaled, then applies the barriers of `barrier`.
+    ///
+    /// The stages and the memory barriers of `barrier` are used the same way as for a pipeline
+    /// barrier. The source stages must contain the stages that were passed to `set_event`, plus
+    /// the `host` stage for events that are set from the CPU with `Event::set`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the events was not created with the same device as this command
+    ///   buffer.
+    /// - Panics if the barrier contains one half of a queue family ownership transfer that must
+    ///   be recorded in a command buffer of another queue family.
+    ///
+    /// # Safety
+    ///
+    /// - The events must be kept alive until the command buffer has finished executing.
+    /// - If the command buffer is submitted, the events must eventually be signaled.
+    /// - `barrier` must not be empty.
+    ///
+    pub unsafe fn wait_events<'a, I>(&mut self, events: I, barrier: PipelineBarrierBuilder)
+        where I: IntoIterator<Item = &'a Event>
+    {
+        debug_assert!(!barrier.is_empty());
+        self.check_barrier_queue_family(&barrier);
+
+        let events: SmallVec<[_; 4]> = events.into_iter().map(|event| {
+            assert_eq!(event.device().internal_object(), self.device.internal_object());
+            event.internal_object()
+        }).collect();
+
+        if events.is_empty() {
+            return;
+        }
+
+        let vk = self.device.pointers();
+        let cmd = self.cmd.clone().take().unwrap();
+        vk.CmdWaitEvents(cmd, events.len() as u32, events.as_ptr(), barrier.src_stage_mask,
+                         barrier.dst_stage_mask, barrier.memory_barriers.len() as u32,
+                         barrier.memory_barriers.as_ptr(), barrier.buffer_barriers.len() as u32,
+                         barrier.buffer_barriers.as_ptr(), barrier.image_barriers.len() as u32,
+                         barrier.image_barriers.as_ptr());
+    }
+
     /// Enters a render pass.
     ///
     /// Any clear value that is equal to `None` is replaced with a dummy value. It is expected that
@@ -838,6 +944,37 @@ impl<P> UnsafeCommandBufferBuilder<P> where P: CommandPool {
         vk.CmdBindPipeline(cmd, vk::PIPELINE_BIND_POINT_COMPUTE, pipeline.internal_object());
     }
 
+    /// Sets the dynamic line width of the currently bound graphics pipeline.
+    ///
+    /// # Safety
+    ///
+    /// - The queue family must support graphics operations.
+    /// - The bound pipeline must have been created with a dynamic line width.
+    /// - If the `wide_lines` feature is not enabled, the width must be 1.0.
+    /// - The width must be within the `line_width_range` limit.
+    ///
+    #[inline]
+    pub unsafe fn set_line_width(&mut self, line_width: f32) {
+        let vk = self.device.pointers();
+        let cmd = self.cmd.clone().take().unwrap();
+        vk.CmdSetLineWidth(cmd, line_width);
+    }
+
+    /// Sets the dynamic depth bounds of the currently bound graphics pipeline.
+    ///
+    /// # Safety
+    ///
+    /// - The queue family must support graphics operations.
+    /// - The bound pipeline must have been created with dynamic depth bounds.
+    /// - Both values must be between 0.0 and 1.0, and `min` must not be greater than `max`.
+    ///
+    #[inline]
+    pub unsafe fn set_depth_bounds(&mut self, min: f32, max: f32) {
+        let vk = self.device.pointers();
+        let cmd = self.cmd.clone().take().unwrap();
+        vk.CmdSetDepthBounds(cmd, min, max);
+    }
+
     /// Calls `vkCmdDraw`.
     #[inline]
     pub unsafe fn draw(&mut self, vertex_count: u32, instance_count: u32, first_vertex: u32,
@@ -864,12 +1001,15 @@ impl<P> UnsafeCommandBufferBuilder<P> where P: CommandPool {
     /// # Panic
     ///
     /// - Panics if the buffer was not created with the same device as this command buffer.
+    /// - Panics if `draw_count` is greater than 1 and the `multi_draw_indirect` feature isn't
+    ///   enabled.
     ///
     #[inline]
     pub unsafe fn draw_indirect(&mut self, buffer: &UnsafeBuffer, offset: usize, draw_count: u32,
                                 stride: u32)
     {
         assert_eq!(buffer.device().internal_object(), self.device.internal_object());
+        assert!(draw_count <= 1 || self.device.enabled_features().multi_draw_indirect);
 
         let vk = self.device.pointers();
         let cmd = self.cmd.clone().take().unwrap();
@@ -882,12 +1022,15 @@ impl<P> UnsafeCommandBufferBuilder<P> where P: CommandPool {
     /// # Panic
     ///
     /// - Panics if the buffer was not created with the same device as this command buffer.
+    /// - Panics if `draw_count` is greater than 1 and the `multi_draw_indirect` feature isn't
+    ///   enabled.
     ///
     #[inline]
     pub unsafe fn draw_indexed_indirect(&mut self, buffer: &UnsafeBuffer, offset: usize,
                                         draw_count: u32, stride: u32)
     {
         assert_eq!(buffer.device().internal_object(), self.device.internal_object());
+        assert!(draw_count <= 1 || self.device.enabled_features().multi_draw_indirect);
 
         let vk = self.device.pointers();
         let cmd = self.cmd.clone().take().unwrap();
@@ -903,6 +1046,46 @@ impl<P> UnsafeCommandBufferBuilder<P> where P: CommandPool {
         vk.CmdDispatch(cmd, x, y, z);
     }
 
+    /// Calls `vkCmdDispatchBaseKHR`.
+    ///
+    /// Same as `dispatch`, except that the workgroup IDs start at `base` instead of 0. This can
+    /// be used to split a dispatch between the physical devices of a device group.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the `khr_device_group` extension isn't enabled on the device.
+    ///
+    #[inline]
+    pub unsafe fn dispatch_base(&mut self, base: [u32; 3], dimensions: [u32; 3]) {
+        assert!(self.device.loaded_extensions().khr_device_group);
+
+        let vk = self.device.pointers();
+        let cmd = self.cmd.clone().take().unwrap();
+        vk.CmdDispatchBaseKHR(cmd, base[0], base[1], base[2],
+                              dimensions[0], dimensions[1], dimensions[2]);
+    }
+
+    /// Calls `vkCmdSetDeviceMaskKHR`.
+    ///
+    /// The commands that follow are only executed by the physical devices of the device group
+    /// whose bit is set in `device_mask`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the `khr_device_group` extension isn't enabled on the device.
+    /// - Panics if `device_mask` is 0 or contains bits that don't correspond to a physical device
+    ///   of the device.
+    ///
+    #[inline]
+    pub unsafe fn set_device_mask(&mut self, device_mask: u32) {
+        assert!(self.device.loaded_extensions().khr_device_group);
+        assert!(device_mask != 0 && (device_mask & !self.device.all_devices_mask()) == 0);
+
+        let vk = self.device.pointers();
+        let cmd = self.cmd.clone().take().unwrap();
+        vk.CmdSetDeviceMaskKHR(cmd, device_mask);
+    }
+
     /// Calls `vkCmdDispatchIndirect`.
     ///
     /// # Panic
@@ -918,6 +1101,129 @@ impl<P> UnsafeCommandBufferBuilder<P> where P: CommandPool {
         vk.CmdDispatchIndirect(cmd, buffer.internal_object(), offset as vk::DeviceSize);
     }
 
+    /// Calls `vkCmdResetQueryPool`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the pool was not created with the same device as this command buffer.
+    ///
+    /// # Safety
+    ///
+    /// - Must be called outside of a render pass.
+    /// - The range must be within the slots of the pool.
+    ///
+    #[inline]
+    pub unsafe fn reset_query_pool(&mut self, pool: &UnsafeQueryPool, slots: Range<u32>) {
+        assert_eq!(pool.device().internal_object(), self.device.internal_object());
+
+        let vk = self.device.pointers();
+        let cmd = self.cmd.clone().take().unwrap();
+        vk.CmdResetQueryPool(cmd, pool.internal_object(), slots.start, slots.end - slots.start);
+    }
+
+    /// Calls `vkCmdBeginQuery`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the pool was not created with the same device as this command buffer.
+    ///
+    /// # Safety
+    ///
+    /// - The slot must be within the pool, must have been reset and must not be active.
+    /// - If `precise` is true, the pool must be an occlusion pool and the
+    ///   `occlusion_query_precise` feature must be enabled.
+    ///
+    #[inline]
+    pub unsafe fn begin_query(&mut self, pool: &UnsafeQueryPool, slot: u32, precise: bool) {
+        assert_eq!(pool.device().internal_object(), self.device.internal_object());
+
+        let flags = if precise { vk::QUERY_CONTROL_PRECISE_BIT } else { 0 };
+
+        let vk = self.device.pointers();
+        let cmd = self.cmd.clone().take().unwrap();
+        vk.CmdBeginQuery(cmd, pool.internal_object(), slot, flags);
+    }
+
+    /// Calls `vkCmdEndQuery`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the pool was not created with the same device as this command buffer.
+    ///
+    /// # Safety
+    ///
+    /// - The query must be active.
+    ///
+    #[inline]
+    pub unsafe fn end_query(&mut self, pool: &UnsafeQueryPool, slot: u32) {
+        assert_eq!(pool.device().internal_object(), self.device.internal_object());
+
+        let vk = self.device.pointers();
+        let cmd = self.cmd.clone().take().unwrap();
+        vk.CmdEndQuery(cmd, pool.internal_object(), slot);
+    }
+
+    /// Calls `vkCmdWriteTimestamp`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the pool was not created with the same device as this command buffer.
+    ///
+    /// # Safety
+    ///
+    /// - The pool must be a timestamp pool and the slot must be within the pool and have been
+    ///   reset.
+    /// - `stage` must contain exactly one stage.
+    /// - The queue family must support timestamps.
+    ///
+    #[inline]
+    pub unsafe fn write_timestamp(&mut self, pool: &UnsafeQueryPool, stage: PipelineStages,
+                                  slot: u32)
+    {
+        assert_eq!(pool.device().internal_object(), self.device.internal_object());
+
+        let vk = self.device.pointers();
+        let cmd = self.cmd.clone().take().unwrap();
+        vk.CmdWriteTimestamp(cmd, stage.into(), pool.internal_object(), slot);
+    }
+
+    /// Calls `vkCmdCopyQueryPoolResults`. The results are always written as 64-bit values.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the pool or the buffer was not created with the same device as this command
+    ///   buffer.
+    ///
+    /// # Safety
+    ///
+    /// - Must be called outside of a render pass.
+    /// - The range must be within the slots of the pool.
+    /// - The buffer must have been created with the transfer destination usage, and the
+    ///   results must fit in it.
+    /// - `offset` and `stride` must be multiples of 8.
+    /// - Partial results are not allowed for timestamp queries.
+    ///
+    #[inline]
+    pub unsafe fn copy_query_pool_results(&mut self, pool: &UnsafeQueryPool, slots: Range<u32>,
+                                          buffer: &UnsafeBuffer, offset: usize, stride: usize,
+                                          flags: QueryResultFlags, with_availability: bool)
+    {
+        assert_eq!(pool.device().internal_object(), self.device.internal_object());
+        assert_eq!(buffer.device().internal_object(), self.device.internal_object());
+
+        let mut vk_flags: vk::QueryResultFlags = flags.into();
+        vk_flags |= vk::QUERY_RESULT_64_BIT;
+        if with_availability {
+            vk_flags |= vk::QUERY_RESULT_WITH_AVAILABILITY_BIT;
+        }
+
+        let vk = self.device.pointers();
+        let cmd = self.cmd.clone().take().unwrap();
+        vk.CmdCopyQueryPoolResults(cmd, pool.internal_object(), slots.start,
+                                   slots.end - slots.start, buffer.internal_object(),
+                                   offset as vk::DeviceSize, stride as vk::DeviceSize, vk_flags);
+    }
+
     /// Calls `vkCmdBindVertexBuffers`.
     ///
     /// The iterator yields a list of buffers and offset of the first byte.
@@ -950,12 +1256,18 @@ impl<P> UnsafeCommandBufferBuilder<P> where P: CommandPool {
     /// # Panic
     ///
     /// - Panics if the buffer was not created with the same device as this command buffer.
+    /// - Panics if the buffer wasn't created with the `index_buffer` usage.
+    /// - Panics if `index_ty` isn't supported by the device. See `IndexType::supported_by_device`.
+    /// - Panics if `offset` is not a multiple of the size of `index_ty`.
     ///
     #[inline]
     pub unsafe fn bind_index_buffer(&mut self, buffer: &UnsafeBuffer, offset: usize,
                                     index_ty: IndexType)
     {
         assert_eq!(buffer.device().internal_object(), self.device.internal_object());
+        assert!(buffer.usage_index_buffer());
+        assert!(index_ty.supported_by_device(&self.device));
+        assert_eq!(offset % index_ty.size(), 0);
 
         let vk = self.device.pointers();
         let cmd = self.cmd.clone().take().unwrap();
@@ -1113,6 +1425,9 @@ pub struct PipelineBarrierBuilder {
     memory_barriers: SmallVec<[vk::MemoryBarrier; 2]>,
     buffer_barriers: SmallVec<[vk::BufferMemoryBarrier; 8]>,
     image_barriers: SmallVec<[vk::ImageMemoryBarrier; 8]>,
+    // If the barrier contains one half of a queue family ownership transfer, contains the queue
+    // family of the command buffers it can be recorded in.
+    queue_family: Option<u32>,
 }
 
 impl PipelineBarrierBuilder {
@@ -1126,6 +1441,7 @@ impl PipelineBarrierBuilder {
             memory_barriers: SmallVec::new(),
             buffer_barriers: SmallVec::new(),
             image_barriers: SmallVec::new(),
+            queue_family: None,
         }
     }
 
@@ -1136,8 +1452,18 @@ impl PipelineBarrierBuilder {
     }
 
     /// Merges another pipeline builder into this one.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the two builders contain queue family ownership transfers that must be
+    ///   recorded in different queue families.
+    ///
     #[inline]
     pub fn merge(&mut self, other: PipelineBarrierBuilder) {
+        if let Some(family) = other.queue_family {
+            self.set_queue_family(family);
+        }
+
         self.src_stage_mask |= other.src_stage_mask;
         self.dst_stage_mask |= other.dst_stage_mask;
         self.dependency_flags &= other.dependency_flags;
@@ -1376,6 +1702,310 @@ impl PipelineBarrierBuilder {
             },
         });
     }
+
+    /// Adds a barrier between the last use of a resource and the first use of another resource
+    /// that is bound to the same `AliasedMemory`.
+    ///
+    /// The source stages and accesses are the ones of the resource that was previously using the
+    /// memory, and the destination stages and accesses are the ones of the new resource. If the
+    /// new resource is an image, pass it with the layout it must be transitioned to. Its previous
+    /// content is discarded.
+    ///
+    /// # Safety
+    ///
+    /// - If the pipeline stages include geometry or tessellation stages, then the corresponding
+    ///   features must have been enabled.
+    /// - The image must be alive for at least as long as the command buffer to which this barrier
+    ///   is added.
+    /// - Access flags must be compatible with the image usage flags passed at image creation.
+    ///
+    pub unsafe fn add_aliasing_barrier(&mut self, source_stage: PipelineStages,
+                                       source_access: AccessFlagBits, dest_stage: PipelineStages,
+                                       dest_access: AccessFlagBits,
+                                       new_image: Option<(&UnsafeImage, Layout)>)
+    {
+        // Aliased resources don't cover the same regions of the framebuffer, therefore the
+        // barrier can't be by region.
+        self.add_memory_barrier(source_stage, source_access, dest_stage, dest_access, false);
+
+        if let Some((image, layout)) = new_image {
+            debug_assert!(layout != Layout::Undefined && layout != Layout::Preinitialized);
+
+            self.image_barriers.push(vk::ImageMemoryBarrier {
+                sType: vk::STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER,
+                pNext: ptr::null(),
+                srcAccessMask: source_access.into(),
+                dstAccessMask: dest_access.into(),
+                oldLayout: Layout::Undefined as u32,
+                newLayout: layout as u32,
+                srcQueueFamilyIndex: vk::QUEUE_FAMILY_IGNORED,
+                dstQueueFamilyIndex: vk::QUEUE_FAMILY_IGNORED,
+                image: image.internal_object(),
+                subresourceRange: vk::ImageSubresourceRange {
+                    aspectMask: ImageAspect::from_format(image.format()).to_vk_bits(),
+                    baseMipLevel: 0,
+                    levelCount: image.mipmap_levels(),
+                    baseArrayLayer: 0,
+                    layerCount: image.dimensions().array_layers(),
+                },
+            });
+        }
+    }
+
+    /// Adds the first half of the transfer of the ownership of a range of a buffer from the
+    /// queue family `source` to the queue family `destination`.
+    ///
+    /// The barrier must be recorded in a command buffer of the `source` family. The returned
+    /// `OwnershipTransfer` must then be passed to `add_acquire` on a barrier that is recorded in
+    /// a command buffer of the `destination` family, which must be executed after this one, for
+    /// example by waiting on a semaphore.
+    ///
+    /// The source stages and accesses are the ones of the last use of the buffer on the source
+    /// queue family.
+    ///
+    /// Transfers are only needed for resources that were created with the exclusive sharing
+    /// mode.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `source` and `destination` are the same queue family or don't belong to the
+    ///   same physical device.
+    /// - Panics if the range is out of the buffer.
+    /// - Panics if the barrier already contains a transfer that must be recorded in another
+    ///   queue family.
+    ///
+    /// # Safety
+    ///
+    /// - If the pipeline stages include geometry or tessellation stages, then the corresponding
+    ///   features must have been enabled.
+    /// - The buffer must be alive for at least as long as the command buffer to which this barrier
+    ///   is added.
+    /// - The buffer must be owned by the `source` queue family.
+    ///
+    pub unsafe fn add_buffer_release(&mut self, buffer: &UnsafeBuffer, range: Range<usize>,
+                                     source: QueueFamily, destination: QueueFamily,
+                                     source_stage: PipelineStages, source_access: AccessFlagBits)
+                                     -> OwnershipTransfer
+    {
+        assert!(range.start < range.end && range.end <= buffer.size());
+
+        let transfer = OwnershipTransfer::new(source, destination, TransferResource::Buffer {
+            buffer: buffer.internal_object(),
+            offset: range.start as vk::DeviceSize,
+            size: (range.end - range.start) as vk::DeviceSize,
+        });
+
+        let family = transfer.source_family;
+        self.add_transfer_half(&transfer, family, source_stage.into(), source_access.into(),
+                               vk::PIPELINE_STAGE_BOTTOM_OF_PIPE_BIT, 0);
+        transfer
+    }
+
+    /// Same as `add_buffer_release`, but for a range of mipmaps and layers of an image.
+    ///
+    /// The layout of the image can also be changed from `current_layout` to `new_layout` by the
+    /// transfer. The same transition is then performed by the acquire half.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `source` and `destination` are the same queue family or don't belong to the
+    ///   same physical device.
+    /// - Panics if the ranges are out of the image.
+    /// - Panics if the barrier already contains a transfer that must be recorded in another
+    ///   queue family.
+    ///
+    /// # Safety
+    ///
+    /// - If the pipeline stages include geometry or tessellation stages, then the corresponding
+    ///   features must have been enabled.
+    /// - The image must be alive for at least as long as the command buffer to which this barrier
+    ///   is added.
+    /// - The image must be owned by the `source` queue family and be in `current_layout`.
+    /// - Access flags must be compatible with the image usage flags passed at image creation.
+    ///
+    pub unsafe fn add_image_release(&mut self, image: &UnsafeImage, mipmaps: Range<u32>,
+                                    layers: Range<u32>, source: QueueFamily,
+                                    destination: QueueFamily, source_stage: PipelineStages,
+                                    source_access: AccessFlagBits, current_layout: Layout,
+                                    new_layout: Layout) -> OwnershipTransfer
+    {
+        assert!(mipmaps.start < mipmaps.end && mipmaps.end <= image.mipmap_levels());
+        assert!(layers.start < layers.end && layers.end <= image.dimensions().array_layers());
+        debug_assert!(new_layout != Layout::Undefined && new_layout != Layout::Preinitialized);
+
+        let transfer = OwnershipTransfer::new(source, destination, TransferResource::Image {
+            image: image.internal_object(),
+            range: vk::ImageSubresourceRange {
+                aspectMask: ImageAspect::from_format(image.format()).to_vk_bits(),
+                baseMipLevel: mipmaps.start,
+                levelCount: mipmaps.end - mipmaps.start,
+                baseArrayLayer: layers.start,
+                layerCount: layers.end - layers.start,
+            },
+            old_layout: current_layout,
+            new_layout: new_layout,
+        });
+
+        let family = transfer.source_family;
+        self.add_transfer_half(&transfer, family, source_stage.into(), source_access.into(),
+                               vk::PIPELINE_STAGE_BOTTOM_OF_PIPE_BIT, 0);
+        transfer
+    }
+
+    /// Adds the second half of a queue family ownership transfer that was started with
+    /// `add_buffer_release` or `add_image_release`.
+    ///
+    /// The barrier must be recorded in a command buffer of the destination queue family of the
+    /// transfer. The destination stages and accesses are the ones of the first use of the
+    /// resource on this queue family.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the barrier already contains a transfer that must be recorded in another
+    ///   queue family.
+    ///
+    /// # Safety
+    ///
+    /// - If the pipeline stages include geometry or tessellation stages, then the corresponding
+    ///   features must have been enabled.
+    /// - The command buffer that contains the release half must be executed before the one that
+    ///   contains this barrier.
+    /// - The resource must be alive for at least as long as the command buffer to which this
+    ///   barrier is added.
+    ///
+    pub unsafe fn add_acquire(&mut self, mut transfer: OwnershipTransfer,
+                              dest_stage: PipelineStages, dest_access: AccessFlagBits)
+    {
+        let family = transfer.destination_family;
+        self.add_transfer_half(&transfer, family, vk::PIPELINE_STAGE_TOP_OF_PIPE_BIT, 0,
+                               dest_stage.into(), dest_access.into());
+        transfer.acquired = true;
+    }
+
+    // Adds one of the two barriers of a queue family ownership transfer.
+    unsafe fn add_transfer_half(&mut self, transfer: &OwnershipTransfer, family: u32,
+                                src_stage: vk::PipelineStageFlags, src_access: vk::AccessFlags,
+                                dst_stage: vk::PipelineStageFlags, dst_access: vk::AccessFlags)
+    {
+        self.set_queue_family(family);
+
+        // The two halves are executed on different queues, therefore they can't be by region.
+        self.dependency_flags = 0;
+        self.src_stage_mask |= src_stage;
+        self.dst_stage_mask |= dst_stage;
+
+        match transfer.resource {
+            TransferResource::Buffer { buffer, offset, size } => {
+                self.buffer_barriers.push(vk::BufferMemoryBarrier {
+                    sType: vk::STRUCTURE_TYPE_BUFFER_MEMORY_BARRIER,
+                    pNext: ptr::null(),
+                    srcAccessMask: src_access,
+                    dstAccessMask: dst_access,
+                    srcQueueFamilyIndex: transfer.source_family,
+                    dstQueueFamilyIndex: transfer.destination_family,
+                    buffer: buffer,
+                    offset: offset,
+                    size: size,
+                });
+            },
+            TransferResource::Image { image, ref range, old_layout, new_layout } => {
+                self.image_barriers.push(vk::ImageMemoryBarrier {
+                    sType: vk::STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER,
+                    pNext: ptr::null(),
+                    srcAccessMask: src_access,
+                    dstAccessMask: dst_access,
+                    oldLayout: old_layout as u32,
+                    newLayout: new_layout as u32,
+                    srcQueueFamilyIndex: transfer.source_family,
+                    dstQueueFamilyIndex: transfer.destination_family,
+                    image: image,
+                    subresourceRange: vk::ImageSubresourceRange {
+                        aspectMask: range.aspectMask,
+                        baseMipLevel: range.baseMipLevel,
+                        levelCount: range.levelCount,
+                        baseArrayLayer: range.baseArrayLayer,
+                        layerCount: range.layerCount,
+                    },
+                });
+            },
+        }
+    }
+
+    fn set_queue_family(&mut self, family: u32) {
+        if let Some(current) = self.queue_family {
+            assert_eq!(current, family, "A pipeline barrier can't contain queue family \
+                                         ownership transfers of different queue families");
+        }
+
+        self.queue_family = Some(family);
+    }
+}
+
+/// A queue family ownership transfer whose release half has been added to a pipeline barrier,
+/// and whose acquire half must still be added with `PipelineBarrierBuilder::add_acquire`.
+///
+/// # Panic
+///
+/// - Panics when destroyed if it hasn't been passed to `add_acquire`.
+///
+#[must_use = "the acquire half of the transfer must be added with `add_acquire`"]
+pub struct OwnershipTransfer {
+    resource: TransferResource,
+    source_family: u32,
+    destination_family: u32,
+    acquired: bool,
+}
+
+enum TransferResource {
+    Buffer {
+        buffer: vk::Buffer,
+        offset: vk::DeviceSize,
+        size: vk::DeviceSize,
+    },
+    Image {
+        image: vk::Image,
+        range: vk::ImageSubresourceRange,
+        old_layout: Layout,
+        new_layout: Layout,
+    },
+}
+
+impl OwnershipTransfer {
+    fn new(source: QueueFamily, destination: QueueFamily, resource: TransferResource)
+           -> OwnershipTransfer
+    {
+        assert_eq!(source.physical_device().internal_object(),
+                   destination.physical_device().internal_object());
+        assert!(source.id() != destination.id());
+
+        OwnershipTransfer {
+            resource: resource,
+            source_family: source.id(),
+            destination_family: destination.id(),
+            acquired: false,
+        }
+    }
+
+    /// Returns the id of the queue family that releases the resource.
+    #[inline]
+    pub fn source_family(&self) -> u32 {
+        self.source_family
+    }
+
+    /// Returns the id of the queue family that acquires the resource.
+    #[inline]
+    pub fn destination_family(&self) -> u32 {
+        self.destination_family
+    }
+}
+
+impl Drop for OwnershipTransfer {
+    #[inline]
+    fn drop(&mut self) {
+        if !self.acquired && !thread::panicking() {
+            panic!("The acquire half of a queue family ownership transfer was never recorded");
+        }
+    }
 }
 
 pub struct UnsafeCommandBuffer<P> where P: CommandPool {
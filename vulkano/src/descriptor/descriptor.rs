@@ -8,6 +8,7 @@
 // according to those terms.
 
 use format::Format;
+use image::ViewType;
 use vk;
 
 /// Describes a single descriptor.
@@ -204,6 +205,20 @@ pub enum DescriptorImageDescDimensions {
     Cube,
 }
 
+impl DescriptorImageDescDimensions {
+    /// Returns the dimensions of a descriptor that an image view of the given type can be
+    /// bound to.
+    #[inline]
+    pub fn from_view_type(ty: ViewType) -> DescriptorImageDescDimensions {
+        match ty {
+            ViewType::Dim1d | ViewType::Dim1dArray => DescriptorImageDescDimensions::OneDimensional,
+            ViewType::Dim2d | ViewType::Dim2dArray => DescriptorImageDescDimensions::TwoDimensional,
+            ViewType::Dim3d => DescriptorImageDescDimensions::ThreeDimensional,
+            ViewType::Cubemap | ViewType::CubemapArray => DescriptorImageDescDimensions::Cube,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct DescriptorBufferDesc {
     pub dynamic: Option<bool>,
@@ -308,6 +323,31 @@ impl ShaderStages {
         (self.fragment || !other.fragment) &&
         (self.compute || !other.compute)
     }
+
+    /// Returns true if at least one stage is in both `self` and `other`.
+    #[inline]
+    pub fn intersects(&self, other: &ShaderStages) -> bool {
+        (self.vertex && other.vertex) ||
+        (self.tessellation_control && other.tessellation_control) ||
+        (self.tessellation_evaluation && other.tessellation_evaluation) ||
+        (self.geometry && other.geometry) ||
+        (self.fragment && other.fragment) ||
+        (self.compute && other.compute)
+    }
+
+    /// Returns the stages that are in `self` or in `other`.
+    #[inline]
+    pub fn union(&self, other: &ShaderStages) -> ShaderStages {
+        ShaderStages {
+            vertex: self.vertex || other.vertex,
+            tessellation_control: self.tessellation_control || other.tessellation_control,
+            tessellation_evaluation: self.tessellation_evaluation ||
+                                     other.tessellation_evaluation,
+            geometry: self.geometry || other.geometry,
+            fragment: self.fragment || other.fragment,
+            compute: self.compute || other.compute,
+        }
+    }
 }
 
 #[doc(hidden)]
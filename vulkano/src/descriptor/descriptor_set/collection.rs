@@ -12,14 +12,17 @@ use std::iter::Empty as EmptyIter;
 use std::option::IntoIter as OptionIntoIter;
 use std::sync::Arc;
 use std::vec::IntoIter as VecIntoIter;
+use smallvec::SmallVec;
 
 use buffer::traits::TrackedBuffer;
 use command_buffer::std::ResourcesStates;
 use command_buffer::submit::SubmitInfo;
 use command_buffer::sys::PipelineBarrierBuilder;
 use descriptor::descriptor::DescriptorDesc;
+use descriptor::descriptor::DescriptorType;
 use descriptor::descriptor_set::DescriptorSet;
 use descriptor::descriptor_set::DescriptorSetDesc;
+use device::Device;
 use device::Queue;
 use image::traits::TrackedImage;
 use sync::Fence;
@@ -42,6 +45,133 @@ pub unsafe trait DescriptorSetsCollection {
 
     /// Produces a description of the sets, as if it was a layout.
     fn description(&self) -> Self::SetsIter;
+
+    /// Returns the dynamic offsets to use when binding the sets.
+    ///
+    /// There must be one offset for each dynamic buffer descriptor of the sets, ordered by set
+    /// number, then by binding number, then by array element. The default implementation
+    /// returns an empty list. Use `DynamicOffsets` to provide offsets.
+    #[inline]
+    fn dynamic_offsets(&self) -> &[u32] {
+        &[]
+    }
+
+    /// Checks that the values returned by `dynamic_offsets` are valid for the sets of this
+    /// collection.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the number of offsets isn't the number of dynamic buffer descriptors of the
+    ///   sets.
+    /// - Panics if an offset for a dynamic uniform buffer isn't a multiple of the
+    ///   `min_uniform_buffer_offset_alignment` limit.
+    /// - Panics if an offset makes a dynamic buffer descriptor go past the end of its buffer.
+    ///
+    fn check_dynamic_offsets(&self, device: &Device) {
+        let limits = device.physical_device().limits();
+        let offsets = self.dynamic_offsets();
+
+        let dynamic_descriptors = self.list().flat_map(|set| {
+            set.inner().layout().dynamic_descriptors().iter().cloned()
+                                .collect::<SmallVec<[_; 8]>>().into_iter()
+        }).collect::<SmallVec<[_; 16]>>();
+
+        let ranges = self.list().flat_map(|set| set.inner().dynamic_buffer_ranges().into_iter())
+                                .collect::<SmallVec<[_; 16]>>();
+        debug_assert_eq!(dynamic_descriptors.len(), ranges.len());
+
+        assert_eq!(dynamic_descriptors.len(), offsets.len());
+
+        for (&range, &offset) in ranges.iter().zip(offsets.iter()) {
+            if let Some((buffer_size, range_offset, range_size)) = range {
+                assert!(offset as u64 + range_offset as u64 + range_size as u64 <=
+                        buffer_size as u64);
+            }
+        }
+
+        for (&ty, &offset) in dynamic_descriptors.iter().zip(offsets.iter()) {
+            match ty {
+                DescriptorType::UniformBufferDynamic => {
+                    let alignment = limits.min_uniform_buffer_offset_alignment();
+                    assert!(offset as u64 % alignment == 0);
+                },
+                DescriptorType::StorageBufferDynamic => {
+                    let alignment = limits.min_storage_buffer_offset_alignment();
+                    assert!(offset as u64 % alignment == 0);
+                },
+                _ => ()
+            }
+        }
+    }
+}
+
+/// Wraps around a collection of descriptor sets and associates dynamic offsets to it.
+///
+/// Dynamic uniform and storage buffer descriptors are bound with an additional offset that is
+/// provided when the descriptor sets are bound. This makes it possible to use a single large
+/// buffer and a single descriptor set for many draw commands, with a different offset each time.
+///
+/// # Example
+///
+/// ```ignore
+/// let sets = DynamicOffsets::new(&set, Some(256 * object_index));
+/// command_buffer.draw(&pipeline, &vertex_buffer, &dynamic, sets, &())
+/// ```
+#[derive(Debug, Clone)]
+pub struct DynamicOffsets<S> {
+    sets: S,
+    offsets: SmallVec<[u32; 8]>,
+}
+
+impl<S> DynamicOffsets<S> where S: DescriptorSetsCollection {
+    /// Associates the list of offsets to the descriptor sets.
+    ///
+    /// The offsets are checked when the sets are bound, not when calling this function.
+    #[inline]
+    pub fn new<I>(sets: S, offsets: I) -> DynamicOffsets<S>
+        where I: IntoIterator<Item = u32>
+    {
+        DynamicOffsets {
+            sets: sets,
+            offsets: offsets.into_iter().collect(),
+        }
+    }
+}
+
+unsafe impl<S> DescriptorSetsCollection for DynamicOffsets<S> where S: DescriptorSetsCollection {
+    type ListIter = S::ListIter;
+    type SetsIter = S::SetsIter;
+    type DescIter = S::DescIter;
+
+    #[inline]
+    fn list(&self) -> Self::ListIter {
+        self.sets.list()
+    }
+
+    #[inline]
+    fn description(&self) -> Self::SetsIter {
+        self.sets.description()
+    }
+
+    #[inline]
+    fn dynamic_offsets(&self) -> &[u32] {
+        &self.offsets
+    }
+}
+
+unsafe impl<S> TrackedDescriptorSetsCollection for DynamicOffsets<S>
+    where S: TrackedDescriptorSetsCollection
+{
+    type State = S::State;
+    type Finished = S::Finished;
+
+    #[inline]
+    unsafe fn extract_states_and_transition<L>(&self, list: &mut L)
+                                               -> (Self::State, usize, PipelineBarrierBuilder)
+        where L: ResourcesStates
+    {
+        self.sets.extract_states_and_transition(list)
+    }
 }
 
 /// Extension trait for a descriptor sets collection so that it can be used with the standard
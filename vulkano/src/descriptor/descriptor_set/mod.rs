@@ -21,14 +21,26 @@ use sync::PipelineStages;
 use sync::Semaphore;
 
 pub use self::collection::DescriptorSetsCollection;
+pub use self::collection::DynamicOffsets;
 pub use self::pool::DescriptorPool;
+pub use self::pool::DescriptorsCount;
+pub use self::ring_pool::RingDescriptorPool;
+pub use self::runtime::RuntimeDescriptorSet;
+pub use self::runtime::RuntimeDescriptorSetCreationError;
+pub use self::std_pool::StdDescriptorPool;
+pub use self::sys::DescriptorCopy;
+pub use self::sys::DescriptorSetAllocError;
 pub use self::sys::UnsafeDescriptorSet;
 pub use self::sys::DescriptorWrite;
+pub use self::sys::DescriptorWriteBatch;
 pub use self::unsafe_layout::UnsafeDescriptorSetLayout;
 
 pub mod collection;
 
 mod pool;
+mod ring_pool;
+mod runtime;
+mod std_pool;
 mod sys;
 mod unsafe_layout;
 
@@ -8,11 +8,16 @@
 // according to those terms.
 
 use std::mem;
+use std::ops;
 use std::ptr;
 use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::sync::Mutex;
 use std::sync::MutexGuard;
+use smallvec::SmallVec;
 
+use descriptor::descriptor::DescriptorType;
 use device::Device;
 
 use OomError;
@@ -22,63 +27,180 @@ use VulkanPointers;
 use check_errors;
 use vk;
 
+macro_rules! descriptors_count {
+    ($($name:ident => $ty:ident,)+) => (
+        /// Number of descriptors of each type.
+        ///
+        /// Used to describe the capacity of a descriptor pool, or the number of descriptors
+        /// required by a descriptor set layout.
+        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+        #[allow(missing_docs)]
+        pub struct DescriptorsCount {
+            $(
+                pub $name: u32,
+            )+
+        }
+
+        impl DescriptorsCount {
+            /// Builds a `DescriptorsCount` with zero descriptors of each type.
+            #[inline]
+            pub fn zero() -> DescriptorsCount {
+                DescriptorsCount {
+                    $(
+                        $name: 0,
+                    )+
+                }
+            }
+
+            /// Builds a `DescriptorsCount` with `num` descriptors of each type.
+            #[inline]
+            pub fn uniform(num: u32) -> DescriptorsCount {
+                DescriptorsCount {
+                    $(
+                        $name: num,
+                    )+
+                }
+            }
+
+            /// Adds `num` descriptors of the given type.
+            #[inline]
+            pub fn add_num(&mut self, ty: DescriptorType, num: u32) {
+                match ty {
+                    $(
+                        DescriptorType::$ty => self.$name += num,
+                    )+
+                }
+            }
+
+            /// Returns true if there are at most as many descriptors of each type in `self` as
+            /// in `other`.
+            #[inline]
+            pub fn fits_in(&self, other: &DescriptorsCount) -> bool {
+                $(
+                    self.$name <= other.$name
+                ) && +
+            }
+
+            // Returns the non-zero counts as a list of pool sizes.
+            fn pool_sizes(&self) -> SmallVec<[vk::DescriptorPoolSize; 16]> {
+                let mut sizes = SmallVec::new();
+                $(
+                    if self.$name != 0 {
+                        sizes.push(vk::DescriptorPoolSize {
+                            ty: DescriptorType::$ty as u32,
+                            descriptorCount: self.$name,
+                        });
+                    }
+                )+
+                sizes
+            }
+        }
+
+        impl ops::Add for DescriptorsCount {
+            type Output = DescriptorsCount;
+
+            #[inline]
+            fn add(self, rhs: DescriptorsCount) -> DescriptorsCount {
+                DescriptorsCount {
+                    $(
+                        $name: self.$name + rhs.$name,
+                    )+
+                }
+            }
+        }
+
+        impl ops::Sub for DescriptorsCount {
+            type Output = DescriptorsCount;
+
+            #[inline]
+            fn sub(self, rhs: DescriptorsCount) -> DescriptorsCount {
+                DescriptorsCount {
+                    $(
+                        $name: self.$name - rhs.$name,
+                    )+
+                }
+            }
+        }
+
+        impl ops::Mul<u32> for DescriptorsCount {
+            type Output = DescriptorsCount;
+
+            #[inline]
+            fn mul(self, rhs: u32) -> DescriptorsCount {
+                DescriptorsCount {
+                    $(
+                        $name: self.$name * rhs,
+                    )+
+                }
+            }
+        }
+    );
+}
+
+descriptors_count!{
+    uniform_buffer => UniformBuffer,
+    storage_buffer => StorageBuffer,
+    uniform_buffer_dynamic => UniformBufferDynamic,
+    storage_buffer_dynamic => StorageBufferDynamic,
+    uniform_texel_buffer => UniformTexelBuffer,
+    storage_texel_buffer => StorageTexelBuffer,
+    sampled_image => SampledImage,
+    storage_image => StorageImage,
+    sampler => Sampler,
+    combined_image_sampler => CombinedImageSampler,
+    input_attachment => InputAttachment,
+}
+
 /// Pool from which descriptor sets are allocated from.
 ///
 /// A pool has a maximum number of descriptor sets and a maximum number of descriptors (one value
-/// per descriptor type) it can allocate.
+/// per descriptor type) it can allocate. Destroying a descriptor set gives its room back to the
+/// pool.
+///
+/// If you don't want to manage the capacity yourself, use a `StdDescriptorPool` instead.
 pub struct DescriptorPool {
     pool: Mutex<vk::DescriptorPool>,
     device: Arc<Device>,
+    max_sets: u32,
+    capacity: DescriptorsCount,
+    // Number of sets and descriptors that can still be allocated.
+    remaining: Mutex<(u32, DescriptorsCount)>,
+    // Number of times the pool has been reset.
+    resets: AtomicUsize,
 }
 
 impl DescriptorPool {
     /// See the docs of new().
-    // FIXME: capacity of the pool
+    #[inline]
     pub fn raw(device: &Arc<Device>) -> Result<DescriptorPool, OomError> {
+        DescriptorPool::raw_with_capacity(device, 100, &DescriptorsCount::uniform(10))
+    }
+
+    /// Initializes a new pool with a default capacity.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device or host ran out of memory.
+    ///
+    #[inline]
+    pub fn new(device: &Arc<Device>) -> Arc<DescriptorPool> {
+        Arc::new(DescriptorPool::raw(device).unwrap())
+    }
+
+    /// See the docs of with_capacity().
+    pub fn raw_with_capacity(device: &Arc<Device>, max_sets: u32, capacity: &DescriptorsCount)
+                             -> Result<DescriptorPool, OomError>
+    {
         let vk = device.pointers();
 
-        // FIXME: arbitrary
-        let pool_sizes = vec![
-            vk::DescriptorPoolSize {
-                ty: vk::DESCRIPTOR_TYPE_UNIFORM_BUFFER,
-                descriptorCount: 10,
-            },
-            vk::DescriptorPoolSize {
-                ty: vk::DESCRIPTOR_TYPE_STORAGE_BUFFER,
-                descriptorCount: 10,
-            },
-            vk::DescriptorPoolSize {
-                ty: vk::DESCRIPTOR_TYPE_UNIFORM_TEXEL_BUFFER,
-                descriptorCount: 10,
-            },
-            vk::DescriptorPoolSize {
-                ty: vk::DESCRIPTOR_TYPE_STORAGE_TEXEL_BUFFER,
-                descriptorCount: 10,
-            },
-            vk::DescriptorPoolSize {
-                 ty: vk::DESCRIPTOR_TYPE_STORAGE_IMAGE,
-                 descriptorCount: 10,
-            },
-            vk::DescriptorPoolSize {
-                ty: vk::DESCRIPTOR_TYPE_INPUT_ATTACHMENT,
-                descriptorCount: 10,
-            },
-            vk::DescriptorPoolSize {
-                ty: vk::DESCRIPTOR_TYPE_SAMPLED_IMAGE,
-                descriptorCount: 10,
-            },
-            vk::DescriptorPoolSize {
-                ty: vk::DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER,
-                descriptorCount: 10,
-            },
-        ];
+        let pool_sizes = capacity.pool_sizes();
 
         let pool = unsafe {
             let infos = vk::DescriptorPoolCreateInfo {
                 sType: vk::STRUCTURE_TYPE_DESCRIPTOR_POOL_CREATE_INFO,
                 pNext: ptr::null(),
-                flags: vk::DESCRIPTOR_POOL_CREATE_FREE_DESCRIPTOR_SET_BIT,   // TODO:
-                maxSets: 100,       // TODO: let user choose
+                flags: vk::DESCRIPTOR_POOL_CREATE_FREE_DESCRIPTOR_SET_BIT,
+                maxSets: max_sets,
                 poolSizeCount: pool_sizes.len() as u32,
                 pPoolSizes: pool_sizes.as_ptr(),
             };
@@ -92,19 +214,28 @@ impl DescriptorPool {
         Ok(DescriptorPool {
             pool: Mutex::new(pool),
             device: device.clone(),
+            max_sets: max_sets,
+            capacity: *capacity,
+            remaining: Mutex::new((max_sets, *capacity)),
+            resets: AtomicUsize::new(0),
         })
     }
-    
-    /// Initializes a new pool.
+
+    /// Initializes a new pool that can hold at most `max_sets` descriptor sets and at most
+    /// the number of descriptors of each type given by `capacity`.
     ///
     /// # Panic
     ///
+    /// - Panics if `max_sets` is 0 or if `capacity` is zero for all the types of descriptors.
     /// - Panics if the device or host ran out of memory.
     ///
-    // FIXME: capacity of the pool
     #[inline]
-    pub fn new(device: &Arc<Device>) -> Arc<DescriptorPool> {
-        Arc::new(DescriptorPool::raw(device).unwrap())
+    pub fn with_capacity(device: &Arc<Device>, max_sets: u32, capacity: &DescriptorsCount)
+                         -> Arc<DescriptorPool>
+    {
+        assert!(max_sets != 0);
+        assert!(*capacity != DescriptorsCount::zero());
+        Arc::new(DescriptorPool::raw_with_capacity(device, max_sets, capacity).unwrap())
     }
 
     /// Returns the device this pool was created from.
@@ -112,6 +243,103 @@ impl DescriptorPool {
     pub fn device(&self) -> &Arc<Device> {
         &self.device
     }
+
+    /// Returns the maximum number of descriptor sets that this pool can hold.
+    #[inline]
+    pub fn max_sets(&self) -> u32 {
+        self.max_sets
+    }
+
+    /// Returns the maximum number of descriptors of each type that this pool can hold.
+    #[inline]
+    pub fn capacity(&self) -> &DescriptorsCount {
+        &self.capacity
+    }
+
+    /// Returns the number of descriptor sets that can still be allocated from this pool.
+    #[inline]
+    pub fn remaining_sets(&self) -> u32 {
+        self.remaining.lock().unwrap().0
+    }
+
+    /// Returns the number of descriptors of each type that can still be allocated from this
+    /// pool.
+    #[inline]
+    pub fn remaining_descriptors(&self) -> DescriptorsCount {
+        self.remaining.lock().unwrap().1
+    }
+
+    /// Returns true if a descriptor set with the given number of descriptors can be allocated
+    /// from this pool.
+    #[inline]
+    pub fn has_room_for(&self, descriptors: &DescriptorsCount) -> bool {
+        let remaining = self.remaining.lock().unwrap();
+        remaining.0 >= 1 && descriptors.fits_in(&remaining.1)
+    }
+
+    /// Marks the room for one descriptor set with the given number of descriptors as used.
+    /// Returns false and doesn't modify anything if there isn't enough room.
+    ///
+    /// # Safety
+    ///
+    /// - Must be matched by a call to `release` with the same value once the descriptor set
+    ///   is destroyed. This is done automatically by `UnsafeDescriptorSet`.
+    ///
+    pub unsafe fn reserve(&self, descriptors: &DescriptorsCount) -> bool {
+        let mut remaining = self.remaining.lock().unwrap();
+
+        if remaining.0 == 0 || !descriptors.fits_in(&remaining.1) {
+            return false;
+        }
+
+        remaining.0 -= 1;
+        remaining.1 = remaining.1 - *descriptors;
+        true
+    }
+
+    /// Gives back the room of a descriptor set that was previously reserved with `reserve`.
+    ///
+    /// # Safety
+    ///
+    /// - Must match a previous call to `reserve`.
+    ///
+    pub unsafe fn release(&self, descriptors: &DescriptorsCount) {
+        let mut remaining = self.remaining.lock().unwrap();
+        remaining.0 += 1;
+        remaining.1 = remaining.1 + *descriptors;
+        debug_assert!(remaining.0 <= self.max_sets);
+        debug_assert!(remaining.1.fits_in(&self.capacity));
+    }
+
+    /// Frees all the descriptor sets that were allocated from this pool at once.
+    ///
+    /// The `UnsafeDescriptorSet`s that were allocated before the reset can still be dropped
+    /// afterwards, in which case they don't give back any room to the pool.
+    ///
+    /// # Safety
+    ///
+    /// - The descriptor sets that were allocated from this pool must no longer be used, neither
+    ///   by the CPU nor by the GPU.
+    ///
+    pub unsafe fn reset(&self) -> Result<(), OomError> {
+        let vk = self.device.pointers();
+        let pool = self.pool.lock().unwrap();
+        let mut remaining = self.remaining.lock().unwrap();
+
+        try!(check_errors(vk.ResetDescriptorPool(self.device.internal_object(), *pool, 0)));
+
+        *remaining = (self.max_sets, self.capacity);
+        self.resets.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Returns the number of times this pool has been reset.
+    ///
+    /// Used by `UnsafeDescriptorSet` to know whether it has already been freed by a reset.
+    #[inline]
+    pub fn num_resets(&self) -> usize {
+        self.resets.load(Ordering::SeqCst)
+    }
 }
 
 unsafe impl SynchronizedVulkanObject for DescriptorPool {
@@ -136,7 +364,9 @@ impl Drop for DescriptorPool {
 
 #[cfg(test)]
 mod tests {
+    use descriptor::descriptor::DescriptorType;
     use descriptor::descriptor_set::DescriptorPool;
+    use descriptor::descriptor_set::DescriptorsCount;
 
     #[test]
     fn create() {
@@ -150,4 +380,39 @@ mod tests {
         let pool = DescriptorPool::new(&device);
         assert_eq!(&**pool.device() as *const _, &*device as *const _);
     }
+
+    #[test]
+    fn reserve_release() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pool = DescriptorPool::with_capacity(&device, 2, &DescriptorsCount::uniform(3));
+
+        let mut count = DescriptorsCount::zero();
+        count.add_num(DescriptorType::UniformBuffer, 2);
+
+        unsafe {
+            assert!(pool.reserve(&count));
+            assert!(!pool.reserve(&count));
+            assert_eq!(pool.remaining_descriptors().uniform_buffer, 1);
+            assert!(pool.has_room_for(&DescriptorsCount::zero()));
+
+            pool.release(&count);
+            assert_eq!(pool.remaining_sets(), 2);
+            assert_eq!(pool.remaining_descriptors(), DescriptorsCount::uniform(3));
+        }
+    }
+
+    #[test]
+    fn reset() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pool = DescriptorPool::with_capacity(&device, 2, &DescriptorsCount::uniform(3));
+
+        unsafe {
+            assert!(pool.reserve(&DescriptorsCount::uniform(1)));
+            pool.reset().unwrap();
+        }
+
+        assert_eq!(pool.num_resets(), 1);
+        assert_eq!(pool.remaining_sets(), 2);
+        assert_eq!(pool.remaining_descriptors(), DescriptorsCount::uniform(3));
+    }
 }
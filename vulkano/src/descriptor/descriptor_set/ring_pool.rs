@@ -0,0 +1,211 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use descriptor::descriptor_set::DescriptorPool;
+use descriptor::descriptor_set::DescriptorSetAllocError;
+use descriptor::descriptor_set::DescriptorsCount;
+use descriptor::descriptor_set::UnsafeDescriptorSet;
+use descriptor::descriptor_set::UnsafeDescriptorSetLayout;
+use device::Device;
+use sync::Fence;
+use sync::FenceWaitError;
+
+use OomError;
+
+// Number of descriptor sets that each newly-created pool can hold.
+const SETS_PER_POOL: u32 = 64;
+
+/// Descriptor pool for descriptor sets that only live for the duration of a frame.
+///
+/// The pool is made of a ring of frames, each frame owning its own list of `DescriptorPool`s.
+/// Descriptor sets are allocated from the pools of the current frame. When you call
+/// `next_frame`, the current frame is associated to the fence of the submission that uses its
+/// descriptor sets, and the pool switches to the next frame of the ring. If that frame was
+/// already used before, the pool waits for its fence to be signaled and then resets all of its
+/// pools at once.
+///
+/// This is cheaper than freeing the descriptor sets one by one, and is suitable for sets whose
+/// content changes every frame.
+pub struct RingDescriptorPool {
+    device: Arc<Device>,
+    state: Mutex<RingState>,
+}
+
+struct RingState {
+    frames: Vec<Frame>,
+    current: usize,
+}
+
+struct Frame {
+    pools: Vec<Arc<DescriptorPool>>,
+    // Fence to wait upon before the pools can be reset. `None` if the frame isn't in use by the
+    // GPU.
+    fence: Option<Arc<Fence>>,
+}
+
+impl RingDescriptorPool {
+    /// Builds a new pool with the given number of frames.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `num_frames` is 0.
+    ///
+    pub fn new(device: &Arc<Device>, num_frames: usize) -> Arc<RingDescriptorPool> {
+        assert!(num_frames != 0);
+
+        let frames = (0 .. num_frames).map(|_| {
+            Frame { pools: Vec::new(), fence: None }
+        }).collect();
+
+        Arc::new(RingDescriptorPool {
+            device: device.clone(),
+            state: Mutex::new(RingState {
+                frames: frames,
+                current: 0,
+            }),
+        })
+    }
+
+    /// Allocates a new descriptor set with the given layout from the current frame.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the pool and the layout were not created from the same `Device`.
+    ///
+    /// # Safety
+    ///
+    /// - The descriptor set is uninitialized. See `UnsafeDescriptorSet::uninitialized`.
+    /// - The descriptor set must not be used anymore, neither by the CPU nor by the GPU, once
+    ///   its frame has been recycled by `next_frame`.
+    ///
+    pub unsafe fn alloc(&self, layout: &Arc<UnsafeDescriptorSetLayout>)
+                        -> Result<UnsafeDescriptorSet, OomError>
+    {
+        assert_eq!(&*self.device as *const Device, &**layout.device() as *const Device);
+
+        let count = layout.descriptors_count();
+        let mut state = self.state.lock().unwrap();
+        let current = state.current;
+        let frame = &mut state.frames[current];
+
+        for pool in frame.pools.iter() {
+            match UnsafeDescriptorSet::uninitialized_raw(pool, layout) {
+                Ok(set) => return Ok(set),
+                Err(DescriptorSetAllocError::OomError(err)) => return Err(err),
+                Err(DescriptorSetAllocError::OutOfPoolMemory) => (),
+            }
+        }
+
+        // Same strategy as `StdDescriptorPool`.
+        let capacity = *count * SETS_PER_POOL + DescriptorsCount::uniform(SETS_PER_POOL);
+        let pool = Arc::new(try!(DescriptorPool::raw_with_capacity(&self.device, SETS_PER_POOL,
+                                                                   &capacity)));
+        frame.pools.push(pool.clone());
+        match UnsafeDescriptorSet::uninitialized_raw(&pool, layout) {
+            Ok(set) => Ok(set),
+            Err(DescriptorSetAllocError::OomError(err)) => Err(err),
+            Err(DescriptorSetAllocError::OutOfPoolMemory) => {
+                unreachable!("A new pool always has room for the descriptor set")
+            },
+        }
+    }
+
+    /// Ends the current frame and switches to the next one.
+    ///
+    /// `fence` must be the fence that is signaled once the GPU has finished using the
+    /// descriptor sets allocated during the current frame, for example the fence of the
+    /// `Submission` of the frame.
+    ///
+    /// If the next frame is still in use by the GPU, this function blocks until its fence is
+    /// signaled or until `timeout` has elapsed. In case of error, the current frame doesn't
+    /// change.
+    pub fn next_frame(&self, fence: Arc<Fence>, timeout: Duration)
+                      -> Result<(), FenceWaitError>
+    {
+        let mut state = self.state.lock().unwrap();
+        let next = (state.current + 1) % state.frames.len();
+
+        if let Some(ref next_fence) = state.frames[next].fence {
+            try!(next_fence.wait(timeout));
+        }
+
+        // The GPU has finished using the sets of the next frame, and the safety requirements of
+        // `alloc` guarantee that the CPU doesn't use them anymore either.
+        for pool in state.frames[next].pools.iter() {
+            unsafe { try!(pool.reset().map_err(FenceWaitError::OomError)); }
+        }
+        state.frames[next].fence = None;
+
+        let current = state.current;
+        state.frames[current].fence = Some(fence);
+        state.current = next;
+        Ok(())
+    }
+
+    /// Returns the number of frames of the ring.
+    #[inline]
+    pub fn num_frames(&self) -> usize {
+        self.state.lock().unwrap().frames.len()
+    }
+
+    /// Returns the number of Vulkan descriptor pools that have been created by this pool,
+    /// summed over all the frames.
+    #[inline]
+    pub fn num_pools(&self) -> usize {
+        self.state.lock().unwrap().frames.iter().map(|f| f.pools.len()).sum()
+    }
+
+    /// Returns the device this pool was created from.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use descriptor::descriptor::DescriptorDesc;
+    use descriptor::descriptor::DescriptorDescTy;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::descriptor_set::RingDescriptorPool;
+    use descriptor::descriptor_set::UnsafeDescriptorSetLayout;
+    use sync::Fence;
+
+    #[test]
+    fn recycles_frames() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let desc = DescriptorDesc {
+            binding: 0,
+            ty: DescriptorDescTy::Sampler,
+            array_count: 1,
+            stages: ShaderStages::all(),
+            readonly: true,
+        };
+
+        let layout = UnsafeDescriptorSetLayout::new(device.clone(), Some(desc));
+        let pool = RingDescriptorPool::new(&device, 2);
+        assert_eq!(pool.num_frames(), 2);
+
+        for _ in 0 .. 6 {
+            let sets = (0 .. 10).map(|_| unsafe { pool.alloc(&layout).unwrap() })
+                                .collect::<Vec<_>>();
+            pool.next_frame(Fence::signaled(device.clone()), Duration::from_secs(1)).unwrap();
+            drop(sets);
+        }
+
+        assert_eq!(pool.num_pools(), 2);
+    }
+}
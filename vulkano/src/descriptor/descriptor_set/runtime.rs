@@ -0,0 +1,300 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+use std::vec::IntoIter as VecIntoIter;
+
+use descriptor::descriptor::DescriptorDesc;
+use descriptor::descriptor::DescriptorDescTy;
+use descriptor::descriptor::DescriptorImageDescDimensions;
+use descriptor::descriptor_set::DescriptorCopy;
+use descriptor::descriptor_set::DescriptorPool;
+use descriptor::descriptor_set::DescriptorSet;
+use descriptor::descriptor_set::DescriptorSetAllocError;
+use descriptor::descriptor_set::DescriptorSetDesc;
+use descriptor::descriptor_set::DescriptorWrite;
+use descriptor::descriptor_set::StdDescriptorPool;
+use descriptor::descriptor_set::UnsafeDescriptorSet;
+use descriptor::descriptor_set::UnsafeDescriptorSetLayout;
+use device::Device;
+use image::ImageAspect;
+
+use Error;
+use OomError;
+use VulkanError;
+
+/// Descriptor set whose layout is only known at runtime.
+///
+/// Contrary to the sets generated by the `pipeline_layout!` macro, the content of this descriptor
+/// set is checked against its layout when it is created or modified.
+pub struct RuntimeDescriptorSet {
+    inner: UnsafeDescriptorSet,
+}
+
+impl RuntimeDescriptorSet {
+    /// Builds a new descriptor set allocated from an explicit pool, and fills it with the given
+    /// writes.
+    ///
+    /// Returns `OutOfPoolMemory` if the pool doesn't have enough room for the descriptor set.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the pool and the layout were not created from the same `Device`.
+    ///
+    pub fn raw(pool: &Arc<DescriptorPool>, layout: &Arc<UnsafeDescriptorSetLayout>,
+               writes: Vec<DescriptorWrite>)
+               -> Result<RuntimeDescriptorSet, RuntimeDescriptorSetCreationError>
+    {
+        try!(check_writes(layout, &writes));
+
+        unsafe {
+            let mut set = try!(UnsafeDescriptorSet::uninitialized_raw(pool, layout));
+            set.write(writes);
+            Ok(RuntimeDescriptorSet { inner: set })
+        }
+    }
+
+    /// Builds a new descriptor set allocated from the standard descriptor pool of the device,
+    /// and fills it with the given writes.
+    pub fn new(layout: &Arc<UnsafeDescriptorSetLayout>, writes: Vec<DescriptorWrite>)
+               -> Result<Arc<RuntimeDescriptorSet>, RuntimeDescriptorSetCreationError>
+    {
+        try!(check_writes(layout, &writes));
+
+        unsafe {
+            let pool = Device::standard_descriptor_pool(layout.device());
+            let mut set = try!(StdDescriptorPool::alloc(&pool, layout));
+            set.write(writes);
+            Ok(Arc::new(RuntimeDescriptorSet { inner: set }))
+        }
+    }
+
+    /// Modifies some descriptors of the set.
+    ///
+    /// Since this requires a mutable reference, the set can't be in use by a command buffer. If
+    /// you only have an `Arc<RuntimeDescriptorSet>`, use `Arc::get_mut`.
+    ///
+    /// The resources that were previously in the modified descriptors are released, and are
+    /// only kept alive by the command buffers that still use them.
+    pub fn update(&mut self, writes: Vec<DescriptorWrite>)
+                  -> Result<(), RuntimeDescriptorSetCreationError>
+    {
+        try!(check_writes(self.inner.layout(), &writes));
+
+        unsafe {
+            self.inner.write(writes);
+        }
+
+        Ok(())
+    }
+
+    /// Copies descriptors from another set into this one.
+    ///
+    /// Since this requires a mutable reference, the set can't be in use by a command buffer.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the two sets were not created from the same `Device`.
+    ///
+    pub fn copy_from(&mut self, source: &RuntimeDescriptorSet, copies: &[DescriptorCopy])
+                     -> Result<(), RuntimeDescriptorSetCreationError>
+    {
+        for copy in copies {
+            let src = try!(find_binding(source.inner.layout(), copy.source_binding));
+            let dst = try!(find_binding(self.inner.layout(), copy.destination_binding));
+
+            if src.ty.ty() != dst.ty.ty() {
+                return Err(RuntimeDescriptorSetCreationError::WrongDescriptorType);
+            }
+
+            if copy.source_first_array_element + copy.count > src.array_count ||
+               copy.destination_first_array_element + copy.count > dst.array_count
+            {
+                return Err(RuntimeDescriptorSetCreationError::ArrayIndexOutOfRange);
+            }
+        }
+
+        unsafe {
+            self.inner.copy(&source.inner, copies);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the layout of this descriptor set.
+    #[inline]
+    pub fn layout(&self) -> &Arc<UnsafeDescriptorSetLayout> {
+        self.inner.layout()
+    }
+}
+
+unsafe impl DescriptorSet for RuntimeDescriptorSet {
+    #[inline]
+    fn inner(&self) -> &UnsafeDescriptorSet {
+        &self.inner
+    }
+}
+
+unsafe impl DescriptorSetDesc for RuntimeDescriptorSet {
+    type Iter = VecIntoIter<DescriptorDesc>;
+
+    #[inline]
+    fn desc(&self) -> Self::Iter {
+        let mut descriptors = self.inner.layout().descriptors().to_vec();
+        descriptors.sort_by_key(|desc| desc.binding);
+        descriptors.into_iter()
+    }
+}
+
+// Checks that each write corresponds to a descriptor of the layout.
+fn check_writes(layout: &UnsafeDescriptorSetLayout, writes: &[DescriptorWrite])
+                -> Result<(), RuntimeDescriptorSetCreationError>
+{
+    for write in writes {
+        let desc = try!(find_binding(layout, write.binding()));
+
+        if desc.ty.ty() != Some(write.ty()) {
+            return Err(RuntimeDescriptorSetCreationError::WrongDescriptorType);
+        }
+
+        if write.first_array_element() >= desc.array_count {
+            return Err(RuntimeDescriptorSetCreationError::ArrayIndexOutOfRange);
+        }
+
+        let image_desc = match desc.ty {
+            DescriptorDescTy::CombinedImageSampler(ref d) => Some(d),
+            DescriptorDescTy::Image(ref d) => Some(d),
+            _ => None
+        };
+
+        if write.image_view_aspect() == Some(ImageAspect::DepthStencil) {
+            return Err(RuntimeDescriptorSetCreationError::DepthStencilAspects);
+        }
+
+        if let (Some(image_desc), Some(dims)) = (image_desc, write.image_view_dimensions()) {
+            let view_dims = DescriptorImageDescDimensions::from_view_type(dims.to_view_type());
+            if view_dims != image_desc.dimensions {
+                return Err(RuntimeDescriptorSetCreationError::WrongImageDimensions);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Returns the description of a binding of the layout.
+fn find_binding(layout: &UnsafeDescriptorSetLayout, binding: u32)
+                -> Result<&DescriptorDesc, RuntimeDescriptorSetCreationError>
+{
+    match layout.descriptors().iter().find(|d| d.binding == binding) {
+        Some(d) => Ok(d),
+        None => Err(RuntimeDescriptorSetCreationError::MissingBinding),
+    }
+}
+
+/// Error that can happen when creating or modifying a `RuntimeDescriptorSet`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RuntimeDescriptorSetCreationError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The pool doesn't have enough room left for the descriptor set.
+    OutOfPoolMemory,
+    /// One of the writes or copies targets a binding that doesn't exist in the layout.
+    MissingBinding,
+    /// The type of one of the writes or copies doesn't match the type of the descriptor in the
+    /// layout.
+    WrongDescriptorType,
+    /// One of the writes or copies targets an array element that is out of range of its
+    /// binding.
+    ArrayIndexOutOfRange,
+    /// The dimensions of an image view that is written don't match the dimensions of the
+    /// descriptor in the layout, for example a 2D view written to a 3D image descriptor.
+    WrongImageDimensions,
+    /// An image view that is written contains both the depth and the stencil aspects of an
+    /// image. Only one of them can be accessed through a descriptor.
+    DepthStencilAspects,
+}
+
+impl error::Error for RuntimeDescriptorSetCreationError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            RuntimeDescriptorSetCreationError::OomError(_) => {
+                "not enough memory available"
+            },
+            RuntimeDescriptorSetCreationError::OutOfPoolMemory => {
+                "the pool doesn't have enough room left for the descriptor set"
+            },
+            RuntimeDescriptorSetCreationError::MissingBinding => {
+                "one of the writes or copies targets a binding that doesn't exist in the layout"
+            },
+            RuntimeDescriptorSetCreationError::WrongDescriptorType => {
+                "the type of one of the writes or copies doesn't match the layout"
+            },
+            RuntimeDescriptorSetCreationError::ArrayIndexOutOfRange => {
+                "one of the writes or copies targets an array element that is out of \
+                 range"
+            },
+            RuntimeDescriptorSetCreationError::WrongImageDimensions => {
+                "the dimensions of an image view don't match the dimensions of the descriptor"
+            },
+            RuntimeDescriptorSetCreationError::DepthStencilAspects => {
+                "an image view contains both the depth and the stencil aspects of an image"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            RuntimeDescriptorSetCreationError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for RuntimeDescriptorSetCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for RuntimeDescriptorSetCreationError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            RuntimeDescriptorSetCreationError::OomError(ref err) => err.error_code(),
+            _ => None
+        }
+    }
+}
+
+impl From<OomError> for RuntimeDescriptorSetCreationError {
+    #[inline]
+    fn from(err: OomError) -> RuntimeDescriptorSetCreationError {
+        RuntimeDescriptorSetCreationError::OomError(err)
+    }
+}
+
+impl From<DescriptorSetAllocError> for RuntimeDescriptorSetCreationError {
+    #[inline]
+    fn from(err: DescriptorSetAllocError) -> RuntimeDescriptorSetCreationError {
+        match err {
+            DescriptorSetAllocError::OomError(err) => {
+                RuntimeDescriptorSetCreationError::OomError(err)
+            },
+            DescriptorSetAllocError::OutOfPoolMemory => {
+                RuntimeDescriptorSetCreationError::OutOfPoolMemory
+            },
+        }
+    }
+}
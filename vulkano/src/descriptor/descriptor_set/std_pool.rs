@@ -0,0 +1,162 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use descriptor::descriptor_set::DescriptorPool;
+use descriptor::descriptor_set::DescriptorSetAllocError;
+use descriptor::descriptor_set::DescriptorsCount;
+use descriptor::descriptor_set::UnsafeDescriptorSet;
+use descriptor::descriptor_set::UnsafeDescriptorSetLayout;
+use descriptor::descriptor_set::sys::alloc_from_std_pool;
+use device::Device;
+
+use OomError;
+
+// Number of descriptor sets that each newly-created pool can hold.
+const SETS_PER_POOL: u32 = 64;
+
+/// Descriptor pool that grows automatically.
+///
+/// Internally holds a list of `DescriptorPool`s. When a descriptor set is allocated, the first
+/// pool that has enough room is used, and a new pool is created if none of them has. When a
+/// descriptor set is destroyed, its room is given back to the pool it was allocated from and can
+/// be reused by later allocations.
+///
+/// This is the pool used by default by the descriptor sets constructors. You can get the
+/// standard pool of a device with `Device::standard_descriptor_pool`. The descriptor sets keep
+/// the pool that they were allocated from alive, so that the device keeps returning the same
+/// pool as long as one of its sets exists.
+pub struct StdDescriptorPool {
+    device: Arc<Device>,
+    pools: Mutex<Vec<Arc<DescriptorPool>>>,
+}
+
+impl StdDescriptorPool {
+    /// Builds a new empty pool.
+    #[inline]
+    pub fn new(device: &Arc<Device>) -> Arc<StdDescriptorPool> {
+        Arc::new(StdDescriptorPool {
+            device: device.clone(),
+            pools: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Allocates a new descriptor set with the given layout.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the pool and the layout were not created from the same `Device`.
+    ///
+    /// # Safety
+    ///
+    /// - The descriptor set is uninitialized. See `UnsafeDescriptorSet::uninitialized`.
+    ///
+    pub unsafe fn alloc(me: &Arc<StdDescriptorPool>, layout: &Arc<UnsafeDescriptorSetLayout>)
+                        -> Result<UnsafeDescriptorSet, OomError>
+    {
+        assert_eq!(&*me.device as *const Device, &**layout.device() as *const Device);
+
+        let count = layout.descriptors_count();
+        let mut pools = me.pools.lock().unwrap();
+
+        for pool in pools.iter() {
+            match alloc_from_std_pool(me, pool, layout) {
+                Ok(set) => return Ok(set),
+                Err(DescriptorSetAllocError::OomError(err)) => return Err(err),
+                Err(DescriptorSetAllocError::OutOfPoolMemory) => (),
+            }
+        }
+
+        // No existing pool has enough room, so we create a new one. The new pool has room for
+        // `SETS_PER_POOL` sets with this layout, plus some room for other layouts.
+        let capacity = *count * SETS_PER_POOL + DescriptorsCount::uniform(SETS_PER_POOL);
+        let pool = Arc::new(try!(DescriptorPool::raw_with_capacity(&me.device, SETS_PER_POOL,
+                                                                   &capacity)));
+        pools.push(pool.clone());
+        match alloc_from_std_pool(me, &pool, layout) {
+            Ok(set) => Ok(set),
+            Err(DescriptorSetAllocError::OomError(err)) => Err(err),
+            Err(DescriptorSetAllocError::OutOfPoolMemory) => {
+                unreachable!("A new pool always has room for the descriptor set")
+            },
+        }
+    }
+
+    /// Returns the number of Vulkan descriptor pools that have been created by this pool.
+    #[inline]
+    pub fn num_pools(&self) -> usize {
+        self.pools.lock().unwrap().len()
+    }
+
+    /// Returns the device this pool was created from.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use descriptor::descriptor::DescriptorDesc;
+    use descriptor::descriptor::DescriptorDescTy;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::descriptor_set::StdDescriptorPool;
+    use descriptor::descriptor_set::UnsafeDescriptorSetLayout;
+    use device::Device;
+
+    #[test]
+    fn grows_and_recycles() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let desc = DescriptorDesc {
+            binding: 0,
+            ty: DescriptorDescTy::Sampler,
+            array_count: 1,
+            stages: ShaderStages::all(),
+            readonly: true,
+        };
+
+        let layout = UnsafeDescriptorSetLayout::new(device.clone(), Some(desc));
+        let pool = StdDescriptorPool::new(&device);
+        assert_eq!(pool.num_pools(), 0);
+
+        let sets = (0 .. 100).map(|_| unsafe { StdDescriptorPool::alloc(&pool, &layout).unwrap() })
+                             .collect::<Vec<_>>();
+        assert_eq!(pool.num_pools(), 2);
+
+        drop(sets);
+        let _set = unsafe { StdDescriptorPool::alloc(&pool, &layout).unwrap() };
+        assert_eq!(pool.num_pools(), 2);
+    }
+
+    #[test]
+    fn sets_keep_standard_pool_alive() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let desc = DescriptorDesc {
+            binding: 0,
+            ty: DescriptorDescTy::Sampler,
+            array_count: 1,
+            stages: ShaderStages::all(),
+            readonly: true,
+        };
+
+        let layout = UnsafeDescriptorSetLayout::new(device.clone(), Some(desc));
+        let pool = Device::standard_descriptor_pool(&device);
+        let pool_ptr = &*pool as *const StdDescriptorPool;
+        let _set = unsafe { StdDescriptorPool::alloc(&pool, &layout).unwrap() };
+        drop(pool);
+
+        let pool = Device::standard_descriptor_pool(&device);
+        assert_eq!(&*pool as *const StdDescriptorPool, pool_ptr);
+        assert_eq!(pool.num_pools(), 1);
+    }
+}
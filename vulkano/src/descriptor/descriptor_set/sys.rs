@@ -7,15 +7,21 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
 use std::mem;
 use std::ops::Range;
 use std::ptr;
 use std::sync::Arc;
+use smallvec::Array;
 use smallvec::SmallVec;
 
 use check_errors;
+use Error;
 use OomError;
 use SynchronizedVulkanObject;
+use VulkanError;
 use VulkanObject;
 use VulkanPointers;
 use vk;
@@ -26,8 +32,11 @@ use buffer::BufferView;
 use descriptor::descriptor::DescriptorType;
 use descriptor::descriptor_set::UnsafeDescriptorSetLayout;
 use descriptor::descriptor_set::DescriptorPool;
+use descriptor::descriptor_set::StdDescriptorPool;
 use device::Device;
+use image::Dimensions;
 use image::Image;
+use image::ImageAspect;
 use image::ImageView;
 use image::Layout as ImageLayout;
 use sampler::Sampler;
@@ -39,12 +48,25 @@ use sampler::Sampler;
 pub struct UnsafeDescriptorSet {
     set: vk::DescriptorSet,
     pool: Arc<DescriptorPool>,
+    // Value of `pool.num_resets()` when the set was allocated.
+    pool_resets: usize,
+    // Standard pool that `pool` belongs to, if any. Keeping it alive keeps it cached by the
+    // device, so that the following sets are allocated from the same Vulkan pools.
+    std_pool: Option<Arc<StdDescriptorPool>>,
     layout: Arc<UnsafeDescriptorSetLayout>,
 
-    // Here we store the resources used by the descriptor set.
-    // TODO: for the moment even when a resource is overwritten it stays in these lists
+    // Resources used by each descriptor of the set, indexed by binding and array element. The
+    // resources of a descriptor are replaced when it is overwritten.
+    resources: BTreeMap<(u32, u32), DescriptorResources>,
+
+    // Here we store the resources used by the descriptor set. These lists are rebuilt from
+    // `resources` each time the set is modified.
     resources_samplers: Vec<Arc<Sampler>>,
     resources_images: Vec<(Arc<Image>, (u32, u32), ImageLayout)>,
+    // Input attachments are stored separately, as their layout is handled by the render pass.
+    resources_input_attachments: Vec<(Arc<Image>, ImageLayout)>,
+    // Storage images are stored separately, as they can be written by shaders.
+    resources_storage_images: Vec<(Arc<Image>, (u32, u32), ImageLayout)>,
     resources_image_views: Vec<Arc<ImageView>>,
     resources_buffers: Vec<Arc<Buffer>>,
     resources_buffer_views: Vec<Arc<BufView>>,
@@ -52,25 +74,37 @@ pub struct UnsafeDescriptorSet {
 
 // Local trait implemented on all buffer views so that we avoid template parameters
 // TODO: crappy ; remove eventually
-trait BufView: 'static + Send + Sync { fn obj(&self) -> vk::BufferView; }
-impl<F, B> BufView for BufferView<F, B> where F: 'static + Send + Sync, B: Buffer {
+trait BufView: 'static + Send + Sync {
+    fn obj(&self) -> vk::BufferView;
+    fn buffer(&self) -> Arc<Buffer>;
+}
+impl<F, B> BufView for BufferView<F, B> where F: 'static + Send + Sync, B: Buffer + 'static {
     fn obj(&self) -> vk::BufferView { self.internal_object() }
+    fn buffer(&self) -> Arc<Buffer> { BufferView::buffer(self).clone() as Arc<_> }
 }
 
 impl UnsafeDescriptorSet {
     /// See the docs of uninitialized().
-    // FIXME: this has to check whether there's still enough room in the pool
+    ///
+    /// Returns `OutOfPoolMemory` instead of panicking if there isn't enough room left in the
+    /// pool.
     pub unsafe fn uninitialized_raw(pool: &Arc<DescriptorPool>,
                                     layout: &Arc<UnsafeDescriptorSetLayout>)
-                                    -> Result<UnsafeDescriptorSet, OomError>
+                                    -> Result<UnsafeDescriptorSet, DescriptorSetAllocError>
     {
         assert_eq!(&**pool.device() as *const Device, &**layout.device() as *const Device);
 
         let vk = pool.device().pointers();
 
-        let set = {
+        // The room is reserved and the number of resets is read while holding the lock of the
+        // pool, so that a concurrent `reset` can't happen in the middle.
+        let (set, pool_resets) = {
             let pool_obj = pool.internal_object_guard();
 
+            if !pool.reserve(layout.descriptors_count()) {
+                return Err(DescriptorSetAllocError::OutOfPoolMemory);
+            }
+
             let infos = vk::DescriptorSetAllocateInfo {
                 sType: vk::STRUCTURE_TYPE_DESCRIPTOR_SET_ALLOCATE_INFO,
                 pNext: ptr::null(),
@@ -80,18 +114,27 @@ impl UnsafeDescriptorSet {
             };
 
             let mut output = mem::uninitialized();
-            try!(check_errors(vk.AllocateDescriptorSets(pool.device().internal_object(), &infos,
-                                                        &mut output)));
-            output
+            let result = check_errors(vk.AllocateDescriptorSets(pool.device().internal_object(),
+                                                                &infos, &mut output));
+            if let Err(err) = result {
+                pool.release(layout.descriptors_count());
+                return Err(err.into());
+            }
+            (output, pool.num_resets())
         };
 
         Ok(UnsafeDescriptorSet {
             set: set,
             pool: pool.clone(),
+            pool_resets: pool_resets,
+            std_pool: None,
             layout: layout.clone(),
 
+            resources: BTreeMap::new(),
             resources_samplers: Vec::new(),
             resources_images: Vec::new(),
+            resources_input_attachments: Vec::new(),
+            resources_storage_images: Vec::new(),
             resources_image_views: Vec::new(),
             resources_buffers: Vec::new(),
             resources_buffer_views: Vec::new(),
@@ -103,9 +146,9 @@ impl UnsafeDescriptorSet {
     /// # Panic
     ///
     /// - Panics if the pool and the layout were not created from the same `Device`.
+    /// - Panics if there isn't enough room left in the pool. See `DescriptorPool::has_room_for`.
     /// - Panics if the device or host ran out of memory.
     ///
-    // FIXME: this has to check whether there's still enough room in the pool
     #[inline]
     pub unsafe fn uninitialized(pool: &Arc<DescriptorPool>,
                                 layout: &Arc<UnsafeDescriptorSetLayout>)
@@ -116,22 +159,43 @@ impl UnsafeDescriptorSet {
 
     /// Modifies a descriptor set without checking that the writes are correct.
     ///
+    /// # Panic
+    ///
+    /// - Panics if the offset of a uniform or storage buffer is not a multiple of the
+    ///   `min_uniform_buffer_offset_alignment` or `min_storage_buffer_offset_alignment` limit.
+    /// - Panics if an image view contains both the depth and the stencil aspects of an image.
+    /// - Panics if a sampler that has a YCbCr conversion is written to a binding that doesn't
+    ///   have immutable samplers, or if a sampler written to a binding that has immutable
+    ///   samplers isn't the immutable sampler of the descriptor.
+    ///
     /// # Safety
     ///
     /// - Doesn't verify that the things you write in the descriptor set match its layout.
     ///
     pub unsafe fn write(&mut self, write: Vec<DescriptorWrite>) {
-        let vk = self.pool.device().pointers();
+        let prepared = self.prepare_writes(write);
+        self.update_resources(&prepared.writes);
 
-        // TODO: how do we remove the existing resources that are overwritten?
+        let mut vk_writes = SmallVec::<[_; 64]>::new();
+        prepared.push_vk_writes(&mut vk_writes);
+
+        if !vk_writes.is_empty() {
+            let vk = self.pool.device().pointers();
+            vk.UpdateDescriptorSets(self.pool.device().internal_object(),
+                                    vk_writes.len() as u32, vk_writes.as_ptr(), 0, ptr::null());
+        }
+    }
+
+    // Checks the writes and builds the descriptor infos that the `vk::WriteDescriptorSet`s point
+    // to. The lists of resources of the set are updated separately by `update_resources`.
+    unsafe fn prepare_writes(&self, write: Vec<DescriptorWrite>) -> PreparedWrites {
+        for write in write.iter() {
+            self.check_sampler(write);
+        }
+
+        let physical_device = self.pool.device().physical_device();
+        let limits = physical_device.limits();
 
-        // This function uses multiple closures which all borrow `self`. In order to satisfy the
-        // borrow checker, we extract references to the members here.
-        let ref mut self_resources_buffers = self.resources_buffers;
-        let ref mut self_resources_samplers = self.resources_samplers;
-        let ref mut self_resources_images = self.resources_images;
-        let ref mut self_resources_image_views = self.resources_image_views;
-        let ref mut self_resources_buffer_views = self.resources_buffer_views;
         let self_set = self.set;
 
         let buffer_descriptors = write.iter().filter_map(|write| {
@@ -139,7 +203,7 @@ impl UnsafeDescriptorSet {
                 DescriptorWriteInner::UniformBuffer { ref buffer, offset, size } |
                 DescriptorWriteInner::DynamicUniformBuffer { ref buffer, offset, size } => {
                     assert!(buffer.inner().usage_uniform_buffer());
-                    self_resources_buffers.push(buffer.clone());
+                    assert!(offset as u64 % limits.min_uniform_buffer_offset_alignment() == 0);
                     Some(vk::DescriptorBufferInfo {
                         buffer: buffer.inner().internal_object(),
                         offset: offset as u64,
@@ -149,7 +213,7 @@ impl UnsafeDescriptorSet {
                 DescriptorWriteInner::StorageBuffer { ref buffer, offset, size } |
                 DescriptorWriteInner::DynamicStorageBuffer { ref buffer, offset, size } => {
                     assert!(buffer.inner().usage_storage_buffer());
-                    self_resources_buffers.push(buffer.clone());
+                    assert!(offset as u64 % limits.min_storage_buffer_offset_alignment() == 0);
                     Some(vk::DescriptorBufferInfo {
                         buffer: buffer.inner().internal_object(),
                         offset: offset as u64,
@@ -158,12 +222,11 @@ impl UnsafeDescriptorSet {
                 },
                 _ => None
             }
-        }).collect::<SmallVec<[_; 64]>>();
+        }).collect::<SmallVec<[_; 16]>>();
 
         let image_descriptors = write.iter().filter_map(|write| {
             match write.inner {
                 DescriptorWriteInner::Sampler(ref sampler) => {
-                    self_resources_samplers.push(sampler.clone());
                     Some(vk::DescriptorImageInfo {
                         sampler: sampler.internal_object(),
                         imageView: 0,
@@ -172,12 +235,8 @@ impl UnsafeDescriptorSet {
                 },
                 DescriptorWriteInner::CombinedImageSampler(ref sampler, ref view, ref image, ref blocks) => {
                     assert!(view.inner().usage_sampled());
+                    assert!(view.inner().aspect() != ImageAspect::DepthStencil);
                     let layout = view.descriptor_set_combined_image_sampler_layout();
-                    self_resources_samplers.push(sampler.clone());
-                    self_resources_image_views.push(view.clone());
-                    for &block in blocks.iter() {
-                        self_resources_images.push((image.clone(), block, layout));       // TODO: check for collisions
-                    }
                     Some(vk::DescriptorImageInfo {
                         sampler: sampler.internal_object(),
                         imageView: view.inner().internal_object(),
@@ -186,12 +245,11 @@ impl UnsafeDescriptorSet {
                 },
                 DescriptorWriteInner::StorageImage(ref view, ref image, ref blocks) => {
                     assert!(view.inner().usage_storage());
+                    assert!(image.inner().supports_storage());
                     assert!(view.identity_swizzle());
+                    assert!(view.inner().aspect() != ImageAspect::DepthStencil);
                     let layout = view.descriptor_set_storage_image_layout();
-                    self_resources_image_views.push(view.clone());
-                    for &block in blocks.iter() {
-                        self_resources_images.push((image.clone(), block, layout));       // TODO: check for collisions
-                    }
+                    assert_eq!(layout, ImageLayout::General);
                     Some(vk::DescriptorImageInfo {
                         sampler: 0,
                         imageView: view.inner().internal_object(),
@@ -200,25 +258,19 @@ impl UnsafeDescriptorSet {
                 },
                 DescriptorWriteInner::SampledImage(ref view, ref image, ref blocks) => {
                     assert!(view.inner().usage_sampled());
+                    assert!(view.inner().aspect() != ImageAspect::DepthStencil);
                     let layout = view.descriptor_set_sampled_image_layout();
-                    self_resources_image_views.push(view.clone());
-                    for &block in blocks.iter() {
-                        self_resources_images.push((image.clone(), block, layout));       // TODO: check for collisions
-                    }
                     Some(vk::DescriptorImageInfo {
                         sampler: 0,
                         imageView: view.inner().internal_object(),
                         imageLayout: layout as u32,
                     })
                 },
-                DescriptorWriteInner::InputAttachment(ref view, ref image, ref blocks) => {
+                DescriptorWriteInner::InputAttachment(ref view, ref image) => {
                     assert!(view.inner().usage_input_attachment());
                     assert!(view.identity_swizzle());
+                    assert!(view.inner().aspect() != ImageAspect::DepthStencil);
                     let layout = view.descriptor_set_input_attachment_layout();
-                    self_resources_image_views.push(view.clone());
-                    for &block in blocks.iter() {
-                        self_resources_images.push((image.clone(), block, layout));       // TODO: check for collisions
-                    }
                     Some(vk::DescriptorImageInfo {
                         sampler: 0,
                         imageView: view.inner().internal_object(),
@@ -227,43 +279,280 @@ impl UnsafeDescriptorSet {
                 },
                 _ => None
             }
-        }).collect::<SmallVec<[_; 64]>>();
+        }).collect::<SmallVec<[_; 16]>>();
 
         let buffer_views_descriptors = write.iter().filter_map(|write| {
             match write.inner {
                 DescriptorWriteInner::UniformTexelBuffer(ref view) |
                 DescriptorWriteInner::StorageTexelBuffer(ref view) => {
-                    self_resources_buffer_views.push(view.clone());
                     Some(view.obj())
                 },
                 _ => None
             }
         }).collect::<SmallVec<[_; 16]>>();
 
+        PreparedWrites {
+            set: self_set,
+            writes: write,
+            buffer_descriptors: buffer_descriptors,
+            image_descriptors: image_descriptors,
+            buffer_views_descriptors: buffer_views_descriptors,
+        }
+    }
+
+    // Adds the resources used by the writes to the lists of resources of the set. The resources
+    // of the descriptors that are overwritten are released.
+    fn update_resources(&mut self, writes: &[DescriptorWrite]) {
+        for write in writes.iter() {
+            self.resources.insert((write.binding, write.first_array_element), write.resources());
+        }
+        self.rebuild_resources_lists();
+    }
 
+    // Checks that the sampler of a write, if any, matches the immutable samplers of the layout.
+    // Samplers that have a YCbCr conversion can only be used as immutable samplers.
+    fn check_sampler(&self, write: &DescriptorWrite) {
+        let immutable = self.layout.immutable_samplers(write.binding);
+
+        let sampler = match write.inner {
+            DescriptorWriteInner::Sampler(ref sampler) => {
+                assert!(immutable.is_none(),
+                        "Can't write a sampler to a binding that has immutable samplers");
+                sampler
+            },
+            DescriptorWriteInner::CombinedImageSampler(ref sampler, _, _, _) => sampler,
+            _ => return
+        };
+
+        match immutable {
+            Some(samplers) => {
+                let expected = &samplers[write.first_array_element as usize];
+                assert!(&**expected as *const Sampler == &**sampler as *const Sampler,
+                        "The sampler isn't the immutable sampler of the descriptor");
+            },
+            None => {
+                assert!(sampler.ycbcr_conversion().is_none(),
+                        "Samplers that have a YCbCr conversion can only be immutable samplers");
+            },
+        }
+    }
+
+    /// Copies descriptors from another descriptor set into this one.
+    ///
+    /// The resources of the copied descriptors replace the resources of the descriptors that
+    /// are overwritten. Each copy must stay within its source and destination bindings.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the two descriptor sets were not created from the same `Device`.
+    ///
+    /// # Safety
+    ///
+    /// - Doesn't verify that the copies match the layouts of the two sets.
+    /// - Neither descriptor set must be in use by the GPU.
+    ///
+    pub unsafe fn copy(&mut self, source: &UnsafeDescriptorSet, copies: &[DescriptorCopy]) {
+        assert_eq!(&**self.pool.device() as *const Device,
+                   &**source.pool.device() as *const Device);
+
+        for copy in copies {
+            for offset in 0 .. copy.count {
+                let src = (copy.source_binding, copy.source_first_array_element + offset);
+                let dest = (copy.destination_binding,
+                            copy.destination_first_array_element + offset);
+                match source.resources.get(&src) {
+                    Some(resources) => { self.resources.insert(dest, resources.clone()); },
+                    None => { self.resources.remove(&dest); },
+                }
+            }
+        }
+        self.rebuild_resources_lists();
+
+        let vk = self.pool.device().pointers();
+
+        let vk_copies = copies.iter().map(|copy| {
+            vk::CopyDescriptorSet {
+                sType: vk::STRUCTURE_TYPE_COPY_DESCRIPTOR_SET,
+                pNext: ptr::null(),
+                srcSet: source.set,
+                srcBinding: copy.source_binding,
+                srcArrayElement: copy.source_first_array_element,
+                dstSet: self.set,
+                dstBinding: copy.destination_binding,
+                dstArrayElement: copy.destination_first_array_element,
+                descriptorCount: copy.count,
+            }
+        }).collect::<SmallVec<[_; 16]>>();
+
+        if !vk_copies.is_empty() {
+            vk.UpdateDescriptorSets(self.pool.device().internal_object(), 0, ptr::null(),
+                                    vk_copies.len() as u32, vk_copies.as_ptr());
+        }
+    }
+
+    /// Returns the layout used to create this descriptor set.
+    #[inline]
+    pub fn layout(&self) -> &Arc<UnsafeDescriptorSetLayout> {
+        &self.layout
+    }
+
+    // Rebuilds the lists of resources of the set from the resources of each descriptor.
+    fn rebuild_resources_lists(&mut self) {
+        self.resources_samplers.clear();
+        self.resources_images.clear();
+        self.resources_input_attachments.clear();
+        self.resources_storage_images.clear();
+        self.resources_image_views.clear();
+        self.resources_buffers.clear();
+        self.resources_buffer_views.clear();
+
+        for resources in self.resources.values() {
+            self.resources_samplers.extend(resources.samplers.iter().cloned());
+            self.resources_images.extend(resources.images.iter().cloned());
+            self.resources_input_attachments.extend(resources.input_attachments.iter().cloned());
+            self.resources_storage_images.extend(resources.storage_images.iter().cloned());
+            self.resources_image_views.extend(resources.image_views.iter().cloned());
+            self.resources_buffers.extend(resources.buffers.iter().cloned());
+            self.resources_buffer_views.extend(resources.buffer_views.iter().cloned());
+        }
+    }
+
+    // TODO: hacky
+    #[doc(hidden)]
+    #[inline]
+    pub fn images_list(&self) -> &[(Arc<Image>, (u32, u32), ImageLayout)] {
+        &self.resources_images
+    }
+
+    // TODO: hacky
+    #[doc(hidden)]
+    #[inline]
+    pub fn storage_images_list(&self) -> &[(Arc<Image>, (u32, u32), ImageLayout)] {
+        &self.resources_storage_images
+    }
+
+    // TODO: hacky
+    #[doc(hidden)]
+    #[inline]
+    pub fn input_attachments_list(&self) -> &[(Arc<Image>, ImageLayout)] {
+        &self.resources_input_attachments
+    }
+
+    // TODO: hacky
+    #[doc(hidden)]
+    #[inline]
+    pub fn buffers_list(&self) -> &[Arc<Buffer>] {
+        &self.resources_buffers
+    }
+
+    /// Returns the size of the buffer and the offset and size of the range of each dynamic
+    /// buffer descriptor of the set, in the same order as `dynamic_descriptors()` of the layout.
+    ///
+    /// Returns `None` for the descriptors that haven't been written.
+    #[doc(hidden)]
+    pub fn dynamic_buffer_ranges(&self) -> SmallVec<[Option<(usize, usize, usize)>; 8]> {
+        let mut sorted = self.layout.descriptors().iter().collect::<SmallVec<[_; 32]>>();
+        sorted.sort_by_key(|desc| desc.binding);
+
+        let mut list = SmallVec::new();
+        for desc in sorted {
+            match desc.ty.ty() {
+                Some(DescriptorType::UniformBufferDynamic) |
+                Some(DescriptorType::StorageBufferDynamic) => {
+                    for elem in 0 .. desc.array_count {
+                        list.push(self.resources.get(&(desc.binding, elem))
+                                                .and_then(|r| r.dynamic_buffer));
+                    }
+                },
+                _ => ()
+            }
+        }
+        list
+    }
+}
+
+unsafe impl VulkanObject for UnsafeDescriptorSet {
+    type Object = vk::DescriptorSet;
+
+    #[inline]
+    fn internal_object(&self) -> vk::DescriptorSet {
+        self.set
+    }
+}
+
+impl Drop for UnsafeDescriptorSet {
+    #[inline]
+    fn drop(&mut self) {
+        // `reset` holds the lock of the pool while it resets it, so the number of resets can't
+        // change while we hold it.
+        let pool = self.pool.internal_object_guard();
+
+        // If the pool has been reset in the meantime, the set has already been freed.
+        if self.pool.num_resets() != self.pool_resets {
+            return;
+        }
+
+        unsafe {
+            let vk = self.pool.device().pointers();
+            vk.FreeDescriptorSets(self.pool.device().internal_object(), *pool, 1, &self.set);
+            self.pool.release(self.layout.descriptors_count());
+        }
+    }
+}
+
+// Resources used by a single descriptor of a set.
+#[derive(Clone, Default)]
+struct DescriptorResources {
+    samplers: Vec<Arc<Sampler>>,
+    images: Vec<(Arc<Image>, (u32, u32), ImageLayout)>,
+    input_attachments: Vec<(Arc<Image>, ImageLayout)>,
+    storage_images: Vec<(Arc<Image>, (u32, u32), ImageLayout)>,
+    image_views: Vec<Arc<ImageView>>,
+    buffers: Vec<Arc<Buffer>>,
+    buffer_views: Vec<Arc<BufView>>,
+    // For dynamic buffer descriptors, the size of the buffer and the offset and size of the
+    // range that the descriptor covers.
+    dynamic_buffer: Option<(usize, usize, usize)>,
+}
+
+// Descriptor infos of the writes to a descriptor set, built by `prepare_writes`.
+struct PreparedWrites {
+    set: vk::DescriptorSet,
+    writes: Vec<DescriptorWrite>,
+    buffer_descriptors: SmallVec<[vk::DescriptorBufferInfo; 16]>,
+    image_descriptors: SmallVec<[vk::DescriptorImageInfo; 16]>,
+    buffer_views_descriptors: SmallVec<[vk::BufferView; 16]>,
+}
+
+impl PreparedWrites {
+    // Appends to `out` one `vk::WriteDescriptorSet` per write. They point to the descriptor
+    // infos of `self`, which therefore must not be moved or dropped before they are used.
+    unsafe fn push_vk_writes<A>(&self, out: &mut SmallVec<A>)
+        where A: Array<Item = vk::WriteDescriptorSet>
+    {
         let mut next_buffer_desc = 0;
         let mut next_image_desc = 0;
         let mut next_buffer_view_desc = 0;
 
-        let vk_writes = write.iter().map(|write| {
+        out.extend(self.writes.iter().map(|write| {
             let (buffer_info, image_info, buffer_view_info) = match write.inner {
                 DescriptorWriteInner::Sampler(_) | DescriptorWriteInner::CombinedImageSampler(_, _, _, _) |
                 DescriptorWriteInner::SampledImage(_, _, _) | DescriptorWriteInner::StorageImage(_, _, _) |
-                DescriptorWriteInner::InputAttachment(_, _, _) => {
-                    let img = image_descriptors.as_ptr().offset(next_image_desc as isize);
+                DescriptorWriteInner::InputAttachment(_, _) => {
+                    let img = self.image_descriptors.as_ptr().offset(next_image_desc as isize);
                     next_image_desc += 1;
                     (ptr::null(), img, ptr::null())
                 },
                 DescriptorWriteInner::UniformBuffer { .. } | DescriptorWriteInner::StorageBuffer { .. } |
                 DescriptorWriteInner::DynamicUniformBuffer { .. } |
                 DescriptorWriteInner::DynamicStorageBuffer { .. } => {
-                    let buf = buffer_descriptors.as_ptr().offset(next_buffer_desc as isize);
+                    let buf = self.buffer_descriptors.as_ptr().offset(next_buffer_desc as isize);
                     next_buffer_desc += 1;
                     (buf, ptr::null(), ptr::null())
                 },
                 DescriptorWriteInner::UniformTexelBuffer(_) |
                 DescriptorWriteInner::StorageTexelBuffer(_) => {
-                    let buf = buffer_views_descriptors.as_ptr().offset(next_buffer_view_desc as isize);
+                    let buf = self.buffer_views_descriptors.as_ptr().offset(next_buffer_view_desc as isize);
                     next_buffer_view_desc += 1;
                     (ptr::null(), ptr::null(), buf)
                 },
@@ -273,7 +562,7 @@ impl UnsafeDescriptorSet {
             vk::WriteDescriptorSet {
                 sType: vk::STRUCTURE_TYPE_WRITE_DESCRIPTOR_SET,
                 pNext: ptr::null(),
-                dstSet: self_set,
+                dstSet: self.set,
                 dstBinding: write.binding,
                 dstArrayElement: write.first_array_element,
                 descriptorCount: 1,
@@ -282,60 +571,112 @@ impl UnsafeDescriptorSet {
                 pBufferInfo: buffer_info,
                 pTexelBufferView: buffer_view_info,
             }
-        }).collect::<SmallVec<[_; 64]>>();
+        }));
 
-        debug_assert_eq!(next_buffer_desc, buffer_descriptors.len());
-        debug_assert_eq!(next_image_desc, image_descriptors.len());
-        debug_assert_eq!(next_buffer_view_desc, buffer_views_descriptors.len());
-
-        if !vk_writes.is_empty() {
-            vk.UpdateDescriptorSets(self.pool.device().internal_object(),
-                                    vk_writes.len() as u32, vk_writes.as_ptr(), 0, ptr::null());
-        }
+        debug_assert_eq!(next_buffer_desc, self.buffer_descriptors.len());
+        debug_assert_eq!(next_image_desc, self.image_descriptors.len());
+        debug_assert_eq!(next_buffer_view_desc, self.buffer_views_descriptors.len());
     }
+}
 
-    /// Returns the layout used to create this descriptor set.
+/// Collects writes to multiple descriptor sets, so that they are all applied with a single call
+/// to `vkUpdateDescriptorSets`.
+///
+/// This is faster than calling `UnsafeDescriptorSet::write` for each set when many sets have to
+/// be updated at once. The writes are checked when they are added, but they are only applied,
+/// and the resources of the sets are only updated, when `flush` is called. If the batch is
+/// dropped without being flushed, none of its writes are applied.
+pub struct DescriptorWriteBatch<'a> {
+    device: Option<Arc<Device>>,
+    sets: Vec<(&'a mut UnsafeDescriptorSet, PreparedWrites)>,
+}
+
+impl<'a> DescriptorWriteBatch<'a> {
+    /// Builds a new empty batch.
     #[inline]
-    pub fn layout(&self) -> &Arc<UnsafeDescriptorSetLayout> {
-        &self.layout
+    pub fn new() -> DescriptorWriteBatch<'a> {
+        DescriptorWriteBatch {
+            device: None,
+            sets: Vec::new(),
+        }
     }
 
+    /// Adds writes to a descriptor set to the batch.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the set doesn't belong to the same device as the other sets of the batch.
+    /// - Same panic reasons as `UnsafeDescriptorSet::write`.
+    ///
+    /// # Safety
+    ///
+    /// - Doesn't verify that the things you write in the descriptor set match its layout.
+    ///
+    pub unsafe fn add(&mut self, set: &'a mut UnsafeDescriptorSet, writes: Vec<DescriptorWrite>) {
+        match self.device {
+            Some(ref device) => {
+                assert_eq!(&**device as *const Device, &**set.pool.device() as *const Device);
+            },
+            None => self.device = Some(set.pool.device().clone()),
+        }
 
-    // TODO: hacky
-    #[doc(hidden)]
-    #[inline]
-    pub fn images_list(&self) -> &[(Arc<Image>, (u32, u32), ImageLayout)] {
-        &self.resources_images
+        let prepared = set.prepare_writes(writes);
+        self.sets.push((set, prepared));
     }
 
-    // TODO: hacky
-    #[doc(hidden)]
+    /// Returns the number of writes in the batch.
     #[inline]
-    pub fn buffers_list(&self) -> &[Arc<Buffer>] {
-        &self.resources_buffers
+    pub fn len(&self) -> usize {
+        self.sets.iter().map(|&(_, ref prepared)| prepared.writes.len()).sum()
     }
-}
-
-unsafe impl VulkanObject for UnsafeDescriptorSet {
-    type Object = vk::DescriptorSet;
 
+    /// Returns true if the batch doesn't contain any write.
     #[inline]
-    fn internal_object(&self) -> vk::DescriptorSet {
-        self.set
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
-}
 
-impl Drop for UnsafeDescriptorSet {
-    #[inline]
-    fn drop(&mut self) {
+    /// Applies all the writes of the batch.
+    pub fn flush(self) {
+        let device = match self.device {
+            Some(device) => device,
+            None => return,
+        };
+
         unsafe {
-            let vk = self.pool.device().pointers();
-            vk.FreeDescriptorSets(self.pool.device().internal_object(),
-                                  *self.pool.internal_object_guard(), 1, &self.set);
+            let mut vk_writes = SmallVec::<[_; 64]>::new();
+            for &(_, ref prepared) in self.sets.iter() {
+                prepared.push_vk_writes(&mut vk_writes);
+            }
+
+            if !vk_writes.is_empty() {
+                let vk = device.pointers();
+                vk.UpdateDescriptorSets(device.internal_object(), vk_writes.len() as u32,
+                                        vk_writes.as_ptr(), 0, ptr::null());
+            }
+        }
+
+        for (set, prepared) in self.sets.into_iter() {
+            set.update_resources(&prepared.writes);
         }
     }
 }
 
+/// Represents a copy of descriptors from one descriptor set to another.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DescriptorCopy {
+    /// Binding to copy from in the source set.
+    pub source_binding: u32,
+    /// First array element to copy from in the source binding.
+    pub source_first_array_element: u32,
+    /// Binding to copy to in the destination set.
+    pub destination_binding: u32,
+    /// First array element to copy to in the destination binding.
+    pub destination_first_array_element: u32,
+    /// Number of descriptors to copy.
+    pub count: u32,
+}
+
 /// Represents a single write entry to a descriptor set.
 pub struct DescriptorWrite {
     binding: u32,
@@ -356,10 +697,67 @@ enum DescriptorWriteInner {
     StorageBuffer { buffer: Arc<Buffer>, offset: usize, size: usize },
     DynamicUniformBuffer { buffer: Arc<Buffer>, offset: usize, size: usize },
     DynamicStorageBuffer { buffer: Arc<Buffer>, offset: usize, size: usize },
-    InputAttachment(Arc<ImageView>, Arc<Image>, Vec<(u32, u32)>),
+    InputAttachment(Arc<ImageView>, Arc<Image>),
 }
 
 impl DescriptorWrite {
+    // Returns the resources used by the descriptor that this write modifies.
+    fn resources(&self) -> DescriptorResources {
+        let mut resources = DescriptorResources::default();
+
+        match self.inner {
+            DescriptorWriteInner::Sampler(ref sampler) => {
+                resources.samplers.push(sampler.clone());
+            },
+            DescriptorWriteInner::CombinedImageSampler(ref sampler, ref view, ref image,
+                                                       ref blocks) => {
+                let layout = view.descriptor_set_combined_image_sampler_layout();
+                resources.samplers.push(sampler.clone());
+                resources.image_views.push(view.clone());
+                for &block in blocks.iter() {
+                    resources.images.push((image.clone(), block, layout));
+                }
+            },
+            DescriptorWriteInner::SampledImage(ref view, ref image, ref blocks) => {
+                let layout = view.descriptor_set_sampled_image_layout();
+                resources.image_views.push(view.clone());
+                for &block in blocks.iter() {
+                    resources.images.push((image.clone(), block, layout));
+                }
+            },
+            DescriptorWriteInner::StorageImage(ref view, ref image, ref blocks) => {
+                let layout = view.descriptor_set_storage_image_layout();
+                resources.image_views.push(view.clone());
+                for &block in blocks.iter() {
+                    resources.storage_images.push((image.clone(), block, layout));
+                }
+            },
+            DescriptorWriteInner::InputAttachment(ref view, ref image) => {
+                let layout = view.descriptor_set_input_attachment_layout();
+                resources.image_views.push(view.clone());
+                resources.input_attachments.push((image.clone(), layout));
+            },
+            DescriptorWriteInner::UniformTexelBuffer(ref view) |
+            DescriptorWriteInner::StorageTexelBuffer(ref view) => {
+                // The underlying buffer is tracked as well, so that the accesses made through
+                // the view are synchronized like any other buffer access.
+                resources.buffers.push(view.buffer());
+                resources.buffer_views.push(view.clone());
+            },
+            DescriptorWriteInner::UniformBuffer { ref buffer, .. } |
+            DescriptorWriteInner::StorageBuffer { ref buffer, .. } => {
+                resources.buffers.push(buffer.clone());
+            },
+            DescriptorWriteInner::DynamicUniformBuffer { ref buffer, offset, size } |
+            DescriptorWriteInner::DynamicStorageBuffer { ref buffer, offset, size } => {
+                resources.buffers.push(buffer.clone());
+                resources.dynamic_buffer = Some((buffer.size(), offset, size));
+            },
+        }
+
+        resources
+    }
+
     #[inline]
     pub fn storage_image<I>(binding: u32, image: &Arc<I>) -> DescriptorWrite
         where I: ImageView + 'static
@@ -391,10 +789,21 @@ impl DescriptorWrite {
         }
     }
 
+    /// Writes a sampler and an image view to a combined image sampler descriptor.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the sampler can't be used with the image view, for example if it has an
+    ///   integer border color and the view has a floating-point format.
+    ///
     #[inline]
     pub fn combined_image_sampler<I>(binding: u32, sampler: &Arc<Sampler>, image: &Arc<I>) -> DescriptorWrite
         where I: ImageView + 'static
     {
+        if let Err(err) = sampler.check_image_view(&**image) {
+            panic!("The sampler can't be used with the image view: {}", err);
+        }
+
         DescriptorWrite {
             binding: binding,
             first_array_element: 0,
@@ -569,7 +978,54 @@ impl DescriptorWrite {
         DescriptorWrite {
             binding: binding,
             first_array_element: 0,
-            inner: DescriptorWriteInner::InputAttachment(image.clone(), ImageView::parent_arc(image), image.blocks())
+            inner: DescriptorWriteInner::InputAttachment(image.clone(), ImageView::parent_arc(image))
+        }
+    }
+
+    /// Returns the binding number that this write modifies.
+    #[inline]
+    pub fn binding(&self) -> u32 {
+        self.binding
+    }
+
+    /// Returns the element of the array of descriptors that this write modifies.
+    #[inline]
+    pub fn first_array_element(&self) -> u32 {
+        self.first_array_element
+    }
+
+    /// Changes the element of the array of descriptors that is written. The default is `0`.
+    #[inline]
+    pub fn array_element(mut self, element: u32) -> DescriptorWrite {
+        self.first_array_element = element;
+        self
+    }
+
+    /// Returns the dimensions of the image view that is written, or `None` if this write
+    /// doesn't write an image view.
+    #[inline]
+    pub fn image_view_dimensions(&self) -> Option<Dimensions> {
+        match self.inner {
+            DescriptorWriteInner::StorageImage(ref view, _, _) => Some(view.dimensions()),
+            DescriptorWriteInner::SampledImage(ref view, _, _) => Some(view.dimensions()),
+            DescriptorWriteInner::CombinedImageSampler(_, ref view, _, _) => Some(view.dimensions()),
+            DescriptorWriteInner::InputAttachment(ref view, _) => Some(view.dimensions()),
+            _ => None
+        }
+    }
+
+    /// Returns the aspect of the image view that is written, or `None` if this write doesn't
+    /// write an image view.
+    #[inline]
+    pub fn image_view_aspect(&self) -> Option<ImageAspect> {
+        match self.inner {
+            DescriptorWriteInner::StorageImage(ref view, _, _) => Some(view.inner().aspect()),
+            DescriptorWriteInner::SampledImage(ref view, _, _) => Some(view.inner().aspect()),
+            DescriptorWriteInner::CombinedImageSampler(_, ref view, _, _) => {
+                Some(view.inner().aspect())
+            },
+            DescriptorWriteInner::InputAttachment(ref view, _) => Some(view.inner().aspect()),
+            _ => None
         }
     }
 
@@ -587,7 +1043,216 @@ impl DescriptorWrite {
             DescriptorWriteInner::StorageBuffer { .. } => DescriptorType::StorageBuffer,
             DescriptorWriteInner::DynamicUniformBuffer { .. } => DescriptorType::UniformBufferDynamic,
             DescriptorWriteInner::DynamicStorageBuffer { .. } => DescriptorType::StorageBufferDynamic,
-            DescriptorWriteInner::InputAttachment(_, _, _) => DescriptorType::InputAttachment,
+            DescriptorWriteInner::InputAttachment(_, _) => DescriptorType::InputAttachment,
+        }
+    }
+}
+
+// Allocates a set from `pool`, which must be one of the pools of `std_pool`. The set keeps
+// `std_pool` alive.
+pub unsafe fn alloc_from_std_pool(std_pool: &Arc<StdDescriptorPool>, pool: &Arc<DescriptorPool>,
+                                  layout: &Arc<UnsafeDescriptorSetLayout>)
+                                  -> Result<UnsafeDescriptorSet, DescriptorSetAllocError>
+{
+    let mut set = try!(UnsafeDescriptorSet::uninitialized_raw(pool, layout));
+    set.std_pool = Some(std_pool.clone());
+    Ok(set)
+}
+
+/// Error that can happen when allocating a descriptor set from a pool.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DescriptorSetAllocError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// There isn't enough room left in the pool for the descriptor set, or the pool is too
+    /// fragmented.
+    OutOfPoolMemory,
+}
+
+impl error::Error for DescriptorSetAllocError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            DescriptorSetAllocError::OomError(_) => "not enough memory available",
+            DescriptorSetAllocError::OutOfPoolMemory => {
+                "there isn't enough room left in the pool for the descriptor set"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            DescriptorSetAllocError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for DescriptorSetAllocError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for DescriptorSetAllocError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            DescriptorSetAllocError::OomError(ref err) => err.error_code(),
+            _ => None
+        }
+    }
+}
+
+impl From<OomError> for DescriptorSetAllocError {
+    #[inline]
+    fn from(err: OomError) -> DescriptorSetAllocError {
+        DescriptorSetAllocError::OomError(err)
+    }
+}
+
+impl From<Error> for DescriptorSetAllocError {
+    #[inline]
+    fn from(err: Error) -> DescriptorSetAllocError {
+        match err {
+            Error::FragmentedPool | Error::OutOfPoolMemory => {
+                DescriptorSetAllocError::OutOfPoolMemory
+            },
+            err => DescriptorSetAllocError::OomError(OomError::from(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use descriptor::descriptor::DescriptorDesc;
+    use descriptor::descriptor::DescriptorDescTy;
+    use descriptor::descriptor::DescriptorImageDesc;
+    use descriptor::descriptor::DescriptorImageDescArray;
+    use descriptor::descriptor::DescriptorImageDescDimensions;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::descriptor_set::DescriptorCopy;
+    use descriptor::descriptor_set::DescriptorPool;
+    use descriptor::descriptor_set::DescriptorWrite;
+    use descriptor::descriptor_set::DescriptorWriteBatch;
+    use descriptor::descriptor_set::UnsafeDescriptorSet;
+    use descriptor::descriptor_set::UnsafeDescriptorSetLayout;
+    use device::Device;
+    use format::Format;
+    use image::Dimensions;
+    use image::Layout;
+    use image::StorageImage;
+
+    // Builds a layout with a single storage image at binding 0.
+    fn storage_image_layout(device: &Arc<Device>) -> Arc<UnsafeDescriptorSetLayout> {
+        let desc = DescriptorDesc {
+            binding: 0,
+            ty: DescriptorDescTy::Image(DescriptorImageDesc {
+                sampled: false,
+                dimensions: DescriptorImageDescDimensions::TwoDimensional,
+                format: None,
+                multisampled: false,
+                array_layers: DescriptorImageDescArray::NonArrayed,
+            }),
+            array_count: 1,
+            stages: ShaderStages::all(),
+            readonly: false,
+        };
+
+        UnsafeDescriptorSetLayout::new(device.clone(), Some(desc))
+    }
+
+    #[test]
+    fn storage_image_general_layout() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let desc = DescriptorDesc {
+            binding: 0,
+            ty: DescriptorDescTy::Image(DescriptorImageDesc {
+                sampled: false,
+                dimensions: DescriptorImageDescDimensions::TwoDimensional,
+                format: None,
+                multisampled: false,
+                array_layers: DescriptorImageDescArray::NonArrayed,
+            }),
+            array_count: 1,
+            stages: ShaderStages::all(),
+            readonly: false,
+        };
+
+        let layout = UnsafeDescriptorSetLayout::new(device.clone(), Some(desc));
+        let pool = DescriptorPool::new(&device);
+        let image = StorageImage::new(&device, Dimensions::Dim2d { width: 32, height: 32 },
+                                      Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        let set = unsafe {
+            let mut set = UnsafeDescriptorSet::uninitialized(&pool, &layout);
+            set.write(vec![DescriptorWrite::storage_image(0, &image)]);
+            set
+        };
+
+        assert!(set.images_list().is_empty());
+        assert_eq!(set.storage_images_list().len(), 1);
+        assert_eq!(set.storage_images_list()[0].2, Layout::General);
+    }
+
+    #[test]
+    fn write_batch() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let layout = storage_image_layout(&device);
+        let pool = DescriptorPool::new(&device);
+        let image = StorageImage::new(&device, Dimensions::Dim2d { width: 32, height: 32 },
+                                      Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        let mut set1 = unsafe { UnsafeDescriptorSet::uninitialized(&pool, &layout) };
+        let mut set2 = unsafe { UnsafeDescriptorSet::uninitialized(&pool, &layout) };
+
+        let mut batch = DescriptorWriteBatch::new();
+        assert!(batch.is_empty());
+        unsafe {
+            batch.add(&mut set1, vec![DescriptorWrite::storage_image(0, &image)]);
+            batch.add(&mut set2, vec![DescriptorWrite::storage_image(0, &image)]);
         }
+        assert_eq!(batch.len(), 2);
+        batch.flush();
+
+        assert_eq!(set1.storage_images_list().len(), 1);
+        assert_eq!(set2.storage_images_list().len(), 1);
+    }
+
+    #[test]
+    fn overwritten_resources_released() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let layout = storage_image_layout(&device);
+        let pool = DescriptorPool::new(&device);
+        let image = StorageImage::new(&device, Dimensions::Dim2d { width: 32, height: 32 },
+                                      Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        let mut set1 = unsafe { UnsafeDescriptorSet::uninitialized(&pool, &layout) };
+        let mut set2 = unsafe { UnsafeDescriptorSet::uninitialized(&pool, &layout) };
+
+        unsafe {
+            for _ in 0 .. 4 {
+                set1.write(vec![DescriptorWrite::storage_image(0, &image)]);
+            }
+            assert_eq!(set1.storage_images_list().len(), 1);
+
+            let copy = DescriptorCopy {
+                source_binding: 0,
+                source_first_array_element: 0,
+                destination_binding: 0,
+                destination_first_array_element: 0,
+                count: 1,
+            };
+            set2.copy(&set1, &[copy]);
+            set2.copy(&set1, &[copy]);
+        }
+
+        assert_eq!(set2.storage_images_list().len(), 1);
     }
 }
@@ -20,7 +20,10 @@ use SafeDeref;
 use vk;
 
 use descriptor::descriptor::DescriptorDesc;
+use descriptor::descriptor::DescriptorType;
+use descriptor::descriptor_set::DescriptorsCount;
 use device::Device;
+use sampler::Sampler;
 
 /// Describes to the Vulkan implementation the layout of all descriptors within a descriptor set.
 ///
@@ -33,21 +36,108 @@ pub struct UnsafeDescriptorSetLayout<P = Arc<Device>> where P: SafeDeref<Target
     layout: vk::DescriptorSetLayout,
     // The device this layout belongs to.
     device: P,
+    // Descriptors of the layout, as passed to the constructor.
+    descriptors: SmallVec<[DescriptorDesc; 32]>,
+    // Number of descriptors of each type required to allocate a set with this layout.
+    descriptors_count: DescriptorsCount,
+    // Type of each dynamic buffer descriptor, in the order of their dynamic offsets.
+    dynamic_descriptors: SmallVec<[DescriptorType; 8]>,
+    // Immutable samplers of each binding that has some, ordered by binding.
+    immutable_samplers: Vec<(u32, Vec<Arc<Sampler>>)>,
 }
 
 impl<P> UnsafeDescriptorSetLayout<P> where P: SafeDeref<Target = Device> {
     /// See the docs of new().
+    #[inline]
     pub fn raw<I>(device: P, descriptors: I)
                   -> Result<UnsafeDescriptorSetLayout<P>, OomError>
         where I: IntoIterator<Item = DescriptorDesc>
     {
-        let bindings = descriptors.into_iter().map(|desc| {
+        UnsafeDescriptorSetLayout::raw_with_immutable_samplers(device, descriptors, None)
+    }
+
+    /// Same as `raw`, but also sets the immutable samplers of some bindings.
+    ///
+    /// Each element of `immutable_samplers` is a binding number and the list of samplers of the
+    /// array elements of this binding. The samplers can't be changed once the layout is created,
+    /// and the samplers written in the descriptor sets that use this layout must be the same.
+    /// Samplers that have a YCbCr conversion can only be used as immutable samplers.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if a binding of `immutable_samplers` isn't a sampler or combined image sampler
+    ///   binding of `descriptors`, or if it is present multiple times.
+    /// - Panics if the number of samplers of a binding isn't its array size.
+    /// - Panics if a sampler doesn't belong to the same device as the layout.
+    ///
+    pub fn raw_with_immutable_samplers<I, J>(device: P, descriptors: I, immutable_samplers: J)
+                                             -> Result<UnsafeDescriptorSetLayout<P>, OomError>
+        where I: IntoIterator<Item = DescriptorDesc>,
+              J: IntoIterator<Item = (u32, Vec<Arc<Sampler>>)>
+    {
+        let descriptors = descriptors.into_iter().collect::<SmallVec<[_; 32]>>();
+
+        let mut immutable_samplers = immutable_samplers.into_iter().collect::<Vec<_>>();
+        immutable_samplers.sort_by_key(|&(binding, _)| binding);
+        for (num, &(binding, ref samplers)) in immutable_samplers.iter().enumerate() {
+            assert!(num == 0 || immutable_samplers[num - 1].0 != binding);
+
+            let desc = match descriptors.iter().find(|d| d.binding == binding) {
+                Some(desc) => desc,
+                None => panic!("Immutable samplers set for a binding that doesn't exist")
+            };
+            match desc.ty.ty() {
+                Some(DescriptorType::Sampler) | Some(DescriptorType::CombinedImageSampler) => (),
+                _ => panic!("Immutable samplers set for a binding that doesn't use samplers")
+            };
+            assert_eq!(samplers.len(), desc.array_count as usize);
+
+            for sampler in samplers.iter() {
+                assert_eq!(&**sampler.device() as *const Device, &*device as *const Device);
+            }
+        }
+
+        let mut descriptors_count = DescriptorsCount::zero();
+        for desc in descriptors.iter() {
+            descriptors_count.add_num(desc.ty.ty().unwrap(), desc.array_count);
+        }
+
+        // Dynamic offsets are ordered by binding number, then by array element.
+        let dynamic_descriptors = {
+            let mut sorted = descriptors.iter().collect::<SmallVec<[_; 32]>>();
+            sorted.sort_by_key(|desc| desc.binding);
+
+            let mut list = SmallVec::new();
+            for desc in sorted {
+                match desc.ty.ty().unwrap() {
+                    ty @ DescriptorType::UniformBufferDynamic |
+                    ty @ DescriptorType::StorageBufferDynamic => {
+                        for _ in 0 .. desc.array_count {
+                            list.push(ty);
+                        }
+                    },
+                    _ => ()
+                }
+            }
+            list
+        };
+
+        let raw_immutable_samplers = immutable_samplers.iter().map(|&(binding, ref samplers)| {
+            (binding, samplers.iter().map(|s| s.internal_object()).collect::<SmallVec<[_; 4]>>())
+        }).collect::<SmallVec<[_; 4]>>();
+
+        let bindings = descriptors.iter().map(|desc| {
+            let immutable = raw_immutable_samplers.iter().find(|&&(b, _)| b == desc.binding);
+
             vk::DescriptorSetLayoutBinding {
                 binding: desc.binding,
                 descriptorType: desc.ty.ty().unwrap() /* TODO: shouldn't panic */ as u32,
                 descriptorCount: desc.array_count,
                 stageFlags: desc.stages.into(),
-                pImmutableSamplers: ptr::null(),        // FIXME: not yet implemented
+                pImmutableSamplers: match immutable {
+                    Some(&(_, ref samplers)) => samplers.as_ptr(),
+                    None => ptr::null(),
+                },
             }
         }).collect::<SmallVec<[_; 32]>>();
 
@@ -70,6 +160,10 @@ impl<P> UnsafeDescriptorSetLayout<P> where P: SafeDeref<Target = Device> {
         Ok(UnsafeDescriptorSetLayout {
             layout: layout,
             device: device,
+            descriptors: descriptors,
+            descriptors_count: descriptors_count,
+            dynamic_descriptors: dynamic_descriptors,
+            immutable_samplers: immutable_samplers,
         })
     }
 
@@ -91,6 +185,68 @@ impl<P> UnsafeDescriptorSetLayout<P> where P: SafeDeref<Target = Device> {
     pub fn device(&self) -> &P {
         &self.device
     }
+
+    /// Returns the list of descriptors that was passed when creating this layout.
+    #[inline]
+    pub fn descriptors(&self) -> &[DescriptorDesc] {
+        &self.descriptors
+    }
+
+    /// Returns the number of descriptors of each type that a descriptor set with this layout
+    /// requires.
+    #[inline]
+    pub fn descriptors_count(&self) -> &DescriptorsCount {
+        &self.descriptors_count
+    }
+
+    /// Returns the type of each dynamic uniform or storage buffer descriptor of the layout.
+    ///
+    /// The list is ordered by binding number and then by array element, which is the order in
+    /// which dynamic offsets must be passed when binding a descriptor set with this layout.
+    #[inline]
+    pub fn dynamic_descriptors(&self) -> &[DescriptorType] {
+        &self.dynamic_descriptors
+    }
+
+    /// Returns the immutable samplers of a binding, or `None` if the binding doesn't have any.
+    #[inline]
+    pub fn immutable_samplers(&self, binding: u32) -> Option<&[Arc<Sampler>]> {
+        self.immutable_samplers.iter().find(|&&(b, _)| b == binding).map(|&(_, ref s)| &s[..])
+    }
+
+    /// Returns true if `other` is identically defined to this layout, as defined by the Vulkan
+    /// specs.
+    ///
+    /// Two layouts are identically defined if they have the same bindings with the same
+    /// descriptor types, the same array sizes, the same shader stages and the same immutable
+    /// samplers. Descriptor sets that were allocated with a layout can be used where an
+    /// identically defined layout is expected.
+    pub fn is_identical_to<P2>(&self, other: &UnsafeDescriptorSetLayout<P2>) -> bool
+        where P2: SafeDeref<Target = Device>
+    {
+        if self.descriptors.len() != other.descriptors.len() {
+            return false;
+        }
+
+        if self.immutable_samplers.len() != other.immutable_samplers.len() {
+            return false;
+        }
+
+        let same_samplers = self.immutable_samplers.iter().zip(other.immutable_samplers.iter())
+                                .all(|(&(b1, ref s1), &(b2, ref s2))| {
+            b1 == b2 && s1.len() == s2.len() &&
+            s1.iter().zip(s2.iter()).all(|(a, b)| &**a as *const _ == &**b as *const _)
+        });
+
+        if !same_samplers {
+            return false;
+        }
+
+        self.descriptors.iter().zip(other.descriptors.iter()).all(|(a, b)| {
+            a.binding == b.binding && a.ty.ty() == b.ty.ty() &&
+            a.array_count == b.array_count && a.stages == b.stages
+        })
+    }
 }
 
 unsafe impl<P> VulkanObject for UnsafeDescriptorSetLayout<P> where P: SafeDeref<Target = Device> {
@@ -116,11 +272,91 @@ impl<P> Drop for UnsafeDescriptorSetLayout<P> where P: SafeDeref<Target = Device
 #[cfg(test)]
 mod tests {
     use std::iter;
+    use descriptor::descriptor::DescriptorBufferDesc;
+    use descriptor::descriptor::DescriptorDesc;
+    use descriptor::descriptor::DescriptorDescTy;
+    use descriptor::descriptor::DescriptorType;
+    use descriptor::descriptor::ShaderStages;
     use descriptor::descriptor_set::unsafe_layout::UnsafeDescriptorSetLayout;
+    use sampler::Sampler;
 
     #[test]
     fn empty() {
         let (device, _) = gfx_dev_and_queue!();
         let _layout = UnsafeDescriptorSetLayout::new(device, iter::empty());
     }
+
+    #[test]
+    fn identical() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let desc = DescriptorDesc {
+            binding: 0,
+            ty: DescriptorDescTy::Sampler,
+            array_count: 1,
+            stages: ShaderStages::all_graphics(),
+            readonly: true,
+        };
+
+        let layout1 = UnsafeDescriptorSetLayout::new(device.clone(), Some(desc));
+        let layout2 = UnsafeDescriptorSetLayout::new(device.clone(), Some(desc));
+        let layout3 = UnsafeDescriptorSetLayout::new(device, iter::empty());
+
+        assert!(layout1.is_identical_to(&layout2));
+        assert!(!layout1.is_identical_to(&layout3));
+    }
+
+    #[test]
+    fn immutable_samplers() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let desc = DescriptorDesc {
+            binding: 0,
+            ty: DescriptorDescTy::Sampler,
+            array_count: 1,
+            stages: ShaderStages::all_graphics(),
+            readonly: true,
+        };
+
+        let sampler = Sampler::simple_repeat_linear(&device);
+        let samplers = Some((0, vec![sampler]));
+        let layout1 = UnsafeDescriptorSetLayout::raw_with_immutable_samplers(device.clone(),
+                                                                              Some(desc),
+                                                                              samplers).unwrap();
+        let layout2 = UnsafeDescriptorSetLayout::new(device, Some(desc));
+
+        assert_eq!(layout1.immutable_samplers(0).map(|s| s.len()), Some(1));
+        assert!(layout2.immutable_samplers(0).is_none());
+        assert!(!layout1.is_identical_to(&layout2));
+    }
+
+    #[test]
+    fn dynamic_descriptors_order() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let dynamic = DescriptorDesc {
+            binding: 2,
+            ty: DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                dynamic: Some(true),
+                storage: false,
+            }),
+            array_count: 1,
+            stages: ShaderStages::all_graphics(),
+            readonly: true,
+        };
+
+        let sampler = DescriptorDesc {
+            binding: 1,
+            ty: DescriptorDescTy::Sampler,
+            .. dynamic.clone()
+        };
+
+        let dynamic2 = DescriptorDesc {
+            binding: 0,
+            .. dynamic.clone()
+        };
+
+        let layout = UnsafeDescriptorSetLayout::new(device, vec![dynamic, sampler, dynamic2]);
+        assert_eq!(layout.dynamic_descriptors(), &[DescriptorType::UniformBufferDynamic; 2]);
+    }
 }
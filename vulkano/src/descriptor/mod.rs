@@ -79,6 +79,10 @@
 //! let _pipeline_layout = pipeline_layout::CustomPipeline::new(&device).unwrap();
 //! ```
 //!
+//! A binding can hold an array of descriptors by giving it an array type, for example
+//! `u_textures: [CombinedImageSampler; 8]`. When building a descriptor set, such a binding accepts
+//! an array, a slice or a `Vec` whose length must be equal to the length of the array.
+//!
 //! # When drawing
 //!
 //! When you call a function that adds a draw command to a command buffer, one of the parameters
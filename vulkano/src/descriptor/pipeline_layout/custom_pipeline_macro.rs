@@ -11,6 +11,7 @@ use std::marker::PhantomData;
 use std::sync::Arc;
 
 use buffer::Buffer;
+use buffer::BufferSlice;
 use buffer::BufferView;
 use buffer::TypedBuffer;
 use descriptor::descriptor::DescriptorDescTy;
@@ -20,6 +21,7 @@ use descriptor::descriptor::DescriptorImageDescDimensions;
 use descriptor::descriptor::DescriptorImageDescArray;
 use descriptor::descriptor_set::DescriptorWrite;
 use image::ImageView;
+use image::ViewType;
 use sampler::Sampler;
 
 /// Call this macro with the layout of a pipeline to generate some helper structs that wrap around
@@ -45,7 +47,7 @@ macro_rules! pipeline_layout {
         }
 
         pub struct CustomPipeline {
-            inner: UnsafePipelineLayout
+            inner: Arc<UnsafePipelineLayout>
         }
 
         impl CustomPipeline {
@@ -66,7 +68,7 @@ macro_rules! pipeline_layout {
                 };
 
                 let inner = {
-                    try!(UnsafePipelineLayout::new(device, layouts.iter(), push_constants))
+                    try!(UnsafePipelineLayout::shared(device, layouts.iter(), push_constants))
                 };
 
                 Ok(Arc::new(CustomPipeline {
@@ -123,7 +125,9 @@ macro_rules! pipeline_layout {
             use $crate::descriptor::descriptor::ShaderStages;
             use $crate::descriptor::descriptor_set::DescriptorPool;
             use $crate::descriptor::descriptor_set::DescriptorSet;
+            use $crate::descriptor::descriptor_set::DescriptorSetAllocError;
             use $crate::descriptor::descriptor_set::DescriptorSetDesc;
+            use $crate::descriptor::descriptor_set::StdDescriptorPool;
             use $crate::descriptor::descriptor_set::UnsafeDescriptorSet;
             use $crate::descriptor::descriptor_set::UnsafeDescriptorSetLayout;
             use $crate::descriptor::descriptor_set::DescriptorWrite;
@@ -131,10 +135,15 @@ macro_rules! pipeline_layout {
             use $crate::descriptor::pipeline_layout::custom_pipeline_macro::UniformTexelBuffer;
             use $crate::descriptor::pipeline_layout::custom_pipeline_macro::StorageTexelBuffer;
             use $crate::descriptor::pipeline_layout::custom_pipeline_macro::CombinedImageSampler;
+            use $crate::descriptor::pipeline_layout::custom_pipeline_macro::CombinedImageSampler3d;
             use $crate::descriptor::pipeline_layout::custom_pipeline_macro::SampledImage;
+            use $crate::descriptor::pipeline_layout::custom_pipeline_macro::SampledImage3d;
             use $crate::descriptor::pipeline_layout::custom_pipeline_macro::DescriptorMarker;
+            use $crate::descriptor::pipeline_layout::custom_pipeline_macro::DynamicStorageBuffer;
+            use $crate::descriptor::pipeline_layout::custom_pipeline_macro::DynamicUniformBuffer;
             use $crate::descriptor::pipeline_layout::custom_pipeline_macro::StorageBuffer;
             use $crate::descriptor::pipeline_layout::custom_pipeline_macro::StorageImage;
+            use $crate::descriptor::pipeline_layout::custom_pipeline_macro::StorageImage3d;
             use $crate::descriptor::pipeline_layout::custom_pipeline_macro::UniformBuffer;
             use $crate::descriptor::pipeline_layout::custom_pipeline_macro::InputAttachment;
             use $crate::descriptor::pipeline_layout::custom_pipeline_macro::ValidParameter;
@@ -157,7 +166,7 @@ macro_rules! pipeline_layout {
                     let mut writes = Vec::new();
                     let mut binding = 0;
                     $(
-                        writes.push(self.$field.write(binding));
+                        self.$field.write(binding, 0, &mut writes);
                         binding += 1;
                     )*
                     writes
@@ -169,12 +178,15 @@ macro_rules! pipeline_layout {
             }
 
             impl Set {
+                /// Builds a new descriptor set allocated from an explicit pool.
+                ///
+                /// Returns `OutOfPoolMemory` if the pool doesn't have enough room left.
                 #[inline]
                 #[allow(non_camel_case_types)]
                 pub fn raw<$($field: ValidParameter<$ty>),*>
                           (pool: &Arc<DescriptorPool>, layout: &Arc<CustomPipeline>,
                            descriptors: &Descriptors<$($field),*>)
-                           -> Result<Set, OomError>
+                           -> Result<Set, DescriptorSetAllocError>
                 {
                     #![allow(unsafe_code)]
                     unsafe {
@@ -184,15 +196,40 @@ macro_rules! pipeline_layout {
                         Ok(Set { inner: set })
                     }
                 }
-                
+
+                /// Builds a new descriptor set allocated from the standard descriptor pool of
+                /// the device.
+                #[inline]
+                #[allow(non_camel_case_types)]
+                pub fn std_raw<$($field: ValidParameter<$ty>),*>
+                              (layout: &Arc<CustomPipeline>,
+                               descriptors: &Descriptors<$($field),*>)
+                               -> Result<Set, OomError>
+                {
+                    #![allow(unsafe_code)]
+                    unsafe {
+                        let pool = Device::standard_descriptor_pool(layout.inner().device());
+                        let layout = layout.inner().descriptor_set_layout($num).unwrap();
+                        let mut set = try!(StdDescriptorPool::alloc(&pool, layout));
+                        set.write(descriptors.writes());
+                        Ok(Set { inner: set })
+                    }
+                }
+
+                /// Builds a new descriptor set allocated from the standard descriptor pool of
+                /// the device.
+                ///
+                /// # Panic
+                ///
+                /// - Panics if the device or host ran out of memory.
+                ///
                 #[inline]
                 #[allow(non_camel_case_types)]
                 pub fn new<$($field: ValidParameter<$ty>),*>
-                          (pool: &Arc<DescriptorPool>, layout: &Arc<CustomPipeline>,
-                           descriptors: &Descriptors<$($field),*>)
+                          (layout: &Arc<CustomPipeline>, descriptors: &Descriptors<$($field),*>)
                            -> Arc<Set>
                 {
-                    Arc::new(Set::raw(pool, layout, descriptors).unwrap())
+                    Arc::new(Set::std_raw(layout, descriptors).unwrap())
                 }
             }
 
@@ -227,7 +264,7 @@ macro_rules! pipeline_layout {
                     descriptors.push(DescriptorDesc {
                         binding: binding,
                         ty: <$ty as DescriptorMarker>::descriptor_type(),
-                        array_count: 1,                     // TODO:
+                        array_count: <$ty as DescriptorMarker>::array_count(),
                         stages: ShaderStages::all(),        // TODO:
                         readonly: false,                    // TODO:
                     });
@@ -254,13 +291,70 @@ macro_rules! pipeline_layout {
 }
 
 pub unsafe trait ValidParameter<Target> {
-    fn write(&self, binding: u32) -> DescriptorWrite;
+    /// Appends to `out` the writes that put this parameter at the given binding, starting at
+    /// the given array element.
+    fn write(&self, binding: u32, array_element: u32, out: &mut Vec<DescriptorWrite>);
 }
 
 pub unsafe trait DescriptorMarker {
     fn descriptor_type() -> DescriptorDescTy;
+
+    /// Returns the number of array elements of the binding.
+    #[inline]
+    fn array_count() -> u32 {
+        1
+    }
+}
+
+// Implements the markers for arrays of descriptors, so that a field of type `[M; N]` declares a
+// binding of `N` descriptors of type `M`.
+//
+// Such a field accepts an array, a slice or a `Vec` of parameters, whose length must be exactly
+// `N`.
+macro_rules! array_markers {
+    ($($n:expr),*) => {
+        $(
+            unsafe impl<M> DescriptorMarker for [M; $n] where M: DescriptorMarker {
+                #[inline]
+                fn descriptor_type() -> DescriptorDescTy {
+                    M::descriptor_type()
+                }
+
+                #[inline]
+                fn array_count() -> u32 {
+                    $n
+                }
+            }
+
+            unsafe impl<M, P> ValidParameter<[M; $n]> for [P; $n] where P: ValidParameter<M> {
+                #[inline]
+                fn write(&self, binding: u32, array_element: u32, out: &mut Vec<DescriptorWrite>) {
+                    ValidParameter::<[M; $n]>::write(&&self[..], binding, array_element, out)
+                }
+            }
+
+            unsafe impl<'a, M, P> ValidParameter<[M; $n]> for &'a [P] where P: ValidParameter<M> {
+                fn write(&self, binding: u32, array_element: u32, out: &mut Vec<DescriptorWrite>) {
+                    assert_eq!(self.len(), $n);
+                    for (num, param) in self.iter().enumerate() {
+                        param.write(binding, array_element + num as u32, out);
+                    }
+                }
+            }
+
+            unsafe impl<M, P> ValidParameter<[M; $n]> for Vec<P> where P: ValidParameter<M> {
+                #[inline]
+                fn write(&self, binding: u32, array_element: u32, out: &mut Vec<DescriptorWrite>) {
+                    ValidParameter::<[M; $n]>::write(&&self[..], binding, array_element, out)
+                }
+            }
+        )*
+    };
 }
 
+array_markers!(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
+               24, 25, 26, 27, 28, 29, 30, 31, 32, 64, 128, 256);
+
 pub struct UniformBuffer<T: ?Sized>(PhantomData<T>);
 unsafe impl<T: ?Sized> DescriptorMarker for UniformBuffer<T> {
     #[inline]
@@ -276,8 +370,37 @@ unsafe impl<'a, B, T: ?Sized + 'static> ValidParameter<UniformBuffer<T>> for &'a
     where B: TypedBuffer<Content = T>
 {
     #[inline]
-    fn write(&self, binding: u32) -> DescriptorWrite {
-        DescriptorWrite::uniform_buffer(binding, *self)
+    fn write(&self, binding: u32, array_element: u32, out: &mut Vec<DescriptorWrite>) {
+        out.push(DescriptorWrite::uniform_buffer(binding, *self).array_element(array_element));
+    }
+}
+
+unsafe impl<'a, B, T: ?Sized + 'static> ValidParameter<UniformBuffer<T>> for BufferSlice<'a, T, B>
+    where B: Buffer + 'static
+{
+    #[inline]
+    fn write(&self, binding: u32, array_element: u32, out: &mut Vec<DescriptorWrite>) {
+        out.push(DescriptorWrite::uniform_buffer(binding, *self).array_element(array_element));
+    }
+}
+
+pub struct DynamicUniformBuffer<T: ?Sized>(PhantomData<T>);
+unsafe impl<T: ?Sized> DescriptorMarker for DynamicUniformBuffer<T> {
+    #[inline]
+    fn descriptor_type() -> DescriptorDescTy {
+        DescriptorDescTy::Buffer(DescriptorBufferDesc {
+            dynamic: Some(true),
+            storage: false,
+        })
+    }
+}
+
+unsafe impl<'a, B, T: ?Sized + 'static> ValidParameter<DynamicUniformBuffer<T>> for &'a Arc<B>
+    where B: TypedBuffer<Content = T>
+{
+    #[inline]
+    fn write(&self, binding: u32, array_element: u32, out: &mut Vec<DescriptorWrite>) {
+        out.push(DescriptorWrite::dynamic_uniform_buffer(binding, *self).array_element(array_element));
     }
 }
 
@@ -296,8 +419,37 @@ unsafe impl<'a, B, T: ?Sized + 'static> ValidParameter<StorageBuffer<T>> for &'a
     where B: TypedBuffer<Content = T>
 {
     #[inline]
-    fn write(&self, binding: u32) -> DescriptorWrite {
-        DescriptorWrite::storage_buffer(binding, *self)
+    fn write(&self, binding: u32, array_element: u32, out: &mut Vec<DescriptorWrite>) {
+        out.push(DescriptorWrite::storage_buffer(binding, *self).array_element(array_element));
+    }
+}
+
+unsafe impl<'a, B, T: ?Sized + 'static> ValidParameter<StorageBuffer<T>> for BufferSlice<'a, T, B>
+    where B: Buffer + 'static
+{
+    #[inline]
+    fn write(&self, binding: u32, array_element: u32, out: &mut Vec<DescriptorWrite>) {
+        out.push(DescriptorWrite::storage_buffer(binding, *self).array_element(array_element));
+    }
+}
+
+pub struct DynamicStorageBuffer<T: ?Sized>(PhantomData<T>);
+unsafe impl<T: ?Sized> DescriptorMarker for DynamicStorageBuffer<T> {
+    #[inline]
+    fn descriptor_type() -> DescriptorDescTy {
+        DescriptorDescTy::Buffer(DescriptorBufferDesc {
+            dynamic: Some(true),
+            storage: true,
+        })
+    }
+}
+
+unsafe impl<'a, B, T: ?Sized + 'static> ValidParameter<DynamicStorageBuffer<T>> for &'a Arc<B>
+    where B: TypedBuffer<Content = T>
+{
+    #[inline]
+    fn write(&self, binding: u32, array_element: u32, out: &mut Vec<DescriptorWrite>) {
+        out.push(DescriptorWrite::dynamic_storage_buffer(binding, *self).array_element(array_element));
     }
 }
 
@@ -316,8 +468,8 @@ unsafe impl<'a, B, F> ValidParameter<UniformTexelBuffer> for &'a Arc<BufferView<
     where B: Buffer, F: 'static + Send + Sync
 {
     #[inline]
-    fn write(&self, binding: u32) -> DescriptorWrite {
-        DescriptorWrite::uniform_texel_buffer(binding, *self)
+    fn write(&self, binding: u32, array_element: u32, out: &mut Vec<DescriptorWrite>) {
+        out.push(DescriptorWrite::uniform_texel_buffer(binding, *self).array_element(array_element));
     }
 }
 
@@ -336,8 +488,8 @@ unsafe impl<'a, B, F> ValidParameter<StorageTexelBuffer> for &'a Arc<BufferView<
     where B: Buffer, F: 'static + Send + Sync
 {
     #[inline]
-    fn write(&self, binding: u32) -> DescriptorWrite {
-        DescriptorWrite::storage_texel_buffer(binding, *self)
+    fn write(&self, binding: u32, array_element: u32, out: &mut Vec<DescriptorWrite>) {
+        out.push(DescriptorWrite::storage_texel_buffer(binding, *self).array_element(array_element));
     }
 }
 
@@ -360,8 +512,8 @@ unsafe impl<'a, I> ValidParameter<CombinedImageSampler> for (&'a Arc<Sampler>, &
     where I: ImageView + 'static
 {
     #[inline]
-    fn write(&self, binding: u32) -> DescriptorWrite {
-        DescriptorWrite::combined_image_sampler(binding, self.0, self.1)
+    fn write(&self, binding: u32, array_element: u32, out: &mut Vec<DescriptorWrite>) {
+        out.push(DescriptorWrite::combined_image_sampler(binding, self.0, self.1).array_element(array_element));
     }
 }
 
@@ -384,8 +536,8 @@ unsafe impl<'a, I> ValidParameter<SampledImage> for &'a Arc<I>
     where I: ImageView + 'static
 {
     #[inline]
-    fn write(&self, binding: u32) -> DescriptorWrite {
-        DescriptorWrite::sampled_image(binding, self)
+    fn write(&self, binding: u32, array_element: u32, out: &mut Vec<DescriptorWrite>) {
+        out.push(DescriptorWrite::sampled_image(binding, self).array_element(array_element));
     }
 }
 
@@ -408,8 +560,83 @@ unsafe impl<'a, I> ValidParameter<StorageImage> for &'a Arc<I>
     where I: ImageView + 'static
 {
     #[inline]
-    fn write(&self, binding: u32) -> DescriptorWrite {
-        DescriptorWrite::storage_image(binding, self)
+    fn write(&self, binding: u32, array_element: u32, out: &mut Vec<DescriptorWrite>) {
+        out.push(DescriptorWrite::storage_image(binding, self).array_element(array_element));
+    }
+}
+
+/// Same as `CombinedImageSampler`, but for three-dimensional images.
+pub struct CombinedImageSampler3d;
+unsafe impl DescriptorMarker for CombinedImageSampler3d {
+    #[inline]
+    fn descriptor_type() -> DescriptorDescTy {
+        DescriptorDescTy::CombinedImageSampler(DescriptorImageDesc {
+            sampled: true,
+            dimensions: DescriptorImageDescDimensions::ThreeDimensional,
+            multisampled: false,
+            array_layers: DescriptorImageDescArray::NonArrayed,
+            format: None,
+        })
+    }
+}
+
+unsafe impl<'a, I> ValidParameter<CombinedImageSampler3d> for (&'a Arc<Sampler>, &'a Arc<I>)
+    where I: ImageView + 'static
+{
+    #[inline]
+    fn write(&self, binding: u32, array_element: u32, out: &mut Vec<DescriptorWrite>) {
+        assert_eq!(self.1.dimensions().to_view_type(), ViewType::Dim3d);
+        out.push(DescriptorWrite::combined_image_sampler(binding, self.0, self.1).array_element(array_element));
+    }
+}
+
+/// Same as `SampledImage`, but for three-dimensional images.
+pub struct SampledImage3d;
+unsafe impl DescriptorMarker for SampledImage3d {
+    #[inline]
+    fn descriptor_type() -> DescriptorDescTy {
+        DescriptorDescTy::Image(DescriptorImageDesc {
+            sampled: true,
+            dimensions: DescriptorImageDescDimensions::ThreeDimensional,
+            multisampled: false,
+            array_layers: DescriptorImageDescArray::NonArrayed,
+            format: None,
+        })
+    }
+}
+
+unsafe impl<'a, I> ValidParameter<SampledImage3d> for &'a Arc<I>
+    where I: ImageView + 'static
+{
+    #[inline]
+    fn write(&self, binding: u32, array_element: u32, out: &mut Vec<DescriptorWrite>) {
+        assert_eq!(self.dimensions().to_view_type(), ViewType::Dim3d);
+        out.push(DescriptorWrite::sampled_image(binding, self).array_element(array_element));
+    }
+}
+
+/// Same as `StorageImage`, but for three-dimensional images.
+pub struct StorageImage3d;
+unsafe impl DescriptorMarker for StorageImage3d {
+    #[inline]
+    fn descriptor_type() -> DescriptorDescTy {
+        DescriptorDescTy::Image(DescriptorImageDesc {
+            sampled: false,
+            dimensions: DescriptorImageDescDimensions::ThreeDimensional,
+            multisampled: false,
+            array_layers: DescriptorImageDescArray::NonArrayed,
+            format: None,
+        })
+    }
+}
+
+unsafe impl<'a, I> ValidParameter<StorageImage3d> for &'a Arc<I>
+    where I: ImageView + 'static
+{
+    #[inline]
+    fn write(&self, binding: u32, array_element: u32, out: &mut Vec<DescriptorWrite>) {
+        assert_eq!(self.dimensions().to_view_type(), ViewType::Dim3d);
+        out.push(DescriptorWrite::storage_image(binding, self).array_element(array_element));
     }
 }
 
@@ -426,8 +653,8 @@ unsafe impl<'a, I> ValidParameter<InputAttachment> for &'a Arc<I>
     where I: ImageView + 'static
 {
     #[inline]
-    fn write(&self, binding: u32) -> DescriptorWrite {
-        DescriptorWrite::input_attachment(binding, self)
+    fn write(&self, binding: u32, array_element: u32, out: &mut Vec<DescriptorWrite>) {
+        out.push(DescriptorWrite::input_attachment(binding, self).array_element(array_element));
     }
 }
 
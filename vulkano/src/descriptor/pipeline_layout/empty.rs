@@ -20,14 +20,14 @@ use descriptor::pipeline_layout::UnsafePipelineLayoutCreationError;
 
 /// Implementation of `PipelineLayout` for an empty pipeline.
 pub struct EmptyPipeline {
-    inner: UnsafePipelineLayout
+    inner: Arc<UnsafePipelineLayout>
 }
 
 impl EmptyPipeline {
     /// Builds a new empty pipeline.
     pub fn new(device: &Arc<Device>) -> Result<Arc<EmptyPipeline>, UnsafePipelineLayoutCreationError> {
         let inner = {
-            try!(UnsafePipelineLayout::new(device, iter::empty(), iter::empty()))
+            try!(UnsafePipelineLayout::shared(device, iter::empty(), iter::empty()))
         };
 
         Ok(Arc::new(EmptyPipeline {
@@ -0,0 +1,283 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+use std::vec::IntoIter as VecIntoIter;
+
+use descriptor::descriptor::DescriptorDesc;
+use descriptor::descriptor::ShaderStages;
+use descriptor::pipeline_layout::PipelineLayoutDesc;
+use descriptor::pipeline_layout::RuntimePipelineLayout;
+use descriptor::pipeline_layout::UnsafePipelineLayoutCreationError;
+use device::Device;
+
+use Error;
+use VulkanError;
+
+/// Description of a pipeline layout built from explicit tables of descriptors and push
+/// constant ranges, for example hand-written or generated by a tool.
+///
+/// The tables of each shader stage are added one after the other and merged. A binding used by
+/// several stages gets the union of their stages and the largest of their array sizes, but the
+/// types of its descriptors must be compatible.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut desc = ExplicitLayoutDesc::new();
+/// try!(desc.add_descriptors(0, vertex_shader_descriptors));
+/// try!(desc.add_descriptors(0, fragment_shader_descriptors));
+/// try!(desc.add_push_constants(0, 64, ShaderStages { vertex: true, .. ShaderStages::none() }));
+/// let layout = desc.build(&device).unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ExplicitLayoutDesc {
+    // Descriptors of each set, sorted by binding.
+    sets: Vec<Vec<DescriptorDesc>>,
+    push_constants: Vec<(usize, usize, ShaderStages)>,
+}
+
+impl ExplicitLayoutDesc {
+    /// Builds a new empty description.
+    #[inline]
+    pub fn new() -> ExplicitLayoutDesc {
+        ExplicitLayoutDesc {
+            sets: Vec::new(),
+            push_constants: Vec::new(),
+        }
+    }
+
+    /// Adds descriptors to the set of the given index, merging them with the descriptors that
+    /// are already there.
+    ///
+    /// If an error is returned, the description is left unmodified.
+    pub fn add_descriptors<I>(&mut self, set: u32, descriptors: I) -> Result<(), LayoutMergeError>
+        where I: IntoIterator<Item = DescriptorDesc>
+    {
+        let mut merged = self.sets.get(set as usize).cloned().unwrap_or(Vec::new());
+
+        for desc in descriptors {
+            let existing = match merged.iter_mut().find(|d| d.binding == desc.binding) {
+                Some(existing) => existing,
+                None => {
+                    merged.push(desc);
+                    continue;
+                },
+            };
+
+            if !existing.ty.is_superset_of(&desc.ty) {
+                if !desc.ty.is_superset_of(&existing.ty) {
+                    return Err(LayoutMergeError::IncompatibleDescriptors {
+                        set: set,
+                        binding: desc.binding,
+                    });
+                }
+
+                existing.ty = desc.ty;
+            }
+
+            if desc.array_count > existing.array_count {
+                existing.array_count = desc.array_count;
+            }
+            existing.stages = existing.stages.union(&desc.stages);
+            existing.readonly = existing.readonly && desc.readonly;
+        }
+
+        merged.sort_by_key(|desc| desc.binding);
+
+        while self.sets.len() <= set as usize {
+            self.sets.push(Vec::new());
+        }
+        self.sets[set as usize] = merged;
+        Ok(())
+    }
+
+    /// Adds a push constant range.
+    ///
+    /// If a range with the same offset and size already exists, the stages are added to it.
+    /// Returns an error if one of the stages is already in another range, as Vulkan only allows
+    /// one range per stage.
+    pub fn add_push_constants(&mut self, offset: usize, size: usize, stages: ShaderStages)
+                              -> Result<(), LayoutMergeError>
+    {
+        let same = self.push_constants.iter().position(|r| r.0 == offset && r.1 == size);
+
+        let conflict = self.push_constants.iter().enumerate().any(|(num, r)| {
+            Some(num) != same && r.2.intersects(&stages)
+        });
+
+        if conflict {
+            return Err(LayoutMergeError::ConflictingPushConstants);
+        }
+
+        match same {
+            Some(num) => self.push_constants[num].2 = self.push_constants[num].2.union(&stages),
+            None => self.push_constants.push((offset, size, stages)),
+        }
+
+        Ok(())
+    }
+
+    /// Merges another description into this one.
+    ///
+    /// If an error is returned, the descriptors of some sets may already have been merged.
+    pub fn merge(&mut self, other: &ExplicitLayoutDesc) -> Result<(), LayoutMergeError> {
+        for (num, set) in other.sets.iter().enumerate() {
+            try!(self.add_descriptors(num as u32, set.iter().cloned()));
+        }
+
+        for &(offset, size, stages) in other.push_constants.iter() {
+            try!(self.add_push_constants(offset, size, stages));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the descriptors of each set, sorted by binding.
+    #[inline]
+    pub fn sets(&self) -> &[Vec<DescriptorDesc>] {
+        &self.sets
+    }
+
+    /// Returns the push constant ranges, as `(offset, size, stages)`.
+    #[inline]
+    pub fn push_constants(&self) -> &[(usize, usize, ShaderStages)] {
+        &self.push_constants
+    }
+
+    /// Builds a pipeline layout that matches this description.
+    #[inline]
+    pub fn build(&self, device: &Arc<Device>)
+                 -> Result<Arc<RuntimePipelineLayout>, UnsafePipelineLayoutCreationError>
+    {
+        RuntimePipelineLayout::from_descriptors(device, self.sets.clone(),
+                                                self.push_constants.iter().cloned())
+    }
+}
+
+unsafe impl PipelineLayoutDesc for ExplicitLayoutDesc {
+    type SetsIter = VecIntoIter<Self::DescIter>;
+    type DescIter = VecIntoIter<DescriptorDesc>;
+
+    #[inline]
+    fn descriptors_desc(&self) -> Self::SetsIter {
+        self.sets.iter().map(|set| set.clone().into_iter()).collect::<Vec<_>>().into_iter()
+    }
+}
+
+/// Error that can happen when merging descriptions of pipeline layouts.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LayoutMergeError {
+    /// A binding is used with descriptors of incompatible types.
+    IncompatibleDescriptors {
+        set: u32,
+        binding: u32,
+    },
+    /// A stage is in two push constant ranges that are different.
+    ConflictingPushConstants,
+}
+
+impl error::Error for LayoutMergeError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            LayoutMergeError::IncompatibleDescriptors { .. } => {
+                "a binding is used with descriptors of incompatible types"
+            },
+            LayoutMergeError::ConflictingPushConstants => {
+                "a stage is in two different push constant ranges"
+            },
+        }
+    }
+}
+
+impl fmt::Display for LayoutMergeError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for LayoutMergeError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use descriptor::descriptor::DescriptorBufferDesc;
+    use descriptor::descriptor::DescriptorDesc;
+    use descriptor::descriptor::DescriptorDescTy;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::pipeline_layout::ExplicitLayoutDesc;
+    use descriptor::pipeline_layout::LayoutMergeError;
+    use descriptor::pipeline_layout::PipelineLayoutDesc;
+
+    fn buffer(binding: u32, storage: bool, stages: ShaderStages) -> DescriptorDesc {
+        DescriptorDesc {
+            binding: binding,
+            ty: DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                dynamic: Some(false),
+                storage: storage,
+            }),
+            array_count: 1,
+            stages: stages,
+            readonly: true,
+        }
+    }
+
+    #[test]
+    fn merge_stages() {
+        let vertex = ShaderStages { vertex: true, .. ShaderStages::none() };
+        let fragment = ShaderStages { fragment: true, .. ShaderStages::none() };
+
+        let mut desc = ExplicitLayoutDesc::new();
+        desc.add_descriptors(1, vec![buffer(2, false, vertex), buffer(0, false, vertex)]).unwrap();
+        desc.add_descriptors(1, vec![buffer(0, false, fragment)]).unwrap();
+
+        assert_eq!(desc.sets().len(), 2);
+        assert!(desc.sets()[0].is_empty());
+        assert_eq!(desc.sets()[1].len(), 2);
+        assert_eq!(desc.sets()[1][0].binding, 0);
+        assert_eq!(desc.sets()[1][0].stages, vertex.union(&fragment));
+        assert_eq!(desc.descriptors_desc().count(), 2);
+
+        assert_eq!(desc.add_descriptors(1, vec![buffer(2, true, fragment)]),
+                   Err(LayoutMergeError::IncompatibleDescriptors { set: 1, binding: 2 }));
+        assert_eq!(desc.sets()[1][1].stages, vertex);
+    }
+
+    #[test]
+    fn merge_push_constants() {
+        let vertex = ShaderStages { vertex: true, .. ShaderStages::none() };
+        let fragment = ShaderStages { fragment: true, .. ShaderStages::none() };
+
+        let mut desc = ExplicitLayoutDesc::new();
+        desc.add_push_constants(0, 16, vertex).unwrap();
+        desc.add_push_constants(0, 16, fragment).unwrap();
+        assert_eq!(desc.push_constants(), &[(0, 16, vertex.union(&fragment))]);
+
+        let mut other = ExplicitLayoutDesc::new();
+        other.add_push_constants(0, 32, vertex).unwrap();
+        assert_eq!(desc.merge(&other), Err(LayoutMergeError::ConflictingPushConstants));
+
+        // A stage can't be added to a range with the same offset and size if it is already in
+        // another range.
+        let mut desc = ExplicitLayoutDesc::new();
+        desc.add_push_constants(0, 16, vertex).unwrap();
+        desc.add_push_constants(16, 16, fragment).unwrap();
+        assert_eq!(desc.add_push_constants(0, 16, fragment),
+                   Err(LayoutMergeError::ConflictingPushConstants));
+        assert_eq!(desc.push_constants(), &[(0, 16, vertex), (16, 16, fragment)]);
+    }
+}
@@ -20,6 +20,11 @@
 
 pub use self::empty::EmptyPipeline;
 pub use self::empty::EmptyPipelineDesc;
+pub use self::explicit::ExplicitLayoutDesc;
+pub use self::explicit::LayoutMergeError;
+pub use self::push_constants::PushConstantMember;
+pub use self::push_constants::PushConstantsDesc;
+pub use self::runtime::RuntimePipelineLayout;
 pub use self::sys::UnsafePipelineLayout;
 pub use self::sys::UnsafePipelineLayoutCreationError;
 pub use self::traits::PipelineLayout;
@@ -29,7 +34,11 @@ pub use self::traits::PipelineLayoutSetsCompatible;
 pub use self::traits::PipelineLayoutPushConstantsCompatible;
 
 pub mod custom_pipeline_macro;
+#[macro_use]
+pub mod push_constants;
 
 mod empty;
+mod explicit;
+mod runtime;
 mod sys;
 mod traits;
@@ -0,0 +1,241 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Describing push constants with Rust structs.
+//!
+//! The `impl_push_constants!` macro implements the `PushConstantsDesc` trait on a struct, given
+//! the shader stages that access each of its fields. The push constant ranges of a pipeline
+//! layout can then be derived from the struct instead of being written by hand, and the struct
+//! can be checked against the push constants of a shader with
+//! `ShaderReflection::check_push_constants`.
+//!
+//! # Example
+//!
+//! ```ignore
+//! #[repr(C)]
+//! #[derive(Copy, Clone)]
+//! struct PushConstants {
+//!     transform: [[f32; 4]; 4],
+//!     color: [f32; 4],
+//! }
+//!
+//! impl_push_constants!(PushConstants, transform => vertex, color => vertex | fragment);
+//!
+//! let layout = RuntimePipelineLayout::new(&device, set_layouts.iter(),
+//!                                         PushConstants::ranges()).unwrap();
+//! ```
+
+use smallvec::SmallVec;
+
+use descriptor::descriptor::ShaderStages;
+
+/// Trait for structs that describe the push constants of a pipeline.
+///
+/// Usually implemented with the `impl_push_constants!` macro.
+///
+/// # Safety
+///
+/// - The members returned by `members()` must be within the struct.
+///
+pub unsafe trait PushConstantsDesc {
+    /// Returns the position of each field of the struct and the stages that access it.
+    fn members() -> Vec<PushConstantMember>;
+
+    /// Returns the push constant ranges, as `(offset, size, stages)`, that a pipeline layout
+    /// must have to use this struct as push constants. They can be passed directly to
+    /// `RuntimePipelineLayout::new`.
+    ///
+    /// There is one range per group of stages that access the same bytes, and each stage is in
+    /// at most one range as required by Vulkan. Ranges are rounded to multiples of 4 bytes.
+    fn ranges() -> Vec<(usize, usize, ShaderStages)> {
+        let members = Self::members();
+        let mut ranges: Vec<(usize, usize, ShaderStages)> = Vec::new();
+
+        for stage in single_stages().iter() {
+            let mut bounds: Option<(usize, usize)> = None;
+            for member in members.iter().filter(|m| m.stages.is_superset_of(stage)) {
+                let end = member.offset + member.size;
+                bounds = Some(match bounds {
+                    None => (member.offset, end),
+                    Some((s, e)) => (if member.offset < s { member.offset } else { s },
+                                     if end > e { end } else { e }),
+                });
+            }
+
+            let (start, end) = match bounds {
+                Some((start, end)) => (start / 4 * 4, (end + 3) / 4 * 4),
+                None => continue,
+            };
+
+            match ranges.iter_mut().find(|r| r.0 == start && r.1 == end - start) {
+                Some(range) => range.2 = range.2.union(stage),
+                None => ranges.push((start, end - start, *stage)),
+            }
+        }
+
+        ranges
+    }
+}
+
+unsafe impl PushConstantsDesc for () {
+    #[inline]
+    fn members() -> Vec<PushConstantMember> {
+        Vec::new()
+    }
+}
+
+/// Describes a field of a struct used as push constants.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PushConstantMember {
+    /// Offset of the field within the struct.
+    pub offset: usize,
+    /// Size of the field in bytes.
+    pub size: usize,
+    /// Stages that access the field.
+    pub stages: ShaderStages,
+}
+
+/// Implements the `PushConstantsDesc` trait on a struct.
+///
+/// The struct is followed by each of its fields and the stages that access it, as the name of
+/// fields of `ShaderStages` separated with `|`. The struct should be `#[repr(C)]` so that its
+/// layout matches the one of the shaders.
+///
+/// ```ignore
+/// impl_push_constants!(PushConstants, transform => vertex, color => vertex | fragment);
+/// ```
+#[macro_export]
+macro_rules! impl_push_constants {
+    ($out:ident $(, $member:ident => $($stage:ident)|+)*) => (
+        #[allow(unsafe_code)]
+        unsafe impl $crate::descriptor::pipeline_layout::PushConstantsDesc for $out {
+            fn members() -> Vec<$crate::descriptor::pipeline_layout::PushConstantMember> {
+                use std::mem;
+                use $crate::descriptor::descriptor::ShaderStages;
+                use $crate::descriptor::pipeline_layout::PushConstantMember;
+
+                #[inline] fn size_of<T>(_: &T) -> usize { mem::size_of::<T>() }
+
+                vec![
+                    $(
+                        unsafe {
+                            let dummy = 0usize as *const $out;
+                            let member = (&(&*dummy).$member) as *const _;
+
+                            PushConstantMember {
+                                offset: member as usize,
+                                size: size_of(&(&*dummy).$member),
+                                stages: ShaderStages {
+                                    $($stage: true,)+
+                                    .. ShaderStages::none()
+                                },
+                            }
+                        }
+                    ),*
+                ]
+            }
+        }
+    );
+}
+
+/// Returns the updates, as `(offset, size, stages)`, that `vkCmdPushConstants` must perform to
+/// write the first `size` bytes of the push constants of a layout with the given ranges.
+///
+/// The stages of each update must be exactly the stages of all the ranges that contain it,
+/// therefore the data is split wherever a range starts or ends. Bytes that are not in any range
+/// are skipped.
+#[doc(hidden)]
+pub fn push_constants_updates(ranges: &[(usize, usize, ShaderStages)], size: usize)
+                              -> SmallVec<[(usize, usize, ShaderStages); 8]>
+{
+    let mut bounds = Vec::with_capacity(ranges.len() * 2 + 2);
+    bounds.push(0);
+    bounds.push(size);
+    for &(offset, range_size, _) in ranges {
+        if offset < size { bounds.push(offset); }
+        if offset + range_size < size { bounds.push(offset + range_size); }
+    }
+    bounds.sort();
+    bounds.dedup();
+
+    let mut updates: SmallVec<[(usize, usize, ShaderStages); 8]> = SmallVec::new();
+    for window in bounds.windows(2) {
+        let (start, end) = (window[0], window[1]);
+        let stages = ranges.iter().filter(|r| r.0 <= start && r.0 + r.1 >= end)
+                           .fold(ShaderStages::none(), |s, r| s.union(&r.2));
+        if stages == ShaderStages::none() {
+            continue;
+        }
+
+        // Merges with the previous update if it has the same stages.
+        if let Some(last) = updates.last_mut() {
+            if last.0 + last.1 == start && last.2 == stages {
+                last.1 = end - last.0;
+                continue;
+            }
+        }
+
+        updates.push((start, end - start, stages));
+    }
+
+    updates
+}
+
+// Returns a `ShaderStages` for each individual stage.
+fn single_stages() -> [ShaderStages; 6] {
+    let none = ShaderStages::none();
+    [
+        ShaderStages { vertex: true, .. none },
+        ShaderStages { tessellation_control: true, .. none },
+        ShaderStages { tessellation_evaluation: true, .. none },
+        ShaderStages { geometry: true, .. none },
+        ShaderStages { fragment: true, .. none },
+        ShaderStages { compute: true, .. none },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::pipeline_layout::PushConstantsDesc;
+    use descriptor::pipeline_layout::push_constants::push_constants_updates;
+
+    #[test]
+    fn ranges_from_fields() {
+        #[repr(C)]
+        #[allow(dead_code)]
+        struct Pc { transform: [f32; 16], color: [f32; 4], time: f32 }
+        impl_push_constants!(Pc, transform => vertex, color => vertex | fragment,
+                             time => fragment);
+
+        let members = Pc::members();
+        assert_eq!(members.len(), 3);
+        assert_eq!((members[1].offset, members[1].size), (64, 16));
+
+        let vertex = ShaderStages { vertex: true, .. ShaderStages::none() };
+        let fragment = ShaderStages { fragment: true, .. ShaderStages::none() };
+        assert_eq!(Pc::ranges(), vec![(0, 80, vertex), (64, 20, fragment)]);
+        assert!(<() as PushConstantsDesc>::ranges().is_empty());
+    }
+
+    #[test]
+    fn updates_split_on_ranges() {
+        let vertex = ShaderStages { vertex: true, .. ShaderStages::none() };
+        let fragment = ShaderStages { fragment: true, .. ShaderStages::none() };
+        let both = vertex.union(&fragment);
+
+        let updates = push_constants_updates(&[(0, 80, vertex), (64, 20, fragment)], 84);
+        assert_eq!(&updates[..], &[(0, 64, vertex), (64, 16, both), (80, 4, fragment)]);
+
+        let updates = push_constants_updates(&[(0, 16, ShaderStages::all())], 8);
+        assert_eq!(&updates[..], &[(0, 8, ShaderStages::all())]);
+
+        assert!(push_constants_updates(&[], 16).is_empty());
+    }
+}
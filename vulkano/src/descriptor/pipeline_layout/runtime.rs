@@ -0,0 +1,240 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::Arc;
+use std::vec::IntoIter as VecIntoIter;
+
+use descriptor::descriptor::DescriptorDesc;
+use descriptor::descriptor::ShaderStages;
+use descriptor::descriptor_set::UnsafeDescriptorSetLayout;
+use descriptor::pipeline_layout::PipelineLayout;
+use descriptor::pipeline_layout::PipelineLayoutDesc;
+use descriptor::pipeline_layout::UnsafePipelineLayout;
+use descriptor::pipeline_layout::UnsafePipelineLayoutCreationError;
+use device::Device;
+
+/// Implementation of `PipelineLayout` whose layout is only known at runtime.
+///
+/// This is the runtime equivalent of the `pipeline_layout!` macro. Descriptor sets for this
+/// layout can be built with `RuntimeDescriptorSet`.
+pub struct RuntimePipelineLayout {
+    inner: Arc<UnsafePipelineLayout>,
+}
+
+impl RuntimePipelineLayout {
+    /// Builds a new pipeline layout from a list of descriptor set layouts and push constant
+    /// ranges.
+    ///
+    /// # Panic
+    ///
+    /// Panics if one of the `UnsafeDescriptorSetLayout` was not created with `device`.
+    #[inline]
+    pub fn new<'a, I, P>(device: &Arc<Device>, layouts: I, push_constants: P)
+                         -> Result<Arc<RuntimePipelineLayout>, UnsafePipelineLayoutCreationError>
+        where I: IntoIterator<Item = &'a Arc<UnsafeDescriptorSetLayout>>,
+              P: IntoIterator<Item = (usize, usize, ShaderStages)>,
+    {
+        let inner = try!(UnsafePipelineLayout::shared(device, layouts, push_constants));
+
+        Ok(Arc::new(RuntimePipelineLayout {
+            inner: inner
+        }))
+    }
+
+    /// Builds a new pipeline layout from the list of descriptors of each set.
+    pub fn from_descriptors<S, P>(device: &Arc<Device>, sets: S, push_constants: P)
+                                  -> Result<Arc<RuntimePipelineLayout>,
+                                            UnsafePipelineLayoutCreationError>
+        where S: IntoIterator<Item = Vec<DescriptorDesc>>,
+              P: IntoIterator<Item = (usize, usize, ShaderStages)>,
+    {
+        let mut layouts = Vec::new();
+        for set in sets {
+            let layout = try!(UnsafeDescriptorSetLayout::raw(device.clone(), set));
+            layouts.push(Arc::new(layout));
+        }
+
+        RuntimePipelineLayout::new(device, layouts.iter(), push_constants)
+    }
+
+    /// Returns the layout of the descriptor set of the specified index.
+    ///
+    /// Returns `None` if out of range.
+    #[inline]
+    pub fn descriptor_set_layout(&self, index: usize) -> Option<&Arc<UnsafeDescriptorSetLayout>> {
+        self.inner.descriptor_set_layout(index)
+    }
+}
+
+unsafe impl PipelineLayout for RuntimePipelineLayout {
+    #[inline]
+    fn inner(&self) -> &UnsafePipelineLayout {
+        &self.inner
+    }
+}
+
+unsafe impl PipelineLayoutDesc for RuntimePipelineLayout {
+    type SetsIter = VecIntoIter<Self::DescIter>;
+    type DescIter = VecIntoIter<DescriptorDesc>;
+
+    fn descriptors_desc(&self) -> Self::SetsIter {
+        let mut sets = Vec::new();
+
+        let mut index = 0;
+        while let Some(layout) = self.inner.descriptor_set_layout(index) {
+            let mut descriptors = layout.descriptors().to_vec();
+            descriptors.sort_by_key(|desc| desc.binding);
+            sets.push(descriptors.into_iter());
+            index += 1;
+        }
+
+        sets.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+    use std::sync::Arc;
+
+    use descriptor::descriptor::DescriptorDesc;
+    use descriptor::descriptor::DescriptorDescTy;
+    use descriptor::descriptor::DescriptorImageDesc;
+    use descriptor::descriptor::DescriptorImageDescArray;
+    use descriptor::descriptor::DescriptorImageDescDimensions;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::descriptor_set::DescriptorCopy;
+    use descriptor::descriptor_set::DescriptorSetDesc;
+    use descriptor::descriptor_set::DescriptorWrite;
+    use descriptor::descriptor_set::RuntimeDescriptorSet;
+    use descriptor::descriptor_set::RuntimeDescriptorSetCreationError;
+    use descriptor::pipeline_layout::PipelineLayoutDesc;
+    use descriptor::pipeline_layout::RuntimePipelineLayout;
+    use format::Format;
+    use image::Dimensions;
+    use image::StorageImage;
+    use sampler::Sampler;
+
+    #[test]
+    fn layout_and_set() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let desc = DescriptorDesc {
+            binding: 0,
+            ty: DescriptorDescTy::Sampler,
+            array_count: 2,
+            stages: ShaderStages::all(),
+            readonly: true,
+        };
+
+        let layout = RuntimePipelineLayout::from_descriptors(&device, vec![vec![desc]],
+                                                             iter::empty()).unwrap();
+        assert_eq!(layout.descriptors_desc().count(), 1);
+
+        let sampler = Sampler::simple_repeat_linear(&device);
+        let set_layout = layout.descriptor_set_layout(0).unwrap();
+
+        let set = RuntimeDescriptorSet::new(set_layout, vec![
+            DescriptorWrite::sampler(0, &sampler),
+            DescriptorWrite::sampler(0, &sampler).array_element(1),
+        ]).unwrap();
+        assert_eq!(set.desc().len(), 1);
+
+        match RuntimeDescriptorSet::new(set_layout, vec![DescriptorWrite::sampler(1, &sampler)]) {
+            Err(RuntimeDescriptorSetCreationError::MissingBinding) => (),
+            _ => panic!()
+        }
+
+        let out_of_range = DescriptorWrite::sampler(0, &sampler).array_element(2);
+        match RuntimeDescriptorSet::new(set_layout, vec![out_of_range]) {
+            Err(RuntimeDescriptorSetCreationError::ArrayIndexOutOfRange) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn update_and_copy() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let desc = DescriptorDesc {
+            binding: 0,
+            ty: DescriptorDescTy::Sampler,
+            array_count: 2,
+            stages: ShaderStages::all(),
+            readonly: true,
+        };
+
+        let layout = RuntimePipelineLayout::from_descriptors(&device, vec![vec![desc]],
+                                                             iter::empty()).unwrap();
+        let set_layout = layout.descriptor_set_layout(0).unwrap();
+        let sampler = Sampler::simple_repeat_linear(&device);
+
+        let source = RuntimeDescriptorSet::new(set_layout, vec![
+            DescriptorWrite::sampler(0, &sampler),
+            DescriptorWrite::sampler(0, &sampler).array_element(1),
+        ]).unwrap();
+
+        let mut dest = RuntimeDescriptorSet::new(set_layout, vec![]).unwrap();
+        let dest = Arc::get_mut(&mut dest).unwrap();
+
+        dest.update(vec![DescriptorWrite::sampler(0, &sampler)]).unwrap();
+
+        let copy = DescriptorCopy {
+            source_binding: 0,
+            source_first_array_element: 0,
+            destination_binding: 0,
+            destination_first_array_element: 1,
+            count: 1,
+        };
+        dest.copy_from(&source, &[copy]).unwrap();
+
+        let too_many = DescriptorCopy { count: 2, .. copy };
+        match dest.copy_from(&source, &[too_many]) {
+            Err(RuntimeDescriptorSetCreationError::ArrayIndexOutOfRange) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn image_dimensions() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let desc = |dimensions| DescriptorDesc {
+            binding: 0,
+            ty: DescriptorDescTy::Image(DescriptorImageDesc {
+                sampled: false,
+                dimensions: dimensions,
+                format: None,
+                multisampled: false,
+                array_layers: DescriptorImageDescArray::NonArrayed,
+            }),
+            array_count: 1,
+            stages: ShaderStages::all(),
+            readonly: false,
+        };
+
+        let layout = RuntimePipelineLayout::from_descriptors(&device, vec![
+            vec![desc(DescriptorImageDescDimensions::ThreeDimensional)],
+            vec![desc(DescriptorImageDescDimensions::TwoDimensional)],
+        ], iter::empty()).unwrap();
+
+        let image = StorageImage::new(&device, Dimensions::Dim3d { width: 8, height: 8, depth: 8 },
+                                      Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        RuntimeDescriptorSet::new(layout.descriptor_set_layout(0).unwrap(),
+                                  vec![DescriptorWrite::storage_image(0, &image)]).unwrap();
+
+        match RuntimeDescriptorSet::new(layout.descriptor_set_layout(1).unwrap(),
+                                        vec![DescriptorWrite::storage_image(0, &image)])
+        {
+            Err(RuntimeDescriptorSetCreationError::WrongImageDimensions) => (),
+            _ => panic!()
+        }
+    }
+}
@@ -17,6 +17,7 @@ use smallvec::SmallVec;
 use check_errors;
 use Error;
 use OomError;
+use VulkanError;
 use VulkanObject;
 use VulkanPointers;
 use vk;
@@ -33,6 +34,7 @@ pub struct UnsafePipelineLayout {
     device: Arc<Device>,
     layout: vk::PipelineLayout,
     layouts: SmallVec<[Arc<UnsafeDescriptorSetLayout>; 16]>,
+    push_constants: SmallVec<[(usize, usize, ShaderStages); 8]>,
 }
 
 impl UnsafePipelineLayout {
@@ -51,6 +53,41 @@ impl UnsafePipelineLayout {
                                         push_constants.into_iter().collect())
     }
 
+    /// Same as `new`, but returns an existing pipeline layout of the device if there is one that
+    /// is identical to the one that would be created. In that case no new Vulkan object is
+    /// created.
+    ///
+    /// Sharing pipeline layouts between pipelines means that the descriptor sets and push
+    /// constants that are bound for a pipeline stay valid when switching to another.
+    ///
+    /// # Panic
+    ///
+    /// Panics if one of the `UnsafeDescriptorSetLayout` was not created with `device`.
+    #[inline]
+    pub fn shared<'a, I, P>(device: &Arc<Device>, layouts: I, push_constants: P)
+                            -> Result<Arc<UnsafePipelineLayout>, UnsafePipelineLayoutCreationError>
+        where I: IntoIterator<Item = &'a Arc<UnsafeDescriptorSetLayout>>,
+              P: IntoIterator<Item = (usize, usize, ShaderStages)>,
+    {
+        let layouts = layouts.into_iter().map(|l| {
+                                    assert_eq!(&**l.device() as *const Device,
+                                               &**device as *const Device);
+                                    l.clone()
+                                 }).collect::<SmallVec<[_; 16]>>();
+        let push_constants = push_constants.into_iter().collect::<SmallVec<[_; 8]>>();
+
+        // Only create a Vulkan object if the device doesn't already have an identical layout.
+        let existing = device.find_shared_pipeline_layout(|l| {
+            l.is_made_of(&layouts, &push_constants)
+        });
+        if let Some(existing) = existing {
+            return Ok(existing);
+        }
+
+        let layout = try!(UnsafePipelineLayout::new_inner(device, layouts, push_constants));
+        Ok(device.shared_pipeline_layout(layout))
+    }
+
     /// Same as `new` but won't be inlined.
     fn new_inner(device: &Arc<Device>, layouts: SmallVec<[Arc<UnsafeDescriptorSetLayout>; 16]>,
                  push_constants: SmallVec<[(usize, usize, ShaderStages); 8]>)
@@ -71,7 +108,27 @@ impl UnsafePipelineLayout {
             return Err(UnsafePipelineLayoutCreationError::MaxDescriptorSetsLimitExceeded);
         }
 
-        let push_constants = {
+        {
+            let uniform_buffers_dynamic = layouts.iter().fold(0, |total, l| {
+                total + l.descriptors_count().uniform_buffer_dynamic
+            });
+
+            if uniform_buffers_dynamic > limits.max_descriptor_set_uniform_buffers_dynamic() {
+                return Err(UnsafePipelineLayoutCreationError::
+                                                MaxDescriptorSetUniformBuffersDynamicLimitExceeded);
+            }
+
+            let storage_buffers_dynamic = layouts.iter().fold(0, |total, l| {
+                total + l.descriptors_count().storage_buffer_dynamic
+            });
+
+            if storage_buffers_dynamic > limits.max_descriptor_set_storage_buffers_dynamic() {
+                return Err(UnsafePipelineLayoutCreationError::
+                                                MaxDescriptorSetStorageBuffersDynamicLimitExceeded);
+            }
+        }
+
+        let push_constant_ranges = {
             let mut out: SmallVec<[_; 8]> = SmallVec::new();
 
             for pc in push_constants.iter() {
@@ -100,8 +157,8 @@ impl UnsafePipelineLayout {
                 flags: 0,   // reserved
                 setLayoutCount: layouts_ids.len() as u32,
                 pSetLayouts: layouts_ids.as_ptr(),
-                pushConstantRangeCount: push_constants.len() as u32,
-                pPushConstantRanges: push_constants.as_ptr(),
+                pushConstantRangeCount: push_constant_ranges.len() as u32,
+                pPushConstantRanges: push_constant_ranges.as_ptr(),
             };
 
             let mut output = mem::uninitialized();
@@ -114,6 +171,7 @@ impl UnsafePipelineLayout {
             device: device.clone(),
             layout: layout,
             layouts: layouts,
+            push_constants: push_constants,
         })
     }
 
@@ -125,11 +183,60 @@ impl UnsafePipelineLayout {
         self.layouts.get(index)
     }
 
+    /// Returns the push constant ranges of the layout, as `(offset, size, stages)`.
+    #[inline]
+    pub fn push_constant_ranges(&self) -> &[(usize, usize, ShaderStages)] {
+        &self.push_constants
+    }
+
     /// Returns the device used to create this pipeline layout.
     #[inline]
     pub fn device(&self) -> &Arc<Device> {
         &self.device
     }
+
+    /// Returns true if this layout is compatible with `other` for the set number `set`, as
+    /// defined by the Vulkan specs.
+    ///
+    /// Two pipeline layouts are compatible for set N if they were created with the same push
+    /// constant ranges and with identically defined descriptor set layouts for sets 0 to N.
+    /// When switching between two pipelines whose layouts are compatible for set N, the
+    /// descriptor sets 0 to N that are already bound stay valid.
+    ///
+    /// Returns false if `set` is out of range for one of the layouts.
+    pub fn is_compatible_for_set(&self, other: &UnsafePipelineLayout, set: u32) -> bool {
+        let set = set as usize;
+
+        if set >= self.layouts.len() || set >= other.layouts.len() {
+            return false;
+        }
+
+        if self as *const UnsafePipelineLayout == other as *const UnsafePipelineLayout {
+            return true;
+        }
+
+        if self.push_constants[..] != other.push_constants[..] {
+            return false;
+        }
+
+        self.layouts[.. set + 1].iter().zip(other.layouts[.. set + 1].iter())
+                                .all(|(a, b)| a.is_identical_to(b))
+    }
+
+    /// Returns true if `other` has the same push constant ranges and identically defined
+    /// descriptor set layouts as this layout.
+    pub fn is_identical_to(&self, other: &UnsafePipelineLayout) -> bool {
+        self.is_made_of(&other.layouts, &other.push_constants)
+    }
+
+    // Returns true if this layout has the same push constant ranges as `push_constants` and
+    // descriptor set layouts that are identical to `layouts`.
+    fn is_made_of(&self, layouts: &[Arc<UnsafeDescriptorSetLayout>],
+                  push_constants: &[(usize, usize, ShaderStages)]) -> bool
+    {
+        self.layouts.len() == layouts.len() && self.push_constants[..] == *push_constants &&
+        self.layouts.iter().zip(layouts.iter()).all(|(a, b)| a.is_identical_to(b))
+    }
 }
 
 unsafe impl VulkanObject for UnsafePipelineLayout {
@@ -158,6 +265,10 @@ pub enum UnsafePipelineLayoutCreationError {
     OomError(OomError),
     /// The maximum number of descriptor sets has been exceeded.
     MaxDescriptorSetsLimitExceeded,
+    /// The maximum number of dynamic uniform buffer descriptors has been exceeded.
+    MaxDescriptorSetUniformBuffersDynamicLimitExceeded,
+    /// The maximum number of dynamic storage buffer descriptors has been exceeded.
+    MaxDescriptorSetStorageBuffersDynamicLimitExceeded,
     /// The maximum size of push constants has been exceeded.
     MaxPushConstantsSizeExceeded,
     /// One of the push constants range didn't obey the rules. The list of stages must not be
@@ -175,6 +286,12 @@ impl error::Error for UnsafePipelineLayoutCreationError {
             UnsafePipelineLayoutCreationError::MaxDescriptorSetsLimitExceeded => {
                 "the maximum number of descriptor sets has been exceeded"
             },
+            UnsafePipelineLayoutCreationError::MaxDescriptorSetUniformBuffersDynamicLimitExceeded => {
+                "the maximum number of dynamic uniform buffer descriptors has been exceeded"
+            },
+            UnsafePipelineLayoutCreationError::MaxDescriptorSetStorageBuffersDynamicLimitExceeded => {
+                "the maximum number of dynamic storage buffer descriptors has been exceeded"
+            },
             UnsafePipelineLayoutCreationError::MaxPushConstantsSizeExceeded => {
                 "the maximum size of push constants has been exceeded"
             },
@@ -200,6 +317,16 @@ impl fmt::Display for UnsafePipelineLayoutCreationError {
     }
 }
 
+impl VulkanError for UnsafePipelineLayoutCreationError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            UnsafePipelineLayoutCreationError::OomError(ref err) => err.error_code(),
+            _ => None
+        }
+    }
+}
+
 impl From<OomError> for UnsafePipelineLayoutCreationError {
     #[inline]
     fn from(err: OomError) -> UnsafePipelineLayoutCreationError {
@@ -226,6 +353,8 @@ impl From<Error> for UnsafePipelineLayoutCreationError {
 mod tests {
     use std::iter;
     use std::sync::Arc;
+    use descriptor::descriptor::DescriptorDesc;
+    use descriptor::descriptor::DescriptorDescTy;
     use descriptor::descriptor::ShaderStages;
     use descriptor::descriptor_set::UnsafeDescriptorSetLayout;
     use descriptor::pipeline_layout::sys::UnsafePipelineLayout;
@@ -286,4 +415,44 @@ mod tests {
             _ => panic!()
         }
     }
+
+    #[test]
+    fn shared_deduplicates() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let push_constant = (0, 8, ShaderStages::all_graphics());
+
+        let layout1 = UnsafePipelineLayout::shared(&device, iter::empty(), Some(push_constant));
+        let layout2 = UnsafePipelineLayout::shared(&device, iter::empty(), Some(push_constant));
+        let layout3 = UnsafePipelineLayout::shared(&device, iter::empty(), iter::empty());
+        let (layout1, layout2, layout3) = (layout1.unwrap(), layout2.unwrap(), layout3.unwrap());
+
+        assert_eq!(&*layout1 as *const UnsafePipelineLayout,
+                   &*layout2 as *const UnsafePipelineLayout);
+        assert!(!layout1.is_identical_to(&layout3));
+    }
+
+    #[test]
+    fn compatible_for_set() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let desc = DescriptorDesc {
+            binding: 0,
+            ty: DescriptorDescTy::Sampler,
+            array_count: 1,
+            stages: ShaderStages::all_graphics(),
+            readonly: true,
+        };
+
+        let set1 = UnsafeDescriptorSetLayout::new(device.clone(), Some(desc));
+        let set2 = UnsafeDescriptorSetLayout::new(device.clone(), iter::empty());
+
+        let layout1 = UnsafePipelineLayout::new(&device, vec![&set1, &set1], iter::empty());
+        let layout2 = UnsafePipelineLayout::new(&device, vec![&set1, &set2], iter::empty());
+        let (layout1, layout2) = (layout1.unwrap(), layout2.unwrap());
+
+        assert!(layout1.is_compatible_for_set(&layout2, 0));
+        assert!(!layout1.is_compatible_for_set(&layout2, 1));
+        assert!(!layout1.is_compatible_for_set(&layout2, 2));
+    }
 }
@@ -7,14 +7,29 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::mem;
+
 use descriptor::descriptor::DescriptorDesc;
 use descriptor::descriptor_set::DescriptorSetsCollection;
 use descriptor::pipeline_layout::UnsafePipelineLayout;
+use descriptor::pipeline_layout::push_constants::push_constants_updates;
 
 /// Trait for objects that describe the layout of the descriptors and push constants of a pipeline.
 pub unsafe trait PipelineLayout: PipelineLayoutDesc + 'static + Send + Sync {
     /// Returns the inner `UnsafePipelineLayout`.
     fn inner(&self) -> &UnsafePipelineLayout;
+
+    /// Returns true if this layout is compatible with `other` for the set number `set`.
+    ///
+    /// Descriptor sets 0 to `set` that are bound for a pipeline that uses this layout stay valid
+    /// when switching to a pipeline that uses `other`. See
+    /// `UnsafePipelineLayout::is_compatible_for_set` for more info.
+    #[inline]
+    fn is_compatible_for_set<O: ?Sized>(&self, other: &O, set: u32) -> bool
+        where Self: Sized, O: PipelineLayout
+    {
+        self.inner().is_compatible_for_set(other.inner(), set)
+    }
 }
 
 /// Trait for objects that describe the layout of the descriptors and push constants of a pipeline.
@@ -105,8 +120,11 @@ unsafe impl<T, U> PipelineLayoutSetsCompatible<U> for T
     }
 }
 
-/// Traits that allow determining whether 
-// TODO: require a trait on Pc
+/// Traits that allow determining whether some push constants can be used with a pipeline layout.
+///
+/// The automatic implementation only checks that every byte of `Pc` is in one of the push
+/// constant ranges of the layout. Use `PushConstantsDesc` and
+/// `ShaderReflection::check_push_constants` to check the stages that access each field.
 pub unsafe trait PipelineLayoutPushConstantsCompatible<Pc>: PipelineLayout {
     /// Returns true if `Pc` can be used with a pipeline that uses `self` as layout.
     fn is_compatible(&self, &Pc) -> bool;
@@ -114,7 +132,12 @@ pub unsafe trait PipelineLayoutPushConstantsCompatible<Pc>: PipelineLayout {
 
 unsafe impl<T, U> PipelineLayoutPushConstantsCompatible<U> for T where T: PipelineLayout {
     fn is_compatible(&self, _: &U) -> bool {
-        // FIXME:
-        true
+        let size = mem::size_of::<U>();
+        if size == 0 {
+            return true;
+        }
+
+        let updates = push_constants_updates(self.inner().push_constant_ranges(), size);
+        updates.iter().map(|u| u.1).sum::<usize>() == size
     }
 }
@@ -81,16 +81,40 @@
 //! each element is a tuple containing a queue family and a number between 0.0 and 1.0 indicating
 //! the priority of execution of the queue relative to the others.
 //!
-//! TODO: write better doc here
+//! Instead of picking the queue families manually, you can describe the queues you need with
+//! `QueueRequest`s and let a `QueuesSelection` assign them to the families of the physical
+//! device.
 //!
 //! The `Device::new` function returns the newly-created device, but also the list of queues.
 //!
+//! # Buffer accesses
+//!
+//! Vulkano can't check all the accesses to buffers that the device performs, for example the
+//! vertices read through an index buffer or the accesses performed by shaders. By default the
+//! `robustBufferAccess` feature is always enabled so that these accesses can't read or write
+//! outside of their buffer. Use `Device::with_buffer_access_checks` to choose a different
+//! `BufferAccessChecks` policy.
+//!
+//! # Device loss
+//!
+//! The connection to the device can be lost, for example if a command takes too long to execute,
+//! if the driver crashes or if the hardware is removed. When this happens, the operations that
+//! submit commands or wait for them return a `DeviceLostError`, and the `Device` is marked as
+//! lost. After that, submitting to one of its queues or waiting for it fails immediately, which
+//! you can check with `Device::is_lost`.
+//!
+//! A lost device can't be recovered. The only way to continue is to drop all the objects that
+//! were created from the device, including the device itself, and to create a new device from
+//! the physical device.
+//!
 //! # Extended example
 //!
 //! TODO: write
 
 use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::collections::hash_map::Entry;
+use std::ffi::CString;
 use std::fmt;
 use std::error;
 use std::hash::BuildHasherDefault;
@@ -100,20 +124,41 @@ use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::MutexGuard;
 use std::sync::Weak;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
 use smallvec::SmallVec;
 use fnv::FnvHasher;
 
 use command_buffer::pool::StandardCommandPool;
+use descriptor::descriptor_set::StdDescriptorPool;
+use descriptor::pipeline_layout::UnsafePipelineLayout;
 use instance::Features;
 use instance::Instance;
 use instance::PhysicalDevice;
+use instance::PhysicalDeviceGroup;
 use instance::QueueFamily;
+use instance::debug::ObjectType;
+use instance::SupportedExtensionsError;
+use instrumentation::ResourceEvent;
+use instrumentation::ResourceStats;
+use instrumentation::ResourceTracker;
+use memory::DeviceMemoryAllocError;
+use memory::MemoryHeapStats;
+use memory::MemoryStats;
+use memory::MemoryStatsTracker;
 use memory::pool::StdMemoryPool;
+use sampler::SamplerCache;
+use swapchain::Surface;
+use sync::Fence;
+use sync::FenceWaitError;
 use sync::Semaphore;
 
 use Error;
 use OomError;
+use Success;
 use SynchronizedVulkanObject;
+use VulkanError;
 use VulkanObject;
 use VulkanPointers;
 use check_errors;
@@ -125,12 +170,26 @@ pub use instance::DeviceExtensions;
 pub struct Device {
     instance: Arc<Instance>,
     physical_device: usize,
+    // Indices of all the physical devices of the device group, starting with `physical_device`.
+    physical_devices: SmallVec<[usize; 4]>,
     device: vk::Device,
     vk: vk::DevicePointers,
     standard_pool: Mutex<Weak<StdMemoryPool>>,
     standard_command_pools: Mutex<HashMap<u32, Weak<StandardCommandPool>, BuildHasherDefault<FnvHasher>>>,
+    standard_descriptor_pool: Mutex<Weak<StdDescriptorPool>>,
+    shared_pipeline_layouts: Mutex<Vec<Weak<UnsafePipelineLayout>>>,
+    sampler_cache: SamplerCache,
     features: Features,
     extensions: DeviceExtensions,
+    // True if the `indexTypeUint8` feature of `ext_index_type_uint8` is enabled.
+    index_type_uint8: bool,
+    memory_stats: MemoryStatsTracker,
+    resources: ResourceTracker,
+    buffer_access_checks: BufferAccessChecks,
+    // True if Vulkan returned `VK_ERROR_DEVICE_LOST` for this device.
+    lost: AtomicBool,
+    // If false, the device was created outside of vulkano and must not be destroyed.
+    owned: bool,
 }
 
 // The `StandardCommandPool` type doesn't implement Send/Sync, so we have to manually reimplement
@@ -163,6 +222,64 @@ impl Device {
                       extensions: &DeviceExtensions, queue_families: I)
                       -> Result<(Arc<Device>, QueuesIter), DeviceCreationError>
         where I: IntoIterator<Item = (QueueFamily<'a>, f32)>
+    {
+        Device::new_impl(phys, &[*phys], requested_features, extensions, queue_families,
+                         BufferAccessChecks::Default)
+    }
+
+    /// Same as `new`, but with a custom policy for checking buffer accesses.
+    ///
+    /// With `BufferAccessChecks::Strict`, the `robust_buffer_access` feature is only enabled if
+    /// it is in `requested_features`.
+    ///
+    /// # Safety
+    ///
+    /// - If `checks` is `BufferAccessChecks::Strict` and `robust_buffer_access` isn't requested,
+    ///   the shaders that are used with this device must not access buffers out of bounds.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the queue families doesn't belong to the given device.
+    ///
+    pub unsafe fn with_buffer_access_checks<'a, I>(phys: &'a PhysicalDevice,
+                                                   requested_features: &Features,
+                                                   extensions: &DeviceExtensions,
+                                                   queue_families: I, checks: BufferAccessChecks)
+                                                   -> Result<(Arc<Device>, QueuesIter),
+                                                             DeviceCreationError>
+        where I: IntoIterator<Item = (QueueFamily<'a>, f32)>
+    {
+        Device::new_impl(phys, &[*phys], requested_features, extensions, queue_families, checks)
+    }
+
+    /// Builds a new Vulkan device that spans all the physical devices of a group.
+    ///
+    /// The physical devices of a group are identical, and the features and queue families are
+    /// those of the first physical device of the group. Use `DeviceMemory::alloc_with_device_mask`
+    /// and `UnsafeCommandBufferBuilder::set_device_mask` to choose on which physical devices
+    /// memory is allocated and commands are executed. These require the `khr_device_group`
+    /// extension to be enabled.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the queue families doesn't belong to the first physical device of the
+    ///   group.
+    ///
+    pub fn with_group<'a, I>(group: &PhysicalDeviceGroup<'a>, requested_features: &Features,
+                             extensions: &DeviceExtensions, queue_families: I)
+                             -> Result<(Arc<Device>, QueuesIter), DeviceCreationError>
+        where I: IntoIterator<Item = (QueueFamily<'a>, f32)>
+    {
+        let devices = group.physical_devices();
+        Device::new_impl(&devices[0], &devices, requested_features, extensions, queue_families,
+                         BufferAccessChecks::Default)
+    }
+
+    fn new_impl<'a, I>(phys: &PhysicalDevice<'a>, group: &[PhysicalDevice<'a>],
+                       requested_features: &Features, extensions: &DeviceExtensions,
+                       queue_families: I, checks: BufferAccessChecks)
+                       -> Result<(Arc<Device>, QueuesIter), DeviceCreationError>
+        where I: IntoIterator<Item = (QueueFamily<'a>, f32)>
     {
         let queue_families = queue_families.into_iter();
 
@@ -170,8 +287,52 @@ impl Device {
             return Err(DeviceCreationError::UnsupportedFeatures);
         }
 
+        let enabled_features = {
+            let mut features = requested_features.clone();
+            if checks != BufferAccessChecks::Strict {
+                features.robust_buffer_access = true;
+            }
+            features
+        };
+
+        {
+            let supported = match DeviceExtensions::supported_by_device_raw(phys) {
+                Ok(s) => s,
+                Err(SupportedExtensionsError::OomError(err)) => return Err(err.into()),
+                Err(SupportedExtensionsError::LoadingError(_)) => unreachable!(),
+            };
+
+            if !supported.superset_of(extensions) {
+                return Err(DeviceCreationError::MissingExtensions(extensions
+                                                                  .difference(&supported)));
+            }
+        }
+
         let vk_i = phys.instance().pointers();
 
+        // The `indexTypeUint8` feature of the `ext_index_type_uint8` extension can only be
+        // queried with `vkGetPhysicalDeviceFeatures2KHR`. It is enabled if it is supported.
+        let index_type_uint8 = extensions.ext_index_type_uint8 &&
+                               phys.instance().loaded_extensions()
+                                   .khr_get_physical_device_properties2 &&
+        unsafe {
+            let mut uint8_features = vk::PhysicalDeviceIndexTypeUint8FeaturesEXT {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_INDEX_TYPE_UINT8_FEATURES_EXT,
+                pNext: ptr::null_mut(),
+                indexTypeUint8: vk::FALSE,
+            };
+
+            let mut features = vk::PhysicalDeviceFeatures2KHR {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_FEATURES_2_KHR,
+                pNext: &mut uint8_features as *mut vk::PhysicalDeviceIndexTypeUint8FeaturesEXT
+                                            as *mut _,
+                features: mem::uninitialized(),
+            };
+
+            vk_i.GetPhysicalDeviceFeatures2KHR(phys.internal_object(), &mut features);
+            uint8_features.indexTypeUint8 != vk::FALSE
+        };
+
         // this variable will contain the queue family ID and queue ID of each requested queue
         let mut output_queues: SmallVec<[(u32, u32); 8]> = SmallVec::new();
 
@@ -211,7 +372,7 @@ impl Device {
                 if let Some(q) = queues.iter_mut().find(|q| q.0 == queue_family.id()) {
                     output_queues.push((queue_family.id(), q.1.len() as u32));
                     q.1.push(priority);
-                    if q.1.len() >= queue_family.queues_count() {
+                    if q.1.len() > queue_family.queues_count() {
                         return Err(DeviceCreationError::TooManyQueuesForFamily);
                     }
                     continue;
@@ -238,22 +399,70 @@ impl Device {
             //       `robustBufferAccess` enabled, an error is returned.
             //
             //       However for the moment this verification isn't performed. In order to be safe,
-            //       we always enable the `robustBufferAccess` feature as it is guaranteed to be
-            //       supported everywhere.
-            //
-            //       The only alternative (while waiting for shaders introspection to work) is to
-            //       make all shaders depend on `robustBufferAccess`. But since usually the
-            //       majority of shaders don't need this feature, it would be very annoying to have
-            //       to enable it manually when you don't need it.
-            let features = {
-                let mut features: vk::PhysicalDeviceFeatures = requested_features.clone().into();
-                features.robustBufferAccess = vk::TRUE;
-                features
+            //       we enable the `robustBufferAccess` feature as it is guaranteed to be supported
+            //       everywhere, unless the user explicitly opted out with
+            //       `BufferAccessChecks::Strict`.
+            let features: vk::PhysicalDeviceFeatures = enabled_features.clone().into();
+
+            // The `samplerYcbcrConversion` feature is supported by all the implementations of the
+            // `khr_sampler_ycbcr_conversion` extension, and is enabled along with it.
+            let ycbcr_features = vk::PhysicalDeviceSamplerYcbcrConversionFeaturesKHR {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_SAMPLER_YCBCR_CONVERSION_FEATURES_KHR,
+                pNext: ptr::null_mut(),
+                samplerYcbcrConversion: vk::TRUE,
+            };
+            let ycbcr_features_ptr = if extensions.khr_sampler_ycbcr_conversion {
+                &ycbcr_features as *const vk::PhysicalDeviceSamplerYcbcrConversionFeaturesKHR
+                                as *const _
+            } else {
+                ptr::null()
+            };
+
+            // Same for the `conditionalRendering` feature of the `ext_conditional_rendering`
+            // extension.
+            let conditional_features = vk::PhysicalDeviceConditionalRenderingFeaturesEXT {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_CONDITIONAL_RENDERING_FEATURES_EXT,
+                pNext: ycbcr_features_ptr as *mut _,
+                conditionalRendering: vk::TRUE,
+                inheritedConditionalRendering: vk::FALSE,
+            };
+            let conditional_features_ptr = if extensions.ext_conditional_rendering {
+                &conditional_features as *const vk::PhysicalDeviceConditionalRenderingFeaturesEXT
+                                      as *const _
+            } else {
+                ycbcr_features_ptr
+            };
+
+            let uint8_features = vk::PhysicalDeviceIndexTypeUint8FeaturesEXT {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_INDEX_TYPE_UINT8_FEATURES_EXT,
+                pNext: conditional_features_ptr as *mut _,
+                indexTypeUint8: vk::TRUE,
+            };
+            let features_ptr = if index_type_uint8 {
+                &uint8_features as *const vk::PhysicalDeviceIndexTypeUint8FeaturesEXT
+                                as *const _
+            } else {
+                conditional_features_ptr
+            };
+
+            // Groups of a single physical device are created like regular devices, which doesn't
+            // require the `khr_device_group_creation` extension.
+            let group_devices = group.iter().map(|dev| dev.internal_object())
+                                     .collect::<SmallVec<[_; 4]>>();
+            let group_infos = vk::DeviceGroupDeviceCreateInfoKHR {
+                sType: vk::STRUCTURE_TYPE_DEVICE_GROUP_DEVICE_CREATE_INFO_KHR,
+                pNext: features_ptr,
+                physicalDeviceCount: group_devices.len() as u32,
+                pPhysicalDevices: group_devices.as_ptr(),
             };
 
             let infos = vk::DeviceCreateInfo {
                 sType: vk::STRUCTURE_TYPE_DEVICE_CREATE_INFO,
-                pNext: ptr::null(),
+                pNext: if group_devices.len() >= 2 {
+                    &group_infos as *const vk::DeviceGroupDeviceCreateInfoKHR as *const _
+                } else {
+                    features_ptr
+                },
                 flags: 0,   // reserved
                 queueCreateInfoCount: queues.len() as u32,
                 pQueueCreateInfos: queues.as_ptr(),
@@ -270,38 +479,93 @@ impl Device {
             output
         };
 
+        let device = unsafe {
+            Device::from_raw_parts(phys, group, device, &enabled_features, extensions,
+                                   index_type_uint8, checks, true)
+        };
+
+        // Iterator for the produced queues.
+        let output_queues = QueuesIter {
+            next_queue: 0,
+            device: device.clone(),
+            families_and_ids: output_queues,
+        };
+
+        Ok((device, output_queues))
+    }
+
+    /// Builds a `Device` from a Vulkan device that was created outside of vulkano.
+    ///
+    /// This can be used to embed vulkano in an application or an engine that handles the
+    /// initialization of Vulkan itself. Use `Queue::from_handle` to build the queues of the
+    /// device. The Vulkan device is not destroyed when the `Device` is dropped.
+    ///
+    /// The device uses `BufferAccessChecks::Strict`, which means that vulkano performs additional
+    /// checks if `robust_buffer_access` isn't in `features`.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be a valid Vulkan device that was created from `phys`.
+    /// - `features` and `extensions` must be exactly the features and extensions that were
+    ///   enabled when creating `handle`.
+    /// - `handle` must stay alive for as long as the `Device` and all the objects created from
+    ///   it are alive.
+    /// - If `robust_buffer_access` isn't in `features`, the shaders that are used with this
+    ///   device must not access buffers out of bounds.
+    /// - If `ext_index_type_uint8` is in `extensions`, its `indexTypeUint8` feature must have
+    ///   been enabled as well.
+    ///
+    #[inline]
+    pub unsafe fn from_handle(phys: &PhysicalDevice, handle: vk::Device, features: &Features,
+                              extensions: &DeviceExtensions) -> Arc<Device>
+    {
+        Device::from_raw_parts(phys, &[*phys], handle, features, extensions,
+                               extensions.ext_index_type_uint8, BufferAccessChecks::Strict, false)
+    }
+
+    // Loads the function pointers of a Vulkan device and builds the `Device`.
+    unsafe fn from_raw_parts(phys: &PhysicalDevice, group: &[PhysicalDevice], device: vk::Device,
+                             features: &Features, extensions: &DeviceExtensions,
+                             index_type_uint8: bool, buffer_access_checks: BufferAccessChecks,
+                             owned: bool)
+                             -> Arc<Device>
+    {
+        let vk_i = phys.instance().pointers();
+
         // loading the function pointers of the newly-created device
         let vk = vk::DevicePointers::load(|name| {
-            unsafe { vk_i.GetDeviceProcAddr(device, name.as_ptr()) as *const _ }
+            vk_i.GetDeviceProcAddr(device, name.as_ptr()) as *const _
         });
 
-        let device = Arc::new(Device {
+        Arc::new(Device {
             instance: phys.instance().clone(),
             physical_device: phys.index(),
+            physical_devices: group.iter().map(|dev| dev.index()).collect(),
             device: device,
             vk: vk,
             standard_pool: Mutex::new(Weak::new()),
             standard_command_pools: Mutex::new(Default::default()),
-            features: requested_features.clone(),
+            standard_descriptor_pool: Mutex::new(Weak::new()),
+            shared_pipeline_layouts: Mutex::new(Vec::new()),
+            sampler_cache: SamplerCache::new(),
+            features: features.clone(),
             extensions: extensions.clone(),
-        });
-
-        // Iterator for the produced queues.
-        let output_queues = QueuesIter {
-            next_queue: 0,
-            device: device.clone(),
-            families_and_ids: output_queues,
-        };
-
-        Ok((device, output_queues))
+            index_type_uint8: index_type_uint8,
+            memory_stats: MemoryStatsTracker::new(phys.memory_types().len()),
+            resources: ResourceTracker::new(),
+            buffer_access_checks: buffer_access_checks,
+            lost: AtomicBool::new(false),
+            owned: owned,
+        })
     }
 
     /// See the docs of wait().
     // FIXME: must synchronize all queuees
     #[inline]
-    pub fn wait_raw(&self) -> Result<(), OomError> {
+    pub fn wait_raw(&self) -> Result<(), QueueError> {
+        try!(self.check_lost());
         unsafe {
-            try!(check_errors(self.vk.DeviceWaitIdle(self.device)));
+            try!(self.check_errors(self.vk.DeviceWaitIdle(self.device)));
             Ok(())
         }
     }
@@ -314,6 +578,7 @@ impl Device {
     /// # Panic
     ///
     /// - Panics if the device or host ran out of memory.
+    /// - Panics if the device has been lost.
     ///
     // FIXME: must synchronize all queuees
     #[inline]
@@ -333,6 +598,23 @@ impl Device {
         PhysicalDevice::from_index(&self.instance, self.physical_device).unwrap()
     }
 
+    /// Returns the physical devices of the device group this device was created with.
+    ///
+    /// For devices created with `Device::new`, this only contains `physical_device()`. The
+    /// position of a physical device in this list is the bit that represents it in device masks.
+    #[inline]
+    pub fn physical_devices(&self) -> Vec<PhysicalDevice> {
+        self.physical_devices.iter().map(|&index| {
+            PhysicalDevice::from_index(&self.instance, index).unwrap()
+        }).collect()
+    }
+
+    /// Returns the device mask that includes all the physical devices of the device.
+    #[inline]
+    pub fn all_devices_mask(&self) -> u32 {
+        (!0u32) >> (32 - self.physical_devices.len())
+    }
+
     /// Returns the features that are enabled in the device.
     #[inline]
     pub fn enabled_features(&self) -> &Features {
@@ -345,6 +627,146 @@ impl Device {
         &self.extensions
     }
 
+    /// Returns true if the `indexTypeUint8` feature of the `ext_index_type_uint8` extension is
+    /// enabled on the device, which is required in order to use `u8` indices.
+    #[inline]
+    pub fn index_type_uint8_enabled(&self) -> bool {
+        self.index_type_uint8
+    }
+
+    /// Returns true if Vulkan reported that the connection to the device has been lost.
+    ///
+    /// See the documentation of the `device` module for how to recover.
+    #[inline]
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Acquire)
+    }
+
+    /// Returns an error if the device has been lost.
+    #[inline]
+    pub fn check_lost(&self) -> Result<(), DeviceLostError> {
+        if self.is_lost() {
+            Err(DeviceLostError)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Same as the `check_errors` function, but also marks the device as lost if `result` is
+    /// `VK_ERROR_DEVICE_LOST`.
+    #[doc(hidden)]
+    #[inline]
+    pub fn check_errors(&self, result: vk::Result) -> Result<Success, Error> {
+        match check_errors(result) {
+            Err(Error::DeviceLost) => {
+                self.lost.store(true, Ordering::Release);
+                Err(Error::DeviceLost)
+            },
+            r => r
+        }
+    }
+
+    /// Returns the policy for checking buffer accesses that was chosen at device creation.
+    #[inline]
+    pub fn buffer_access_checks(&self) -> BufferAccessChecks {
+        self.buffer_access_checks
+    }
+
+    /// Returns statistics about the memory that is currently allocated on this device.
+    ///
+    /// The budget of each heap is only available if the `ext_memory_budget` extension is enabled
+    /// on the device and the `khr_get_physical_device_properties2` extension is enabled on the
+    /// instance.
+    pub fn memory_stats(&self) -> MemoryStats {
+        let physical_device = self.physical_device();
+        let memory_types = self.memory_stats.memory_types();
+
+        let mut heaps = vec![MemoryHeapStats::default(); physical_device.memory_heaps().len()];
+        for ty in physical_device.memory_types() {
+            let heap = &mut heaps[ty.heap().id() as usize];
+            heap.allocated += memory_types[ty.id() as usize].allocated;
+            heap.allocation_count += memory_types[ty.id() as usize].allocation_count;
+        }
+
+        if self.extensions.ext_memory_budget &&
+           self.instance.loaded_extensions().khr_get_physical_device_properties2
+        {
+            unsafe {
+                let vk_i = self.instance.pointers();
+
+                let mut budget = vk::PhysicalDeviceMemoryBudgetPropertiesEXT {
+                    sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_MEMORY_BUDGET_PROPERTIES_EXT,
+                    pNext: ptr::null_mut(),
+                    heapBudget: mem::uninitialized(),
+                    heapUsage: mem::uninitialized(),
+                };
+
+                let mut properties = vk::PhysicalDeviceMemoryProperties2KHR {
+                    sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_MEMORY_PROPERTIES_2_KHR,
+                    pNext: &mut budget as *mut vk::PhysicalDeviceMemoryBudgetPropertiesEXT as
+                           *mut _,
+                    memoryProperties: mem::uninitialized(),
+                };
+
+                vk_i.GetPhysicalDeviceMemoryProperties2KHR(physical_device.internal_object(),
+                                                           &mut properties);
+
+                for (id, heap) in heaps.iter_mut().enumerate() {
+                    heap.budget = Some(budget.heapBudget[id] as usize);
+                    heap.usage = Some(budget.heapUsage[id] as usize);
+                }
+            }
+        }
+
+        MemoryStats {
+            memory_types: memory_types,
+            heaps: heaps,
+        }
+    }
+
+    /// Returns the object that keeps track of the memory allocated on this device.
+    ///
+    /// `DeviceMemory` registers its allocations in it, and `memory_stats` reads from it. This
+    /// isn't meant to be used outside of vulkano.
+    #[doc(hidden)]
+    #[inline]
+    pub fn memory_stats_tracker(&self) -> &MemoryStatsTracker {
+        &self.memory_stats
+    }
+
+    /// Returns the number of buffers, images and pipelines that are currently alive on this
+    /// device. The number of memory allocations is returned by `memory_stats`.
+    ///
+    /// See the documentation of the `instrumentation` module for more info.
+    #[inline]
+    pub fn resource_stats(&self) -> ResourceStats {
+        self.resources.stats()
+    }
+
+    /// Registers a function that is called every time a buffer, an image, a memory allocation
+    /// or a pipeline is created or destroyed on this device. Replaces the previous function, if
+    /// any.
+    ///
+    /// The function is called from the thread that creates or destroys the resource.
+    #[inline]
+    pub fn set_resource_callback<F>(&self, callback: F)
+        where F: Fn(&ResourceEvent) + Send + Sync + 'static
+    {
+        self.resources.set_callback(Some(Arc::new(callback)));
+    }
+
+    /// Removes the function registered with `set_resource_callback`, if any.
+    #[inline]
+    pub fn remove_resource_callback(&self) {
+        self.resources.set_callback(None);
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn resource_tracker(&self) -> &ResourceTracker {
+        &self.resources
+    }
+
     /// Returns the standard memory pool used by default if you don't provide any other pool.
     pub fn standard_pool(me: &Arc<Self>) -> Arc<StdMemoryPool> {
         let mut pool = me.standard_pool.lock().unwrap();
@@ -386,6 +808,123 @@ impl Device {
             }
         }
     }
+
+    /// Returns the standard descriptor pool used by default if you don't provide any other pool.
+    pub fn standard_descriptor_pool(me: &Arc<Self>) -> Arc<StdDescriptorPool> {
+        let mut pool = me.standard_descriptor_pool.lock().unwrap();
+
+        if let Some(p) = pool.upgrade() {
+            return p;
+        }
+
+        // The weak pointer is empty, so we create the pool.
+        let new_pool = StdDescriptorPool::new(me);
+        *pool = Arc::downgrade(&new_pool);
+        new_pool
+    }
+
+    /// Returns the cache that deduplicates the samplers of this device.
+    ///
+    /// See the documentation of `SamplerCache` for more info.
+    #[inline]
+    pub fn sampler_cache(&self) -> &SamplerCache {
+        &self.sampler_cache
+    }
+
+    /// Gives a name to the device, that debugging tools and validation layers can display.
+    ///
+    /// Does nothing if the `VK_EXT_debug_marker` extension isn't enabled.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `name` contains a nul character.
+    ///
+    #[inline]
+    pub fn set_object_name(&self, name: &str) -> Result<(), OomError> {
+        unsafe { self.set_object_name_raw(ObjectType::Device, self.device as u64, name) }
+    }
+
+    /// Gives a name to an object of this device. Does nothing if the `VK_EXT_debug_marker`
+    /// extension isn't enabled.
+    ///
+    /// This is used to implement the `set_object_name` methods of the various objects.
+    ///
+    /// # Safety
+    ///
+    /// - `object` must be a handle of type `ty` that belongs to this device.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `name` contains a nul character.
+    ///
+    #[doc(hidden)]
+    pub unsafe fn set_object_name_raw(&self, ty: ObjectType, object: u64, name: &str)
+                                      -> Result<(), OomError>
+    {
+        if !self.extensions.ext_debug_marker {
+            return Ok(());
+        }
+
+        let name = CString::new(name).expect("The object name must not contain a nul character");
+
+        let infos = vk::DebugMarkerObjectNameInfoEXT {
+            sType: vk::STRUCTURE_TYPE_DEBUG_MARKER_OBJECT_NAME_INFO_EXT,
+            pNext: ptr::null(),
+            objectType: ty.to_raw(),
+            object: object,
+            pObjectName: name.as_ptr(),
+        };
+
+        try!(check_errors(self.vk.DebugMarkerSetObjectNameEXT(self.device, &infos)));
+        Ok(())
+    }
+
+    /// Returns a still-alive pipeline layout that was previously passed to
+    /// `shared_pipeline_layout` and for which `filter` returns true, if there is one.
+    ///
+    /// This is used by `UnsafePipelineLayout::shared` and isn't meant to be used outside of
+    /// vulkano.
+    #[doc(hidden)]
+    pub fn find_shared_pipeline_layout<F>(&self, mut filter: F)
+                                          -> Option<Arc<UnsafePipelineLayout>>
+        where F: FnMut(&UnsafePipelineLayout) -> bool
+    {
+        let shared = self.shared_pipeline_layouts.lock().unwrap();
+        shared.iter().filter_map(|l| l.upgrade()).find(|l| filter(l))
+    }
+
+    /// Looks for a still-alive pipeline layout that is identical to `layout` and that was
+    /// previously passed to this function. Returns it if there is one, otherwise stores `layout`
+    /// and returns it.
+    ///
+    /// This is used by `UnsafePipelineLayout::shared` and isn't meant to be used outside of
+    /// vulkano.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `layout` was not created with this device.
+    ///
+    #[doc(hidden)]
+    pub fn shared_pipeline_layout(&self, layout: UnsafePipelineLayout)
+                                  -> Arc<UnsafePipelineLayout>
+    {
+        assert_eq!(&**layout.device() as *const Device, self as *const Device);
+
+        let mut shared = self.shared_pipeline_layouts.lock().unwrap();
+
+        // Removing the layouts that have been destroyed in the meanwhile.
+        shared.retain(|l| l.upgrade().is_some());
+
+        for existing in shared.iter().filter_map(|l| l.upgrade()) {
+            if existing.is_identical_to(&layout) {
+                return existing;
+            }
+        }
+
+        let layout = Arc::new(layout);
+        shared.push(Arc::downgrade(&layout));
+        layout
+    }
 }
 
 impl fmt::Debug for Device {
@@ -416,6 +955,10 @@ impl VulkanPointers for Device {
 impl Drop for Device {
     #[inline]
     fn drop(&mut self) {
+        if !self.owned {
+            return;
+        }
+
         unsafe {
             self.vk.DeviceWaitIdle(self.device);
             self.vk.DestroyDevice(self.device, ptr::null());
@@ -451,6 +994,7 @@ impl Iterator for QueuesIter {
                 family: family,
                 id: id,
                 dedicated_semaphore: Mutex::new(None),
+                submissions: Mutex::new(SubmissionLedger::new()),
             }))
         }
     }
@@ -464,62 +1008,565 @@ impl Iterator for QueuesIter {
 
 impl ExactSizeIterator for QueuesIter {}
 
-/// Error that can be returned when creating a device.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub enum DeviceCreationError {
-    /// There is no memory available on the host (ie. the CPU, RAM, etc.).
-    OutOfHostMemory,
-    /// There is no memory available on the device (ie. video memory).
-    OutOfDeviceMemory,
-    /// Tried to create too many queues for a given family.
-    TooManyQueuesForFamily,
-    /// Some of the requested features are unsupported by the physical device.
-    UnsupportedFeatures,
-    /// The priority of one of the queues is out of the [0.0; 1.0] range.
-    PriorityOutOfRange,
+/// Describes a queue that the application needs, for use with `QueuesSelection`.
+///
+/// # Example
+///
+/// ```ignore
+/// let requests = [
+///     QueueRequest::graphics(1.0).with_present(&surface),
+///     QueueRequest::compute(0.5).dedicated(),
+///     QueueRequest::transfers(0.5).dedicated(),
+/// ];
+/// ```
+#[derive(Debug, Clone)]
+pub struct QueueRequest<'s> {
+    /// The queue must support graphics operations.
+    pub graphics: bool,
+    /// The queue must support compute operations.
+    pub compute: bool,
+    /// The queue must support transfer operations.
+    pub transfers: bool,
+    /// If `Some`, the queue must be able to present images on this surface.
+    pub present: Option<&'s Surface>,
+    /// Priority of the queue, between 0.0 and 1.0.
+    pub priority: f32,
+    /// If true, a queue family that supports as few operations other than the requested ones
+    /// is preferred. Use this to find the families dedicated to async compute or to transfers.
+    pub dedicated: bool,
 }
 
-impl error::Error for DeviceCreationError {
+impl<'s> QueueRequest<'s> {
+    /// Builds a request for a queue that supports graphics operations.
     #[inline]
-    fn description(&self) -> &str {
-        match *self {
-            DeviceCreationError::OutOfHostMemory => "no memory available on the host",
-            DeviceCreationError::OutOfDeviceMemory => {
-                "no memory available on the graphical device"
-            },
-            DeviceCreationError::TooManyQueuesForFamily => {
-                "tried to create too many queues for a given family"
-            },
-            DeviceCreationError::UnsupportedFeatures => {
-                "some of the requested features are unsupported by the physical device"
-            },
-            DeviceCreationError::PriorityOutOfRange => {
-                "the priority of one of the queues is out of the [0.0; 1.0] range"
-            },
+    pub fn graphics(priority: f32) -> QueueRequest<'s> {
+        QueueRequest {
+            graphics: true,
+            compute: false,
+            transfers: false,
+            present: None,
+            priority: priority,
+            dedicated: false,
         }
     }
-}
 
-impl fmt::Display for DeviceCreationError {
+    /// Builds a request for a queue that supports compute operations.
     #[inline]
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(fmt, "{}", error::Error::description(self))
+    pub fn compute(priority: f32) -> QueueRequest<'s> {
+        QueueRequest {
+            graphics: false,
+            compute: true,
+            transfers: false,
+            present: None,
+            priority: priority,
+            dedicated: false,
+        }
     }
-}
 
-impl From<Error> for DeviceCreationError {
+    /// Builds a request for a queue that supports transfer operations.
     #[inline]
-    fn from(err: Error) -> DeviceCreationError {
-        match err {
-            Error::OutOfHostMemory => DeviceCreationError::OutOfHostMemory,
-            Error::OutOfDeviceMemory => DeviceCreationError::OutOfDeviceMemory,
-            _ => panic!("Unexpected error value: {}", err as i32)
+    pub fn transfers(priority: f32) -> QueueRequest<'s> {
+        QueueRequest {
+            graphics: false,
+            compute: false,
+            transfers: true,
+            present: None,
+            priority: priority,
+            dedicated: false,
         }
     }
-}
 
-/// Represents a queue where commands can be submitted.
-// TODO: should use internal synchronization
+    /// Additionally requires that the queue can present on the given surface.
+    #[inline]
+    pub fn with_present(self, surface: &'s Surface) -> QueueRequest<'s> {
+        QueueRequest { present: Some(surface), .. self }
+    }
+
+    /// Prefers a queue family that doesn't support more than what is requested.
+    #[inline]
+    pub fn dedicated(self) -> QueueRequest<'s> {
+        QueueRequest { dedicated: true, .. self }
+    }
+
+    // Returns true if the family fulfills the requirements of the request.
+    fn matches(&self, family: &QueueFamily) -> Result<bool, OomError> {
+        // Graphics and compute queues implicitly support transfer operations.
+        let transfers = family.supports_transfers() || family.supports_graphics() ||
+                        family.supports_compute();
+
+        if (self.graphics && !family.supports_graphics()) ||
+           (self.compute && !family.supports_compute()) || (self.transfers && !transfers)
+        {
+            return Ok(false);
+        }
+
+        match self.present {
+            Some(surface) => surface.is_supported(family),
+            None => Ok(true),
+        }
+    }
+
+    // Returns the number of capabilities of the family that weren't requested. Lower is better
+    // for dedicated requests.
+    fn extra_capabilities(&self, family: &QueueFamily) -> u32 {
+        if !self.dedicated {
+            return 0;
+        }
+
+        let mut extra = 0;
+        if !self.graphics && family.supports_graphics() { extra += 2; }
+        if !self.compute && family.supports_compute() { extra += 1; }
+        extra
+    }
+}
+
+/// Assigns a list of `QueueRequest`s to the queue families of a physical device.
+///
+/// Each request gets its own queue if possible. When a physical device doesn't have enough
+/// queues, multiple requests share the same queue.
+///
+/// # Example
+///
+/// ```ignore
+/// let selection = try!(QueuesSelection::new(&physical, &requests));
+/// let (device, queues) = try!(Device::new(&physical, &features, &extensions,
+///                                         selection.families()));
+/// let queues = selection.assign(queues);
+/// let (graphics, compute, transfers) = (&queues[0], &queues[1], &queues[2]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct QueuesSelection<'a> {
+    physical_device: PhysicalDevice<'a>,
+    // Family and priority of each queue to create.
+    queues: SmallVec<[(u32, f32); 8]>,
+    // For each request, the index within `queues` of the queue that serves it.
+    assignments: SmallVec<[usize; 8]>,
+}
+
+impl<'a> QueuesSelection<'a> {
+    /// Assigns each request to a queue family of the physical device.
+    ///
+    /// Requests are processed in order, so the first ones have a better chance of getting a
+    /// queue of their own.
+    pub fn new(phys: &PhysicalDevice<'a>, requests: &[QueueRequest])
+               -> Result<QueuesSelection<'a>, QueuesSelectionError>
+    {
+        let mut used: SmallVec<[usize; 8]> = phys.queue_families().map(|_| 0).collect();
+        let mut queues: SmallVec<[(u32, f32); 8]> = SmallVec::new();
+        let mut assignments = SmallVec::new();
+
+        for (num, request) in requests.iter().enumerate() {
+            if request.priority < 0.0 || request.priority > 1.0 {
+                return Err(QueuesSelectionError::PriorityOutOfRange);
+            }
+
+            let mut candidates = Vec::new();
+            for family in phys.queue_families() {
+                if try!(request.matches(&family)) {
+                    candidates.push(family);
+                }
+            }
+
+            // `min_by_key` returns the first minimum, which keeps the enumeration order of
+            // families among equivalent candidates.
+            let free = candidates.iter().filter(|f| used[f.id() as usize] < f.queues_count())
+                                 .min_by_key(|f| request.extra_capabilities(f));
+
+            if let Some(family) = free {
+                used[family.id() as usize] += 1;
+                queues.push((family.id(), request.priority));
+                assignments.push(queues.len() - 1);
+                continue;
+            }
+
+            // All the queues of the suitable families are already taken. Share the last queue
+            // that was created for the best family.
+            let family = match candidates.iter().min_by_key(|f| request.extra_capabilities(f)) {
+                Some(f) => f,
+                None => return Err(QueuesSelectionError::NoSuitableQueueFamily(num)),
+            };
+
+            let shared = queues.iter().rposition(|&(id, _)| id == family.id()).unwrap();
+            if queues[shared].1 < request.priority {
+                queues[shared].1 = request.priority;
+            }
+            assignments.push(shared);
+        }
+
+        Ok(QueuesSelection {
+            physical_device: *phys,
+            queues: queues,
+            assignments: assignments,
+        })
+    }
+
+    /// Returns the list of queues to pass to `Device::new`.
+    #[inline]
+    pub fn families(&self) -> Vec<(QueueFamily<'a>, f32)> {
+        self.queues.iter().map(|&(id, priority)| {
+            (self.physical_device.queue_family_by_id(id).unwrap(), priority)
+        }).collect()
+    }
+
+    /// Returns the queue family that was chosen for the request at the given index.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `request` is out of range.
+    ///
+    #[inline]
+    pub fn family(&self, request: usize) -> QueueFamily<'a> {
+        let id = self.queues[self.assignments[request]].0;
+        self.physical_device.queue_family_by_id(id).unwrap()
+    }
+
+    /// Returns true if the request at the given index shares its queue with another request.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `request` is out of range.
+    ///
+    #[inline]
+    pub fn is_shared(&self, request: usize) -> bool {
+        let queue = self.assignments[request];
+        self.assignments.iter().filter(|&&q| q == queue).count() >= 2
+    }
+
+    /// Takes the queues returned by `Device::new` and returns one queue per request, in the
+    /// same order as the requests.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the queues weren't created from the list returned by `families()`.
+    ///
+    pub fn assign(&self, queues: QueuesIter) -> Vec<Arc<Queue>> {
+        let queues = queues.collect::<Vec<_>>();
+        assert_eq!(queues.len(), self.queues.len());
+        for (queue, &(family, _)) in queues.iter().zip(self.queues.iter()) {
+            assert_eq!(queue.family().id(), family);
+        }
+
+        self.assignments.iter().map(|&q| queues[q].clone()).collect()
+    }
+}
+
+/// Error that can be returned when selecting queues.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum QueuesSelectionError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The priority of one of the requests is out of the [0.0; 1.0] range.
+    PriorityOutOfRange,
+    /// No queue family can fulfill the request at the given index.
+    NoSuitableQueueFamily(usize),
+}
+
+impl error::Error for QueuesSelectionError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            QueuesSelectionError::OomError(_) => "not enough memory available",
+            QueuesSelectionError::PriorityOutOfRange => {
+                "the priority of one of the requests is out of the [0.0; 1.0] range"
+            },
+            QueuesSelectionError::NoSuitableQueueFamily(_) => {
+                "no queue family can fulfill one of the requests"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            QueuesSelectionError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for QueuesSelectionError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for QueuesSelectionError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            QueuesSelectionError::OomError(ref err) => err.error_code(),
+            _ => None
+        }
+    }
+}
+
+impl From<OomError> for QueuesSelectionError {
+    #[inline]
+    fn from(err: OomError) -> QueuesSelectionError {
+        QueuesSelectionError::OomError(err)
+    }
+}
+
+/// Policy that determines how the accesses to buffers performed by the device are checked.
+///
+/// The `robustBufferAccess` feature guarantees that out-of-bounds accesses to buffers don't
+/// access memory outside of the buffer. Some accesses can't be checked by vulkano, and therefore
+/// rely on this feature.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BufferAccessChecks {
+    /// The `robust_buffer_access` feature is always enabled, and vulkano performs all of its
+    /// checks. This is the default.
+    Default,
+
+    /// The `robust_buffer_access` feature is always enabled, and vulkano skips the checks that
+    /// this feature makes unnecessary.
+    ///
+    /// The number of vertices and instances of a draw command is determined by the largest
+    /// vertex buffer instead of the smallest one. The attributes read past the end of the
+    /// smaller buffers are either zero or values from inside the buffer.
+    Relaxed,
+
+    /// The `robust_buffer_access` feature is only enabled if requested. If it is disabled,
+    /// vulkano performs additional checks on the accesses it can't verify:
+    ///
+    /// - `draw_indexed` panics if an index of the type of the index buffer can be larger than
+    ///   the number of vertices in the vertex buffers.
+    /// - `draw_indirect` panics, as the content of the indirect buffer is unknown.
+    ///
+    Strict,
+}
+
+impl BufferAccessChecks {
+    /// Returns true if vulkano must perform its additional checks on a device with the given
+    /// features.
+    #[inline]
+    pub fn strict_checks(&self, features: &Features) -> bool {
+        *self == BufferAccessChecks::Strict && !features.robust_buffer_access
+    }
+}
+
+/// Error that can be returned when creating a device.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DeviceCreationError {
+    /// There is no memory available on the host (ie. the CPU, RAM, etc.).
+    OutOfHostMemory,
+    /// There is no memory available on the device (ie. video memory).
+    OutOfDeviceMemory,
+    /// Tried to create too many queues for a given family.
+    TooManyQueuesForFamily,
+    /// Some of the requested features are unsupported by the physical device.
+    UnsupportedFeatures,
+    /// The priority of one of the queues is out of the [0.0; 1.0] range.
+    PriorityOutOfRange,
+    /// The requested extensions that are not supported by the physical device.
+    MissingExtensions(DeviceExtensions),
+    /// The initialization of the device failed for implementation-specific reasons.
+    InitializationFailed,
+    /// Too many devices have been created on the physical device.
+    TooManyObjects,
+    /// The connection to the physical device has been lost.
+    DeviceLost,
+}
+
+impl error::Error for DeviceCreationError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            DeviceCreationError::OutOfHostMemory => "no memory available on the host",
+            DeviceCreationError::OutOfDeviceMemory => {
+                "no memory available on the graphical device"
+            },
+            DeviceCreationError::TooManyQueuesForFamily => {
+                "tried to create too many queues for a given family"
+            },
+            DeviceCreationError::UnsupportedFeatures => {
+                "some of the requested features are unsupported by the physical device"
+            },
+            DeviceCreationError::PriorityOutOfRange => {
+                "the priority of one of the queues is out of the [0.0; 1.0] range"
+            },
+            DeviceCreationError::MissingExtensions(_) => {
+                "some of the requested extensions are unsupported by the physical device"
+            },
+            DeviceCreationError::InitializationFailed => {
+                "the initialization of the device failed"
+            },
+            DeviceCreationError::TooManyObjects => {
+                "too many devices have been created on the physical device"
+            },
+            DeviceCreationError::DeviceLost => {
+                "the connection to the physical device has been lost"
+            },
+        }
+    }
+}
+
+impl fmt::Display for DeviceCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            DeviceCreationError::MissingExtensions(ref missing) => {
+                write!(fmt, "{}: {}", error::Error::description(self),
+                       missing.names().join(", "))
+            },
+            _ => write!(fmt, "{}", error::Error::description(self)),
+        }
+    }
+}
+
+impl From<Error> for DeviceCreationError {
+    #[inline]
+    fn from(err: Error) -> DeviceCreationError {
+        match err {
+            Error::OutOfHostMemory => DeviceCreationError::OutOfHostMemory,
+            Error::OutOfDeviceMemory => DeviceCreationError::OutOfDeviceMemory,
+            Error::InitializationFailed => DeviceCreationError::InitializationFailed,
+            Error::TooManyObjects => DeviceCreationError::TooManyObjects,
+            Error::DeviceLost => DeviceCreationError::DeviceLost,
+            _ => panic!("Unexpected error value: {}", err as i32)
+        }
+    }
+}
+
+impl From<OomError> for DeviceCreationError {
+    #[inline]
+    fn from(err: OomError) -> DeviceCreationError {
+        match err {
+            OomError::OutOfHostMemory => DeviceCreationError::OutOfHostMemory,
+            OomError::OutOfDeviceMemory => DeviceCreationError::OutOfDeviceMemory,
+        }
+    }
+}
+
+impl VulkanError for DeviceCreationError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            DeviceCreationError::OutOfHostMemory => Some(Error::OutOfHostMemory),
+            DeviceCreationError::OutOfDeviceMemory => Some(Error::OutOfDeviceMemory),
+            DeviceCreationError::InitializationFailed => Some(Error::InitializationFailed),
+            DeviceCreationError::TooManyObjects => Some(Error::TooManyObjects),
+            DeviceCreationError::DeviceLost => Some(Error::DeviceLost),
+            _ => None
+        }
+    }
+}
+
+/// Error that is returned when the connection to the device has been lost.
+///
+/// The device can't be used anymore. See the documentation of the `device` module.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DeviceLostError;
+
+impl error::Error for DeviceLostError {
+    #[inline]
+    fn description(&self) -> &str {
+        "the connection to the device has been lost"
+    }
+}
+
+impl fmt::Display for DeviceLostError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for DeviceLostError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        Some(Error::DeviceLost)
+    }
+}
+
+/// Error that can happen when submitting commands to a queue or waiting for them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum QueueError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The maximum number of memory allocations of the device has been reached.
+    TooManyObjects,
+    /// The connection to the device has been lost.
+    DeviceLost(DeviceLostError),
+}
+
+impl error::Error for QueueError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            QueueError::OomError(_) => "not enough memory",
+            QueueError::TooManyObjects => {
+                "the maximum number of memory allocations has been reached"
+            },
+            QueueError::DeviceLost(_) => "the connection to the device has been lost",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            QueueError::OomError(ref err) => Some(err),
+            QueueError::TooManyObjects => None,
+            QueueError::DeviceLost(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for QueueError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for QueueError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            QueueError::OomError(ref err) => err.error_code(),
+            QueueError::TooManyObjects => Some(Error::TooManyObjects),
+            QueueError::DeviceLost(ref err) => err.error_code(),
+        }
+    }
+}
+
+impl From<OomError> for QueueError {
+    #[inline]
+    fn from(err: OomError) -> QueueError {
+        QueueError::OomError(err)
+    }
+}
+
+impl From<DeviceMemoryAllocError> for QueueError {
+    #[inline]
+    fn from(err: DeviceMemoryAllocError) -> QueueError {
+        match err {
+            DeviceMemoryAllocError::OomError(err) => QueueError::OomError(err),
+            DeviceMemoryAllocError::TooManyObjects => QueueError::TooManyObjects,
+        }
+    }
+}
+
+impl From<DeviceLostError> for QueueError {
+    #[inline]
+    fn from(err: DeviceLostError) -> QueueError {
+        QueueError::DeviceLost(err)
+    }
+}
+
+impl From<Error> for QueueError {
+    #[inline]
+    fn from(err: Error) -> QueueError {
+        match err {
+            Error::DeviceLost => QueueError::DeviceLost(DeviceLostError),
+            err => QueueError::OomError(OomError::from(err)),
+        }
+    }
+}
+
+/// Represents a queue where commands can be submitted.
+// TODO: should use internal synchronization
 #[derive(Debug)]
 pub struct Queue {
     queue: Mutex<vk::Queue>,
@@ -534,9 +1581,41 @@ pub struct Queue {
     //
     // For more infos, see TODO: see what?
     dedicated_semaphore: Mutex<Option<Arc<Semaphore>>>,
+
+    // Submissions that may still be executing.
+    submissions: Mutex<SubmissionLedger>,
 }
 
 impl Queue {
+    /// Builds a `Queue` from a Vulkan queue that was retrieved outside of vulkano.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be the queue of `device` with index `id` within `family`.
+    /// - If other code outside of vulkano uses the queue, it must synchronize with vulkano.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `family` doesn't belong to the physical device of `device`.
+    /// - Panics if `id` is out of range for `family`.
+    ///
+    pub unsafe fn from_handle(device: &Arc<Device>, handle: vk::Queue, family: QueueFamily,
+                              id: u32) -> Arc<Queue>
+    {
+        assert_eq!(family.physical_device().internal_object(),
+                   device.physical_device().internal_object());
+        assert!((id as usize) < family.queues_count());
+
+        Arc::new(Queue {
+            queue: Mutex::new(handle),
+            device: device.clone(),
+            family: family.id(),
+            id: id,
+            dedicated_semaphore: Mutex::new(None),
+            submissions: Mutex::new(SubmissionLedger::new()),
+        })
+    }
+
     /// Returns the device this queue belongs to.
     #[inline]
     pub fn device(&self) -> &Arc<Device> {
@@ -557,13 +1636,77 @@ impl Queue {
 
     /// See the docs of wait().
     #[inline]
-    pub fn wait_raw(&self) -> Result<(), OomError> {
+    pub fn wait_raw(&self) -> Result<(), QueueError> {
+        self.wait_idle()
+    }
+
+    /// Waits until all work on this queue has finished.
+    ///
+    /// Contrary to `Device::wait()`, this doesn't wait for the other queues of the device, for
+    /// example a queue dedicated to async compute.
+    pub fn wait_idle(&self) -> Result<(), QueueError> {
+        try!(self.device.check_lost());
         unsafe {
             let vk = self.device.pointers();
             let queue = self.queue.lock().unwrap();
-            try!(check_errors(vk.QueueWaitIdle(*queue)));
-            Ok(())
+            try!(self.device.check_errors(vk.QueueWaitIdle(*queue)));
         }
+
+        self.submissions.lock().unwrap().in_flight.clear();
+        Ok(())
+    }
+
+    /// Returns the serial number of the last submission to this queue, or 0 if nothing has been
+    /// submitted yet. Submissions are numbered from 1 in the order in which they are made.
+    ///
+    /// The serial can then be passed to `is_complete` or `wait_for` to know whether everything
+    /// that was submitted up to that point has finished executing.
+    ///
+    /// Only the submissions made by vulkano with a fence are numbered. This includes command
+    /// buffers and futures, but not sparse bindings or presentations.
+    #[inline]
+    pub fn last_submission(&self) -> u64 {
+        self.submissions.lock().unwrap().next - 1
+    }
+
+    /// Returns true if all the submissions up to the one with the given serial number have
+    /// finished executing.
+    ///
+    /// Errors while checking the fences are treated as if the submissions were still executing.
+    pub fn is_complete(&self, serial: u64) -> bool {
+        let mut submissions = self.submissions.lock().unwrap();
+        submissions.cleanup();
+        submissions.in_flight.front().map(|&(s, _)| s > serial).unwrap_or(true)
+    }
+
+    /// Waits until all the submissions up to the one with the given serial number have
+    /// finished executing, or until `timeout` has elapsed.
+    ///
+    /// Contrary to `wait_idle`, the submissions made after that point aren't waited for.
+    pub fn wait_for(&self, serial: u64, timeout: Duration) -> Result<(), FenceWaitError> {
+        // The fences are cloned so that the lock isn't held while waiting.
+        let fences = {
+            let submissions = self.submissions.lock().unwrap();
+            submissions.in_flight.iter().take_while(|&&(s, _)| s <= serial)
+                       .map(|&(_, ref fence)| fence.clone()).collect::<Vec<_>>()
+        };
+
+        try!(Fence::multi_wait(fences.iter().map(|f| &**f), timeout));
+        self.submissions.lock().unwrap().cleanup();
+        Ok(())
+    }
+
+    /// Records a submission to this queue whose end is signaled by `fence`, and returns its
+    /// serial number.
+    #[doc(hidden)]
+    pub fn record_submission(&self, fence: Arc<Fence>) -> u64 {
+        let mut submissions = self.submissions.lock().unwrap();
+        submissions.cleanup();
+
+        let serial = submissions.next;
+        submissions.next += 1;
+        submissions.in_flight.push_back((serial, fence));
+        serial
     }
     
     /// Waits until all work on this queue has finished.
@@ -573,6 +1716,7 @@ impl Queue {
     /// # Panic
     ///
     /// - Panics if the device or host ran out of memory.
+    /// - Panics if the device has been lost.
     ///
     #[inline]
     pub fn wait(&self) {
@@ -602,6 +1746,34 @@ impl Queue {
     }
 }
 
+// Fences of the submissions to a queue that may still be executing, with their serial number.
+#[derive(Debug)]
+struct SubmissionLedger {
+    // Serial number of the next submission.
+    next: u64,
+    // Sorted by serial number.
+    in_flight: VecDeque<(u64, Arc<Fence>)>,
+}
+
+impl SubmissionLedger {
+    #[inline]
+    fn new() -> SubmissionLedger {
+        SubmissionLedger {
+            next: 1,
+            in_flight: VecDeque::new(),
+        }
+    }
+
+    // Removes the submissions at the front that have finished executing.
+    fn cleanup(&mut self) {
+        while self.in_flight.front().map(|&(_, ref f)| f.ready().unwrap_or(false))
+                                    .unwrap_or(false)
+        {
+            self.in_flight.pop_front();
+        }
+    }
+}
+
 unsafe impl SynchronizedVulkanObject for Queue {
     type Object = vk::Queue;
 
@@ -614,11 +1786,26 @@ unsafe impl SynchronizedVulkanObject for Queue {
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
+    use std::time::Duration;
+    use command_buffer::PrimaryCommandBufferBuilder;
+    use command_buffer::submit;
+    use device::BufferAccessChecks;
     use device::Device;
     use device::DeviceCreationError;
     use device::DeviceExtensions;
+    use device::DeviceLostError;
+    use device::Queue;
+    use device::QueueError;
+    use device::QueueRequest;
+    use device::QueuesSelection;
+    use device::QueuesSelectionError;
     use features::Features;
     use instance;
+    use Error;
+    use OomError;
+    use SynchronizedVulkanObject;
+    use VulkanError;
+    use VulkanObject;
 
     #[test]
     fn one_ref() {
@@ -626,6 +1813,176 @@ mod tests {
         assert!(Arc::get_mut(&mut device).is_some());
     }
 
+    #[test]
+    fn not_lost() {
+        let (device, queue) = gfx_dev_and_queue!();
+        assert!(!device.is_lost());
+        assert_eq!(device.check_lost(), Ok(()));
+        assert_eq!(queue.wait_raw(), Ok(()));
+    }
+
+    #[test]
+    fn submission_ledger() {
+        let (device, queue) = gfx_dev_and_queue!();
+        assert_eq!(queue.last_submission(), 0);
+        assert!(queue.is_complete(0));
+
+        let cb = PrimaryCommandBufferBuilder::new(&device, queue.family()).build();
+        submit(&cb, &queue).unwrap();
+        submit(&cb, &queue).unwrap();
+        assert_eq!(queue.last_submission(), 2);
+
+        queue.wait_for(1, Duration::from_secs(5)).unwrap();
+        assert!(queue.is_complete(1));
+
+        queue.wait_idle().unwrap();
+        assert!(queue.is_complete(2));
+    }
+
+    #[test]
+    fn queue_error_from_vk_error() {
+        assert_eq!(QueueError::from(Error::DeviceLost), QueueError::DeviceLost(DeviceLostError));
+        assert_eq!(QueueError::from(Error::OutOfHostMemory),
+                   QueueError::OomError(OomError::OutOfHostMemory));
+    }
+
+    #[test]
+    fn error_codes() {
+        let err = DeviceCreationError::from(Error::DeviceLost);
+        assert_eq!(err, DeviceCreationError::DeviceLost);
+        assert_eq!(err.error_code(), Some(Error::DeviceLost));
+        assert!(err.is_device_lost());
+        assert_eq!(DeviceCreationError::PriorityOutOfRange.error_code(), None);
+
+        let err = QueuesSelectionError::from(OomError::OutOfDeviceMemory);
+        assert_eq!(err.error_code(), Some(Error::OutOfDeviceMemory));
+        assert!(err.is_out_of_memory());
+        assert!(!err.is_device_lost());
+
+        assert_eq!(Error::DeviceLost.raw(), -4);
+    }
+
+    #[test]
+    fn from_handle() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let raw_device = unsafe {
+            Device::from_handle(&device.physical_device(), device.internal_object(),
+                                device.enabled_features(), device.loaded_extensions())
+        };
+        let raw_queue = unsafe {
+            Queue::from_handle(&raw_device, *queue.internal_object_guard(), queue.family(),
+                               queue.id_within_family())
+        };
+
+        raw_queue.wait();
+        drop(raw_queue);
+        drop(raw_device);
+
+        // Dropping the objects built from the handles must not have destroyed the device.
+        queue.wait();
+        device.wait();
+    }
+
+    #[test]
+    fn single_device_group() {
+        let instance = instance!();
+        let group = match instance::PhysicalDeviceGroup::enumerate(&instance).next() {
+            Some(g) => g,
+            None => return
+        };
+
+        let physical = group.physical_devices()[0];
+        let family = physical.queue_families().next().unwrap();
+        let (device, _) = Device::with_group(&group, &Features::none(),
+                                             &DeviceExtensions::none(),
+                                             Some((family, 1.0))).unwrap();
+
+        assert_eq!(device.physical_devices().len(), group.physical_devices().len());
+        assert_eq!(device.all_devices_mask().count_ones() as usize,
+                   group.physical_devices().len());
+    }
+
+    #[test]
+    fn buffer_access_checks() {
+        let instance = instance!();
+        let physical = match instance::PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return
+        };
+
+        let family = physical.queue_families().next().unwrap();
+
+        let (device, _) = Device::new(&physical, &Features::none(), &DeviceExtensions::none(),
+                                      Some((family, 1.0))).unwrap();
+        assert_eq!(device.buffer_access_checks(), BufferAccessChecks::Default);
+        assert!(device.enabled_features().robust_buffer_access);
+
+        let (device, _) = unsafe {
+            Device::with_buffer_access_checks(&physical, &Features::none(),
+                                              &DeviceExtensions::none(), Some((family, 1.0)),
+                                              BufferAccessChecks::Strict).unwrap()
+        };
+        assert_eq!(device.buffer_access_checks(), BufferAccessChecks::Strict);
+        assert!(!device.enabled_features().robust_buffer_access);
+    }
+
+    #[test]
+    fn strict_checks() {
+        let robust = Features { robust_buffer_access: true, .. Features::none() };
+
+        assert!(BufferAccessChecks::Strict.strict_checks(&Features::none()));
+        assert!(!BufferAccessChecks::Strict.strict_checks(&robust));
+        assert!(!BufferAccessChecks::Default.strict_checks(&Features::none()));
+        assert!(!BufferAccessChecks::Relaxed.strict_checks(&Features::none()));
+    }
+
+    #[test]
+    fn queues_selection() {
+        let instance = instance!();
+        let physical = match instance::PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return
+        };
+
+        let requests = [
+            QueueRequest::graphics(1.0),
+            QueueRequest::compute(0.5).dedicated(),
+            QueueRequest::transfers(0.5).dedicated(),
+        ];
+
+        let selection = match QueuesSelection::new(&physical, &requests) {
+            Ok(s) => s,
+            Err(QueuesSelectionError::NoSuitableQueueFamily(_)) => return,
+            Err(err) => panic!("{:?}", err),
+        };
+
+        let (_, queues) = Device::new(&physical, &Features::none(), &DeviceExtensions::none(),
+                                      selection.families()).unwrap();
+        let queues = selection.assign(queues);
+
+        assert_eq!(queues.len(), 3);
+        assert!(queues[0].family().supports_graphics());
+        assert!(queues[1].family().supports_compute());
+        for (num, queue) in queues.iter().enumerate() {
+            assert_eq!(queue.family().id(), selection.family(num).id());
+        }
+    }
+
+    #[test]
+    fn queues_selection_priority_out_of_range() {
+        let instance = instance!();
+        let physical = match instance::PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return
+        };
+
+        match QueuesSelection::new(&physical, &[QueueRequest::graphics(1.5)]) {
+            Err(QueuesSelectionError::PriorityOutOfRange) => (),
+            _ => panic!()
+        }
+    }
+
     #[test]
     fn too_many_queues() {
         let instance = instance!();
@@ -89,6 +89,40 @@ macro_rules! features {
                     )+
                 }
             }
+
+            /// Builds a `Features` that is the union of `self` and another `Features` object.
+            ///
+            /// The result's field will be true if it is true in `self` or in `other`. This can
+            /// be used to merge the features that an application requires with the optional
+            /// ones that are supported: `required.union(&optional.intersection(supported))`.
+            pub fn union(&self, other: &Features) -> Features {
+                Features {
+                    $(
+                        $name: self.$name || other.$name,
+                    )+
+                }
+            }
+
+            /// Builds a `Features` that contains the features of `self` that are not in `other`.
+            ///
+            /// For example `requested.difference(supported)` returns the features that are
+            /// missing.
+            pub fn difference(&self, other: &Features) -> Features {
+                Features {
+                    $(
+                        $name: self.$name && !other.$name,
+                    )+
+                }
+            }
+
+            /// Returns the names of the features that are true.
+            pub fn names(&self) -> Vec<&'static str> {
+                let mut list = Vec::new();
+                $(
+                    if self.$name { list.push(stringify!($name)); }
+                )+
+                list
+            }
         }
 
         #[doc(hidden)]
@@ -172,3 +206,20 @@ features!{
     variable_multisample_rate => variableMultisampleRate,
     inherited_queries => inheritedQueries,
 }
+
+#[cfg(test)]
+mod tests {
+    use features::Features;
+
+    #[test]
+    fn set_operations() {
+        let a = Features { geometry_shader: true, logic_op: true, .. Features::none() };
+        let b = Features { logic_op: true, dual_src_blend: true, .. Features::none() };
+
+        assert_eq!(a.union(&b).names(), vec!["geometry_shader", "dual_src_blend", "logic_op"]);
+        assert_eq!(a.intersection(&b).names(), vec!["logic_op"]);
+        assert_eq!(a.difference(&b).names(), vec!["geometry_shader"]);
+        assert!(a.union(&b).superset_of(&a));
+        assert!(!a.superset_of(&b));
+    }
+}
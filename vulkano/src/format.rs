@@ -101,8 +101,31 @@
 //!
 //! // TODO: storage formats
 //!
+//! Support for other formats can be queried at runtime with `Format::properties()`, or with
+//! one of the `supports_*` methods of `Format`.
+//!
+//! # YCbCr formats
+//!
+//! The formats whose name starts with `G8` or `G16` store YCbCr data, for example decoded video
+//! frames. They require the `khr_sampler_ycbcr_conversion` extension. Images of these formats
+//! can only be sampled through a `SamplerYcbcrConversion`, see the `sampler` module.
+//!
+//! The multi-planar formats store each component in a separate plane, whose chroma planes can
+//! be smaller than the luma plane. Each plane can be accessed individually as an image of the
+//! format returned by `plane_format()`, for example to upload it.
+//!
+//! Since the supported depth formats vary between implementations, `select_depth_format` can be
+//! used to pick the first supported format from a list of preferences, for example
+//! `DEPTH_FORMATS` or `DEPTH_STENCIL_FORMATS`.
+//!
+use std::mem;
 use std::vec::IntoIter as VecIntoIter;
+
+use features::Features;
+use instance::PhysicalDevice;
 use vk;
+use VulkanObject;
+use VulkanPointers;
 
 // TODO: add enumerations for color, depth, stencil and depthstencil formats
 
@@ -176,6 +199,20 @@ macro_rules! formats {
                     )+
                 }
             }
+
+            /// Returns true if the features that are required to use this format are enabled in
+            /// `features`.
+            ///
+            /// Only compressed formats require a feature, for example `texture_compression_bc`
+            /// for the BC formats.
+            #[inline]
+            pub fn is_enabled_by(&self, features: &Features) -> bool {
+                match *self {
+                    $(
+                        Format::$name => formats!(__inner_feature__ features $($f_ty)*),
+                    )+
+                }
+            }
         }
 
         $(
@@ -351,6 +388,9 @@ macro_rules! formats {
     (__inner_ty__ $name:ident depthstencil) => { FormatTy::DepthStencil };
     (__inner_ty__ $name:ident compressed=$f:tt) => { FormatTy::Compressed };
 
+    (__inner_feature__ $features:ident compressed=$f:tt) => { $features.$f };
+    (__inner_feature__ $features:ident $($f_ty:tt)*) => { true };
+
 
     (__inner_strongstorage__ $name:ident $ty:ty) => {
         unsafe impl StrongStorage for $name {
@@ -545,6 +585,342 @@ formats! {
     ASTC_12x10SrgbBlock => FORMAT_ASTC_12x10_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
     ASTC_12x12UnormBlock => FORMAT_ASTC_12x12_UNORM_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
     ASTC_12x12SrgbBlock => FORMAT_ASTC_12x12_SRGB_BLOCK [None] [compressed=texture_compression_astc_ldr] {},
+    G8B8G8R8_422Unorm => FORMAT_G8B8G8R8_422_UNORM_KHR [None] [float=4] {},
+    B8G8R8G8_422Unorm => FORMAT_B8G8R8G8_422_UNORM_KHR [None] [float=4] {},
+    G8_B8_R8_3Plane420Unorm => FORMAT_G8_B8_R8_3PLANE_420_UNORM_KHR [None] [float=3] {},
+    G8_B8R8_2Plane420Unorm => FORMAT_G8_B8R8_2PLANE_420_UNORM_KHR [None] [float=3] {},
+    G8_B8_R8_3Plane422Unorm => FORMAT_G8_B8_R8_3PLANE_422_UNORM_KHR [None] [float=3] {},
+    G8_B8R8_2Plane422Unorm => FORMAT_G8_B8R8_2PLANE_422_UNORM_KHR [None] [float=3] {},
+    G8_B8_R8_3Plane444Unorm => FORMAT_G8_B8_R8_3PLANE_444_UNORM_KHR [None] [float=3] {},
+    G16B16G16R16_422Unorm => FORMAT_G16B16G16R16_422_UNORM_KHR [None] [float=4] {},
+    B16G16R16G16_422Unorm => FORMAT_B16G16R16G16_422_UNORM_KHR [None] [float=4] {},
+    G16_B16_R16_3Plane420Unorm => FORMAT_G16_B16_R16_3PLANE_420_UNORM_KHR [None] [float=3] {},
+    G16_B16R16_2Plane420Unorm => FORMAT_G16_B16R16_2PLANE_420_UNORM_KHR [None] [float=3] {},
+    G16_B16_R16_3Plane422Unorm => FORMAT_G16_B16_R16_3PLANE_422_UNORM_KHR [None] [float=3] {},
+    G16_B16R16_2Plane422Unorm => FORMAT_G16_B16R16_2PLANE_422_UNORM_KHR [None] [float=3] {},
+    G16_B16_R16_3Plane444Unorm => FORMAT_G16_B16_R16_3PLANE_444_UNORM_KHR [None] [float=3] {},
+}
+
+impl Format {
+    /// Returns true if the color components of this format are in the sRGB color space.
+    ///
+    /// The values are converted from sRGB to linear when the image is sampled, and from linear to
+    /// sRGB when the image is written as a color attachment.
+    pub fn is_srgb(&self) -> bool {
+        match *self {
+            Format::R8Srgb | Format::R8G8Srgb | Format::R8G8B8Srgb | Format::B8G8R8Srgb |
+            Format::R8G8B8A8Srgb | Format::B8G8R8A8Srgb | Format::A8B8G8R8SrgbPack32 |
+            Format::BC1_RGBSrgbBlock | Format::BC1_RGBASrgbBlock | Format::BC2SrgbBlock |
+            Format::BC3SrgbBlock | Format::BC7SrgbBlock | Format::ETC2_R8G8B8SrgbBlock |
+            Format::ETC2_R8G8B8A1SrgbBlock | Format::ETC2_R8G8B8A8SrgbBlock |
+            Format::ASTC_4x4SrgbBlock | Format::ASTC_5x4SrgbBlock | Format::ASTC_5x5SrgbBlock |
+            Format::ASTC_6x5SrgbBlock | Format::ASTC_6x6SrgbBlock | Format::ASTC_8x5SrgbBlock |
+            Format::ASTC_8x6SrgbBlock | Format::ASTC_8x8SrgbBlock | Format::ASTC_10x5SrgbBlock |
+            Format::ASTC_10x6SrgbBlock | Format::ASTC_10x8SrgbBlock |
+            Format::ASTC_10x10SrgbBlock | Format::ASTC_12x10SrgbBlock |
+            Format::ASTC_12x12SrgbBlock => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the width and height in texels of a block of this format.
+    ///
+    /// Compressed formats store texels by blocks. For other formats, this returns `[1, 1]`.
+    pub fn block_dimensions(&self) -> [u32; 2] {
+        match *self {
+            Format::ASTC_5x4UnormBlock | Format::ASTC_5x4SrgbBlock => [5, 4],
+            Format::ASTC_5x5UnormBlock | Format::ASTC_5x5SrgbBlock => [5, 5],
+            Format::ASTC_6x5UnormBlock | Format::ASTC_6x5SrgbBlock => [6, 5],
+            Format::ASTC_6x6UnormBlock | Format::ASTC_6x6SrgbBlock => [6, 6],
+            Format::ASTC_8x5UnormBlock | Format::ASTC_8x5SrgbBlock => [8, 5],
+            Format::ASTC_8x6UnormBlock | Format::ASTC_8x6SrgbBlock => [8, 6],
+            Format::ASTC_8x8UnormBlock | Format::ASTC_8x8SrgbBlock => [8, 8],
+            Format::ASTC_10x5UnormBlock | Format::ASTC_10x5SrgbBlock => [10, 5],
+            Format::ASTC_10x6UnormBlock | Format::ASTC_10x6SrgbBlock => [10, 6],
+            Format::ASTC_10x8UnormBlock | Format::ASTC_10x8SrgbBlock => [10, 8],
+            Format::ASTC_10x10UnormBlock | Format::ASTC_10x10SrgbBlock => [10, 10],
+            Format::ASTC_12x10UnormBlock | Format::ASTC_12x10SrgbBlock => [12, 10],
+            Format::ASTC_12x12UnormBlock | Format::ASTC_12x12SrgbBlock => [12, 12],
+            Format::G8B8G8R8_422Unorm | Format::B8G8R8G8_422Unorm |
+            Format::G16B16G16R16_422Unorm | Format::B16G16R16G16_422Unorm => [2, 1],
+            f if f.ty() == FormatTy::Compressed => [4, 4],
+            _ => [1, 1],
+        }
+    }
+
+    /// Returns the size in bytes of a block of this format.
+    ///
+    /// For formats that aren't compressed, this is the same as `size()`. Returns `None` for
+    /// depth-stencil formats, whose size in memory isn't known.
+    pub fn block_size(&self) -> Option<usize> {
+        match *self {
+            Format::BC1_RGBUnormBlock | Format::BC1_RGBSrgbBlock | Format::BC1_RGBAUnormBlock |
+            Format::BC1_RGBASrgbBlock | Format::BC4UnormBlock | Format::BC4SnormBlock |
+            Format::ETC2_R8G8B8UnormBlock | Format::ETC2_R8G8B8SrgbBlock |
+            Format::ETC2_R8G8B8A1UnormBlock | Format::ETC2_R8G8B8A1SrgbBlock |
+            Format::EAC_R11UnormBlock | Format::EAC_R11SnormBlock => Some(8),
+            Format::G8B8G8R8_422Unorm | Format::B8G8R8G8_422Unorm => Some(4),
+            Format::G16B16G16R16_422Unorm | Format::B16G16R16G16_422Unorm => Some(8),
+            f if f.ty() == FormatTy::Compressed => Some(16),
+            f => f.size(),
+        }
+    }
+
+    /// Returns the number of bytes that a region of `extent` texels of this format occupies
+    /// when it is tightly packed in a buffer, for example when copying between a buffer and an
+    /// image.
+    ///
+    /// For compressed formats, the region is rounded up to a whole number of blocks. Returns
+    /// `None` if `block_size()` returns `None`.
+    pub fn packed_size(&self, extent: [u32; 3]) -> Option<usize> {
+        let block_size = match self.block_size() {
+            Some(s) => s,
+            None => return None,
+        };
+
+        let blocks = self.block_extent(extent);
+        Some(blocks[0] as usize * blocks[1] as usize * blocks[2] as usize * block_size)
+    }
+
+    /// Returns the number of blocks of this format in each dimension that are needed to cover
+    /// a region of `extent` texels.
+    ///
+    /// Partial blocks at the edges are counted as whole blocks. For formats that aren't
+    /// compressed, this returns `extent`.
+    #[inline]
+    pub fn block_extent(&self, extent: [u32; 3]) -> [u32; 3] {
+        let block = self.block_dimensions();
+        [(extent[0] + block[0] - 1) / block[0], (extent[1] + block[1] - 1) / block[1], extent[2]]
+    }
+
+    /// Returns true if this is a YCbCr format, which can only be sampled through a
+    /// `SamplerYcbcrConversion`.
+    pub fn is_ycbcr(&self) -> bool {
+        match *self {
+            Format::G8B8G8R8_422Unorm | Format::B8G8R8G8_422Unorm |
+            Format::G16B16G16R16_422Unorm | Format::B16G16R16G16_422Unorm => true,
+            f => f.num_planes() > 1,
+        }
+    }
+
+    /// Returns the number of planes of this format. This is 1 for all the formats that aren't
+    /// multi-planar.
+    pub fn num_planes(&self) -> u32 {
+        match *self {
+            Format::G8_B8_R8_3Plane420Unorm | Format::G8_B8_R8_3Plane422Unorm |
+            Format::G8_B8_R8_3Plane444Unorm | Format::G16_B16_R16_3Plane420Unorm |
+            Format::G16_B16_R16_3Plane422Unorm | Format::G16_B16_R16_3Plane444Unorm => 3,
+            Format::G8_B8R8_2Plane420Unorm | Format::G8_B8R8_2Plane422Unorm |
+            Format::G16_B16R16_2Plane420Unorm | Format::G16_B16R16_2Plane422Unorm => 2,
+            _ => 1,
+        }
+    }
+
+    /// Returns the format of the given plane of a multi-planar format. Views and copies that
+    /// only access this plane use this format.
+    ///
+    /// Returns `None` if the format isn't multi-planar or if `plane` is out of range.
+    pub fn plane_format(&self, plane: u32) -> Option<Format> {
+        if self.num_planes() == 1 || plane >= self.num_planes() {
+            return None;
+        }
+
+        let sixteen_bits = match *self {
+            Format::G16_B16_R16_3Plane420Unorm | Format::G16_B16R16_2Plane420Unorm |
+            Format::G16_B16_R16_3Plane422Unorm | Format::G16_B16R16_2Plane422Unorm |
+            Format::G16_B16_R16_3Plane444Unorm => true,
+            _ => false,
+        };
+
+        // The second plane of two-planar formats contains both chroma components.
+        Some(match (sixteen_bits, self.num_planes() == 2 && plane == 1) {
+            (false, false) => Format::R8Unorm,
+            (false, true) => Format::R8G8Unorm,
+            (true, false) => Format::R16Unorm,
+            (true, true) => Format::R16G16Unorm,
+        })
+    }
+
+    /// Returns the width and height in texels of the given plane of a multi-planar format, for
+    /// an image whose dimensions are `extent`.
+    ///
+    /// The chroma planes of `420` formats are half the width and height of the luma plane, and
+    /// those of `422` formats are half the width. Returns `extent` for the first plane and for
+    /// formats that aren't multi-planar.
+    pub fn plane_extent(&self, plane: u32, extent: [u32; 3]) -> [u32; 3] {
+        if plane == 0 || plane >= self.num_planes() {
+            return extent;
+        }
+
+        let (div_x, div_y) = match *self {
+            Format::G8_B8_R8_3Plane420Unorm | Format::G8_B8R8_2Plane420Unorm |
+            Format::G16_B16_R16_3Plane420Unorm | Format::G16_B16R16_2Plane420Unorm => (2, 2),
+            Format::G8_B8_R8_3Plane422Unorm | Format::G8_B8R8_2Plane422Unorm |
+            Format::G16_B16_R16_3Plane422Unorm | Format::G16_B16R16_2Plane422Unorm => (2, 1),
+            _ => (1, 1),
+        };
+
+        [(extent[0] + div_x - 1) / div_x, (extent[1] + div_y - 1) / div_y, extent[2]]
+    }
+
+    /// Queries the features that the physical device supports for this format.
+    pub fn properties(&self, physical_device: PhysicalDevice) -> FormatProperties {
+        let vk_i = physical_device.instance().pointers();
+
+        let output = unsafe {
+            let mut output = mem::uninitialized();
+            vk_i.GetPhysicalDeviceFormatProperties(physical_device.internal_object(),
+                                                   *self as u32, &mut output);
+            output
+        };
+
+        FormatProperties {
+            linear_tiling_features: FormatFeatures::from_bits(output.linearTilingFeatures),
+            optimal_tiling_features: FormatFeatures::from_bits(output.optimalTilingFeatures),
+            buffer_features: FormatFeatures::from_bits(output.bufferFeatures),
+        }
+    }
+
+    /// Returns true if images of this format with optimal tiling can be sampled with a linear
+    /// filter.
+    #[inline]
+    pub fn supports_sampled_linear_filtering(&self, physical_device: PhysicalDevice) -> bool {
+        let features = self.properties(physical_device).optimal_tiling_features;
+        features.sampled_image && features.sampled_image_filter_linear
+    }
+
+    /// Returns true if images of this format with optimal tiling can be used as color
+    /// attachments with blending enabled.
+    #[inline]
+    pub fn supports_color_attachment_blend(&self, physical_device: PhysicalDevice) -> bool {
+        let features = self.properties(physical_device).optimal_tiling_features;
+        features.color_attachment && features.color_attachment_blend
+    }
+
+    /// Returns true if images of this format with optimal tiling can be used as storage images.
+    #[inline]
+    pub fn supports_storage_image(&self, physical_device: PhysicalDevice) -> bool {
+        self.properties(physical_device).optimal_tiling_features.storage_image
+    }
+}
+
+/// Depth formats from the most to the least precise. `D16Unorm` is always supported.
+pub const DEPTH_FORMATS: [Format; 6] = [
+    Format::D32Sfloat,
+    Format::D32Sfloat_S8Uint,
+    Format::D24Unorm_S8Uint,
+    Format::X8_D24UnormPack32,
+    Format::D16Unorm,
+    Format::D16Unorm_S8Uint,
+];
+
+/// Depth-stencil formats from the most to the least precise. Either `D24Unorm_S8Uint` or
+/// `D32Sfloat_S8Uint` is always supported.
+pub const DEPTH_STENCIL_FORMATS: [Format; 3] = [
+    Format::D32Sfloat_S8Uint,
+    Format::D24Unorm_S8Uint,
+    Format::D16Unorm_S8Uint,
+];
+
+/// Returns the first format of `candidates` that the physical device supports as a
+/// depth-stencil attachment with optimal tiling, or `None` if none is supported.
+///
+/// # Panic
+///
+/// - Panics if one of the candidates isn't a depth, stencil or depth-stencil format.
+///
+/// # Example
+///
+/// ```no_run
+/// use vulkano::format;
+///
+/// # let physical: vulkano::instance::PhysicalDevice = unsafe { ::std::mem::uninitialized() };
+/// let depth_format = format::select_depth_format(physical, &format::DEPTH_FORMATS).unwrap();
+/// ```
+pub fn select_depth_format(physical_device: PhysicalDevice, candidates: &[Format])
+                           -> Option<Format>
+{
+    for &format in candidates {
+        match format.ty() {
+            FormatTy::Depth | FormatTy::Stencil | FormatTy::DepthStencil => (),
+            _ => panic!("{:?} isn't a depth or stencil format", format),
+        }
+
+        let features = format.properties(physical_device).optimal_tiling_features;
+        if features.depth_stencil_attachment {
+            return Some(format);
+        }
+    }
+
+    None
+}
+
+/// Features that a physical device supports for a format.
+///
+/// Returned by `Format::properties()`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FormatProperties {
+    /// Features supported for images with linear tiling.
+    pub linear_tiling_features: FormatFeatures,
+    /// Features supported for images with optimal tiling.
+    pub optimal_tiling_features: FormatFeatures,
+    /// Features supported for buffers.
+    pub buffer_features: FormatFeatures,
+}
+
+/// List of the ways a format can be used.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct FormatFeatures {
+    /// Can be used in a sampled image.
+    pub sampled_image: bool,
+    /// Can be used in a storage image.
+    pub storage_image: bool,
+    /// Can be used in a storage image with atomic operations.
+    pub storage_image_atomic: bool,
+    /// Can be used in a uniform texel buffer.
+    pub uniform_texel_buffer: bool,
+    /// Can be used in a storage texel buffer.
+    pub storage_texel_buffer: bool,
+    /// Can be used in a storage texel buffer with atomic operations.
+    pub storage_texel_buffer_atomic: bool,
+    /// Can be used as the format of a vertex attribute.
+    pub vertex_buffer: bool,
+    /// Can be used in a color attachment.
+    pub color_attachment: bool,
+    /// Can be used in a color attachment with blending enabled.
+    pub color_attachment_blend: bool,
+    /// Can be used in a depth-stencil attachment.
+    pub depth_stencil_attachment: bool,
+    /// Can be used as the source of a blit.
+    pub blit_src: bool,
+    /// Can be used as the destination of a blit.
+    pub blit_dst: bool,
+    /// Sampled images can be sampled with a linear filter.
+    pub sampled_image_filter_linear: bool,
+}
+
+impl FormatFeatures {
+    #[inline]
+    fn from_bits(val: vk::FormatFeatureFlags) -> FormatFeatures {
+        FormatFeatures {
+            sampled_image: (val & vk::FORMAT_FEATURE_SAMPLED_IMAGE_BIT) != 0,
+            storage_image: (val & vk::FORMAT_FEATURE_STORAGE_IMAGE_BIT) != 0,
+            storage_image_atomic: (val & vk::FORMAT_FEATURE_STORAGE_IMAGE_ATOMIC_BIT) != 0,
+            uniform_texel_buffer: (val & vk::FORMAT_FEATURE_UNIFORM_TEXEL_BUFFER_BIT) != 0,
+            storage_texel_buffer: (val & vk::FORMAT_FEATURE_STORAGE_TEXEL_BUFFER_BIT) != 0,
+            storage_texel_buffer_atomic:
+                (val & vk::FORMAT_FEATURE_STORAGE_TEXEL_BUFFER_ATOMIC_BIT) != 0,
+            vertex_buffer: (val & vk::FORMAT_FEATURE_VERTEX_BUFFER_BIT) != 0,
+            color_attachment: (val & vk::FORMAT_FEATURE_COLOR_ATTACHMENT_BIT) != 0,
+            color_attachment_blend: (val & vk::FORMAT_FEATURE_COLOR_ATTACHMENT_BLEND_BIT) != 0,
+            depth_stencil_attachment:
+                (val & vk::FORMAT_FEATURE_DEPTH_STENCIL_ATTACHMENT_BIT) != 0,
+            blit_src: (val & vk::FORMAT_FEATURE_BLIT_SRC_BIT) != 0,
+            blit_dst: (val & vk::FORMAT_FEATURE_BLIT_DST_BIT) != 0,
+            sampled_image_filter_linear:
+                (val & vk::FORMAT_FEATURE_SAMPLED_IMAGE_FILTER_LINEAR_BIT) != 0,
+        }
+    }
 }
 
 pub unsafe trait FormatDesc {
@@ -564,16 +940,9 @@ unsafe impl FormatDesc for Format {
     }
 
     fn decode_clear_value(&self, value: Self::ClearValue) -> ClearValue {
-        match (self.ty(), value) {
-            (FormatTy::Float, f @ ClearValue::Float(_)) => f,
-            (FormatTy::Compressed, f @ ClearValue::Float(_)) => f,
-            (FormatTy::Sint, f @ ClearValue::Int(_)) => f,
-            (FormatTy::Uint, f @ ClearValue::Uint(_)) => f,
-            (FormatTy::Depth, f @ ClearValue::Depth(_)) => f,
-            (FormatTy::Stencil, f @ ClearValue::Stencil(_)) => f,
-            (FormatTy::DepthStencil, f @ ClearValue::DepthStencil(_)) => f,
-            _ => panic!("Wrong clear value")
-        }
+        assert!(value.is_compatible_with(*self), "The clear value {:?} doesn't match the \
+                                                  format {:?}", value, self);
+        value
     }
 }
 
@@ -690,6 +1059,27 @@ pub enum ClearValue {
     DepthStencil((f32, u32)),
 }
 
+impl ClearValue {
+    /// Returns true if this value can be used to clear an image or an attachment of the given
+    /// format.
+    ///
+    /// `Float` values are for float and compressed formats, `Int` values for `Sint` formats and
+    /// `Uint` values for `Uint` formats. Depth and stencil values must match the aspects of the
+    /// format exactly. `None` isn't compatible with any format.
+    pub fn is_compatible_with(&self, format: Format) -> bool {
+        match (*self, format.ty()) {
+            (ClearValue::Float(_), FormatTy::Float) => true,
+            (ClearValue::Float(_), FormatTy::Compressed) => true,
+            (ClearValue::Int(_), FormatTy::Sint) => true,
+            (ClearValue::Uint(_), FormatTy::Uint) => true,
+            (ClearValue::Depth(_), FormatTy::Depth) => true,
+            (ClearValue::Stencil(_), FormatTy::Stencil) => true,
+            (ClearValue::DepthStencil(_), FormatTy::DepthStencil) => true,
+            _ => false,
+        }
+    }
+}
+
 // TODO: remove all these From implementations once they are no longer needed
 
 impl From<[f32; 1]> for ClearValue {
@@ -837,3 +1227,93 @@ macro_rules! impl_clear_values_tuple {
 }
 
 impl_clear_values_tuple!(A B C D E F G H I J K L M N O P Q R S T U V W X Y Z);
+
+#[cfg(test)]
+mod tests {
+    use format;
+    use format::ClearValue;
+    use format::Format;
+    use format::FormatDesc;
+
+    #[test]
+    fn packed_size() {
+        assert_eq!(Format::R8G8B8A8Unorm.packed_size([16, 16, 1]), Some(16 * 16 * 4));
+        assert_eq!(Format::BC1_RGBUnormBlock.packed_size([16, 16, 1]), Some(4 * 4 * 8));
+        assert_eq!(Format::BC3UnormBlock.packed_size([2, 2, 1]), Some(16));
+        assert_eq!(Format::ASTC_12x10UnormBlock.packed_size([13, 10, 1]), Some(2 * 16));
+        assert_eq!(Format::D24Unorm_S8Uint.packed_size([16, 16, 1]), None);
+    }
+
+    #[test]
+    fn clear_value_compatibility() {
+        assert!(ClearValue::Float([0.0; 4]).is_compatible_with(Format::R8G8B8A8Unorm));
+        assert!(ClearValue::Uint([0; 4]).is_compatible_with(Format::R32Uint));
+        assert!(ClearValue::Int([0; 4]).is_compatible_with(Format::R32Sint));
+        assert!(ClearValue::DepthStencil((1.0, 0)).is_compatible_with(Format::D24Unorm_S8Uint));
+
+        assert!(!ClearValue::Float([0.0; 4]).is_compatible_with(Format::R32Uint));
+        assert!(!ClearValue::Uint([0; 4]).is_compatible_with(Format::R32Sint));
+        assert!(!ClearValue::Depth(1.0).is_compatible_with(Format::R8G8B8A8Unorm));
+        assert!(!ClearValue::None.is_compatible_with(Format::R8G8B8A8Unorm));
+    }
+
+    #[test]
+    #[should_panic]
+    fn decode_wrong_clear_value() {
+        Format::R32Uint.decode_clear_value(ClearValue::Float([0.0; 4]));
+    }
+
+    #[test]
+    fn planes() {
+        assert_eq!(Format::R8G8B8A8Unorm.num_planes(), 1);
+        assert_eq!(Format::R8G8B8A8Unorm.plane_format(0), None);
+        assert!(!Format::R8G8B8A8Unorm.is_ycbcr());
+
+        let nv12 = Format::G8_B8R8_2Plane420Unorm;
+        assert!(nv12.is_ycbcr());
+        assert_eq!(nv12.num_planes(), 2);
+        assert_eq!(nv12.plane_format(0), Some(Format::R8Unorm));
+        assert_eq!(nv12.plane_format(1), Some(Format::R8G8Unorm));
+        assert_eq!(nv12.plane_format(2), None);
+        assert_eq!(nv12.plane_extent(0, [1920, 1081, 1]), [1920, 1081, 1]);
+        assert_eq!(nv12.plane_extent(1, [1920, 1081, 1]), [960, 541, 1]);
+
+        let yuv422 = Format::G16_B16_R16_3Plane422Unorm;
+        assert_eq!(yuv422.plane_format(2), Some(Format::R16Unorm));
+        assert_eq!(yuv422.plane_extent(2, [64, 64, 1]), [32, 64, 1]);
+
+        assert!(Format::G8B8G8R8_422Unorm.is_ycbcr());
+        assert_eq!(Format::G8B8G8R8_422Unorm.packed_size([4, 2, 1]), Some(16));
+    }
+
+    #[test]
+    fn block_extent() {
+        assert_eq!(Format::R8G8B8A8Unorm.block_extent([7, 5, 3]), [7, 5, 3]);
+        assert_eq!(Format::BC1_RGBUnormBlock.block_extent([7, 5, 3]), [2, 2, 3]);
+        assert_eq!(Format::ASTC_10x6UnormBlock.block_extent([1, 1, 1]), [1, 1, 1]);
+    }
+
+    #[test]
+    fn properties() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        // Guaranteed by the specifications.
+        let physical = device.physical_device();
+        assert!(Format::R8G8B8A8Unorm.supports_sampled_linear_filtering(physical));
+        assert!(Format::R8G8B8A8Unorm.supports_color_attachment_blend(physical));
+        assert!(Format::R8G8B8A8Unorm.supports_storage_image(physical));
+        assert!(Format::R8G8B8A8Unorm.properties(physical).buffer_features.vertex_buffer);
+    }
+
+    #[test]
+    fn select_depth_format() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let physical = device.physical_device();
+        assert!(format::select_depth_format(physical, &format::DEPTH_FORMATS).is_some());
+        assert!(format::select_depth_format(physical, &format::DEPTH_STENCIL_FORMATS).is_some());
+        assert_eq!(format::select_depth_format(physical, &[Format::D16Unorm]),
+                   Some(Format::D16Unorm));
+        assert_eq!(format::select_depth_format(physical, &[]), None);
+    }
+}
@@ -20,12 +20,14 @@ use framebuffer::RenderPassAttachmentsList;
 use framebuffer::RenderPassCompatible;
 use framebuffer::UnsafeRenderPass;
 use framebuffer::traits::Framebuffer as FramebufferTrait;
+use instance::debug::ObjectType;
 use image::Layout as ImageLayout;
 use image::traits::Image;
 use image::traits::ImageView;
 
 use Error;
 use OomError;
+use VulkanError;
 use VulkanObject;
 use VulkanPointers;
 use check_errors;
@@ -77,12 +79,14 @@ impl<L> Framebuffer<L> {
             let mut ids = SmallVec::<[_; 8]>::new();
 
             for &(ref a, _, _, _) in attachments.iter() {
-                debug_assert!(a.identity_swizzle());
+                if !a.identity_swizzle() {
+                    return Err(FramebufferCreationError::AttachmentNotIdentitySwizzled);
+                }
                 // TODO: add more checks with debug_assert!
 
-                let atch_dims = a.parent().dimensions();
+                let atch_dims = a.dimensions();
                 if atch_dims.width() < dimensions[0] || atch_dims.height() < dimensions[1] ||
-                   atch_dims.array_layers() < dimensions[2]      // TODO: wrong, since it must be the array layers of the view and not of the image
+                   atch_dims.array_layers_with_cube() < dimensions[2]
                 {
                     return Err(FramebufferCreationError::AttachmentTooSmall);
                 }
@@ -162,6 +166,21 @@ impl<L> Framebuffer<L> {
         &self.device
     }
 
+    /// Gives a name to the framebuffer, that debugging tools and validation layers can display.
+    ///
+    /// Does nothing if the `VK_EXT_debug_marker` extension isn't enabled on the device.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `name` contains a nul character.
+    ///
+    #[inline]
+    pub fn set_object_name(&self, name: &str) -> Result<(), OomError> {
+        unsafe {
+            self.device.set_object_name_raw(ObjectType::Framebuffer, self.framebuffer as u64, name)
+        }
+    }
+
     /// Returns the renderpass that was used to create this framebuffer.
     #[inline]
     pub fn render_pass(&self) -> &Arc<L> {
@@ -221,6 +240,9 @@ pub enum FramebufferCreationError {
     AttachmentNotIdentitySwizzled,
     /// One of the attachments is too small compared to the requested framebuffer dimensions.
     AttachmentTooSmall,
+    /// The number of samples of one of the attachments doesn't match the number of samples of
+    /// the corresponding attachment of the render pass.
+    AttachmentSamplesMismatch,
 }
 
 impl From<OomError> for FramebufferCreationError {
@@ -244,6 +266,9 @@ impl error::Error for FramebufferCreationError {
                 "one of the attachments is too small compared to the requested framebuffer \
                  dimensions"
             },
+            FramebufferCreationError::AttachmentSamplesMismatch => {
+                "the number of samples of one of the attachments doesn't match the render pass"
+            },
         }
     }
 
@@ -263,6 +288,16 @@ impl fmt::Display for FramebufferCreationError {
     }
 }
 
+impl VulkanError for FramebufferCreationError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            FramebufferCreationError::OomError(ref err) => err.error_code(),
+            _ => None
+        }
+    }
+}
+
 impl From<Error> for FramebufferCreationError {
     #[inline]
     fn from(err: Error) -> FramebufferCreationError {
@@ -343,4 +378,25 @@ mod tests {
             _ => panic!()
         }
     }
+
+    #[test]
+    fn multisampled() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let render_pass = example::CustomRenderPass::new(&device, &example::Formats {
+            color: (R8G8B8A8Unorm, 4)
+        }).unwrap();
+
+        let image = AttachmentImage::transient_multisampled(&device, [512, 512], 4,
+                                                            R8G8B8A8Unorm).unwrap();
+        let _ = Framebuffer::new(&render_pass, [512, 512, 1], example::AList {
+            color: &image
+        }).unwrap();
+
+        let image = AttachmentImage::new(&device, [512, 512], R8G8B8A8Unorm).unwrap();
+        match Framebuffer::new(&render_pass, [512, 512, 1], example::AList { color: &image }) {
+            Err(FramebufferCreationError::AttachmentSamplesMismatch) => (),
+            _ => panic!()
+        }
+    }
 }
@@ -55,7 +55,6 @@ macro_rules! ordered_passes_renderpass {
             ),*
         ]
     ) => {
-        use std::vec::IntoIter as VecIntoIter;
         use std::sync::Arc;
         use $crate::device::Device;
         use $crate::format::ClearValue;
@@ -64,6 +63,7 @@ macro_rules! ordered_passes_renderpass {
         use $crate::framebuffer::RenderPassDesc;
         use $crate::framebuffer::RenderPassClearValues;
         use $crate::framebuffer::RenderPassAttachmentsList;
+        use $crate::framebuffer::AttachmentsVec;
         use $crate::framebuffer::LayoutAttachmentDescription;
         use $crate::framebuffer::LayoutPassDescription;
         use $crate::framebuffer::LayoutPassDependencyDescription;
@@ -364,14 +364,13 @@ macro_rules! ordered_passes_renderpass {
         #[allow(non_camel_case_types)]
         #[allow(unsafe_code)]
         unsafe impl<'a, $($atch_name: 'static + ImageView),*> RenderPassAttachmentsList<AList<'a, $($atch_name),*>> for CustomRenderPass {
-            // TODO: shouldn't build a Vec
-            type AttachmentsIter = VecIntoIter<(Arc<ImageView>, Arc<Image>, Layout, Layout)>;
+            type AttachmentsIter = <AttachmentsVec as IntoIterator>::IntoIter;
 
             #[inline]
             fn convert_attachments_list(&self, l: AList<'a, $($atch_name),*>) -> Result<Self::AttachmentsIter, FramebufferCreationError> {
                 #![allow(unused_assignments)]
 
-                let mut result = Vec::new();
+                let mut result = AttachmentsVec::new();
 
                 let mut num = 0;
                 $({
@@ -379,7 +378,11 @@ macro_rules! ordered_passes_renderpass {
                         return Err(FramebufferCreationError::AttachmentNotIdentitySwizzled);
                     }
 
-                    // FIXME: lots of checks missing (format, samples, layout, etc.)
+                    if l.$atch_name.samples() != (self.formats.$atch_name).1 {
+                        return Err(FramebufferCreationError::AttachmentSamplesMismatch);
+                    }
+
+                    // FIXME: lots of checks missing (format, layout, etc.)
 
                     let (initial_layout, final_layout) = attachment_layouts(num);
                     num += 1;
@@ -74,9 +74,12 @@ pub use self::sys::RenderPassCreationError;
 pub use self::traits::RenderPass;
 pub use self::traits::RenderPassDesc;
 pub use self::traits::RenderPassAttachmentsList;
+#[doc(hidden)]
+pub use self::traits::AttachmentsVec;
 pub use self::traits::RenderPassClearValues;
 pub use self::traits::RenderPassCompatible;
 pub use self::traits::RenderPassSubpassInterface;
+pub use self::traits::FragmentOutputMismatchError;
 pub use self::traits::LayoutAttachmentDescription;
 pub use self::traits::LayoutPassDescription;
 pub use self::traits::LayoutPassDependencyDescription;
@@ -24,6 +24,7 @@ use framebuffer::LoadOp;
 
 use Error;
 use OomError;
+use VulkanError;
 use VulkanObject;
 use VulkanPointers;
 use check_errors;
@@ -367,6 +368,16 @@ impl fmt::Display for RenderPassCreationError {
     }
 }
 
+impl VulkanError for RenderPassCreationError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            RenderPassCreationError::OomError(ref err) => err.error_code(),
+            _ => None
+        }
+    }
+}
+
 impl From<OomError> for RenderPassCreationError {
     #[inline]
     fn from(err: OomError) -> RenderPassCreationError {
@@ -7,7 +7,10 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::error;
+use std::fmt;
 use std::sync::Arc;
+use smallvec::SmallVec;
 
 use format::ClearValue;
 use format::Format;
@@ -23,6 +26,8 @@ use sync::PipelineStages;
 
 use vk;
 
+use Error;
+use VulkanError;
 use VulkanObject;
 
 pub unsafe trait Framebuffer: VulkanObject<Object = vk::Framebuffer> {
@@ -214,6 +219,12 @@ pub unsafe trait RenderPassAttachmentsList<A>: RenderPass {
                                                     FramebufferCreationError>;
 }
 
+/// List of attachments that is stored inline when there are few of them. Used by the
+/// implementations of `RenderPassAttachmentsList` generated by the macros, so that creating a
+/// framebuffer doesn't require a heap allocation.
+#[doc(hidden)]
+pub type AttachmentsVec = SmallVec<[(Arc<ImageView>, Arc<Image>, ImageLayout, ImageLayout); 8]>;
+
 /// Extension trait for `RenderPass`. Defines which types are allowed as a list of clear values.
 ///
 /// # Safety
@@ -242,38 +253,126 @@ pub unsafe trait RenderPassClearValues<C>: RenderPass {
 // TODO: once specialization lands, this trait can be specialized for pairs that are known to
 //       always be compatible
 pub unsafe trait RenderPassSubpassInterface<Other>: RenderPass where Other: ShaderInterfaceDef {
+    /// Checks whether this subpass is compatible with the fragment output definition.
+    ///
+    /// Each output of the fragment shader must be written to a color attachment of the subpass
+    /// whose format has the same numeric type, ie. floating-point or normalized, unsigned
+    /// integer or signed integer.
+    fn check_compatibility(&self, subpass: u32, other: &Other)
+                           -> Result<(), FragmentOutputMismatchError>;
+
     /// Returns `true` if this subpass is compatible with the fragment output definition.
     /// Also returns `false` if the subpass is out of range.
-    // TODO: return proper error
-    fn is_compatible_with(&self, subpass: u32, other: &Other) -> bool;
+    #[inline]
+    fn is_compatible_with(&self, subpass: u32, other: &Other) -> bool {
+        self.check_compatibility(subpass, other).is_ok()
+    }
 }
 
 unsafe impl<A, B> RenderPassSubpassInterface<B> for A
     where A: RenderPass + RenderPassDesc, B: ShaderInterfaceDef
 {
-    fn is_compatible_with(&self, subpass: u32, other: &B) -> bool {
+    fn check_compatibility(&self, subpass: u32, other: &B)
+                           -> Result<(), FragmentOutputMismatchError>
+    {
         let pass_descr = match self.passes().skip(subpass as usize).next() {
             Some(s) => s,
-            None => return false,
+            None => return Err(FragmentOutputMismatchError::SubpassOutOfRange),
         };
 
         for element in other.elements() {
             for location in element.location.clone() {
                 let attachment_id = match pass_descr.color_attachments.get(location as usize) {
                     Some(a) => a.0,
-                    None => return false,
+                    None => {
+                        return Err(FragmentOutputMismatchError::MissingColorAttachment {
+                            location: location,
+                        });
+                    },
                 };
 
                 let attachment_desc = self.attachments().skip(attachment_id).next().unwrap();
 
-                // FIXME: compare formats depending on the number of components and data type
-                /*if attachment_desc.format != element.format {
-                    return false;
-                }*/
+                let compatible = match (element.format.ty(), attachment_desc.format.ty()) {
+                    (FormatTy::Float, FormatTy::Float) => true,
+                    (FormatTy::Uint, FormatTy::Uint) => true,
+                    (FormatTy::Sint, FormatTy::Sint) => true,
+                    _ => false,
+                };
+
+                if !compatible {
+                    return Err(FragmentOutputMismatchError::FormatMismatch {
+                        location: location,
+                        output_format: element.format,
+                        attachment_format: attachment_desc.format,
+                    });
+                }
             }
         }
 
-        true
+        Ok(())
+    }
+}
+
+/// Error that can happen when the output of a fragment shader doesn't match a subpass.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FragmentOutputMismatchError {
+    /// The subpass doesn't exist in the render pass.
+    SubpassOutOfRange,
+    /// The fragment shader writes to a location that doesn't have a color attachment.
+    MissingColorAttachment {
+        location: u32,
+    },
+    /// The numeric type of an output doesn't match the format of its color attachment.
+    FormatMismatch {
+        location: u32,
+        /// Format of the output of the fragment shader.
+        output_format: Format,
+        /// Format of the attachment.
+        attachment_format: Format,
+    },
+}
+
+impl error::Error for FragmentOutputMismatchError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            FragmentOutputMismatchError::SubpassOutOfRange => {
+                "the subpass doesn't exist in the render pass"
+            },
+            FragmentOutputMismatchError::MissingColorAttachment { .. } => {
+                "the fragment shader writes to a location that doesn't have a color attachment"
+            },
+            FragmentOutputMismatchError::FormatMismatch { .. } => {
+                "the type of an output of the fragment shader doesn't match the format of its \
+                 color attachment"
+            },
+        }
+    }
+}
+
+impl fmt::Display for FragmentOutputMismatchError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            FragmentOutputMismatchError::MissingColorAttachment { location } => {
+                write!(fmt, "the fragment shader writes to location {}, which doesn't have a \
+                             color attachment", location)
+            },
+            FragmentOutputMismatchError::FormatMismatch { location, output_format,
+                                                          attachment_format } => {
+                write!(fmt, "the fragment shader output at location {} has the format {:?}, \
+                             which doesn't match the format {:?} of its color attachment",
+                       location, output_format, attachment_format)
+            },
+            _ => write!(fmt, "{}", error::Error::description(self)),
+        }
+    }
+}
+
+impl VulkanError for FragmentOutputMismatchError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        None
     }
 }
 
@@ -13,6 +13,7 @@ use std::ops::Range;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::Weak;
+use smallvec::SmallVec;
 
 use command_buffer::Submission;
 use device::Device;
@@ -21,6 +22,7 @@ use format::FormatDesc;
 use format::FormatTy;
 use image::Dimensions;
 use image::ImageDimensions;
+use image::Swizzle;
 use image::ViewType;
 use image::sys::ImageCreationError;
 use image::sys::Layout;
@@ -34,10 +36,14 @@ use image::traits::ImageClearValue;
 use image::traits::ImageContent;
 use image::traits::ImageView;
 use image::traits::Transition;
+use memory::DedicatedAlloc;
+use memory::DedicatedAllocHint;
+use memory::MemoryUsage;
 use memory::pool::AllocLayout;
 use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
 use memory::pool::StdMemoryPool;
+use memory::pool::alloc_for_resource;
 use sync::Sharing;
 
 /// Image whose purpose is to be used as a framebuffer attachment.
@@ -93,8 +99,9 @@ pub struct AttachmentImage<F, A = Arc<StdMemoryPool>> where A: MemoryPool {
 
 #[derive(Debug)]
 struct Guarded {
-    // If false, the image is still in the undefined layout.
-    correct_layout: bool,
+    // Layout the image will be in once all the submissions that use it so far are finished.
+    // Starts as `Undefined`.
+    layout: Layout,
 
     // The latest submission that used the image. Used for synchronization purposes.
     latest_submission: Option<Weak<Submission>>,    // TODO: can use `Weak::new()` once it's stabilized
@@ -116,13 +123,56 @@ impl<F> AttachmentImage<F> {
             .. Usage::none()
         };
 
-        AttachmentImage::new_impl(device, dimensions, format, base_usage)
+        AttachmentImage::new_impl(device, dimensions, format, 1, base_usage)
+    }
+
+    /// Same as `new`, except that the image will be multisampled.
+    ///
+    /// Multisampled images are typically used as color or depth attachments of a render pass
+    /// whose color attachments are then resolved into a regular image.
+    ///
+    /// Returns an error if the number of samples is not supported by the backend for this format.
+    /// Note that 4 samples are always supported for color and depth-stencil attachments.
+    pub fn multisampled(device: &Arc<Device>, dimensions: [u32; 2], samples: u32, format: F)
+                        -> Result<Arc<AttachmentImage<F>>, ImageCreationError>
+        where F: FormatDesc
+    {
+        let base_usage = Usage {
+            transfer_source: true,
+            transfer_dest: true,
+            sampled: true,
+            .. Usage::none()
+        };
+
+        AttachmentImage::new_impl(device, dimensions, format, samples, base_usage)
+    }
+
+    /// Same as `multisampled`, except that the image will be transient.
+    ///
+    /// This is the most common kind of multisampled image, as the content of a multisampled
+    /// attachment is usually only needed until it is resolved at the end of the render pass.
+    /// Just like `transient`, the image uses lazily-allocated memory if possible.
+    pub fn transient_multisampled(device: &Arc<Device>, dimensions: [u32; 2], samples: u32,
+                                  format: F)
+                                  -> Result<Arc<AttachmentImage<F>>, ImageCreationError>
+        where F: FormatDesc
+    {
+        let base_usage = Usage {
+            transient_attachment: true,
+            .. Usage::none()
+        };
+
+        AttachmentImage::new_impl(device, dimensions, format, samples, base_usage)
     }
 
     /// Same as `new`, except that the image will be transient.
     ///
     /// A transient image is special because its content is undefined outside of a render pass.
     /// This means that the implementation has the possibility to not allocate any memory for it.
+    ///
+    /// If the physical device has a lazily-allocated memory type that is compatible with the
+    /// image, it will be used. This is typically the case on tile-based GPUs, where depth buffers
+    /// and multisampled attachments can then live entirely in on-chip memory.
     pub fn transient(device: &Arc<Device>, dimensions: [u32; 2], format: F)
                      -> Result<Arc<AttachmentImage<F>>, ImageCreationError>
         where F: FormatDesc
@@ -132,11 +182,94 @@ impl<F> AttachmentImage<F> {
             .. Usage::none()
         };
 
-        AttachmentImage::new_impl(device, dimensions, format, base_usage)
+        AttachmentImage::new_impl(device, dimensions, format, 1, base_usage)
     }
 
-    fn new_impl(device: &Arc<Device>, dimensions: [u32; 2], format: F, base_usage: Usage)
-                -> Result<Arc<AttachmentImage<F>>, ImageCreationError>
+    #[inline]
+    fn new_impl(device: &Arc<Device>, dimensions: [u32; 2], format: F, samples: u32,
+                base_usage: Usage) -> Result<Arc<AttachmentImage<F>>, ImageCreationError>
+        where F: FormatDesc
+    {
+        AttachmentImage::new_impl_pool(device, dimensions, format, samples, base_usage,
+                                       &Device::standard_pool(device), DedicatedAllocHint::Auto)
+    }
+}
+
+impl<F, A> AttachmentImage<F, A> where A: MemoryPool {
+    /// Same as `multisampled`, but allocates the memory of the image from `pool` instead of the
+    /// standard memory pool of the device.
+    ///
+    /// Pass 1 for `samples` to get the equivalent of `new`.
+    #[inline]
+    pub fn multisampled_with_pool(device: &Arc<Device>, dimensions: [u32; 2], samples: u32,
+                                  format: F, pool: &A)
+                                  -> Result<Arc<AttachmentImage<F, A>>, ImageCreationError>
+        where F: FormatDesc
+    {
+        AttachmentImage::multisampled_with_pool_and_hint(device, dimensions, samples, format,
+                                                         pool, DedicatedAllocHint::Auto)
+    }
+
+    /// Same as `multisampled_with_pool`, but `dedicated` controls whether the image gets a
+    /// memory allocation of its own.
+    #[inline]
+    pub fn multisampled_with_pool_and_hint(device: &Arc<Device>, dimensions: [u32; 2],
+                                           samples: u32, format: F, pool: &A,
+                                           dedicated: DedicatedAllocHint)
+                                           -> Result<Arc<AttachmentImage<F, A>>,
+                                                     ImageCreationError>
+        where F: FormatDesc
+    {
+        let base_usage = Usage {
+            transfer_source: true,
+            transfer_dest: true,
+            sampled: true,
+            .. Usage::none()
+        };
+
+        AttachmentImage::new_impl_pool(device, dimensions, format, samples, base_usage, pool,
+                                       dedicated)
+    }
+
+    /// Same as `transient_multisampled`, but allocates the memory of the image from `pool`
+    /// instead of the standard memory pool of the device.
+    ///
+    /// Pass 1 for `samples` to get the equivalent of `transient`. A lazily-allocated memory type
+    /// is still requested from the pool if one is available.
+    #[inline]
+    pub fn transient_multisampled_with_pool(device: &Arc<Device>, dimensions: [u32; 2],
+                                            samples: u32, format: F, pool: &A)
+                                            -> Result<Arc<AttachmentImage<F, A>>,
+                                                      ImageCreationError>
+        where F: FormatDesc
+    {
+        AttachmentImage::transient_multisampled_with_pool_and_hint(device, dimensions, samples,
+                                                                   format, pool,
+                                                                   DedicatedAllocHint::Auto)
+    }
+
+    /// Same as `transient_multisampled_with_pool`, but `dedicated` controls whether the image
+    /// gets a memory allocation of its own.
+    #[inline]
+    pub fn transient_multisampled_with_pool_and_hint(device: &Arc<Device>, dimensions: [u32; 2],
+                                                     samples: u32, format: F, pool: &A,
+                                                     dedicated: DedicatedAllocHint)
+                                                     -> Result<Arc<AttachmentImage<F, A>>,
+                                                               ImageCreationError>
+        where F: FormatDesc
+    {
+        let base_usage = Usage {
+            transient_attachment: true,
+            .. Usage::none()
+        };
+
+        AttachmentImage::new_impl_pool(device, dimensions, format, samples, base_usage, pool,
+                                       dedicated)
+    }
+
+    fn new_impl_pool(device: &Arc<Device>, dimensions: [u32; 2], format: F, samples: u32,
+                     base_usage: Usage, pool: &A, dedicated: DedicatedAllocHint)
+                     -> Result<Arc<AttachmentImage<F, A>>, ImageCreationError>
         where F: FormatDesc
     {
         let is_depth = match format.format().ty() {
@@ -157,25 +290,27 @@ impl<F> AttachmentImage<F> {
         let (image, mem_reqs) = unsafe {
             try!(UnsafeImage::new(device, &usage, format.format(),
                                   ImageDimensions::Dim2d { width: dimensions[0], height: dimensions[1], array_layers: 1, cubemap_compatible: false },
-                                  1, 1, Sharing::Exclusive::<Empty<u32>>, false, false))
+                                  samples, 1, Sharing::Exclusive::<Empty<u32>>, false, false))
         };
 
         let mem_ty = {
-            let device_local = device.physical_device().memory_types()
-                                     .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
-                                     .filter(|t| t.is_device_local());
-            let any = device.physical_device().memory_types()
-                            .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0);
-            device_local.chain(any).next().unwrap()
+            // Transient images can use lazily-allocated memory, in which case the implementation
+            // only commits memory if it actually needs it.
+            let usage = if base_usage.transient_attachment { MemoryUsage::Transient }
+                        else { MemoryUsage::DeviceLocal };
+            usage.choose(device.physical_device(), mem_reqs.memory_type_bits).unwrap()
         };
 
-        let mem = try!(MemoryPool::alloc(&Device::standard_pool(device), mem_ty,
-                                         mem_reqs.size, mem_reqs.alignment, AllocLayout::Optimal));
+        // Render targets are usually large and drivers often prefer them to have their own
+        // memory allocation.
+        let mem = try!(alloc_for_resource(pool, mem_ty, &mem_reqs, AllocLayout::Optimal,
+                                          dedicated, DedicatedAlloc::Image(&image)));
         debug_assert!((mem.offset() % mem_reqs.alignment) == 0);
         unsafe { try!(image.bind_memory(mem.memory(), mem.offset())); }
 
         let view = unsafe {
-            try!(UnsafeImageView::raw(&image, ViewType::Dim2d, 0 .. 1, 0 .. 1))
+            try!(UnsafeImageView::raw(&image, ViewType::Dim2d, 0 .. 1, 0 .. 1,
+                                      Swizzle::default()))
         };
 
         Ok(Arc::new(AttachmentImage {
@@ -186,7 +321,7 @@ impl<F> AttachmentImage<F> {
             attachment_layout: if is_depth { Layout::DepthStencilAttachmentOptimal }
                                else { Layout::ColorAttachmentOptimal },
             guarded: Mutex::new(Guarded {
-                correct_layout: false,
+                layout: Layout::Undefined,
                 latest_submission: None,
             }),
         }))
@@ -223,21 +358,25 @@ unsafe impl<F, A> Image for AttachmentImage<F, A> where F: 'static + Send + Sync
         0 .. 1
     }
 
+    // The layout of the image is tracked across submissions by `gpu_access`, therefore command
+    // buffers don't need to transition the image from or to the attachment layout.
     #[inline]
-    fn initial_layout(&self, _: (u32, u32), _: Layout) -> (Layout, bool, bool) {
-        (self.attachment_layout, false, false)
+    fn initial_layout(&self, _: (u32, u32), first_required_layout: Layout)
+                      -> (Layout, bool, bool)
+    {
+        (first_required_layout, false, false)
     }
 
     #[inline]
-    fn final_layout(&self, _: (u32, u32), _: Layout) -> (Layout, bool, bool) {
-        (self.attachment_layout, false, false)
+    fn final_layout(&self, _: (u32, u32), last_required_layout: Layout) -> (Layout, bool, bool) {
+        (last_required_layout, false, false)
     }
 
     fn needs_fence(&self, access: &mut Iterator<Item = AccessRange>) -> Option<bool> {
         Some(false)
     }
 
-    unsafe fn gpu_access(&self, _: &mut Iterator<Item = AccessRange>,
+    unsafe fn gpu_access(&self, ranges: &mut Iterator<Item = AccessRange>,
                          submission: &Arc<Submission>) -> GpuAccessResult
     {
         let mut guarded = self.guarded.lock().unwrap();
@@ -246,17 +385,24 @@ unsafe impl<F, A> Image for AttachmentImage<F, A> where F: 'static + Send + Sync
                                       Some(Arc::downgrade(submission)));
         let dependency = dependency.and_then(|d| d.upgrade());
 
-        let transition = if !guarded.correct_layout {
-            vec![Transition {
-                block: (0, 0),
-                from: Layout::Undefined,
-                to: self.attachment_layout,
-            }]
-        } else {
-            vec![]
-        };
-
-        guarded.correct_layout = true;
+        // The image only has one block. If the command buffer expects a different layout than
+        // the one the previous submissions left the image in, a transition is submitted first.
+        // A command buffer that starts from the undefined layout discards the content instead.
+        let ranges = ranges.collect::<SmallVec<[AccessRange; 1]>>();
+        let mut transition = Vec::new();
+        if let Some(range) = ranges.first() {
+            if range.initial_layout != Layout::Undefined &&
+               range.initial_layout != guarded.layout
+            {
+                transition.push(Transition {
+                    block: (0, 0),
+                    from: guarded.layout,
+                    to: range.initial_layout,
+                });
+            }
+
+            guarded.layout = range.final_layout;
+        }
 
         GpuAccessResult {
             dependencies: if let Some(dependency) = dependency {
@@ -321,7 +467,7 @@ unsafe impl<F, A> ImageView for AttachmentImage<F, A>
 
     #[inline]
     fn descriptor_set_storage_image_layout(&self) -> Layout {
-        Layout::ShaderReadOnlyOptimal
+        Layout::General
     }
 
     #[inline]
@@ -348,7 +494,16 @@ unsafe impl<F, A> ImageView for AttachmentImage<F, A>
 #[cfg(test)]
 mod tests {
     use super::AttachmentImage;
+    use command_buffer::PrimaryCommandBufferBuilder;
+    use command_buffer::submit;
+    use device::Device;
     use format::Format;
+    use image::Image;
+    use image::ImageCreationError;
+    use image::sys::Layout;
+    use image::traits::AccessRange;
+    use memory::DedicatedAllocHint;
+    use memory::pool::MemoryPoolAlloc;
 
     #[test]
     fn create_regular() {
@@ -361,4 +516,80 @@ mod tests {
         let (device, _) = gfx_dev_and_queue!();
         let _img = AttachmentImage::transient(&device, [32, 32], Format::R8G8B8A8Unorm).unwrap();
     }
+
+    #[test]
+    fn transient_lazily_allocated() {
+        let (device, _) = gfx_dev_and_queue!();
+        let img = AttachmentImage::transient(&device, [32, 32], Format::R8G8B8A8Unorm).unwrap();
+
+        let mem_reqs = img.image.memory_requirements();
+        let has_lazy = device.physical_device().memory_types()
+                             .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
+                             .any(|t| t.is_lazily_allocated());
+        assert_eq!(img.memory.memory().memory_type().is_lazily_allocated(), has_lazy);
+    }
+
+    #[test]
+    fn dedicated_allocation() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pool = Device::standard_pool(&device);
+        let img = AttachmentImage::multisampled_with_pool_and_hint(&device, [32, 32], 1,
+                                                                   Format::R8G8B8A8Unorm, &pool,
+                                                                   DedicatedAllocHint::Always);
+        let img = img.unwrap();
+        assert_eq!(img.memory.offset(), 0);
+    }
+
+    #[test]
+    fn create_multisampled() {
+        let (device, _) = gfx_dev_and_queue!();
+        let img = AttachmentImage::transient_multisampled(&device, [32, 32], 4,
+                                                          Format::R8G8B8A8Unorm).unwrap();
+        assert_eq!(img.samples(), 4);
+    }
+
+    #[test]
+    fn multisampled_non_po2() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        match AttachmentImage::multisampled(&device, [32, 32], 3, Format::R8G8B8A8Unorm) {
+            Err(ImageCreationError::UnsupportedSamplesCount { obtained: 3 }) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn layout_tracked_across_submissions() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let img = AttachmentImage::new(&device, [32, 32], Format::R8G8B8A8Unorm).unwrap();
+
+        let cb = PrimaryCommandBufferBuilder::new(&device, queue.family()).build();
+        let submission = submit(&cb, &queue).unwrap();
+
+        let access = |initial, final_| {
+            vec![AccessRange { block: (0, 0), write: true, initial_layout: initial,
+                               final_layout: final_ }]
+        };
+
+        unsafe {
+            // Starting from the undefined layout discards the content and needs no transition.
+            let result = img.gpu_access(&mut access(Layout::Undefined,
+                                                    Layout::ColorAttachmentOptimal).into_iter(),
+                                        &submission);
+            assert!(result.before_transitions.is_empty());
+
+            let result = img.gpu_access(&mut access(Layout::ShaderReadOnlyOptimal,
+                                                    Layout::ShaderReadOnlyOptimal).into_iter(),
+                                        &submission);
+            assert_eq!(result.before_transitions.len(), 1);
+            assert_eq!(result.before_transitions[0].from, Layout::ColorAttachmentOptimal);
+            assert_eq!(result.before_transitions[0].to, Layout::ShaderReadOnlyOptimal);
+
+            // The image is already in the right layout.
+            let result = img.gpu_access(&mut access(Layout::ShaderReadOnlyOptimal,
+                                                    Layout::ColorAttachmentOptimal).into_iter(),
+                                        &submission);
+            assert!(result.before_transitions.is_empty());
+        }
+    }
 }
@@ -0,0 +1,340 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::mem;
+use std::ops::Range;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::Weak;
+use smallvec::SmallVec;
+
+use command_buffer::Submission;
+use device::Device;
+use format::ClearValue;
+use format::Format;
+use format::FormatDesc;
+use image::Dimensions;
+use image::ImageAspect;
+use image::Swizzle;
+use image::sys::Layout;
+use image::sys::UnsafeImage;
+use image::sys::UnsafeImageView;
+use image::sys::Usage;
+use image::traits::AccessRange;
+use image::traits::GpuAccessResult;
+use image::traits::Image;
+use image::traits::ImageClearValue;
+use image::traits::ImageContent;
+use image::traits::ImageView;
+
+use OomError;
+
+/// Image that was created outside of vulkano, for example by another library or by the OS
+/// compositor.
+///
+/// Vulkano doesn't own the image. The `VkImage` (and the `VkImageView` if one was passed) are
+/// not destroyed when the `ExternalImage` is destroyed, and it is the responsibility of the user
+/// to keep them alive for as long as the `ExternalImage` is alive.
+///
+/// The image is expected to be in the layout that was passed at construction every time it is
+/// used by a command buffer, and is put back in this layout at the end of each command buffer.
+#[derive(Debug)]
+pub struct ExternalImage {
+    // Inner implementation.
+    image: UnsafeImage,
+
+    // View of the whole image.
+    view: UnsafeImageView,
+
+    // Dimensions of the view.
+    dimensions: Dimensions,
+
+    // Layout the image is in outside of command buffers.
+    layout: Layout,
+
+    // Additional state that must be guarded by a mutex.
+    guarded: Mutex<Guarded>,
+}
+
+#[derive(Debug)]
+struct Guarded {
+    // Submissions that read the image.
+    read_submissions: SmallVec<[Weak<Submission>; 4]>,
+    // The latest submission that wrote the image.
+    write_submission: Option<Weak<Submission>>,     // TODO: can use `Weak::new()` once it's stabilized
+}
+
+impl ExternalImage {
+    /// Wraps an existing image handle.
+    ///
+    /// `view` is an optional handle to a view of the whole image. If `None` is passed, vulkano
+    /// creates its own view of the image.
+    ///
+    /// # Safety
+    ///
+    /// - `image` must be a valid image created from `device`, with exactly the given format,
+    ///   dimensions, number of mipmap levels and usage, and with one sample per pixel.
+    /// - The image must be bound to memory and be in the `layout` layout.
+    /// - If `view` is `Some`, it must be a valid view of the whole image with identity
+    ///   swizzling, whose type corresponds to `dimensions`.
+    /// - `image` and `view` must stay alive for as long as the returned object exists.
+    /// - The image must not be accessed by anything else than vulkano while it is used by a
+    ///   command buffer.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `mipmaps` is 0.
+    ///
+    pub unsafe fn from_raw(device: &Arc<Device>, image: u64, view: Option<u64>, format: Format,
+                           dimensions: Dimensions, mipmaps: u32, usage: &Usage, layout: Layout)
+                           -> Result<Arc<ExternalImage>, OomError>
+    {
+        assert!(mipmaps >= 1);
+
+        let image = UnsafeImage::from_raw(device, image, usage.to_usage_bits(), format,
+                                          dimensions.to_image_dimensions(), 1, mipmaps);
+
+        let view = match view {
+            Some(view) => {
+                UnsafeImageView::from_raw(&image, view, 0 .. mipmaps,
+                                          0 .. dimensions.array_layers_with_cube(),
+                                          Swizzle::default(), ImageAspect::from_format(format))
+            },
+            None => {
+                try!(UnsafeImageView::raw(&image, dimensions.to_view_type(), 0 .. mipmaps,
+                                          0 .. dimensions.array_layers_with_cube(),
+                                          Swizzle::default()))
+            },
+        };
+
+        Ok(Arc::new(ExternalImage {
+            image: image,
+            view: view,
+            dimensions: dimensions,
+            layout: layout,
+            guarded: Mutex::new(Guarded {
+                read_submissions: SmallVec::new(),
+                write_submission: None,
+            }),
+        }))
+    }
+
+    /// Returns the dimensions of the image.
+    #[inline]
+    pub fn dimensions(&self) -> Dimensions {
+        self.dimensions
+    }
+
+    /// Returns the format of the image.
+    #[inline]
+    pub fn format(&self) -> Format {
+        self.image.format()
+    }
+
+    /// Returns the layout the image is in outside of command buffers.
+    #[inline]
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+}
+
+unsafe impl Image for ExternalImage {
+    #[inline]
+    fn inner(&self) -> &UnsafeImage {
+        &self.image
+    }
+
+    #[inline]
+    fn blocks(&self, _: Range<u32>, _: Range<u32>) -> Vec<(u32, u32)> {
+        vec![(0, 0)]
+    }
+
+    #[inline]
+    fn block_mipmap_levels_range(&self, block: (u32, u32)) -> Range<u32> {
+        0 .. self.image.mipmap_levels()
+    }
+
+    #[inline]
+    fn block_array_layers_range(&self, block: (u32, u32)) -> Range<u32> {
+        0 .. self.image.dimensions().array_layers()
+    }
+
+    #[inline]
+    fn initial_layout(&self, _: (u32, u32), _: Layout) -> (Layout, bool, bool) {
+        // The image may have been written by something outside of vulkano, so we add a memory
+        // barrier.
+        (self.layout, false, true)
+    }
+
+    #[inline]
+    fn final_layout(&self, _: (u32, u32), _: Layout) -> (Layout, bool, bool) {
+        (self.layout, false, true)
+    }
+
+    fn needs_fence(&self, access: &mut Iterator<Item = AccessRange>) -> Option<bool> {
+        Some(false)
+    }
+
+    unsafe fn gpu_access(&self, ranges: &mut Iterator<Item = AccessRange>,
+                         submission: &Arc<Submission>) -> GpuAccessResult
+    {
+        let mut guarded = self.guarded.lock().unwrap();
+
+        let is_written = {
+            let mut written = false;
+            while let Some(r) = ranges.next() { if r.write { written = true; break; } }
+            written
+        };
+
+        let dependencies = if is_written {
+            let write_dep = mem::replace(&mut guarded.write_submission,
+                                         Some(Arc::downgrade(submission)));
+
+            let read_submissions = mem::replace(&mut guarded.read_submissions,
+                                                SmallVec::new());
+
+            // We use a temporary variable to bypass a lifetime error in rustc.
+            let list = read_submissions.into_iter()
+                                       .chain(write_dep.into_iter())
+                                       .filter_map(|s| s.upgrade())
+                                       .collect::<Vec<_>>();
+            list
+
+        } else {
+            guarded.read_submissions.push(Arc::downgrade(submission));
+            guarded.write_submission.clone().and_then(|s| s.upgrade()).into_iter().collect()
+        };
+
+        GpuAccessResult {
+            dependencies: dependencies,
+            additional_wait_semaphore: None,
+            additional_signal_semaphore: None,
+            before_transitions: vec![],
+            after_transitions: vec![],
+        }
+    }
+}
+
+unsafe impl ImageClearValue<<Format as FormatDesc>::ClearValue> for ExternalImage {
+    #[inline]
+    fn decode(&self, value: <Format as FormatDesc>::ClearValue) -> Option<ClearValue> {
+        Some(self.image.format().decode_clear_value(value))
+    }
+}
+
+unsafe impl<P> ImageContent<P> for ExternalImage {
+    #[inline]
+    fn matches_format(&self) -> bool {
+        true        // FIXME:
+    }
+}
+
+unsafe impl ImageView for ExternalImage {
+    #[inline]
+    fn parent(&self) -> &Image {
+        self
+    }
+
+    #[inline]
+    fn parent_arc(me: &Arc<Self>) -> Arc<Image> where Self: Sized {
+        me.clone() as Arc<_>
+    }
+
+    #[inline]
+    fn dimensions(&self) -> Dimensions {
+        self.dimensions
+    }
+
+    #[inline]
+    fn blocks(&self) -> Vec<(u32, u32)> {
+        vec![(0, 0)]
+    }
+
+    #[inline]
+    fn inner(&self) -> &UnsafeImageView {
+        &self.view
+    }
+
+    #[inline]
+    fn descriptor_set_storage_image_layout(&self) -> Layout {
+        Layout::General
+    }
+
+    #[inline]
+    fn descriptor_set_combined_image_sampler_layout(&self) -> Layout {
+        if self.layout == Layout::General { Layout::General }
+        else { Layout::ShaderReadOnlyOptimal }
+    }
+
+    #[inline]
+    fn descriptor_set_sampled_image_layout(&self) -> Layout {
+        if self.layout == Layout::General { Layout::General }
+        else { Layout::ShaderReadOnlyOptimal }
+    }
+
+    #[inline]
+    fn descriptor_set_input_attachment_layout(&self) -> Layout {
+        if self.layout == Layout::General { Layout::General }
+        else { Layout::ShaderReadOnlyOptimal }
+    }
+
+    #[inline]
+    fn identity_swizzle(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter::Empty;
+
+    use format::Format;
+    use image::Dimensions;
+    use image::ExternalImage;
+    use image::Image;
+    use image::ImageView;
+    use image::Layout;
+    use image::Usage;
+    use image::sys::UnsafeImage;
+    use memory::DeviceMemory;
+    use sync::Sharing;
+    use VulkanObject;
+
+    #[test]
+    fn wrap_raw() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = Usage { sampled: true, .. Usage::none() };
+        let dimensions = Dimensions::Dim2d { width: 32, height: 32 };
+
+        // Simulates an image created by another library.
+        let (raw, mem_reqs) = unsafe {
+            UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
+                             dimensions.to_image_dimensions(), 1, 1,
+                             Sharing::Exclusive::<Empty<u32>>, false, false).unwrap()
+        };
+        let mem_ty = device.physical_device().memory_types()
+                           .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
+                           .next().unwrap();
+        let mem = DeviceMemory::alloc(&device, mem_ty, mem_reqs.size).unwrap();
+        unsafe { raw.bind_memory(&mem, 0).unwrap(); }
+
+        let image = unsafe {
+            ExternalImage::from_raw(&device, raw.internal_object(), None, Format::R8G8B8A8Unorm,
+                                    dimensions, 1, &usage, Layout::General).unwrap()
+        };
+
+        assert_eq!(image.dimensions(), dimensions);
+        assert_eq!(image.format(), Format::R8G8B8A8Unorm);
+        assert_eq!(image.layout(), Layout::General);
+        assert_eq!(Image::inner(&*image).internal_object(), raw.internal_object());
+        assert!(ImageView::inner(&*image).usage_sampled());
+        assert_eq!(ImageView::inner(&*image).mipmap_levels(), 0 .. 1);
+    }
+}
@@ -7,6 +7,8 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::error;
+use std::fmt;
 use std::mem;
 use std::iter::Empty;
 use std::ops::Range;
@@ -17,10 +19,17 @@ use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use smallvec::SmallVec;
 
+use buffer::BufferUsage;
+use buffer::CpuAccessibleBuffer;
+use command_buffer::PrimaryCommandBufferBuilder;
 use command_buffer::Submission;
+use command_buffer::submit;
 use device::Device;
+use device::Queue;
+use device::QueueError;
 use format::FormatDesc;
 use image::Dimensions;
+use image::Swizzle;
 use image::sys::ImageCreationError;
 use image::sys::Layout;
 use image::sys::UnsafeImage;
@@ -32,12 +41,22 @@ use image::traits::Image;
 use image::traits::ImageContent;
 use image::traits::ImageView;
 use instance::QueueFamily;
+use memory::Content;
+use memory::DedicatedAlloc;
+use memory::DedicatedAllocHint;
+use memory::DeviceMemoryAllocError;
+use memory::MemoryUsage;
 use memory::pool::AllocLayout;
 use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
 use memory::pool::StdMemoryPool;
+use memory::pool::alloc_for_resource;
 use sync::Sharing;
 
+use Error;
+use OomError;
+use VulkanError;
+
 /// Image whose purpose is to be used for read-only purposes. You can write to the image once,
 /// but then you must only ever read from it. TODO: clarify because of blit operations
 // TODO: type (2D, 3D, array, etc.) as template parameter
@@ -59,9 +78,35 @@ struct PerLayer {
 
 impl<F> ImmutableImage<F> {
     /// Builds a new immutable image.
+    #[inline]
     pub fn new<'a, I>(device: &Arc<Device>, dimensions: Dimensions, format: F, queue_families: I)
                       -> Result<Arc<ImmutableImage<F>>, ImageCreationError>
         where F: FormatDesc, I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        ImmutableImage::with_pool(device, dimensions, format, queue_families,
+                                  &Device::standard_pool(device))
+    }
+}
+
+impl<F, A> ImmutableImage<F, A> where A: MemoryPool {
+    /// Same as `new`, but allocates the memory of the image from `pool` instead of the standard
+    /// memory pool of the device.
+    #[inline]
+    pub fn with_pool<'a, I>(device: &Arc<Device>, dimensions: Dimensions, format: F,
+                            queue_families: I, pool: &A)
+                            -> Result<Arc<ImmutableImage<F, A>>, ImageCreationError>
+        where F: FormatDesc, I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        ImmutableImage::with_pool_and_hint(device, dimensions, format, queue_families, pool,
+                                           DedicatedAllocHint::Auto)
+    }
+
+    /// Same as `with_pool`, but `dedicated` controls whether the image gets a memory allocation
+    /// of its own.
+    pub fn with_pool_and_hint<'a, I>(device: &Arc<Device>, dimensions: Dimensions, format: F,
+                                     queue_families: I, pool: &A, dedicated: DedicatedAllocHint)
+                                     -> Result<Arc<ImmutableImage<F, A>>, ImageCreationError>
+        where F: FormatDesc, I: IntoIterator<Item = QueueFamily<'a>>
     {
         let usage = Usage {
             transfer_source: true,  // for blits
@@ -84,23 +129,18 @@ impl<F> ImmutableImage<F> {
                                   1, 1, Sharing::Exclusive::<Empty<u32>>, false, false))
         };
 
-        let mem_ty = {
-            let device_local = device.physical_device().memory_types()
-                                     .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
-                                     .filter(|t| t.is_device_local());
-            let any = device.physical_device().memory_types()
-                            .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0);
-            device_local.chain(any).next().unwrap()
-        };
+        let mem_ty = MemoryUsage::DeviceLocal.choose(device.physical_device(),
+                                                     mem_reqs.memory_type_bits).unwrap();
 
-        let mem = try!(MemoryPool::alloc(&Device::standard_pool(device), mem_ty,
-                                         mem_reqs.size, mem_reqs.alignment, AllocLayout::Optimal));
+        let mem = try!(alloc_for_resource(pool, mem_ty, &mem_reqs, AllocLayout::Optimal,
+                                          dedicated, DedicatedAlloc::Image(&image)));
         debug_assert!((mem.offset() % mem_reqs.alignment) == 0);
         unsafe { try!(image.bind_memory(mem.memory(), mem.offset())); }
 
         let view = unsafe {
             try!(UnsafeImageView::raw(&image, dimensions.to_view_type(), 0 .. image.mipmap_levels(),
-                                      0 .. image.dimensions().array_layers()))
+                                      0 .. image.dimensions().array_layers(),
+                                      Swizzle::default()))
         };
 
         Ok(Arc::new(ImmutableImage {
@@ -123,6 +163,139 @@ impl<F> ImmutableImage<F> {
     }
 }
 
+impl<F> ImmutableImage<F> where F: FormatDesc + 'static + Send + Sync {
+    /// Builds a new immutable image whose content is produced by an iterator.
+    ///
+    /// The content is written to a staging buffer, then copied to the first mipmap level of all
+    /// the array layers of the image by a command buffer that is submitted to `queue`. The
+    /// iterator must produce the texels of each layer one after the other, row by row, or the
+    /// blocks of each layer if the format is compressed.
+    ///
+    /// Returns the image and the submission of the copy. Command buffers that later use the
+    /// image automatically wait for the copy to be finished.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the iterator doesn't produce enough data for the whole image.
+    ///
+    pub fn from_iter<P, I>(data: I, dimensions: Dimensions, format: F, queue: &Arc<Queue>)
+                           -> Result<(Arc<ImmutableImage<F>>, Arc<Submission>),
+                                     ImmutableImageCreationError>
+        where I: ExactSizeIterator<Item = P>, P: Content + 'static + Send + Sync
+    {
+        let source = try!(CpuAccessibleBuffer::from_iter(queue.device(),
+                                                         &BufferUsage::transfer_source(),
+                                                         Some(queue.family()), data));
+        let image = try!(ImmutableImage::new(queue.device(), dimensions, format,
+                                             Some(queue.family())));
+
+        let cb = PrimaryCommandBufferBuilder::new(queue.device(), queue.family())
+                                             .copy_buffer_to_color_image(&source, &image, 0,
+                                                                         upload_layers(dimensions),
+                                                                         [0, 0, 0],
+                                                                         upload_extent(dimensions))
+                                             .build();
+        let submission = try!(submit(&cb, queue));
+        Ok((image, submission))
+    }
+
+    /// Builds a new immutable image whose content is a slice of texels or blocks.
+    ///
+    /// Same as `from_iter`, but for data that is already in memory, for example a decoded
+    /// texture file.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `data` isn't large enough for the whole image.
+    ///
+    #[inline]
+    pub fn from_slice<P>(data: &[P], dimensions: Dimensions, format: F, queue: &Arc<Queue>)
+                         -> Result<(Arc<ImmutableImage<F>>, Arc<Submission>),
+                                   ImmutableImageCreationError>
+        where P: Content + Copy + 'static + Send + Sync
+    {
+        ImmutableImage::from_iter(data.iter().cloned(), dimensions, format, queue)
+    }
+
+    /// Builds a new immutable image whose content is produced by an iterator, and performs the
+    /// copy on a dedicated transfer queue.
+    ///
+    /// The image is exclusively owned by the family of `queue`. The copy is submitted to
+    /// `transfer_queue` and releases the ownership of the image, then a command buffer that
+    /// acquires the ownership is submitted to `queue`. This command buffer waits for the copy
+    /// with a semaphore, which allows streaming assets in the background while `queue` keeps
+    /// rendering.
+    ///
+    /// If both queues belong to the same family, this is the same as calling `from_iter` with
+    /// `transfer_queue`.
+    ///
+    /// Returns the image and the submission of the acquire operation, which keeps the copy
+    /// alive.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the two queues don't belong to the same device.
+    /// - Panics if the iterator doesn't produce enough data for the whole image.
+    ///
+    pub fn from_iter_with_transfer_queue<P, I>(data: I, dimensions: Dimensions, format: F,
+                                               transfer_queue: &Arc<Queue>, queue: &Arc<Queue>)
+                                               -> Result<(Arc<ImmutableImage<F>>,
+                                                          Arc<Submission>),
+                                                         ImmutableImageCreationError>
+        where I: ExactSizeIterator<Item = P>, P: Content + 'static + Send + Sync
+    {
+        assert_eq!(&**transfer_queue.device() as *const Device,
+                   &**queue.device() as *const Device);
+
+        if transfer_queue.family().id() == queue.family().id() {
+            return ImmutableImage::from_iter(data, dimensions, format, transfer_queue);
+        }
+
+        let device = queue.device();
+        let (transfer_family, family) = (transfer_queue.family(), queue.family());
+
+        let source = try!(CpuAccessibleBuffer::from_iter(device, &BufferUsage::transfer_source(),
+                                                         Some(transfer_family), data));
+        let image = try!(ImmutableImage::new(device, dimensions, format, Some(family)));
+
+        // The layout of the image at the end of command buffers, which is kept during the
+        // ownership transfer.
+        let layout = Layout::ShaderReadOnlyOptimal;
+
+        let (release, acquire) = unsafe {
+            let release = PrimaryCommandBufferBuilder::new(device, transfer_family)
+                              .copy_buffer_to_color_image(&source, &image, 0,
+                                                          upload_layers(dimensions), [0, 0, 0],
+                                                          upload_extent(dimensions))
+                              .transfer_image_ownership(&image, transfer_family, family, layout)
+                              .build();
+            let acquire = PrimaryCommandBufferBuilder::new(device, family)
+                              .transfer_image_ownership(&image, transfer_family, family, layout)
+                              .build();
+            (release, acquire)
+        };
+
+        // The acquire depends on the release through the write tracking of the image, and
+        // therefore waits upon it with a semaphore.
+        let release_submission = try!(submit(&release, transfer_queue));
+        let acquire_submission = try!(submit(&acquire, queue));
+        acquire_submission.keep_alive(release_submission);
+        Ok((image, acquire_submission))
+    }
+}
+
+// Returns the array layers of an image that are filled by `from_iter`.
+#[inline]
+fn upload_layers(dimensions: Dimensions) -> Range<u32> {
+    0 .. dimensions.array_layers_with_cube()
+}
+
+// Returns the extent of the region of an image that is filled by `from_iter`.
+#[inline]
+fn upload_extent(dimensions: Dimensions) -> [u32; 3] {
+    dimensions.to_image_dimensions().mipmap_extent(0)
+}
+
 impl<F, A> ImmutableImage<F, A> where A: MemoryPool {
     /// Returns the dimensions of the image.
     #[inline]
@@ -152,6 +325,10 @@ unsafe impl<F, A> Image for ImmutableImage<F, A> where F: 'static + Send + Sync,
         block.1 .. (block.1 + 1)
     }
 
+    // Contrary to `StorageImage` and `AttachmentImage`, the layout of an immutable image isn't
+    // tracked across submissions. Once uploaded, the image can be read by multiple queues at the
+    // same time, which is only possible if it always stays in `ShaderReadOnlyOptimal` between
+    // two submissions.
     #[inline]
     fn initial_layout(&self, _: (u32, u32), first_usage: Layout) -> (Layout, bool, bool) {
         let l = if first_usage == Layout::TransferDstOptimal {
@@ -248,7 +425,7 @@ unsafe impl<F: 'static, A> ImageView for ImmutableImage<F, A>
 
     #[inline]
     fn descriptor_set_storage_image_layout(&self) -> Layout {
-        Layout::ShaderReadOnlyOptimal
+        Layout::General
     }
 
     #[inline]
@@ -271,3 +448,190 @@ unsafe impl<F: 'static, A> ImageView for ImmutableImage<F, A>
         true
     }
 }
+
+/// Error that can happen when creating an immutable image with some initial content.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ImmutableImageCreationError {
+    /// Error while creating the image or the buffer that holds its content.
+    ImageCreationError(ImageCreationError),
+    /// Error while submitting the copy of the content.
+    QueueError(QueueError),
+}
+
+impl error::Error for ImmutableImageCreationError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            ImmutableImageCreationError::ImageCreationError(_) => "error while creating the image",
+            ImmutableImageCreationError::QueueError(_) => {
+                "error while submitting the copy of the content of the image"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ImmutableImageCreationError::ImageCreationError(ref err) => Some(err),
+            ImmutableImageCreationError::QueueError(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for ImmutableImageCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for ImmutableImageCreationError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            ImmutableImageCreationError::ImageCreationError(ref err) => err.error_code(),
+            ImmutableImageCreationError::QueueError(ref err) => err.error_code(),
+        }
+    }
+}
+
+impl From<ImageCreationError> for ImmutableImageCreationError {
+    #[inline]
+    fn from(err: ImageCreationError) -> ImmutableImageCreationError {
+        ImmutableImageCreationError::ImageCreationError(err)
+    }
+}
+
+impl From<OomError> for ImmutableImageCreationError {
+    #[inline]
+    fn from(err: OomError) -> ImmutableImageCreationError {
+        ImmutableImageCreationError::ImageCreationError(ImageCreationError::OomError(err))
+    }
+}
+
+impl From<DeviceMemoryAllocError> for ImmutableImageCreationError {
+    #[inline]
+    fn from(err: DeviceMemoryAllocError) -> ImmutableImageCreationError {
+        ImmutableImageCreationError::ImageCreationError(err.into())
+    }
+}
+
+impl From<QueueError> for ImmutableImageCreationError {
+    #[inline]
+    fn from(err: QueueError) -> ImmutableImageCreationError {
+        ImmutableImageCreationError::QueueError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+    use std::time::Duration;
+
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::PrimaryCommandBufferBuilder;
+    use command_buffer::submit;
+    use device::Device;
+    use device::DeviceExtensions;
+    use device::QueueRequest;
+    use device::QueuesSelection;
+    use device::QueuesSelectionError;
+    use features::Features;
+    use format::Format;
+    use image::Dimensions;
+    use image::ImmutableImage;
+    use instance;
+
+    #[test]
+    fn from_iter() {
+        let (_, queue) = gfx_dev_and_queue!();
+
+        let data = iter::repeat(0xffu8).take(16 * 16 * 4);
+        let (image, submission) = ImmutableImage::from_iter(data,
+                                                            Dimensions::Dim2d { width: 16,
+                                                                                height: 16 },
+                                                            Format::R8G8B8A8Unorm,
+                                                            &queue).unwrap();
+
+        assert_eq!(image.dimensions(), Dimensions::Dim2d { width: 16, height: 16 });
+        submission.wait(Duration::from_secs(5)).unwrap();
+    }
+
+    #[test]
+    fn from_slice() {
+        let (_, queue) = gfx_dev_and_queue!();
+
+        let data = vec![[0u8, 0, 0xff, 0xff]; 8 * 4];
+        let (_, submission) = ImmutableImage::from_slice(&data,
+                                                         Dimensions::Dim2d { width: 8, height: 4 },
+                                                         Format::R8G8B8A8Unorm, &queue).unwrap();
+        submission.wait(Duration::from_secs(5)).unwrap();
+    }
+
+    #[test]
+    fn from_iter_same_family() {
+        let (_, queue) = gfx_dev_and_queue!();
+
+        let data = iter::repeat(0xffu8).take(16 * 16 * 4 * 3);
+        let dimensions = Dimensions::Dim2dArray { width: 16, height: 16, array_layers: 3 };
+        let (_, submission) = ImmutableImage::from_iter_with_transfer_queue(data, dimensions,
+                                                                            Format::R8G8B8A8Unorm,
+                                                                            &queue, &queue)
+                                                                            .unwrap();
+        submission.wait(Duration::from_secs(5)).unwrap();
+    }
+
+    #[test]
+    fn from_iter_cross_family() {
+        let instance = instance!();
+        let physical = match instance::PhysicalDevice::enumerate(&instance).next() {
+            Some(p) => p,
+            None => return
+        };
+
+        let requests = [
+            QueueRequest::graphics(1.0),
+            QueueRequest::transfers(0.5).dedicated(),
+        ];
+
+        let selection = match QueuesSelection::new(&physical, &requests) {
+            Ok(s) => s,
+            Err(QueuesSelectionError::NoSuitableQueueFamily(_)) => return,
+            Err(err) => panic!("{:?}", err),
+        };
+
+        // The ownership is only transferred between two different families.
+        if selection.family(0).id() == selection.family(1).id() {
+            return;
+        }
+
+        let (device, queues) = Device::new(&physical, &Features::none(),
+                                           &DeviceExtensions::none(),
+                                           selection.families()).unwrap();
+        let queues = selection.assign(queues);
+        let (queue, transfer_queue) = (&queues[0], &queues[1]);
+
+        let data = iter::repeat(0xffu8).take(16 * 16 * 4);
+        let dimensions = Dimensions::Dim2d { width: 16, height: 16 };
+        let (image, submission) = {
+            let format = Format::R8G8B8A8Unorm;
+            ImmutableImage::from_iter_with_transfer_queue(data, dimensions, format,
+                                                          transfer_queue, queue).unwrap()
+        };
+
+        // After the acquire, the image must be usable by the family of `queue`.
+        let destination = CpuAccessibleBuffer::<[u8]>::array(&device, 16 * 16 * 4,
+                                                             &BufferUsage::transfer_dest(),
+                                                             Some(queue.family())).unwrap();
+        let read = PrimaryCommandBufferBuilder::new(&device, queue.family())
+                       .copy_color_image_to_buffer(&destination, &image, 0, 0 .. 1, [0, 0, 0],
+                                                   [16, 16, 1])
+                       .build();
+        let read_submission = submit(&read, queue).unwrap();
+
+        submission.wait(Duration::from_secs(5)).unwrap();
+        read_submission.wait(Duration::from_secs(5)).unwrap();
+        assert!(destination.read(Duration::new(0, 0)).unwrap().iter().all(|&b| b == 0xff));
+    }
+}
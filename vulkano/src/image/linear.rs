@@ -0,0 +1,612 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Image whose content is directly accessible by the CPU.
+//!
+//! A `LinearImage` is created with linear tiling in host-visible memory. Its texels are stored
+//! row by row, which means that the CPU can write or read them directly without going through a
+//! staging buffer. This is useful for example for video frames or for textures generated in
+//! software.
+//!
+//! Each row of the image starts `row_pitch` bytes after the start of the previous row, where
+//! `row_pitch` is given by the Vulkan implementation and may be larger than the size of a row
+//! of texels. Use the `LinearLayout` of the lock to compute the position of a texel.
+//!
+//! Linear tiling is much less capable than optimal tiling. Implementations are only required to
+//! support linear tiling for a few formats and usages, and accessing a linear image from the GPU
+//! can be slower than accessing an optimal image.
+
+use std::cmp;
+use std::mem;
+use std::iter::Empty;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::ops::Range;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::Weak;
+use std::time::Duration;
+use smallvec::SmallVec;
+
+use command_buffer::Submission;
+use device::Device;
+use format::ClearValue;
+use format::FormatDesc;
+use format::FormatTy;
+use image::Dimensions;
+use image::ImageDimensions;
+use image::Swizzle;
+use image::ViewType;
+use image::sys::ImageCreationError;
+use image::sys::Layout;
+use image::sys::LinearLayout;
+use image::sys::UnsafeImage;
+use image::sys::UnsafeImageView;
+use image::sys::Usage;
+use image::traits::AccessRange;
+use image::traits::GpuAccessResult;
+use image::traits::Image;
+use image::traits::ImageClearValue;
+use image::traits::ImageContent;
+use image::traits::ImageView;
+use image::traits::Transition;
+use instance::QueueFamily;
+use memory::CpuAccess as MemCpuAccess;
+use memory::DedicatedAlloc;
+use memory::DedicatedAllocHint;
+use memory::MemoryUsage;
+use memory::pool::AllocLayout;
+use memory::pool::MemoryPool;
+use memory::pool::MemoryPoolAlloc;
+use memory::pool::StdMemoryPool;
+use memory::pool::alloc_for_resource;
+use sync::FenceWaitError;
+use sync::Sharing;
+
+/// Two-dimensional image with linear tiling in host-visible memory.
+#[derive(Debug)]
+pub struct LinearImage<F, A = Arc<StdMemoryPool>> where A: MemoryPool {
+    // Inner implementation.
+    image: UnsafeImage,
+
+    // We maintain a view of the whole image.
+    view: UnsafeImageView,
+
+    // Memory used to back the image.
+    memory: A::Alloc,
+
+    // Layout of the image in memory.
+    layout: LinearLayout,
+
+    // Format.
+    format: F,
+
+    // Queue families allowed to access this image.
+    queue_families: SmallVec<[u32; 4]>,
+
+    // Additional info behind a mutex. Also locked while the CPU accesses the image, so that
+    // submissions that use the image block until the access is over.
+    guarded: Mutex<Guarded>,
+}
+
+#[derive(Debug)]
+struct Guarded {
+    // If false, the image is still in the preinitialized layout.
+    correct_layout: bool,
+
+    // The latest submissions that read from this image.
+    read_submissions: SmallVec<[Weak<Submission>; 4]>,
+
+    // The latest submission that writes to this image.
+    write_submission: Option<Weak<Submission>>,         // TODO: can use `Weak::new()` once it's stabilized
+}
+
+impl<F> LinearImage<F> {
+    /// Creates a new image with the given dimensions, format and usage.
+    ///
+    /// Returns an error if the backend doesn't support linear tiling for this format and usage.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the format is not a color format.
+    ///
+    #[inline]
+    pub fn new<'a, I>(device: &Arc<Device>, dimensions: [u32; 2], format: F, usage: &Usage,
+                      queue_families: I)
+                      -> Result<Arc<LinearImage<F>>, ImageCreationError>
+        where F: FormatDesc,
+              I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        LinearImage::with_pool(device, dimensions, format, usage, queue_families,
+                               &Device::standard_pool(device))
+    }
+}
+
+impl<F, A> LinearImage<F, A> where A: MemoryPool {
+    /// Same as `new`, but allocates the memory of the image from `pool` instead of the standard
+    /// memory pool of the device.
+    ///
+    /// The pool must return allocations that are mapped when they are in a host-visible memory
+    /// type.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the format is not a color format.
+    ///
+    #[inline]
+    pub fn with_pool<'a, I>(device: &Arc<Device>, dimensions: [u32; 2], format: F, usage: &Usage,
+                            queue_families: I, pool: &A)
+                            -> Result<Arc<LinearImage<F, A>>, ImageCreationError>
+        where F: FormatDesc,
+              I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        LinearImage::with_pool_and_hint(device, dimensions, format, usage, queue_families, pool,
+                                        DedicatedAllocHint::Auto)
+    }
+
+    /// Same as `with_pool`, but `dedicated` controls whether the image gets a memory allocation
+    /// of its own.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the format is not a color format.
+    ///
+    pub fn with_pool_and_hint<'a, I>(device: &Arc<Device>, dimensions: [u32; 2], format: F,
+                                     usage: &Usage, queue_families: I, pool: &A,
+                                     dedicated: DedicatedAllocHint)
+                                     -> Result<Arc<LinearImage<F, A>>, ImageCreationError>
+        where F: FormatDesc,
+              I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        match format.format().ty() {
+            FormatTy::Float | FormatTy::Uint | FormatTy::Sint => (),
+            _ => panic!("linear images must have a color format")
+        };
+
+        let queue_families = queue_families.into_iter().map(|f| f.id())
+                                           .collect::<SmallVec<[u32; 4]>>();
+
+        let image_dims = ImageDimensions::Dim2d { width: dimensions[0], height: dimensions[1],
+                                                  array_layers: 1, cubemap_compatible: false };
+
+        let (image, mem_reqs) = unsafe {
+            if queue_families.len() >= 2 {
+                try!(UnsafeImage::new(device, usage, format.format(), image_dims, 1, 1,
+                                      Sharing::Concurrent(queue_families.iter().cloned()),
+                                      true, true))
+            } else {
+                try!(UnsafeImage::new(device, usage, format.format(), image_dims, 1, 1,
+                                      Sharing::Exclusive::<Empty<u32>>, true, true))
+            }
+        };
+
+        // Vk specs guarantee that there's always a host-visible memory type. We don't use
+        // `MemoryUsage::Upload`, as it could waste the scarce memory that is both host-visible
+        // and device-local on large staging resources.
+        let mem_ty = MemoryUsage::Staging.choose(device.physical_device(),
+                                                 mem_reqs.memory_type_bits).unwrap();
+
+        let mem = try!(alloc_for_resource(pool, mem_ty, &mem_reqs, AllocLayout::Linear,
+                                          dedicated, DedicatedAlloc::Image(&image)));
+        debug_assert!((mem.offset() % mem_reqs.alignment) == 0);
+        debug_assert!(mem.mapped_memory().is_some());
+        unsafe { try!(image.bind_memory(mem.memory(), mem.offset())); }
+
+        let layout = unsafe { image.color_linear_layout(0) };
+
+        let view = unsafe {
+            try!(UnsafeImageView::raw(&image, ViewType::Dim2d, 0 .. 1, 0 .. 1,
+                                      Swizzle::default()))
+        };
+
+        Ok(Arc::new(LinearImage {
+            image: image,
+            view: view,
+            memory: mem,
+            layout: layout,
+            format: format,
+            queue_families: queue_families,
+            guarded: Mutex::new(Guarded {
+                correct_layout: false,
+                read_submissions: SmallVec::new(),
+                write_submission: None,
+            }),
+        }))
+    }
+}
+
+impl<F, A> LinearImage<F, A> where A: MemoryPool {
+    /// Returns the dimensions of the image.
+    #[inline]
+    pub fn dimensions(&self) -> [u32; 2] {
+        let dims = self.image.dimensions();
+        [dims.width(), dims.height()]
+    }
+
+    /// Returns the layout of the texels of the image in memory.
+    #[inline]
+    pub fn layout(&self) -> LinearLayout {
+        self.layout
+    }
+
+    /// Locks the image in order to read its content.
+    ///
+    /// If the image is currently being written by the GPU, this function will block until either
+    /// the image is available or the timeout is reached.
+    ///
+    /// After this function successfully locks the image, any attempt to submit a command buffer
+    /// that uses it will block until you unlock it.
+    pub fn read(&self, timeout: Duration) -> Result<ReadLock, FenceWaitError> {
+        let guarded = self.guarded.lock().unwrap();
+
+        if let Some(submission) = guarded.write_submission.clone().and_then(|s| s.upgrade()) {
+            try!(submission.wait(timeout));
+        }
+
+        Ok(ReadLock {
+            inner: unsafe { self.map_memory() },
+            layout: self.layout,
+            lock: guarded,
+        })
+    }
+
+    /// Locks the image in order to write its content.
+    ///
+    /// If the image is currently in use by the GPU, this function will block until either the
+    /// image is available or the timeout is reached.
+    ///
+    /// After this function successfully locks the image, any attempt to submit a command buffer
+    /// that uses it will block until you unlock it.
+    pub fn write(&self, timeout: Duration) -> Result<WriteLock, FenceWaitError> {
+        let mut guarded = self.guarded.lock().unwrap();
+
+        for submission in guarded.read_submissions.drain() {
+            if let Some(submission) = submission.upgrade() {
+                try!(submission.wait(timeout));
+            }
+        }
+
+        if let Some(submission) = guarded.write_submission.take().and_then(|s| s.upgrade()) {
+            try!(submission.wait(timeout));
+        }
+
+        Ok(WriteLock {
+            inner: unsafe { self.map_memory() },
+            layout: self.layout,
+            lock: guarded,
+        })
+    }
+
+    // Returns an access to the memory of the subresource of the image.
+    unsafe fn map_memory(&self) -> MemCpuAccess<[u8]> {
+        let start = self.memory.offset() + self.layout.offset;
+        self.memory.mapped_memory().unwrap().read_write(start .. start + self.layout.size)
+    }
+}
+
+// Returns the range of bytes of the row `y` within the subresource described by `layout`. The
+// implementation isn't required to pad the last row, so it is clamped to the subresource size.
+#[inline]
+fn row_range(layout: &LinearLayout, y: u32) -> Range<usize> {
+    let start = y as usize * layout.row_pitch;
+    let end = cmp::min(start + layout.row_pitch, layout.size);
+    start .. end
+}
+
+unsafe impl<F, A> Image for LinearImage<F, A> where F: 'static + Send + Sync, A: MemoryPool {
+    #[inline]
+    fn inner(&self) -> &UnsafeImage {
+        &self.image
+    }
+
+    #[inline]
+    fn blocks(&self, _: Range<u32>, _: Range<u32>) -> Vec<(u32, u32)> {
+        vec![(0, 0)]
+    }
+
+    #[inline]
+    fn block_mipmap_levels_range(&self, block: (u32, u32)) -> Range<u32> {
+        0 .. 1
+    }
+
+    #[inline]
+    fn block_array_layers_range(&self, block: (u32, u32)) -> Range<u32> {
+        0 .. 1
+    }
+
+    #[inline]
+    fn initial_layout(&self, _: (u32, u32), _: Layout) -> (Layout, bool, bool) {
+        // The content may have been written by the host.
+        (Layout::General, true, false)
+    }
+
+    #[inline]
+    fn final_layout(&self, _: (u32, u32), _: Layout) -> (Layout, bool, bool) {
+        // The content may be read by the host afterwards.
+        (Layout::General, true, false)
+    }
+
+    fn needs_fence(&self, access: &mut Iterator<Item = AccessRange>) -> Option<bool> {
+        Some(true)
+    }
+
+    unsafe fn gpu_access(&self, ranges: &mut Iterator<Item = AccessRange>,
+                         submission: &Arc<Submission>) -> GpuAccessResult
+    {
+        let queue_id = submission.queue().family().id();
+        if self.queue_families.iter().find(|&&id| id == queue_id).is_none() {
+            panic!("Trying to submit to family {} an image suitable for families {:?}",
+                   queue_id, self.queue_families);
+        }
+
+        let mut guarded = self.guarded.lock().unwrap();
+
+        let is_written = {
+            let mut written = false;
+            while let Some(r) = ranges.next() { if r.write { written = true; break; } }
+            written
+        };
+
+        let dependencies = if is_written {
+            let write_dep = mem::replace(&mut guarded.write_submission,
+                                         Some(Arc::downgrade(submission)));
+
+            let read_submissions = mem::replace(&mut guarded.read_submissions,
+                                                SmallVec::new());
+
+            // We use a temporary variable to bypass a lifetime error in rustc.
+            let list = read_submissions.into_iter()
+                                       .chain(write_dep.into_iter())
+                                       .filter_map(|s| s.upgrade())
+                                       .collect::<Vec<_>>();
+            list
+
+        } else {
+            guarded.read_submissions.push(Arc::downgrade(submission));
+            guarded.write_submission.clone().and_then(|s| s.upgrade()).into_iter().collect()
+        };
+
+        // Transitioning from the preinitialized layout keeps what the host has written.
+        let transition = if !guarded.correct_layout {
+            vec![Transition {
+                block: (0, 0),
+                from: Layout::Preinitialized,
+                to: Layout::General,
+            }]
+        } else {
+            vec![]
+        };
+
+        guarded.correct_layout = true;
+
+        GpuAccessResult {
+            dependencies: dependencies,
+            additional_wait_semaphore: None,
+            additional_signal_semaphore: None,
+            before_transitions: transition,
+            after_transitions: vec![],
+        }
+    }
+}
+
+unsafe impl<F, A> ImageClearValue<F::ClearValue> for LinearImage<F, A>
+    where F: FormatDesc + 'static + Send + Sync, A: MemoryPool
+{
+    #[inline]
+    fn decode(&self, value: F::ClearValue) -> Option<ClearValue> {
+        Some(self.format.decode_clear_value(value))
+    }
+}
+
+unsafe impl<P, F, A> ImageContent<P> for LinearImage<F, A>
+    where F: 'static + Send + Sync, A: MemoryPool
+{
+    #[inline]
+    fn matches_format(&self) -> bool {
+        true        // FIXME:
+    }
+}
+
+unsafe impl<F, A> ImageView for LinearImage<F, A>
+    where F: 'static + Send + Sync, A: MemoryPool
+{
+    #[inline]
+    fn parent(&self) -> &Image {
+        self
+    }
+
+    #[inline]
+    fn parent_arc(me: &Arc<Self>) -> Arc<Image> where Self: Sized {
+        me.clone() as Arc<_>
+    }
+
+    #[inline]
+    fn dimensions(&self) -> Dimensions {
+        let dims = self.image.dimensions();
+        Dimensions::Dim2d { width: dims.width(), height: dims.height() }
+    }
+
+    #[inline]
+    fn blocks(&self) -> Vec<(u32, u32)> {
+        vec![(0, 0)]
+    }
+
+    #[inline]
+    fn inner(&self) -> &UnsafeImageView {
+        &self.view
+    }
+
+    #[inline]
+    fn descriptor_set_storage_image_layout(&self) -> Layout {
+        Layout::General
+    }
+
+    #[inline]
+    fn descriptor_set_combined_image_sampler_layout(&self) -> Layout {
+        Layout::General
+    }
+
+    #[inline]
+    fn descriptor_set_sampled_image_layout(&self) -> Layout {
+        Layout::General
+    }
+
+    #[inline]
+    fn descriptor_set_input_attachment_layout(&self) -> Layout {
+        Layout::General
+    }
+
+    #[inline]
+    fn identity_swizzle(&self) -> bool {
+        true
+    }
+}
+
+/// Object that can be used to read the content of a `LinearImage`.
+///
+/// Dereferences to the bytes of the image, starting with the first row.
+pub struct ReadLock<'a> {
+    inner: MemCpuAccess<'a, [u8]>,
+    layout: LinearLayout,
+    lock: MutexGuard<'a, Guarded>,
+}
+
+impl<'a> ReadLock<'a> {
+    /// Returns the layout of the texels in memory.
+    #[inline]
+    pub fn layout(&self) -> LinearLayout {
+        self.layout
+    }
+
+    /// Returns the bytes of the row `y` of the image, including the padding at the end of the
+    /// row if there is any. The last row stops at the end of the subresource, so it can be
+    /// shorter than `row_pitch`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `y` is out of range.
+    ///
+    #[inline]
+    pub fn row(&self, y: u32) -> &[u8] {
+        let range = row_range(&self.layout, y);
+        &self[range]
+    }
+}
+
+impl<'a> Deref for ReadLock<'a> {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        self.inner.deref()
+    }
+}
+
+/// Object that can be used to read or write the content of a `LinearImage`.
+///
+/// Dereferences to the bytes of the image, starting with the first row.
+pub struct WriteLock<'a> {
+    inner: MemCpuAccess<'a, [u8]>,
+    layout: LinearLayout,
+    lock: MutexGuard<'a, Guarded>,
+}
+
+impl<'a> WriteLock<'a> {
+    /// Returns the layout of the texels in memory.
+    #[inline]
+    pub fn layout(&self) -> LinearLayout {
+        self.layout
+    }
+
+    /// Returns the bytes of the row `y` of the image, including the padding at the end of the
+    /// row if there is any. The last row stops at the end of the subresource, so it can be
+    /// shorter than `row_pitch`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `y` is out of range.
+    ///
+    #[inline]
+    pub fn row_mut(&mut self, y: u32) -> &mut [u8] {
+        let range = row_range(&self.layout, y);
+        &mut self[range]
+    }
+}
+
+impl<'a> Deref for WriteLock<'a> {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        self.inner.deref()
+    }
+}
+
+impl<'a> DerefMut for WriteLock<'a> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.inner.deref_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use format::Format;
+    use image::ImageCreationError;
+    use image::LinearImage;
+    use image::Usage;
+    use image::sys::LinearLayout;
+
+    use super::row_range;
+
+    #[test]
+    fn last_row_clamped() {
+        let layout = LinearLayout {
+            offset: 0,
+            size: 3 * 256 + 64,
+            row_pitch: 256,
+            array_pitch: 0,
+            depth_pitch: 0,
+        };
+
+        assert_eq!(row_range(&layout, 0), 0 .. 256);
+        assert_eq!(row_range(&layout, 3), 3 * 256 .. 3 * 256 + 64);
+    }
+
+    #[test]
+    fn write_rows() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let usage = Usage { transfer_source: true, .. Usage::none() };
+        let image = match LinearImage::new(&device, [16, 16], Format::R8G8B8A8Unorm, &usage,
+                                           Some(queue.family()))
+        {
+            Ok(img) => img,
+            // Linear tiling support is optional.
+            Err(ImageCreationError::FormatNotSupported) => return,
+            Err(ImageCreationError::UnsupportedUsage) => return,
+            Err(err) => panic!("{:?}", err)
+        };
+
+        assert!(image.layout().row_pitch >= 16 * 4);
+
+        {
+            let mut lock = image.write(Duration::new(0, 0)).unwrap();
+            for byte in lock.row_mut(15)[.. 16 * 4].iter_mut() {
+                *byte = 0xff;
+            }
+        }
+
+        let lock = image.read(Duration::new(0, 0)).unwrap();
+        assert!(lock.row(15)[.. 16 * 4].iter().all(|&b| b == 0xff));
+    }
+}
@@ -40,15 +40,31 @@
 //! - An `AttachmentImage` can be used when you want to draw to an image.
 //! - An `ImmutableImage` stores data which never need be changed after the initial upload,
 //!   like a texture.
+//! - A `LinearImage` can be read and written directly by the CPU, without a staging buffer.
+//! - An `ExternalImage` wraps an image that was created outside of vulkano, for example by
+//!   another library.
+//!
+//! A `SubImageView` can be used to access only some of the mipmap levels and array layers of an
+//! image, for example to render to one layer of an array image.
 //!
 //! # Low-level informations
 //!
 //! To be written.
 //!
 
+use std::cmp;
+
+use format::Format;
+use format::FormatTy;
+use vk;
+
 pub use self::attachment::AttachmentImage;
+pub use self::external::ExternalImage;
 pub use self::immutable::ImmutableImage;
+pub use self::immutable::ImmutableImageCreationError;
+pub use self::linear::LinearImage;
 pub use self::storage::StorageImage;
+pub use self::subview::SubImageView;
 pub use self::swapchain::SwapchainImage;
 pub use self::sys::ImageCreationError;
 pub use self::sys::Layout;
@@ -57,8 +73,11 @@ pub use self::traits::Image;
 pub use self::traits::ImageView;
 
 pub mod attachment;     // TODO: make private
+mod external;
 pub mod immutable;      // TODO: make private
+pub mod linear;
 mod storage;
+mod subview;
 pub mod swapchain;      // TODO: make private
 pub mod sys;
 pub mod traits;
@@ -111,23 +130,38 @@ pub struct Swizzle {
     pub a: ComponentSwizzle,
 }
 
+impl Swizzle {
+    /// Returns true if all the components return their own value.
+    ///
+    /// A component that is `Identity` or that refers to itself (for example `r: Red`) is
+    /// identity-swizzled.
+    #[inline]
+    pub fn is_identity(&self) -> bool {
+        (self.r == ComponentSwizzle::Identity || self.r == ComponentSwizzle::Red) &&
+        (self.g == ComponentSwizzle::Identity || self.g == ComponentSwizzle::Green) &&
+        (self.b == ComponentSwizzle::Identity || self.b == ComponentSwizzle::Blue) &&
+        (self.a == ComponentSwizzle::Identity || self.a == ComponentSwizzle::Alpha)
+    }
+}
+
 /// Describes the value that an individual component must return when being accessed.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u32)]
 pub enum ComponentSwizzle {
     /// Returns the value that this component should normally have.
-    Identity,
+    Identity = vk::COMPONENT_SWIZZLE_IDENTITY,
     /// Always return zero.
-    Zero,
+    Zero = vk::COMPONENT_SWIZZLE_ZERO,
     /// Always return one.
-    One,
+    One = vk::COMPONENT_SWIZZLE_ONE,
     /// Returns the value of the first component.
-    Red,
+    Red = vk::COMPONENT_SWIZZLE_R,
     /// Returns the value of the second component.
-    Green,
+    Green = vk::COMPONENT_SWIZZLE_G,
     /// Returns the value of the third component.
-    Blue,
+    Blue = vk::COMPONENT_SWIZZLE_B,
     /// Returns the value of the fourth component.
-    Alpha,
+    Alpha = vk::COMPONENT_SWIZZLE_A,
 }
 
 impl Default for ComponentSwizzle {
@@ -137,6 +171,97 @@ impl Default for ComponentSwizzle {
     }
 }
 
+/// Describes which aspect of an image a view gives access to.
+///
+/// Color images only have a color aspect, but a view of an image with a combined depth-stencil
+/// format can choose to give access to only the depth or only the stencil. Views that contain
+/// both the depth and the stencil aspects can be used as framebuffer attachments but can't be
+/// used in descriptor sets.
+///
+/// Images with a multi-planar format have a color aspect that covers all the planes, and one
+/// aspect per plane.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImageAspect {
+    /// The color components of the image.
+    Color,
+    /// The depth component of the image.
+    Depth,
+    /// The stencil component of the image.
+    Stencil,
+    /// Both the depth and the stencil components of the image.
+    DepthStencil,
+    /// The first plane of an image with a multi-planar format.
+    Plane0,
+    /// The second plane of an image with a multi-planar format.
+    Plane1,
+    /// The third plane of an image with a multi-planar format.
+    Plane2,
+}
+
+impl ImageAspect {
+    /// Returns the aspect that covers all the components of the given format.
+    #[inline]
+    pub fn from_format(format: Format) -> ImageAspect {
+        match format.ty() {
+            FormatTy::Float | FormatTy::Uint | FormatTy::Sint | FormatTy::Compressed => {
+                ImageAspect::Color
+            },
+            FormatTy::Depth => ImageAspect::Depth,
+            FormatTy::Stencil => ImageAspect::Stencil,
+            FormatTy::DepthStencil => ImageAspect::DepthStencil,
+        }
+    }
+
+    /// Returns true if `self` is a subset of the components of the given format.
+    #[inline]
+    pub fn is_subset_of(&self, format: Format) -> bool {
+        match (ImageAspect::from_format(format), *self) {
+            (a, b) if a == b => true,
+            (ImageAspect::DepthStencil, ImageAspect::Depth) => true,
+            (ImageAspect::DepthStencil, ImageAspect::Stencil) => true,
+            (_, aspect) => match aspect.plane() {
+                Some(plane) => format.plane_format(plane).is_some(),
+                None => false,
+            },
+        }
+    }
+
+    /// Returns the index of the plane if this is a plane aspect.
+    #[inline]
+    pub fn plane(&self) -> Option<u32> {
+        match *self {
+            ImageAspect::Plane0 => Some(0),
+            ImageAspect::Plane1 => Some(1),
+            ImageAspect::Plane2 => Some(2),
+            _ => None,
+        }
+    }
+
+    /// Returns the format of the data that this aspect of an image of the given format gives
+    /// access to. This is `format` itself, except for plane aspects.
+    #[inline]
+    pub fn format(&self, format: Format) -> Format {
+        match self.plane().and_then(|p| format.plane_format(p)) {
+            Some(f) => f,
+            None => format,
+        }
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn to_vk_bits(&self) -> u32 {     // TODO: hacky
+        match *self {
+            ImageAspect::Color => vk::IMAGE_ASPECT_COLOR_BIT,
+            ImageAspect::Depth => vk::IMAGE_ASPECT_DEPTH_BIT,
+            ImageAspect::Stencil => vk::IMAGE_ASPECT_STENCIL_BIT,
+            ImageAspect::DepthStencil => vk::IMAGE_ASPECT_DEPTH_BIT | vk::IMAGE_ASPECT_STENCIL_BIT,
+            ImageAspect::Plane0 => vk::IMAGE_ASPECT_PLANE_0_BIT_KHR,
+            ImageAspect::Plane1 => vk::IMAGE_ASPECT_PLANE_1_BIT_KHR,
+            ImageAspect::Plane2 => vk::IMAGE_ASPECT_PLANE_2_BIT_KHR,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Dimensions {
     Dim1d { width: u32 },
@@ -325,4 +450,23 @@ impl ImageDimensions {
             ImageDimensions::Dim3d { .. }  => 1,
         }
     }
+
+    /// Returns the width, height and depth of the given mipmap level.
+    ///
+    /// Each level is half the size of the previous one, rounded down, with a minimum of 1.
+    #[inline]
+    pub fn mipmap_extent(&self, level: u32) -> [u32; 3] {
+        let shrink = |d: u32| if level >= 32 { 1 } else { cmp::max(1, d >> level) };
+        [shrink(self.width()), shrink(self.height()), shrink(self.depth())]
+    }
+
+    /// Returns the maximum number of mipmap levels of an image with these dimensions.
+    ///
+    /// Mipmap levels are generated until all the dimensions are 1, so this depends on the
+    /// largest dimension.
+    #[inline]
+    pub fn max_mipmaps(&self) -> u32 {
+        let largest = cmp::max(cmp::max(self.width(), self.height()), self.depth());
+        32 - largest.leading_zeros()
+    }
 }
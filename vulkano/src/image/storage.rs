@@ -21,6 +21,7 @@ use format::ClearValue;
 use format::FormatDesc;
 use format::FormatTy;
 use image::Dimensions;
+use image::Swizzle;
 use image::sys::ImageCreationError;
 use image::sys::Layout;
 use image::sys::UnsafeImage;
@@ -34,14 +35,22 @@ use image::traits::ImageContent;
 use image::traits::ImageView;
 use image::traits::Transition;
 use instance::QueueFamily;
+use memory::DedicatedAlloc;
+use memory::DedicatedAllocHint;
+use memory::MemoryUsage;
 use memory::pool::AllocLayout;
 use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
 use memory::pool::StdMemoryPool;
+use memory::pool::alloc_for_resource;
 use sync::Sharing;
 
 /// General-purpose image in device memory. Can be used for any usage, but will be slower than a
 /// specialized image.
+///
+/// The image always stays in the `General` layout, which makes it suitable for being read and
+/// written by compute shaders. Use `StorageImage::new` for an image that is used as a storage
+/// image, and `StorageImage::with_usage` if you need other usages as well.
 #[derive(Debug)]
 pub struct StorageImage<F, A = Arc<StdMemoryPool>> where A: MemoryPool {
     // Inner implementation.
@@ -68,8 +77,9 @@ pub struct StorageImage<F, A = Arc<StdMemoryPool>> where A: MemoryPool {
 
 #[derive(Debug)]
 struct Guarded {
-    // If false, the image is still in the undefined layout.
-    correct_layout: bool,
+    // Layout the image will be in once all the submissions that use it so far are finished.
+    // Starts as `Undefined`.
+    layout: Layout,
 
     // The latest submissions that read from this image.
     read_submissions: SmallVec<[Weak<Submission>; 4]>,
@@ -80,17 +90,23 @@ struct Guarded {
 
 impl<F> StorageImage<F> {
     /// Creates a new image with the given dimensions and format.
+    ///
+    /// The image can be used as a storage image, can be sampled, and can be the source or the
+    /// destination of transfer operations.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the format is not a color format.
+    ///
+    #[inline]
     pub fn new<'a, I>(device: &Arc<Device>, dimensions: Dimensions, format: F, queue_families: I)
                       -> Result<Arc<StorageImage<F>>, ImageCreationError>
         where F: FormatDesc,
-                 I: IntoIterator<Item = QueueFamily<'a>>
+              I: IntoIterator<Item = QueueFamily<'a>>
     {
-        let is_depth = match format.format().ty() {
-            FormatTy::Depth => true,
-            FormatTy::DepthStencil => true,
-            FormatTy::Stencil => true,
-            FormatTy::Compressed => panic!(),
-            _ => false
+        match format.format().ty() {
+            FormatTy::Float | FormatTy::Uint | FormatTy::Sint => (),
+            _ => panic!("storage images must have a color format")
         };
 
         let usage = Usage {
@@ -98,43 +114,78 @@ impl<F> StorageImage<F> {
             transfer_dest: true,
             sampled: true,
             storage: true,
-            color_attachment: !is_depth,
-            depth_stencil_attachment: is_depth,
-            input_attachment: true,
-            transient_attachment: false,
+            .. Usage::none()
         };
 
+        StorageImage::with_usage(device, dimensions, format, &usage, queue_families)
+    }
+
+    /// Same as `new`, but lets you specify the usage of the image.
+    ///
+    /// The image stays in the `General` layout whatever its usage is.
+    #[inline]
+    pub fn with_usage<'a, I>(device: &Arc<Device>, dimensions: Dimensions, format: F,
+                             usage: &Usage, queue_families: I)
+                             -> Result<Arc<StorageImage<F>>, ImageCreationError>
+        where F: FormatDesc,
+              I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        StorageImage::with_usage_and_pool(device, dimensions, format, usage, queue_families,
+                                          &Device::standard_pool(device))
+    }
+}
+
+impl<F, A> StorageImage<F, A> where A: MemoryPool {
+    /// Same as `with_usage`, but allocates the memory of the image from `pool` instead of the
+    /// standard memory pool of the device.
+    #[inline]
+    pub fn with_usage_and_pool<'a, I>(device: &Arc<Device>, dimensions: Dimensions, format: F,
+                                      usage: &Usage, queue_families: I, pool: &A)
+                                      -> Result<Arc<StorageImage<F, A>>, ImageCreationError>
+        where F: FormatDesc,
+              I: IntoIterator<Item = QueueFamily<'a>>
+    {
+        StorageImage::with_usage_pool_and_hint(device, dimensions, format, usage, queue_families,
+                                               pool, DedicatedAllocHint::Auto)
+    }
+
+    /// Same as `with_usage_and_pool`, but `dedicated` controls whether the image gets a memory
+    /// allocation of its own.
+    pub fn with_usage_pool_and_hint<'a, I>(device: &Arc<Device>, dimensions: Dimensions,
+                                           format: F, usage: &Usage, queue_families: I, pool: &A,
+                                           dedicated: DedicatedAllocHint)
+                                           -> Result<Arc<StorageImage<F, A>>, ImageCreationError>
+        where F: FormatDesc,
+              I: IntoIterator<Item = QueueFamily<'a>>
+    {
         let queue_families = queue_families.into_iter().map(|f| f.id())
                                            .collect::<SmallVec<[u32; 4]>>();
 
         let (image, mem_reqs) = unsafe {
-            let sharing = if queue_families.len() >= 2 {
-                Sharing::Concurrent(queue_families.iter().cloned())
+            if queue_families.len() >= 2 {
+                try!(UnsafeImage::new(device, usage, format.format(),
+                                      dimensions.to_image_dimensions(), 1, 1,
+                                      Sharing::Concurrent(queue_families.iter().cloned()),
+                                      false, false))
             } else {
-                Sharing::Exclusive
-            };
-
-            try!(UnsafeImage::new(device, &usage, format.format(), dimensions.to_image_dimensions(),
-                                  1, 1, Sharing::Exclusive::<Empty<u32>>, false, false))
+                try!(UnsafeImage::new(device, usage, format.format(),
+                                      dimensions.to_image_dimensions(), 1, 1,
+                                      Sharing::Exclusive::<Empty<u32>>, false, false))
+            }
         };
 
-        let mem_ty = {
-            let device_local = device.physical_device().memory_types()
-                                     .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0)
-                                     .filter(|t| t.is_device_local());
-            let any = device.physical_device().memory_types()
-                            .filter(|t| (mem_reqs.memory_type_bits & (1 << t.id())) != 0);
-            device_local.chain(any).next().unwrap()
-        };
+        let mem_ty = MemoryUsage::DeviceLocal.choose(device.physical_device(),
+                                                     mem_reqs.memory_type_bits).unwrap();
 
-        let mem = try!(MemoryPool::alloc(&Device::standard_pool(device), mem_ty,
-                                         mem_reqs.size, mem_reqs.alignment, AllocLayout::Optimal));
+        let mem = try!(alloc_for_resource(pool, mem_ty, &mem_reqs, AllocLayout::Optimal,
+                                          dedicated, DedicatedAlloc::Image(&image)));
         debug_assert!((mem.offset() % mem_reqs.alignment) == 0);
         unsafe { try!(image.bind_memory(mem.memory(), mem.offset())); }
 
         let view = unsafe {
             try!(UnsafeImageView::raw(&image, dimensions.to_view_type(), 0 .. image.mipmap_levels(),
-                                      0 .. image.dimensions().array_layers()))
+                                      0 .. image.dimensions().array_layers(),
+                                      Swizzle::default()))
         };
 
         Ok(Arc::new(StorageImage {
@@ -145,7 +196,7 @@ impl<F> StorageImage<F> {
             format: format,
             queue_families: queue_families,
             guarded: Mutex::new(Guarded {
-                correct_layout: false,
+                layout: Layout::Undefined,
                 read_submissions: SmallVec::new(),
                 write_submission: None,
             }),
@@ -179,17 +230,21 @@ unsafe impl<F, A> Image for StorageImage<F, A> where F: 'static + Send + Sync, A
 
     #[inline]
     fn block_array_layers_range(&self, block: (u32, u32)) -> Range<u32> {
-        0 .. 1
+        0 .. self.image.dimensions().array_layers()
     }
 
+    // The layout of the image is tracked across submissions by `gpu_access`, therefore command
+    // buffers don't need to transition the image from or to a fixed layout.
     #[inline]
-    fn initial_layout(&self, _: (u32, u32), _: Layout) -> (Layout, bool, bool) {
-        (Layout::General, false, false)
+    fn initial_layout(&self, _: (u32, u32), first_required_layout: Layout)
+                      -> (Layout, bool, bool)
+    {
+        (first_required_layout, false, false)
     }
 
     #[inline]
-    fn final_layout(&self, _: (u32, u32), _: Layout) -> (Layout, bool, bool) {
-        (Layout::General, false, false)
+    fn final_layout(&self, _: (u32, u32), last_required_layout: Layout) -> (Layout, bool, bool) {
+        (last_required_layout, false, false)
     }
 
     fn needs_fence(&self, access: &mut Iterator<Item = AccessRange>) -> Option<bool> {
@@ -207,11 +262,8 @@ unsafe impl<F, A> Image for StorageImage<F, A> where F: 'static + Send + Sync, A
 
         let mut guarded = self.guarded.lock().unwrap();
 
-        let is_written = {
-            let mut written = false;
-            while let Some(r) = ranges.next() { if r.write { written = true; break; } }
-            written
-        };
+        let ranges = ranges.collect::<SmallVec<[AccessRange; 1]>>();
+        let is_written = ranges.iter().any(|r| r.write);
 
         let dependencies = if is_written {
             let write_dep = mem::replace(&mut guarded.write_submission,
@@ -232,17 +284,23 @@ unsafe impl<F, A> Image for StorageImage<F, A> where F: 'static + Send + Sync, A
             guarded.write_submission.clone().and_then(|s| s.upgrade()).into_iter().collect()
         };
 
-        let transition = if !guarded.correct_layout {
-            vec![Transition {
-                block: (0, 0),
-                from: Layout::Undefined,
-                to: Layout::General,
-            }]
-        } else {
-            vec![]
-        };
-
-        guarded.correct_layout = true;
+        // The image only has one block. If the command buffer expects a different layout than
+        // the one the previous submissions left the image in, a transition is submitted first.
+        // A command buffer that starts from the undefined layout discards the content instead.
+        let mut transition = Vec::new();
+        if let Some(range) = ranges.first() {
+            if range.initial_layout != Layout::Undefined &&
+               range.initial_layout != guarded.layout
+            {
+                transition.push(Transition {
+                    block: (0, 0),
+                    from: guarded.layout,
+                    to: range.initial_layout,
+                });
+            }
+
+            guarded.layout = range.final_layout;
+        }
 
         GpuAccessResult {
             dependencies: dependencies,
@@ -329,8 +387,14 @@ unsafe impl<F, A> ImageView for StorageImage<F, A>
 #[cfg(test)]
 mod tests {
     use super::StorageImage;
+    use command_buffer::PrimaryCommandBufferBuilder;
+    use command_buffer::submit;
     use format::Format;
     use image::Dimensions;
+    use image::ImageView;
+    use image::Usage;
+    use image::sys::Layout;
+    use image::traits::AccessRange;
 
     #[test]
     fn create() {
@@ -338,4 +402,66 @@ mod tests {
         let _img = StorageImage::new(&device, Dimensions::Dim2d { width: 32, height: 32 },
                                      Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
     }
+
+    #[test]
+    fn with_usage() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let usage = Usage {
+            storage: true,
+            color_attachment: true,
+            .. Usage::none()
+        };
+
+        let img = StorageImage::with_usage(&device, Dimensions::Dim2d { width: 32, height: 32 },
+                                           Format::R8G8B8A8Unorm, &usage,
+                                           Some(queue.family())).unwrap();
+        assert!(img.inner().usage_color_attachment());
+        assert!(!img.inner().usage_sampled());
+    }
+
+    #[test]
+    fn create_cubemap() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let _img = StorageImage::new(&device, Dimensions::Cubemap { size: 32 },
+                                     Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+    }
+
+    #[test]
+    fn layout_tracked_across_submissions() {
+        use image::traits::Image;
+
+        let (device, queue) = gfx_dev_and_queue!();
+        let img = StorageImage::new(&device, Dimensions::Dim2d { width: 32, height: 32 },
+                                    Format::R8G8B8A8Unorm, Some(queue.family())).unwrap();
+
+        let cb = PrimaryCommandBufferBuilder::new(&device, queue.family()).build();
+        let submission = submit(&cb, &queue).unwrap();
+
+        let access = |initial, final_| {
+            vec![AccessRange { block: (0, 0), write: true, initial_layout: initial,
+                               final_layout: final_ }]
+        };
+
+        unsafe {
+            let result = img.gpu_access(&mut access(Layout::TransferDstOptimal,
+                                                    Layout::TransferDstOptimal).into_iter(),
+                                        &submission);
+            assert_eq!(result.before_transitions.len(), 1);
+            assert_eq!(result.before_transitions[0].from, Layout::Undefined);
+            assert_eq!(result.before_transitions[0].to, Layout::TransferDstOptimal);
+
+            // The image is already in the right layout.
+            let result = img.gpu_access(&mut access(Layout::TransferDstOptimal,
+                                                    Layout::General).into_iter(),
+                                        &submission);
+            assert!(result.before_transitions.is_empty());
+
+            let result = img.gpu_access(&mut access(Layout::ShaderReadOnlyOptimal,
+                                                    Layout::ShaderReadOnlyOptimal).into_iter(),
+                                        &submission);
+            assert_eq!(result.before_transitions.len(), 1);
+            assert_eq!(result.before_transitions[0].from, Layout::General);
+        }
+    }
 }
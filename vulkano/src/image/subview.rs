@@ -0,0 +1,285 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use image::Dimensions;
+use image::ImageAspect;
+use image::ImageDimensions;
+use image::Swizzle;
+use image::ViewType;
+use image::sys::Layout;
+use image::sys::UnsafeImageView;
+use image::traits::Image;
+use image::traits::ImageView;
+
+use OomError;
+
+/// View of a subset of the mipmap levels and array layers of an image.
+///
+/// The images of vulkano are also views that cover the whole image. A `SubImageView` can be
+/// used instead when only a part of the image must be accessed, for example to render to a
+/// single layer of an array image or to a single mipmap level.
+///
+/// A `SubImageView` can also swizzle the components of the image, for example to sample a
+/// single-channel image as if it was a grayscale RGB image, or give access to only the depth or
+/// only the stencil of an image with a combined depth-stencil format.
+///
+/// The layouts that are used in descriptor sets are the same as for the view of the whole image.
+pub struct SubImageView<I> {
+    image: Arc<I>,
+    view: UnsafeImageView,
+    dimensions: Dimensions,
+}
+
+impl<I> SubImageView<I> where I: ImageView {
+    /// Builds a new view of the given mipmap levels and array layers of `image`, whose components
+    /// are swizzled according to `swizzle`.
+    ///
+    /// Pass `Swizzle::default()` if you don't want any swizzling. Views that aren't
+    /// identity-swizzled can't be used as framebuffer attachments, storage images or input
+    /// attachments.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `mipmap_levels` or `array_layers` is empty or out of range of the image.
+    /// - Panics if the view type doesn't match the dimensions of the image. See
+    ///   `UnsafeImageView::new`.
+    ///
+    #[inline]
+    pub fn new(image: &Arc<I>, ty: ViewType, mipmap_levels: Range<u32>, array_layers: Range<u32>,
+               swizzle: Swizzle) -> Result<Arc<SubImageView<I>>, OomError>
+    {
+        let aspect = ImageAspect::from_format(image.parent().format());
+        SubImageView::with_aspect(image, ty, mipmap_levels, array_layers, swizzle, aspect)
+    }
+
+    /// Same as `new`, but the view only gives access to the given aspect of the image.
+    ///
+    /// This is mostly useful for images with a combined depth-stencil format, as views that cover
+    /// both the depth and the stencil can't be put in a descriptor set. Use `ImageAspect::Depth`
+    /// or `ImageAspect::Stencil` to sample one of them.
+    ///
+    /// # Panic
+    ///
+    /// - Panics for the same reasons as `new`.
+    /// - Panics if `aspect` isn't part of the format of the image.
+    ///
+    pub fn with_aspect(image: &Arc<I>, ty: ViewType, mipmap_levels: Range<u32>,
+                       array_layers: Range<u32>, swizzle: Swizzle, aspect: ImageAspect)
+                       -> Result<Arc<SubImageView<I>>, OomError>
+    {
+        let view = unsafe {
+            try!(UnsafeImageView::raw_aspect(image.parent().inner(), ty, mipmap_levels.clone(),
+                                             array_layers.clone(), swizzle, aspect))
+        };
+
+        let extent = image.parent().dimensions().mipmap_extent(mipmap_levels.start);
+        let num_layers = array_layers.end - array_layers.start;
+
+        let dimensions = match ty {
+            ViewType::Dim1d => Dimensions::Dim1d { width: extent[0] },
+            ViewType::Dim1dArray => {
+                Dimensions::Dim1dArray { width: extent[0], array_layers: num_layers }
+            },
+            ViewType::Dim2d => Dimensions::Dim2d { width: extent[0], height: extent[1] },
+            ViewType::Dim2dArray => {
+                Dimensions::Dim2dArray { width: extent[0], height: extent[1],
+                                         array_layers: num_layers }
+            },
+            ViewType::Dim3d => {
+                Dimensions::Dim3d { width: extent[0], height: extent[1], depth: extent[2] }
+            },
+            ViewType::Cubemap => Dimensions::Cubemap { size: extent[0] },
+            ViewType::CubemapArray => {
+                Dimensions::CubemapArray { size: extent[0], array_layers: num_layers / 6 }
+            },
+        };
+
+        Ok(Arc::new(SubImageView {
+            image: image.clone(),
+            view: view,
+            dimensions: dimensions,
+        }))
+    }
+
+    /// Builds a new view of a single array layer and a single mipmap level of `image`.
+    ///
+    /// The view is one-dimensional, two-dimensional or three-dimensional depending on the
+    /// dimensions of the image.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `layer` or `mipmap_level` is out of range of the image.
+    ///
+    #[inline]
+    pub fn for_layer_mip(image: &Arc<I>, layer: u32, mipmap_level: u32)
+                         -> Result<Arc<SubImageView<I>>, OomError>
+    {
+        let ty = match image.parent().dimensions() {
+            ImageDimensions::Dim1d { .. } => ViewType::Dim1d,
+            ImageDimensions::Dim2d { .. } => ViewType::Dim2d,
+            ImageDimensions::Dim3d { .. } => ViewType::Dim3d,
+        };
+
+        SubImageView::new(image, ty, mipmap_level .. mipmap_level + 1, layer .. layer + 1,
+                          Swizzle::default())
+    }
+
+    /// Returns the image this view was created from.
+    #[inline]
+    pub fn image(&self) -> &Arc<I> {
+        &self.image
+    }
+
+    /// Returns the swizzling of the components of the view.
+    #[inline]
+    pub fn swizzle(&self) -> Swizzle {
+        self.view.swizzle()
+    }
+
+    /// Returns the aspect of the image that this view gives access to.
+    #[inline]
+    pub fn aspect(&self) -> ImageAspect {
+        self.view.aspect()
+    }
+
+    /// Returns the range of mipmap levels of the image that this view covers.
+    #[inline]
+    pub fn mipmap_levels(&self) -> Range<u32> {
+        self.view.mipmap_levels()
+    }
+
+    /// Returns the range of array layers of the image that this view covers.
+    #[inline]
+    pub fn array_layers(&self) -> Range<u32> {
+        self.view.array_layers()
+    }
+}
+
+unsafe impl<I> ImageView for SubImageView<I> where I: ImageView {
+    #[inline]
+    fn parent(&self) -> &Image {
+        self.image.parent()
+    }
+
+    #[inline]
+    fn parent_arc(me: &Arc<Self>) -> Arc<Image> where Self: Sized {
+        ImageView::parent_arc(&me.image)
+    }
+
+    #[inline]
+    fn dimensions(&self) -> Dimensions {
+        self.dimensions
+    }
+
+    #[inline]
+    fn blocks(&self) -> Vec<(u32, u32)> {
+        self.image.parent().blocks(self.view.mipmap_levels(), self.view.array_layers())
+    }
+
+    #[inline]
+    fn inner(&self) -> &UnsafeImageView {
+        &self.view
+    }
+
+    #[inline]
+    fn descriptor_set_storage_image_layout(&self) -> Layout {
+        self.image.descriptor_set_storage_image_layout()
+    }
+
+    #[inline]
+    fn descriptor_set_combined_image_sampler_layout(&self) -> Layout {
+        self.image.descriptor_set_combined_image_sampler_layout()
+    }
+
+    #[inline]
+    fn descriptor_set_sampled_image_layout(&self) -> Layout {
+        self.image.descriptor_set_sampled_image_layout()
+    }
+
+    #[inline]
+    fn descriptor_set_input_attachment_layout(&self) -> Layout {
+        self.image.descriptor_set_input_attachment_layout()
+    }
+
+    #[inline]
+    fn identity_swizzle(&self) -> bool {
+        self.view.identity_swizzle()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use format::Format;
+    use image::AttachmentImage;
+    use image::ComponentSwizzle;
+    use image::Dimensions;
+    use image::ImageAspect;
+    use image::ImageView;
+    use image::StorageImage;
+    use image::SubImageView;
+    use image::Swizzle;
+    use image::ViewType;
+
+    #[test]
+    fn layer_of_array() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let dimensions = Dimensions::Dim2dArray { width: 32, height: 32, array_layers: 4 };
+        let image = StorageImage::new(&device, dimensions, Format::R8G8B8A8Unorm,
+                                      Some(queue.family())).unwrap();
+
+        let view = SubImageView::for_layer_mip(&image, 2, 0).unwrap();
+        assert_eq!(view.dimensions(), Dimensions::Dim2d { width: 32, height: 32 });
+        assert_eq!(view.array_layers(), 2 .. 3);
+        assert_eq!(view.mipmap_levels(), 0 .. 1);
+        assert!(!view.blocks().is_empty());
+    }
+
+    #[test]
+    fn swizzled() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let dimensions = Dimensions::Dim2d { width: 32, height: 32 };
+        let image = StorageImage::new(&device, dimensions, Format::R8G8B8A8Unorm,
+                                      Some(queue.family())).unwrap();
+
+        let swizzle = Swizzle {
+            r: ComponentSwizzle::Red,
+            g: ComponentSwizzle::Red,
+            b: ComponentSwizzle::Red,
+            a: ComponentSwizzle::One,
+        };
+
+        let view = SubImageView::new(&image, ViewType::Dim2d, 0 .. 1, 0 .. 1, swizzle).unwrap();
+        assert_eq!(view.swizzle(), swizzle);
+        assert!(!view.identity_swizzle());
+
+        let view = SubImageView::for_layer_mip(&image, 0, 0).unwrap();
+        assert!(view.identity_swizzle());
+    }
+
+    #[test]
+    fn stencil_aspect() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        // At least one of these two formats must be supported as a depth-stencil attachment.
+        let image = match AttachmentImage::new(&device, [32, 32], Format::D24Unorm_S8Uint) {
+            Ok(img) => img,
+            Err(_) => AttachmentImage::new(&device, [32, 32], Format::D32Sfloat_S8Uint).unwrap(),
+        };
+        assert_eq!(image.inner().aspect(), ImageAspect::DepthStencil);
+
+        let view = SubImageView::with_aspect(&image, ViewType::Dim2d, 0 .. 1, 0 .. 1,
+                                             Swizzle::default(), ImageAspect::Stencil).unwrap();
+        assert_eq!(view.aspect(), ImageAspect::Stencil);
+    }
+}
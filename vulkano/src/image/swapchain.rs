@@ -19,6 +19,7 @@ use format::ClearValue;
 use format::Format;
 use format::FormatDesc;
 use image::Dimensions;
+use image::Swizzle;
 use image::ViewType;
 use image::traits::AccessRange;
 use image::traits::CommandBufferState;
@@ -80,7 +81,8 @@ impl SwapchainImage {
     pub unsafe fn from_raw(image: UnsafeImage, format: Format, swapchain: &Arc<Swapchain>, id: u32)
                            -> Result<Arc<SwapchainImage>, OomError>
     {
-        let view = try!(UnsafeImageView::raw(&image, ViewType::Dim2d, 0 .. 1, 0 .. 1));
+        let view = try!(UnsafeImageView::raw(&image, ViewType::Dim2d, 0 .. 1, 0 .. 1,
+                                             Swizzle::default()));
 
         Ok(Arc::new(SwapchainImage {
             image: image,
@@ -243,7 +245,7 @@ unsafe impl ImageView for SwapchainImage {
 
     #[inline]
     fn descriptor_set_storage_image_layout(&self) -> Layout {
-        Layout::ShaderReadOnlyOptimal
+        Layout::General
     }
 
     #[inline]
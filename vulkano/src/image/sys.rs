@@ -21,18 +21,28 @@ use std::ptr;
 use std::sync::Arc;
 use smallvec::SmallVec;
 
+use buffer::sys::SparseLevel;
 use device::Device;
 use format::Format;
 use format::FormatTy;
+use instance::debug::ObjectType;
+use instrumentation::ResourceKind;
+use image::ImageAspect;
 use image::ImageDimensions;
 use image::MipmapsCount;
+use image::Swizzle;
 use image::ViewType;
 use memory::DeviceMemory;
+use memory::DeviceMemoryAllocError;
+use memory::ExternalMemoryHandleType;
 use memory::MemoryRequirements;
+use memory::pool::DedicatedAllocError;
+use sampler::SamplerYcbcrConversion;
 use sync::Sharing;
 
 use Error;
 use OomError;
+use VulkanError;
 use VulkanObject;
 use VulkanPointers;
 use check_errors;
@@ -65,6 +75,17 @@ pub struct UnsafeImage {
     // Features that are supported for this particular format.
     format_features: vk::FormatFeatureFlagBits,
 
+    // Sparse flags the image was created with.
+    sparse: SparseLevel,
+
+    // Memory requirements queried at creation. `None` for images created from a raw handle,
+    // whose memory isn't managed by vulkano.
+    memory_requirements: Option<MemoryRequirements>,
+
+    // Sparse memory requirements queried at creation. Empty if the image doesn't have sparse
+    // residency.
+    sparse_memory_requirements: Vec<SparseImageMemoryRequirements>,
+
     // `vkDestroyImage` is called only if `needs_destruction` is true.
     needs_destruction: bool,
 }
@@ -92,21 +113,98 @@ impl UnsafeImage {
         };
 
         UnsafeImage::new_impl(device, usage, format, dimensions, num_samples, mipmaps.into(),
-                              sharing, linear_tiling, preinitialized_layout)
+                              sharing, linear_tiling, preinitialized_layout, SparseLevel::none(),
+                              None)
+    }
+
+    /// Same as `new`, but the image can be bound to memory that is shared with another API or
+    /// another process through a handle of the given type.
+    ///
+    /// See `DeviceMemory::alloc_exportable`, `DeviceMemory::import_fd` and
+    /// `DeviceMemory::import_win32_handle`.
+    ///
+    /// # Panic
+    ///
+    /// Same as `new`.
+    ///
+    #[inline]
+    pub unsafe fn new_external<'a, Mi, I>(device: &Arc<Device>, usage: &Usage, format: Format,
+                                          dimensions: ImageDimensions, num_samples: u32,
+                                          mipmaps: Mi, sharing: Sharing<I>, linear_tiling: bool,
+                                          preinitialized_layout: bool,
+                                          handle_type: ExternalMemoryHandleType)
+                                          -> Result<(UnsafeImage, MemoryRequirements),
+                                                    ImageCreationError>
+        where Mi: Into<MipmapsCount>, I: Iterator<Item = u32>
+    {
+        let sharing = match sharing {
+            Sharing::Exclusive => (vk::SHARING_MODE_EXCLUSIVE, SmallVec::<[u32; 8]>::new()),
+            Sharing::Concurrent(ids) => (vk::SHARING_MODE_CONCURRENT, ids.collect()),
+        };
+
+        UnsafeImage::new_impl(device, usage, format, dimensions, num_samples, mipmaps.into(),
+                              sharing, linear_tiling, preinitialized_layout, SparseLevel::none(),
+                              Some(handle_type))
+    }
+
+    /// Creates a new sparse image.
+    ///
+    /// Contrary to `new`, no memory must be bound to the image with `bind_memory`. Instead,
+    /// memory is bound to the image with a sparse binding operation. See the `sync::sparse`
+    /// module. The image always has optimal tiling and starts in the undefined layout.
+    ///
+    /// If `sparse.sparse_residency` is true, memory can be bound to individual regions of the
+    /// image and the image doesn't need to be fully backed by memory. Use
+    /// `sparse_memory_requirements` to know the granularity of these regions.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the dimensions is 0.
+    /// - Panics if the number of mipmaps is 0.
+    /// - Panics if the number of samples is 0.
+    /// - Panics if `sparse.sparse` is false.
+    ///
+    #[inline]
+    pub unsafe fn new_sparse<'a, Mi, I>(device: &Arc<Device>, usage: &Usage, format: Format,
+                                        dimensions: ImageDimensions, num_samples: u32,
+                                        mipmaps: Mi, sharing: Sharing<I>, sparse: SparseLevel)
+                                        -> Result<(UnsafeImage, MemoryRequirements),
+                                                  ImageCreationError>
+        where Mi: Into<MipmapsCount>, I: Iterator<Item = u32>
+    {
+        assert!(sparse.sparse, "Sparse images must be created with sparse binding");
+
+        let sharing = match sharing {
+            Sharing::Exclusive => (vk::SHARING_MODE_EXCLUSIVE, SmallVec::<[u32; 8]>::new()),
+            Sharing::Concurrent(ids) => (vk::SHARING_MODE_CONCURRENT, ids.collect()),
+        };
+
+        UnsafeImage::new_impl(device, usage, format, dimensions, num_samples, mipmaps.into(),
+                              sharing, false, false, sparse, None)
     }
 
     // Non-templated version to avoid inlining and improve compile times.
     unsafe fn new_impl(device: &Arc<Device>, usage: &Usage, format: Format,
                        dimensions: ImageDimensions, num_samples: u32, mipmaps: MipmapsCount,
                        (sh_mode, sh_indices): (vk::SharingMode, SmallVec<[u32; 8]>),
-                       linear_tiling: bool, preinitialized_layout: bool)
+                       linear_tiling: bool, preinitialized_layout: bool, sparse: SparseLevel,
+                       external: Option<ExternalMemoryHandleType>)
                        -> Result<(UnsafeImage, MemoryRequirements), ImageCreationError>
     {
-        // TODO: doesn't check that the proper features are enabled
-
         let vk = device.pointers();
         let vk_i = device.instance().pointers();
 
+        if let Some(handle_type) = external {
+            if !handle_type.is_supported_by(device.loaded_extensions()) {
+                return Err(ImageCreationError::ExternalMemoryExtensionNotEnabled);
+            }
+        }
+
+        // Compressed formats require a feature to be enabled.
+        if !format.is_enabled_by(device.enabled_features()) {
+            return Err(ImageCreationError::FormatFeatureNotEnabled);
+        }
+
         // Checking if image usage conforms to what is supported.
         let format_features = {
             let physical_device = device.physical_device().internal_object();
@@ -167,24 +265,7 @@ impl UnsafeImage {
         let mut capabilities_error = None;
 
         // Compute the maximum number of mipmaps.
-        // TODO: only compte if necessary?
-        let max_mipmaps = {
-            let smallest_dim: u32 = match dimensions {
-                ImageDimensions::Dim1d { width, .. } => width,
-                ImageDimensions::Dim2d { width, height, .. } => {
-                    if width < height { width } else { height }
-                },
-                ImageDimensions::Dim3d { width, height, depth } => {
-                    if width < height {
-                        if depth < width { depth } else { width }
-                    } else {
-                        if depth < height { depth } else { height }
-                    }
-                },
-            };
-
-            32 - smallest_dim.leading_zeros()
-        };
+        let max_mipmaps = dimensions.max_mipmaps();
 
         // Compute the number of mipmaps.
         let mipmaps = match mipmaps.into() {
@@ -299,7 +380,8 @@ impl UnsafeImage {
                 if width == 0 || height == 0 || array_layers == 0 {
                     return Err(ImageCreationError::UnsupportedDimensions { dimensions: dimensions });
                 }
-                if cubemap_compatible && width != height {
+                // Cube-compatible images must be square and have at least one layer per face.
+                if cubemap_compatible && (width != height || array_layers < 6) {
                     return Err(ImageCreationError::UnsupportedDimensions { dimensions: dimensions });
                 }
                 let extent = vk::Extent3D { width: width, height: height, depth: 1 };
@@ -316,6 +398,60 @@ impl UnsafeImage {
             },
         };
 
+        // Cube-compatible images can't be multisampled.
+        if (flags & vk::IMAGE_CREATE_CUBE_COMPATIBLE_BIT) != 0 && num_samples != 1 {
+            return Err(ImageCreationError::UnsupportedSamplesCount { obtained: num_samples });
+        }
+
+        // Checking sparse features.
+        assert!(sparse.sparse || !sparse.sparse_residency, "Can't enable sparse residency without \
+                                                            enabling sparse binding as well");
+        assert!(sparse.sparse || !sparse.sparse_aliased, "Can't enable sparse aliasing without \
+                                                          enabling sparse binding as well");
+        if sparse.sparse && !device.enabled_features().sparse_binding {
+            return Err(ImageCreationError::SparseBindingFeatureNotEnabled);
+        }
+        if sparse.sparse_residency {
+            let features = device.enabled_features();
+            let enabled = match ty {
+                vk::IMAGE_TYPE_2D => features.sparse_residency_image2d,
+                vk::IMAGE_TYPE_3D => features.sparse_residency_image3d,
+                _ => false,
+            } && match num_samples {
+                1 => true,
+                2 => features.sparse_residency2_samples,
+                4 => features.sparse_residency4_samples,
+                8 => features.sparse_residency8_samples,
+                16 => features.sparse_residency16_samples,
+                _ => false,
+            };
+
+            if !enabled {
+                return Err(ImageCreationError::SparseResidencyImageFeatureNotEnabled);
+            }
+
+            // The format must support sparse residency with these parameters.
+            let mut num = 0;
+            vk_i.GetPhysicalDeviceSparseImageFormatProperties(
+                device.physical_device().internal_object(), format as u32, ty, num_samples,
+                usage.to_usage_bits(), vk::IMAGE_TILING_OPTIMAL, &mut num, ptr::null_mut());
+            if num == 0 {
+                return Err(ImageCreationError::FormatNotSupported);
+            }
+        }
+        if sparse.sparse_aliased && !device.enabled_features().sparse_residency_aliased {
+            return Err(ImageCreationError::SparseResidencyAliasedFeatureNotEnabled);
+        }
+
+        let flags = flags | sparse.to_image_flags();
+
+        // Allows creating views of the individual planes, which use a different format.
+        let flags = if format.num_planes() > 1 {
+            flags | vk::IMAGE_CREATE_MUTABLE_FORMAT_BIT
+        } else {
+            flags
+        };
+
         // Checking the dimensions against the limits.
         if array_layers > device.physical_device().limits().max_image_array_layers() {
             let err = ImageCreationError::UnsupportedDimensions { dimensions: dimensions };
@@ -368,7 +504,7 @@ impl UnsafeImage {
             let mut output = mem::uninitialized();
             let physical_device = device.physical_device().internal_object();
             let r = vk_i.GetPhysicalDeviceImageFormatProperties(physical_device, format as u32, ty,
-                                                                tiling, usage, 0 /* TODO */,
+                                                                tiling, usage, flags,
                                                                 &mut output);
 
             match check_errors(r) {
@@ -387,9 +523,19 @@ impl UnsafeImage {
 
         // Everything now ok. Creating the image.
         let image = {
+            let external_infos = external.map(|ty| {
+                vk::ExternalMemoryImageCreateInfoKHR {
+                    sType: vk::STRUCTURE_TYPE_EXTERNAL_MEMORY_IMAGE_CREATE_INFO_KHR,
+                    pNext: ptr::null(),
+                    handleTypes: ty.to_bits(),
+                }
+            });
+
             let infos = vk::ImageCreateInfo {
                 sType: vk::STRUCTURE_TYPE_IMAGE_CREATE_INFO,
-                pNext: ptr::null(),
+                pNext: external_infos.as_ref()
+                                     .map(|i| i as *const vk::ExternalMemoryImageCreateInfoKHR)
+                                     .unwrap_or(ptr::null()) as *const _,
                 flags: flags,
                 imageType: ty,
                 format: format as u32,
@@ -419,11 +565,13 @@ impl UnsafeImage {
             output
         };
 
-        let mem_reqs: vk::MemoryRequirements = {
-            let mut output = mem::uninitialized();
-            vk.GetImageMemoryRequirements(device.internal_object(), image, &mut output);
-            debug_assert!(output.memoryTypeBits != 0);
-            output
+        let mem_reqs = image_memory_requirements(device, image);
+        debug_assert!(mem_reqs.memory_type_bits != 0);
+
+        let sparse_mem_reqs = if sparse.sparse_residency {
+            image_sparse_memory_requirements(device, image)
+        } else {
+            Vec::new()
         };
 
         let image = UnsafeImage {
@@ -435,10 +583,14 @@ impl UnsafeImage {
             samples: num_samples,
             mipmaps: mipmaps,
             format_features: format_features,
+            sparse: sparse,
+            memory_requirements: Some(mem_reqs),
+            sparse_memory_requirements: sparse_mem_reqs,
             needs_destruction: true,
         };
 
-        Ok((image, mem_reqs.into()))
+        device.resource_tracker().created(ResourceKind::Image, image.image);
+        Ok((image, mem_reqs))
     }
 
     /// Creates an image from a raw handle. The image won't be destroyed.
@@ -465,6 +617,9 @@ impl UnsafeImage {
             samples: samples,
             mipmaps: mipmaps,
             format_features: output.optimalTilingFeatures,
+            sparse: SparseLevel::none(),
+            memory_requirements: None,
+            sparse_memory_requirements: Vec::new(),
             needs_destruction: false,       // TODO: pass as parameter
         }
     }
@@ -494,6 +649,21 @@ impl UnsafeImage {
         &self.device
     }
 
+    /// Gives a name to the image, that debugging tools and validation layers can display.
+    ///
+    /// Does nothing if the `VK_EXT_debug_marker` extension isn't enabled on the device.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `name` contains a nul character.
+    ///
+    #[inline]
+    pub fn set_object_name(&self, name: &str) -> Result<(), OomError> {
+        unsafe {
+            self.device.set_object_name_raw(ObjectType::Image, self.image as u64, name)
+        }
+    }
+
     #[inline]
     pub fn format(&self) -> Format {
         self.format
@@ -514,6 +684,32 @@ impl UnsafeImage {
         self.samples
     }
 
+    /// Returns the sparse level the image was created with.
+    #[inline]
+    pub fn sparse_level(&self) -> SparseLevel {
+        self.sparse
+    }
+
+    /// Returns the memory requirements of the image.
+    ///
+    /// The same value is returned when creating the image. For sparse images, the alignment is
+    /// the size of a sparse block.
+    #[inline]
+    pub fn memory_requirements(&self) -> MemoryRequirements {
+        match self.memory_requirements {
+            Some(reqs) => reqs,
+            None => unsafe { image_memory_requirements(&self.device, self.image) },
+        }
+    }
+
+    /// Returns the sparse memory requirements of the image, one for each aspect of the image.
+    ///
+    /// Returns an empty list if the image wasn't created with sparse residency.
+    #[inline]
+    pub fn sparse_memory_requirements(&self) -> &[SparseImageMemoryRequirements] {
+        &self.sparse_memory_requirements
+    }
+
     /// Queries the layout of an image in memory. Only valid for images with linear tiling.
     ///
     /// This function is only valid for images with a color format. See the other similar functions
@@ -609,6 +805,18 @@ impl UnsafeImage {
     pub fn supports_blit_destination(&self) -> bool {
         (self.format_features & vk::FORMAT_FEATURE_BLIT_DST_BIT) != 0
     }
+
+    /// Returns true if the format of the image supports being used as a storage image.
+    #[inline]
+    pub fn supports_storage(&self) -> bool {
+        (self.format_features & vk::FORMAT_FEATURE_STORAGE_IMAGE_BIT) != 0
+    }
+
+    /// Returns true if the image was created with the `transfer_source` usage.
+    #[inline]
+    pub fn usage_transfer_src(&self) -> bool {
+        (self.usage & vk::IMAGE_USAGE_TRANSFER_SRC_BIT) != 0
+    }
 }
 
 unsafe impl VulkanObject for UnsafeImage {
@@ -620,6 +828,81 @@ unsafe impl VulkanObject for UnsafeImage {
     }
 }
 
+// Queries the memory requirements of an image. If the `khr_dedicated_allocation` extension is
+// enabled, also queries whether the image should be bound to a dedicated allocation.
+unsafe fn image_memory_requirements(device: &Device, image: vk::Image) -> MemoryRequirements {
+    let vk = device.pointers();
+
+    let extensions = device.loaded_extensions();
+    if !extensions.khr_get_memory_requirements2 || !extensions.khr_dedicated_allocation {
+        let mut output: vk::MemoryRequirements = mem::uninitialized();
+        vk.GetImageMemoryRequirements(device.internal_object(), image, &mut output);
+        return output.into();
+    }
+
+    let infos = vk::ImageMemoryRequirementsInfo2KHR {
+        sType: vk::STRUCTURE_TYPE_IMAGE_MEMORY_REQUIREMENTS_INFO_2_KHR,
+        pNext: ptr::null(),
+        image: image,
+    };
+
+    let mut dedicated = vk::MemoryDedicatedRequirementsKHR {
+        sType: vk::STRUCTURE_TYPE_MEMORY_DEDICATED_REQUIREMENTS_KHR,
+        pNext: ptr::null_mut(),
+        prefersDedicatedAllocation: vk::FALSE,
+        requiresDedicatedAllocation: vk::FALSE,
+    };
+
+    let mut output = vk::MemoryRequirements2KHR {
+        sType: vk::STRUCTURE_TYPE_MEMORY_REQUIREMENTS_2_KHR,
+        pNext: &mut dedicated as *mut vk::MemoryDedicatedRequirementsKHR as *mut _,
+        memoryRequirements: mem::uninitialized(),
+    };
+
+    vk.GetImageMemoryRequirements2KHR(device.internal_object(), &infos, &mut output);
+
+    let mut reqs: MemoryRequirements = output.memoryRequirements.into();
+    reqs.prefer_dedicated = dedicated.prefersDedicatedAllocation != vk::FALSE;
+    reqs.require_dedicated = dedicated.requiresDedicatedAllocation != vk::FALSE;
+    reqs
+}
+
+// Queries the sparse memory requirements of an image, one for each aspect of the image.
+unsafe fn image_sparse_memory_requirements(device: &Device, image: vk::Image)
+                                           -> Vec<SparseImageMemoryRequirements>
+{
+    let vk = device.pointers();
+
+    let mut num = 0;
+    vk.GetImageSparseMemoryRequirements(device.internal_object(), image, &mut num,
+                                        ptr::null_mut());
+
+    let mut output = Vec::with_capacity(num as usize);
+    vk.GetImageSparseMemoryRequirements(device.internal_object(), image, &mut num,
+                                        output.as_mut_ptr());
+    output.set_len(num as usize);
+
+    output.into_iter().map(|reqs: vk::SparseImageMemoryRequirements| {
+        let props = reqs.formatProperties;
+        SparseImageMemoryRequirements {
+            color: (props.aspectMask & vk::IMAGE_ASPECT_COLOR_BIT) != 0,
+            depth: (props.aspectMask & vk::IMAGE_ASPECT_DEPTH_BIT) != 0,
+            stencil: (props.aspectMask & vk::IMAGE_ASPECT_STENCIL_BIT) != 0,
+            metadata: (props.aspectMask & vk::IMAGE_ASPECT_METADATA_BIT) != 0,
+            granularity: [props.imageGranularity.width, props.imageGranularity.height,
+                          props.imageGranularity.depth],
+            single_mip_tail: (props.flags & vk::SPARSE_IMAGE_FORMAT_SINGLE_MIPTAIL_BIT) != 0,
+            aligned_mip_size: (props.flags & vk::SPARSE_IMAGE_FORMAT_ALIGNED_MIP_SIZE_BIT) != 0,
+            nonstandard_block_size: (props.flags &
+                                     vk::SPARSE_IMAGE_FORMAT_NONSTANDARD_BLOCK_SIZE_BIT) != 0,
+            mip_tail_first_lod: reqs.imageMipTailFirstLod,
+            mip_tail_size: reqs.imageMipTailSize as usize,
+            mip_tail_offset: reqs.imageMipTailOffset as usize,
+            mip_tail_stride: reqs.imageMipTailStride as usize,
+        }
+    }).collect()
+}
+
 impl Drop for UnsafeImage {
     #[inline]
     fn drop(&mut self) {
@@ -630,6 +913,7 @@ impl Drop for UnsafeImage {
         unsafe {
             let vk = self.device.pointers();
             vk.DestroyImage(self.device.internal_object(), self.image, ptr::null());
+            self.device.resource_tracker().destroyed(ResourceKind::Image, self.image);
         }
     }
 }
@@ -639,18 +923,37 @@ impl Drop for UnsafeImage {
 pub enum ImageCreationError {
     /// Not enough memory.
     OomError(OomError),
+    /// The maximum number of memory allocations of the device has been reached.
+    TooManyObjects,
     /// A wrong number of mipmaps was provided.
     InvalidMipmapsCount { obtained: u32, valid_range: Range<u32> },
     /// The requeted number of samples is not supported, or is 0.
     UnsupportedSamplesCount { obtained: u32 },
-    /// The dimensions are too large, or one of the dimensions is 0.
+    /// The dimensions are too large, or one of the dimensions is 0, or the image is
+    /// cubemap-compatible but isn't square or has less than 6 array layers.
     UnsupportedDimensions { dimensions: ImageDimensions },
     /// The requested format is not supported by the Vulkan implementation.
     FormatNotSupported,
     /// The format is supported, but at least one of the requested usages is not supported.
     UnsupportedUsage,
+    /// The format is compressed and the feature that allows using it is not enabled. For
+    /// example the `texture_compression_bc` feature for the BC formats.
+    FormatFeatureNotEnabled,
     /// The `shader_storage_image_multisample` feature must be enabled to create such an image.
     ShaderStorageImageMultisampleFeatureNotEnabled,
+    /// Sparse binding was requested but the corresponding feature wasn't enabled.
+    SparseBindingFeatureNotEnabled,
+    /// Sparse residency was requested but the feature that corresponds to the type and the
+    /// number of samples of the image wasn't enabled.
+    SparseResidencyImageFeatureNotEnabled,
+    /// Sparse aliasing was requested but the corresponding feature wasn't enabled.
+    SparseResidencyAliasedFeatureNotEnabled,
+    /// External memory was requested but the extensions required by the handle type weren't
+    /// enabled.
+    ExternalMemoryExtensionNotEnabled,
+    /// The image must be bound to a dedicated allocation, but the memory pool doesn't support
+    /// dedicated allocations.
+    DedicatedAllocUnsupported,
 }
 
 impl error::Error for ImageCreationError {
@@ -658,20 +961,44 @@ impl error::Error for ImageCreationError {
     fn description(&self) -> &str {
         match *self {
             ImageCreationError::OomError(_) => "not enough memory available",
+            ImageCreationError::TooManyObjects => {
+                "the maximum number of memory allocations has been reached"
+            },
             ImageCreationError::InvalidMipmapsCount { .. } => "a wrong number of mipmaps was \
                                                                provided",
             ImageCreationError::UnsupportedSamplesCount { .. } => "the requeted number of samples \
                                                                    is not supported, or is 0",
             ImageCreationError::UnsupportedDimensions { .. } => "the dimensions are too large, or \
-                                                                 one of the dimensions is 0",
+                                                                 one of the dimensions is 0, or \
+                                                                 the dimensions are invalid for \
+                                                                 a cubemap",
             ImageCreationError::FormatNotSupported => "the requested format is not supported by \
                                                        the Vulkan implementation",
             ImageCreationError::UnsupportedUsage => "the format is supported, but at least one \
                                                      of the requested usages is not supported",
+            ImageCreationError::FormatFeatureNotEnabled => {
+                "the feature that allows using this compressed format is not enabled"
+            },
             ImageCreationError::ShaderStorageImageMultisampleFeatureNotEnabled => {
                 "the `shader_storage_image_multisample` feature must be enabled to create such \
                  an image"
             },
+            ImageCreationError::SparseBindingFeatureNotEnabled => {
+                "sparse binding was requested but the corresponding feature wasn't enabled"
+            },
+            ImageCreationError::SparseResidencyImageFeatureNotEnabled => {
+                "sparse residency was requested but the corresponding feature wasn't enabled"
+            },
+            ImageCreationError::SparseResidencyAliasedFeatureNotEnabled => {
+                "sparse aliasing was requested but the corresponding feature wasn't enabled"
+            },
+            ImageCreationError::ExternalMemoryExtensionNotEnabled => {
+                "external memory was requested but the corresponding extensions weren't enabled"
+            },
+            ImageCreationError::DedicatedAllocUnsupported => {
+                "the image must be bound to a dedicated allocation, but the memory pool doesn't \
+                 support dedicated allocations"
+            },
         }
     }
 
@@ -691,6 +1018,17 @@ impl fmt::Display for ImageCreationError {
     }
 }
 
+impl VulkanError for ImageCreationError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            ImageCreationError::OomError(ref err) => err.error_code(),
+            ImageCreationError::TooManyObjects => Some(Error::TooManyObjects),
+            _ => None
+        }
+    }
+}
+
 impl From<OomError> for ImageCreationError {
     #[inline]
     fn from(err: OomError) -> ImageCreationError {
@@ -698,6 +1036,27 @@ impl From<OomError> for ImageCreationError {
     }
 }
 
+impl From<DedicatedAllocError> for ImageCreationError {
+    #[inline]
+    fn from(err: DedicatedAllocError) -> ImageCreationError {
+        match err {
+            DedicatedAllocError::OomError(err) => ImageCreationError::OomError(err),
+            DedicatedAllocError::TooManyObjects => ImageCreationError::TooManyObjects,
+            DedicatedAllocError::Unsupported => ImageCreationError::DedicatedAllocUnsupported,
+        }
+    }
+}
+
+impl From<DeviceMemoryAllocError> for ImageCreationError {
+    #[inline]
+    fn from(err: DeviceMemoryAllocError) -> ImageCreationError {
+        match err {
+            DeviceMemoryAllocError::OomError(err) => ImageCreationError::OomError(err),
+            DeviceMemoryAllocError::TooManyObjects => ImageCreationError::TooManyObjects,
+        }
+    }
+}
+
 impl From<Error> for ImageCreationError {
     #[inline]
     fn from(err: Error) -> ImageCreationError {
@@ -733,19 +1092,95 @@ pub struct LinearLayout {
     pub depth_pitch: usize,
 }
 
+/// Describes how memory can be bound to the regions of an aspect of a sparse image.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SparseImageMemoryRequirements {
+    /// True if these requirements apply to the color aspect.
+    pub color: bool,
+    /// True if these requirements apply to the depth aspect.
+    pub depth: bool,
+    /// True if these requirements apply to the stencil aspect.
+    pub stencil: bool,
+    /// True if these requirements apply to the metadata aspect, which must be bound with opaque
+    /// bindings.
+    pub metadata: bool,
+    /// Width, height and depth in texels of a sparse block. Regions that are bound must be
+    /// aligned to this granularity.
+    pub granularity: [u32; 3],
+    /// If true, the mip tail is shared by all the array layers.
+    pub single_mip_tail: bool,
+    /// If true, the first mipmap level whose dimensions are not a multiple of the granularity
+    /// is the first level of the mip tail.
+    pub aligned_mip_size: bool,
+    /// If true, the granularity doesn't match the standard sparse block shapes.
+    pub nonstandard_block_size: bool,
+    /// First mipmap level of the mip tail. The mip tail must be bound with opaque bindings.
+    pub mip_tail_first_lod: u32,
+    /// Size in bytes of the mip tail of an array layer.
+    pub mip_tail_size: usize,
+    /// Offset of the mip tail in the opaque memory range of the image.
+    pub mip_tail_offset: usize,
+    /// Number of bytes between the mip tails of two array layers.
+    pub mip_tail_stride: usize,
+}
+
 #[derive(Debug)]
 pub struct UnsafeImageView {
     view: vk::ImageView,
     device: Arc<Device>,
     usage: vk::ImageUsageFlagBits,
-    identity_swizzle: bool,
+    swizzle: Swizzle,
+    aspect: ImageAspect,
     format: Format,
+    mipmap_levels: Range<u32>,
+    array_layers: Range<u32>,
+    ycbcr_conversion: Option<Arc<SamplerYcbcrConversion>>,
+    // `vkDestroyImageView` is called only if `needs_destruction` is true.
+    needs_destruction: bool,
 }
 
 impl UnsafeImageView {
     /// See the docs of new().
+    #[inline]
     pub unsafe fn raw(image: &UnsafeImage, ty: ViewType, mipmap_levels: Range<u32>,
-                      array_layers: Range<u32>) -> Result<UnsafeImageView, OomError>
+                      array_layers: Range<u32>, swizzle: Swizzle)
+                      -> Result<UnsafeImageView, OomError>
+    {
+        UnsafeImageView::raw_aspect(image, ty, mipmap_levels, array_layers, swizzle,
+                                    ImageAspect::from_format(image.format))
+    }
+
+    /// See the docs of new_aspect().
+    #[inline]
+    pub unsafe fn raw_aspect(image: &UnsafeImage, ty: ViewType, mipmap_levels: Range<u32>,
+                             array_layers: Range<u32>, swizzle: Swizzle, aspect: ImageAspect)
+                             -> Result<UnsafeImageView, OomError>
+    {
+        UnsafeImageView::raw_impl(image, ty, mipmap_levels, array_layers, swizzle, aspect, None)
+    }
+
+    /// Creates a view of an image with a YCbCr format, that can be sampled with a sampler that
+    /// uses the same conversion. See the documentation of the `sampler` module.
+    ///
+    /// # Panic
+    ///
+    /// - Panics for the same reasons as `new`.
+    /// - Panics if the format of the conversion isn't the format of the image.
+    ///
+    #[inline]
+    pub unsafe fn raw_ycbcr(image: &UnsafeImage, ty: ViewType, mipmap_levels: Range<u32>,
+                            array_layers: Range<u32>, conversion: &Arc<SamplerYcbcrConversion>)
+                            -> Result<UnsafeImageView, OomError>
+    {
+        assert_eq!(conversion.format(), image.format);
+        UnsafeImageView::raw_impl(image, ty, mipmap_levels, array_layers, Swizzle::default(),
+                                  ImageAspect::Color, Some(conversion))
+    }
+
+    unsafe fn raw_impl(image: &UnsafeImage, ty: ViewType, mipmap_levels: Range<u32>,
+                       array_layers: Range<u32>, swizzle: Swizzle, aspect: ImageAspect,
+                       conversion: Option<&Arc<SamplerYcbcrConversion>>)
+                       -> Result<UnsafeImageView, OomError>
     {
         let vk = image.device.pointers();
 
@@ -754,14 +1189,8 @@ impl UnsafeImageView {
         assert!(array_layers.end > array_layers.start);
         assert!(array_layers.end <= image.dimensions.array_layers());
 
-        let aspect_mask = match image.format.ty() {
-            FormatTy::Float | FormatTy::Uint | FormatTy::Sint | FormatTy::Compressed => {
-                vk::IMAGE_ASPECT_COLOR_BIT
-            },
-            FormatTy::Depth => vk::IMAGE_ASPECT_DEPTH_BIT,
-            FormatTy::Stencil => vk::IMAGE_ASPECT_STENCIL_BIT,
-            FormatTy::DepthStencil => vk::IMAGE_ASPECT_DEPTH_BIT | vk::IMAGE_ASPECT_STENCIL_BIT,
-        };
+        assert!(aspect.is_subset_of(image.format),
+                "the aspect {:?} isn't part of the format {:?}", aspect, image.format);
 
         let view_type = match (image.dimensions(), ty, array_layers.end - array_layers.start) {
             (ImageDimensions::Dim1d { .. }, ViewType::Dim1d, 1) => vk::IMAGE_VIEW_TYPE_1D,
@@ -774,23 +1203,48 @@ impl UnsafeImageView {
             },
             (ImageDimensions::Dim2d { cubemap_compatible, .. }, ViewType::CubemapArray, n) if cubemap_compatible => {
                 assert_eq!(n % 6, 0);
+                assert!(image.device.enabled_features().image_cube_array,
+                        "the `image_cube_array` feature must be enabled to create a cubemap array \
+                         view");
                 vk::IMAGE_VIEW_TYPE_CUBE_ARRAY
             },
             (ImageDimensions::Dim3d { .. }, ViewType::Dim3d, _) => vk::IMAGE_VIEW_TYPE_3D,
-            _ => panic!()
+            (dimensions, ty, _) => {
+                panic!("the view type {:?} doesn't match the dimensions of the image {:?}",
+                       ty, dimensions)
+            },
         };
 
+        // Views of a single plane of a multi-planar image use the format of the plane.
+        let format = aspect.format(image.format);
+
         let view = {
+            let conversion_infos = conversion.map(|conversion| {
+                vk::SamplerYcbcrConversionInfoKHR {
+                    sType: vk::STRUCTURE_TYPE_SAMPLER_YCBCR_CONVERSION_INFO_KHR,
+                    pNext: ptr::null(),
+                    conversion: conversion.internal_object(),
+                }
+            });
+
             let infos = vk::ImageViewCreateInfo {
                 sType: vk::STRUCTURE_TYPE_IMAGE_VIEW_CREATE_INFO,
-                pNext: ptr::null(),
+                pNext: match conversion_infos {
+                    Some(ref infos) => infos as *const vk::SamplerYcbcrConversionInfoKHR as *const _,
+                    None => ptr::null(),
+                },
                 flags: 0,   // reserved
                 image: image.internal_object(),
                 viewType: view_type,
-                format: image.format as u32,
-                components: vk::ComponentMapping { r: 0, g: 0, b: 0, a: 0 },     // FIXME:
+                format: format as u32,
+                components: vk::ComponentMapping {
+                    r: swizzle.r as u32,
+                    g: swizzle.g as u32,
+                    b: swizzle.b as u32,
+                    a: swizzle.a as u32,
+                },
                 subresourceRange: vk::ImageSubresourceRange {
-                    aspectMask: aspect_mask,
+                    aspectMask: aspect.to_vk_bits(),
                     baseMipLevel: mipmap_levels.start,
                     levelCount: mipmap_levels.end - mipmap_levels.start,
                     baseArrayLayer: array_layers.start,
@@ -808,31 +1262,89 @@ impl UnsafeImageView {
             view: view,
             device: image.device.clone(),
             usage: image.usage,
-            identity_swizzle: true,     // FIXME:
-            format: image.format,
+            swizzle: swizzle,
+            aspect: aspect,
+            format: format,
+            mipmap_levels: mipmap_levels,
+            array_layers: array_layers,
+            ycbcr_conversion: conversion.cloned(),
+            needs_destruction: true,
         })
     }
+
+    /// Creates a view from a raw handle. The view won't be destroyed.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be a valid view of `image` that covers the given mipmap levels, array
+    ///   layers and aspect, and uses the given swizzling.
+    /// - `handle` must stay alive for as long as the returned object exists.
+    ///
+    pub unsafe fn from_raw(image: &UnsafeImage, handle: u64, mipmap_levels: Range<u32>,
+                           array_layers: Range<u32>, swizzle: Swizzle, aspect: ImageAspect)
+                           -> UnsafeImageView
+    {
+        UnsafeImageView {
+            view: handle,
+            device: image.device.clone(),
+            usage: image.usage,
+            swizzle: swizzle,
+            aspect: aspect,
+            format: aspect.format(image.format),
+            mipmap_levels: mipmap_levels,
+            array_layers: array_layers,
+            ycbcr_conversion: None,
+            needs_destruction: false,
+        }
+    }
     
     /// Creates a new view from an image.
     ///
+    /// `swizzle` describes the value returned by each component when the view is accessed.
     /// Note that you must create the view with identity swizzling if you want to use this view
-    /// as a framebuffer attachment.
+    /// as a framebuffer attachment, a storage image or an input attachment.
     ///
     /// # Panic
     ///
     /// - Panics if `mipmap_levels` or `array_layers` is out of range of the image.
     /// - Panics if the view types doesn't match the dimensions of the image (for example a 2D
     ///   view from a 3D image).
+    /// - Panics if trying to create a cubemap or a cubemap array view from an image that wasn't
+    ///   created with `cubemap_compatible`.
     /// - Panics if trying to create a cubemap with a number of array layers different from 6.
     /// - Panics if trying to create a cubemap array with a number of array layers not a multiple
     ///   of 6.
+    /// - Panics if trying to create a cubemap array and the `image_cube_array` feature isn't
+    ///   enabled on the device.
     /// - Panics if the device or host ran out of memory.
     ///
     #[inline]
     pub unsafe fn new(image: &UnsafeImage, ty: ViewType, mipmap_levels: Range<u32>,
-                      array_layers: Range<u32>) -> UnsafeImageView
+                      array_layers: Range<u32>, swizzle: Swizzle) -> UnsafeImageView
+    {
+        UnsafeImageView::raw(image, ty, mipmap_levels, array_layers, swizzle).unwrap()
+    }
+
+    /// Same as `new`, but the view only gives access to the given aspect of the image.
+    ///
+    /// This can be used to create a view of only the depth or only the stencil of an image with a
+    /// combined depth-stencil format, so that they can be sampled separately.
+    ///
+    /// It can also be used to create a view of one plane of an image with a multi-planar format.
+    /// The view then has the format returned by `Format::plane_format`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics for the same reasons as `new`.
+    /// - Panics if `aspect` isn't part of the format of the image.
+    ///
+    #[inline]
+    pub unsafe fn new_aspect(image: &UnsafeImage, ty: ViewType, mipmap_levels: Range<u32>,
+                             array_layers: Range<u32>, swizzle: Swizzle, aspect: ImageAspect)
+                             -> UnsafeImageView
     {
-        UnsafeImageView::raw(image, ty, mipmap_levels, array_layers).unwrap()
+        UnsafeImageView::raw_aspect(image, ty, mipmap_levels, array_layers, swizzle, aspect)
+            .unwrap()
     }
 
     #[inline]
@@ -840,6 +1352,42 @@ impl UnsafeImageView {
         self.format
     }
 
+    /// Returns the swizzling of the components of the view.
+    #[inline]
+    pub fn swizzle(&self) -> Swizzle {
+        self.swizzle
+    }
+
+    /// Returns the aspect of the image that the view gives access to.
+    #[inline]
+    pub fn aspect(&self) -> ImageAspect {
+        self.aspect
+    }
+
+    /// Returns the YCbCr conversion of the view, if it was created with `raw_ycbcr`.
+    #[inline]
+    pub fn ycbcr_conversion(&self) -> Option<&Arc<SamplerYcbcrConversion>> {
+        self.ycbcr_conversion.as_ref()
+    }
+
+    /// Returns true if the view doesn't swizzle the components of the image.
+    #[inline]
+    pub fn identity_swizzle(&self) -> bool {
+        self.swizzle.is_identity()
+    }
+
+    /// Returns the range of mipmap levels of the image that this view covers.
+    #[inline]
+    pub fn mipmap_levels(&self) -> Range<u32> {
+        self.mipmap_levels.clone()
+    }
+
+    /// Returns the range of array layers of the image that this view covers.
+    #[inline]
+    pub fn array_layers(&self) -> Range<u32> {
+        self.array_layers.clone()
+    }
+
     #[inline]
     pub fn usage_transfer_src(&self) -> bool {
         (self.usage & vk::IMAGE_USAGE_TRANSFER_SRC_BIT) != 0
@@ -893,6 +1441,10 @@ unsafe impl VulkanObject for UnsafeImageView {
 impl Drop for UnsafeImageView {
     #[inline]
     fn drop(&mut self) {
+        if !self.needs_destruction {
+            return;
+        }
+
         unsafe {
             let vk = self.device.pointers();
             vk.DestroyImageView(self.device.internal_object(), self.view, ptr::null());
@@ -1038,6 +1590,7 @@ mod tests {
     use super::UnsafeImage;
     use super::Usage;
 
+    use buffer::sys::SparseLevel;
     use image::ImageDimensions;
     use format::Format;
     use sync::Sharing;
@@ -1059,6 +1612,52 @@ mod tests {
         }.unwrap();
     }
 
+    #[test]
+    fn compressed_feature_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = Usage {
+            sampled: true,
+            .. Usage::none()
+        };
+
+        let res = unsafe {
+            UnsafeImage::new(&device, &usage, Format::BC1_RGBUnormBlock,
+                             ImageDimensions::Dim2d { width: 32, height: 32, array_layers: 1,
+                                                      cubemap_compatible: false }, 1, 1,
+                             Sharing::Exclusive::<Empty<_>>, false, false)
+        };
+
+        match res {
+            Err(ImageCreationError::FormatFeatureNotEnabled) => (),
+            _ => panic!()
+        };
+    }
+
+    #[test]
+    fn sparse_missing_feature() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = Usage {
+            sampled: true,
+            .. Usage::none()
+        };
+
+        let sparse = SparseLevel { sparse: true, sparse_residency: false, sparse_aliased: false };
+        let res = unsafe {
+            UnsafeImage::new_sparse(&device, &usage, Format::R8G8B8A8Unorm,
+                                    ImageDimensions::Dim2d { width: 32, height: 32,
+                                                             array_layers: 1,
+                                                             cubemap_compatible: false }, 1, 1,
+                                    Sharing::Exclusive::<Empty<_>>, sparse)
+        };
+
+        match res {
+            Err(ImageCreationError::SparseBindingFeatureNotEnabled) => (),
+            _ => panic!()
+        };
+    }
+
     #[test]
     fn create_transient() {
         let (device, _) = gfx_dev_and_queue!();
@@ -1169,6 +1768,67 @@ mod tests {
         };
     }
 
+    #[test]
+    fn create_cubemap() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = Usage {
+            sampled: true,
+            .. Usage::none()
+        };
+
+        let (_img, _) = unsafe {
+            UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
+                             ImageDimensions::Dim2d { width: 32, height: 32, array_layers: 6,
+                                                      cubemap_compatible: true }, 1, 1,
+                             Sharing::Exclusive::<Empty<_>>, false, false)
+        }.unwrap();
+    }
+
+    #[test]
+    fn cubemap_not_enough_layers() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = Usage {
+            sampled: true,
+            .. Usage::none()
+        };
+
+        let res = unsafe {
+            UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
+                             ImageDimensions::Dim2d { width: 32, height: 32, array_layers: 5,
+                                                      cubemap_compatible: true }, 1, 1,
+                             Sharing::Exclusive::<Empty<_>>, false, false)
+        };
+
+        match res {
+            Err(ImageCreationError::UnsupportedDimensions { .. }) => (),
+            _ => panic!()
+        };
+    }
+
+    #[test]
+    fn cubemap_not_square() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = Usage {
+            sampled: true,
+            .. Usage::none()
+        };
+
+        let res = unsafe {
+            UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
+                             ImageDimensions::Dim2d { width: 32, height: 16, array_layers: 6,
+                                                      cubemap_compatible: true }, 1, 1,
+                             Sharing::Exclusive::<Empty<_>>, false, false)
+        };
+
+        match res {
+            Err(ImageCreationError::UnsupportedDimensions { .. }) => (),
+            _ => panic!()
+        };
+    }
+
     #[test]
     fn shader_storage_image_multisample() {
         let (device, _) = gfx_dev_and_queue!();
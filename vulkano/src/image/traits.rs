@@ -83,6 +83,11 @@ pub unsafe trait Image: 'static + Send + Sync {
     /// - Whether a pipeline barrier should be added in order to address a read or write from
     ///   memory (VK_ACCESS_MEMORY_READ_BIT | VK_ACCESS_MEMORY_WRITE_BIT).
     ///
+    /// An image that tracks its layout across submissions can return `first_required_layout`
+    /// and return the transition from its actual layout in the `before_transitions` of
+    /// `gpu_access`. Images that are read by multiple queues at the same time should instead
+    /// always return to the same layout at the end of each command buffer.
+    ///
     fn initial_layout(&self, block: (u32, u32), first_required_layout: Layout) -> (Layout, bool, bool);
 
     /// Called when a command buffer that uses this image is being built. Given a block, this
@@ -298,9 +303,11 @@ pub unsafe trait ImageView: 'static + Send + Sync {
     /// Returns true if the given sampler can be used with this image view.
     ///
     /// This method should check whether the sampler's configuration can be used with the format
-    /// of the view.
-    // TODO: return a Result
-    fn can_be_sampled(&self, sampler: &Sampler) -> bool { true /* FIXME */ }
+    /// of the view. See `Sampler::check_image_view` for the details.
+    #[inline]
+    fn can_be_sampled(&self, sampler: &Sampler) -> bool {
+        sampler.check_image_view(self).is_ok()
+    }
 
     //fn usable_as_render_pass_attachment(&self, ???) -> Result<(), ???>;
 }
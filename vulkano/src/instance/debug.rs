@@ -33,20 +33,37 @@
 //! be callable. If you don't store the return value of `DebugCallback`'s constructor in a
 //! variable, it will be immediately destroyed and your callback will not work. 
 //!
+//! The instance and the callback can also be created at the same time with
+//! `DebugCallback::with_instance`, which enables the `EXT_debug_report` extension for you.
+//!
+//! # Tests
+//!
+//! `DebugCallback::panic_on_errors` registers a callback that remembers the errors reported by
+//! the validation layers. When debug assertions are enabled, the `DebugCallback` panics when it is
+//! destroyed if an error has been reported, which makes tests fail on invalid API usages.
+//!
 
 use std::error;
 use std::ffi::CStr;
 use std::fmt;
+use std::io;
+use std::io::Write;
 use std::mem;
 use std::os::raw::{c_void, c_char};
 use std::panic;
 use std::ptr;
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
 
+use instance::ApplicationInfo;
 use instance::Instance;
+use instance::InstanceCreationError;
+use instance::InstanceExtensions;
 
 use check_errors;
 use Error;
+use VulkanError;
 use VulkanObject;
 use VulkanPointers;
 use vk;
@@ -60,6 +77,9 @@ pub struct DebugCallback {
     instance: Arc<Instance>,
     debug_report_callback: vk::DebugReportCallbackEXT,
     user_callback: Box<Box<Fn(&Message)>>,
+    // If `Some`, contains the first error message that was reported. Only used by
+    // `panic_on_errors`.
+    first_error: Option<Arc<Mutex<Option<String>>>>,
 }
 
 impl DebugCallback {
@@ -79,8 +99,8 @@ impl DebugCallback {
         let user_callback = Box::new(Box::new(user_callback) as Box<_>);
 
         extern "system" fn callback(ty: vk::DebugReportFlagsEXT,
-                                    _: vk::DebugReportObjectTypeEXT, _: u64, _: usize,
-                                    _: i32, layer_prefix: *const c_char,
+                                    object_type: vk::DebugReportObjectTypeEXT, object: u64,
+                                    _: usize, message_code: i32, layer_prefix: *const c_char,
                                     description: *const c_char, user_data: *mut c_void) -> u32
         {
             unsafe {
@@ -102,6 +122,9 @@ impl DebugCallback {
                         error: (ty & vk::DEBUG_REPORT_ERROR_BIT_EXT) != 0,
                         debug: (ty & vk::DEBUG_REPORT_DEBUG_BIT_EXT) != 0,
                     },
+                    object_type: ObjectType::from_raw(object_type),
+                    object: object,
+                    message_code: message_code,
                     layer_prefix: layer_prefix,
                     description: description,
                 };
@@ -147,9 +170,77 @@ impl DebugCallback {
             instance: instance.clone(),
             debug_report_callback: debug_report_callback,
             user_callback: user_callback,
+            first_error: None,
         })
     }
 
+    /// Creates a new instance with the `EXT_debug_report` extension enabled in addition to
+    /// `extensions`, and registers a debug callback on it.
+    ///
+    /// See `Instance::new` for the other parameters.
+    pub fn with_instance<'a, L, F>(app_infos: Option<&ApplicationInfo>,
+                                   extensions: &InstanceExtensions, layers: L,
+                                   messages: MessageTypes, user_callback: F)
+                                   -> Result<(Arc<Instance>, DebugCallback), InstanceCreationError>
+        where L: IntoIterator<Item = &'a &'a str>,
+              F: Fn(&Message) + 'static + panic::RefUnwindSafe
+    {
+        let extensions = InstanceExtensions {
+            ext_debug_report: true,
+            .. extensions.clone()
+        };
+
+        let instance = try!(Instance::new(app_infos, &extensions, layers));
+
+        match DebugCallback::new(&instance, messages, user_callback) {
+            Ok(callback) => Ok((instance, callback)),
+            Err(DebugCallbackCreationError::MissingExtension) => unreachable!(),
+        }
+    }
+
+    /// Initializes a debug callback that remembers the first error that is reported and prints
+    /// all errors and warnings to the standard error.
+    ///
+    /// If debug assertions are enabled, destroying the `DebugCallback` panics if an error has
+    /// been reported. You can also call `check` to panic earlier.
+    pub fn panic_on_errors(instance: &Arc<Instance>)
+                           -> Result<DebugCallback, DebugCallbackCreationError>
+    {
+        let first_error = Arc::new(Mutex::new(None));
+
+        let mut callback = {
+            let first_error = first_error.clone();
+            try!(DebugCallback::errors_and_warnings(instance, move |msg| {
+                let text = format!("{}: {}", msg.layer_prefix, msg.description);
+                if msg.ty.error {
+                    let mut first_error = first_error.lock().unwrap();
+                    if first_error.is_none() {
+                        *first_error = Some(text.clone());
+                    }
+                }
+                let _ = writeln!(io::stderr(), "Debug callback: {}", text);
+            }))
+        };
+
+        callback.first_error = Some(first_error);
+        Ok(callback)
+    }
+
+    /// Returns the first error that has been reported, if the callback was created with
+    /// `panic_on_errors`.
+    #[inline]
+    pub fn first_error(&self) -> Option<String> {
+        self.first_error.as_ref().and_then(|e| e.lock().unwrap().clone())
+    }
+
+    /// Panics if the callback was created with `panic_on_errors` and an error has been reported.
+    #[inline]
+    pub fn check(&self) {
+        if let Some(err) = self.first_error() {
+            panic!("Validation error: {}", err);
+        }
+    }
+
     /// Initializes a debug callback with errors and warnings.
     ///
     /// Shortcut for `new(instance, MessageTypes::errors_and_warnings(), user_callback)`.
@@ -170,6 +261,10 @@ impl Drop for DebugCallback {
             vk.DestroyDebugReportCallbackEXT(self.instance.internal_object(),
                                              self.debug_report_callback, ptr::null());
         }
+
+        if cfg!(debug_assertions) && !thread::panicking() {
+            self.check();
+        }
     }
 }
 
@@ -177,6 +272,12 @@ impl Drop for DebugCallback {
 pub struct Message<'a> {
     /// Type of message.
     pub ty: MessageTypes,
+    /// Type of the object that the message is about.
+    pub object_type: ObjectType,
+    /// Handle of the object that the message is about, or 0 if unknown.
+    pub object: u64,
+    /// Code of the message, as defined by the layer that reported it.
+    pub message_code: i32,
     /// Prefix of the layer that reported this message.
     pub layer_prefix: &'a str,
     /// Description of the message.
@@ -233,6 +334,118 @@ impl MessageTypes {
     }
 }
 
+/// Type of the object that a message is about.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ObjectType {
+    Unknown,
+    Instance,
+    PhysicalDevice,
+    Device,
+    Queue,
+    Semaphore,
+    CommandBuffer,
+    Fence,
+    DeviceMemory,
+    Buffer,
+    Image,
+    Event,
+    QueryPool,
+    BufferView,
+    ImageView,
+    ShaderModule,
+    PipelineCache,
+    PipelineLayout,
+    RenderPass,
+    Pipeline,
+    DescriptorSetLayout,
+    Sampler,
+    DescriptorPool,
+    DescriptorSet,
+    Framebuffer,
+    CommandPool,
+    Surface,
+    Swapchain,
+    DebugReport,
+}
+
+impl ObjectType {
+    #[inline]
+    fn from_raw(ty: vk::DebugReportObjectTypeEXT) -> ObjectType {
+        match ty {
+            vk::DEBUG_REPORT_OBJECT_TYPE_INSTANCE_EXT => ObjectType::Instance,
+            vk::DEBUG_REPORT_OBJECT_TYPE_PHYSICAL_DEVICE_EXT => ObjectType::PhysicalDevice,
+            vk::DEBUG_REPORT_OBJECT_TYPE_DEVICE_EXT => ObjectType::Device,
+            vk::DEBUG_REPORT_OBJECT_TYPE_QUEUE_EXT => ObjectType::Queue,
+            vk::DEBUG_REPORT_OBJECT_TYPE_SEMAPHORE_EXT => ObjectType::Semaphore,
+            vk::DEBUG_REPORT_OBJECT_TYPE_COMMAND_BUFFER_EXT => ObjectType::CommandBuffer,
+            vk::DEBUG_REPORT_OBJECT_TYPE_FENCE_EXT => ObjectType::Fence,
+            vk::DEBUG_REPORT_OBJECT_TYPE_DEVICE_MEMORY_EXT => ObjectType::DeviceMemory,
+            vk::DEBUG_REPORT_OBJECT_TYPE_BUFFER_EXT => ObjectType::Buffer,
+            vk::DEBUG_REPORT_OBJECT_TYPE_IMAGE_EXT => ObjectType::Image,
+            vk::DEBUG_REPORT_OBJECT_TYPE_EVENT_EXT => ObjectType::Event,
+            vk::DEBUG_REPORT_OBJECT_TYPE_QUERY_POOL_EXT => ObjectType::QueryPool,
+            vk::DEBUG_REPORT_OBJECT_TYPE_BUFFER_VIEW_EXT => ObjectType::BufferView,
+            vk::DEBUG_REPORT_OBJECT_TYPE_IMAGE_VIEW_EXT => ObjectType::ImageView,
+            vk::DEBUG_REPORT_OBJECT_TYPE_SHADER_MODULE_EXT => ObjectType::ShaderModule,
+            vk::DEBUG_REPORT_OBJECT_TYPE_PIPELINE_CACHE_EXT => ObjectType::PipelineCache,
+            vk::DEBUG_REPORT_OBJECT_TYPE_PIPELINE_LAYOUT_EXT => ObjectType::PipelineLayout,
+            vk::DEBUG_REPORT_OBJECT_TYPE_RENDER_PASS_EXT => ObjectType::RenderPass,
+            vk::DEBUG_REPORT_OBJECT_TYPE_PIPELINE_EXT => ObjectType::Pipeline,
+            vk::DEBUG_REPORT_OBJECT_TYPE_DESCRIPTOR_SET_LAYOUT_EXT => {
+                ObjectType::DescriptorSetLayout
+            },
+            vk::DEBUG_REPORT_OBJECT_TYPE_SAMPLER_EXT => ObjectType::Sampler,
+            vk::DEBUG_REPORT_OBJECT_TYPE_DESCRIPTOR_POOL_EXT => ObjectType::DescriptorPool,
+            vk::DEBUG_REPORT_OBJECT_TYPE_DESCRIPTOR_SET_EXT => ObjectType::DescriptorSet,
+            vk::DEBUG_REPORT_OBJECT_TYPE_FRAMEBUFFER_EXT => ObjectType::Framebuffer,
+            vk::DEBUG_REPORT_OBJECT_TYPE_COMMAND_POOL_EXT => ObjectType::CommandPool,
+            vk::DEBUG_REPORT_OBJECT_TYPE_SURFACE_KHR_EXT => ObjectType::Surface,
+            vk::DEBUG_REPORT_OBJECT_TYPE_SWAPCHAIN_KHR_EXT => ObjectType::Swapchain,
+            vk::DEBUG_REPORT_OBJECT_TYPE_DEBUG_REPORT_EXT => ObjectType::DebugReport,
+            _ => ObjectType::Unknown,
+        }
+    }
+
+    // Returns the raw value of the type, as used by the debug extensions.
+    #[doc(hidden)]
+    #[inline]
+    pub fn to_raw(&self) -> vk::DebugReportObjectTypeEXT {
+        match *self {
+            ObjectType::Unknown => vk::DEBUG_REPORT_OBJECT_TYPE_UNKNOWN_EXT,
+            ObjectType::Instance => vk::DEBUG_REPORT_OBJECT_TYPE_INSTANCE_EXT,
+            ObjectType::PhysicalDevice => vk::DEBUG_REPORT_OBJECT_TYPE_PHYSICAL_DEVICE_EXT,
+            ObjectType::Device => vk::DEBUG_REPORT_OBJECT_TYPE_DEVICE_EXT,
+            ObjectType::Queue => vk::DEBUG_REPORT_OBJECT_TYPE_QUEUE_EXT,
+            ObjectType::Semaphore => vk::DEBUG_REPORT_OBJECT_TYPE_SEMAPHORE_EXT,
+            ObjectType::CommandBuffer => vk::DEBUG_REPORT_OBJECT_TYPE_COMMAND_BUFFER_EXT,
+            ObjectType::Fence => vk::DEBUG_REPORT_OBJECT_TYPE_FENCE_EXT,
+            ObjectType::DeviceMemory => vk::DEBUG_REPORT_OBJECT_TYPE_DEVICE_MEMORY_EXT,
+            ObjectType::Buffer => vk::DEBUG_REPORT_OBJECT_TYPE_BUFFER_EXT,
+            ObjectType::Image => vk::DEBUG_REPORT_OBJECT_TYPE_IMAGE_EXT,
+            ObjectType::Event => vk::DEBUG_REPORT_OBJECT_TYPE_EVENT_EXT,
+            ObjectType::QueryPool => vk::DEBUG_REPORT_OBJECT_TYPE_QUERY_POOL_EXT,
+            ObjectType::BufferView => vk::DEBUG_REPORT_OBJECT_TYPE_BUFFER_VIEW_EXT,
+            ObjectType::ImageView => vk::DEBUG_REPORT_OBJECT_TYPE_IMAGE_VIEW_EXT,
+            ObjectType::ShaderModule => vk::DEBUG_REPORT_OBJECT_TYPE_SHADER_MODULE_EXT,
+            ObjectType::PipelineCache => vk::DEBUG_REPORT_OBJECT_TYPE_PIPELINE_CACHE_EXT,
+            ObjectType::PipelineLayout => vk::DEBUG_REPORT_OBJECT_TYPE_PIPELINE_LAYOUT_EXT,
+            ObjectType::RenderPass => vk::DEBUG_REPORT_OBJECT_TYPE_RENDER_PASS_EXT,
+            ObjectType::Pipeline => vk::DEBUG_REPORT_OBJECT_TYPE_PIPELINE_EXT,
+            ObjectType::DescriptorSetLayout => {
+                vk::DEBUG_REPORT_OBJECT_TYPE_DESCRIPTOR_SET_LAYOUT_EXT
+            },
+            ObjectType::Sampler => vk::DEBUG_REPORT_OBJECT_TYPE_SAMPLER_EXT,
+            ObjectType::DescriptorPool => vk::DEBUG_REPORT_OBJECT_TYPE_DESCRIPTOR_POOL_EXT,
+            ObjectType::DescriptorSet => vk::DEBUG_REPORT_OBJECT_TYPE_DESCRIPTOR_SET_EXT,
+            ObjectType::Framebuffer => vk::DEBUG_REPORT_OBJECT_TYPE_FRAMEBUFFER_EXT,
+            ObjectType::CommandPool => vk::DEBUG_REPORT_OBJECT_TYPE_COMMAND_POOL_EXT,
+            ObjectType::Surface => vk::DEBUG_REPORT_OBJECT_TYPE_SURFACE_KHR_EXT,
+            ObjectType::Swapchain => vk::DEBUG_REPORT_OBJECT_TYPE_SWAPCHAIN_KHR_EXT,
+            ObjectType::DebugReport => vk::DEBUG_REPORT_OBJECT_TYPE_DEBUG_REPORT_EXT,
+        }
+    }
+}
+
 /// Error that can happen when creating a debug callback.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum DebugCallbackCreationError {
@@ -257,6 +470,13 @@ impl fmt::Display for DebugCallbackCreationError {
     }
 }
 
+impl VulkanError for DebugCallbackCreationError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        None
+    }
+}
+
 impl From<Error> for DebugCallbackCreationError {
     #[inline]
     fn from(err: Error) -> DebugCallbackCreationError {
@@ -8,16 +8,22 @@
 // according to those terms.
 
 use std::error;
+use std::ffi::CStr;
 use std::ffi::CString;
 use std::fmt;
 use std::ptr;
+use std::str;
 
 use Error;
 use OomError;
+use instance::PhysicalDevice;
 use instance::loader;
 use instance::loader::LoadingError;
 use vk;
 use check_errors;
+use VulkanError;
+use VulkanObject;
+use VulkanPointers;
 
 macro_rules! extensions {
     ($sname:ident, $($ext:ident => $s:expr,)*) => (
@@ -53,6 +59,13 @@ macro_rules! extensions {
                 data
             }
 
+            /// Returns the names of the extensions of the list.
+            pub fn names(&self) -> Vec<&'static str> {
+                let mut data = Vec::new();
+                $(if self.$ext { data.push(str::from_utf8(&$s[..]).unwrap()); })*
+                data
+            }
+
             /// Returns the intersection of this list and another list.
             #[inline]
             pub fn intersection(&self, other: &$sname) -> $sname {
@@ -63,6 +76,53 @@ macro_rules! extensions {
                     _unbuildable: Unbuildable(())
                 }
             }
+
+            /// Returns the union of this list and another list.
+            ///
+            /// Combined with `intersection`, this can be used to merge the extensions that an
+            /// application requires with the optional ones that are supported:
+            /// `required.union(&optional.intersection(&supported))`.
+            #[inline]
+            pub fn union(&self, other: &$sname) -> $sname {
+                $sname {
+                    $(
+                        $ext: self.$ext || other.$ext,
+                    )*
+                    _unbuildable: Unbuildable(())
+                }
+            }
+
+            /// Returns the extensions of this list that are not in the other list.
+            #[inline]
+            pub fn difference(&self, other: &$sname) -> $sname {
+                $sname {
+                    $(
+                        $ext: self.$ext && !other.$ext,
+                    )*
+                    _unbuildable: Unbuildable(())
+                }
+            }
+
+            /// Returns true if all the extensions of `other` are in this list.
+            #[inline]
+            pub fn superset_of(&self, other: &$sname) -> bool {
+                $((self.$ext || !other.$ext))&&*
+            }
+
+            // Builds the list from the properties returned by Vulkan.
+            fn from_properties(properties: &[vk::ExtensionProperties]) -> $sname {
+                let mut extensions = $sname::none();
+                for property in properties {
+                    // TODO: Check specVersion?
+                    let name = unsafe { CStr::from_ptr(property.extensionName.as_ptr()) };
+                    $(
+                        if name.to_bytes() == &$s[..] {
+                            extensions.$ext = true;
+                        }
+                    )*
+                }
+                extensions
+            }
         }
     );
 }
@@ -76,39 +136,32 @@ macro_rules! instance_extensions {
         
         impl $sname {
             /// See the docs of supported_by_core().
+            #[inline]
             pub fn supported_by_core_raw() -> Result<$sname, SupportedExtensionsError> {
+                $sname::supported_by_layer_raw(None)
+            }
+
+            /// Returns the extensions that are provided by the given layer, or by the core driver
+            /// if `layer` is `None`.
+            pub fn supported_by_layer_raw(layer: Option<&CStr>)
+                                          -> Result<$sname, SupportedExtensionsError>
+            {
                 let entry_points = try!(loader::entry_points());
+                let layer = layer.map(|l| l.as_ptr()).unwrap_or(ptr::null());
 
                 let properties: Vec<vk::ExtensionProperties> = unsafe {
                     let mut num = 0;
                     try!(check_errors(entry_points.EnumerateInstanceExtensionProperties(
-                        ptr::null(), &mut num, ptr::null_mut())));
+                        layer, &mut num, ptr::null_mut())));
                     
                     let mut properties = Vec::with_capacity(num as usize);
                     try!(check_errors(entry_points.EnumerateInstanceExtensionProperties(
-                        ptr::null(), &mut num, properties.as_mut_ptr())));
+                        layer, &mut num, properties.as_mut_ptr())));
                     properties.set_len(num as usize);
                     properties
                 };
-                
-                let mut extensions = $sname::none();
-                for property in properties {
-                    let name = property.extensionName;
-                    $(
-                        // TODO: this is VERY inefficient
-                        // TODO: Check specVersion?
-                        let same = {
-                            let mut i = 0;
-                            while name[i] != 0 && $s[i] != 0 && name[i] as u8 == $s[i] && i < $s.len() { i += 1; }
-                            name[i] == 0 && (i >= $s.len() || name[i] as u8 == $s[i])
-                        };
-                        if same {
-                            extensions.$ext = true;
-                        }
-                    )*
-                }
-                
-                Ok(extensions)
+
+                Ok($sname::from_properties(&properties))
             }
             
             /// Returns an `Extensions` object with extensions supported by the core driver.
@@ -134,12 +187,70 @@ instance_extensions! {
     khr_android_surface => b"VK_KHR_android_surface",
     khr_win32_surface => b"VK_KHR_win32_surface",
     ext_debug_report => b"VK_EXT_debug_report",
+    khr_get_physical_device_properties2 => b"VK_KHR_get_physical_device_properties2",
+    ext_swapchain_colorspace => b"VK_EXT_swapchain_colorspace",
+    khr_device_group_creation => b"VK_KHR_device_group_creation",
 }
 
 extensions! {
     DeviceExtensions,
     khr_swapchain => b"VK_KHR_swapchain",
     khr_display_swapchain => b"VK_KHR_display_swapchain",
+    ext_conservative_rasterization => b"VK_EXT_conservative_rasterization",
+    ext_index_type_uint8 => b"VK_EXT_index_type_uint8",
+    khr_get_memory_requirements2 => b"VK_KHR_get_memory_requirements2",
+    khr_dedicated_allocation => b"VK_KHR_dedicated_allocation",
+    ext_memory_budget => b"VK_EXT_memory_budget",
+    khr_external_memory => b"VK_KHR_external_memory",
+    khr_external_memory_fd => b"VK_KHR_external_memory_fd",
+    khr_external_memory_win32 => b"VK_KHR_external_memory_win32",
+    ext_external_memory_dma_buf => b"VK_EXT_external_memory_dma_buf",
+    ext_full_screen_exclusive => b"VK_EXT_full_screen_exclusive",
+    khr_incremental_present => b"VK_KHR_incremental_present",
+    khr_external_semaphore => b"VK_KHR_external_semaphore",
+    khr_external_semaphore_fd => b"VK_KHR_external_semaphore_fd",
+    khr_external_fence => b"VK_KHR_external_fence",
+    khr_external_fence_fd => b"VK_KHR_external_fence_fd",
+    khr_device_group => b"VK_KHR_device_group",
+    khr_sampler_mirror_clamp_to_edge => b"VK_KHR_sampler_mirror_clamp_to_edge",
+    khr_sampler_ycbcr_conversion => b"VK_KHR_sampler_ycbcr_conversion",
+    ext_debug_marker => b"VK_EXT_debug_marker",
+    ext_conditional_rendering => b"VK_EXT_conditional_rendering",
+    khr_draw_indirect_count => b"VK_KHR_draw_indirect_count",
+}
+
+impl DeviceExtensions {
+    /// See the docs of supported_by_device().
+    pub fn supported_by_device_raw(physical_device: &PhysicalDevice)
+                                   -> Result<DeviceExtensions, SupportedExtensionsError>
+    {
+        let vk = physical_device.instance().pointers();
+
+        let properties: Vec<vk::ExtensionProperties> = unsafe {
+            let mut num = 0;
+            try!(check_errors(vk.EnumerateDeviceExtensionProperties(
+                physical_device.internal_object(), ptr::null(), &mut num, ptr::null_mut())));
+
+            let mut properties = Vec::with_capacity(num as usize);
+            try!(check_errors(vk.EnumerateDeviceExtensionProperties(
+                physical_device.internal_object(), ptr::null(), &mut num,
+                properties.as_mut_ptr())));
+            properties.set_len(num as usize);
+            properties
+        };
+
+        Ok(DeviceExtensions::from_properties(&properties))
+    }
+
+    /// Returns a `DeviceExtensions` object with extensions supported by the physical device.
+    #[inline]
+    pub fn supported_by_device(physical_device: &PhysicalDevice) -> DeviceExtensions {
+        match DeviceExtensions::supported_by_device_raw(physical_device) {
+            Ok(l) => l,
+            Err(SupportedExtensionsError::LoadingError(_)) => unreachable!(),
+            Err(SupportedExtensionsError::OomError(e)) => panic!("{:?}", e),
+        }
+    }
 }
 
 /// Error that can happen when loading the list of layers.
@@ -176,6 +287,16 @@ impl fmt::Display for SupportedExtensionsError {
     }
 }
 
+impl VulkanError for SupportedExtensionsError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            SupportedExtensionsError::LoadingError(ref err) => err.error_code(),
+            SupportedExtensionsError::OomError(ref err) => err.error_code(),
+        }
+    }
+}
+
 impl From<OomError> for SupportedExtensionsError {
     #[inline]
     fn from(err: OomError) -> SupportedExtensionsError {
@@ -224,4 +345,20 @@ mod tests {
         let d = DeviceExtensions::none().build_extensions_list();
         assert!(d.is_empty());
     }
+
+    #[test]
+    fn set_operations() {
+        let a = InstanceExtensions { khr_surface: true, khr_display: true,
+                                     .. InstanceExtensions::none() };
+        let b = InstanceExtensions { khr_surface: true, khr_xcb_surface: true,
+                                     .. InstanceExtensions::none() };
+
+        assert_eq!(a.intersection(&b).names(), vec!["VK_KHR_surface"]);
+        assert_eq!(a.difference(&b).names(), vec!["VK_KHR_display"]);
+        assert_eq!(a.union(&b).names(),
+                   vec!["VK_KHR_surface", "VK_KHR_display", "VK_KHR_xcb_surface"]);
+        assert!(a.union(&b).superset_of(&a));
+        assert!(!a.superset_of(&b));
+        assert!(a.superset_of(&InstanceExtensions::none()));
+    }
 }
@@ -20,9 +20,12 @@ use smallvec::SmallVec;
 
 use instance::loader;
 use instance::loader::LoadingError;
+use instance::LayerSelection;
+use instance::LayersListError;
 use check_errors;
 use Error;
 use OomError;
+use VulkanError;
 use VulkanObject;
 use VulkanPointers;
 use vk;
@@ -84,9 +87,12 @@ pub struct Instance {
     instance: vk::Instance,
     //alloc: Option<Box<Alloc + Send + Sync>>,
     physical_devices: Vec<PhysicalDeviceInfos>,
+    device_groups: Vec<DeviceGroupInfos>,
     vk: vk::InstancePointers,
     extensions: InstanceExtensions,
     layers: SmallVec<[CString; 16]>,
+    // If false, the instance was created outside of vulkano and must not be destroyed.
+    owned: bool,
 }
 
 impl Instance {
@@ -119,10 +125,17 @@ impl Instance {
                       layers: L) -> Result<Arc<Instance>, InstanceCreationError>
         where L: IntoIterator<Item = &'a &'a str>
     {
-        let layers = layers.into_iter().map(|&layer| {
-            CString::new(layer).unwrap()
-        }).collect::<SmallVec<[_; 16]>>();
+        let layers = try!(layer_names(layers.into_iter().map(|&layer| layer)));
+        Instance::new_inner(app_infos, extensions, layers)
+    }
 
+    /// Same as `new`, but enables the layers of `layers` that are available on the system.
+    ///
+    /// Use `loaded_layers` to know which layers were enabled.
+    pub fn with_layers(app_infos: Option<&ApplicationInfo>, extensions: &InstanceExtensions,
+                       layers: &LayerSelection) -> Result<Arc<Instance>, InstanceCreationError>
+    {
+        let layers = try!(layer_names(try!(layers.resolve())));
         Instance::new_inner(app_infos, extensions, layers)
     }
 
@@ -194,10 +207,52 @@ impl Instance {
                 ppEnabledExtensionNames: extensions_list.as_ptr(),
             };
 
-            try!(check_errors(entry_points.CreateInstance(&infos, ptr::null(), &mut output)));
+            match check_errors(entry_points.CreateInstance(&infos, ptr::null(), &mut output)) {
+                Ok(_) => (),
+                Err(Error::ExtensionNotPresent) => {
+                    return Err(Instance::missing_extensions(extensions, &layers));
+                },
+                Err(err) => return Err(err.into()),
+            };
+
             output
         };
 
+        unsafe { Instance::from_raw_parts(instance, extensions, layers, true) }
+    }
+
+    /// Builds an `Instance` from a Vulkan instance that was created outside of vulkano.
+    ///
+    /// This can be used to embed vulkano in an application or an engine that handles the
+    /// initialization of Vulkan itself. The Vulkan instance is not destroyed when the `Instance`
+    /// is dropped.
+    ///
+    /// # Safety
+    ///
+    /// - `handle` must be a valid Vulkan instance that was created with the same loader as the
+    ///   one vulkano uses.
+    /// - `extensions` and `layers` must be exactly the extensions and layers that were enabled
+    ///   when creating `handle`.
+    /// - `handle` must stay alive for as long as the `Instance` and all the objects created from
+    ///   it are alive.
+    ///
+    pub unsafe fn from_handle<'a, L>(handle: vk::Instance, extensions: &InstanceExtensions,
+                                     layers: L) -> Result<Arc<Instance>, InstanceCreationError>
+        where L: IntoIterator<Item = &'a &'a str>
+    {
+        let layers = try!(layer_names(layers.into_iter().map(|&layer| layer)));
+
+        // Make sure that the loader is available, since we use it to load the function pointers.
+        try!(loader::entry_points());
+
+        Instance::from_raw_parts(handle, extensions, layers, false)
+    }
+
+    // Loads the function pointers and queries the physical devices of a Vulkan instance.
+    unsafe fn from_raw_parts(instance: vk::Instance, extensions: &InstanceExtensions,
+                             layers: SmallVec<[CString; 16]>, owned: bool)
+                             -> Result<Arc<Instance>, InstanceCreationError>
+    {
         // Loading the function pointers of the newly-created instance.
         let vk = {
             let f = loader::static_functions().unwrap();        // TODO: return proper error
@@ -263,16 +318,84 @@ impl Instance {
             output
         };
 
+        // Enumerating the groups of physical devices. Without the `khr_device_group_creation`
+        // extension, each physical device is alone in its own group.
+        let device_groups = if extensions.khr_device_group_creation {
+            unsafe {
+                let mut num = 0;
+                try!(check_errors(vk.EnumeratePhysicalDeviceGroupsKHR(instance, &mut num,
+                                                                      ptr::null_mut())));
+
+                let mut groups = (0 .. num).map(|_| {
+                    vk::PhysicalDeviceGroupPropertiesKHR {
+                        sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_GROUP_PROPERTIES_KHR,
+                        pNext: ptr::null_mut(),
+                        physicalDeviceCount: 0,
+                        physicalDevices: [0; vk::MAX_DEVICE_GROUP_SIZE_KHR as usize],
+                        subsetAllocation: vk::FALSE,
+                    }
+                }).collect::<Vec<_>>();
+                try!(check_errors(vk.EnumeratePhysicalDeviceGroupsKHR(instance, &mut num,
+                                                                      groups.as_mut_ptr())));
+                groups.truncate(num as usize);
+
+                groups.iter().map(|group| {
+                    let devices = &group.physicalDevices[.. group.physicalDeviceCount as usize];
+                    DeviceGroupInfos {
+                        physical_devices: devices.iter().map(|&dev| {
+                            physical_devices.iter().position(|infos| infos.device == dev)
+                                            .expect("device group contains an unknown device")
+                        }).collect(),
+                        subset_allocation: group.subsetAllocation != 0,
+                    }
+                }).collect()
+            }
+        } else {
+            (0 .. physical_devices.len()).map(|index| {
+                DeviceGroupInfos {
+                    physical_devices: Some(index).into_iter().collect(),
+                    subset_allocation: false,
+                }
+            }).collect()
+        };
+
         Ok(Arc::new(Instance {
             instance: instance,
             //alloc: None,
             physical_devices: physical_devices,
+            device_groups: device_groups,
             vk: vk,
             extensions: extensions.clone(),
             layers: layers,
+            owned: owned,
         }))
     }
 
+    // Builds the error to return when the creation of the instance failed because of a missing
+    // extension, by finding out which of the requested extensions are neither supported by the
+    // core driver nor by the requested layers.
+    fn missing_extensions(extensions: &InstanceExtensions, layers: &[CString])
+                          -> InstanceCreationError
+    {
+        let mut supported = match InstanceExtensions::supported_by_core_raw() {
+            Ok(s) => s,
+            Err(_) => return InstanceCreationError::ExtensionNotPresent,
+        };
+
+        for layer in layers {
+            if let Ok(l) = InstanceExtensions::supported_by_layer_raw(Some(layer)) {
+                supported = supported.union(&l);
+            }
+        }
+
+        let missing = extensions.difference(&supported);
+        if missing == InstanceExtensions::none() {
+            InstanceCreationError::ExtensionNotPresent
+        } else {
+            InstanceCreationError::MissingExtensions(missing)
+        }
+    }
+
     /*/// Same as `new`, but provides an allocator that will be used by the Vulkan library whenever
     /// it needs to allocate memory on the host.
     ///
@@ -300,8 +423,7 @@ impl Instance {
         &self.extensions
     }
 
-    /// Returns the list of layers requested when creating this instance.
-    #[doc(hidden)]
+    /// Returns the list of layers that are enabled on this instance.
     #[inline]
     pub fn loaded_layers(&self) -> slice::Iter<CString> {
         self.layers.iter()
@@ -336,6 +458,10 @@ impl VulkanPointers for Instance {
 impl Drop for Instance {
     #[inline]
     fn drop(&mut self) {
+        if !self.owned {
+            return;
+        }
+
         unsafe {
             self.vk.DestroyInstance(self.instance, ptr::null());
         }
@@ -401,6 +527,9 @@ pub enum InstanceCreationError {
     LayerNotPresent,
     /// One of the requested extensions is missing.
     ExtensionNotPresent,
+    /// The requested extensions that are supported neither by the implementation nor by the
+    /// requested layers.
+    MissingExtensions(InstanceExtensions),
     /// The version requested is not supported by the implementation.
     // TODO: more info about this once the question of the version has been resolved
     IncompatibleDriver,
@@ -415,6 +544,7 @@ impl error::Error for InstanceCreationError {
             InstanceCreationError::InitializationFailed => "initialization failed",
             InstanceCreationError::LayerNotPresent => "layer not present",
             InstanceCreationError::ExtensionNotPresent => "extension not present",
+            InstanceCreationError::MissingExtensions(_) => "some extensions are not present",
             InstanceCreationError::IncompatibleDriver => "incompatible driver",
         }
     }
@@ -432,7 +562,28 @@ impl error::Error for InstanceCreationError {
 impl fmt::Display for InstanceCreationError {
     #[inline]
     fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(fmt, "{}", error::Error::description(self))
+        match *self {
+            InstanceCreationError::MissingExtensions(ref missing) => {
+                write!(fmt, "{}: {}", error::Error::description(self),
+                       missing.names().join(", "))
+            },
+            _ => write!(fmt, "{}", error::Error::description(self)),
+        }
+    }
+}
+
+impl VulkanError for InstanceCreationError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            InstanceCreationError::LoadingError(ref err) => err.error_code(),
+            InstanceCreationError::OomError(ref err) => err.error_code(),
+            InstanceCreationError::InitializationFailed => Some(Error::InitializationFailed),
+            InstanceCreationError::LayerNotPresent => Some(Error::LayerNotPresent),
+            InstanceCreationError::ExtensionNotPresent => Some(Error::ExtensionNotPresent),
+            InstanceCreationError::IncompatibleDriver => Some(Error::IncompatibleDriver),
+            _ => None
+        }
     }
 }
 
@@ -443,6 +594,16 @@ impl From<OomError> for InstanceCreationError {
     }
 }
 
+impl From<LayersListError> for InstanceCreationError {
+    #[inline]
+    fn from(err: LayersListError) -> InstanceCreationError {
+        match err {
+            LayersListError::LoadingError(err) => InstanceCreationError::LoadingError(err),
+            LayersListError::OomError(err) => InstanceCreationError::OomError(err),
+        }
+    }
+}
+
 impl From<LoadingError> for InstanceCreationError {
     #[inline]
     fn from(err: LoadingError) -> InstanceCreationError {
@@ -465,6 +626,17 @@ impl From<Error> for InstanceCreationError {
     }
 }
 
+// Converts a list of layer names to `CString`s. A name that contains a null character can't be
+// the name of an existing layer.
+fn layer_names<I>(layers: I) -> Result<SmallVec<[CString; 16]>, InstanceCreationError>
+    where I: IntoIterator,
+          I::Item: Into<Vec<u8>>
+{
+    layers.into_iter().map(|layer| {
+        CString::new(layer).map_err(|_| InstanceCreationError::LayerNotPresent)
+    }).collect()
+}
+
 struct PhysicalDeviceInfos {
     device: vk::PhysicalDevice,
     properties: vk::PhysicalDeviceProperties,
@@ -473,6 +645,12 @@ struct PhysicalDeviceInfos {
     available_features: Features,
 }
 
+struct DeviceGroupInfos {
+    // Indices of the physical devices within `Instance::physical_devices`.
+    physical_devices: SmallVec<[usize; 4]>,
+    subset_allocation: bool,
+}
+
 /// Represents one of the available devices on this machine.
 ///
 /// This struct simply contains a pointer to an instance and a number representing the physical
@@ -784,6 +962,116 @@ impl<'a> Iterator for PhysicalDevicesIter<'a> {
 impl<'a> ExactSizeIterator for PhysicalDevicesIter<'a> {
 }
 
+/// Group of physical devices that can be used together to create a single logical device.
+///
+/// The implementation only reports groups that contain multiple physical devices if the
+/// `khr_device_group_creation` extension is enabled on the instance. Otherwise each physical
+/// device is alone in its own group.
+///
+/// # Example
+///
+/// ```no_run
+/// # use vulkano::instance::Instance;
+/// # use vulkano::instance::InstanceExtensions;
+/// use vulkano::instance::PhysicalDeviceGroup;
+///
+/// # let instance = Instance::new(None, &InstanceExtensions::none(), None).unwrap();
+/// for group in PhysicalDeviceGroup::enumerate(&instance) {
+///     println!("Group of {} device(s)", group.physical_devices().len());
+/// }
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct PhysicalDeviceGroup<'a> {
+    instance: &'a Arc<Instance>,
+    group: usize,
+}
+
+impl<'a> PhysicalDeviceGroup<'a> {
+    /// Returns an iterator that enumerates the groups of physical devices available.
+    #[inline]
+    pub fn enumerate(instance: &'a Arc<Instance>) -> PhysicalDeviceGroupsIter<'a> {
+        PhysicalDeviceGroupsIter {
+            instance: instance,
+            current_id: 0,
+        }
+    }
+
+    /// Returns the instance corresponding to this group.
+    #[inline]
+    pub fn instance(&self) -> &'a Arc<Instance> {
+        &self.instance
+    }
+
+    /// Returns the index of the group in the list of groups.
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.group
+    }
+
+    /// Returns the physical devices of this group.
+    ///
+    /// The position of a physical device in this list is its index within the group, which is
+    /// the bit that represents it in device masks.
+    #[inline]
+    pub fn physical_devices(&self) -> Vec<PhysicalDevice<'a>> {
+        self.infos().physical_devices.iter().map(|&index| {
+            PhysicalDevice {
+                instance: self.instance,
+                device: index,
+            }
+        }).collect()
+    }
+
+    /// Returns true if memory can be allocated on a subset of the physical devices of the group.
+    ///
+    /// If false, the device mask of memory allocations must include all the physical devices.
+    #[inline]
+    pub fn supports_subset_allocation(&self) -> bool {
+        self.infos().subset_allocation
+    }
+
+    // Internal function to make it easier to get the infos of this group.
+    #[inline]
+    fn infos(&self) -> &'a DeviceGroupInfos {
+        &self.instance.device_groups[self.group]
+    }
+}
+
+/// Iterator for all the groups of physical devices.
+#[derive(Debug, Clone)]
+pub struct PhysicalDeviceGroupsIter<'a> {
+    instance: &'a Arc<Instance>,
+    current_id: usize,
+}
+
+impl<'a> Iterator for PhysicalDeviceGroupsIter<'a> {
+    type Item = PhysicalDeviceGroup<'a>;
+
+    #[inline]
+    fn next(&mut self) -> Option<PhysicalDeviceGroup<'a>> {
+        if self.current_id >= self.instance.device_groups.len() {
+            return None;
+        }
+
+        let group = PhysicalDeviceGroup {
+            instance: self.instance,
+            group: self.current_id,
+        };
+
+        self.current_id += 1;
+        Some(group)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.instance.device_groups.len() - self.current_id;
+        (len, Some(len))
+    }
+}
+
+impl<'a> ExactSizeIterator for PhysicalDeviceGroupsIter<'a> {
+}
+
 /// Type of a physical device.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[repr(u32)]
@@ -857,6 +1145,21 @@ impl<'a> QueueFamily<'a> {
         (self.flags() & vk::QUEUE_SPARSE_BINDING_BIT) != 0
     }
 
+    /// Returns the number of meaningful bits in the timestamps written by queues of this family.
+    ///
+    /// Returns 0 if the queues of this family don't support timestamps. Otherwise the value is
+    /// between 36 and 64.
+    #[inline]
+    pub fn timestamp_valid_bits(&self) -> u32 {
+        self.physical_device.infos().queue_families[self.id as usize].timestampValidBits
+    }
+
+    /// Returns true if queues of this family can write timestamps.
+    #[inline]
+    pub fn supports_timestamps(&self) -> bool {
+        self.timestamp_valid_bits() != 0
+    }
+
     /// Internal utility function that returns the flags of this queue family.
     #[inline]
     fn flags(&self) -> u32 {
@@ -1209,6 +1512,7 @@ limits_impl!{
 #[cfg(test)]
 mod tests {
     use instance;
+    use instance::InstanceCreationError;
 
     #[test]
     fn create_instance() {
@@ -1232,4 +1536,41 @@ mod tests {
         let by_id = phys.queue_family_by_id(queue_family.id()).unwrap();
         assert_eq!(by_id.id(), queue_family.id());
     }
+
+    #[test]
+    fn from_handle() {
+        use VulkanObject;
+
+        let instance = instance!();
+        let raw = unsafe {
+            instance::Instance::from_handle(instance.internal_object(),
+                                            instance.loaded_extensions(), None).unwrap()
+        };
+
+        assert_eq!(instance::PhysicalDevice::enumerate(&raw).len(),
+                   instance::PhysicalDevice::enumerate(&instance).len());
+
+    }
+
+    #[test]
+    fn device_groups_cover_physical_devices() {
+        let instance = instance!();
+
+        let mut grouped = instance::PhysicalDeviceGroup::enumerate(&instance)
+                                      .flat_map(|g| g.physical_devices())
+                                      .map(|d| d.index()).collect::<Vec<_>>();
+        grouped.sort();
+
+        let all = instance::PhysicalDevice::enumerate(&instance).map(|d| d.index())
+                                                                 .collect::<Vec<_>>();
+        assert_eq!(grouped, all);
+    }
+
+    #[test]
+    fn layer_name_with_null_character() {
+        match super::layer_names(vec!["VK_LAYER_foo", "VK_LAYER\0bar"]) {
+            Err(InstanceCreationError::LayerNotPresent) => (),
+            _ => panic!()
+        }
+    }
 }
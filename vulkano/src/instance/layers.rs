@@ -16,6 +16,7 @@ use std::vec::IntoIter;
 use check_errors;
 use OomError;
 use Error;
+use VulkanError;
 use vk;
 use instance::loader;
 use instance::loader::LoadingError;
@@ -146,6 +147,108 @@ impl LayerProperties {
     }
 }
 
+/// Names of the layers to enable when creating an instance.
+///
+/// Layers can either be required, in which case creating the instance fails if they are missing,
+/// or requested, in which case they are only enabled if they are available on the system.
+///
+/// Pass the selection to `Instance::with_layers`. The layers that were actually enabled can then
+/// be retrieved with `Instance::loaded_layers`.
+///
+/// # Example
+///
+/// ```no_run
+/// use vulkano::instance::Instance;
+/// use vulkano::instance::InstanceExtensions;
+/// use vulkano::instance::LayerSelection;
+///
+/// let layers = LayerSelection::new().with_standard_validation();
+/// let instance = Instance::with_layers(None, &InstanceExtensions::none(), &layers).unwrap();
+///
+/// for layer in instance.loaded_layers() {
+///     println!("Enabled layer: {:?}", layer);
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LayerSelection {
+    required: Vec<String>,
+    requested: Vec<String>,
+    standard_validation: bool,
+}
+
+impl LayerSelection {
+    /// Builds an empty selection.
+    #[inline]
+    pub fn new() -> LayerSelection {
+        LayerSelection::default()
+    }
+
+    /// Adds a layer that must be enabled. Creating the instance fails with `LayerNotPresent` if
+    /// it isn't available.
+    #[inline]
+    pub fn require(mut self, name: &str) -> LayerSelection {
+        self.required.push(name.to_owned());
+        self
+    }
+
+    /// Adds a layer that is enabled only if it is available.
+    #[inline]
+    pub fn request(mut self, name: &str) -> LayerSelection {
+        self.requested.push(name.to_owned());
+        self
+    }
+
+    /// Requests the standard validation layers, if they are available.
+    ///
+    /// Enables `VK_LAYER_KHRONOS_validation`, or `VK_LAYER_LUNARG_standard_validation` on systems
+    /// that only provide the older meta-layer.
+    #[inline]
+    pub fn with_standard_validation(mut self) -> LayerSelection {
+        self.standard_validation = true;
+        self
+    }
+
+    /// Returns the names of the layers that would be enabled, given the layers that are
+    /// available on the system.
+    pub fn resolve(&self) -> Result<Vec<String>, LayersListError> {
+        let available = try!(layers_list()).map(|l| l.name().to_owned()).collect::<Vec<_>>();
+        Ok(self.resolve_from(&available))
+    }
+
+    fn resolve_from(&self, available: &[String]) -> Vec<String> {
+        let is_available = |name: &str| available.iter().any(|l| l == name);
+
+        let mut list = self.required.clone();
+
+        for name in self.requested.iter() {
+            if is_available(name) {
+                list.push(name.clone());
+            }
+        }
+
+        if self.standard_validation {
+            let validation = STANDARD_VALIDATION_LAYERS.iter().find(|&&l| is_available(l));
+            if let Some(&name) = validation {
+                list.push(name.to_owned());
+            }
+        }
+
+        let mut deduplicated: Vec<String> = Vec::with_capacity(list.len());
+        for name in list {
+            if !deduplicated.contains(&name) {
+                deduplicated.push(name);
+            }
+        }
+        deduplicated
+    }
+}
+
+// Names of the standard validation layers, by order of preference.
+const STANDARD_VALIDATION_LAYERS: &'static [&'static str] = &[
+    "VK_LAYER_KHRONOS_validation",
+    "VK_LAYER_LUNARG_standard_validation",
+];
+
 /// Error that can happen when loading the list of layers.
 #[derive(Clone, Debug)]
 pub enum LayersListError {
@@ -180,6 +283,16 @@ impl fmt::Display for LayersListError {
     }
 }
 
+impl VulkanError for LayersListError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            LayersListError::LoadingError(ref err) => err.error_code(),
+            LayersListError::OomError(ref err) => err.error_code(),
+        }
+    }
+}
+
 impl From<OomError> for LayersListError {
     #[inline]
     fn from(err: OomError) -> LayersListError {
@@ -231,6 +344,7 @@ impl ExactSizeIterator for LayersIterator {
 #[cfg(test)]
 mod tests {
     use instance;
+    use instance::LayerSelection;
 
     #[test]
     fn layers_list() {
@@ -241,4 +355,19 @@ mod tests {
 
         while let Some(_) = list.next() {}
     }
+
+    #[test]
+    fn selection_resolve() {
+        let available = vec!["VK_LAYER_LUNARG_standard_validation".to_owned(),
+                             "VK_LAYER_foo".to_owned()];
+
+        let selection = LayerSelection::new().require("VK_LAYER_missing")
+                                             .request("VK_LAYER_foo")
+                                             .request("VK_LAYER_bar")
+                                             .with_standard_validation();
+
+        assert_eq!(selection.resolve_from(&available),
+                   vec!["VK_LAYER_missing".to_owned(), "VK_LAYER_foo".to_owned(),
+                        "VK_LAYER_LUNARG_standard_validation".to_owned()]);
+    }
 }
@@ -13,6 +13,8 @@ use std::mem;
 use std::path::Path;
 use std::ptr;
 
+use Error;
+use VulkanError;
 use shared_library;
 use vk;
 
@@ -117,3 +119,10 @@ impl fmt::Display for LoadingError {
         write!(fmt, "{}", error::Error::description(self))
     }
 }
+
+impl VulkanError for LoadingError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        None
+    }
+}
@@ -107,11 +107,14 @@
 pub use features::Features;
 pub use self::extensions::DeviceExtensions;
 pub use self::extensions::InstanceExtensions;
+pub use self::extensions::SupportedExtensionsError;
 pub use self::instance::Instance;
 pub use self::instance::InstanceCreationError;
 pub use self::instance::ApplicationInfo;
 pub use self::instance::PhysicalDevice;
 pub use self::instance::PhysicalDevicesIter;
+pub use self::instance::PhysicalDeviceGroup;
+pub use self::instance::PhysicalDeviceGroupsIter;
 pub use self::instance::PhysicalDeviceType;
 pub use self::instance::QueueFamiliesIter;
 pub use self::instance::QueueFamily;
@@ -121,6 +124,8 @@ pub use self::instance::MemoryHeapsIter;
 pub use self::instance::MemoryHeap;
 pub use self::instance::Limits;
 pub use self::layers::layers_list;
+pub use self::layers::LayerSelection;
+pub use self::layers::LayersListError;
 pub use self::layers::LayerProperties;
 pub use self::layers::LayersIterator;
 pub use self::loader::LoadingError;
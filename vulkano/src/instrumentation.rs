@@ -0,0 +1,233 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Statistics and events about the resources created on a device.
+//!
+//! Each `Device` counts the buffers, images and pipelines that are currently alive. These counts
+//! are returned by `Device::resource_stats()` and can for example be checked at the end of each
+//! frame to find out whether some resources are leaking. The memory allocations are counted by
+//! `Device::memory_stats()` instead.
+//!
+//! In addition to this, a callback can be registered with `Device::set_resource_callback()`.
+//! It is called with a `ResourceEvent` every time one of these resources or a memory allocation
+//! is created or destroyed, which makes it possible to build tools that track the lifetime of
+//! individual resources. When no callback is registered, the events aren't built at all.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use vulkano::instrumentation::ResourceEvent;
+//!
+//! # let device: std::sync::Arc<vulkano::device::Device> = unsafe { std::mem::uninitialized() };
+//! device.set_resource_callback(|event| {
+//!     match *event {
+//!         ResourceEvent::Created { kind, handle } => println!("+ {:?} {:x}", kind, handle),
+//!         ResourceEvent::Destroyed { kind, handle } => println!("- {:?} {:x}", kind, handle),
+//!     }
+//! });
+//!
+//! // ...
+//!
+//! println!("{} buffers alive", device.resource_stats().buffers);
+//! ```
+//!
+//! Only the objects that vulkano creates and destroys itself are counted. For example the images
+//! of a swapchain are not.
+
+use std::fmt;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+/// Number of resources of each kind that are currently alive on a device. Returned by
+/// `Device::resource_stats()`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ResourceStats {
+    /// Number of `UnsafeBuffer`s.
+    pub buffers: usize,
+    /// Number of `UnsafeImage`s, not including the images of swapchains.
+    pub images: usize,
+    /// Number of compute and graphics pipelines.
+    pub pipelines: usize,
+}
+
+/// Kind of a resource whose creation and destruction is tracked.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    Buffer,
+    Image,
+    DeviceMemory,
+    Pipeline,
+}
+
+/// Event passed to the callback registered with `Device::set_resource_callback()`.
+///
+/// `handle` is the raw Vulkan handle of the resource, as returned by `internal_object()`. The
+/// same handle can be reused by the implementation after the resource has been destroyed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ResourceEvent {
+    /// A resource has been created.
+    Created { kind: ResourceKind, handle: u64 },
+    /// A resource has been destroyed.
+    Destroyed { kind: ResourceKind, handle: u64 },
+}
+
+/// Keeps track of the resources that are alive on a device.
+#[doc(hidden)]
+pub struct ResourceTracker {
+    buffers: AtomicUsize,
+    images: AtomicUsize,
+    pipelines: AtomicUsize,
+    // True if `callback` contains a callback. Avoids locking the mutex for each event otherwise.
+    has_callback: AtomicBool,
+    callback: Mutex<Option<Arc<Fn(&ResourceEvent) + Send + Sync>>>,
+}
+
+impl ResourceTracker {
+    #[inline]
+    pub fn new() -> ResourceTracker {
+        ResourceTracker {
+            buffers: AtomicUsize::new(0),
+            images: AtomicUsize::new(0),
+            pipelines: AtomicUsize::new(0),
+            has_callback: AtomicBool::new(false),
+            callback: Mutex::new(None),
+        }
+    }
+
+    /// Registers that a resource has been created.
+    #[inline]
+    pub fn created(&self, kind: ResourceKind, handle: u64) {
+        if let Some(count) = self.count(kind) {
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+        self.notify(ResourceEvent::Created { kind: kind, handle: handle });
+    }
+
+    /// Registers that a resource has been destroyed.
+    #[inline]
+    pub fn destroyed(&self, kind: ResourceKind, handle: u64) {
+        if let Some(count) = self.count(kind) {
+            let previous = count.fetch_sub(1, Ordering::Relaxed);
+            debug_assert!(previous >= 1);
+        }
+        self.notify(ResourceEvent::Destroyed { kind: kind, handle: handle });
+    }
+
+    /// Returns the number of resources of each kind that are alive.
+    #[inline]
+    pub fn stats(&self) -> ResourceStats {
+        ResourceStats {
+            buffers: self.buffers.load(Ordering::Relaxed),
+            images: self.images.load(Ordering::Relaxed),
+            pipelines: self.pipelines.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Replaces the callback that is called for each event.
+    #[inline]
+    pub fn set_callback(&self, callback: Option<Arc<Fn(&ResourceEvent) + Send + Sync>>) {
+        let mut lock = self.callback.lock().unwrap();
+        self.has_callback.store(callback.is_some(), Ordering::Release);
+        *lock = callback;
+    }
+
+    // Returns the counter of the given kind of resource. The memory allocations are counted by
+    // the `MemoryStatsTracker` of the device instead.
+    #[inline]
+    fn count(&self, kind: ResourceKind) -> Option<&AtomicUsize> {
+        match kind {
+            ResourceKind::Buffer => Some(&self.buffers),
+            ResourceKind::Image => Some(&self.images),
+            ResourceKind::DeviceMemory => None,
+            ResourceKind::Pipeline => Some(&self.pipelines),
+        }
+    }
+
+    #[inline]
+    fn notify(&self, event: ResourceEvent) {
+        if !self.has_callback.load(Ordering::Acquire) {
+            return;
+        }
+
+        // The lock isn't held while the callback runs, so that the callback can itself create
+        // or destroy resources.
+        let callback = match *self.callback.lock().unwrap() {
+            Some(ref callback) => callback.clone(),
+            None => return,
+        };
+
+        callback(&event);
+    }
+}
+
+impl fmt::Debug for ResourceTracker {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("ResourceTracker").field("stats", &self.stats()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use device::Device;
+    use instrumentation::ResourceEvent;
+    use instrumentation::ResourceKind;
+    use instrumentation::ResourceStats;
+    use instrumentation::ResourceTracker;
+    use memory::DeviceMemory;
+
+    #[test]
+    fn tracker_counts_and_events() {
+        let tracker = ResourceTracker::new();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events2 = events.clone();
+        tracker.set_callback(Some(Arc::new(move |ev: &ResourceEvent| {
+            events2.lock().unwrap().push(*ev);
+        })));
+
+        tracker.created(ResourceKind::Buffer, 1);
+        tracker.created(ResourceKind::Buffer, 2);
+        tracker.created(ResourceKind::Pipeline, 3);
+        tracker.destroyed(ResourceKind::Buffer, 1);
+
+        tracker.created(ResourceKind::DeviceMemory, 4);
+
+        assert_eq!(tracker.stats(), ResourceStats { buffers: 1, images: 0, pipelines: 1 });
+        assert_eq!(events.lock().unwrap().len(), 5);
+        assert_eq!(events.lock().unwrap()[3],
+                   ResourceEvent::Destroyed { kind: ResourceKind::Buffer, handle: 1 });
+
+        tracker.set_callback(None);
+        tracker.created(ResourceKind::Image, 5);
+        assert_eq!(events.lock().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn device_stats() {
+        let (device, _) = gfx_dev_and_queue!();
+        let mem_ty = device.physical_device().memory_types().next().unwrap();
+
+        let before = device.resource_stats();
+        let count = |device: &Device| {
+            device.memory_stats().memory_types[mem_ty.id() as usize].allocation_count
+        };
+        let count_before = count(&device);
+
+        let mem = DeviceMemory::alloc(&device, mem_ty, 256).unwrap();
+        assert_eq!(count(&device), count_before + 1);
+        assert_eq!(device.resource_stats(), before);
+
+        drop(mem);
+        assert_eq!(count(&device), count_before);
+    }
+}
@@ -50,6 +50,8 @@
 
 extern crate crossbeam;
 extern crate fnv;
+#[cfg(feature = "glsl")]
+extern crate glsl_to_spirv;
 #[macro_use]
 extern crate lazy_static;
 extern crate shared_library;
@@ -71,8 +73,10 @@ pub mod format;
 pub mod framebuffer;
 pub mod image;
 pub mod instance;
+pub mod instrumentation;
 pub mod memory;
 pub mod pipeline;
+pub mod profiler;
 pub mod query;
 pub mod sampler;
 pub mod swapchain;
@@ -116,6 +120,45 @@ trait VulkanPointers {
     fn pointers(&self) -> &Self::Pointers;
 }
 
+/// Trait implemented on all the error types of vulkano.
+///
+/// Errors that originate from a Vulkan function keep the result code that the function returned,
+/// which is accessible with `error_code()`. Errors that wrap another error delegate to it, and
+/// also return it from `cause()`. This makes it possible to write error handling and logging
+/// that works for any vulkano error, for example to detect a lost device regardless of the
+/// operation that failed.
+///
+/// # Example
+///
+/// ```ignore
+/// fn log_error<E: VulkanError>(err: &E) {
+///     match err.error_code() {
+///         Some(code) => println!("{} (Vulkan error {})", err, code.raw()),
+///         None => println!("{}", err),
+///     }
+/// }
+/// ```
+pub trait VulkanError: error::Error {
+    /// Returns the Vulkan result code at the origin of this error, or `None` if the error was
+    /// detected by vulkano without calling Vulkan.
+    fn error_code(&self) -> Option<Error>;
+
+    /// Returns true if the error is caused by a lack of host or device memory.
+    #[inline]
+    fn is_out_of_memory(&self) -> bool {
+        match self.error_code() {
+            Some(Error::OutOfHostMemory) | Some(Error::OutOfDeviceMemory) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns true if the error is caused by the connection to the device being lost.
+    #[inline]
+    fn is_device_lost(&self) -> bool {
+        self.error_code() == Some(Error::DeviceLost)
+    }
+}
+
 /// Error type returned by most Vulkan functions.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum OomError {
@@ -142,6 +185,16 @@ impl fmt::Display for OomError {
     }
 }
 
+impl VulkanError for OomError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            OomError::OutOfHostMemory => Some(Error::OutOfHostMemory),
+            OomError::OutOfDeviceMemory => Some(Error::OutOfDeviceMemory),
+        }
+    }
+}
+
 impl From<Error> for OomError {
     #[inline]
     fn from(err: Error) -> OomError {
@@ -156,7 +209,8 @@ impl From<Error> for OomError {
 /// All possible success codes returned by any Vulkan function.
 #[derive(Debug, Copy, Clone)]
 #[repr(u32)]
-enum Success {
+#[doc(hidden)]      // TODO: this is necessary because of the stupid visibility rules in rustc
+pub enum Success {
     Success = vk::SUCCESS,
     NotReady = vk::NOT_READY,
     Timeout = vk::TIMEOUT,
@@ -168,11 +222,11 @@ enum Success {
 
 /// All possible errors returned by any Vulkan function.
 ///
-/// This type is not public. Instead all public error types should implement `From<Error>` and
-/// panic for error code that arent supposed to happen.
-#[derive(Debug, Copy, Clone)]
+/// Functions don't return this type directly. Instead all public error types implement
+/// `From<Error>` and panic for error codes that aren't supposed to happen, and give back the
+/// original code with `VulkanError::error_code()`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u32)]
-#[doc(hidden)]      // TODO: this is necessary because of the stupid visibility rules in rustc
 pub enum Error {
     OutOfHostMemory = vk::ERROR_OUT_OF_HOST_MEMORY,
     OutOfDeviceMemory = vk::ERROR_OUT_OF_DEVICE_MEMORY,
@@ -185,11 +239,66 @@ pub enum Error {
     IncompatibleDriver = vk::ERROR_INCOMPATIBLE_DRIVER,
     TooManyObjects = vk::ERROR_TOO_MANY_OBJECTS,
     FormatNotSupported = vk::ERROR_FORMAT_NOT_SUPPORTED,
+    FragmentedPool = vk::ERROR_FRAGMENTED_POOL,
     SurfaceLost = vk::ERROR_SURFACE_LOST_KHR,
     NativeWindowInUse = vk::ERROR_NATIVE_WINDOW_IN_USE_KHR,
     OutOfDate = vk::ERROR_OUT_OF_DATE_KHR,
     IncompatibleDisplay = vk::ERROR_INCOMPATIBLE_DISPLAY_KHR,
     ValidationFailed = vk::ERROR_VALIDATION_FAILED_EXT,
+    OutOfPoolMemory = vk::ERROR_OUT_OF_POOL_MEMORY_KHR,
+    InvalidExternalHandle = vk::ERROR_INVALID_EXTERNAL_HANDLE_KHR,
+    FullScreenExclusiveLost = vk::ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT,
+}
+
+impl Error {
+    /// Returns the raw value of the result code, as defined by the Vulkan headers. Error codes
+    /// are always negative.
+    #[inline]
+    pub fn raw(&self) -> i32 {
+        *self as u32 as i32
+    }
+}
+
+impl error::Error for Error {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            Error::OutOfHostMemory => "no memory available on the host",
+            Error::OutOfDeviceMemory => "no memory available on the graphical device",
+            Error::InitializationFailed => "the initialization of an object failed",
+            Error::DeviceLost => "the connection to the device has been lost",
+            Error::MemoryMapFailed => "mapping a memory object failed",
+            Error::LayerNotPresent => "a requested layer is not present",
+            Error::ExtensionNotPresent => "a requested extension is not supported",
+            Error::FeatureNotPresent => "a requested feature is not supported",
+            Error::IncompatibleDriver => "the requested version of Vulkan is not supported",
+            Error::TooManyObjects => "too many objects of this type have been created",
+            Error::FormatNotSupported => "the requested format is not supported",
+            Error::FragmentedPool => "the pool is too fragmented to allocate the object",
+            Error::SurfaceLost => "the surface is no longer available",
+            Error::NativeWindowInUse => "the window is already in use by Vulkan or another API",
+            Error::OutOfDate => "the surface has changed and the swapchain must be recreated",
+            Error::IncompatibleDisplay => "the display is incompatible with the swapchain",
+            Error::ValidationFailed => "a command failed because of a validation error",
+            Error::OutOfPoolMemory => "there is no room left in the pool",
+            Error::InvalidExternalHandle => "the external handle isn't valid",
+            Error::FullScreenExclusiveLost => "the full-screen exclusive mode has been lost",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for Error {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        Some(*self)
+    }
 }
 
 /// Checks whether the result returned correctly.
@@ -212,12 +321,16 @@ fn check_errors(result: vk::Result) -> Result<Success, Error> {
         vk::ERROR_INCOMPATIBLE_DRIVER => Err(Error::IncompatibleDriver),
         vk::ERROR_TOO_MANY_OBJECTS => Err(Error::TooManyObjects),
         vk::ERROR_FORMAT_NOT_SUPPORTED => Err(Error::FormatNotSupported),
+        vk::ERROR_FRAGMENTED_POOL => Err(Error::FragmentedPool),
         vk::ERROR_SURFACE_LOST_KHR => Err(Error::SurfaceLost),
         vk::ERROR_NATIVE_WINDOW_IN_USE_KHR => Err(Error::NativeWindowInUse),
         vk::SUBOPTIMAL_KHR => Ok(Success::Suboptimal),
         vk::ERROR_OUT_OF_DATE_KHR => Err(Error::OutOfDate),
         vk::ERROR_INCOMPATIBLE_DISPLAY_KHR => Err(Error::IncompatibleDisplay),
         vk::ERROR_VALIDATION_FAILED_EXT => Err(Error::ValidationFailed),
+        vk::ERROR_OUT_OF_POOL_MEMORY_KHR => Err(Error::OutOfPoolMemory),
+        vk::ERROR_INVALID_EXTERNAL_HANDLE_KHR => Err(Error::InvalidExternalHandle),
+        vk::ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT => Err(Error::FullScreenExclusiveLost),
         c => unreachable!("Unexpected error code returned by Vulkan: {}", c)
     }
 }
@@ -0,0 +1,438 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use buffer::sys::UnsafeBuffer;
+use command_buffer::sys::PipelineBarrierBuilder;
+use device::Device;
+use image::sys::Layout;
+use image::sys::UnsafeImage;
+use memory::DeviceMemoryAllocError;
+use memory::MemoryRequirements;
+use memory::MemoryUsage;
+use memory::pool::AllocLayout;
+use memory::pool::MemoryPool;
+use memory::pool::MemoryPoolAlloc;
+use memory::pool::StdMemoryPoolAlloc;
+use sync::AccessFlagBits;
+use sync::PipelineStages;
+
+use Error;
+use OomError;
+use VulkanError;
+use VulkanObject;
+use vk;
+
+/// Memory that is shared by multiple transient buffers and images that are never used at the
+/// same time.
+///
+/// A renderer that knows in advance in which order its intermediate targets are used can bind
+/// several of them to the same `AliasedMemory`, so that they occupy the memory of the largest
+/// one instead of the sum of all of them.
+///
+/// Every resource is bound at the start of the memory. The content of a resource is lost as soon
+/// as another resource that aliases it is written. Before the commands that use a resource, call
+/// `use_buffer` or `use_image` so that the aliasing barrier that is needed when switching from
+/// one resource to another is added to the pipeline barrier that precedes these commands.
+#[derive(Debug)]
+pub struct AliasedMemory<A = StdMemoryPoolAlloc> {
+    device: Arc<Device>,
+    memory: A,
+    size: usize,
+    memory_type_bits: u32,
+    // The resource that is currently using the memory, if any.
+    current: Mutex<Option<CurrentUse>>,
+}
+
+// Resource that is using an `AliasedMemory`, and how it is used.
+#[derive(Debug)]
+struct CurrentUse {
+    resource: AliasedResource,
+    stages: PipelineStages,
+    access: AccessFlagBits,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum AliasedResource {
+    Buffer(vk::Buffer),
+    Image(vk::Image),
+}
+
+impl AliasedMemory<StdMemoryPoolAlloc> {
+    /// Allocates memory from the standard pool of the device that is large enough to hold any
+    /// of the resources whose requirements are passed.
+    #[inline]
+    pub fn new<'a, I>(device: &Arc<Device>, requirements: I)
+                      -> Result<AliasedMemory<StdMemoryPoolAlloc>, AliasedMemoryError>
+        where I: IntoIterator<Item = &'a MemoryRequirements>
+    {
+        AliasedMemory::with_pool(device, &Device::standard_pool(device), requirements)
+    }
+}
+
+impl<A> AliasedMemory<A> where A: MemoryPoolAlloc {
+    /// Same as `new`, but allocates the memory from `pool`.
+    pub fn with_pool<'a, P, I>(device: &Arc<Device>, pool: &P, requirements: I)
+                               -> Result<AliasedMemory<A>, AliasedMemoryError>
+        where P: MemoryPool<Alloc = A>, I: IntoIterator<Item = &'a MemoryRequirements>
+    {
+        #[inline] fn align(val: usize, al: usize) -> usize { al * ((val + al - 1) / al) }
+
+        // Buffers and images can be mixed, therefore everything is aligned to the buffer-image
+        // granularity so that the memory never shares a page with a neighbour allocation.
+        let granularity = device.physical_device().limits().buffer_image_granularity() as usize;
+        let mut size = 0;
+        let mut alignment = if granularity == 0 { 1 } else { granularity };
+        let mut memory_type_bits = 0xffffffff;
+
+        for reqs in requirements {
+            if reqs.require_dedicated {
+                return Err(AliasedMemoryError::DedicatedAllocationRequired);
+            }
+
+            size = ::std::cmp::max(size, reqs.size);
+            // Alignments are always powers of two.
+            alignment = ::std::cmp::max(alignment, reqs.alignment);
+            memory_type_bits &= reqs.memory_type_bits;
+        }
+
+        if size == 0 {
+            return Err(AliasedMemoryError::NoRequirements);
+        }
+
+        let mem_ty = match MemoryUsage::DeviceLocal.choose(device.physical_device(),
+                                                           memory_type_bits)
+        {
+            Some(ty) => ty,
+            None => return Err(AliasedMemoryError::NoCommonMemoryType),
+        };
+
+        let size = align(size, alignment);
+        let memory = try!(MemoryPool::alloc(pool, mem_ty, size, alignment, AllocLayout::Optimal));
+        debug_assert!((memory.offset() % alignment) == 0);
+
+        Ok(AliasedMemory {
+            device: device.clone(),
+            memory: memory,
+            size: size,
+            memory_type_bits: 1 << mem_ty.id(),
+            current: Mutex::new(None),
+        })
+    }
+
+    /// Returns the size in bytes of the memory.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the allocation that holds the memory.
+    #[inline]
+    pub fn memory(&self) -> &A {
+        &self.memory
+    }
+
+    /// Binds a buffer to the memory.
+    ///
+    /// # Safety
+    ///
+    /// - The buffer must not have been bound to memory yet.
+    /// - The `AliasedMemory` must outlive the buffer.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the buffer doesn't fit in the memory or isn't compatible with its memory type.
+    /// - Panics if the buffer wasn't created from the same device.
+    ///
+    pub unsafe fn bind_buffer(&self, buffer: &UnsafeBuffer) -> Result<(), OomError> {
+        assert_eq!(&**buffer.device() as *const Device, &*self.device as *const Device);
+        self.check_requirements(&buffer.memory_requirements());
+        buffer.bind_memory(self.memory.memory(), self.memory.offset())
+    }
+
+    /// Binds an image to the memory.
+    ///
+    /// # Safety
+    ///
+    /// - The image must not have been bound to memory yet.
+    /// - The `AliasedMemory` must outlive the image.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the image doesn't fit in the memory or isn't compatible with its memory type.
+    /// - Panics if the image wasn't created from the same device.
+    ///
+    pub unsafe fn bind_image(&self, image: &UnsafeImage) -> Result<(), OomError> {
+        assert_eq!(&**image.device() as *const Device, &*self.device as *const Device);
+        self.check_requirements(&image.memory_requirements());
+        image.bind_memory(self.memory.memory(), self.memory.offset())
+    }
+
+    /// Declares that `buffer` is going to be used by the next commands, and adds to `barrier`
+    /// the aliasing barrier that is needed if another resource was using the memory until now.
+    ///
+    /// `stages` and `access` describe how the buffer is going to be used. Declaring the same
+    /// resource multiple times in a row merges the stages and accesses. Returns true if a
+    /// barrier was added.
+    ///
+    /// # Safety
+    ///
+    /// - The buffer must be bound to this memory.
+    /// - `barrier` must be recorded before the commands that use the buffer, and the uses of the
+    ///   resources bound to this memory must be declared in the order in which they execute.
+    ///
+    pub unsafe fn use_buffer(&self, barrier: &mut PipelineBarrierBuilder, buffer: &UnsafeBuffer,
+                             stages: PipelineStages, access: AccessFlagBits) -> bool
+    {
+        let resource = AliasedResource::Buffer(buffer.internal_object());
+        self.switch_to(barrier, resource, stages, access, None)
+    }
+
+    /// Declares that `image` is going to be used by the next commands in the given layout, and
+    /// adds to `barrier` the aliasing barrier that is needed if another resource was using the
+    /// memory until now.
+    ///
+    /// When the image starts using the memory, the barrier also transitions it from the
+    /// undefined layout to `layout`. Declaring the same resource multiple times in a row merges
+    /// the stages and accesses, and doesn't change the layout of the image. Returns true if a
+    /// barrier was added.
+    ///
+    /// # Safety
+    ///
+    /// - The image must be bound to this memory.
+    /// - `barrier` must be recorded before the commands that use the image, and the uses of the
+    ///   resources bound to this memory must be declared in the order in which they execute.
+    ///
+    pub unsafe fn use_image(&self, barrier: &mut PipelineBarrierBuilder, image: &UnsafeImage,
+                            layout: Layout, stages: PipelineStages, access: AccessFlagBits)
+                            -> bool
+    {
+        let resource = AliasedResource::Image(image.internal_object());
+        self.switch_to(barrier, resource, stages, access, Some((image, layout)))
+    }
+
+    unsafe fn switch_to(&self, barrier: &mut PipelineBarrierBuilder, resource: AliasedResource,
+                        stages: PipelineStages, access: AccessFlagBits,
+                        new_image: Option<(&UnsafeImage, Layout)>) -> bool
+    {
+        let mut current = self.current.lock().unwrap();
+
+        if let Some(ref mut current) = *current {
+            if current.resource == resource {
+                current.stages |= stages;
+                current.access |= access;
+                return false;
+            }
+        }
+
+        let added = match *current {
+            Some(ref previous) => {
+                barrier.add_aliasing_barrier(previous.stages, previous.access, stages, access,
+                                             new_image);
+                true
+            },
+            // The first resource doesn't have to wait for anything, but an image still has to be
+            // transitioned out of the undefined layout.
+            None if new_image.is_some() => {
+                let top_of_pipe = PipelineStages { top_of_pipe: true, .. PipelineStages::none() };
+                barrier.add_aliasing_barrier(top_of_pipe, AccessFlagBits::none(), stages, access,
+                                             new_image);
+                true
+            },
+            None => false,
+        };
+
+        *current = Some(CurrentUse {
+            resource: resource,
+            stages: stages,
+            access: access,
+        });
+
+        added
+    }
+
+    fn check_requirements(&self, reqs: &MemoryRequirements) {
+        assert!(reqs.size <= self.size);
+        assert!((reqs.memory_type_bits & self.memory_type_bits) != 0);
+        assert!((self.memory.offset() % reqs.alignment) == 0);
+    }
+}
+
+/// Error that can happen when allocating aliased memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AliasedMemoryError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The maximum number of memory allocations of the device has been reached.
+    TooManyObjects,
+    /// There is no memory type that is supported by all the resources.
+    NoCommonMemoryType,
+    /// One of the resources must have a dedicated allocation and can't alias other resources.
+    DedicatedAllocationRequired,
+    /// No memory requirements were passed.
+    NoRequirements,
+}
+
+impl error::Error for AliasedMemoryError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            AliasedMemoryError::OomError(_) => "not enough memory available",
+            AliasedMemoryError::TooManyObjects => {
+                "the maximum number of memory allocations has been reached"
+            },
+            AliasedMemoryError::NoCommonMemoryType => {
+                "there is no memory type that is supported by all the resources"
+            },
+            AliasedMemoryError::DedicatedAllocationRequired => {
+                "one of the resources requires a dedicated allocation"
+            },
+            AliasedMemoryError::NoRequirements => "no memory requirements were passed",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            AliasedMemoryError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for AliasedMemoryError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for AliasedMemoryError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            AliasedMemoryError::OomError(ref err) => err.error_code(),
+            AliasedMemoryError::TooManyObjects => Some(Error::TooManyObjects),
+            _ => None
+        }
+    }
+}
+
+impl From<OomError> for AliasedMemoryError {
+    #[inline]
+    fn from(err: OomError) -> AliasedMemoryError {
+        AliasedMemoryError::OomError(err)
+    }
+}
+
+impl From<DeviceMemoryAllocError> for AliasedMemoryError {
+    #[inline]
+    fn from(err: DeviceMemoryAllocError) -> AliasedMemoryError {
+        match err {
+            DeviceMemoryAllocError::OomError(err) => AliasedMemoryError::OomError(err),
+            DeviceMemoryAllocError::TooManyObjects => AliasedMemoryError::TooManyObjects,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter::Empty;
+
+    use command_buffer::sys::PipelineBarrierBuilder;
+    use format::Format;
+    use image::ImageDimensions;
+    use image::Usage;
+    use image::sys::Layout;
+    use image::sys::UnsafeImage;
+    use memory::AliasedMemory;
+    use memory::AliasedMemoryError;
+    use sync::AccessFlagBits;
+    use sync::PipelineStages;
+    use sync::Sharing;
+
+    #[test]
+    fn two_images() {
+        let (device, _) = gfx_dev_and_queue!();
+        let usage = Usage { color_attachment: true, .. Usage::none() };
+
+        let create = |width| unsafe {
+            let dims = ImageDimensions::Dim2d { width: width, height: 64, array_layers: 1,
+                                                cubemap_compatible: false };
+            UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm, dims, 1, 1,
+                             Sharing::Exclusive::<Empty<u32>>, false, false).unwrap()
+        };
+
+        let (small, small_reqs) = create(32);
+        let (big, big_reqs) = create(256);
+
+        let memory = AliasedMemory::new(&device, vec![&small_reqs, &big_reqs]).unwrap();
+        assert!(memory.size() >= big_reqs.size);
+        assert!(memory.size() < small_reqs.size + big_reqs.size);
+
+        unsafe {
+            memory.bind_image(&small).unwrap();
+            memory.bind_image(&big).unwrap();
+        }
+    }
+
+    #[test]
+    fn no_requirements() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        match AliasedMemory::new(&device, vec![]) {
+            Err(AliasedMemoryError::NoRequirements) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn switching_adds_barrier() {
+        let (device, _) = gfx_dev_and_queue!();
+        let usage = Usage { color_attachment: true, .. Usage::none() };
+
+        let create = || unsafe {
+            let dims = ImageDimensions::Dim2d { width: 64, height: 64, array_layers: 1,
+                                                cubemap_compatible: false };
+            UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm, dims, 1, 1,
+                             Sharing::Exclusive::<Empty<u32>>, false, false).unwrap()
+        };
+
+        let (first, first_reqs) = create();
+        let (second, second_reqs) = create();
+        let memory = AliasedMemory::new(&device, vec![&first_reqs, &second_reqs]).unwrap();
+
+        let stages = PipelineStages { color_attachment_output: true, .. PipelineStages::none() };
+        let access = AccessFlagBits { color_attachment_write: true, .. AccessFlagBits::none() };
+
+        unsafe {
+            memory.bind_image(&first).unwrap();
+            memory.bind_image(&second).unwrap();
+
+            let mut barrier = PipelineBarrierBuilder::new();
+            assert!(memory.use_image(&mut barrier, &first, Layout::ColorAttachmentOptimal,
+                                     stages, access));
+            assert!(!barrier.is_empty());
+
+            let mut barrier = PipelineBarrierBuilder::new();
+            assert!(!memory.use_image(&mut barrier, &first, Layout::ColorAttachmentOptimal,
+                                      stages, access));
+            assert!(barrier.is_empty());
+
+            let mut barrier = PipelineBarrierBuilder::new();
+            assert!(memory.use_image(&mut barrier, &second, Layout::ColorAttachmentOptimal,
+                                     stages, access));
+            assert!(!barrier.is_empty());
+        }
+    }
+}
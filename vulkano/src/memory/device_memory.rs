@@ -7,24 +7,45 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::error;
+use std::fmt;
 use std::mem;
 use std::ptr;
 use std::ops::Deref;
 use std::ops::DerefMut;
 use std::ops::Range;
+use std::os::raw::c_int;
 use std::os::raw::c_void;
 use std::sync::Arc;
 
+use buffer::sys::UnsafeBuffer;
 use instance::MemoryType;
 use device::Device;
+use image::sys::UnsafeImage;
+use instrumentation::ResourceKind;
 use memory::Content;
+use memory::ExternalMemoryError;
+use memory::ExternalMemoryHandleType;
+use Error;
 use OomError;
 use SafeDeref;
+use VulkanError;
 use VulkanObject;
 use VulkanPointers;
 use check_errors;
 use vk;
 
+/// Buffer or image that a memory allocation is dedicated to.
+#[derive(Debug, Copy, Clone)]
+pub enum DedicatedAlloc<'a> {
+    /// The allocation isn't dedicated to a specific resource.
+    None,
+    /// The allocation is dedicated to a buffer.
+    Buffer(&'a UnsafeBuffer),
+    /// The allocation is dedicated to an image.
+    Image(&'a UnsafeImage),
+}
+
 /// Represents memory that has been allocated.
 ///
 /// The destructor of `DeviceMemory` automatically frees the memory.
@@ -46,6 +67,16 @@ pub struct DeviceMemory<D = Arc<Device>> where D: SafeDeref<Target = Device> {
     device: D,
     size: usize,
     memory_type_index: u32,
+    // Handle types the memory can be exported to.
+    export_handle_types: vk::ExternalMemoryHandleTypeFlagsKHR,
+}
+
+// Describes how the memory is shared with the outside of vulkano at allocation.
+enum External {
+    None,
+    Export(ExternalMemoryHandleType),
+    ImportFd(ExternalMemoryHandleType, c_int),
+    ImportWin32(ExternalMemoryHandleType, *mut c_void),
 }
 
 impl<D> DeviceMemory<D> where D: SafeDeref<Target = Device> {
@@ -59,11 +90,164 @@ impl<D> DeviceMemory<D> where D: SafeDeref<Target = Device> {
     /// - Panics if `size` is 0.
     /// - Panics if `memory_type` doesn't belong to the same physical device as `device`.
     ///
-    // TODO: VK_ERROR_TOO_MANY_OBJECTS error
     // TODO: remove that `D` generic and use `Arc<Device>`
     #[inline]
     pub fn alloc(device: &D, memory_type: MemoryType, size: usize)
-                 -> Result<DeviceMemory<D>, OomError>
+                 -> Result<DeviceMemory<D>, DeviceMemoryAllocError>
+        where D: Clone
+    {
+        DeviceMemory::dedicated_alloc(device, memory_type, size, DedicatedAlloc::None)
+    }
+
+    /// Same as `alloc`, but the memory is dedicated to a single buffer or image.
+    ///
+    /// The resource must then be bound at offset 0 of the memory, and nothing else can be bound
+    /// to it. If the `khr_dedicated_allocation` extension isn't enabled on the device, the
+    /// resource is ignored and a regular allocation is performed.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `size` is 0.
+    /// - Panics if `memory_type` doesn't belong to the same physical device as `device`.
+    /// - Panics if the resource wasn't created from `device`.
+    ///
+    #[inline]
+    pub fn dedicated_alloc(device: &D, memory_type: MemoryType, size: usize,
+                           resource: DedicatedAlloc)
+                           -> Result<DeviceMemory<D>, DeviceMemoryAllocError>
+        where D: Clone
+    {
+        unsafe {
+            DeviceMemory::alloc_impl(device, memory_type, size, resource, External::None, None)
+                .map_err(DeviceMemoryAllocError::from_external)
+        }
+    }
+
+    /// Same as `dedicated_alloc`, but the memory can later be exported with `export_fd` or
+    /// `export_win32_handle` in order to be shared with another API or another process.
+    ///
+    /// Buffers and images that are bound to this memory must have been created with
+    /// `UnsafeBuffer::new_external` or `UnsafeImage::new_external` with the same handle type.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `size` is 0.
+    /// - Panics if `memory_type` doesn't belong to the same physical device as `device`.
+    /// - Panics if the resource wasn't created from `device`.
+    ///
+    #[inline]
+    pub fn alloc_exportable(device: &D, memory_type: MemoryType, size: usize,
+                            resource: DedicatedAlloc, handle_type: ExternalMemoryHandleType)
+                            -> Result<DeviceMemory<D>, ExternalMemoryError>
+        where D: Clone
+    {
+        if !handle_type.is_supported_by(device.loaded_extensions()) {
+            return Err(ExternalMemoryError::MissingExtension);
+        }
+
+        unsafe {
+            DeviceMemory::alloc_impl(device, memory_type, size, resource,
+                                     External::Export(handle_type), None)
+        }
+    }
+
+    /// Imports memory from a file descriptor that was exported by another API or another
+    /// process.
+    ///
+    /// On success, the ownership of the file descriptor is transferred to the Vulkan
+    /// implementation and you must not use or close it anymore.
+    ///
+    /// # Safety
+    ///
+    /// - `size` and `memory_type` must match the memory that was exported. The memory type must
+    ///   be one that is supported for this handle.
+    /// - If `resource` is not `None`, the exported memory must have been a dedicated allocation
+    ///   for a compatible resource.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `size` is 0.
+    /// - Panics if `memory_type` doesn't belong to the same physical device as `device`.
+    /// - Panics if the resource wasn't created from `device`.
+    /// - Panics if `handle_type` is not a file descriptor handle type.
+    ///
+    pub unsafe fn import_fd(device: &D, memory_type: MemoryType, size: usize,
+                            resource: DedicatedAlloc, handle_type: ExternalMemoryHandleType,
+                            fd: c_int) -> Result<DeviceMemory<D>, ExternalMemoryError>
+        where D: Clone
+    {
+        assert!(handle_type.is_fd());
+
+        if !handle_type.is_supported_by(device.loaded_extensions()) {
+            return Err(ExternalMemoryError::MissingExtension);
+        }
+
+        DeviceMemory::alloc_impl(device, memory_type, size, resource,
+                                 External::ImportFd(handle_type, fd), None)
+    }
+
+    /// Imports memory from a Windows handle that was exported by another API or another
+    /// process.
+    ///
+    /// Contrary to file descriptors, the ownership of the handle is not transferred. You must
+    /// close it yourself when you no longer need it.
+    ///
+    /// # Safety
+    ///
+    /// Same as `import_fd`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `size` is 0.
+    /// - Panics if `memory_type` doesn't belong to the same physical device as `device`.
+    /// - Panics if the resource wasn't created from `device`.
+    /// - Panics if `handle_type` is a file descriptor handle type.
+    ///
+    pub unsafe fn import_win32_handle(device: &D, memory_type: MemoryType, size: usize,
+                                      resource: DedicatedAlloc,
+                                      handle_type: ExternalMemoryHandleType, handle: *mut c_void)
+                                      -> Result<DeviceMemory<D>, ExternalMemoryError>
+        where D: Clone
+    {
+        assert!(!handle_type.is_fd());
+
+        if !handle_type.is_supported_by(device.loaded_extensions()) {
+            return Err(ExternalMemoryError::MissingExtension);
+        }
+
+        DeviceMemory::alloc_impl(device, memory_type, size, resource,
+                                 External::ImportWin32(handle_type, handle), None)
+    }
+
+    /// Same as `alloc`, but the memory is only allocated on the physical devices of the device
+    /// group whose bit is set in `device_mask`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `size` is 0.
+    /// - Panics if `memory_type` doesn't belong to the same physical device as `device`.
+    /// - Panics if the `khr_device_group` extension isn't enabled on the device.
+    /// - Panics if `device_mask` is 0 or contains bits that don't correspond to a physical device
+    ///   of the device.
+    ///
+    pub fn alloc_with_device_mask(device: &D, memory_type: MemoryType, size: usize,
+                                  device_mask: u32)
+                                  -> Result<DeviceMemory<D>, DeviceMemoryAllocError>
+        where D: Clone
+    {
+        assert!(device.loaded_extensions().khr_device_group);
+        assert!(device_mask != 0 && (device_mask & !device.all_devices_mask()) == 0);
+
+        unsafe {
+            DeviceMemory::alloc_impl(device, memory_type, size, DedicatedAlloc::None,
+                                     External::None, Some(device_mask))
+                .map_err(DeviceMemoryAllocError::from_external)
+        }
+    }
+
+    unsafe fn alloc_impl(device: &D, memory_type: MemoryType, size: usize,
+                         resource: DedicatedAlloc, external: External, device_mask: Option<u32>)
+                         -> Result<DeviceMemory<D>, ExternalMemoryError>
         where D: Clone
     {
         assert!(size >= 1);
@@ -71,15 +255,97 @@ impl<D> DeviceMemory<D> where D: SafeDeref<Target = Device> {
                    memory_type.physical_device().internal_object());
 
         if size > memory_type.heap().size() {
-            return Err(OomError::OutOfDeviceMemory);
+            return Err(OomError::OutOfDeviceMemory.into());
         }
 
         let vk = device.pointers();
 
-        let memory = unsafe {
+        let dedicated_infos = if device.loaded_extensions().khr_dedicated_allocation {
+            match resource {
+                DedicatedAlloc::None => None,
+                DedicatedAlloc::Buffer(buffer) => {
+                    assert_eq!(&**buffer.device() as *const Device, &**device as *const Device);
+                    Some(vk::MemoryDedicatedAllocateInfoKHR {
+                        sType: vk::STRUCTURE_TYPE_MEMORY_DEDICATED_ALLOCATE_INFO_KHR,
+                        pNext: ptr::null(),
+                        image: 0,
+                        buffer: buffer.internal_object(),
+                    })
+                },
+                DedicatedAlloc::Image(image) => {
+                    assert_eq!(&**image.device() as *const Device, &**device as *const Device);
+                    Some(vk::MemoryDedicatedAllocateInfoKHR {
+                        sType: vk::STRUCTURE_TYPE_MEMORY_DEDICATED_ALLOCATE_INFO_KHR,
+                        pNext: ptr::null(),
+                        image: image.internal_object(),
+                        buffer: 0,
+                    })
+                },
+            }
+        } else {
+            None
+        };
+
+        let dedicated_ptr = dedicated_infos.as_ref()
+                                           .map(|i| i as *const vk::MemoryDedicatedAllocateInfoKHR)
+                                           .unwrap_or(ptr::null()) as *const c_void;
+
+        // The structs of the external memory extensions are chained before the dedicated
+        // allocation struct.
+        let export_infos;
+        let import_fd_infos;
+        let import_win32_infos;
+        let (next, export_handle_types) = match external {
+            External::None => (dedicated_ptr, 0),
+            External::Export(ty) => {
+                export_infos = vk::ExportMemoryAllocateInfoKHR {
+                    sType: vk::STRUCTURE_TYPE_EXPORT_MEMORY_ALLOCATE_INFO_KHR,
+                    pNext: dedicated_ptr,
+                    handleTypes: ty.to_bits(),
+                };
+                (&export_infos as *const vk::ExportMemoryAllocateInfoKHR as *const c_void,
+                 ty.to_bits())
+            },
+            External::ImportFd(ty, fd) => {
+                import_fd_infos = vk::ImportMemoryFdInfoKHR {
+                    sType: vk::STRUCTURE_TYPE_IMPORT_MEMORY_FD_INFO_KHR,
+                    pNext: dedicated_ptr,
+                    handleType: ty.to_bits(),
+                    fd: fd,
+                };
+                (&import_fd_infos as *const vk::ImportMemoryFdInfoKHR as *const c_void, 0)
+            },
+            External::ImportWin32(ty, handle) => {
+                import_win32_infos = vk::ImportMemoryWin32HandleInfoKHR {
+                    sType: vk::STRUCTURE_TYPE_IMPORT_MEMORY_WIN32_HANDLE_INFO_KHR,
+                    pNext: dedicated_ptr,
+                    handleType: ty.to_bits(),
+                    handle: handle,
+                    name: ptr::null(),
+                };
+                (&import_win32_infos as *const vk::ImportMemoryWin32HandleInfoKHR as *const c_void,
+                 0)
+            },
+        };
+
+        let flags_infos;
+        let next = match device_mask {
+            Some(mask) => {
+                flags_infos = vk::MemoryAllocateFlagsInfoKHR {
+                    sType: vk::STRUCTURE_TYPE_MEMORY_ALLOCATE_FLAGS_INFO_KHR,
+                    pNext: next,
+                    flags: vk::MEMORY_ALLOCATE_DEVICE_MASK_BIT_KHR,
+                    deviceMask: mask,
+                };
+                &flags_infos as *const vk::MemoryAllocateFlagsInfoKHR as *const c_void
+            },
+            None => next,
+        };
+
+        let memory = {
             let infos = vk::MemoryAllocateInfo {
                 sType: vk::STRUCTURE_TYPE_MEMORY_ALLOCATE_INFO,
-                pNext: ptr::null(),
+                pNext: next as *const _,
                 allocationSize: size as u64,
                 memoryTypeIndex: memory_type.id(),
             };
@@ -90,11 +356,15 @@ impl<D> DeviceMemory<D> where D: SafeDeref<Target = Device> {
             output
         };
 
+        device.memory_stats_tracker().add(memory_type.id(), size);
+        device.resource_tracker().created(ResourceKind::DeviceMemory, memory);
+
         Ok(DeviceMemory {
             memory: memory,
             device: device.clone(),
             size: size,
             memory_type_index: memory_type.id(),
+            export_handle_types: export_handle_types,
         })
     }
 
@@ -105,14 +375,32 @@ impl<D> DeviceMemory<D> where D: SafeDeref<Target = Device> {
     /// - Panics if `memory_type` doesn't belong to the same physical device as `device`.
     /// - Panics if the memory type is not host-visible.
     ///
+    #[inline]
     pub fn alloc_and_map(device: &D, memory_type: MemoryType, size: usize)
-                         -> Result<MappedDeviceMemory<D>, OomError>
+                         -> Result<MappedDeviceMemory<D>, DeviceMemoryAllocError>
+        where D: Clone
+    {
+        DeviceMemory::dedicated_alloc_and_map(device, memory_type, size, DedicatedAlloc::None)
+    }
+
+    /// Same as `alloc_and_map`, but the memory is dedicated to a single buffer or image. See
+    /// `dedicated_alloc`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `memory_type` doesn't belong to the same physical device as `device`.
+    /// - Panics if the memory type is not host-visible.
+    /// - Panics if the resource wasn't created from `device`.
+    ///
+    pub fn dedicated_alloc_and_map(device: &D, memory_type: MemoryType, size: usize,
+                                   resource: DedicatedAlloc)
+                                   -> Result<MappedDeviceMemory<D>, DeviceMemoryAllocError>
         where D: Clone
     {
         let vk = device.pointers();
 
         assert!(memory_type.is_host_visible());
-        let mem = try!(DeviceMemory::alloc(device, memory_type, size));
+        let mem = try!(DeviceMemory::dedicated_alloc(device, memory_type, size, resource));
 
         let coherent = memory_type.is_host_coherent();
 
@@ -143,6 +431,79 @@ impl<D> DeviceMemory<D> where D: SafeDeref<Target = Device> {
         self.size
     }
 
+    /// Exports the memory as a file descriptor, in order to share it with another API or another
+    /// process.
+    ///
+    /// The memory must have been allocated with `alloc_exportable` with the same handle type.
+    /// Each call returns a new file descriptor, which you are responsible for closing.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `handle_type` is not a file descriptor handle type.
+    ///
+    pub fn export_fd(&self, handle_type: ExternalMemoryHandleType)
+                     -> Result<c_int, ExternalMemoryError>
+    {
+        assert!(handle_type.is_fd());
+
+        if (self.export_handle_types & handle_type.to_bits()) == 0 {
+            return Err(ExternalMemoryError::NotExportable);
+        }
+
+        let vk = self.device.pointers();
+
+        unsafe {
+            let infos = vk::MemoryGetFdInfoKHR {
+                sType: vk::STRUCTURE_TYPE_MEMORY_GET_FD_INFO_KHR,
+                pNext: ptr::null(),
+                memory: self.memory,
+                handleType: handle_type.to_bits(),
+            };
+
+            let mut output = mem::uninitialized();
+            try!(check_errors(vk.GetMemoryFdKHR(self.device.internal_object(), &infos,
+                                                &mut output)));
+            Ok(output)
+        }
+    }
+
+    /// Exports the memory as a Windows handle, in order to share it with another API or another
+    /// process.
+    ///
+    /// The memory must have been allocated with `alloc_exportable` with the same handle type.
+    /// Handles of type `OpaqueWin32` must be closed with `CloseHandle` when you no longer need
+    /// them.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `handle_type` is a file descriptor handle type.
+    ///
+    pub fn export_win32_handle(&self, handle_type: ExternalMemoryHandleType)
+                               -> Result<*mut c_void, ExternalMemoryError>
+    {
+        assert!(!handle_type.is_fd());
+
+        if (self.export_handle_types & handle_type.to_bits()) == 0 {
+            return Err(ExternalMemoryError::NotExportable);
+        }
+
+        let vk = self.device.pointers();
+
+        unsafe {
+            let infos = vk::MemoryGetWin32HandleInfoKHR {
+                sType: vk::STRUCTURE_TYPE_MEMORY_GET_WIN32_HANDLE_INFO_KHR,
+                pNext: ptr::null(),
+                memory: self.memory,
+                handleType: handle_type.to_bits(),
+            };
+
+            let mut output = mem::uninitialized();
+            try!(check_errors(vk.GetMemoryWin32HandleKHR(self.device.internal_object(), &infos,
+                                                         &mut output)));
+            Ok(output)
+        }
+    }
+
     /// Returns the device associated with this allocation.
     #[inline]
     pub fn device(&self) -> &Device {
@@ -166,6 +527,8 @@ impl<D> Drop for DeviceMemory<D> where D: SafeDeref<Target = Device> {
             let device = self.device();
             let vk = device.pointers();
             vk.FreeMemory(device.internal_object(), self.memory, ptr::null());
+            device.memory_stats_tracker().remove(self.memory_type_index, self.size);
+            device.resource_tracker().destroyed(ResourceKind::DeviceMemory, self.memory);
         }
     }
 }
@@ -178,6 +541,12 @@ impl<D> Drop for DeviceMemory<D> where D: SafeDeref<Target = Device> {
 /// In order to access the content of the allocated memory, you can use the `read_write` method.
 /// This method returns a guard object that derefs to the content. 
 ///
+/// The memory stays mapped for the whole lifetime of the `MappedDeviceMemory`, so accessing it
+/// doesn't cost a call to `vkMapMemory`. If the memory type is not host-coherent, `read_write`
+/// invalidates and flushes the range that is accessed. Use `read_write_manual` together with
+/// `invalidate_range` and `flush_range` if you prefer to do it yourself, for example to flush
+/// multiple small writes at once.
+///
 /// # Example
 ///
 /// ```no_run
@@ -214,6 +583,95 @@ impl<D> MappedDeviceMemory<D> where D: SafeDeref<Target = Device> {
         &self.memory
     }
 
+    /// Returns true if the memory is host-coherent, in which case flushing and invalidating
+    /// ranges is not necessary.
+    #[inline]
+    pub fn is_coherent(&self) -> bool {
+        self.coherent
+    }
+
+    /// Makes the writes that the host has performed in the given range visible to the device.
+    ///
+    /// Does nothing if the memory is host-coherent. The range is automatically extended to
+    /// respect the `non_coherent_atom_size` limit of the physical device.
+    ///
+    /// # Safety
+    ///
+    /// - Accesses are not synchronized. Synchronization must be handled outside of
+    ///   the `MappedDeviceMemory`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the range is out of the memory.
+    ///
+    #[inline]
+    pub unsafe fn flush_range(&self, range: Range<usize>) -> Result<(), OomError> {
+        if self.coherent {
+            return Ok(());
+        }
+
+        let vk = self.memory.device().pointers();
+        let range = self.atom_aligned_range(range);
+        try!(check_errors(vk.FlushMappedMemoryRanges(self.memory.device().internal_object(),
+                                                     1, &range)));
+        Ok(())
+    }
+
+    /// Makes the writes that the device has performed in the given range visible to the host.
+    ///
+    /// Does nothing if the memory is host-coherent. The range is automatically extended to
+    /// respect the `non_coherent_atom_size` limit of the physical device.
+    ///
+    /// # Safety
+    ///
+    /// - Accesses are not synchronized. Synchronization must be handled outside of
+    ///   the `MappedDeviceMemory`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the range is out of the memory.
+    ///
+    #[inline]
+    pub unsafe fn invalidate_range(&self, range: Range<usize>) -> Result<(), OomError> {
+        if self.coherent {
+            return Ok(());
+        }
+
+        let vk = self.memory.device().pointers();
+        let range = self.atom_aligned_range(range);
+        try!(check_errors(vk.InvalidateMappedMemoryRanges(self.memory.device().internal_object(),
+                                                          1, &range)));
+        Ok(())
+    }
+
+    // Builds a `VkMappedMemoryRange` that covers `range` and whose bounds are multiples of the
+    // `non_coherent_atom_size` limit, or the end of the memory.
+    fn atom_aligned_range(&self, range: Range<usize>) -> vk::MappedMemoryRange {
+        assert!(range.start <= range.end);
+        assert!(range.end <= self.memory.size);
+
+        let atom = self.memory.device().physical_device().limits().non_coherent_atom_size();
+        let atom = if atom == 0 { 1 } else { atom as usize };
+
+        let start = range.start - range.start % atom;
+        let end = if range.end % atom == 0 { range.end }
+                  else { range.end + atom - range.end % atom };
+
+        let size = if end >= self.memory.size {
+            vk::WHOLE_SIZE
+        } else {
+            (end - start) as vk::DeviceSize
+        };
+
+        vk::MappedMemoryRange {
+            sType: vk::STRUCTURE_TYPE_MAPPED_MEMORY_RANGE,
+            pNext: ptr::null(),
+            memory: self.memory.internal_object(),
+            offset: start as vk::DeviceSize,
+            size: size,
+        }
+    }
+
     /// Gives access to the content of the memory.
     ///
     /// This function takes care of calling `vkInvalidateMappedMemoryRanges` and
@@ -232,27 +690,40 @@ impl<D> MappedDeviceMemory<D> where D: SafeDeref<Target = Device> {
     pub unsafe fn read_write<T: ?Sized>(&self, range: Range<usize>) -> CpuAccess<T, D>
         where T: Content + 'static
     {
-        let vk = self.memory.device().pointers();
         let pointer = T::ref_from_ptr((self.pointer as usize + range.start) as *mut _,
                                       range.end - range.start).unwrap();       // TODO: error
 
-        if !self.coherent {
-            let range = vk::MappedMemoryRange {
-                sType: vk::STRUCTURE_TYPE_MAPPED_MEMORY_RANGE,
-                pNext: ptr::null(),
-                memory: self.memory.internal_object(),
-                offset: range.start as u64,
-                size: (range.end - range.start) as u64,
-            };
+        // TODO: check result?
+        let _ = self.invalidate_range(range.clone());
 
-            // TODO: check result?
-            vk.InvalidateMappedMemoryRanges(self.memory.device().internal_object(), 1, &range);
+        CpuAccess {
+            pointer: pointer,
+            mem: self,
+            coherent: self.coherent,
+            range: range,
         }
+    }
+
+    /// Same as `read_write`, but doesn't invalidate or flush anything.
+    ///
+    /// If the memory is not host-coherent, you must call `invalidate_range` before reading and
+    /// `flush_range` after writing.
+    ///
+    /// # Safety
+    ///
+    /// Same as `read_write`.
+    ///
+    #[inline]
+    pub unsafe fn read_write_manual<T: ?Sized>(&self, range: Range<usize>) -> CpuAccess<T, D>
+        where T: Content + 'static
+    {
+        let pointer = T::ref_from_ptr((self.pointer as usize + range.start) as *mut _,
+                                      range.end - range.start).unwrap();       // TODO: error
 
         CpuAccess {
             pointer: pointer,
             mem: self,
-            coherent: self.coherent,
+            coherent: true,
             range: range,
         }
     }
@@ -330,29 +801,96 @@ impl<'a, T: ?Sized + 'a, D: 'a> Drop for CpuAccess<'a, T, D> where D: SafeDeref<
     fn drop(&mut self) {
         // If the memory doesn't have the `coherent` flag, we need to flush the data.
         if !self.coherent {
-            let vk = self.mem.memory().device().pointers();
-
-            let range = vk::MappedMemoryRange {
-                sType: vk::STRUCTURE_TYPE_MAPPED_MEMORY_RANGE,
-                pNext: ptr::null(),
-                memory: self.mem.memory().internal_object(),
-                offset: self.range.start as u64,
-                size: (self.range.end - self.range.start) as u64,
-            };
-
             // TODO: check result?
             unsafe {
-                vk.FlushMappedMemoryRanges(self.mem.memory().device().internal_object(),
-                                           1, &range);
+                let _ = self.mem.flush_range(self.range.clone());
             }
         }
     }
 }
 
+/// Error that can happen when allocating device memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeviceMemoryAllocError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The maximum number of memory allocations of the device has been reached.
+    TooManyObjects,
+}
+
+impl DeviceMemoryAllocError {
+    // Converts the error of an allocation that doesn't import or export memory.
+    fn from_external(err: ExternalMemoryError) -> DeviceMemoryAllocError {
+        match err {
+            ExternalMemoryError::OomError(err) => DeviceMemoryAllocError::OomError(err),
+            ExternalMemoryError::TooManyObjects => DeviceMemoryAllocError::TooManyObjects,
+            err => unreachable!("unexpected error: {:?}", err),
+        }
+    }
+}
+
+impl error::Error for DeviceMemoryAllocError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            DeviceMemoryAllocError::OomError(_) => "not enough memory available",
+            DeviceMemoryAllocError::TooManyObjects => {
+                "the maximum number of memory allocations has been reached"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            DeviceMemoryAllocError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for DeviceMemoryAllocError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for DeviceMemoryAllocError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            DeviceMemoryAllocError::OomError(ref err) => err.error_code(),
+            DeviceMemoryAllocError::TooManyObjects => Some(Error::TooManyObjects),
+        }
+    }
+}
+
+impl From<OomError> for DeviceMemoryAllocError {
+    #[inline]
+    fn from(err: OomError) -> DeviceMemoryAllocError {
+        DeviceMemoryAllocError::OomError(err)
+    }
+}
+
+impl From<Error> for DeviceMemoryAllocError {
+    #[inline]
+    fn from(err: Error) -> DeviceMemoryAllocError {
+        match err {
+            Error::TooManyObjects => DeviceMemoryAllocError::TooManyObjects,
+            err => DeviceMemoryAllocError::OomError(OomError::from(err)),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use OomError;
+    use memory::DedicatedAlloc;
     use memory::DeviceMemory;
+    use memory::DeviceMemoryAllocError;
+    use memory::ExternalMemoryError;
+    use memory::ExternalMemoryHandleType;
 
     #[test]
     fn create() {
@@ -369,6 +907,50 @@ mod tests {
         let _ = DeviceMemory::alloc(&device, mem_ty, 0);
     }
 
+    #[test]
+    fn flush_invalidate_unaligned() {
+        let (device, _) = gfx_dev_and_queue!();
+        let mem_ty = device.physical_device().memory_types().filter(|m| m.is_host_visible())
+                           .next().unwrap();
+        let mem = DeviceMemory::alloc_and_map(&device, mem_ty, 1000).unwrap();
+        assert_eq!(mem.is_coherent(), mem_ty.is_host_coherent());
+
+        unsafe {
+            {
+                let mut content = mem.read_write_manual::<[u8]>(3 .. 17);
+                content[0] = 12;
+            }
+            mem.flush_range(3 .. 17).unwrap();
+            mem.invalidate_range(999 .. 1000).unwrap();
+            assert_eq!(mem.read_write::<[u8]>(3 .. 17)[0], 12);
+        }
+    }
+
+    #[test]
+    fn export_not_exportable() {
+        let (device, _) = gfx_dev_and_queue!();
+        let mem_ty = device.physical_device().memory_types().next().unwrap();
+        let mem = DeviceMemory::alloc(&device, mem_ty, 256).unwrap();
+
+        match mem.export_fd(ExternalMemoryHandleType::OpaqueFd) {
+            Err(ExternalMemoryError::NotExportable) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn exportable_missing_extension() {
+        let (device, _) = gfx_dev_and_queue!();
+        let mem_ty = device.physical_device().memory_types().next().unwrap();
+
+        match DeviceMemory::alloc_exportable(&device, mem_ty, 256, DedicatedAlloc::None,
+                                             ExternalMemoryHandleType::OpaqueFd)
+        {
+            Err(ExternalMemoryError::MissingExtension) => (),
+            _ => panic!()
+        }
+    }
+
     #[test]
     #[cfg(target_pointer_width = "64")]
     fn oom_single() {
@@ -377,7 +959,7 @@ mod tests {
                            .next().unwrap();
     
         match DeviceMemory::alloc(&device, mem_ty, 0xffffffffffffffff) {
-            Err(OomError::OutOfDeviceMemory) => (),
+            Err(DeviceMemoryAllocError::OomError(OomError::OutOfDeviceMemory)) => (),
             _ => panic!()
         }
     }
@@ -393,7 +975,9 @@ mod tests {
     
         for _ in 0 .. 4 {
             match DeviceMemory::alloc(&device, mem_ty, heap_size / 3) {
-                Err(OomError::OutOfDeviceMemory) => return,     // test succeeded
+                Err(DeviceMemoryAllocError::OomError(OomError::OutOfDeviceMemory)) => {
+                    return      // test succeeded
+                },
                 Ok(a) => allocs.push(a),
                 _ => ()
             }
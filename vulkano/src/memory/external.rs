@@ -0,0 +1,166 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use instance::DeviceExtensions;
+use Error;
+use OomError;
+use VulkanError;
+use vk;
+
+/// Type of handle that is used to share memory with other APIs or other processes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ExternalMemoryHandleType {
+    /// POSIX file descriptor that is only meaningful to Vulkan implementations and to other
+    /// APIs that use the same driver, for example CUDA or OpenGL.
+    ///
+    /// Requires the `khr_external_memory_fd` extension.
+    OpaqueFd,
+    /// Windows NT handle that is only meaningful to Vulkan implementations and to other APIs
+    /// that use the same driver.
+    ///
+    /// Requires the `khr_external_memory_win32` extension.
+    OpaqueWin32,
+    /// Windows global share handle that is only meaningful to Vulkan implementations and to
+    /// other APIs that use the same driver.
+    ///
+    /// Requires the `khr_external_memory_win32` extension.
+    OpaqueWin32Kmt,
+    /// Linux dma-buf file descriptor.
+    ///
+    /// Requires the `khr_external_memory_fd` and `ext_external_memory_dma_buf` extensions.
+    DmaBuf,
+}
+
+impl ExternalMemoryHandleType {
+    /// Returns true if the handle is a file descriptor.
+    #[inline]
+    pub fn is_fd(&self) -> bool {
+        match *self {
+            ExternalMemoryHandleType::OpaqueFd | ExternalMemoryHandleType::DmaBuf => true,
+            ExternalMemoryHandleType::OpaqueWin32 |
+            ExternalMemoryHandleType::OpaqueWin32Kmt => false,
+        }
+    }
+
+    /// Returns true if the extensions required to use this handle type are in the list.
+    #[inline]
+    pub fn is_supported_by(&self, extensions: &DeviceExtensions) -> bool {
+        if !extensions.khr_external_memory {
+            return false;
+        }
+
+        match *self {
+            ExternalMemoryHandleType::OpaqueFd => extensions.khr_external_memory_fd,
+            ExternalMemoryHandleType::OpaqueWin32 |
+            ExternalMemoryHandleType::OpaqueWin32Kmt => extensions.khr_external_memory_win32,
+            ExternalMemoryHandleType::DmaBuf => {
+                extensions.khr_external_memory_fd && extensions.ext_external_memory_dma_buf
+            },
+        }
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn to_bits(&self) -> vk::ExternalMemoryHandleTypeFlagBitsKHR {
+        match *self {
+            ExternalMemoryHandleType::OpaqueFd => vk::EXTERNAL_MEMORY_HANDLE_TYPE_OPAQUE_FD_BIT_KHR,
+            ExternalMemoryHandleType::OpaqueWin32 => {
+                vk::EXTERNAL_MEMORY_HANDLE_TYPE_OPAQUE_WIN32_BIT_KHR
+            },
+            ExternalMemoryHandleType::OpaqueWin32Kmt => {
+                vk::EXTERNAL_MEMORY_HANDLE_TYPE_OPAQUE_WIN32_KMT_BIT_KHR
+            },
+            ExternalMemoryHandleType::DmaBuf => vk::EXTERNAL_MEMORY_HANDLE_TYPE_DMA_BUF_BIT_EXT,
+        }
+    }
+}
+
+/// Error that can happen when importing or exporting external memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExternalMemoryError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The extensions required by the handle type are not enabled on the device.
+    MissingExtension,
+    /// The memory wasn't allocated with `alloc_exportable` with this handle type.
+    NotExportable,
+    /// The handle is invalid or isn't compatible with the memory type or the size.
+    InvalidExternalHandle,
+    /// Too many allocations or handles of this type have been created.
+    TooManyObjects,
+}
+
+impl error::Error for ExternalMemoryError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            ExternalMemoryError::OomError(_) => "not enough memory available",
+            ExternalMemoryError::MissingExtension => {
+                "the extensions required by the handle type are not enabled"
+            },
+            ExternalMemoryError::NotExportable => {
+                "the memory can't be exported with this handle type"
+            },
+            ExternalMemoryError::InvalidExternalHandle => "the external handle is invalid",
+            ExternalMemoryError::TooManyObjects => {
+                "too many allocations or handles of this type have been created"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ExternalMemoryError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for ExternalMemoryError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for ExternalMemoryError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            ExternalMemoryError::OomError(ref err) => err.error_code(),
+            ExternalMemoryError::InvalidExternalHandle => Some(Error::InvalidExternalHandle),
+            ExternalMemoryError::TooManyObjects => Some(Error::TooManyObjects),
+            _ => None
+        }
+    }
+}
+
+impl From<OomError> for ExternalMemoryError {
+    #[inline]
+    fn from(err: OomError) -> ExternalMemoryError {
+        ExternalMemoryError::OomError(err)
+    }
+}
+
+impl From<Error> for ExternalMemoryError {
+    #[inline]
+    fn from(err: Error) -> ExternalMemoryError {
+        match err {
+            err @ Error::OutOfHostMemory => ExternalMemoryError::OomError(OomError::from(err)),
+            err @ Error::OutOfDeviceMemory => ExternalMemoryError::OomError(OomError::from(err)),
+            Error::InvalidExternalHandle => ExternalMemoryError::InvalidExternalHandle,
+            Error::TooManyObjects => ExternalMemoryError::TooManyObjects,
+            _ => panic!("unexpected error: {:?}", err)
+        }
+    }
+}
@@ -58,6 +58,11 @@
 //! Mobile machines usually have a single memory heap that is "equally local" to both the CPU and
 //! the GPU. It is both host-accessible and device-local.
 //!
+//! Instead of enumerating the memory types yourself, you can describe what the memory is going to
+//! be used for with the `MemoryUsage` enum and call `MemoryUsage::choose`. It walks the memory
+//! types from the most appropriate to the least appropriate for this usage on the current
+//! hardware.
+//!
 //! # Allocating memory and memory pools
 //!
 //! Allocating memory can be done by calling `DeviceMemory::alloc()`.
@@ -84,20 +89,52 @@
 //! trait on your own structure and then use it when you create buffers and images so that they
 //! get memory from that pool. By default if you don't specify any pool when creating a buffer or
 //! an image, an instance of `StdMemoryPool` that is shared by the `Device` object is used.
+//! Constructors that take a custom pool end with `with_pool`, for example
+//! `DeviceLocalBuffer::raw_with_pool` or `ImmutableImage::with_pool`. Their `_and_hint` variants
+//! additionally take a `DedicatedAllocHint` that forces or forbids a dedicated allocation.
+//!
+//! Long-running applications that create and destroy a lot of buffers can fragment the blocks of
+//! memory of the `StdMemoryPool`. `StdMemoryPool::defragment_buffers` moves buffers out of blocks
+//! that are mostly empty, after which `StdMemoryPool::release_empty_blocks` frees these blocks.
+//!
+//! Transient resources that are never used at the same time, like the intermediate targets of a
+//! frame, can share the same memory with `AliasedMemory`.
+//!
+//! # Memory statistics
+//!
+//! `Device::memory_stats()` returns the number of bytes and the number of allocations that are
+//! currently alive for each memory type and each heap of the device. If the `ext_memory_budget`
+//! extension is enabled, it also returns the budget of each heap as estimated by the driver.
 
 use std::mem;
 use std::os::raw::c_void;
 use std::slice;
 
+use instance::MemoryType;
+use instance::PhysicalDevice;
 use vk;
 
+pub use self::aliased::AliasedMemory;
+pub use self::aliased::AliasedMemoryError;
 pub use self::device_memory::CpuAccess;
+pub use self::device_memory::DedicatedAlloc;
 pub use self::device_memory::DeviceMemory;
+pub use self::device_memory::DeviceMemoryAllocError;
 pub use self::device_memory::MappedDeviceMemory;
+pub use self::external::ExternalMemoryError;
+pub use self::external::ExternalMemoryHandleType;
 pub use self::pool::MemoryPool;
+pub use self::stats::MemoryHeapStats;
+pub use self::stats::MemoryStats;
+pub use self::stats::MemoryTypeStats;
+#[doc(hidden)]
+pub use self::stats::MemoryStatsTracker;
 
+mod aliased;
 mod device_memory;
+mod external;
 pub mod pool;
+mod stats;
 
 /// Represents requirements expressed by the Vulkan implementation.
 #[derive(Debug, Copy, Clone)]
@@ -112,6 +149,17 @@ pub struct MemoryRequirements {
     /// Indicates which memory types can be used. Each bit that is set to 1 means that the memory
     /// type whose index is the same as the position of the bit can be used.
     pub memory_type_bits: u32,
+
+    /// True if the implementation would prefer the resource to be bound to a dedicated
+    /// allocation, for performance reasons.
+    ///
+    /// Always false if the `khr_dedicated_allocation` extension isn't enabled.
+    pub prefer_dedicated: bool,
+
+    /// True if the resource must be bound to a dedicated allocation.
+    ///
+    /// Always false if the `khr_dedicated_allocation` extension isn't enabled.
+    pub require_dedicated: bool,
 }
 
 #[doc(hidden)]
@@ -122,10 +170,129 @@ impl From<vk::MemoryRequirements> for MemoryRequirements {
             size: reqs.size as usize,
             alignment: reqs.alignment as usize,
             memory_type_bits: reqs.memoryTypeBits,
+            prefer_dedicated: false,
+            require_dedicated: false,
+        }
+    }
+}
+
+/// Whether a buffer or an image should be bound to a memory allocation of its own.
+///
+/// Dedicated allocations require the `khr_dedicated_allocation` extension to be enabled on the
+/// device. Without it, regular allocations are always used.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DedicatedAllocHint {
+    /// Use a dedicated allocation if the implementation prefers it.
+    Auto,
+    /// Always use a dedicated allocation.
+    Always,
+    /// Don't use a dedicated allocation, unless the implementation requires it.
+    Never,
+}
+
+impl DedicatedAllocHint {
+    /// Returns true if a resource with the given requirements should be bound to a dedicated
+    /// allocation.
+    #[inline]
+    pub fn use_dedicated(&self, requirements: &MemoryRequirements) -> bool {
+        match *self {
+            DedicatedAllocHint::Auto => {
+                requirements.prefer_dedicated || requirements.require_dedicated
+            },
+            DedicatedAllocHint::Always => true,
+            DedicatedAllocHint::Never => requirements.require_dedicated,
         }
     }
 }
 
+impl Default for DedicatedAllocHint {
+    #[inline]
+    fn default() -> DedicatedAllocHint {
+        DedicatedAllocHint::Auto
+    }
+}
+
+/// Describes what a memory allocation is going to be used for, in order to choose an appropriate
+/// memory type.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MemoryUsage {
+    /// The memory is only accessed by the device. This is the fastest memory for the GPU.
+    DeviceLocal,
+    /// The memory is written by the host very often and read by the device, for example for
+    /// uniform buffers that are updated every frame.
+    ///
+    /// Device-local memory types that are also host-visible are preferred, which is the case of
+    /// all the memory on unified memory architectures.
+    Upload,
+    /// The memory is written by the device and read back by the host. Host-cached memory types
+    /// are preferred.
+    Readback,
+    /// The memory is written once by the host and then copied to a device-local resource by a
+    /// transfer command. Memory types that aren't device-local are preferred, so that the rarer
+    /// device-local and host-visible memory isn't wasted.
+    Staging,
+    /// The memory is used by a transient attachment. Lazily-allocated memory types are
+    /// preferred, and device-local memory types are used as a fallback.
+    Transient,
+}
+
+impl MemoryUsage {
+    /// Returns true if the memory type must be host-visible for this usage.
+    #[inline]
+    pub fn requires_host_visible(&self) -> bool {
+        match *self {
+            MemoryUsage::DeviceLocal | MemoryUsage::Transient => false,
+            MemoryUsage::Upload | MemoryUsage::Readback | MemoryUsage::Staging => true,
+        }
+    }
+
+    /// Chooses the most appropriate memory type for this usage among the memory types whose
+    /// bit is set in `memory_type_bits`.
+    ///
+    /// `memory_type_bits` usually comes from the `MemoryRequirements` of a buffer or an image.
+    /// Returns `None` if no memory type is allowed, or if the usage requires host-visible memory
+    /// and none of the allowed memory types is host-visible.
+    pub fn choose<'a>(&self, physical_device: PhysicalDevice<'a>, memory_type_bits: u32)
+                      -> Option<MemoryType<'a>>
+    {
+        fn device_local_only(t: &MemoryType) -> bool { t.is_device_local() && !t.is_host_visible() }
+        fn device_local(t: &MemoryType) -> bool { t.is_device_local() }
+        fn lazily_allocated(t: &MemoryType) -> bool { t.is_lazily_allocated() }
+        fn host_visible_device_local(t: &MemoryType) -> bool {
+            t.is_host_visible() && t.is_device_local()
+        }
+        fn host_coherent(t: &MemoryType) -> bool { t.is_host_visible() && t.is_host_coherent() }
+        fn host_cached(t: &MemoryType) -> bool { t.is_host_visible() && t.is_host_cached() }
+        fn host_only_coherent(t: &MemoryType) -> bool {
+            t.is_host_visible() && t.is_host_coherent() && !t.is_device_local()
+        }
+        fn host_visible(t: &MemoryType) -> bool { t.is_host_visible() }
+        fn any(_: &MemoryType) -> bool { true }
+
+        // Each list goes from the most appropriate to the least appropriate criteria. Within
+        // a criteria, memory types are ordered by the implementation's preference.
+        let criterias: &[fn(&MemoryType) -> bool] = match *self {
+            MemoryUsage::DeviceLocal => &[device_local_only, device_local, any],
+            MemoryUsage::Upload => &[host_visible_device_local, host_coherent, host_visible],
+            MemoryUsage::Readback => &[host_cached, host_coherent, host_visible],
+            MemoryUsage::Staging => &[host_only_coherent, host_coherent, host_visible],
+            MemoryUsage::Transient => &[lazily_allocated, device_local, any],
+        };
+
+        for criteria in criterias {
+            let found = physical_device.memory_types()
+                                       .filter(|t| (memory_type_bits & (1 << t.id())) != 0)
+                                       .filter(|t| criteria(t))
+                                       .next();
+            if found.is_some() {
+                return found;
+            }
+        }
+
+        None
+    }
+}
+
 /// Trait for types of data that can be mapped.
 // TODO: move to `buffer` module
 pub unsafe trait Content {
@@ -190,3 +357,30 @@ impl<T> !Content for Box<T> {}
 impl<T> !Content for UnsafeCell<T> {}
 
 */
+
+#[cfg(test)]
+mod tests {
+    use memory::MemoryUsage;
+
+    #[test]
+    fn choose_memory_type() {
+        let (device, _) = gfx_dev_and_queue!();
+        let physical = device.physical_device();
+
+        let usages = [MemoryUsage::DeviceLocal, MemoryUsage::Upload, MemoryUsage::Readback,
+                      MemoryUsage::Staging, MemoryUsage::Transient];
+
+        for usage in usages.iter() {
+            let ty = usage.choose(physical, 0xffffffff).unwrap();
+            if usage.requires_host_visible() {
+                assert!(ty.is_host_visible());
+            }
+
+            // Only allowing a single memory type.
+            let only = usage.choose(physical, 1 << ty.id()).unwrap();
+            assert_eq!(only.id(), ty.id());
+        }
+
+        assert!(MemoryUsage::DeviceLocal.choose(physical, 0).is_none());
+    }
+}
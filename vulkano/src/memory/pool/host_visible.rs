@@ -7,7 +7,6 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
-use std::cmp;
 use std::ops::Range;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -16,8 +15,12 @@ use device::Device;
 use instance::Instance;
 use instance::MemoryType;
 use memory::DeviceMemory;
+use memory::DeviceMemoryAllocError;
+use memory::pool::DEDICATED_ALLOC_THRESHOLD;
+use memory::pool::MIN_BLOCK_SIZE;
+use memory::pool::block_usage;
+use memory::pool::find_free_range;
 use memory::MappedDeviceMemory;
-use OomError;
 
 /// Memory pool that operates on a given memory type.
 #[derive(Debug)]
@@ -58,54 +61,42 @@ impl StdHostVisibleMemoryTypePool {
     /// - Panics if `alignment` is 0.
     ///
     pub fn alloc(me: &Arc<Self>, size: usize, alignment: usize)
-                 -> Result<StdHostVisibleMemoryTypePoolAlloc, OomError>
+                 -> Result<StdHostVisibleMemoryTypePoolAlloc, DeviceMemoryAllocError>
     {
         assert!(size != 0);
         assert!(alignment != 0);
 
-        #[inline] fn align(val: usize, al: usize) -> usize { al * (1 + (val - 1) / al) }
+        // Large allocations get their own block of memory, which is freed when the allocation
+        // is destroyed.
+        if size > DEDICATED_ALLOC_THRESHOLD {
+            let block = try!(DeviceMemory::alloc_and_map(&me.device, me.memory_type(), size));
+            return Ok(StdHostVisibleMemoryTypePoolAlloc {
+                pool: me.clone(),
+                memory: Arc::new(block),
+                offset: 0,
+                size: size,
+            });
+        }
 
-        // Find a location.
         let mut occupied = me.occupied.lock().unwrap();
 
-        // Try finding an entry in already-allocated chunks.
+        // Try finding some free space in already-allocated blocks.
         for &mut (ref dev_mem, ref mut entries) in occupied.iter_mut() {
-            // Try find some free space in-between two entries.
-            for i in 0 .. entries.len().saturating_sub(1) {
-                let entry1 = entries[i].clone();
-                let entry1_end = align(entry1.end, alignment);
-                let entry2 = entries[i + 1].clone();
-                if entry1_end + size <= entry2.start {
-                    entries.insert(i + 1, entry1_end .. entry1_end + size);
-                    return Ok(StdHostVisibleMemoryTypePoolAlloc {
-                        pool: me.clone(),
-                        memory: dev_mem.clone(),
-                        offset: entry1_end,
-                        size: size,
-                    });
-                }
-            }
-
-            // Try append at the end.
-            let last_end = entries.last().map(|e| align(e.end, alignment)).unwrap_or(0);
-            if last_end + size <= dev_mem.memory().size() {
-                entries.push(last_end .. last_end + size);
+            let block_size = dev_mem.memory().size();
+            if let Some(offset) = find_free_range(entries, block_size, size, alignment) {
                 return Ok(StdHostVisibleMemoryTypePoolAlloc {
                     pool: me.clone(),
                     memory: dev_mem.clone(),
-                    offset: last_end,
+                    offset: offset,
                     size: size,
                 });
             }
         }
 
         // We need to allocate a new block.
-        let new_block = {
-            const MIN_BLOCK_SIZE: usize = 8 * 1024 * 1024;      // 8 MB
-            let to_alloc = cmp::max(MIN_BLOCK_SIZE, size.next_power_of_two());
-            let new_block = try!(DeviceMemory::alloc_and_map(&me.device, me.memory_type(), to_alloc));
-            Arc::new(new_block)
-        };
+        let new_block = try!(DeviceMemory::alloc_and_map(&me.device, me.memory_type(),
+                                                         MIN_BLOCK_SIZE));
+        let new_block = Arc::new(new_block);
 
         occupied.push((new_block.clone(), vec![0 .. size]));
         Ok(StdHostVisibleMemoryTypePoolAlloc {
@@ -116,6 +107,51 @@ impl StdHostVisibleMemoryTypePool {
         })
     }
 
+    /// Allocates memory for an allocation that is being moved by a defragmentation pass.
+    ///
+    /// Only the blocks that are more occupied than the block of `source` are considered, and no
+    /// new block is allocated. Returns `None` if there is no room in these blocks.
+    pub fn alloc_for_defrag(me: &Arc<Self>, size: usize, alignment: usize,
+                            source: &StdHostVisibleMemoryTypePoolAlloc)
+                            -> Option<StdHostVisibleMemoryTypePoolAlloc>
+    {
+        let mut occupied = me.occupied.lock().unwrap();
+
+        let source_usage = {
+            let source_block = occupied.iter()
+                                       .find(|e| &*e.0 as *const MappedDeviceMemory == &*source.memory);
+            match source_block {
+                Some(&(_, ref entries)) => block_usage(entries),
+                None => return None,
+            }
+        };
+
+        for &mut (ref dev_mem, ref mut entries) in occupied.iter_mut() {
+            if block_usage(entries) <= source_usage {
+                continue;
+            }
+
+            let block_size = dev_mem.memory().size();
+            if let Some(offset) = find_free_range(entries, block_size, size, alignment) {
+                return Some(StdHostVisibleMemoryTypePoolAlloc {
+                    pool: me.clone(),
+                    memory: dev_mem.clone(),
+                    offset: offset,
+                    size: size,
+                });
+            }
+        }
+
+        None
+    }
+
+    /// Frees the blocks of memory that no longer contain any allocation.
+    #[inline]
+    pub fn release_empty_blocks(&self) {
+        let mut occupied = self.occupied.lock().unwrap();
+        occupied.retain(|&(_, ref entries)| !entries.is_empty());
+    }
+
     /// Returns the device this pool operates on.
     #[inline]
     pub fn device(&self) -> &Arc<Device> {
@@ -138,6 +174,12 @@ pub struct StdHostVisibleMemoryTypePoolAlloc {
 }
 
 impl StdHostVisibleMemoryTypePoolAlloc {
+    /// Returns the pool this allocation belongs to.
+    #[inline]
+    pub fn pool(&self) -> &Arc<StdHostVisibleMemoryTypePool> {
+        &self.pool
+    }
+
     #[inline]
     pub fn memory(&self) -> &MappedDeviceMemory {
         &self.memory
@@ -152,14 +194,39 @@ impl StdHostVisibleMemoryTypePoolAlloc {
     pub fn size(&self) -> usize {
         self.size
     }
+
+    /// Returns true if moving this allocation to another block could allow freeing its block.
+    ///
+    /// This is the case if the block is less than half full and if the pool has other blocks.
+    /// Allocations that have a block of their own are never movable.
+    pub fn is_movable(&self) -> bool {
+        let occupied = self.pool.occupied.lock().unwrap();
+
+        if occupied.len() < 2 {
+            return false;
+        }
+
+        match occupied.iter().find(|e| &*e.0 as *const MappedDeviceMemory == &*self.memory) {
+            Some(&(ref dev_mem, ref entries)) => {
+                let block_size = dev_mem.memory().size();
+                block_usage(entries) * 2 < block_size
+            },
+            None => false,
+        }
+    }
 }
 
 impl Drop for StdHostVisibleMemoryTypePoolAlloc {
     fn drop(&mut self) {
         let mut occupied = self.pool.occupied.lock().unwrap();
 
-        let entries = occupied.iter_mut()
-                              .find(|e| &*e.0 as *const MappedDeviceMemory == &*self.memory).unwrap();
+        // Dedicated allocations aren't tracked by the pool.
+        let entries = match occupied.iter_mut()
+                                    .find(|e| &*e.0 as *const MappedDeviceMemory == &*self.memory)
+        {
+            Some(e) => e,
+            None => return,
+        };
 
         entries.1.retain(|e| e.start != self.offset);
     }
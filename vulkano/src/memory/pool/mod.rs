@@ -7,11 +7,22 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::error;
+use std::fmt;
+use std::ops::Range;
+
 use instance::MemoryType;
+use memory::DedicatedAlloc;
+use memory::DedicatedAllocHint;
 use memory::DeviceMemory;
+use memory::DeviceMemoryAllocError;
 use memory::MappedDeviceMemory;
+use memory::MemoryRequirements;
+use Error;
 use OomError;
+use VulkanError;
 
+pub use self::pool::MovedBuffer;
 pub use self::pool::StdMemoryPool;
 pub use self::pool::StdMemoryPoolAlloc;
 pub use self::host_visible::StdHostVisibleMemoryTypePool;
@@ -45,7 +56,139 @@ pub unsafe trait MemoryPool: 'static + Send + Sync {
     /// - Panics if `alignment` is 0.
     ///
     fn alloc(&self, ty: MemoryType, size: usize, alignment: usize, layout: AllocLayout)
-             -> Result<Self::Alloc, OomError>;
+             -> Result<Self::Alloc, DeviceMemoryAllocError>;
+
+    /// Same as `alloc`, but allocates memory that is dedicated to the given buffer or image.
+    ///
+    /// The default implementation calls `alloc` if `dedicated` is `DedicatedAlloc::None`, and
+    /// returns `DedicatedAllocError::Unsupported` otherwise. Pools that want to take advantage
+    /// of the `khr_dedicated_allocation` extension should override this method.
+    ///
+    /// # Safety
+    ///
+    /// Same as `alloc`. In addition, if the allocation is dedicated, the returned object must
+    /// start at offset 0 of its memory and nothing else must be bound to this memory.
+    ///
+    /// # Panic
+    ///
+    /// Same as `alloc`.
+    ///
+    #[inline]
+    fn alloc_dedicated(&self, ty: MemoryType, size: usize, alignment: usize,
+                       layout: AllocLayout, dedicated: DedicatedAlloc)
+                       -> Result<Self::Alloc, DedicatedAllocError>
+    {
+        match dedicated {
+            DedicatedAlloc::None => Ok(try!(self.alloc(ty, size, alignment, layout))),
+            _ => Err(DedicatedAllocError::Unsupported),
+        }
+    }
+}
+
+/// Allocates from `pool` the memory of a buffer or an image whose requirements are
+/// `requirements`, using a dedicated allocation if `hint` asks for it.
+///
+/// If the pool doesn't support dedicated allocations, a regular allocation is performed instead,
+/// unless the resource requires a dedicated allocation or `hint` is `DedicatedAllocHint::Always`.
+///
+/// # Safety
+///
+/// Same as `MemoryPool::alloc_dedicated`.
+///
+/// # Panic
+///
+/// Same as `MemoryPool::alloc_dedicated`.
+///
+pub fn alloc_for_resource<P>(pool: &P, ty: MemoryType, requirements: &MemoryRequirements,
+                             layout: AllocLayout, hint: DedicatedAllocHint,
+                             resource: DedicatedAlloc)
+                             -> Result<P::Alloc, DedicatedAllocError>
+    where P: MemoryPool
+{
+    let size = requirements.size;
+    let alignment = requirements.alignment;
+
+    if !hint.use_dedicated(requirements) {
+        return Ok(try!(pool.alloc(ty, size, alignment, layout)));
+    }
+
+    match pool.alloc_dedicated(ty, size, alignment, layout, resource) {
+        Err(DedicatedAllocError::Unsupported) if !requirements.require_dedicated &&
+                                                 hint != DedicatedAllocHint::Always =>
+        {
+            Ok(try!(pool.alloc(ty, size, alignment, layout)))
+        },
+        result => result,
+    }
+}
+
+/// Error that can happen when allocating memory dedicated to a buffer or an image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DedicatedAllocError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The maximum number of memory allocations of the device has been reached.
+    TooManyObjects,
+    /// The memory pool doesn't support dedicated allocations.
+    Unsupported,
+}
+
+impl error::Error for DedicatedAllocError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            DedicatedAllocError::OomError(_) => "not enough memory available",
+            DedicatedAllocError::TooManyObjects => {
+                "the maximum number of memory allocations has been reached"
+            },
+            DedicatedAllocError::Unsupported => {
+                "the memory pool doesn't support dedicated allocations"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            DedicatedAllocError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for DedicatedAllocError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for DedicatedAllocError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            DedicatedAllocError::OomError(ref err) => err.error_code(),
+            DedicatedAllocError::TooManyObjects => Some(Error::TooManyObjects),
+            _ => None
+        }
+    }
+}
+
+impl From<OomError> for DedicatedAllocError {
+    #[inline]
+    fn from(err: OomError) -> DedicatedAllocError {
+        DedicatedAllocError::OomError(err)
+    }
+}
+
+impl From<DeviceMemoryAllocError> for DedicatedAllocError {
+    #[inline]
+    fn from(err: DeviceMemoryAllocError) -> DedicatedAllocError {
+        match err {
+            DeviceMemoryAllocError::OomError(err) => DedicatedAllocError::OomError(err),
+            DeviceMemoryAllocError::TooManyObjects => DedicatedAllocError::TooManyObjects,
+        }
+    }
 }
 
 /// Object that represents a single allocation. Its destructor should free the chunk.
@@ -71,3 +214,206 @@ pub enum AllocLayout {
     /// The object has an optimal layout.
     Optimal,
 }
+
+// Minimum size of the blocks of memory that the standard pools allocate and divide between
+// multiple buffers and images.
+const MIN_BLOCK_SIZE: usize = 8 * 1024 * 1024;      // 8 MB
+
+// Allocations larger than this get a block of memory of their own instead of being carved out of
+// a shared block, in order to not waste the rest of a large block.
+const DEDICATED_ALLOC_THRESHOLD: usize = MIN_BLOCK_SIZE / 2;
+
+// Tries to find room for an allocation of `size` bytes aligned to `alignment` inside a block of
+// `block_size` bytes whose occupied ranges are `entries`. The ranges must be sorted by their start.
+//
+// On success, inserts the new range in `entries` and returns its offset.
+fn find_free_range(entries: &mut Vec<Range<usize>>, block_size: usize, size: usize,
+                   alignment: usize) -> Option<usize>
+{
+    #[inline] fn align(val: usize, al: usize) -> usize { al * ((val + al - 1) / al) }
+
+    let mut start = 0;
+
+    for i in 0 .. entries.len() {
+        if start + size <= entries[i].start {
+            entries.insert(i, start .. start + size);
+            return Some(start);
+        }
+
+        start = align(entries[i].end, alignment);
+    }
+
+    if start + size <= block_size {
+        entries.push(start .. start + size);
+        return Some(start);
+    }
+
+    None
+}
+
+// Returns the number of bytes that are occupied in a block whose occupied ranges are `entries`.
+#[inline]
+fn block_usage(entries: &[Range<usize>]) -> usize {
+    entries.iter().map(|r| r.end - r.start).fold(0, |a, b| a + b)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter::Empty;
+    use std::sync::Arc;
+
+    use buffer::sys::SparseLevel;
+    use buffer::sys::UnsafeBuffer;
+    use buffer::sys::Usage as BufferUsage;
+    use command_buffer::sys::Flags;
+    use command_buffer::sys::Kind;
+    use command_buffer::sys::UnsafeCommandBufferBuilder;
+    use device::Device;
+    use framebuffer::EmptySinglePassRenderPass;
+    use framebuffer::Framebuffer as OldFramebuffer;
+    use instance::MemoryType;
+    use memory::DedicatedAlloc;
+    use memory::DedicatedAllocHint;
+    use memory::MemoryUsage;
+    use memory::pool::StdMemoryPool;
+    use memory::pool::StdMemoryPoolAlloc;
+    use sync::Sharing;
+    use memory::DeviceMemory;
+    use memory::DeviceMemoryAllocError;
+    use memory::pool::AllocLayout;
+    use memory::pool::DedicatedAllocError;
+    use memory::pool::MemoryPool;
+    use memory::pool::MemoryPoolAlloc;
+    use memory::pool::alloc_for_resource;
+    use super::DEDICATED_ALLOC_THRESHOLD;
+    use super::find_free_range;
+    use OomError;
+
+    #[test]
+    fn free_range_empty() {
+        let mut entries = Vec::new();
+        assert_eq!(find_free_range(&mut entries, 1024, 128, 16), Some(0));
+        assert_eq!(entries, vec![0 .. 128]);
+    }
+
+    #[test]
+    fn free_range_alignment() {
+        let mut entries = vec![0 .. 10];
+        assert_eq!(find_free_range(&mut entries, 1024, 16, 64), Some(64));
+        assert_eq!(entries, vec![0 .. 10, 64 .. 80]);
+    }
+
+    #[test]
+    fn free_range_reuses_holes() {
+        let mut entries = vec![128 .. 256, 512 .. 1024];
+        assert_eq!(find_free_range(&mut entries, 1024, 128, 16), Some(0));
+        assert_eq!(find_free_range(&mut entries, 1024, 256, 16), Some(256));
+        assert_eq!(entries, vec![0 .. 128, 128 .. 256, 256 .. 512, 512 .. 1024]);
+    }
+
+    #[test]
+    fn free_range_full() {
+        let mut entries = vec![0 .. 1000];
+        assert_eq!(find_free_range(&mut entries, 1024, 128, 16), None);
+        assert_eq!(entries, vec![0 .. 1000]);
+    }
+
+    #[test]
+    fn defragment_buffer() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let pool = StdMemoryPool::new(&device);
+
+        let usage = BufferUsage { transfer_source: true, .. BufferUsage::none() };
+        let (buffer, mem_reqs) = unsafe {
+            UnsafeBuffer::new(&device, 2 * 1024 * 1024, &usage, Sharing::Exclusive::<Empty<u32>>,
+                              SparseLevel::none()).unwrap()
+        };
+        let mem_ty = MemoryUsage::DeviceLocal.choose(device.physical_device(),
+                                                     mem_reqs.memory_type_bits).unwrap();
+
+        // Fills a first block, then puts the buffer in a second block.
+        let a1 = pool.alloc(mem_ty, 2560 * 1024, 16, AllocLayout::Linear).unwrap();
+        let a2 = pool.alloc(mem_ty, 3072 * 1024, 16, AllocLayout::Linear).unwrap();
+        let _a3 = pool.alloc(mem_ty, 2560 * 1024, 16, AllocLayout::Linear).unwrap();
+        let alloc = pool.alloc(mem_ty, mem_reqs.size, mem_reqs.alignment,
+                               AllocLayout::Linear).unwrap();
+        assert!(alloc.memory() as *const DeviceMemory != a1.memory() as *const DeviceMemory);
+        unsafe { buffer.bind_memory(alloc.memory(), alloc.offset()).unwrap(); }
+
+        // The second block is mostly empty, and freeing space in the first block gives room for
+        // the buffer.
+        assert!(alloc.is_movable());
+        drop(a2);
+
+        let cb_pool = Device::standard_command_pool(&device, queue.family());
+        let kind = Kind::Primary::<EmptySinglePassRenderPass,
+                                   OldFramebuffer<EmptySinglePassRenderPass>>;
+        let mut cb = UnsafeCommandBufferBuilder::new(cb_pool, kind, Flags::OneTimeSubmit).unwrap();
+        let moved = unsafe { pool.defragment_buffers(&mut cb, Some((&buffer, &alloc))).unwrap() };
+
+        assert_eq!(moved.len(), 1);
+        assert_eq!(moved[0].index, 0);
+        assert_eq!(moved[0].memory.memory() as *const DeviceMemory,
+                   a1.memory() as *const DeviceMemory);
+        assert!(moved[0].buffer.usage_transfer_dest());
+    }
+
+    #[test]
+    fn small_allocs_share_memory() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pool = Device::standard_pool(&device);
+        let mem_ty = device.physical_device().memory_types().filter(|m| !m.is_lazily_allocated())
+                           .next().unwrap();
+
+        let a = pool.alloc(mem_ty, 256, 16, AllocLayout::Linear).unwrap();
+        let b = pool.alloc(mem_ty, 256, 16, AllocLayout::Linear).unwrap();
+        assert_eq!(a.memory() as *const DeviceMemory, b.memory() as *const DeviceMemory);
+        assert!(a.offset() + 256 <= b.offset() || b.offset() + 256 <= a.offset());
+
+        let big = pool.alloc(mem_ty, DEDICATED_ALLOC_THRESHOLD + 1, 16,
+                             AllocLayout::Linear).unwrap();
+        assert_eq!(big.memory().size(), DEDICATED_ALLOC_THRESHOLD + 1);
+        assert_eq!(big.offset(), 0);
+    }
+
+    // Memory pool that forwards to the standard pool, but doesn't support dedicated allocations.
+    struct NoDedicatedPool(Arc<StdMemoryPool>);
+
+    unsafe impl MemoryPool for NoDedicatedPool {
+        type Alloc = StdMemoryPoolAlloc;
+
+        fn alloc(&self, ty: MemoryType, size: usize, alignment: usize, layout: AllocLayout)
+                 -> Result<StdMemoryPoolAlloc, DeviceMemoryAllocError>
+        {
+            self.0.alloc(ty, size, alignment, layout)
+        }
+    }
+
+    #[test]
+    fn dedicated_unsupported() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pool = NoDedicatedPool(StdMemoryPool::new(&device));
+
+        let usage = BufferUsage { transfer_source: true, .. BufferUsage::none() };
+        let (buffer, mem_reqs) = unsafe {
+            UnsafeBuffer::new(&device, 256, &usage, Sharing::Exclusive::<Empty<u32>>,
+                              SparseLevel::none()).unwrap()
+        };
+        let mem_ty = MemoryUsage::DeviceLocal.choose(device.physical_device(),
+                                                     mem_reqs.memory_type_bits).unwrap();
+        let dedicated = DedicatedAlloc::Buffer(&buffer);
+
+        match alloc_for_resource(&pool, mem_ty, &mem_reqs, AllocLayout::Linear,
+                                 DedicatedAllocHint::Always, dedicated)
+        {
+            Err(DedicatedAllocError::Unsupported) => (),
+            _ => panic!()
+        }
+
+        // Falls back to a regular allocation when the dedicated allocation is only preferred.
+        if !mem_reqs.require_dedicated {
+            assert!(alloc_for_resource(&pool, mem_ty, &mem_reqs, AllocLayout::Linear,
+                                       DedicatedAllocHint::Auto, dedicated).is_ok());
+        }
+    }
+}
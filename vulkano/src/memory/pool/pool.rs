@@ -14,19 +14,37 @@ use std::sync::Arc;
 use std::sync::Mutex;
 use fnv::FnvHasher;
 
+use buffer::sys::BufferCreationError;
+use buffer::sys::SparseLevel;
+use buffer::sys::UnsafeBuffer;
+use buffer::sys::Usage as BufferUsage;
+use command_buffer::pool::CommandPool;
+use command_buffer::sys::BufferCopyRegion;
+use command_buffer::sys::UnsafeCommandBufferBuilder;
 use device::Device;
 use instance::MemoryType;
 use memory::pool::AllocLayout;
+use memory::pool::DedicatedAllocError;
 use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
 use memory::pool::StdHostVisibleMemoryTypePool;
 use memory::pool::StdHostVisibleMemoryTypePoolAlloc;
 use memory::pool::StdNonHostVisibleMemoryTypePool;
 use memory::pool::StdNonHostVisibleMemoryTypePoolAlloc;
+use memory::DedicatedAlloc;
 use memory::DeviceMemory;
+use memory::DeviceMemoryAllocError;
 use memory::MappedDeviceMemory;
+use sync::Sharing;
 use OomError;
 
+/// Standard implementation of `MemoryPool`, used by default when creating buffers and images.
+///
+/// Small allocations are carved out of large blocks of device memory, which avoids hitting the
+/// `max_memory_allocation_count` limit. Each memory type and each `AllocLayout` have their own
+/// blocks, so that linear and optimal resources are never neighbours and the
+/// `buffer_image_granularity` limit doesn't need to be taken into account. Large allocations get
+/// a block of memory of their own.
 #[derive(Debug)]
 pub struct StdMemoryPool {
     device: Arc<Device>,
@@ -47,13 +65,157 @@ impl StdMemoryPool {
             pools: Mutex::new(HashMap::with_capacity_and_hasher(cap, hasher)),
         })
     }
+
+    /// Moves the buffers whose allocation is movable to other blocks of memory of the pool, in
+    /// order to reduce fragmentation.
+    ///
+    /// For each buffer whose allocation is movable (see `StdMemoryPoolAlloc::is_movable`) and
+    /// for which there is room in a fuller block, a new buffer with the same size and usage is
+    /// created, bound to a new allocation, and a copy from the old buffer to the new one is
+    /// recorded in `cb`. Buffers that aren't moved are not part of the returned list.
+    ///
+    /// Once the command buffer has finished executing, you should replace the old buffers and
+    /// allocations with the new ones, destroy the old ones, then call `release_empty_blocks` to
+    /// free the blocks of memory that are now unused.
+    ///
+    /// Only buffers that have the `transfer_source` usage and that aren't sparse can be moved.
+    /// The new buffers have the same sharing mode as the old buffers, and the `transfer_dest`
+    /// usage in addition to the usage of the old buffers. Images are never moved.
+    ///
+    /// The high-level buffer types wrap this function with their `defragment` method.
+    ///
+    /// # Safety
+    ///
+    /// - Each allocation must be the one that is bound to the buffer it is paired with, and
+    ///   must have been allocated from this pool.
+    /// - The buffers must not be written by the device while the command buffer executes, and
+    ///   must not be used anymore once it has finished executing.
+    /// - Must be called outside of a render pass.
+    /// - The old buffers that were created with an exclusive sharing mode must be owned by the
+    ///   queue family of the command buffer. The new exclusive buffers are owned by that queue
+    ///   family as well.
+    ///
+    pub unsafe fn defragment_buffers<'a, P, I>(&self, cb: &mut UnsafeCommandBufferBuilder<P>,
+                                               buffers: I)
+                                               -> Result<Vec<MovedBuffer>, OomError>
+        where P: CommandPool,
+              I: IntoIterator<Item = (&'a UnsafeBuffer, &'a StdMemoryPoolAlloc)>
+    {
+        let mut moved = Vec::new();
+
+        for (index, (buffer, alloc)) in buffers.into_iter().enumerate() {
+            assert_eq!(&*alloc.pool as *const StdMemoryPool, self as *const StdMemoryPool);
+
+            if !alloc.is_movable() || !buffer.usage_transfer_src() ||
+               buffer.sparse_level().sparse
+            {
+                continue;
+            }
+
+            let usage = BufferUsage {
+                transfer_source: true,
+                transfer_dest: true,
+                uniform_texel_buffer: buffer.usage_uniform_texel_buffer(),
+                storage_texel_buffer: buffer.usage_storage_texel_buffer(),
+                uniform_buffer: buffer.usage_uniform_buffer(),
+                storage_buffer: buffer.usage_storage_buffer(),
+                index_buffer: buffer.usage_index_buffer(),
+                vertex_buffer: buffer.usage_vertex_buffer(),
+                indirect_buffer: buffer.usage_indirect_buffer(),
+                conditional_rendering: buffer.usage_conditional_rendering(),
+            };
+
+            // The new buffer must be accessible from the same queue families as the old one.
+            let sharing = if buffer.concurrent_queue_families().is_empty() {
+                Sharing::Exclusive
+            } else {
+                Sharing::Concurrent(buffer.concurrent_queue_families().iter().cloned())
+            };
+
+            let (new_buffer, mem_reqs) = {
+                match UnsafeBuffer::new(&self.device, buffer.size(), &usage, sharing,
+                                        SparseLevel::none())
+                {
+                    Ok(b) => b,
+                    Err(BufferCreationError::OomError(err)) => return Err(err),
+                    Err(_) => unreachable!(),
+                }
+            };
+
+            if (mem_reqs.memory_type_bits & (1 << alloc.memory().memory_type().id())) == 0 {
+                continue;
+            }
+
+            let new_alloc = match alloc.inner {
+                StdMemoryPoolAllocInner::HostVisible(ref old) => {
+                    StdHostVisibleMemoryTypePool::alloc_for_defrag(old.pool(), mem_reqs.size,
+                                                                   mem_reqs.alignment, old)
+                        .map(StdMemoryPoolAllocInner::HostVisible)
+                },
+                StdMemoryPoolAllocInner::NonHostVisible(ref old) => {
+                    StdNonHostVisibleMemoryTypePool::alloc_for_defrag(old.pool(),
+                                                                      mem_reqs.size,
+                                                                      mem_reqs.alignment, old)
+                        .map(StdMemoryPoolAllocInner::NonHostVisible)
+                },
+                _ => None,
+            };
+
+            let new_alloc = match new_alloc {
+                Some(inner) => StdMemoryPoolAlloc { inner: inner, pool: alloc.pool.clone() },
+                None => continue,
+            };
+
+            try!(new_buffer.bind_memory(new_alloc.memory(), new_alloc.offset()));
+            cb.copy_buffer(buffer, &new_buffer, Some(BufferCopyRegion {
+                source_offset: 0,
+                destination_offset: 0,
+                size: buffer.size(),
+            }));
+
+            moved.push(MovedBuffer {
+                index: index,
+                buffer: new_buffer,
+                memory: new_alloc,
+            });
+        }
+
+        Ok(moved)
+    }
+
+    /// Frees the blocks of memory that no longer contain any allocation.
+    ///
+    /// Blocks are normally kept alive in order to be reused by later allocations. Call this
+    /// function after a defragmentation pass or after destroying a lot of resources.
+    pub fn release_empty_blocks(&self) {
+        let pools = self.pools.lock().unwrap();
+
+        for pool in pools.values() {
+            match *pool {
+                Pool::HostVisible(ref pool) => pool.release_empty_blocks(),
+                Pool::NonHostVisible(ref pool) => pool.release_empty_blocks(),
+            }
+        }
+    }
+}
+
+/// Buffer that has been moved by `StdMemoryPool::defragment_buffers`.
+#[derive(Debug)]
+pub struct MovedBuffer {
+    /// Index of the old buffer in the list that was passed to `defragment_buffers`.
+    pub index: usize,
+    /// The new buffer. Contains the same data as the old buffer once the command buffer has
+    /// finished executing.
+    pub buffer: UnsafeBuffer,
+    /// Memory the new buffer is bound to.
+    pub memory: StdMemoryPoolAlloc,
 }
 
 unsafe impl MemoryPool for Arc<StdMemoryPool> {
     type Alloc = StdMemoryPoolAlloc;
 
     fn alloc(&self, memory_type: MemoryType, size: usize, alignment: usize,
-             layout: AllocLayout) -> Result<StdMemoryPoolAlloc, OomError>
+             layout: AllocLayout) -> Result<StdMemoryPoolAlloc, DeviceMemoryAllocError>
     {
         let mut pools = self.pools.lock().unwrap();
 
@@ -93,6 +255,29 @@ unsafe impl MemoryPool for Arc<StdMemoryPool> {
             },
         }
     }
+
+    fn alloc_dedicated(&self, memory_type: MemoryType, size: usize, alignment: usize,
+                       layout: AllocLayout, dedicated: DedicatedAlloc)
+                       -> Result<StdMemoryPoolAlloc, DedicatedAllocError>
+    {
+        if let DedicatedAlloc::None = dedicated {
+            return Ok(try!(self.alloc(memory_type, size, alignment, layout)));
+        }
+
+        // A dedicated allocation always starts at offset 0 of its own memory, so the alignment
+        // is automatically satisfied.
+        let inner = if memory_type.is_host_visible() {
+            let mem = try!(DeviceMemory::dedicated_alloc_and_map(&self.device, memory_type, size,
+                                                                 dedicated));
+            StdMemoryPoolAllocInner::DedicatedMapped(mem)
+        } else {
+            let mem = try!(DeviceMemory::dedicated_alloc(&self.device, memory_type, size,
+                                                         dedicated));
+            StdMemoryPoolAllocInner::Dedicated(mem)
+        };
+
+        Ok(StdMemoryPoolAlloc { inner: inner, pool: self.clone() })
+    }
 }
 
 #[derive(Debug)]
@@ -108,11 +293,31 @@ pub struct StdMemoryPoolAlloc {
 }
 
 impl StdMemoryPoolAlloc {
+    /// Returns the pool this allocation belongs to.
+    #[inline]
+    pub fn pool(&self) -> &Arc<StdMemoryPool> {
+        &self.pool
+    }
+
+    /// Returns true if moving this allocation to another block of memory could allow freeing
+    /// its block. See `StdMemoryPool::defragment_buffers`.
+    #[inline]
+    pub fn is_movable(&self) -> bool {
+        match self.inner {
+            StdMemoryPoolAllocInner::NonHostVisible(ref mem) => mem.is_movable(),
+            StdMemoryPoolAllocInner::HostVisible(ref mem) => mem.is_movable(),
+            StdMemoryPoolAllocInner::Dedicated(_) => false,
+            StdMemoryPoolAllocInner::DedicatedMapped(_) => false,
+        }
+    }
+
     #[inline]
     pub fn size(&self) -> usize {
         match self.inner {
             StdMemoryPoolAllocInner::NonHostVisible(ref mem) => mem.size(),
             StdMemoryPoolAllocInner::HostVisible(ref mem) => mem.size(),
+            StdMemoryPoolAllocInner::Dedicated(ref mem) => mem.size(),
+            StdMemoryPoolAllocInner::DedicatedMapped(ref mem) => mem.memory().size(),
         }
     }
 }
@@ -123,6 +328,8 @@ unsafe impl MemoryPoolAlloc for StdMemoryPoolAlloc {
         match self.inner {
             StdMemoryPoolAllocInner::NonHostVisible(ref mem) => mem.memory(),
             StdMemoryPoolAllocInner::HostVisible(ref mem) => mem.memory().memory(),
+            StdMemoryPoolAllocInner::Dedicated(ref mem) => mem,
+            StdMemoryPoolAllocInner::DedicatedMapped(ref mem) => mem.memory(),
         }
     }
 
@@ -131,6 +338,8 @@ unsafe impl MemoryPoolAlloc for StdMemoryPoolAlloc {
         match self.inner {
             StdMemoryPoolAllocInner::NonHostVisible(_) => None,
             StdMemoryPoolAllocInner::HostVisible(ref mem) => Some(mem.memory()),
+            StdMemoryPoolAllocInner::Dedicated(_) => None,
+            StdMemoryPoolAllocInner::DedicatedMapped(ref mem) => Some(mem),
         }
     }
 
@@ -139,6 +348,8 @@ unsafe impl MemoryPoolAlloc for StdMemoryPoolAlloc {
         match self.inner {
             StdMemoryPoolAllocInner::NonHostVisible(ref mem) => mem.offset(),
             StdMemoryPoolAllocInner::HostVisible(ref mem) => mem.offset(),
+            StdMemoryPoolAllocInner::Dedicated(_) => 0,
+            StdMemoryPoolAllocInner::DedicatedMapped(_) => 0,
         }
     }
 }
@@ -147,4 +358,7 @@ unsafe impl MemoryPoolAlloc for StdMemoryPoolAlloc {
 enum StdMemoryPoolAllocInner {
     NonHostVisible(StdNonHostVisibleMemoryTypePoolAlloc),
     HostVisible(StdHostVisibleMemoryTypePoolAlloc),
+    // Memory allocated specifically for one buffer or image.
+    Dedicated(DeviceMemory),
+    DedicatedMapped(MappedDeviceMemory),
 }
@@ -0,0 +1,133 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::Mutex;
+
+/// Statistics about the memory allocated on a device. Returned by `Device::memory_stats()`.
+///
+/// Only the memory allocated through `DeviceMemory` is counted in `allocated` and
+/// `allocation_count`. Memory pools usually allocate large blocks of memory and divide them
+/// between multiple buffers and images, therefore these values are about the blocks and not
+/// about individual resources.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Statistics of each memory type. The index in this list is the id of the memory type.
+    pub memory_types: Vec<MemoryTypeStats>,
+    /// Statistics of each memory heap. The index in this list is the id of the heap.
+    pub heaps: Vec<MemoryHeapStats>,
+}
+
+/// Statistics about a single memory type.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct MemoryTypeStats {
+    /// Number of bytes currently allocated from this memory type.
+    pub allocated: usize,
+    /// Number of allocations currently alive in this memory type.
+    pub allocation_count: u32,
+}
+
+/// Statistics about a single memory heap.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct MemoryHeapStats {
+    /// Number of bytes currently allocated from the memory types of this heap.
+    pub allocated: usize,
+    /// Number of allocations currently alive in the memory types of this heap.
+    pub allocation_count: u32,
+    /// Number of bytes of this heap that the process can use before allocations start to fail
+    /// or to hurt performances, as estimated by the driver.
+    ///
+    /// `None` if the `ext_memory_budget` extension isn't enabled on the device.
+    pub budget: Option<usize>,
+    /// Number of bytes of this heap that the driver considers used by the process. Contrary to
+    /// `allocated`, this includes memory that wasn't allocated by vulkano.
+    ///
+    /// `None` if the `ext_memory_budget` extension isn't enabled on the device.
+    pub usage: Option<usize>,
+}
+
+/// Keeps track of the memory allocated from each memory type of a device.
+///
+/// Each `Device` owns one tracker, which is accessible with `Device::memory_stats_tracker`.
+/// `DeviceMemory` calls `add` when it allocates memory and `remove` when it frees it.
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct MemoryStatsTracker {
+    memory_types: Mutex<Vec<MemoryTypeStats>>,
+}
+
+impl MemoryStatsTracker {
+    #[inline]
+    pub fn new(num_memory_types: usize) -> MemoryStatsTracker {
+        MemoryStatsTracker {
+            memory_types: Mutex::new(vec![Default::default(); num_memory_types]),
+        }
+    }
+
+    /// Registers a new allocation.
+    #[inline]
+    pub fn add(&self, memory_type: u32, size: usize) {
+        let mut memory_types = self.memory_types.lock().unwrap();
+        let stats = &mut memory_types[memory_type as usize];
+        stats.allocated += size;
+        stats.allocation_count += 1;
+    }
+
+    /// Registers that an allocation has been freed.
+    #[inline]
+    pub fn remove(&self, memory_type: u32, size: usize) {
+        let mut memory_types = self.memory_types.lock().unwrap();
+        let stats = &mut memory_types[memory_type as usize];
+        debug_assert!(stats.allocated >= size && stats.allocation_count >= 1);
+        stats.allocated -= size;
+        stats.allocation_count -= 1;
+    }
+
+    /// Returns the current statistics of each memory type.
+    #[inline]
+    pub fn memory_types(&self) -> Vec<MemoryTypeStats> {
+        self.memory_types.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use memory::DeviceMemory;
+    use memory::MemoryTypeStats;
+    use super::MemoryStatsTracker;
+
+    #[test]
+    fn tracker_add_remove() {
+        let tracker = MemoryStatsTracker::new(2);
+        tracker.add(1, 1024);
+        tracker.add(1, 512);
+        tracker.remove(1, 1024);
+
+        let types = tracker.memory_types();
+        assert_eq!(types[0], MemoryTypeStats { allocated: 0, allocation_count: 0 });
+        assert_eq!(types[1], MemoryTypeStats { allocated: 512, allocation_count: 1 });
+    }
+
+    #[test]
+    fn device_stats() {
+        let (device, _) = gfx_dev_and_queue!();
+        let mem_ty = device.physical_device().memory_types().next().unwrap();
+
+        let before = device.memory_stats();
+        let mem = DeviceMemory::alloc(&device, mem_ty, 256).unwrap();
+
+        let during = device.memory_stats();
+        assert_eq!(during.memory_types[mem_ty.id() as usize].allocated,
+                   before.memory_types[mem_ty.id() as usize].allocated + 256);
+        assert_eq!(during.heaps[mem_ty.heap().id() as usize].allocation_count,
+                   before.heaps[mem_ty.heap().id() as usize].allocation_count + 1);
+
+        drop(mem);
+        assert_eq!(device.memory_stats().memory_types, before.memory_types);
+    }
+}
@@ -149,6 +149,14 @@ impl AttachmentBlend {
             mask_alpha: true,
         }
     }
+
+    /// Returns true if one of the blend factors uses the second color output of the fragment
+    /// shader. This requires the `dual_src_blend` feature.
+    #[inline]
+    pub fn uses_dual_source(&self) -> bool {
+        self.enabled && [self.color_src, self.color_dst, self.alpha_src, self.alpha_dst].iter()
+                                                                  .any(|f| f.is_dual_source())
+    }
 }
 
 #[doc(hidden)]
@@ -259,3 +267,15 @@ pub enum BlendFactor {
     Src1Alpha = vk::BLEND_FACTOR_SRC1_ALPHA,
     OneMinusSrc1Alpha = vk::BLEND_FACTOR_ONE_MINUS_SRC1_ALPHA,
 }
+
+impl BlendFactor {
+    /// Returns true if this factor reads the second color output of the fragment shader.
+    #[inline]
+    pub fn is_dual_source(&self) -> bool {
+        match *self {
+            BlendFactor::Src1Color | BlendFactor::OneMinusSrc1Color | BlendFactor::Src1Alpha |
+            BlendFactor::OneMinusSrc1Alpha => true,
+            _ => false,
+        }
+    }
+}
@@ -13,8 +13,8 @@
 //! pipelines on the disk.
 //! 
 //! You can create either an empty cache or a cache from some initial data. Whenever you create a
-//! graphics or compute pipeline, you have the possibility to pass a reference to that cache.
-//! TODO: ^ that's not the case yet
+//! graphics or compute pipeline, you have the possibility to pass a reference to that cache with
+//! `GraphicsPipeline::with_cache` or `ComputePipeline::with_cache`.
 //! The Vulkan implementation will then look in the cache for an existing entry, or add one if it
 //! doesn't exist.
 //! 
@@ -25,6 +25,8 @@
 use std::mem;
 use std::ptr;
 use std::sync::Arc;
+use std::sync::RwLock;
+use std::sync::RwLockReadGuard;
 
 use device::Device;
 
@@ -40,6 +42,9 @@ use vk;
 pub struct PipelineCache {
     device: Arc<Device>,
     cache: vk::PipelineCache,
+    // `vkMergePipelineCaches` requires external synchronization of the destination cache. Merging
+    // locks it for writing, and the other operations that use the cache lock it for reading.
+    merge_lock: RwLock<()>,
 }
 
 impl PipelineCache {
@@ -128,6 +133,7 @@ impl PipelineCache {
         Ok(Arc::new(PipelineCache {
             device: device.clone(),
             cache: cache,
+            merge_lock: RwLock::new(()),
         }))
     }
 
@@ -135,12 +141,39 @@ impl PipelineCache {
     ///
     /// It is `self` that is modified here. The pipeline caches passed as parameter are untouched.
     ///
+    /// This can be called from multiple threads at once, and while the caches are used to build
+    /// pipelines. A typical use is to let each worker thread build its pipelines with its own
+    /// cache, then merge all these caches in a master cache whose data is stored on the disk.
+    ///
     /// # Panic
     ///
     /// - Panics if `self` is included in the list of other pipelines.
+    /// - Panics if one of the other pipelines doesn't belong to the same device as `self`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use std::thread;
+    /// # use vulkano::device::Device;
+    /// use vulkano::pipeline::cache::PipelineCache;
+    /// # let device: Arc<Device> = return;
+    ///
+    /// let workers = (0 .. 4).map(|_| {
+    ///     let device = device.clone();
+    ///     thread::spawn(move || {
+    ///         let cache = PipelineCache::empty(&device).unwrap();
+    ///         // Build some pipelines with `cache` here.
+    ///         cache
+    ///     })
+    /// }).collect::<Vec<_>>();
     ///
-    // FIXME: vkMergePipelineCaches is not thread safe for the destination cache
-    // TODO: write example
+    /// let caches = workers.into_iter().map(|w| w.join().unwrap()).collect::<Vec<_>>();
+    ///
+    /// let master = PipelineCache::empty(&device).unwrap();
+    /// master.merge(caches.iter().collect::<Vec<_>>().iter()).unwrap();
+    /// let data = master.get_data().unwrap();
+    /// ```
     pub fn merge<'a, I>(&self, pipelines: I) -> Result<(), OomError>
         where I: IntoIterator<Item = &'a &'a Arc<PipelineCache>>
     {
@@ -149,9 +182,11 @@ impl PipelineCache {
 
             let pipelines = pipelines.into_iter().map(|pipeline| {
                 assert!(&***pipeline as *const _ != &*self as *const _);
+                assert_eq!(pipeline.device.internal_object(), self.device.internal_object());
                 pipeline.cache
             }).collect::<Vec<_>>();
 
+            let _lock = self.merge_lock.write().unwrap();
             try!(check_errors(vk.MergePipelineCaches(self.device.internal_object(), self.cache,
                                                      pipelines.len() as u32, pipelines.as_ptr())));
 
@@ -190,6 +225,7 @@ impl PipelineCache {
     pub fn get_data(&self) -> Result<Vec<u8>, OomError> {
         unsafe {
             let vk = self.device.pointers();
+            let _lock = self.lock_shared();
 
             let mut num = 0;
             try!(check_errors(vk.GetPipelineCacheData(self.device.internal_object(), self.cache,
@@ -203,6 +239,14 @@ impl PipelineCache {
             Ok(data)
         }
     }
+
+    /// Locks the cache for an operation that uses it without being a merge, such as building a
+    /// pipeline. Merging into this cache waits until the returned guard is dropped.
+    #[doc(hidden)]
+    #[inline]
+    pub fn lock_shared(&self) -> RwLockReadGuard<()> {
+        self.merge_lock.read().unwrap()
+    }
 }
 
 unsafe impl VulkanObject for PipelineCache {
@@ -226,6 +270,7 @@ impl Drop for PipelineCache {
 
 #[cfg(test)]
 mod tests {
+    use std::thread;
     use pipeline::cache::PipelineCache;
 
     #[test]
@@ -235,4 +280,24 @@ mod tests {
         let pipeline = PipelineCache::empty(&device).unwrap();
         pipeline.merge(&[&pipeline]).unwrap();
     }
+
+    #[test]
+    fn merge_from_threads() {
+        let (device, _) = gfx_dev_and_queue!();
+        let master = PipelineCache::empty(&device).unwrap();
+
+        let workers = (0 .. 4).map(|_| {
+            let device = device.clone();
+            let master = master.clone();
+            thread::spawn(move || {
+                let cache = PipelineCache::empty(&device).unwrap();
+                master.merge(&[&cache]).unwrap();
+                cache
+            })
+        }).collect::<Vec<_>>();
+
+        let caches = workers.into_iter().map(|w| w.join().unwrap()).collect::<Vec<_>>();
+        master.merge(caches.iter().collect::<Vec<_>>().iter()).unwrap();
+        master.get_data().unwrap();
+    }
 }
@@ -16,12 +16,16 @@ use std::sync::Arc;
 use descriptor::PipelineLayout;
 use descriptor::pipeline_layout::PipelineLayoutDesc;
 use descriptor::pipeline_layout::PipelineLayoutSuperset;
+use pipeline::cache::PipelineCache;
 use pipeline::shader::ComputeShaderEntryPoint;
 use pipeline::shader::SpecializationConstants;
 
 use device::Device;
+use instance::debug::ObjectType;
+use instrumentation::ResourceKind;
 use Error;
 use OomError;
+use VulkanError;
 use VulkanObject;
 use VulkanPointers;
 use check_errors;
@@ -35,15 +39,39 @@ pub struct ComputePipeline<Pl> {
     pipeline: vk::Pipeline,
     device: Arc<Device>,
     pipeline_layout: Arc<Pl>,
+    local_size: [u32; 3],
 }
 
 impl<Pl> ComputePipeline<Pl> {
     /// Builds a new `ComputePipeline`.
+    #[inline]
     pub fn new<Css, Csl>(device: &Arc<Device>, pipeline_layout: &Arc<Pl>,
                          shader: &ComputeShaderEntryPoint<Css, Csl>, specialization: &Css) 
                          -> Result<Arc<ComputePipeline<Pl>>, ComputePipelineCreationError>
         where Pl: PipelineLayout + PipelineLayoutSuperset<Csl>, Csl: PipelineLayoutDesc,
               Css: SpecializationConstants
+    {
+        ComputePipeline::new_impl(device, pipeline_layout, shader, specialization, None)
+    }
+
+    /// Same as `new`, but looks up and stores the pipeline in a pipeline cache.
+    #[inline]
+    pub fn with_cache<Css, Csl>(device: &Arc<Device>, pipeline_layout: &Arc<Pl>,
+                                shader: &ComputeShaderEntryPoint<Css, Csl>, specialization: &Css,
+                                cache: &Arc<PipelineCache>)
+                                -> Result<Arc<ComputePipeline<Pl>>, ComputePipelineCreationError>
+        where Pl: PipelineLayout + PipelineLayoutSuperset<Csl>, Csl: PipelineLayoutDesc,
+              Css: SpecializationConstants
+    {
+        ComputePipeline::new_impl(device, pipeline_layout, shader, specialization, Some(cache))
+    }
+
+    fn new_impl<Css, Csl>(device: &Arc<Device>, pipeline_layout: &Arc<Pl>,
+                          shader: &ComputeShaderEntryPoint<Css, Csl>, specialization: &Css,
+                          cache: Option<&Arc<PipelineCache>>)
+                          -> Result<Arc<ComputePipeline<Pl>>, ComputePipelineCreationError>
+        where Pl: PipelineLayout + PipelineLayoutSuperset<Csl>, Csl: PipelineLayoutDesc,
+              Css: SpecializationConstants
     {
         let vk = device.pointers();
 
@@ -52,6 +80,22 @@ impl<Pl> ComputePipeline<Pl> {
             return Err(ComputePipelineCreationError::IncompatiblePipelineLayout);
         }
 
+        let local_size = shader.local_size();
+        {
+            let limits = device.physical_device().limits();
+            let max_size = limits.max_compute_work_group_size();
+            if local_size[0] == 0 || local_size[1] == 0 || local_size[2] == 0 ||
+               local_size[0] > max_size[0] || local_size[1] > max_size[1] ||
+               local_size[2] > max_size[2] ||
+               local_size[0] as u64 * local_size[1] as u64 * local_size[2] as u64 >
+                   limits.max_compute_work_group_invocations() as u64
+            {
+                return Err(ComputePipelineCreationError::MaxComputeWorkGroupSizeExceeded {
+                    obtained: local_size,
+                });
+            }
+        }
+
         let pipeline = unsafe {
             let spec_descriptors = <Css as SpecializationConstants>::descriptors();
             let specialization = vk::SpecializationInfo {
@@ -78,7 +122,12 @@ impl<Pl> ComputePipeline<Pl> {
             let infos = vk::ComputePipelineCreateInfo {
                 sType: vk::STRUCTURE_TYPE_COMPUTE_PIPELINE_CREATE_INFO,
                 pNext: ptr::null(),
-                flags: 0,
+                // Allows the pipeline to be used with `vkCmdDispatchBaseKHR`.
+                flags: if device.loaded_extensions().khr_device_group {
+                    vk::PIPELINE_CREATE_DISPATCH_BASE_KHR
+                } else {
+                    0
+                },
                 stage: stage,
                 layout: PipelineLayout::inner(&**pipeline_layout).internal_object(),
                 basePipelineHandle: 0,
@@ -86,15 +135,20 @@ impl<Pl> ComputePipeline<Pl> {
             };
 
             let mut output = mem::uninitialized();
-            try!(check_errors(vk.CreateComputePipelines(device.internal_object(), 0,
+            let _cache_lock = cache.map(|c| c.lock_shared());
+            let cache = cache.map(|c| c.internal_object()).unwrap_or(0);
+            try!(check_errors(vk.CreateComputePipelines(device.internal_object(), cache,
                                                         1, &infos, ptr::null(), &mut output)));
             output
         };
 
+        device.resource_tracker().created(ResourceKind::Pipeline, pipeline);
+
         Ok(Arc::new(ComputePipeline {
             device: device.clone(),
             pipeline: pipeline,
             pipeline_layout: pipeline_layout.clone(),
+            local_size: local_size,
         }))
     }
 
@@ -104,11 +158,45 @@ impl<Pl> ComputePipeline<Pl> {
         &self.device
     }
 
+    /// Gives a name to the pipeline, that debugging tools and validation layers can display.
+    ///
+    /// Does nothing if the `VK_EXT_debug_marker` extension isn't enabled on the device.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `name` contains a nul character.
+    ///
+    #[inline]
+    pub fn set_object_name(&self, name: &str) -> Result<(), OomError> {
+        unsafe {
+            self.device.set_object_name_raw(ObjectType::Pipeline, self.pipeline as u64, name)
+        }
+    }
+
     /// Returns the pipeline layout used in this compute pipeline.
     #[inline]
     pub fn layout(&self) -> &Arc<Pl> {
         &self.pipeline_layout
     }
+
+    /// Returns the number of invocations of each work group, as declared in the shader.
+    #[inline]
+    pub fn local_size(&self) -> [u32; 3] {
+        self.local_size
+    }
+
+    /// Returns the number of work groups to dispatch so that at least `elements` invocations
+    /// are started.
+    ///
+    /// This assumes that the shader processes a one-dimensional list of elements indexed by
+    /// `gl_GlobalInvocationID.x`. Only the first dimension of the local size is taken into
+    /// account, and the other two dimensions of the returned value are always 1. The shader is
+    /// responsible for ignoring the invocations whose index is out of range.
+    #[inline]
+    pub fn group_counts_for_elements(&self, elements: u32) -> [u32; 3] {
+        let x = self.local_size[0];
+        [(elements / x) + if elements % x != 0 { 1 } else { 0 }, 1, 1]
+    }
 }
 
 unsafe impl<Pl> VulkanObject for ComputePipeline<Pl> {
@@ -126,6 +214,7 @@ impl<Pl> Drop for ComputePipeline<Pl> {
         unsafe {
             let vk = self.device.pointers();
             vk.DestroyPipeline(self.device.internal_object(), self.pipeline, ptr::null());
+            self.device.resource_tracker().destroyed(ResourceKind::Pipeline, self.pipeline);
         }
     }
 }
@@ -137,6 +226,12 @@ pub enum ComputePipelineCreationError {
     OomError(OomError),
     /// The pipeline layout is not compatible with what the shader expects.
     IncompatiblePipelineLayout,
+    /// The local size of the shader is zero in one of its dimensions, or exceeds the
+    /// `maxComputeWorkGroupSize` or `maxComputeWorkGroupInvocations` limits.
+    MaxComputeWorkGroupSizeExceeded {
+        /// Value that was passed.
+        obtained: [u32; 3],
+    },
 }
 
 impl error::Error for ComputePipelineCreationError {
@@ -147,6 +242,9 @@ impl error::Error for ComputePipelineCreationError {
             ComputePipelineCreationError::IncompatiblePipelineLayout => "the pipeline layout is \
                                                                          not compatible with what \
                                                                          the shader expects",
+            ComputePipelineCreationError::MaxComputeWorkGroupSizeExceeded { .. } => {
+                "the local size of the shader is invalid or exceeds the limits of the device"
+            },
         }
     }
 
@@ -166,6 +264,16 @@ impl fmt::Display for ComputePipelineCreationError {
     }
 }
 
+impl VulkanError for ComputePipelineCreationError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            ComputePipelineCreationError::OomError(ref err) => err.error_code(),
+            _ => None
+        }
+    }
+}
+
 impl From<OomError> for ComputePipelineCreationError {
     #[inline]
     fn from(err: OomError) -> ComputePipelineCreationError {
@@ -20,8 +20,12 @@
 //! value in the stencil buffer at each fragment's location. Depending on the outcome of the
 //! depth and stencil tests, the value of the stencil buffer at that location can be updated.
 
+use std::error;
+use std::fmt;
 use std::ops::Range;
 use std::u32;
+use Error;
+use VulkanError;
 use vk;
 
 /// Configuration of the depth and stencil tests.
@@ -71,6 +75,19 @@ impl DepthStencil {
             stencil_back: Default::default(),
         }
     }
+
+    /// Creates a `DepthStencil` with the depth test disabled and the same stencil operations for
+    /// both front and back faces.
+    #[inline]
+    pub fn stencil_only(stencil: Stencil) -> DepthStencil {
+        DepthStencil {
+            depth_write: false,
+            depth_compare: Compare::Always,
+            depth_bounds_test: DepthBounds::Disabled,
+            stencil_front: stencil,
+            stencil_back: stencil,
+        }
+    }
 }
 
 impl Default for DepthStencil {
@@ -81,6 +98,9 @@ impl Default for DepthStencil {
 }
 
 /// Configuration of a stencil test.
+///
+/// The default value is a test that always passes and keeps the stencil values, which disables
+/// the test.
 #[derive(Debug, Copy, Clone)]
 pub struct Stencil {
     /// The comparison to perform between the existing stencil value in the stencil buffer, and
@@ -141,13 +161,23 @@ impl Stencil {
                  self.depth_fail_op == StencilOp::Keep,
         }
     }
+
+    /// Returns true if the stencil test always passes and never modifies the stencil buffer, in
+    /// which case it has no effect and is disabled.
+    ///
+    /// A test that keeps the stencil values but whose comparison isn't `Always` still reads the
+    /// stencil buffer to discard fragments.
+    #[inline]
+    pub fn is_disabled(&self) -> bool {
+        self.compare == Compare::Always && self.always_keep()
+    }
 }
 
 impl Default for Stencil {
     #[inline]
     fn default() -> Stencil {
         Stencil {
-            compare: Compare::Never,
+            compare: Compare::Always,
             pass_op: StencilOp::Keep,
             fail_op: StencilOp::Keep,
             depth_fail_op: StencilOp::Keep,
@@ -173,6 +203,12 @@ pub enum StencilOp {
 }
 
 /// Allows you to ask the GPU to exclude fragments that are outside of a certain range.
+///
+/// The test compares the depth value already in the depth buffer at the fragment's location
+/// with the range, which is for example useful to skip the pixels that are outside of a light
+/// volume in a deferred renderer.
+///
+/// Enabling the test requires the `depth_bounds` feature.
 #[derive(Debug, Clone, PartialEq)]
 pub enum DepthBounds {
     /// The test is disabled. All fragments pass the depth bounds test.
@@ -183,7 +219,7 @@ pub enum DepthBounds {
     Fixed(Range<f32>),
 
     /// The depth bounds test is enabled, but the range will need to specified when you submit
-    /// a draw command.
+    /// a draw command, through the `depth_bounds` field of the `DynamicState`.
     Dynamic,
 }
 
@@ -198,10 +234,62 @@ impl DepthBounds {
     }
 }
 
+/// Checks that `min` and `max` can be used as depth bounds, either in `DepthBounds::Fixed` or as
+/// the dynamic depth bounds of a draw command.
+#[inline]
+pub fn check_depth_bounds(min: f32, max: f32) -> Result<(), DepthBoundsError> {
+    if !(min >= 0.0 && min <= 1.0 && max >= 0.0 && max <= 1.0) {
+        return Err(DepthBoundsError::OutOfRange);
+    }
+
+    if min > max {
+        return Err(DepthBoundsError::MinGreaterThanMax);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when checking depth bounds.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DepthBoundsError {
+    /// One of the bounds is not between 0.0 and 1.0.
+    OutOfRange,
+    /// The minimum is greater than the maximum.
+    MinGreaterThanMax,
+}
+
+impl error::Error for DepthBoundsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            DepthBoundsError::OutOfRange => {
+                "one of the depth bounds is not between 0.0 and 1.0"
+            },
+            DepthBoundsError::MinGreaterThanMax => {
+                "the minimum depth bound is greater than the maximum"
+            },
+        }
+    }
+}
+
+impl fmt::Display for DepthBoundsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for DepthBoundsError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        None
+    }
+}
+
 /// Specifies how two values should be compared to decide whether a test passes or fails.
 ///
 /// Used for both depth testing and stencil testing.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[repr(u32)]
 pub enum Compare {
     /// The test never passes.
@@ -221,3 +309,18 @@ pub enum Compare {
     /// The test always passes.
     Always = vk::COMPARE_OP_ALWAYS,
 }
+
+#[cfg(test)]
+mod tests {
+    use pipeline::depth_stencil::DepthBoundsError;
+    use pipeline::depth_stencil::check_depth_bounds;
+
+    #[test]
+    fn depth_bounds() {
+        assert_eq!(check_depth_bounds(0.0, 1.0), Ok(()));
+        assert_eq!(check_depth_bounds(0.5, 0.5), Ok(()));
+        assert_eq!(check_depth_bounds(-0.5, 1.0), Err(DepthBoundsError::OutOfRange));
+        assert_eq!(check_depth_bounds(0.0, 2.0), Err(DepthBoundsError::OutOfRange));
+        assert_eq!(check_depth_bounds(0.8, 0.2), Err(DepthBoundsError::MinGreaterThanMax));
+    }
+}
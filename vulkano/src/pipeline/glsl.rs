@@ -0,0 +1,217 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Compilation of GLSL shaders at runtime.
+//!
+//! This module is only available with the `glsl` feature. It is meant for workflows where
+//! shaders are modified while the application is running, for example to reload them when their
+//! source file changes. For shaders that are known at compile time, prefer the `vulkano-shaders`
+//! crate.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use vulkano::pipeline::glsl::GlslShaderStage;
+//! use vulkano::pipeline::shader::ShaderModule;
+//!
+//! let source = std::fs::read_to_string("shader.frag").unwrap();
+//! match unsafe { ShaderModule::from_glsl_source(device.clone(), GlslShaderStage::Fragment,
+//!                                               &source) }
+//! {
+//!     Ok(module) => { /* rebuild the pipeline */ },
+//!     Err(err) => println!("{}", err),
+//! }
+//! ```
+
+use std::error;
+use std::fmt;
+use std::io::Read;
+use std::sync::Arc;
+
+use glsl_to_spirv;
+
+use device::Device;
+use pipeline::shader::ShaderModule;
+use pipeline::shader::ShaderModuleCreationError;
+use Error;
+use SafeDeref;
+use VulkanError;
+
+/// Stage of the pipeline a GLSL shader is written for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[allow(missing_docs)]
+pub enum GlslShaderStage {
+    Vertex,
+    TessellationControl,
+    TessellationEvaluation,
+    Geometry,
+    Fragment,
+    Compute,
+}
+
+impl GlslShaderStage {
+    #[inline]
+    fn to_glsl_to_spirv(&self) -> glsl_to_spirv::ShaderType {
+        match *self {
+            GlslShaderStage::Vertex => glsl_to_spirv::ShaderType::Vertex,
+            GlslShaderStage::TessellationControl => {
+                glsl_to_spirv::ShaderType::TessellationControl
+            },
+            GlslShaderStage::TessellationEvaluation => {
+                glsl_to_spirv::ShaderType::TessellationEvaluation
+            },
+            GlslShaderStage::Geometry => glsl_to_spirv::ShaderType::Geometry,
+            GlslShaderStage::Fragment => glsl_to_spirv::ShaderType::Fragment,
+            GlslShaderStage::Compute => glsl_to_spirv::ShaderType::Compute,
+        }
+    }
+}
+
+impl<P> ShaderModule<P> where P: SafeDeref<Target = Device> {
+    /// Compiles GLSL source code to SPIR-V and builds a shader module from the result.
+    ///
+    /// Returns `GlslError::Compilation` with the messages of the compiler if the source code
+    /// is invalid.
+    ///
+    /// # Safety
+    ///
+    /// - The shader may require some features that are not enabled. This isn't checked by this
+    ///   function.
+    ///
+    pub unsafe fn from_glsl_source(device: P, stage: GlslShaderStage, source: &str)
+                                   -> Result<Arc<ShaderModule<P>>, GlslError>
+    {
+        let mut output = match glsl_to_spirv::compile(source, stage.to_glsl_to_spirv()) {
+            Ok(output) => output,
+            Err(log) => return Err(GlslError::Compilation(parse_log(&log))),
+        };
+
+        let mut spirv = Vec::new();
+        output.read_to_end(&mut spirv).expect("failed to read the output of the GLSL compiler");
+
+        Ok(try!(ShaderModule::from_bytes(device, &spirv)))
+    }
+}
+
+/// Message produced by the GLSL compiler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlslMessage {
+    /// Line of the source code the message refers to, starting at 1, if known.
+    pub line: Option<u32>,
+    /// Text of the message.
+    pub message: String,
+}
+
+impl fmt::Display for GlslMessage {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.line {
+            Some(line) => write!(fmt, "line {}: {}", line, self.message),
+            None => write!(fmt, "{}", self.message),
+        }
+    }
+}
+
+/// Error that can happen when building a shader module from GLSL source code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GlslError {
+    /// The source code couldn't be compiled. Contains the errors reported by the compiler.
+    Compilation(Vec<GlslMessage>),
+    /// The compiled code couldn't be turned into a shader module.
+    ShaderModuleCreation(ShaderModuleCreationError),
+}
+
+impl error::Error for GlslError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            GlslError::Compilation(_) => "the GLSL source code couldn't be compiled",
+            GlslError::ShaderModuleCreation(_) => "error while creating the shader module",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            GlslError::ShaderModuleCreation(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for GlslError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        try!(write!(fmt, "{}", error::Error::description(self)));
+        if let GlslError::Compilation(ref messages) = *self {
+            for message in messages.iter() {
+                try!(write!(fmt, "\n{}", message));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl VulkanError for GlslError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            GlslError::ShaderModuleCreation(ref err) => err.error_code(),
+            _ => None
+        }
+    }
+}
+
+impl From<ShaderModuleCreationError> for GlslError {
+    #[inline]
+    fn from(err: ShaderModuleCreationError) -> GlslError {
+        GlslError::ShaderModuleCreation(err)
+    }
+}
+
+// Extracts the errors from the output of `glslangValidator`. Errors look like
+// `ERROR: 0:12: 'foo' : undeclared identifier`, where `0` is the index of the source file and
+// `12` the line.
+fn parse_log(log: &str) -> Vec<GlslMessage> {
+    log.lines().filter_map(|line| {
+        let line = line.trim();
+        if !line.starts_with("ERROR: ") {
+            return None;
+        }
+
+        let rest = line["ERROR: ".len() ..].trim();
+        let mut parts = rest.splitn(3, ':');
+        let (source, line_num, message) = (parts.next(), parts.next(), parts.next());
+
+        match (source, line_num.and_then(|l| l.trim().parse().ok()), message) {
+            (Some(_), Some(line_num), Some(message)) => {
+                Some(GlslMessage { line: Some(line_num), message: message.trim().to_owned() })
+            },
+            _ => Some(GlslMessage { line: None, message: rest.to_owned() }),
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pipeline::glsl::GlslMessage;
+    use pipeline::glsl::parse_log;
+
+    #[test]
+    fn log_parsing() {
+        let log = "/tmp/glslang-compile/0.frag\n\
+                   ERROR: 0:12: 'foo' : undeclared identifier\n\
+                   ERROR: 1 compilation errors.  No code generated.\n";
+
+        assert_eq!(parse_log(log), vec![
+            GlslMessage { line: Some(12), message: "'foo' : undeclared identifier".to_owned() },
+            GlslMessage { line: None,
+                          message: "1 compilation errors.  No code generated.".to_owned() },
+        ]);
+    }
+}
@@ -0,0 +1,484 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::Arc;
+
+use device::Device;
+use descriptor::PipelineLayout;
+use descriptor::pipeline_layout::PipelineLayoutDesc;
+use descriptor::pipeline_layout::PipelineLayoutSuperset;
+use framebuffer::RenderPass;
+use framebuffer::RenderPassDesc;
+use framebuffer::RenderPassSubpassInterface;
+use framebuffer::Subpass;
+
+use pipeline::blend::AttachmentBlend;
+use pipeline::blend::AttachmentsBlend;
+use pipeline::blend::Blend;
+use pipeline::cache::PipelineCache;
+use pipeline::depth_stencil::DepthStencil;
+use pipeline::graphics_pipeline::GraphicsPipeline;
+use pipeline::graphics_pipeline::GraphicsPipelineCreationError;
+use pipeline::graphics_pipeline::GraphicsPipelineParams;
+use pipeline::input_assembly::InputAssembly;
+use pipeline::input_assembly::PrimitiveTopology;
+use pipeline::multisample::Multisample;
+use pipeline::raster::CullMode;
+use pipeline::raster::FrontFace;
+use pipeline::raster::PolygonMode;
+use pipeline::raster::Rasterization;
+use pipeline::shader::FragmentShaderEntryPoint;
+use pipeline::shader::ShaderInterfaceDef;
+use pipeline::shader::ShaderInterfaceDefMatch;
+use pipeline::shader::VertexShaderEntryPoint;
+use pipeline::vertex::Definition as VertexDefinition;
+use pipeline::vertex::SingleBufferDefinition;
+use pipeline::viewport::Scissor;
+use pipeline::viewport::Viewport;
+use pipeline::viewport::ViewportsState;
+
+/// Prototype for a `GraphicsPipeline`.
+///
+/// Use `GraphicsPipeline::start()` to obtain one. All the fixed states have a default value, but
+/// the vertex input, the vertex and fragment shaders, the pipeline layout and the subpass must be
+/// provided before `build()` becomes available. Forgetting one of them is a compilation error.
+///
+/// The builder only handles pipelines made of a vertex shader and a fragment shader. Use
+/// `GraphicsPipelineParams` directly if you need tessellation or a geometry shader.
+// TODO: add geometry and tessellation shaders
+pub struct GraphicsPipelineBuilder<Vdef, Vs, Fs, Ly, Sp> {
+    vertex_input: Vdef,
+    vertex_shader: Vs,
+    input_assembly: InputAssembly,
+    viewport: ViewportsState,
+    raster: Rasterization,
+    multisample: Option<Multisample>,
+    fragment_shader: Fs,
+    depth_stencil: DepthStencil,
+    blend: Blend,
+    layout: Ly,
+    render_pass: Sp,
+    cache: Option<Arc<PipelineCache>>,
+}
+
+impl GraphicsPipelineBuilder<(), (), (), (), ()> {
+    /// Builds a new empty builder.
+    ///
+    /// The default states are:
+    ///
+    /// - A triangle list without primitive restart.
+    /// - One viewport and one scissor box, both dynamic.
+    /// - The default rasterization state, which doesn't cull anything.
+    /// - Multisampling with the number of samples of the subpass.
+    /// - Depth and stencil tests disabled.
+    /// - Blending disabled.
+    ///
+    pub fn new() -> GraphicsPipelineBuilder<(), (), (), (), ()> {
+        GraphicsPipelineBuilder {
+            vertex_input: (),
+            vertex_shader: (),
+            input_assembly: InputAssembly::triangle_list(),
+            viewport: ViewportsState::Dynamic { num: 1 },
+            raster: Default::default(),
+            multisample: None,
+            fragment_shader: (),
+            depth_stencil: DepthStencil::disabled(),
+            blend: Blend::pass_through(),
+            layout: (),
+            render_pass: (),
+            cache: None,
+        }
+    }
+}
+
+impl<'a, Vdef, Vsp, Vi, Vo, Vl, Fs, Fi, Fo, Fl, L, Rp>
+    GraphicsPipelineBuilder<Vdef, VertexShaderEntryPoint<'a, Vsp, Vi, Vo, Vl>,
+                            FragmentShaderEntryPoint<'a, Fs, Fi, Fo, Fl>, &'a Arc<L>,
+                            Subpass<'a, Rp>>
+    where Vdef: VertexDefinition<Vi>,
+          L: PipelineLayout + PipelineLayoutSuperset<Vl> + PipelineLayoutSuperset<Fl>,
+          Vl: PipelineLayoutDesc, Fl: PipelineLayoutDesc,
+          Fi: ShaderInterfaceDefMatch<Vo>,
+          Fo: ShaderInterfaceDef,
+          Vo: ShaderInterfaceDef,
+          Rp: RenderPass + RenderPassDesc + RenderPassSubpassInterface<Fo>,
+{
+    /// Builds the graphics pipeline.
+    ///
+    /// This is equivalent to calling `GraphicsPipeline::new`, or `GraphicsPipeline::with_cache`
+    /// if a pipeline cache was set, with the states of the builder.
+    pub fn build(self, device: &Arc<Device>)
+                 -> Result<Arc<GraphicsPipeline<Vdef, L, Rp>>, GraphicsPipelineCreationError>
+    {
+        let multisample = match self.multisample {
+            Some(multisample) => multisample,
+            None => Multisample::samples(self.render_pass.num_samples().unwrap_or(1)),
+        };
+
+        let params = GraphicsPipelineParams {
+            vertex_input: self.vertex_input,
+            vertex_shader: self.vertex_shader,
+            input_assembly: self.input_assembly,
+            tessellation: None,
+            geometry_shader: None,
+            viewport: self.viewport,
+            raster: self.raster,
+            multisample: multisample,
+            fragment_shader: self.fragment_shader,
+            depth_stencil: self.depth_stencil,
+            blend: self.blend,
+            layout: self.layout,
+            render_pass: self.render_pass,
+        };
+
+        match self.cache {
+            Some(ref cache) => GraphicsPipeline::with_cache(device, params, cache),
+            None => GraphicsPipeline::new(device, params),
+        }
+    }
+}
+
+impl<Vdef, Vs, Fs, Ly, Sp> GraphicsPipelineBuilder<Vdef, Vs, Fs, Ly, Sp> {
+    /// Sets the vertex input.
+    #[inline]
+    pub fn vertex_input<T>(self, vertex_input: T) -> GraphicsPipelineBuilder<T, Vs, Fs, Ly, Sp> {
+        GraphicsPipelineBuilder {
+            vertex_input: vertex_input,
+            vertex_shader: self.vertex_shader,
+            input_assembly: self.input_assembly,
+            viewport: self.viewport,
+            raster: self.raster,
+            multisample: self.multisample,
+            fragment_shader: self.fragment_shader,
+            depth_stencil: self.depth_stencil,
+            blend: self.blend,
+            layout: self.layout,
+            render_pass: self.render_pass,
+            cache: self.cache,
+        }
+    }
+
+    /// Sets the vertex input to a single vertex buffer.
+    ///
+    /// You will most likely need to explicitely specify the template parameter to the type of a
+    /// vertex.
+    #[inline]
+    pub fn vertex_input_single_buffer<V>(self)
+                                         -> GraphicsPipelineBuilder<SingleBufferDefinition<V>, Vs,
+                                                                    Fs, Ly, Sp>
+    {
+        self.vertex_input(SingleBufferDefinition::new())
+    }
+
+    /// Sets the vertex shader to use.
+    #[inline]
+    pub fn vertex_shader<'a, Vsp, Vi, Vo, Vl>(self, shader: VertexShaderEntryPoint<'a, Vsp, Vi,
+                                                                                  Vo, Vl>)
+        -> GraphicsPipelineBuilder<Vdef, VertexShaderEntryPoint<'a, Vsp, Vi, Vo, Vl>, Fs, Ly, Sp>
+    {
+        GraphicsPipelineBuilder {
+            vertex_input: self.vertex_input,
+            vertex_shader: shader,
+            input_assembly: self.input_assembly,
+            viewport: self.viewport,
+            raster: self.raster,
+            multisample: self.multisample,
+            fragment_shader: self.fragment_shader,
+            depth_stencil: self.depth_stencil,
+            blend: self.blend,
+            layout: self.layout,
+            render_pass: self.render_pass,
+            cache: self.cache,
+        }
+    }
+
+    /// Sets the fragment shader to use.
+    #[inline]
+    pub fn fragment_shader<'a, Fsp, Fi, Fo, Fl>(self, shader: FragmentShaderEntryPoint<'a, Fsp,
+                                                                                      Fi, Fo, Fl>)
+        -> GraphicsPipelineBuilder<Vdef, Vs, FragmentShaderEntryPoint<'a, Fsp, Fi, Fo, Fl>, Ly, Sp>
+    {
+        GraphicsPipelineBuilder {
+            vertex_input: self.vertex_input,
+            vertex_shader: self.vertex_shader,
+            input_assembly: self.input_assembly,
+            viewport: self.viewport,
+            raster: self.raster,
+            multisample: self.multisample,
+            fragment_shader: shader,
+            depth_stencil: self.depth_stencil,
+            blend: self.blend,
+            layout: self.layout,
+            render_pass: self.render_pass,
+            cache: self.cache,
+        }
+    }
+
+    /// Sets the layout of the descriptors and push constants used by the shaders.
+    #[inline]
+    pub fn layout<'a, L>(self, layout: &'a Arc<L>)
+                         -> GraphicsPipelineBuilder<Vdef, Vs, Fs, &'a Arc<L>, Sp>
+    {
+        GraphicsPipelineBuilder {
+            vertex_input: self.vertex_input,
+            vertex_shader: self.vertex_shader,
+            input_assembly: self.input_assembly,
+            viewport: self.viewport,
+            raster: self.raster,
+            multisample: self.multisample,
+            fragment_shader: self.fragment_shader,
+            depth_stencil: self.depth_stencil,
+            blend: self.blend,
+            layout: layout,
+            render_pass: self.render_pass,
+            cache: self.cache,
+        }
+    }
+
+    /// Sets the subpass of the render pass that the pipeline will run on.
+    #[inline]
+    pub fn render_pass<'a, Rp>(self, subpass: Subpass<'a, Rp>)
+                               -> GraphicsPipelineBuilder<Vdef, Vs, Fs, Ly, Subpass<'a, Rp>>
+    {
+        GraphicsPipelineBuilder {
+            vertex_input: self.vertex_input,
+            vertex_shader: self.vertex_shader,
+            input_assembly: self.input_assembly,
+            viewport: self.viewport,
+            raster: self.raster,
+            multisample: self.multisample,
+            fragment_shader: self.fragment_shader,
+            depth_stencil: self.depth_stencil,
+            blend: self.blend,
+            layout: self.layout,
+            render_pass: subpass,
+            cache: self.cache,
+        }
+    }
+
+    /// Sets the whole input assembly state.
+    #[inline]
+    pub fn input_assembly(mut self, input_assembly: InputAssembly) -> Self {
+        self.input_assembly = input_assembly;
+        self
+    }
+
+    /// Sets how vertices are assembled into primitives. Disables primitive restart.
+    #[inline]
+    pub fn primitive_topology(mut self, topology: PrimitiveTopology) -> Self {
+        self.input_assembly = InputAssembly::new(topology);
+        self
+    }
+
+    /// Enables or disables primitive restart. Must be called after setting the topology.
+    #[inline]
+    pub fn primitive_restart(mut self, enabled: bool) -> Self {
+        self.input_assembly.primitive_restart_enable = enabled;
+        self
+    }
+
+    /// Shortcut for `primitive_topology(PrimitiveTopology::PointList)`.
+    #[inline]
+    pub fn point_list(self) -> Self {
+        self.primitive_topology(PrimitiveTopology::PointList)
+    }
+
+    /// Shortcut for `primitive_topology(PrimitiveTopology::LineList)`.
+    #[inline]
+    pub fn line_list(self) -> Self {
+        self.primitive_topology(PrimitiveTopology::LineList)
+    }
+
+    /// Shortcut for `primitive_topology(PrimitiveTopology::LineStrip)`.
+    #[inline]
+    pub fn line_strip(self) -> Self {
+        self.primitive_topology(PrimitiveTopology::LineStrip)
+    }
+
+    /// Shortcut for `primitive_topology(PrimitiveTopology::TriangleList)`.
+    #[inline]
+    pub fn triangle_list(self) -> Self {
+        self.primitive_topology(PrimitiveTopology::TriangleList)
+    }
+
+    /// Shortcut for `primitive_topology(PrimitiveTopology::TriangleStrip)`.
+    #[inline]
+    pub fn triangle_strip(self) -> Self {
+        self.primitive_topology(PrimitiveTopology::TriangleStrip)
+    }
+
+    /// Shortcut for `primitive_topology(PrimitiveTopology::TriangleFan)`.
+    #[inline]
+    pub fn triangle_fan(self) -> Self {
+        self.primitive_topology(PrimitiveTopology::TriangleFan)
+    }
+
+    /// Sets the whole viewports state.
+    #[inline]
+    pub fn viewports_state(mut self, viewport: ViewportsState) -> Self {
+        self.viewport = viewport;
+        self
+    }
+
+    /// Sets fixed viewports, with scissor boxes that cover the whole framebuffer.
+    #[inline]
+    pub fn viewports<I>(self, viewports: I) -> Self
+        where I: IntoIterator<Item = Viewport>
+    {
+        self.viewports_scissors(viewports.into_iter().map(|v| (v, Scissor::irrelevant())))
+    }
+
+    /// Sets fixed viewports and scissor boxes.
+    #[inline]
+    pub fn viewports_scissors<I>(self, data: I) -> Self
+        where I: IntoIterator<Item = (Viewport, Scissor)>
+    {
+        self.viewports_state(ViewportsState::Fixed { data: data.into_iter().collect() })
+    }
+
+    /// Sets `num` dynamic viewports, with fixed scissor boxes that cover the whole framebuffer.
+    #[inline]
+    pub fn viewports_dynamic_scissors_irrelevant(self, num: u32) -> Self {
+        let scissors = (0 .. num).map(|_| Scissor::irrelevant()).collect();
+        self.viewports_state(ViewportsState::DynamicViewports { scissors: scissors })
+    }
+
+    /// Sets `num` viewports and scissor boxes that are both dynamic.
+    #[inline]
+    pub fn viewports_scissors_dynamic(self, num: u32) -> Self {
+        self.viewports_state(ViewportsState::Dynamic { num: num })
+    }
+
+    /// Sets the whole rasterization state.
+    #[inline]
+    pub fn raster(mut self, raster: Rasterization) -> Self {
+        self.raster = raster;
+        self
+    }
+
+    /// Sets whether front faces, back faces, both or none are discarded.
+    #[inline]
+    pub fn cull_mode(mut self, cull_mode: CullMode) -> Self {
+        self.raster.cull_mode = cull_mode;
+        self
+    }
+
+    /// Shortcut for `cull_mode(CullMode::Back)`.
+    #[inline]
+    pub fn cull_mode_back(self) -> Self {
+        self.cull_mode(CullMode::Back)
+    }
+
+    /// Shortcut for `cull_mode(CullMode::Front)`.
+    #[inline]
+    pub fn cull_mode_front(self) -> Self {
+        self.cull_mode(CullMode::Front)
+    }
+
+    /// Sets which triangle orientation corresponds to the front of the triangle.
+    #[inline]
+    pub fn front_face(mut self, front_face: FrontFace) -> Self {
+        self.raster.front_face = front_face;
+        self
+    }
+
+    /// Sets how triangles are turned into fragments. Anything else than `Fill` requires the
+    /// `fill_mode_non_solid` feature.
+    #[inline]
+    pub fn polygon_mode(mut self, polygon_mode: PolygonMode) -> Self {
+        self.raster.polygon_mode = polygon_mode;
+        self
+    }
+
+    /// Sets the width of the lines. Anything else than `1.0` requires the `wide_lines` feature.
+    #[inline]
+    pub fn line_width(mut self, width: f32) -> Self {
+        self.raster.line_width = Some(width);
+        self
+    }
+
+    /// Makes the line width dynamic. It will have to be set in the `DynamicState` when drawing.
+    #[inline]
+    pub fn line_width_dynamic(mut self) -> Self {
+        self.raster.line_width = None;
+        self
+    }
+
+    /// Sets the multisample state.
+    ///
+    /// If you don't call this method, the number of samples of the subpass is used with sample
+    /// shading disabled.
+    #[inline]
+    pub fn multisample(mut self, multisample: Multisample) -> Self {
+        self.multisample = Some(multisample);
+        self
+    }
+
+    /// Sets the whole depth and stencil state.
+    #[inline]
+    pub fn depth_stencil(mut self, depth_stencil: DepthStencil) -> Self {
+        self.depth_stencil = depth_stencil;
+        self
+    }
+
+    /// Shortcut for `depth_stencil(DepthStencil::disabled())`.
+    #[inline]
+    pub fn depth_stencil_disabled(self) -> Self {
+        self.depth_stencil(DepthStencil::disabled())
+    }
+
+    /// Shortcut for `depth_stencil(DepthStencil::simple_depth_test())`.
+    #[inline]
+    pub fn depth_stencil_simple_depth(self) -> Self {
+        self.depth_stencil(DepthStencil::simple_depth_test())
+    }
+
+    /// Sets the whole blending state.
+    #[inline]
+    pub fn blend(mut self, blend: Blend) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    /// Uses the same blending for all the color attachments.
+    #[inline]
+    pub fn blend_collective(mut self, blend: AttachmentBlend) -> Self {
+        self.blend.attachments = AttachmentsBlend::Collective(blend);
+        self
+    }
+
+    /// Uses a different blending for each color attachment. Requires the `independent_blend`
+    /// feature.
+    #[inline]
+    pub fn blend_individual<I>(mut self, blend: I) -> Self
+        where I: IntoIterator<Item = AttachmentBlend>
+    {
+        self.blend.attachments = AttachmentsBlend::Individual(blend.into_iter().collect());
+        self
+    }
+
+    /// Shortcut for `blend_collective(AttachmentBlend::pass_through())`.
+    #[inline]
+    pub fn blend_pass_through(self) -> Self {
+        self.blend_collective(AttachmentBlend::pass_through())
+    }
+
+    /// Shortcut for `blend_collective(AttachmentBlend::alpha_blending())`.
+    #[inline]
+    pub fn blend_alpha(self) -> Self {
+        self.blend_collective(AttachmentBlend::alpha_blending())
+    }
+
+    /// Sets the pipeline cache to look up and store the pipeline in.
+    #[inline]
+    pub fn pipeline_cache(mut self, cache: &Arc<PipelineCache>) -> Self {
+        self.cache = Some(cache.clone());
+        self
+    }
+}
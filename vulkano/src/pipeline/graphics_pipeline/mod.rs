@@ -22,10 +22,14 @@ use descriptor::pipeline_layout::PipelineLayoutSuperset;
 use descriptor::pipeline_layout::EmptyPipeline;
 use framebuffer::RenderPass;
 use framebuffer::RenderPassDesc;
+use framebuffer::FragmentOutputMismatchError;
 use framebuffer::RenderPassSubpassInterface;
 use framebuffer::Subpass;
+use instance::debug::ObjectType;
+use instrumentation::ResourceKind;
 use Error;
 use OomError;
+use VulkanError;
 use VulkanObject;
 use VulkanPointers;
 use check_errors;
@@ -33,12 +37,16 @@ use vk;
 
 use pipeline::blend::Blend;
 use pipeline::blend::AttachmentsBlend;
+use pipeline::cache::PipelineCache;
 use pipeline::depth_stencil::Compare;
 use pipeline::depth_stencil::DepthStencil;
 use pipeline::depth_stencil::DepthBounds;
+use pipeline::depth_stencil::Stencil;
+use pipeline::depth_stencil::check_depth_bounds;
 use pipeline::input_assembly::InputAssembly;
 use pipeline::input_assembly::PrimitiveTopology;
 use pipeline::multisample::Multisample;
+use pipeline::raster::ConservativeRasterizationMode;
 use pipeline::raster::DepthBiasControl;
 use pipeline::raster::PolygonMode;
 use pipeline::raster::Rasterization;
@@ -52,8 +60,12 @@ use pipeline::shader::GeometryShaderEntryPoint;
 use pipeline::shader::FragmentShaderEntryPoint;
 use pipeline::vertex::Definition as VertexDefinition;
 use pipeline::vertex::IncompatibleVertexDefinitionError;
+use pipeline::vertex::InputRate;
 use pipeline::viewport::ViewportsState;
 
+pub use self::builder::GraphicsPipelineBuilder;
+
+mod builder;
 mod tests;
 
 /// Description of a `GraphicsPipeline`.
@@ -92,7 +104,7 @@ pub struct GraphicsPipelineParams<'a, Vdef, Vsp, Vi, Vo, Vl, Tcs, Tci, Tco, Tcl,
     /// Describes how the implementation determines which pixels are covered by the shape.
     pub raster: Rasterization,
 
-    // TODO: document
+    /// Describes how the rasterizer generates and resolves multiple samples per pixel.
     pub multisample: Multisample,
 
     /// The entry point of the fragment shader that will be run on the pixels.
@@ -135,6 +147,8 @@ pub struct GraphicsPipeline<VertexDefinition, Layout, RenderP> {
     render_pass_subpass: u32,
 
     vertex_definition: VertexDefinition,
+    // Stride and input rate of each vertex buffer binding, in the order of the bindings.
+    vertex_bindings: SmallVec<[(usize, InputRate); 8]>,
 
     dynamic_line_width: bool,
     dynamic_viewport: bool,
@@ -173,6 +187,48 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
               Fo: ShaderInterfaceDef,
               Vo: ShaderInterfaceDef,
               Rp: RenderPassSubpassInterface<Fo>,
+    {
+        GraphicsPipeline::new_vertex_fragment(device, params, None)
+    }
+
+    /// Same as `new`, but looks up and stores the pipeline in a pipeline cache.
+    ///
+    /// Creating a pipeline that is already in the cache, for example after reloading a shader
+    /// that didn't change, is much faster.
+    #[inline]
+    pub fn with_cache<'a, Vsp, Vi, Vo, Vl, Fs, Fi, Fo, Fl>
+              (device: &Arc<Device>,
+               params: GraphicsPipelineParams<'a, Vdef, Vsp, Vi, Vo, Vl, (), (), (), EmptyPipeline,
+                                              (), (), (), EmptyPipeline, (), (), (), EmptyPipeline,
+                                              Fs, Fi, Fo, Fl, L, Rp>,
+               cache: &Arc<PipelineCache>)
+              -> Result<Arc<GraphicsPipeline<Vdef, L, Rp>>, GraphicsPipelineCreationError>
+        where Vdef: VertexDefinition<Vi>,
+              L: PipelineLayout + PipelineLayoutSuperset<Vl> + PipelineLayoutSuperset<Fl>,
+              Vl: PipelineLayoutDesc, Fl: PipelineLayoutDesc,
+              Fi: ShaderInterfaceDefMatch<Vo>,
+              Fo: ShaderInterfaceDef,
+              Vo: ShaderInterfaceDef,
+              Rp: RenderPassSubpassInterface<Fo>,
+    {
+        GraphicsPipeline::new_vertex_fragment(device, params, Some(cache))
+    }
+
+    // Implementation of `new` and `with_cache`.
+    fn new_vertex_fragment<'a, Vsp, Vi, Vo, Vl, Fs, Fi, Fo, Fl>
+              (device: &Arc<Device>,
+               params: GraphicsPipelineParams<'a, Vdef, Vsp, Vi, Vo, Vl, (), (), (), EmptyPipeline,
+                                              (), (), (), EmptyPipeline, (), (), (), EmptyPipeline,
+                                              Fs, Fi, Fo, Fl, L, Rp>,
+               cache: Option<&Arc<PipelineCache>>)
+              -> Result<Arc<GraphicsPipeline<Vdef, L, Rp>>, GraphicsPipelineCreationError>
+        where Vdef: VertexDefinition<Vi>,
+              L: PipelineLayout + PipelineLayoutSuperset<Vl> + PipelineLayoutSuperset<Fl>,
+              Vl: PipelineLayoutDesc, Fl: PipelineLayoutDesc,
+              Fi: ShaderInterfaceDefMatch<Vo>,
+              Fo: ShaderInterfaceDef,
+              Vo: ShaderInterfaceDef,
+              Rp: RenderPassSubpassInterface<Fo>,
     {
         if let Err(err) = params.fragment_shader.input().matches(params.vertex_shader.output()) {
            return Err(GraphicsPipelineCreationError::VertexFragmentStagesMismatch(err));
@@ -180,7 +236,7 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
 
         GraphicsPipeline::new_inner::<_, _, _, _, (), (), (), EmptyPipeline, (), (), (),
                                       EmptyPipeline, (), (), (), EmptyPipeline, _, _, _, _>
-                                      (device, params)
+                                      (device, params, cache)
     }
 
     /// Builds a new graphics pipeline object with a geometry shader.
@@ -224,7 +280,7 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
             }
         }
 
-        GraphicsPipeline::new_inner(device, params)
+        GraphicsPipeline::new_inner(device, params, None)
     }
 
     /// Builds a new graphics pipeline object with tessellation shaders.
@@ -276,7 +332,7 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
             }
         }
 
-        GraphicsPipeline::new_inner(device, params)
+        GraphicsPipeline::new_inner(device, params, None)
     }
 
     fn new_inner<'a, Vsp, Vi, Vo, Vl, Tcs, Tci, Tco, Tcl, Tes, Tei, Teo, Tel, Gsp, Gi, Go, Gl, Fs,
@@ -284,7 +340,8 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
                 (device: &Arc<Device>,
                  params: GraphicsPipelineParams<'a, Vdef, Vsp, Vi, Vo, Vl, Tcs, Tci, Tco, Tcl, Tes,
                                                 Tei, Teo, Tel, Gsp, Gi, Go, Gl, Fs, Fi, Fo, Fl, L,
-                                                Rp>)
+                                                Rp>,
+                 cache: Option<&Arc<PipelineCache>>)
                  -> Result<Arc<GraphicsPipeline<Vdef, L, Rp>>, GraphicsPipelineCreationError>
         where Vdef: VertexDefinition<Vi>,
               Fo: ShaderInterfaceDef,
@@ -333,10 +390,11 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
         }
 
         // Check that the subpass can accept the output of the fragment shader.
-        if !params.render_pass.render_pass().is_compatible_with(params.render_pass.index(),
-                                                                params.fragment_shader.output())
+        if let Err(err) = params.render_pass.render_pass()
+                                .check_compatibility(params.render_pass.index(),
+                                                     params.fragment_shader.output())
         {
-            return Err(GraphicsPipelineCreationError::FragmentShaderRenderPassIncompatible);
+            return Err(GraphicsPipelineCreationError::FragmentShaderRenderPassIncompatible(err));
         }
 
         // Will contain the list of dynamic states. Filled throughout this function.
@@ -414,6 +472,7 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
         };
 
         // Vertex bindings.
+        let mut vertex_bindings = SmallVec::new();
         let (binding_descriptions, attribute_descriptions) = {
             let (buffers_iter, attribs_iter) = try!(params.vertex_input.definition(params.vertex_shader.input_definition()));
 
@@ -432,6 +491,7 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
                     stride: stride as u32,
                     inputRate: rate as u32,
                 });
+                vertex_bindings.push((stride, rate));
             }
 
             let mut attribute_descriptions = SmallVec::<[_; 8]>::new();
@@ -494,6 +554,12 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
             });
         }
 
+        if params.input_assembly.topology.has_adjacency() &&
+           !device.enabled_features().geometry_shader
+        {
+            return Err(GraphicsPipelineCreationError::GeometryShaderFeatureNotEnabled);
+        }
+
         // TODO: should check from the tess eval shader instead of the input assembly
         if let Some(ref gs) = params.geometry_shader {
             if !gs.primitives().matches(params.input_assembly.topology) {
@@ -515,7 +581,7 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
 
         let tessellation = match params.input_assembly.topology {
             PrimitiveTopology::PatchList { vertices_per_patch } => {
-                if params.tessellation.is_none() {
+                if params.tessellation.is_none() || vertices_per_patch == 0 {
                     return Err(GraphicsPipelineCreationError::InvalidPrimitiveTopology);
                 }
                 if vertices_per_patch > device.physical_device().limits()
@@ -608,6 +674,11 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
             if line_width != 1.0 && !device.enabled_features().wide_lines {
                 return Err(GraphicsPipelineCreationError::WideLinesFeatureNotEnabled);
             }
+
+            let range = device.physical_device().limits().line_width_range();
+            if line_width != 1.0 && (line_width < range[0] || line_width > range[1]) {
+                return Err(GraphicsPipelineCreationError::LineWidthOutOfRange);
+            }
         } else {
             dynamic_states.push(vk::DYNAMIC_STATE_LINE_WIDTH);
         }
@@ -639,9 +710,47 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
             return Err(GraphicsPipelineCreationError::FillModeNonSolidFeatureNotEnabled);
         }
 
+        let conservative = match params.raster.conservative {
+            Some(conservative) => {
+                if !device.loaded_extensions().ext_conservative_rasterization {
+                    return Err(GraphicsPipelineCreationError::ConservativeRasterizationExtensionNotEnabled);
+                }
+
+                if !(conservative.extra_primitive_overestimation_size >= 0.0) {
+                    return Err(GraphicsPipelineCreationError::InvalidExtraPrimitiveOverestimationSize);
+                }
+
+                let properties = conservative_rasterization_properties(device);
+
+                if let Some(ref properties) = properties {
+                    let max = properties.maxExtraPrimitiveOverestimationSize;
+                    if conservative.extra_primitive_overestimation_size > max {
+                        return Err(GraphicsPipelineCreationError::ExtraPrimitiveOverestimationSizeOutOfRange);
+                    }
+                }
+
+                if conservative.mode == ConservativeRasterizationMode::Underestimate {
+                    let supported = properties.map(|p| p.primitiveUnderestimation != 0)
+                                              .unwrap_or(false);
+                    if !supported {
+                        return Err(GraphicsPipelineCreationError::PrimitiveUnderestimationNotSupported);
+                    }
+                }
+
+                Some(vk::PipelineRasterizationConservativeStateCreateInfoEXT {
+                    sType: vk::STRUCTURE_TYPE_PIPELINE_RASTERIZATION_CONSERVATIVE_STATE_CREATE_INFO_EXT,
+                    pNext: ptr::null(),
+                    flags: 0,   // reserved
+                    conservativeRasterizationMode: conservative.mode as u32,
+                    extraPrimitiveOverestimationSize: conservative.extra_primitive_overestimation_size,
+                })
+            },
+            None => None,
+        };
+
         let rasterization = vk::PipelineRasterizationStateCreateInfo {
             sType: vk::STRUCTURE_TYPE_PIPELINE_RASTERIZATION_STATE_CREATE_INFO,
-            pNext: ptr::null(),
+            pNext: conservative.as_ref().map(|c| c as *const _ as *const _).unwrap_or(ptr::null()),
             flags: 0,   // reserved
             depthClampEnable: if params.raster.depth_clamp { vk::TRUE } else { vk::FALSE },
             rasterizerDiscardEnable: if params.raster.rasterizer_discard { vk::TRUE } else { vk::FALSE },
@@ -655,17 +764,44 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
             lineWidth: params.raster.line_width.unwrap_or(1.0),
         };
 
-        assert!(params.multisample.rasterization_samples >= 1);
-        // FIXME: check that rasterization_samples is equal to what's in the renderpass
-        if let Some(s) = params.multisample.sample_shading { assert!(s >= 0.0 && s <= 1.0); }
+        let samples = params.multisample.rasterization_samples;
+        if samples == 0 || samples > 64 || !samples.is_power_of_two() {
+            return Err(GraphicsPipelineCreationError::InvalidRasterizationSamples {
+                obtained: samples,
+            });
+        }
+
+        if let Some(expected) = params.render_pass.num_samples() {
+            if expected != samples {
+                return Err(GraphicsPipelineCreationError::RasterizationSamplesMismatch {
+                    expected: expected,
+                    obtained: samples,
+                });
+            }
+        }
+
+        if let Some(s) = params.multisample.sample_shading {
+            if !device.enabled_features().sample_rate_shading {
+                return Err(GraphicsPipelineCreationError::SampleRateShadingFeatureNotEnabled);
+            }
+
+            if !(s >= 0.0 && s <= 1.0) {
+                return Err(GraphicsPipelineCreationError::MinSampleShadingOutOfRange);
+            }
+        }
+
+        if params.multisample.alpha_to_one && !device.enabled_features().alpha_to_one {
+            return Err(GraphicsPipelineCreationError::AlphaToOneFeatureNotEnabled);
+        }
+
         let multisample = vk::PipelineMultisampleStateCreateInfo {
             sType: vk::STRUCTURE_TYPE_PIPELINE_MULTISAMPLE_STATE_CREATE_INFO,
             pNext: ptr::null(),
             flags: 0,   // reserved
-            rasterizationSamples: params.multisample.rasterization_samples,
+            rasterizationSamples: samples,
             sampleShadingEnable: if params.multisample.sample_shading.is_some() { vk::TRUE } else { vk::FALSE },
             minSampleShading: params.multisample.sample_shading.unwrap_or(1.0),
-            pSampleMask: ptr::null(),   //params.multisample.sample_mask.as_ptr(),     // FIXME:
+            pSampleMask: params.multisample.sample_mask.as_ptr(),
             alphaToCoverageEnable: if params.multisample.alpha_to_coverage { vk::TRUE } else { vk::FALSE },
             alphaToOneEnable: if params.multisample.alpha_to_one { vk::TRUE } else { vk::FALSE },
         };
@@ -678,6 +814,10 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
                         return Err(GraphicsPipelineCreationError::DepthBoundsFeatureNotEnabled);
                     }
 
+                    if check_depth_bounds(range.start, range.end).is_err() {
+                        return Err(GraphicsPipelineCreationError::DepthBoundsOutOfRange);
+                    }
+
                     (vk::TRUE, range.start, range.end)
                 },
                 DepthBounds::Dynamic => {
@@ -731,14 +871,21 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
                 return Err(GraphicsPipelineCreationError::NoDepthAttachment);
             }
 
-            if (!params.depth_stencil.stencil_front.always_keep() ||
-                !params.depth_stencil.stencil_back.always_keep()) &&
-                !params.render_pass.has_stencil()
-            {
+            let stencil_test = !params.depth_stencil.stencil_front.is_disabled() ||
+                               !params.depth_stencil.stencil_back.is_disabled();
+            if stencil_test && !params.render_pass.has_stencil() {
                 return Err(GraphicsPipelineCreationError::NoStencilAttachment);
             }
 
-            // FIXME: stencil writability
+            // The stencil attachment is written if any of the operations that can be triggered
+            // isn't `Keep`, unless the write mask is known to be 0.
+            let stencil_written = |s: &Stencil| !s.always_keep() && s.write_mask != Some(0);
+            if (stencil_written(&params.depth_stencil.stencil_front) ||
+                stencil_written(&params.depth_stencil.stencil_back)) &&
+                !params.render_pass.has_writable_stencil()
+            {
+                return Err(GraphicsPipelineCreationError::NoStencilAttachment);
+            }
 
             vk::PipelineDepthStencilStateCreateInfo {
                 sType: vk::STRUCTURE_TYPE_PIPELINE_DEPTH_STENCIL_STATE_CREATE_INFO,
@@ -751,9 +898,7 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
                                   else { vk::FALSE },
                 depthCompareOp: params.depth_stencil.depth_compare as u32,
                 depthBoundsTestEnable: db.0,
-                stencilTestEnable: if params.depth_stencil.stencil_front.always_keep() &&
-                                      params.depth_stencil.stencil_back.always_keep()
-                                      { vk::FALSE } else { vk::TRUE },
+                stencilTestEnable: if stencil_test { vk::TRUE } else { vk::FALSE },
                 front: vk::StencilOpState {
                     failOp: params.depth_stencil.stencil_front.fail_op as u32,
                     passOp: params.depth_stencil.stencil_front.pass_op as u32,
@@ -782,6 +927,10 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
 
             match params.blend.attachments {
                 AttachmentsBlend::Collective(blend) => {
+                    if blend.uses_dual_source() && !device.enabled_features().dual_src_blend {
+                        return Err(GraphicsPipelineCreationError::DualSrcBlendFeatureNotEnabled);
+                    }
+
                     (0 .. num_atch).map(|_| blend.clone().into()).collect()
                 },
                 AttachmentsBlend::Individual(blend) => {
@@ -793,6 +942,12 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
                         return Err(GraphicsPipelineCreationError::IndependentBlendFeatureNotEnabled);
                     }
 
+                    if blend.iter().any(|b| b.uses_dual_source()) &&
+                       !device.enabled_features().dual_src_blend
+                    {
+                        return Err(GraphicsPipelineCreationError::DualSrcBlendFeatureNotEnabled);
+                    }
+
                     blend.iter().map(|b| b.clone().into()).collect()
                 },
             }
@@ -859,17 +1014,22 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
             };
 
             let mut output = mem::uninitialized();
-            try!(check_errors(vk.CreateGraphicsPipelines(device.internal_object(), 0,
+            let _cache_lock = cache.map(|c| c.lock_shared());
+            let cache = cache.map(|c| c.internal_object()).unwrap_or(0);
+            try!(check_errors(vk.CreateGraphicsPipelines(device.internal_object(), cache,
                                                          1, &infos, ptr::null(), &mut output)));
             output
         };
 
+        device.resource_tracker().created(ResourceKind::Pipeline, pipeline);
+
         Ok(Arc::new(GraphicsPipeline {
             device: device.clone(),
             pipeline: pipeline,
             layout: params.layout.clone(),
 
             vertex_definition: params.vertex_input,
+            vertex_bindings: vertex_bindings,
 
             render_pass: params.render_pass.render_pass().clone(),
             render_pass_subpass: params.render_pass.index(),
@@ -889,12 +1049,42 @@ impl<Vdef, L, Rp> GraphicsPipeline<Vdef, L, Rp>
     }
 }
 
+impl GraphicsPipeline<(), (), ()> {
+    /// Starts the building process of a graphics pipeline. Returns a builder object that you can
+    /// fill with the various parameters.
+    #[inline]
+    pub fn start() -> GraphicsPipelineBuilder<(), (), (), (), ()> {
+        GraphicsPipelineBuilder::new()
+    }
+}
+
 impl<Mv, L, Rp> GraphicsPipeline<Mv, L, Rp> {
     /// Returns the vertex definition used in the constructor.
     #[inline]
     pub fn vertex_definition(&self) -> &Mv {
         &self.vertex_definition
     }
+
+    /// Returns the stride and the input rate of each vertex buffer binding of the pipeline.
+    #[inline]
+    pub fn vertex_bindings(&self) -> &[(usize, InputRate)] {
+        &self.vertex_bindings
+    }
+
+    /// Gives a name to the pipeline, that debugging tools and validation layers can display.
+    ///
+    /// Does nothing if the `VK_EXT_debug_marker` extension isn't enabled on the device.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `name` contains a nul character.
+    ///
+    #[inline]
+    pub fn set_object_name(&self, name: &str) -> Result<(), OomError> {
+        unsafe {
+            self.device.set_object_name_raw(ObjectType::Pipeline, self.pipeline as u64, name)
+        }
+    }
 }
 
 impl<Mv, L, Rp> GraphicsPipeline<Mv, L, Rp>
@@ -988,10 +1178,44 @@ impl<Mv, L, Rp> Drop for GraphicsPipeline<Mv, L, Rp> {
         unsafe {
             let vk = self.device.pointers();
             vk.DestroyPipeline(self.device.internal_object(), self.pipeline, ptr::null());
+            self.device.resource_tracker().destroyed(ResourceKind::Pipeline, self.pipeline);
         }
     }
 }
 
+/// Queries the conservative rasterization properties of the physical device.
+///
+/// The properties are only reachable through `vkGetPhysicalDeviceProperties2KHR`, which the
+/// conservative rasterization extension depends on. Returns `None` if the instance didn't load it.
+fn conservative_rasterization_properties(device: &Device)
+    -> Option<vk::PhysicalDeviceConservativeRasterizationPropertiesEXT>
+{
+    let instance = device.instance();
+    if !instance.loaded_extensions().khr_get_physical_device_properties2 {
+        return None;
+    }
+
+    unsafe {
+        let vk_i = instance.pointers();
+
+        let mut conservative: vk::PhysicalDeviceConservativeRasterizationPropertiesEXT =
+            mem::zeroed();
+        conservative.sType =
+            vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_CONSERVATIVE_RASTERIZATION_PROPERTIES_EXT;
+
+        let mut properties = vk::PhysicalDeviceProperties2KHR {
+            sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_PROPERTIES_2_KHR,
+            pNext: &mut conservative as *mut _ as *mut _,
+            properties: mem::uninitialized(),
+        };
+
+        vk_i.GetPhysicalDeviceProperties2KHR(device.physical_device().internal_object(),
+                                             &mut properties);
+        conservative.pNext = ptr::null_mut();
+        Some(conservative)
+    }
+}
+
 /// Error that can happen when creating a graphics pipeline.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum GraphicsPipelineCreationError {
@@ -1027,7 +1251,7 @@ pub enum GraphicsPipelineCreationError {
 
     /// The output of the fragment shader is not compatible with what the render pass subpass
     /// expects.
-    FragmentShaderRenderPassIncompatible,
+    FragmentShaderRenderPassIncompatible(FragmentOutputMismatchError),
 
     /// The vertex definition is not compatible with the input of the vertex shader.
     IncompatibleVertexDefinition(IncompatibleVertexDefinitionError),
@@ -1094,6 +1318,24 @@ pub enum GraphicsPipelineCreationError {
     /// The `wide_lines` feature must be enabled in order to use a line width superior to 1.0.
     WideLinesFeatureNotEnabled,
 
+    /// The line width is outside of the `line_width_range` limit.
+    LineWidthOutOfRange,
+
+    /// The `ext_conservative_rasterization` extension must be enabled in order to use
+    /// conservative rasterization.
+    ConservativeRasterizationExtensionNotEnabled,
+
+    /// The extra primitive overestimation size of conservative rasterization is negative.
+    InvalidExtraPrimitiveOverestimationSize,
+
+    /// The extra primitive overestimation size of conservative rasterization exceeds the
+    /// `maxExtraPrimitiveOverestimationSize` limit.
+    ExtraPrimitiveOverestimationSizeOutOfRange,
+
+    /// Underestimating conservative rasterization was requested, but the implementation doesn't
+    /// support the `primitiveUnderestimation` property.
+    PrimitiveUnderestimationNotSupported,
+
     /// The `depth_clamp` feature must be enabled in order to use depth clamping.
     DepthClampFeatureNotEnabled,
 
@@ -1108,13 +1350,18 @@ pub enum GraphicsPipelineCreationError {
     /// The `depth_bounds` feature must be enabled in order to use depth bounds testing.
     DepthBoundsFeatureNotEnabled,
 
+    /// The fixed depth bounds must be between 0.0 and 1.0, and the minimum must not be greater
+    /// than the maximum.
+    DepthBoundsOutOfRange,
+
     /// The requested stencil test is invalid.
     WrongStencilState,
 
     /// The primitives topology does not match what the geometry shader expects.
     TopologyNotMatchingGeometryShader,
 
-    /// The `geometry_shader` feature must be enabled in order to use geometry shaders.
+    /// The `geometry_shader` feature must be enabled in order to use geometry shaders or
+    /// primitive topologies with adjacency.
     GeometryShaderFeatureNotEnabled,
 
     /// The `tessellation_shader` feature must be enabled in order to use tessellation shaders.
@@ -1131,6 +1378,10 @@ pub enum GraphicsPipelineCreationError {
     /// The `logic_op` feature must be enabled in order to use logic operations.
     LogicOpFeatureNotEnabled,
 
+    /// The `dual_src_blend` feature must be enabled in order to use blend factors that read
+    /// the second color output of the fragment shader.
+    DualSrcBlendFeatureNotEnabled,
+
     /// The depth test requires a depth attachment but render pass has no depth attachment, or
     /// depth writing is enabled and the depth attachment is read-only.
     NoDepthAttachment,
@@ -1140,11 +1391,35 @@ pub enum GraphicsPipelineCreationError {
     NoStencilAttachment,
 
     /// Tried to use a patch list without a tessellation shader, or a non-patch-list with a
-    /// tessellation shader.
+    /// tessellation shader, or a patch list with zero vertices per patch.
     InvalidPrimitiveTopology,
 
     /// The `maxTessellationPatchSize` limit was exceeded.
     MaxTessellationPatchSizeExceeded,
+
+    /// The number of rasterization samples is not a power of two between 1 and 64.
+    InvalidRasterizationSamples {
+        /// Value that was passed.
+        obtained: u32,
+    },
+
+    /// The number of rasterization samples doesn't match the number of samples of the
+    /// attachments of the subpass.
+    RasterizationSamplesMismatch {
+        /// Number of samples of the attachments of the subpass.
+        expected: u32,
+        /// Value that was passed.
+        obtained: u32,
+    },
+
+    /// The `sample_rate_shading` feature must be enabled in order to use sample shading.
+    SampleRateShadingFeatureNotEnabled,
+
+    /// The minimum sample shading value must be between 0.0 and 1.0.
+    MinSampleShadingOutOfRange,
+
+    /// The `alpha_to_one` feature must be enabled in order to use alpha-to-one.
+    AlphaToOneFeatureNotEnabled,
 }
 
 impl error::Error for GraphicsPipelineCreationError {
@@ -1181,7 +1456,7 @@ impl error::Error for GraphicsPipelineCreationError {
             GraphicsPipelineCreationError::IncompatiblePipelineLayout => {
                 "the pipeline layout is not compatible with what the shaders expect"
             },
-            GraphicsPipelineCreationError::FragmentShaderRenderPassIncompatible => {
+            GraphicsPipelineCreationError::FragmentShaderRenderPassIncompatible(_) => {
                 "the output of the fragment shader is not compatible with what the render pass \
                  subpass expects"
             },
@@ -1222,6 +1497,24 @@ impl error::Error for GraphicsPipelineCreationError {
                 "the `wide_lines` feature must be enabled in order to use a line width \
                  superior to 1.0"
             },
+            GraphicsPipelineCreationError::LineWidthOutOfRange => {
+                "the line width is outside of the `line_width_range` limit"
+            },
+            GraphicsPipelineCreationError::ConservativeRasterizationExtensionNotEnabled => {
+                "the `ext_conservative_rasterization` extension must be enabled in order to use \
+                 conservative rasterization"
+            },
+            GraphicsPipelineCreationError::InvalidExtraPrimitiveOverestimationSize => {
+                "the extra primitive overestimation size of conservative rasterization is negative"
+            },
+            GraphicsPipelineCreationError::ExtraPrimitiveOverestimationSizeOutOfRange => {
+                "the extra primitive overestimation size of conservative rasterization exceeds the \
+                 `maxExtraPrimitiveOverestimationSize` limit"
+            },
+            GraphicsPipelineCreationError::PrimitiveUnderestimationNotSupported => {
+                "underestimating conservative rasterization was requested, but the implementation \
+                 doesn't support the `primitiveUnderestimation` property"
+            },
             GraphicsPipelineCreationError::DepthClampFeatureNotEnabled => {
                 "the `depth_clamp` feature must be enabled in order to use depth clamping"
             },
@@ -1236,6 +1529,10 @@ impl error::Error for GraphicsPipelineCreationError {
             GraphicsPipelineCreationError::DepthBoundsFeatureNotEnabled => {
                 "the `depth_bounds` feature must be enabled in order to use depth bounds testing"
             },
+            GraphicsPipelineCreationError::DepthBoundsOutOfRange => {
+                "the fixed depth bounds are not between 0.0 and 1.0, or the minimum is greater \
+                 than the maximum"
+            },
             GraphicsPipelineCreationError::WrongStencilState => {
                 "the requested stencil test is invalid"
             },
@@ -1243,7 +1540,8 @@ impl error::Error for GraphicsPipelineCreationError {
                 "the primitives topology does not match what the geometry shader expects"
             },
             GraphicsPipelineCreationError::GeometryShaderFeatureNotEnabled => {
-                "the `geometry_shader` feature must be enabled in order to use geometry shaders"
+                "the `geometry_shader` feature must be enabled in order to use geometry shaders \
+                 or primitive topologies with adjacency"
             },
             GraphicsPipelineCreationError::TessellationShaderFeatureNotEnabled => {
                 "the `tessellation_shader` feature must be enabled in order to use tessellation \
@@ -1260,6 +1558,10 @@ impl error::Error for GraphicsPipelineCreationError {
             GraphicsPipelineCreationError::LogicOpFeatureNotEnabled => {
                 "the `logic_op` feature must be enabled in order to use logic operations"
             },
+            GraphicsPipelineCreationError::DualSrcBlendFeatureNotEnabled => {
+                "the `dual_src_blend` feature must be enabled in order to use dual-source blend \
+                 factors"
+            },
             GraphicsPipelineCreationError::NoDepthAttachment => {
                 "the depth attachment of the render pass does not match the depth test"
             },
@@ -1268,11 +1570,27 @@ impl error::Error for GraphicsPipelineCreationError {
             },
             GraphicsPipelineCreationError::InvalidPrimitiveTopology => {
                 "trying to use a patch list without a tessellation shader, or a non-patch-list \
-                 with a tessellation shader"
+                 with a tessellation shader, or a patch list with zero vertices per patch"
             },
             GraphicsPipelineCreationError::MaxTessellationPatchSizeExceeded => {
                 "the maximum tessellation patch size was exceeded"
             },
+            GraphicsPipelineCreationError::InvalidRasterizationSamples { .. } => {
+                "the number of rasterization samples is not a power of two between 1 and 64"
+            },
+            GraphicsPipelineCreationError::RasterizationSamplesMismatch { .. } => {
+                "the number of rasterization samples doesn't match the number of samples of the \
+                 attachments of the subpass"
+            },
+            GraphicsPipelineCreationError::SampleRateShadingFeatureNotEnabled => {
+                "the `sample_rate_shading` feature must be enabled in order to use sample shading"
+            },
+            GraphicsPipelineCreationError::MinSampleShadingOutOfRange => {
+                "the minimum sample shading value must be between 0.0 and 1.0"
+            },
+            GraphicsPipelineCreationError::AlphaToOneFeatureNotEnabled => {
+                "the `alpha_to_one` feature must be enabled in order to use alpha-to-one"
+            },
         }
     }
 
@@ -1287,6 +1605,9 @@ impl error::Error for GraphicsPipelineCreationError {
             GraphicsPipelineCreationError::TessEvalGeometryStagesMismatch(ref err) => Some(err),
             GraphicsPipelineCreationError::TessEvalFragmentStagesMismatch(ref err) => Some(err),
             GraphicsPipelineCreationError::GeometryFragmentStagesMismatch(ref err) => Some(err),
+            GraphicsPipelineCreationError::FragmentShaderRenderPassIncompatible(ref err) => {
+                Some(err)
+            },
             GraphicsPipelineCreationError::IncompatibleVertexDefinition(ref err) => Some(err),
             _ => None
         }
@@ -1300,6 +1621,16 @@ impl fmt::Display for GraphicsPipelineCreationError {
     }
 }
 
+impl VulkanError for GraphicsPipelineCreationError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            GraphicsPipelineCreationError::OomError(ref err) => err.error_code(),
+            _ => None
+        }
+    }
+}
+
 impl From<OomError> for GraphicsPipelineCreationError {
     #[inline]
     fn from(err: OomError) -> GraphicsPipelineCreationError {
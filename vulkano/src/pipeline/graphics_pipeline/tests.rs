@@ -20,10 +20,16 @@ use pipeline::GraphicsPipeline;
 use pipeline::GraphicsPipelineParams;
 use pipeline::GraphicsPipelineCreationError;
 use pipeline::blend::Blend;
+use pipeline::depth_stencil::Compare;
+use pipeline::depth_stencil::DepthBounds;
 use pipeline::depth_stencil::DepthStencil;
+use pipeline::depth_stencil::Stencil;
 use pipeline::input_assembly::InputAssembly;
 use pipeline::input_assembly::PrimitiveTopology;
 use pipeline::multisample::Multisample;
+use pipeline::raster::ConservativeRasterization;
+use pipeline::raster::PolygonMode;
+use pipeline::raster::Rasterization;
 use pipeline::shader::ShaderModule;
 use pipeline::shader::EmptyShaderInterfaceDef;
 use pipeline::vertex::SingleBufferDefinition;
@@ -65,6 +71,38 @@ fn create() {
     }).unwrap();
 }
 
+#[test]
+fn create_with_builder() {
+    let (device, _) = gfx_dev_and_queue!();
+
+    let vs = unsafe { ShaderModule::new(device.clone(), &BASIC_VS).unwrap() };
+    let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS).unwrap() };
+
+    let _ = GraphicsPipeline::start()
+        .vertex_input_single_buffer::<()>()
+        .vertex_shader(unsafe {
+            vs.vertex_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyPipelineDesc)
+        })
+        .triangle_list()
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(unsafe {
+            fs.fragment_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyPipelineDesc)
+        })
+        .blend_alpha()
+        .layout(&EmptyPipeline::new(&device).unwrap())
+        .render_pass(Subpass::from(&simple_rp::CustomRenderPass::new(&device, &{
+            simple_rp::Formats { color: (Format::R8G8B8A8Unorm, 1) }
+        }).unwrap(), 0).unwrap())
+        .build(&device)
+        .unwrap();
+}
+
 #[test]
 fn bad_primitive_restart() {
     let (device, _) = gfx_dev_and_queue!();
@@ -232,6 +270,429 @@ fn no_depth_attachment() {
     }
 }
 
+#[test]
+fn rasterization_samples_mismatch() {
+    let (device, _) = gfx_dev_and_queue!();
+
+    let vs = unsafe { ShaderModule::new(device.clone(), &BASIC_VS).unwrap() };
+    let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS).unwrap() };
+
+    let result = GraphicsPipeline::new(&device, GraphicsPipelineParams {
+        vertex_input: SingleBufferDefinition::<()>::new(),
+        vertex_shader: unsafe {
+            vs.vertex_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyPipelineDesc)
+        },
+        input_assembly: InputAssembly::triangle_list(),
+        tessellation: None,
+        geometry_shader: None,
+        viewport: ViewportsState::Dynamic { num: 1 },
+        raster: Default::default(),
+        multisample: Multisample::samples(4),
+        fragment_shader: unsafe {
+            fs.fragment_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyPipelineDesc)
+        },
+        depth_stencil: DepthStencil::disabled(),
+        blend: Blend::pass_through(),
+        layout: &EmptyPipeline::new(&device).unwrap(),
+        render_pass: Subpass::from(&simple_rp::CustomRenderPass::new(&device, &{
+            simple_rp::Formats { color: (Format::R8G8B8A8Unorm, 1) }
+        }).unwrap(), 0).unwrap(),
+    });
+
+    match result {
+        Err(GraphicsPipelineCreationError::RasterizationSamplesMismatch { .. }) => (),
+        _ => panic!()
+    }
+}
+
+#[test]
+fn sample_rate_shading_feature() {
+    let (device, _) = gfx_dev_and_queue!();
+
+    let vs = unsafe { ShaderModule::new(device.clone(), &BASIC_VS).unwrap() };
+    let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS).unwrap() };
+
+    let result = GraphicsPipeline::new(&device, GraphicsPipelineParams {
+        vertex_input: SingleBufferDefinition::<()>::new(),
+        vertex_shader: unsafe {
+            vs.vertex_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyPipelineDesc)
+        },
+        input_assembly: InputAssembly::triangle_list(),
+        tessellation: None,
+        geometry_shader: None,
+        viewport: ViewportsState::Dynamic { num: 1 },
+        raster: Default::default(),
+        multisample: Multisample {
+            sample_shading: Some(0.5),
+            .. Multisample::disabled()
+        },
+        fragment_shader: unsafe {
+            fs.fragment_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyPipelineDesc)
+        },
+        depth_stencil: DepthStencil::disabled(),
+        blend: Blend::pass_through(),
+        layout: &EmptyPipeline::new(&device).unwrap(),
+        render_pass: Subpass::from(&simple_rp::CustomRenderPass::new(&device, &{
+            simple_rp::Formats { color: (Format::R8G8B8A8Unorm, 1) }
+        }).unwrap(), 0).unwrap(),
+    });
+
+    match result {
+        Err(GraphicsPipelineCreationError::SampleRateShadingFeatureNotEnabled) => (),
+        _ => panic!()
+    }
+}
+
+#[test]
+fn depth_bounds_out_of_range() {
+    let (device, _) = gfx_dev_and_queue!(depth_bounds);
+
+    let vs = unsafe { ShaderModule::new(device.clone(), &BASIC_VS).unwrap() };
+    let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS).unwrap() };
+
+    let result = GraphicsPipeline::new(&device, GraphicsPipelineParams {
+        vertex_input: SingleBufferDefinition::<()>::new(),
+        vertex_shader: unsafe {
+            vs.vertex_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyPipelineDesc)
+        },
+        input_assembly: InputAssembly::triangle_list(),
+        tessellation: None,
+        geometry_shader: None,
+        viewport: ViewportsState::Dynamic { num: 1 },
+        raster: Default::default(),
+        multisample: Multisample::disabled(),
+        fragment_shader: unsafe {
+            fs.fragment_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyPipelineDesc)
+        },
+        depth_stencil: DepthStencil {
+            depth_bounds_test: DepthBounds::Fixed(0.5 .. 2.0),
+            .. DepthStencil::disabled()
+        },
+        blend: Blend::pass_through(),
+        layout: &EmptyPipeline::new(&device).unwrap(),
+        render_pass: Subpass::from(&simple_rp::CustomRenderPass::new(&device, &{
+            simple_rp::Formats { color: (Format::R8G8B8A8Unorm, 1) }
+        }).unwrap(), 0).unwrap(),
+    });
+
+    match result {
+        Err(GraphicsPipelineCreationError::DepthBoundsOutOfRange) => (),
+        _ => panic!()
+    }
+}
+
+#[test]
+fn wrong_stencil_state() {
+    let (device, _) = gfx_dev_and_queue!();
+
+    let vs = unsafe { ShaderModule::new(device.clone(), &BASIC_VS).unwrap() };
+    let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS).unwrap() };
+
+    let result = GraphicsPipeline::new(&device, GraphicsPipelineParams {
+        vertex_input: SingleBufferDefinition::<()>::new(),
+        vertex_shader: unsafe {
+            vs.vertex_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyPipelineDesc)
+        },
+        input_assembly: InputAssembly::triangle_list(),
+        tessellation: None,
+        geometry_shader: None,
+        viewport: ViewportsState::Dynamic { num: 1 },
+        raster: Default::default(),
+        multisample: Multisample::disabled(),
+        fragment_shader: unsafe {
+            fs.fragment_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyPipelineDesc)
+        },
+        depth_stencil: DepthStencil {
+            stencil_front: Stencil { compare_mask: None, .. Default::default() },
+            .. DepthStencil::disabled()
+        },
+        blend: Blend::pass_through(),
+        layout: &EmptyPipeline::new(&device).unwrap(),
+        render_pass: Subpass::from(&simple_rp::CustomRenderPass::new(&device, &{
+            simple_rp::Formats { color: (Format::R8G8B8A8Unorm, 1) }
+        }).unwrap(), 0).unwrap(),
+    });
+
+    match result {
+        Err(GraphicsPipelineCreationError::WrongStencilState) => (),
+        _ => panic!()
+    }
+}
+
+#[test]
+fn stencil_test_without_attachment() {
+    let (device, _) = gfx_dev_and_queue!();
+
+    let vs = unsafe { ShaderModule::new(device.clone(), &BASIC_VS).unwrap() };
+    let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS).unwrap() };
+
+    let result = GraphicsPipeline::new(&device, GraphicsPipelineParams {
+        vertex_input: SingleBufferDefinition::<()>::new(),
+        vertex_shader: unsafe {
+            vs.vertex_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyPipelineDesc)
+        },
+        input_assembly: InputAssembly::triangle_list(),
+        tessellation: None,
+        geometry_shader: None,
+        viewport: ViewportsState::Dynamic { num: 1 },
+        raster: Default::default(),
+        multisample: Multisample::disabled(),
+        fragment_shader: unsafe {
+            fs.fragment_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyPipelineDesc)
+        },
+        depth_stencil: DepthStencil {
+            stencil_front: Stencil { compare: Compare::Less, .. Default::default() },
+            stencil_back: Stencil { compare: Compare::Less, .. Default::default() },
+            .. DepthStencil::disabled()
+        },
+        blend: Blend::pass_through(),
+        layout: &EmptyPipeline::new(&device).unwrap(),
+        render_pass: Subpass::from(&simple_rp::CustomRenderPass::new(&device, &{
+            simple_rp::Formats { color: (Format::R8G8B8A8Unorm, 1) }
+        }).unwrap(), 0).unwrap(),
+    });
+
+    match result {
+        Err(GraphicsPipelineCreationError::NoStencilAttachment) => (),
+        _ => panic!()
+    }
+}
+
+#[test]
+fn adjacency_topology_feature() {
+    let (device, _) = gfx_dev_and_queue!();
+
+    let vs = unsafe { ShaderModule::new(device.clone(), &BASIC_VS).unwrap() };
+    let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS).unwrap() };
+
+    let result = GraphicsPipeline::new(&device, GraphicsPipelineParams {
+        vertex_input: SingleBufferDefinition::<()>::new(),
+        vertex_shader: unsafe {
+            vs.vertex_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyPipelineDesc)
+        },
+        input_assembly: InputAssembly::new(PrimitiveTopology::TriangleListWithAdjacency),
+        tessellation: None,
+        geometry_shader: None,
+        viewport: ViewportsState::Dynamic { num: 1 },
+        raster: Default::default(),
+        multisample: Multisample::disabled(),
+        fragment_shader: unsafe {
+            fs.fragment_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyPipelineDesc)
+        },
+        depth_stencil: DepthStencil::disabled(),
+        blend: Blend::pass_through(),
+        layout: &EmptyPipeline::new(&device).unwrap(),
+        render_pass: Subpass::from(&simple_rp::CustomRenderPass::new(&device, &{
+            simple_rp::Formats { color: (Format::R8G8B8A8Unorm, 1) }
+        }).unwrap(), 0).unwrap(),
+    });
+
+    match result {
+        Err(GraphicsPipelineCreationError::GeometryShaderFeatureNotEnabled) => (),
+        _ => panic!()
+    }
+}
+
+#[test]
+fn patch_list_without_tessellation() {
+    let (device, _) = gfx_dev_and_queue!();
+
+    let vs = unsafe { ShaderModule::new(device.clone(), &BASIC_VS).unwrap() };
+    let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS).unwrap() };
+
+    let result = GraphicsPipeline::new(&device, GraphicsPipelineParams {
+        vertex_input: SingleBufferDefinition::<()>::new(),
+        vertex_shader: unsafe {
+            vs.vertex_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyPipelineDesc)
+        },
+        input_assembly: InputAssembly::new(PrimitiveTopology::PatchList { vertices_per_patch: 3 }),
+        tessellation: None,
+        geometry_shader: None,
+        viewport: ViewportsState::Dynamic { num: 1 },
+        raster: Default::default(),
+        multisample: Multisample::disabled(),
+        fragment_shader: unsafe {
+            fs.fragment_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyPipelineDesc)
+        },
+        depth_stencil: DepthStencil::disabled(),
+        blend: Blend::pass_through(),
+        layout: &EmptyPipeline::new(&device).unwrap(),
+        render_pass: Subpass::from(&simple_rp::CustomRenderPass::new(&device, &{
+            simple_rp::Formats { color: (Format::R8G8B8A8Unorm, 1) }
+        }).unwrap(), 0).unwrap(),
+    });
+
+    match result {
+        Err(GraphicsPipelineCreationError::InvalidPrimitiveTopology) => (),
+        _ => panic!()
+    }
+}
+
+#[test]
+fn wide_lines_feature() {
+    let (device, _) = gfx_dev_and_queue!();
+
+    let vs = unsafe { ShaderModule::new(device.clone(), &BASIC_VS).unwrap() };
+    let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS).unwrap() };
+
+    let result = GraphicsPipeline::new(&device, GraphicsPipelineParams {
+        vertex_input: SingleBufferDefinition::<()>::new(),
+        vertex_shader: unsafe {
+            vs.vertex_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyPipelineDesc)
+        },
+        input_assembly: InputAssembly::triangle_list(),
+        tessellation: None,
+        geometry_shader: None,
+        viewport: ViewportsState::Dynamic { num: 1 },
+        raster: Rasterization { line_width: Some(2.0), .. Default::default() },
+        multisample: Multisample::disabled(),
+        fragment_shader: unsafe {
+            fs.fragment_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyPipelineDesc)
+        },
+        depth_stencil: DepthStencil::disabled(),
+        blend: Blend::pass_through(),
+        layout: &EmptyPipeline::new(&device).unwrap(),
+        render_pass: Subpass::from(&simple_rp::CustomRenderPass::new(&device, &{
+            simple_rp::Formats { color: (Format::R8G8B8A8Unorm, 1) }
+        }).unwrap(), 0).unwrap(),
+    });
+
+    match result {
+        Err(GraphicsPipelineCreationError::WideLinesFeatureNotEnabled) => (),
+        _ => panic!()
+    }
+}
+
+#[test]
+fn fill_mode_non_solid_feature() {
+    let (device, _) = gfx_dev_and_queue!();
+
+    let vs = unsafe { ShaderModule::new(device.clone(), &BASIC_VS).unwrap() };
+    let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS).unwrap() };
+
+    let result = GraphicsPipeline::new(&device, GraphicsPipelineParams {
+        vertex_input: SingleBufferDefinition::<()>::new(),
+        vertex_shader: unsafe {
+            vs.vertex_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyPipelineDesc)
+        },
+        input_assembly: InputAssembly::triangle_list(),
+        tessellation: None,
+        geometry_shader: None,
+        viewport: ViewportsState::Dynamic { num: 1 },
+        raster: Rasterization { polygon_mode: PolygonMode::Line, .. Default::default() },
+        multisample: Multisample::disabled(),
+        fragment_shader: unsafe {
+            fs.fragment_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyPipelineDesc)
+        },
+        depth_stencil: DepthStencil::disabled(),
+        blend: Blend::pass_through(),
+        layout: &EmptyPipeline::new(&device).unwrap(),
+        render_pass: Subpass::from(&simple_rp::CustomRenderPass::new(&device, &{
+            simple_rp::Formats { color: (Format::R8G8B8A8Unorm, 1) }
+        }).unwrap(), 0).unwrap(),
+    });
+
+    match result {
+        Err(GraphicsPipelineCreationError::FillModeNonSolidFeatureNotEnabled) => (),
+        _ => panic!()
+    }
+}
+
+#[test]
+fn conservative_rasterization_extension() {
+    let (device, _) = gfx_dev_and_queue!();
+
+    let vs = unsafe { ShaderModule::new(device.clone(), &BASIC_VS).unwrap() };
+    let fs = unsafe { ShaderModule::new(device.clone(), &BASIC_FS).unwrap() };
+
+    let result = GraphicsPipeline::new(&device, GraphicsPipelineParams {
+        vertex_input: SingleBufferDefinition::<()>::new(),
+        vertex_shader: unsafe {
+            vs.vertex_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyShaderInterfaceDef,
+                                                        EmptyPipelineDesc)
+        },
+        input_assembly: InputAssembly::triangle_list(),
+        tessellation: None,
+        geometry_shader: None,
+        viewport: ViewportsState::Dynamic { num: 1 },
+        raster: Rasterization { conservative: Some(ConservativeRasterization::overestimate()), .. Default::default() },
+        multisample: Multisample::disabled(),
+        fragment_shader: unsafe {
+            fs.fragment_shader_entry_point::<(), _, _, _>(&CString::new("main").unwrap(),
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyShaderInterfaceDef,
+                                                          EmptyPipelineDesc)
+        },
+        depth_stencil: DepthStencil::disabled(),
+        blend: Blend::pass_through(),
+        layout: &EmptyPipeline::new(&device).unwrap(),
+        render_pass: Subpass::from(&simple_rp::CustomRenderPass::new(&device, &{
+            simple_rp::Formats { color: (Format::R8G8B8A8Unorm, 1) }
+        }).unwrap(), 0).unwrap(),
+    });
+
+    match result {
+        Err(GraphicsPipelineCreationError::ConservativeRasterizationExtensionNotEnabled) => (),
+        _ => panic!()
+    }
+}
+
 
 mod simple_rp {
     use format::Format;
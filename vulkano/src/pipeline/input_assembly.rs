@@ -11,6 +11,8 @@
 //!
 //! The input assembly is the stage where lists of vertices are turned into primitives.
 //!
+
+use device::Device;
 use vk;
 
 /// How the input assembly stage should behave.
@@ -29,31 +31,65 @@ pub struct InputAssembly {
 }
 
 impl InputAssembly {
-    /// Builds an `InputAssembly` struct with the `TriangleList` topology.
+    /// Builds an `InputAssembly` struct with the given topology and primitive restart disabled.
     #[inline]
-    pub fn triangle_list() -> InputAssembly {
+    pub fn new(topology: PrimitiveTopology) -> InputAssembly {
         InputAssembly {
-            topology: PrimitiveTopology::TriangleList,
+            topology: topology,
             primitive_restart_enable: false,
         }
     }
+
+    /// Builds an `InputAssembly` struct with the given topology and primitive restart enabled.
+    ///
+    /// The topology must support primitive restart, otherwise creating the pipeline will fail.
+    #[inline]
+    pub fn with_primitive_restart(topology: PrimitiveTopology) -> InputAssembly {
+        InputAssembly {
+            topology: topology,
+            primitive_restart_enable: true,
+        }
+    }
+
+    /// Builds an `InputAssembly` struct with the `TriangleList` topology.
+    #[inline]
+    pub fn triangle_list() -> InputAssembly {
+        InputAssembly::new(PrimitiveTopology::TriangleList)
+    }
 }
 
 /// Describes how vertices must be grouped together to form primitives.
 ///
 /// Note that some topologies don't support primitive restart.
+///
+/// Topologies with adjacency require the `geometry_shader` feature. Patch lists can only be used
+/// with tessellation shaders, and must be used if tessellation shaders are present.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum PrimitiveTopology {
+    /// Each vertex is a separate point.
     PointList,
+    /// Each pair of vertices is a separate line.
     LineList,
+    /// Each vertex forms a line with the previous vertex.
     LineStrip,
+    /// Each group of three vertices is a separate triangle.
     TriangleList,
+    /// Each vertex forms a triangle with the two previous vertices.
     TriangleStrip,
+    /// Each vertex forms a triangle with the previous vertex and the first vertex.
     TriangleFan,
+    /// Same as `LineList`, but each line has two additional vertices only visible to the
+    /// geometry shader.
     LineListWithAdjacency,
+    /// Same as `LineStrip`, but with adjacency vertices only visible to the geometry shader.
     LineStripWithAdjacency,
+    /// Same as `TriangleList`, but each triangle has three additional vertices only visible to
+    /// the geometry shader.
     TriangleListWithAdjacency,
+    /// Same as `TriangleStrip`, but with adjacency vertices only visible to the geometry shader.
     TriangleStripWithAdjacency,
+    /// Each group of `vertices_per_patch` vertices is a patch to be processed by the
+    /// tessellation shaders. Must be between 1 and the `max_tessellation_patch_size` limit.
     PatchList { vertices_per_patch: u32 },
 }
 
@@ -89,14 +125,38 @@ impl PrimitiveTopology {
             _ => false
         }
     }
+
+    /// Returns true if this primitive topology provides adjacency information to the geometry
+    /// shader.
+    #[inline]
+    pub fn has_adjacency(&self) -> bool {
+        match *self {
+            PrimitiveTopology::LineListWithAdjacency => true,
+            PrimitiveTopology::LineStripWithAdjacency => true,
+            PrimitiveTopology::TriangleListWithAdjacency => true,
+            PrimitiveTopology::TriangleStripWithAdjacency => true,
+            _ => false
+        }
+    }
 }
 
 /// Trait for types that can be used as indices by the GPU.
+///
+/// The index type that is passed to `vkCmdBindIndexBuffer` is chosen from the type of the
+/// elements of the index buffer. `u16` and `u32` are always supported, while `u8` requires the
+/// `ext_index_type_uint8` extension and its `indexTypeUint8` feature to be enabled on the device.
 pub unsafe trait Index {
     /// Returns the type of data.
     fn ty() -> IndexType;
 }
 
+unsafe impl Index for u8 {
+    #[inline(always)]
+    fn ty() -> IndexType {
+        IndexType::U8
+    }
+}
+
 unsafe impl Index for u16 {
     #[inline(always)]
     fn ty() -> IndexType {
@@ -112,10 +172,69 @@ unsafe impl Index for u32 {
 }
 
 /// An enumeration of all valid index types.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[allow(missing_docs)]
 #[repr(u32)]
 pub enum IndexType {
+    U8 = vk::INDEX_TYPE_UINT8_EXT,
     U16 = vk::INDEX_TYPE_UINT16,
     U32 = vk::INDEX_TYPE_UINT32,
 }
+
+impl IndexType {
+    /// Returns the size in bytes of an index of this type.
+    #[inline]
+    pub fn size(&self) -> usize {
+        match *self {
+            IndexType::U8 => 1,
+            IndexType::U16 => 2,
+            IndexType::U32 => 4,
+        }
+    }
+
+    /// Returns the largest index that can be represented with this type.
+    #[inline]
+    pub fn max_value(&self) -> u32 {
+        match *self {
+            IndexType::U8 => 0xff,
+            IndexType::U16 => 0xffff,
+            IndexType::U32 => 0xffffffff,
+        }
+    }
+
+    /// Returns true if this index type can be used with the given device.
+    ///
+    /// `U8` requires the `ext_index_type_uint8` extension to be enabled, along with its
+    /// `indexTypeUint8` feature.
+    #[inline]
+    pub fn supported_by_device(&self, device: &Device) -> bool {
+        match *self {
+            IndexType::U8 => device.loaded_extensions().ext_index_type_uint8 &&
+                             device.index_type_uint8_enabled(),
+            IndexType::U16 | IndexType::U32 => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pipeline::input_assembly::Index;
+    use pipeline::input_assembly::IndexType;
+
+    #[test]
+    fn index_types() {
+        assert_eq!(<u8 as Index>::ty(), IndexType::U8);
+        assert_eq!(<u16 as Index>::ty(), IndexType::U16);
+        assert_eq!(<u32 as Index>::ty(), IndexType::U32);
+        assert_eq!(IndexType::U32.size(), 4);
+        assert_eq!(IndexType::U16.max_value(), 65535);
+    }
+
+    #[test]
+    fn u8_requires_extension() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        assert!(IndexType::U16.supported_by_device(&device));
+        assert!(!IndexType::U8.supported_by_device(&device));
+    }
+}
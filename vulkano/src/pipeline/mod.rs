@@ -24,6 +24,7 @@
 pub use self::compute_pipeline::ComputePipeline;
 pub use self::compute_pipeline::ComputePipelineCreationError;
 pub use self::graphics_pipeline::GraphicsPipeline;
+pub use self::graphics_pipeline::GraphicsPipelineBuilder;
 pub use self::graphics_pipeline::GraphicsPipelineParams;
 pub use self::graphics_pipeline::GraphicsPipelineParamsTess;
 pub use self::graphics_pipeline::GraphicsPipelineCreationError;
@@ -34,9 +35,13 @@ mod graphics_pipeline;
 pub mod blend;
 pub mod cache;
 pub mod depth_stencil;
+#[cfg(feature = "glsl")]
+pub mod glsl;
 pub mod input_assembly;
 pub mod multisample;
 pub mod raster;
+pub mod reload;
 pub mod shader;
+pub mod spirv;
 pub mod vertex;
 pub mod viewport;
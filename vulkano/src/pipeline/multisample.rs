@@ -37,15 +37,34 @@
 /// State of the multisampling.
 ///
 /// See the documentation in this module.
+#[derive(Debug, Copy, Clone)]
 pub struct Multisample {
+    /// Number of samples per pixel. Must be a power of two between 1 and 64, and must be equal
+    /// to the number of samples of the attachments of the subpass.
     pub rasterization_samples: u32,
+
+    /// Bitmask of the samples that are allowed to be written. Bit `n` of element `n / 32`
+    /// corresponds to sample `n`. Only the first `ceil(rasterization_samples / 32)` elements are
+    /// used.
     pub sample_mask: [u32; 4],
+
+    /// Minimum proportion of samples that must be run through the fragment shader, between 0.0
+    /// and 1.0. `None` disables sample shading.
+    ///
+    /// Requires the `sample_rate_shading` feature to be enabled when `Some`.
     pub sample_shading: Option<f32>,
+
+    /// If true, the alpha value of the first color output determines which samples are covered.
     pub alpha_to_coverage: bool,
+
+    /// If true, the alpha value of the first color output is replaced with 1.0.
+    ///
+    /// Requires the `alpha_to_one` feature to be enabled.
     pub alpha_to_one: bool,
 }
 
 impl Multisample {
+    /// Builds a `Multisample` with one sample per pixel and everything else disabled.
     #[inline]
     pub fn disabled() -> Multisample {
         Multisample {
@@ -56,4 +75,14 @@ impl Multisample {
             alpha_to_one: false,
         }
     }
+
+    /// Builds a `Multisample` with the given number of samples per pixel, and sample shading
+    /// and alpha operations disabled.
+    #[inline]
+    pub fn samples(rasterization_samples: u32) -> Multisample {
+        Multisample {
+            rasterization_samples: rasterization_samples,
+            .. Multisample::disabled()
+        }
+    }
 }
@@ -39,9 +39,17 @@ pub struct Rasterization {
     ///
     /// If you pass `None`, then this state will be considered as dynamic and the line width will
     /// need to be set when you build the command buffer.
+    ///
+    /// Anything else than 1.0 requires the `wide_lines` feature to be enabled on the device, and
+    /// must be within the `line_width_range` limit.
     pub line_width: Option<f32>,
 
     pub depth_bias: DepthBiasControl,
+
+    /// Conservative rasterization configuration, or `None` to use the regular rasterization.
+    ///
+    /// Requires the `ext_conservative_rasterization` device extension to be enabled.
+    pub conservative: Option<ConservativeRasterization>,
 }
 
 impl Default for Rasterization {
@@ -55,6 +63,7 @@ impl Default for Rasterization {
             front_face: Default::default(),
             line_width: Some(1.0),
             depth_bias: DepthBiasControl::Disabled,
+            conservative: None,
         }
     }
 }
@@ -130,11 +139,69 @@ impl Default for FrontFace {
     }
 }
 
+/// Configuration of conservative rasterization.
+///
+/// With conservative rasterization, the coverage of a primitive is computed conservatively
+/// instead of by sampling the center of pixels. This is typically used for voxelization.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ConservativeRasterization {
+    /// Whether the coverage is overestimated or underestimated.
+    ///
+    /// Underestimation is only available if the implementation supports the
+    /// `primitiveUnderestimation` property. Otherwise creating the pipeline returns an error.
+    pub mode: ConservativeRasterizationMode,
+
+    /// Extra size in pixels by which the primitives are increased in each direction when
+    /// overestimating. Ignored when underestimating. Must be positive and must not exceed the
+    /// `maxExtraPrimitiveOverestimationSize` limit of the implementation.
+    pub extra_primitive_overestimation_size: f32,
+}
+
+impl ConservativeRasterization {
+    /// Builds a `ConservativeRasterization` that overestimates the coverage, without extra
+    /// size.
+    #[inline]
+    pub fn overestimate() -> ConservativeRasterization {
+        ConservativeRasterization {
+            mode: ConservativeRasterizationMode::Overestimate,
+            extra_primitive_overestimation_size: 0.0,
+        }
+    }
+
+    /// Builds a `ConservativeRasterization` that underestimates the coverage.
+    ///
+    /// Requires the implementation to support the `primitiveUnderestimation` property.
+    #[inline]
+    pub fn underestimate() -> ConservativeRasterization {
+        ConservativeRasterization {
+            mode: ConservativeRasterizationMode::Underestimate,
+            extra_primitive_overestimation_size: 0.0,
+        }
+    }
+}
+
+/// Mode of conservative rasterization.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum ConservativeRasterizationMode {
+    /// A fragment is generated for each pixel that is partially covered by the primitive.
+    Overestimate = vk::CONSERVATIVE_RASTERIZATION_MODE_OVERESTIMATE_EXT,
+    /// A fragment is generated only for pixels that are fully covered by the primitive.
+    Underestimate = vk::CONSERVATIVE_RASTERIZATION_MODE_UNDERESTIMATE_EXT,
+}
+
+/// Specifies how polygons are turned into fragments.
+///
+/// Anything else than `Fill` requires the `fill_mode_non_solid` feature to be enabled on the
+/// device.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(u32)]
 pub enum PolygonMode {
+    /// The whole surface of polygons is drawn.
     Fill = vk::POLYGON_MODE_FILL,
+    /// Only the edges of polygons are drawn, as lines. Useful for wireframe views.
     Line = vk::POLYGON_MODE_LINE,
+    /// Only the vertices of polygons are drawn, as points.
     Point = vk::POLYGON_MODE_POINT,
 }
 
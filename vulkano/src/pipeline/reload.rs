@@ -0,0 +1,269 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Rebuilding pipelines when their shaders change.
+//!
+//! When iterating on shaders, it is convenient to be able to replace a shader module while the
+//! program is running, for example when the file that contains it is modified. However a
+//! pipeline can't be modified once it is created, and has to be rebuilt from the new modules.
+//!
+//! A `ReloadableShader` holds a shader module that can be replaced at any time. A
+//! `ReloadablePipeline` holds a pipeline along with the function that builds it from the
+//! modules of a list of `ReloadableShader`s. Calling `reload()`, for example once per frame,
+//! rebuilds the pipeline if one of the modules has been replaced since the last build.
+//!
+//! The pipeline returned by `pipeline()` should be retreived again every time a command buffer
+//! is built. Command buffers that were built with the previous pipeline keep it alive and are
+//! not affected by the rebuild.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let vs = ReloadableShader::new(load_module(&device, "shader.vert.spv"));
+//! let fs = ReloadableShader::new(load_module(&device, "shader.frag.spv"));
+//! let cache = PipelineCache::empty(&device).unwrap();
+//!
+//! let pipeline = ReloadablePipeline::new(vec![vs.clone(), fs.clone()], Some(cache),
+//!                                        move |modules, cache| {
+//!     let vs = unsafe { modules[0].vertex_shader_entry_point(/* ... */) };
+//!     let fs = unsafe { modules[1].fragment_shader_entry_point(/* ... */) };
+//!     GraphicsPipeline::start().vertex_shader(vs).fragment_shader(fs)
+//!         .pipeline_cache(cache.unwrap())
+//!         /* ... */
+//!         .build(&device)
+//! }).unwrap();
+//!
+//! // When the file is modified.
+//! fs.replace(load_module(&device, "shader.frag.spv"));
+//!
+//! // Before building the command buffers of the next frame.
+//! if let Err(err) = pipeline.reload() {
+//!     println!("Failed to rebuild the pipeline: {}", err);
+//! }
+//! let current = pipeline.pipeline();
+//! ```
+
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use pipeline::cache::PipelineCache;
+use pipeline::shader::ShaderModule;
+
+/// Shader module that can be replaced while it is in use.
+///
+/// See [the documentation of the module](index.html) for more info.
+pub struct ReloadableShader {
+    // The current module, and the number of times it has been replaced.
+    state: Mutex<(Arc<ShaderModule>, usize)>,
+}
+
+impl ReloadableShader {
+    /// Builds a new `ReloadableShader` that holds `module`.
+    #[inline]
+    pub fn new(module: Arc<ShaderModule>) -> Arc<ReloadableShader> {
+        Arc::new(ReloadableShader {
+            state: Mutex::new((module, 0)),
+        })
+    }
+
+    /// Returns the current module.
+    #[inline]
+    pub fn module(&self) -> Arc<ShaderModule> {
+        self.state.lock().unwrap().0.clone()
+    }
+
+    /// Replaces the module. The pipelines that use this shader are rebuilt the next time they
+    /// are reloaded.
+    #[inline]
+    pub fn replace(&self, module: Arc<ShaderModule>) {
+        let mut state = self.state.lock().unwrap();
+        state.0 = module;
+        state.1 += 1;
+    }
+
+    /// Returns the number of times the module has been replaced.
+    #[inline]
+    pub fn generation(&self) -> usize {
+        self.state.lock().unwrap().1
+    }
+}
+
+/// Pipeline that is rebuilt when its shaders are replaced.
+///
+/// `P` is the type of the pipeline, and `E` the error that can happen when building it.
+///
+/// See [the documentation of the module](index.html) for more info.
+pub struct ReloadablePipeline<P, E> {
+    shaders: Vec<Arc<ReloadableShader>>,
+    cache: Option<Arc<PipelineCache>>,
+    build: Box<Fn(&[Arc<ShaderModule>], Option<&Arc<PipelineCache>>) -> Result<Arc<P>, E> +
+               Send + Sync>,
+    // The current pipeline, and the generations of the shaders it was built from.
+    state: Mutex<(Arc<P>, Vec<usize>)>,
+}
+
+impl<P, E> ReloadablePipeline<P, E> {
+    /// Builds the pipeline for the first time.
+    ///
+    /// `build` is called with the current modules of `shaders`, in the same order, and with
+    /// `cache`. It is called again every time the pipeline has to be rebuilt.
+    pub fn new<F>(shaders: Vec<Arc<ReloadableShader>>, cache: Option<Arc<PipelineCache>>,
+                  build: F) -> Result<Arc<ReloadablePipeline<P, E>>, E>
+        where F: Fn(&[Arc<ShaderModule>], Option<&Arc<PipelineCache>>) -> Result<Arc<P>, E> +
+                 Send + Sync + 'static
+    {
+        let generations = shaders.iter().map(|s| s.generation()).collect();
+        let modules = shaders.iter().map(|s| s.module()).collect::<Vec<_>>();
+        let pipeline = try!(build(&modules, cache.as_ref()));
+
+        Ok(Arc::new(ReloadablePipeline {
+            shaders: shaders,
+            cache: cache,
+            build: Box::new(build),
+            state: Mutex::new((pipeline, generations)),
+        }))
+    }
+
+    /// Returns the current pipeline.
+    #[inline]
+    pub fn pipeline(&self) -> Arc<P> {
+        self.state.lock().unwrap().0.clone()
+    }
+
+    /// Returns the shaders that the pipeline is built from.
+    #[inline]
+    pub fn shaders(&self) -> &[Arc<ReloadableShader>] {
+        &self.shaders
+    }
+
+    /// Returns the pipeline cache that is passed when building the pipeline, if any.
+    #[inline]
+    pub fn cache(&self) -> Option<&Arc<PipelineCache>> {
+        self.cache.as_ref()
+    }
+
+    /// Rebuilds the pipeline if one of its shaders has been replaced since the last build.
+    /// Returns `true` if the pipeline has been rebuilt.
+    ///
+    /// If the build fails, the previous pipeline is kept and the error is returned. The build
+    /// isn't attempted again until one of the shaders is replaced again.
+    pub fn reload(&self) -> Result<bool, E> {
+        let mut state = self.state.lock().unwrap();
+
+        let generations = self.shaders.iter().map(|s| s.generation()).collect::<Vec<_>>();
+        if generations == state.1 {
+            return Ok(false);
+        }
+
+        state.1 = generations;
+        state.0 = try!(self.build_pipeline());
+        Ok(true)
+    }
+
+    /// Rebuilds the pipeline unconditionally, for example after the render pass it uses has
+    /// been recreated.
+    ///
+    /// If the build fails, the previous pipeline is kept and the error is returned.
+    pub fn rebuild(&self) -> Result<(), E> {
+        let mut state = self.state.lock().unwrap();
+        state.1 = self.shaders.iter().map(|s| s.generation()).collect();
+        state.0 = try!(self.build_pipeline());
+        Ok(())
+    }
+
+    // Calls the build function with the current modules.
+    fn build_pipeline(&self) -> Result<Arc<P>, E> {
+        let modules = self.shaders.iter().map(|s| s.module()).collect::<Vec<_>>();
+        (self.build)(&modules, self.cache.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use pipeline::reload::ReloadablePipeline;
+    use pipeline::reload::ReloadableShader;
+    use pipeline::shader::ShaderModule;
+
+    // Compute shader with an empty entry point named `main`.
+    fn empty_compute_shader() -> Vec<u8> {
+        let words: Vec<u32> = vec![
+            0x07230203, 0x00010000, 0, 5, 0,
+            (2 << 16) | 17, 1,                                      // OpCapability Shader
+            (3 << 16) | 14, 0, 1,                                   // OpMemoryModel GLSL450
+            (5 << 16) | 15, 5, 1, 0x6e69616d, 0,                    // OpEntryPoint "main"
+            (6 << 16) | 16, 1, 17, 1, 1, 1,                         // OpExecutionMode LocalSize
+            (2 << 16) | 19, 2,                                      // %2 = OpTypeVoid
+            (3 << 16) | 33, 3, 2,                                   // %3 = OpTypeFunction %2
+            (5 << 16) | 54, 2, 1, 0, 3,                             // %1 = OpFunction %2 %3
+            (2 << 16) | 248, 4,                                     // %4 = OpLabel
+            (1 << 16) | 253,                                        // OpReturn
+            (1 << 16) | 56,                                         // OpFunctionEnd
+        ];
+
+        words.iter().flat_map(|&w| (0 .. 4).map(move |n| (w >> (n * 8)) as u8)).collect()
+    }
+
+    #[test]
+    fn reload_after_replace() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let spirv = empty_compute_shader();
+        let first = unsafe { ShaderModule::new(device.clone(), &spirv).unwrap() };
+        let second = unsafe { ShaderModule::new(device.clone(), &spirv).unwrap() };
+        let broken = unsafe { ShaderModule::new(device.clone(), &spirv).unwrap() };
+
+        let shader = ReloadableShader::new(first.clone());
+        let broken2 = broken.clone();
+        let pipeline = ReloadablePipeline::new(vec![shader.clone()], None, move |modules, _| {
+            if Arc::ptr_eq(&modules[0], &broken2) {
+                return Err(());
+            }
+            Ok(modules[0].clone())
+        }).unwrap();
+        assert!(Arc::ptr_eq(&pipeline.pipeline(), &first));
+
+        shader.replace(second.clone());
+        let old = pipeline.pipeline();
+        assert_eq!(pipeline.reload(), Ok(true));
+        assert!(Arc::ptr_eq(&pipeline.pipeline(), &second));
+        assert!(Arc::ptr_eq(&old, &first));
+        assert_eq!(pipeline.reload(), Ok(false));
+
+        // A failed build keeps the previous pipeline and isn't attempted again.
+        shader.replace(broken.clone());
+        assert_eq!(pipeline.reload(), Err(()));
+        assert!(Arc::ptr_eq(&pipeline.pipeline(), &second));
+        assert_eq!(pipeline.reload(), Ok(false));
+    }
+
+    #[test]
+    fn rebuild_swaps_pipeline() {
+        let builds = Arc::new(AtomicUsize::new(0));
+        let builds2 = builds.clone();
+
+        let pipeline = ReloadablePipeline::new(Vec::new(), None, move |modules, cache| {
+            assert!(modules.is_empty());
+            assert!(cache.is_none());
+            Ok::<_, ()>(Arc::new(builds2.fetch_add(1, Ordering::SeqCst)))
+        }).unwrap();
+
+        assert_eq!(*pipeline.pipeline(), 0);
+        assert_eq!(pipeline.reload(), Ok(false));
+        assert_eq!(*pipeline.pipeline(), 0);
+
+        let old = pipeline.pipeline();
+        pipeline.rebuild().unwrap();
+        assert_eq!(*pipeline.pipeline(), 1);
+        assert_eq!(*old, 0);
+        assert_eq!(builds.load(Ordering::SeqCst), 2);
+    }
+}
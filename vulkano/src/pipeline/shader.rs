@@ -13,9 +13,11 @@
 //! code and can contain one or more entry points. Note that for the moment the official
 //! GLSL-to-SPIR-V compiler does not support multiple entry points.
 //! 
-//! The vulkano library does not provide any functionnality that checks and introspects the SPIR-V
-//! code, therefore the whole shader-related API is unsafe. You are encouraged to use the
+//! The vulkano library does not provide any functionnality that checks the SPIR-V code besides
+//! its header, therefore the whole shader-related API is unsafe. You are encouraged to use the
 //! `vulkano-shaders` crate that will generate Rust code that wraps around vulkano's shaders API.
+//! For shaders that are only known at runtime, the `spirv` module can extract the descriptions to
+//! pass to this API.
 
 use std::borrow::Cow;
 use std::error;
@@ -26,14 +28,19 @@ use std::marker::PhantomData;
 use std::mem;
 use std::ops::Range;
 use std::ptr;
+use std::slice;
 use std::sync::Arc;
 use std::ffi::CStr;
 
 use format::Format;
 use pipeline::input_assembly::PrimitiveTopology;
+use pipeline::spirv::MAGIC;
+use pipeline::spirv::bytes_to_words;
 
 use device::Device;
+use Error;
 use OomError;
+use VulkanError;
 use VulkanObject;
 use VulkanPointers;
 use SafeDeref;
@@ -86,6 +93,48 @@ impl<P> ShaderModule<P> where P: SafeDeref<Target = Device> {
         }))
     }
 
+    /// Builds a new shader module from SPIR-V code stored as words in the native endianness.
+    ///
+    /// This is the format produced by most runtime compilers, and unlike `new` it doesn't
+    /// require reinterpreting the code as bytes. The header of the code is checked before the
+    /// module is created: it must start with the SPIR-V magic number, and its version must be
+    /// supported. Since vulkano uses Vulkan 1.0, only SPIR-V 1.0 is supported.
+    ///
+    /// # Safety
+    ///
+    /// - The SPIR-V code after the header is not validated.
+    /// - The SPIR-V code may require some features that are not enabled. This isn't checked by
+    ///   this function either.
+    ///
+    pub unsafe fn from_words(device: P, spirv: &[u32])
+                             -> Result<Arc<ShaderModule<P>>, ShaderModuleCreationError>
+    {
+        try!(check_header(spirv));
+
+        let bytes = slice::from_raw_parts(spirv.as_ptr() as *const u8,
+                                          spirv.len() * mem::size_of::<u32>());
+        Ok(try!(ShaderModule::new(device, bytes)))
+    }
+
+    /// Same as `from_words`, but for SPIR-V code stored as bytes in either endianness.
+    ///
+    /// Contrary to `new`, the code doesn't need to be aligned, and an error is returned if its
+    /// length isn't a multiple of 4.
+    ///
+    /// # Safety
+    ///
+    /// Same as `from_words`.
+    ///
+    pub unsafe fn from_bytes(device: P, spirv: &[u8])
+                             -> Result<Arc<ShaderModule<P>>, ShaderModuleCreationError>
+    {
+        if spirv.len() % 4 != 0 {
+            return Err(ShaderModuleCreationError::MisalignedCode);
+        }
+
+        ShaderModule::from_words(device, &bytes_to_words(spirv))
+    }
+
     /// Gets access to an entry point contained in this module.
     ///
     /// This is purely a *logical* operation. It returns a struct that *represents* the entry
@@ -226,15 +275,18 @@ impl<P> ShaderModule<P> where P: SafeDeref<Target = Device> {
     ///
     /// - The user must check that the entry point exists in the module, as this is not checked
     ///   by Vulkan.
+    /// - The local size must match the `LocalSize` execution mode of the entry point.
     /// - The layout must correctly describe the layout used by this stage.
     ///
     #[inline]
-    pub unsafe fn compute_shader_entry_point<'a, S, L>(&'a self, name: &'a CStr, layout: L)
+    pub unsafe fn compute_shader_entry_point<'a, S, L>(&'a self, name: &'a CStr,
+                                                       local_size: [u32; 3], layout: L)
                                                        -> ComputeShaderEntryPoint<'a, S, L, P>
     {
         ComputeShaderEntryPoint {
             module: self,
             name: name,
+            local_size: local_size,
             layout: layout,
             marker: PhantomData,
         }
@@ -557,6 +609,7 @@ pub struct ComputeShaderEntryPoint<'a, S, L, P = Arc<Device>>
 {
     module: &'a ShaderModule<P>,
     name: &'a CStr,
+    local_size: [u32; 3],
     layout: L,
     marker: PhantomData<S>,
 }
@@ -576,6 +629,13 @@ impl<'a, S, L, P> ComputeShaderEntryPoint<'a, S, L, P>
         self.name
     }
 
+    /// Returns the number of invocations of each work group, as declared by the `LocalSize`
+    /// execution mode of the shader.
+    #[inline]
+    pub fn local_size(&self) -> [u32; 3] {
+        self.local_size
+    }
+
     /// Returns the pipeline layout used by the shader stage.
     #[inline]
     pub fn layout(&self) -> &L {
@@ -636,18 +696,29 @@ unsafe impl<T, I> ShaderInterfaceDefMatch<I> for T
 {
     fn matches(&self, other: &I) -> Result<(), ShaderInterfaceMismatchError> {
         if self.elements().len() != other.elements().len() {
-            return Err(ShaderInterfaceMismatchError::ElementsCountMismatch);
+            return Err(ShaderInterfaceMismatchError::ElementsCountMismatch {
+                self_elements: self.elements().len() as u32,
+                other_elements: other.elements().len() as u32,
+            });
         }
 
         for a in self.elements() {
             for loc in a.location.clone() {
-                let b = match other.elements().find(|e| loc >= e.location.start && loc < e.location.end) {
-                    None => return Err(ShaderInterfaceMismatchError::MissingElement { location: loc }),
+                let b = match other.elements()
+                                   .find(|e| loc >= e.location.start && loc < e.location.end)
+                {
+                    None => return Err(ShaderInterfaceMismatchError::MissingElement {
+                        location: loc,
+                    }),
                     Some(b) => b,
                 };
 
                 if a.format != b.format {
-                    return Err(ShaderInterfaceMismatchError::FormatMismatch);
+                    return Err(ShaderInterfaceMismatchError::FormatMismatch {
+                        location: loc,
+                        self_format: a.format,
+                        other_format: b.format,
+                    });
                 }
 
                 // TODO: enforce this?
@@ -662,32 +733,165 @@ unsafe impl<T, I> ShaderInterfaceDefMatch<I> for T
     }
 }
 
+// Checks the header of SPIR-V code before it is passed to Vulkan.
+fn check_header(spirv: &[u32]) -> Result<(), ShaderModuleCreationError> {
+    if spirv.len() < 5 {
+        return Err(ShaderModuleCreationError::MissingHeader);
+    }
+
+    if spirv[0] != MAGIC {
+        return Err(ShaderModuleCreationError::WrongMagicNumber);
+    }
+
+    // The version is stored as `0x00MMmm00`.
+    let major = ((spirv[1] >> 16) & 0xff) as u8;
+    let minor = ((spirv[1] >> 8) & 0xff) as u8;
+    if major != 1 || minor != 0 {
+        return Err(ShaderModuleCreationError::UnsupportedVersion { major: major, minor: minor });
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when creating a shader module.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ShaderModuleCreationError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The length of the code in bytes isn't a multiple of 4.
+    MisalignedCode,
+    /// The code is too short to contain a SPIR-V header.
+    MissingHeader,
+    /// The code doesn't start with the SPIR-V magic number.
+    WrongMagicNumber,
+    /// The version of SPIR-V of the code isn't supported.
+    UnsupportedVersion {
+        /// Major version of the code.
+        major: u8,
+        /// Minor version of the code.
+        minor: u8,
+    },
+}
+
+impl error::Error for ShaderModuleCreationError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            ShaderModuleCreationError::OomError(_) => "not enough memory available",
+            ShaderModuleCreationError::MisalignedCode => {
+                "the length of the code isn't a multiple of 4 bytes"
+            },
+            ShaderModuleCreationError::MissingHeader => {
+                "the code is too short to contain a SPIR-V header"
+            },
+            ShaderModuleCreationError::WrongMagicNumber => {
+                "the code doesn't start with the SPIR-V magic number"
+            },
+            ShaderModuleCreationError::UnsupportedVersion { .. } => {
+                "the version of SPIR-V of the code isn't supported"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ShaderModuleCreationError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for ShaderModuleCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for ShaderModuleCreationError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            ShaderModuleCreationError::OomError(ref err) => err.error_code(),
+            _ => None
+        }
+    }
+}
+
+impl From<OomError> for ShaderModuleCreationError {
+    #[inline]
+    fn from(err: OomError) -> ShaderModuleCreationError {
+        ShaderModuleCreationError::OomError(err)
+    }
+}
+
+impl From<Error> for ShaderModuleCreationError {
+    #[inline]
+    fn from(err: Error) -> ShaderModuleCreationError {
+        ShaderModuleCreationError::OomError(OomError::from(err))
+    }
+}
+
 /// Error that can happen when the interface mismatches between two shader stages.
-// TODO: improve diagnostic a bit
+///
+/// `self` designates the inputs of a stage, and `other` the outputs of the previous stage.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ShaderInterfaceMismatchError {
-    ElementsCountMismatch,
+    /// The two interfaces don't have the same number of elements.
+    ElementsCountMismatch {
+        self_elements: u32,
+        other_elements: u32,
+    },
+    /// An element of the inputs doesn't have a corresponding element in the outputs.
     MissingElement { location: u32 },
-    FormatMismatch,
+    /// The type or the number of components of an element is different in the two interfaces.
+    FormatMismatch {
+        location: u32,
+        self_format: Format,
+        other_format: Format,
+    },
 }
 
 impl error::Error for ShaderInterfaceMismatchError {
     #[inline]
     fn description(&self) -> &str {
         match *self {
-            ShaderInterfaceMismatchError::ElementsCountMismatch => "the number of elements \
-                                                                    mismatches",
+            ShaderInterfaceMismatchError::ElementsCountMismatch { .. } => {
+                "the number of elements mismatches"
+            },
             ShaderInterfaceMismatchError::MissingElement { .. } => "an element is missing",
-            ShaderInterfaceMismatchError::FormatMismatch => "the format of an element does not \
-                                                             match",
+            ShaderInterfaceMismatchError::FormatMismatch { .. } => {
+                "the format of an element does not match"
+            },
         }
     }
 }
 
 impl fmt::Display for ShaderInterfaceMismatchError {
-    #[inline]
     fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(fmt, "{}", error::Error::description(self))
+        match *self {
+            ShaderInterfaceMismatchError::ElementsCountMismatch { self_elements,
+                                                                  other_elements } => {
+                write!(fmt, "the number of elements mismatches ({} inputs and {} outputs)",
+                       self_elements, other_elements)
+            },
+            ShaderInterfaceMismatchError::MissingElement { location } => {
+                write!(fmt, "the input at location {} has no corresponding output", location)
+            },
+            ShaderInterfaceMismatchError::FormatMismatch { location, self_format,
+                                                           other_format } => {
+                write!(fmt, "the input at location {} has the format {:?}, but the output has \
+                             the format {:?}", location, self_format, other_format)
+            },
+        }
+    }
+}
+
+impl VulkanError for ShaderInterfaceMismatchError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        None
     }
 }
 
@@ -722,3 +926,83 @@ pub struct SpecializationMapEntry {
     /// Size of the data in bytes.
     pub size: usize,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::vec::IntoIter as VecIntoIter;
+    use format::Format;
+    use pipeline::shader::ShaderInterfaceDef;
+    use pipeline::shader::ShaderInterfaceDefEntry;
+    use pipeline::shader::ShaderInterfaceDefMatch;
+    use pipeline::shader::ShaderInterfaceMismatchError;
+    use pipeline::shader::ShaderModule;
+    use pipeline::shader::ShaderModuleCreationError;
+    use pipeline::shader::check_header;
+    use pipeline::spirv::bytes_to_words;
+
+    #[test]
+    fn header_checks() {
+        assert_eq!(check_header(&[0x07230203, 0x00010000, 0, 1, 0]), Ok(()));
+        assert_eq!(check_header(&[0x07230203, 0x00010000]),
+                   Err(ShaderModuleCreationError::MissingHeader));
+        assert_eq!(check_header(&[0x03022307, 0x00010000, 0, 1, 0]),
+                   Err(ShaderModuleCreationError::WrongMagicNumber));
+        assert_eq!(check_header(&[0x07230203, 0x00010300, 0, 1, 0]),
+                   Err(ShaderModuleCreationError::UnsupportedVersion { major: 1, minor: 3 }));
+    }
+
+    #[test]
+    fn bytes_endianness() {
+        let little = [0x03, 0x02, 0x23, 0x07, 0x00, 0x00, 0x01, 0x00];
+        let big = [0x07, 0x23, 0x02, 0x03, 0x00, 0x01, 0x00, 0x00];
+        assert_eq!(bytes_to_words(&little), vec![0x07230203, 0x00010000]);
+        assert_eq!(bytes_to_words(&big), vec![0x07230203, 0x00010000]);
+    }
+
+    #[test]
+    fn from_bytes_misaligned() {
+        let (device, _) = gfx_dev_and_queue!();
+        let spirv = [0x03, 0x02, 0x23, 0x07, 0x00];
+        match unsafe { ShaderModule::from_bytes(device, &spirv) } {
+            Err(ShaderModuleCreationError::MisalignedCode) => (),
+            _ => panic!()
+        }
+    }
+
+    struct Interface(Vec<(u32, Format)>);
+    unsafe impl ShaderInterfaceDef for Interface {
+        type Iter = VecIntoIter<ShaderInterfaceDefEntry>;
+        fn elements(&self) -> Self::Iter {
+            self.0.iter().map(|&(loc, format)| {
+                ShaderInterfaceDefEntry { location: loc .. loc + 1, format: format, name: None }
+            }).collect::<Vec<_>>().into_iter()
+        }
+    }
+
+    #[test]
+    fn interface_mismatches() {
+        let outputs = Interface(vec![(0, Format::R32G32B32A32Sfloat), (1, Format::R32Uint)]);
+
+        let inputs = Interface(vec![(1, Format::R32Uint), (0, Format::R32G32B32A32Sfloat)]);
+        assert_eq!(inputs.matches(&outputs), Ok(()));
+
+        let inputs = Interface(vec![(0, Format::R32G32B32A32Sfloat)]);
+        assert_eq!(inputs.matches(&outputs),
+                   Err(ShaderInterfaceMismatchError::ElementsCountMismatch {
+                       self_elements: 1,
+                       other_elements: 2,
+                   }));
+
+        let inputs = Interface(vec![(0, Format::R32G32B32A32Sfloat), (2, Format::R32Uint)]);
+        assert_eq!(inputs.matches(&outputs),
+                   Err(ShaderInterfaceMismatchError::MissingElement { location: 2 }));
+
+        let inputs = Interface(vec![(0, Format::R32G32B32Sfloat), (1, Format::R32Uint)]);
+        assert_eq!(inputs.matches(&outputs),
+                   Err(ShaderInterfaceMismatchError::FormatMismatch {
+                       location: 0,
+                       self_format: Format::R32G32B32Sfloat,
+                       other_format: Format::R32G32B32A32Sfloat,
+                   }));
+    }
+}
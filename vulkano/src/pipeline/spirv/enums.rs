@@ -7,26 +7,29 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+//! Enumerations of the SPIR-V specification that are used by the operands of instructions.
+
 #![allow(dead_code)]
 #![allow(non_camel_case_types)]
 
-use parse::ParseError;
+use pipeline::spirv::SpirvParseError;
 
 macro_rules! enumeration {
     ($(typedef enum $unused:ident { $($elem:ident = $value:expr,)+ } $name:ident;)+) => (
         $(
-            #[derive(Debug, Clone)]
+            #[derive(Debug, Copy, Clone, PartialEq, Eq)]
             pub enum $name {
                 $($elem),+
             }
 
             impl $name {
-                pub fn from_num(num: u32) -> Result<$name, ParseError> {
+                /// Turns the value of an operand into an enumeration value.
+                pub fn from_num(num: u32) -> Result<$name, SpirvParseError> {
                     match num {
                         $(
                             $value => Ok($name::$elem),
                         )+
-                        _ => Err(ParseError::UnknownConstant(stringify!($name), num)),
+                        _ => Err(SpirvParseError::UnknownConstant(stringify!($name), num)),
                     }
                 }
             }
@@ -34,7 +37,8 @@ macro_rules! enumeration {
     )
 }
 
-// The code below is a copy-paste from `spirv-2.h`, with the `Spv` prefixes removed.
+// The code below is a copy-paste from `spirv-2.h`, with the `Spv` prefixes removed. The
+// `StorageBuffer` storage class comes from the `SPV_KHR_storage_buffer_storage_class` extension.
 
 enumeration! {
     typedef enum SourceLanguage_ {
@@ -114,6 +118,7 @@ enumeration! {
         StorageClassPushConstant = 9,
         StorageClassAtomicCounter = 10,
         StorageClassImage = 11,
+        StorageClassStorageBuffer = 12,
     } StorageClass;
 
     typedef enum Dim_ {
@@ -0,0 +1,1484 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Runtime introspection of SPIR-V code.
+//!
+//! The `vulkano-shaders` crate analyzes shaders at compile time and generates Rust code that
+//! describes them. When a shader is only known at runtime, for example because it is loaded from
+//! disk, this module can instead be used to extract from the SPIR-V code the descriptors, push
+//! constants and input/output interfaces of the shader, and to build matching layouts.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let reflection = ShaderReflection::from_bytes(&spirv).unwrap();
+//! let layout_desc = reflection.layout_desc();
+//! let layout = layout_desc.build(&device).unwrap();
+//!
+//! let module = unsafe { ShaderModule::new(device.clone(), &spirv).unwrap() };
+//! let entry = reflection.entry_point("main").unwrap();
+//! let vs = unsafe {
+//!     module.vertex_shader_entry_point(entry.name(), entry.inputs().clone(),
+//!                                      entry.outputs().clone(), layout_desc.clone())
+//! };
+//! ```
+//!
+//! When a pipeline uses multiple shaders, the layout descriptions of all the shaders should be
+//! merged with `ReflectedLayoutDesc::union`, and the result used both to build the pipeline
+//! layout and as the layout of each entry point.
+//!
+//! The specialization constants of the module can be listed with
+//! `ShaderReflection::specialization_constants`, for example to expose them by name, and the
+//! `SpecializationConstants` struct passed when creating a pipeline can be checked against them
+//! with `ShaderReflection::check_specialization_constants`. Similarly, a struct that describes
+//! push constants can be checked with `ShaderReflection::check_push_constants`.
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::error;
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::fmt;
+use std::sync::Arc;
+use std::vec::IntoIter as VecIntoIter;
+
+use descriptor::descriptor::DescriptorBufferDesc;
+use descriptor::descriptor::DescriptorDesc;
+use descriptor::descriptor::DescriptorDescTy;
+use descriptor::descriptor::DescriptorImageDesc;
+use descriptor::descriptor::DescriptorImageDescArray;
+use descriptor::descriptor::DescriptorImageDescDimensions;
+use descriptor::descriptor::ShaderStages;
+use descriptor::pipeline_layout::PipelineLayoutDesc;
+use descriptor::pipeline_layout::PushConstantsDesc;
+use descriptor::pipeline_layout::RuntimePipelineLayout;
+use descriptor::pipeline_layout::UnsafePipelineLayoutCreationError;
+use device::Device;
+use format::Format;
+use pipeline::shader::ShaderInterfaceDef;
+use pipeline::shader::ShaderInterfaceDefEntry;
+use pipeline::shader::SpecializationConstants;
+use pipeline::spirv::enums::Decoration;
+use pipeline::spirv::enums::Dim;
+use pipeline::spirv::enums::ExecutionMode;
+use pipeline::spirv::enums::ExecutionModel;
+use pipeline::spirv::enums::StorageClass;
+use pipeline::spirv::parse::Instruction;
+
+use Error;
+use VulkanError;
+
+pub mod enums;
+pub mod parse;
+
+// First word of every SPIR-V module.
+#[doc(hidden)]
+pub const MAGIC: u32 = 0x07230203;
+
+/// Information extracted from a SPIR-V module.
+#[derive(Debug, Clone)]
+pub struct ShaderReflection {
+    entry_points: Vec<ReflectedEntryPoint>,
+    descriptors: Vec<(u32, DescriptorDesc)>,
+    push_constants_size: usize,
+    specialization_constants: Vec<ReflectedSpecConstant>,
+    stages: ShaderStages,
+}
+
+impl ShaderReflection {
+    /// Analyzes SPIR-V code stored as bytes, in either endianness.
+    pub fn from_bytes(spirv: &[u8]) -> Result<ShaderReflection, SpirvParseError> {
+        let doc = try!(parse::parse_spirv(spirv));
+        Module { instructions: doc.instructions }.reflect()
+    }
+
+    /// Analyzes SPIR-V code stored as words in the native endianness.
+    pub fn parse(spirv: &[u32]) -> Result<ShaderReflection, SpirvParseError> {
+        let doc = try!(parse::parse_words(spirv));
+        Module { instructions: doc.instructions }.reflect()
+    }
+
+    /// Returns the list of entry points of the module.
+    #[inline]
+    pub fn entry_points(&self) -> &[ReflectedEntryPoint] {
+        &self.entry_points
+    }
+
+    /// Returns the entry point with the given name, or `None` if there is none.
+    #[inline]
+    pub fn entry_point(&self, name: &str) -> Option<&ReflectedEntryPoint> {
+        self.entry_points.iter().find(|e| e.name.to_bytes() == name.as_bytes())
+    }
+
+    /// Returns the descriptors used by the module, with the set they belong to.
+    ///
+    /// The list is sorted by set, then by binding. The stages of each descriptor are the stages
+    /// of all the entry points of the module.
+    #[inline]
+    pub fn descriptors(&self) -> &[(u32, DescriptorDesc)] {
+        &self.descriptors
+    }
+
+    /// Returns the size in bytes of the push constants used by the module, or 0 if the module
+    /// doesn't use any push constant.
+    #[inline]
+    pub fn push_constants_size(&self) -> usize {
+        self.push_constants_size
+    }
+
+    /// Returns the specialization constants of the module, sorted by constant ID.
+    ///
+    /// Only the scalar constants that have a `SpecId` decoration are returned, as they are the
+    /// only ones whose value can be provided when creating a pipeline. Constants whose type
+    /// can't be represented by a `SpecConstantValue`, such as 16-bit integers, are skipped.
+    #[inline]
+    pub fn specialization_constants(&self) -> &[ReflectedSpecConstant] {
+        &self.specialization_constants
+    }
+
+    /// Returns the specialization constant with the given name, or `None` if there is none.
+    #[inline]
+    pub fn specialization_constant(&self, name: &str) -> Option<&ReflectedSpecConstant> {
+        self.specialization_constants.iter().find(|c| c.name() == Some(name))
+    }
+
+    /// Checks that the specialization constants of `S` match the ones of the module.
+    ///
+    /// Each entry of `S` must correspond to a specialization constant of the module, and must
+    /// have the same size. Constants of the module that are not in `S` keep their default value
+    /// and are not an error.
+    pub fn check_specialization_constants<S>(&self) -> Result<(), SpecializationConstantsError>
+        where S: SpecializationConstants
+    {
+        for entry in S::descriptors() {
+            let constant = match self.specialization_constants.iter()
+                                     .find(|c| c.constant_id == entry.constant_id)
+            {
+                Some(c) => c,
+                None => return Err(SpecializationConstantsError::UnknownConstant {
+                    constant_id: entry.constant_id,
+                }),
+            };
+
+            if constant.default.size() != entry.size {
+                return Err(SpecializationConstantsError::SizeMismatch {
+                    constant_id: entry.constant_id,
+                    expected: constant.default.size(),
+                    obtained: entry.size,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the push constants described by `P` match the ones of the module.
+    ///
+    /// Each stage of the module must have access to the fields of `P` up to the size of the
+    /// push constants of the module. Since the module doesn't say which of its push constants
+    /// each stage actually reads, the offset of the fields is not checked.
+    pub fn check_push_constants<P>(&self) -> Result<(), PushConstantsError>
+        where P: PushConstantsDesc
+    {
+        if self.push_constants_size == 0 {
+            return Ok(());
+        }
+
+        let ranges = P::ranges();
+        let stages = ranges.iter().fold(ShaderStages::none(), |s, r| s.union(&r.2));
+        if !stages.is_superset_of(&self.stages) {
+            return Err(PushConstantsError::MissingStages);
+        }
+
+        for &(offset, size, stages) in ranges.iter() {
+            if stages.intersects(&self.stages) && offset + size < self.push_constants_size {
+                return Err(PushConstantsError::TooSmall {
+                    expected: self.push_constants_size,
+                    obtained: offset + size,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns a description of the pipeline layout required by the module.
+    pub fn layout_desc(&self) -> ReflectedLayoutDesc {
+        let num_sets = self.descriptors.iter().map(|&(set, _)| set + 1).max().unwrap_or(0);
+
+        let sets = (0 .. num_sets).map(|num| {
+            self.descriptors.iter().filter(|&&(set, _)| set == num).map(|&(_, desc)| desc)
+                            .collect()
+        }).collect();
+
+        let push_constants = if self.push_constants_size != 0 {
+            Some((self.push_constants_size, self.stages))
+        } else {
+            None
+        };
+
+        ReflectedLayoutDesc {
+            sets: sets,
+            push_constants: push_constants,
+        }
+    }
+}
+
+/// Description of an entry point of a SPIR-V module.
+#[derive(Debug, Clone)]
+pub struct ReflectedEntryPoint {
+    name: CString,
+    stages: ShaderStages,
+    inputs: ReflectedInterface,
+    outputs: ReflectedInterface,
+    local_size: Option<[u32; 3]>,
+}
+
+impl ReflectedEntryPoint {
+    /// Returns the name of the entry point.
+    #[inline]
+    pub fn name(&self) -> &CStr {
+        &self.name
+    }
+
+    /// Returns the stage that the entry point is made for.
+    #[inline]
+    pub fn stages(&self) -> ShaderStages {
+        self.stages
+    }
+
+    /// Returns the interface between the entry point and the previous stage.
+    #[inline]
+    pub fn inputs(&self) -> &ReflectedInterface {
+        &self.inputs
+    }
+
+    /// Returns the interface between the entry point and the next stage.
+    #[inline]
+    pub fn outputs(&self) -> &ReflectedInterface {
+        &self.outputs
+    }
+
+    /// Returns the local size of a compute entry point, or `None` if it isn't specified.
+    #[inline]
+    pub fn local_size(&self) -> Option<[u32; 3]> {
+        self.local_size
+    }
+}
+
+/// Description of a specialization constant of a SPIR-V module.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReflectedSpecConstant {
+    constant_id: u32,
+    name: Option<String>,
+    default: SpecConstantValue,
+}
+
+impl ReflectedSpecConstant {
+    /// Returns the identifier of the constant, as found in its `SpecId` decoration.
+    #[inline]
+    pub fn constant_id(&self) -> u32 {
+        self.constant_id
+    }
+
+    /// Returns the name of the constant in the source code, if the module contains it.
+    #[inline]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(|n| &n[..])
+    }
+
+    /// Returns the value of the constant when it isn't specialized. Also gives its type.
+    #[inline]
+    pub fn default_value(&self) -> SpecConstantValue {
+        self.default
+    }
+}
+
+/// Type and value of a specialization constant.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SpecConstantValue {
+    Bool(bool),
+    I32(i32),
+    U32(u32),
+    I64(i64),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+}
+
+impl SpecConstantValue {
+    /// Returns the size in bytes that the value must have in the specialization data.
+    ///
+    /// Booleans are provided as a `VkBool32` and therefore have a size of 4.
+    #[inline]
+    pub fn size(&self) -> usize {
+        match *self {
+            SpecConstantValue::Bool(_) | SpecConstantValue::I32(_) | SpecConstantValue::U32(_) |
+            SpecConstantValue::F32(_) => 4,
+            SpecConstantValue::I64(_) | SpecConstantValue::U64(_) |
+            SpecConstantValue::F64(_) => 8,
+        }
+    }
+}
+
+/// Input or output interface of an entry point, extracted from SPIR-V code.
+#[derive(Debug, Clone)]
+pub struct ReflectedInterface {
+    elements: Vec<ShaderInterfaceDefEntry>,
+}
+
+unsafe impl ShaderInterfaceDef for ReflectedInterface {
+    type Iter = VecIntoIter<ShaderInterfaceDefEntry>;
+
+    #[inline]
+    fn elements(&self) -> Self::Iter {
+        self.elements.clone().into_iter()
+    }
+}
+
+/// Description of a pipeline layout, extracted from SPIR-V code.
+#[derive(Debug, Clone)]
+pub struct ReflectedLayoutDesc {
+    sets: Vec<Vec<DescriptorDesc>>,
+    push_constants: Option<(usize, ShaderStages)>,
+}
+
+impl ReflectedLayoutDesc {
+    /// Merges two descriptions, so that the result can be used by the shaders of both.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the two descriptions use the same binding with different types of
+    ///   descriptors.
+    ///
+    pub fn union(&self, other: &ReflectedLayoutDesc) -> ReflectedLayoutDesc {
+        let num_sets = if self.sets.len() > other.sets.len() { self.sets.len() }
+                       else { other.sets.len() };
+
+        let sets = (0 .. num_sets).map(|num| {
+            let mut set: Vec<DescriptorDesc> = self.sets.get(num).cloned().unwrap_or(Vec::new());
+
+            for desc in other.sets.get(num).map(|s| &s[..]).unwrap_or(&[]) {
+                if let Some(existing) = set.iter_mut().find(|d| d.binding == desc.binding) {
+                    assert!(existing.ty.ty() == desc.ty.ty(),
+                            "Binding {} of set {} has different types", desc.binding, num);
+                    if desc.array_count > existing.array_count {
+                        existing.array_count = desc.array_count;
+                    }
+                    existing.stages = existing.stages.union(&desc.stages);
+                    existing.readonly = existing.readonly && desc.readonly;
+                    continue;
+                }
+
+                set.push(*desc);
+            }
+
+            set.sort_by_key(|desc| desc.binding);
+            set
+        }).collect();
+
+        let push_constants = match (self.push_constants, other.push_constants) {
+            (Some((a_size, a_stages)), Some((b_size, b_stages))) => {
+                let size = if a_size > b_size { a_size } else { b_size };
+                Some((size, a_stages.union(&b_stages)))
+            },
+            (Some(pc), None) | (None, Some(pc)) => Some(pc),
+            (None, None) => None,
+        };
+
+        ReflectedLayoutDesc {
+            sets: sets,
+            push_constants: push_constants,
+        }
+    }
+
+    /// Builds a pipeline layout that matches this description.
+    pub fn build(&self, device: &Arc<Device>)
+                 -> Result<Arc<RuntimePipelineLayout>, UnsafePipelineLayoutCreationError>
+    {
+        let push_constants = self.push_constants.map(|(size, stages)| (0, size, stages));
+        RuntimePipelineLayout::from_descriptors(device, self.sets.clone(), push_constants)
+    }
+}
+
+unsafe impl PipelineLayoutDesc for ReflectedLayoutDesc {
+    type SetsIter = VecIntoIter<Self::DescIter>;
+    type DescIter = VecIntoIter<DescriptorDesc>;
+
+    #[inline]
+    fn descriptors_desc(&self) -> Self::SetsIter {
+        self.sets.iter().map(|set| set.clone().into_iter()).collect::<Vec<_>>().into_iter()
+    }
+}
+
+/// Error that can happen when analyzing SPIR-V code.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SpirvParseError {
+    /// The code is too short to contain a SPIR-V header.
+    MissingHeader,
+    /// The code doesn't start with the SPIR-V magic number.
+    WrongHeader,
+    /// An instruction is truncated.
+    IncompleteInstruction,
+    /// A descriptor or an interface variable is missing a `Binding` or `Location` decoration.
+    MissingDecoration,
+    /// A type used by a descriptor or by an interface variable isn't supported.
+    UnsupportedType,
+    /// A descriptor is an array whose length isn't known, which Vulkan doesn't support.
+    RuntimeDescriptorArray,
+    /// A type refers to a type that is declared after it.
+    ForwardTypeReference,
+    /// An operand has a value that isn't valid for its enumeration. Contains the name of the
+    /// enumeration and the value.
+    UnknownConstant(&'static str, u32),
+}
+
+impl error::Error for SpirvParseError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            SpirvParseError::MissingHeader => "the code is too short to contain a SPIR-V header",
+            SpirvParseError::WrongHeader => "the code doesn't start with the SPIR-V magic number",
+            SpirvParseError::IncompleteInstruction => "an instruction is truncated",
+            SpirvParseError::MissingDecoration => {
+                "a descriptor or an interface variable is missing a decoration"
+            },
+            SpirvParseError::UnsupportedType => {
+                "a type used by a descriptor or by an interface variable isn't supported"
+            },
+            SpirvParseError::RuntimeDescriptorArray => {
+                "a descriptor is an array whose length isn't known"
+            },
+            SpirvParseError::ForwardTypeReference => {
+                "a type refers to a type that is declared after it"
+            },
+            SpirvParseError::UnknownConstant(_, _) => {
+                "an operand has a value that isn't valid for its enumeration"
+            },
+        }
+    }
+}
+
+impl fmt::Display for SpirvParseError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for SpirvParseError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        None
+    }
+}
+
+/// Error that can happen when checking specialization constants against a SPIR-V module.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SpecializationConstantsError {
+    /// The module doesn't have any specialization constant with this ID.
+    UnknownConstant {
+        constant_id: u32,
+    },
+    /// The size of a constant doesn't match the size of its type in the module.
+    SizeMismatch {
+        constant_id: u32,
+        /// Size of the type of the constant in the module.
+        expected: usize,
+        /// Size that was provided.
+        obtained: usize,
+    },
+}
+
+impl error::Error for SpecializationConstantsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            SpecializationConstantsError::UnknownConstant { .. } => {
+                "the module doesn't have any specialization constant with this ID"
+            },
+            SpecializationConstantsError::SizeMismatch { .. } => {
+                "the size of a specialization constant doesn't match its type in the module"
+            },
+        }
+    }
+}
+
+impl fmt::Display for SpecializationConstantsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for SpecializationConstantsError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        None
+    }
+}
+
+/// Error that can happen when checking push constants against a SPIR-V module.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PushConstantsError {
+    /// Some stages of the module don't have access to any field of the push constants.
+    MissingStages,
+    /// The fields accessed by a stage end before the end of the push constants of the module.
+    TooSmall {
+        /// Size of the push constants of the module.
+        expected: usize,
+        /// End of the fields that are accessed by the stage.
+        obtained: usize,
+    },
+}
+
+impl error::Error for PushConstantsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            PushConstantsError::MissingStages => {
+                "some stages of the module don't have access to the push constants"
+            },
+            PushConstantsError::TooSmall { .. } => {
+                "the push constants accessed by a stage are smaller than the ones of the module"
+            },
+        }
+    }
+}
+
+impl fmt::Display for PushConstantsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for PushConstantsError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        None
+    }
+}
+
+/// Converts SPIR-V code stored as bytes, in either endianness, to words in the native endianness.
+///
+/// The endianness is determined from the magic number. Code that doesn't start with the magic
+/// number is assumed to be little endian. Trailing bytes that don't form a whole word are
+/// ignored.
+#[doc(hidden)]
+pub fn bytes_to_words(spirv: &[u8]) -> Vec<u32> {
+    if spirv.len() >= 4 && spirv[0] == 0x07 && spirv[1] == 0x23 && spirv[2] == 0x02 &&
+       spirv[3] == 0x03
+    {
+        // Big endian.
+        spirv.chunks(4).filter(|c| c.len() == 4).map(|c| {
+            ((c[0] as u32) << 24) | ((c[1] as u32) << 16) | ((c[2] as u32) << 8) | c[3] as u32
+        }).collect()
+    } else {
+        // Little endian.
+        spirv.chunks(4).filter(|c| c.len() == 4).map(|c| {
+            ((c[3] as u32) << 24) | ((c[2] as u32) << 16) | ((c[1] as u32) << 8) | c[0] as u32
+        }).collect()
+    }
+}
+
+// Wrapper around the instructions of a module that extracts information from them.
+struct Module {
+    instructions: Vec<Instruction>,
+}
+
+impl Module {
+    // Checks that types only refer to types that are declared before them, as required by the
+    // specification. This guarantees that walking through nested types always terminates.
+    fn check_type_order(&self) -> Result<(), SpirvParseError> {
+        let mut declared = HashSet::new();
+
+        for inst in self.instructions.iter() {
+            let valid = match *inst {
+                Instruction::TypeVector { component_id: ty, .. } |
+                Instruction::TypeMatrix { column_type_id: ty, .. } |
+                Instruction::TypeSampledImage { image_type_id: ty, .. } |
+                Instruction::TypeArray { type_id: ty, .. } |
+                Instruction::TypeRuntimeArray { type_id: ty, .. } |
+                Instruction::TypePointer { type_id: ty, .. } => declared.contains(&ty),
+                Instruction::TypeStruct { ref member_types, .. } => {
+                    member_types.iter().all(|ty| declared.contains(ty))
+                },
+                _ => true,
+            };
+
+            if !valid {
+                return Err(SpirvParseError::ForwardTypeReference);
+            }
+
+            if let Some(id) = inst.result_id() {
+                declared.insert(id);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn reflect(&self) -> Result<ShaderReflection, SpirvParseError> {
+        try!(self.check_type_order());
+
+        let mut entry_points = Vec::new();
+        let mut stages = ShaderStages::none();
+
+        for inst in self.instructions.iter() {
+            let (execution, id, name, interface) = match *inst {
+                Instruction::EntryPoint { execution, id, ref name, ref interface } => {
+                    (execution, id, name, interface)
+                },
+                _ => continue,
+            };
+
+            let (entry, stage_inputs_arrayed, stage_outputs_arrayed) = match execution {
+                ExecutionModel::ExecutionModelVertex => {
+                    (ShaderStages { vertex: true, .. ShaderStages::none() }, false, false)
+                },
+                ExecutionModel::ExecutionModelTessellationControl => {
+                    (ShaderStages { tessellation_control: true, .. ShaderStages::none() },
+                     true, true)
+                },
+                ExecutionModel::ExecutionModelTessellationEvaluation => {
+                    (ShaderStages { tessellation_evaluation: true, .. ShaderStages::none() },
+                     true, false)
+                },
+                ExecutionModel::ExecutionModelGeometry => {
+                    (ShaderStages { geometry: true, .. ShaderStages::none() }, true, false)
+                },
+                ExecutionModel::ExecutionModelFragment => {
+                    (ShaderStages { fragment: true, .. ShaderStages::none() }, false, false)
+                },
+                ExecutionModel::ExecutionModelGLCompute => {
+                    (ShaderStages::compute(), false, false)
+                },
+                ExecutionModel::ExecutionModelKernel => continue,   // Not supported by Vulkan.
+            };
+
+            stages = stages.union(&entry);
+
+            let local_size = self.instructions.iter().filter_map(|i| {
+                match *i {
+                    Instruction::ExecutionMode { target_id,
+                                                 mode: ExecutionMode::ExecutionModeLocalSize,
+                                                 ref optional_literals }
+                        if target_id == id && optional_literals.len() >= 3 =>
+                    {
+                        Some([optional_literals[0], optional_literals[1], optional_literals[2]])
+                    },
+                    _ => None,
+                }
+            }).next();
+
+            entry_points.push(ReflectedEntryPoint {
+                // The parser stops strings at the first nul character.
+                name: CString::new(name.as_bytes()).unwrap(),
+                stages: entry,
+                inputs: try!(self.interface(interface, StorageClass::StorageClassInput,
+                                            stage_inputs_arrayed)),
+                outputs: try!(self.interface(interface, StorageClass::StorageClassOutput,
+                                             stage_outputs_arrayed)),
+                local_size: local_size,
+            });
+        }
+
+        let mut descriptors = Vec::new();
+        let mut push_constants_size = 0;
+
+        for inst in self.instructions.iter() {
+            let (var_ty, variable, storage_class) = match *inst {
+                Instruction::Variable { result_type_id, result_id, storage_class, .. } => {
+                    (result_type_id, result_id, storage_class)
+                },
+                _ => continue,
+            };
+
+            match storage_class {
+                StorageClass::StorageClassUniformConstant | StorageClass::StorageClassUniform |
+                StorageClass::StorageClassStorageBuffer => (),
+                StorageClass::StorageClassPushConstant => {
+                    let pointed_ty = try!(self.pointed_type(var_ty));
+                    let size = try!(self.type_size(pointed_ty)
+                                        .ok_or(SpirvParseError::UnsupportedType));
+                    if size > push_constants_size {
+                        push_constants_size = size;
+                    }
+                    continue;
+                },
+                _ => continue,
+            }
+
+            let pointed_ty = try!(self.pointed_type(var_ty));
+
+            let set = match self.decoration(variable, Decoration::DecorationDescriptorSet) {
+                Some(params) => params[0],
+                None => continue,
+            };
+
+            let binding = match self.decoration(variable, Decoration::DecorationBinding) {
+                Some(params) => params[0],
+                None => return Err(SpirvParseError::MissingDecoration),
+            };
+
+            let (ty, readonly, array_count) = try!(self.descriptor_ty(pointed_ty, storage_class,
+                                                                      false));
+            let readonly = readonly ||
+                           self.decoration(variable, Decoration::DecorationNonWritable).is_some();
+
+            descriptors.push((set, DescriptorDesc {
+                binding: binding,
+                ty: ty,
+                array_count: array_count,
+                stages: stages,
+                readonly: readonly,
+            }));
+        }
+
+        descriptors.sort_by_key(|&(set, ref desc)| (set, desc.binding));
+
+        let mut specialization_constants = try!(self.spec_constants());
+        specialization_constants.sort_by_key(|c| c.constant_id);
+
+        Ok(ShaderReflection {
+            entry_points: entry_points,
+            descriptors: descriptors,
+            push_constants_size: push_constants_size,
+            specialization_constants: specialization_constants,
+            stages: stages,
+        })
+    }
+
+    // Builds the list of the scalar specialization constants that have a `SpecId`. The constants
+    // whose type isn't supported are skipped, so that they don't prevent parsing the module.
+    fn spec_constants(&self) -> Result<Vec<ReflectedSpecConstant>, SpirvParseError> {
+        let mut constants = Vec::new();
+
+        for inst in self.instructions.iter() {
+            let id = match *inst {
+                Instruction::SpecConstantTrue { result_id, .. } |
+                Instruction::SpecConstantFalse { result_id, .. } |
+                Instruction::SpecConstant { result_id, .. } => result_id,
+                _ => continue,
+            };
+
+            let constant_id = match self.decoration(id, Decoration::DecorationSpecId) {
+                Some(params) => params[0],
+                None => continue,
+            };
+
+            let default = match *inst {
+                Instruction::SpecConstantTrue { .. } => SpecConstantValue::Bool(true),
+                Instruction::SpecConstantFalse { .. } => SpecConstantValue::Bool(false),
+                Instruction::SpecConstant { result_type_id, ref data, .. } => {
+                    match self.spec_constant_value(result_type_id, data) {
+                        Ok(value) => value,
+                        Err(SpirvParseError::UnsupportedType) => continue,
+                        Err(err) => return Err(err),
+                    }
+                },
+                _ => unreachable!(),
+            };
+
+            constants.push(ReflectedSpecConstant {
+                constant_id: constant_id,
+                name: self.name(id),
+                default: default,
+            });
+        }
+
+        Ok(constants)
+    }
+
+    // Decodes the literal value of an `OpSpecConstant` of type `ty`.
+    fn spec_constant_value(&self, ty: u32, words: &[u32])
+                           -> Result<SpecConstantValue, SpirvParseError>
+    {
+        let (width, signed, float) = match self.definition(ty) {
+            Some(&Instruction::TypeInt { width, signedness, .. }) => (width, signedness, false),
+            Some(&Instruction::TypeFloat { width, .. }) => (width, false, true),
+            _ => return Err(SpirvParseError::UnsupportedType),
+        };
+
+        let num_words = match width {
+            32 => 1,
+            64 => 2,
+            _ => return Err(SpirvParseError::UnsupportedType),
+        };
+
+        if words.len() < num_words {
+            return Err(SpirvParseError::IncompleteInstruction);
+        }
+
+        let bits = words[.. num_words].iter().rev().fold(0u64, |a, &b| (a << 32) | b as u64);
+
+        Ok(match (width, signed, float) {
+            (32, _, true) => SpecConstantValue::F32(f32::from_bits(bits as u32)),
+            (64, _, true) => SpecConstantValue::F64(f64::from_bits(bits)),
+            (32, true, _) => SpecConstantValue::I32(bits as u32 as i32),
+            (32, false, _) => SpecConstantValue::U32(bits as u32),
+            (64, true, _) => SpecConstantValue::I64(bits as i64),
+            _ => SpecConstantValue::U64(bits),
+        })
+    }
+
+    // Builds the interface made of the variables of `interface` with the given storage class.
+    fn interface(&self, interface: &[u32], storage_class: StorageClass, arrayed: bool)
+                 -> Result<ReflectedInterface, SpirvParseError>
+    {
+        let mut elements = Vec::new();
+
+        for &variable in interface {
+            let var_ty = match self.definition(variable) {
+                Some(&Instruction::Variable { result_type_id, storage_class: class, .. })
+                    if class == storage_class => result_type_id,
+                _ => continue,
+            };
+
+            if self.decoration(variable, Decoration::DecorationBuiltIn).is_some() {
+                continue;
+            }
+
+            let mut ty = try!(self.pointed_type(var_ty));
+
+            // Blocks of built-in variables, such as `gl_PerVertex`.
+            let block = self.strip_arrays(ty);
+            let is_builtin_block = self.instructions.iter().any(|i| {
+                match *i {
+                    Instruction::MemberDecorate { target_id,
+                                                  decoration: Decoration::DecorationBuiltIn, .. }
+                        => target_id == block,
+                    _ => false,
+                }
+            });
+            if is_builtin_block {
+                continue;
+            }
+
+            // The inputs of some stages are arrays containing one element per vertex.
+            if arrayed {
+                match self.definition(ty) {
+                    Some(&Instruction::TypeArray { type_id, .. }) |
+                    Some(&Instruction::TypeRuntimeArray { type_id, .. }) => ty = type_id,
+                    _ => (),
+                }
+            }
+
+            let location = match self.decoration(variable, Decoration::DecorationLocation) {
+                Some(params) => params[0],
+                None => return Err(SpirvParseError::MissingDecoration),
+            };
+
+            let (format, num_locations) = try!(self.interface_format(ty));
+            let end = try!(location.checked_add(num_locations)
+                                   .ok_or(SpirvParseError::UnsupportedType));
+
+            elements.push(ShaderInterfaceDefEntry {
+                location: location .. end,
+                format: format,
+                name: self.name(variable).map(|n| Cow::Owned(n)),
+            });
+        }
+
+        Ok(ReflectedInterface { elements: elements })
+    }
+
+    // Returns the format of an interface type and the number of locations it uses.
+    fn interface_format(&self, ty: u32) -> Result<(Format, u32), SpirvParseError> {
+        match self.definition(ty) {
+            Some(&Instruction::TypeArray { type_id, length_id, .. }) => {
+                let (format, num) = try!(self.interface_format(type_id));
+                let len = try!(self.constant(length_id).ok_or(SpirvParseError::UnsupportedType));
+                let num = try!(multiply(num, len));
+                return Ok((format, num));
+            },
+            Some(&Instruction::TypeMatrix { column_type_id, column_count, .. }) => {
+                let (format, num) = try!(self.interface_format(column_type_id));
+                let num = try!(multiply(num, column_count as u64));
+                return Ok((format, num));
+            },
+            _ => (),
+        }
+
+        let (component, count) = match self.definition(ty) {
+            Some(&Instruction::TypeVector { component_id, count, .. }) => (component_id, count),
+            _ => (ty, 1),
+        };
+
+        // `kind` is 0 for unsigned integers, 1 for signed integers and 2 for floats.
+        let (kind, width) = match self.definition(component) {
+            Some(&Instruction::TypeFloat { width, .. }) => (2, width),
+            Some(&Instruction::TypeInt { width, signedness, .. }) => {
+                (if signedness { 1 } else { 0 }, width)
+            },
+            _ => return Err(SpirvParseError::UnsupportedType),
+        };
+
+        let format = match (width, kind, count) {
+            (32, 0, 1) => Format::R32Uint,
+            (32, 1, 1) => Format::R32Sint,
+            (32, 2, 1) => Format::R32Sfloat,
+            (32, 0, 2) => Format::R32G32Uint,
+            (32, 1, 2) => Format::R32G32Sint,
+            (32, 2, 2) => Format::R32G32Sfloat,
+            (32, 0, 3) => Format::R32G32B32Uint,
+            (32, 1, 3) => Format::R32G32B32Sint,
+            (32, 2, 3) => Format::R32G32B32Sfloat,
+            (32, 0, 4) => Format::R32G32B32A32Uint,
+            (32, 1, 4) => Format::R32G32B32A32Sint,
+            (32, 2, 4) => Format::R32G32B32A32Sfloat,
+            (64, 0, 1) => Format::R64Uint,
+            (64, 1, 1) => Format::R64Sint,
+            (64, 2, 1) => Format::R64Sfloat,
+            (64, 0, 2) => Format::R64G64Uint,
+            (64, 1, 2) => Format::R64G64Sint,
+            (64, 2, 2) => Format::R64G64Sfloat,
+            (64, 0, 3) => Format::R64G64B64Uint,
+            (64, 1, 3) => Format::R64G64B64Sint,
+            (64, 2, 3) => Format::R64G64B64Sfloat,
+            (64, 0, 4) => Format::R64G64B64A64Uint,
+            (64, 1, 4) => Format::R64G64B64A64Sint,
+            (64, 2, 4) => Format::R64G64B64A64Sfloat,
+            _ => return Err(SpirvParseError::UnsupportedType),
+        };
+
+        // 64-bits vectors of three or four components use two locations.
+        let num_locations = if width == 64 && count >= 3 { 2 } else { 1 };
+        Ok((format, num_locations))
+    }
+
+    // Returns the description of a descriptor, whether it is read-only and its number of array
+    // elements.
+    fn descriptor_ty(&self, ty: u32, storage_class: StorageClass,
+                     force_combined_image_sampler: bool)
+                     -> Result<(DescriptorDescTy, bool, u32), SpirvParseError>
+    {
+        match self.definition(ty) {
+            Some(&Instruction::TypeArray { type_id, length_id, .. }) => {
+                let force_combined = force_combined_image_sampler;
+                let (desc, readonly, count) = try!(self.descriptor_ty(type_id, storage_class,
+                                                                      force_combined));
+                let len = try!(self.constant(length_id).ok_or(SpirvParseError::UnsupportedType));
+                let count = try!(multiply(count, len));
+                Ok((desc, readonly, count))
+            },
+
+            Some(&Instruction::TypeRuntimeArray { .. }) => {
+                Err(SpirvParseError::RuntimeDescriptorArray)
+            },
+
+            Some(&Instruction::TypeStruct { .. }) => {
+                let storage = if self.decoration(ty, Decoration::DecorationBufferBlock).is_some() {
+                    true
+                } else if self.decoration(ty, Decoration::DecorationBlock).is_some() {
+                    storage_class == StorageClass::StorageClassStorageBuffer
+                } else {
+                    return Err(SpirvParseError::UnsupportedType);
+                };
+
+                let desc = DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                    dynamic: Some(false),
+                    storage: storage,
+                });
+
+                Ok((desc, !storage, 1))
+            },
+
+            Some(&Instruction::TypeImage { dim, arrayed, ms, sampled, .. }) => {
+                let sampled = match sampled {
+                    Some(sampled) => sampled,
+                    None => return Err(SpirvParseError::UnsupportedType),
+                };
+
+                let array_layers = if arrayed {
+                    DescriptorImageDescArray::Arrayed { max_layers: None }
+                } else {
+                    DescriptorImageDescArray::NonArrayed
+                };
+
+                match dim {
+                    // Input attachments can only be read.
+                    Dim::DimSubpassData => {
+                        let desc = DescriptorDescTy::InputAttachment {
+                            multisampled: ms,
+                            array_layers: array_layers,
+                        };
+
+                        Ok((desc, true, 1))
+                    },
+
+                    Dim::DimBuffer => {
+                        let desc = DescriptorDescTy::TexelBuffer {
+                            storage: !sampled,
+                            format: None,
+                        };
+
+                        Ok((desc, sampled, 1))
+                    },
+
+                    _ => {
+                        let dimensions = match dim {
+                            Dim::Dim1D => DescriptorImageDescDimensions::OneDimensional,
+                            Dim::Dim2D => DescriptorImageDescDimensions::TwoDimensional,
+                            Dim::Dim3D => DescriptorImageDescDimensions::ThreeDimensional,
+                            Dim::DimCube => DescriptorImageDescDimensions::Cube,
+                            _ => return Err(SpirvParseError::UnsupportedType),
+                        };
+
+                        let desc = DescriptorImageDesc {
+                            sampled: sampled,
+                            dimensions: dimensions,
+                            format: None,
+                            multisampled: ms,
+                            array_layers: array_layers,
+                        };
+
+                        let desc = if force_combined_image_sampler {
+                            DescriptorDescTy::CombinedImageSampler(desc)
+                        } else {
+                            DescriptorDescTy::Image(desc)
+                        };
+
+                        Ok((desc, sampled, 1))
+                    },
+                }
+            },
+
+            Some(&Instruction::TypeSampledImage { image_type_id, .. }) => {
+                self.descriptor_ty(image_type_id, storage_class, true)
+            },
+
+            Some(&Instruction::TypeSampler { .. }) => Ok((DescriptorDescTy::Sampler, true, 1)),
+
+            _ => Err(SpirvParseError::UnsupportedType),
+        }
+    }
+
+    // Returns the size in bytes of a type, or `None` if it is unknown.
+    fn type_size(&self, ty: u32) -> Option<usize> {
+        match self.definition(ty) {
+            Some(&Instruction::TypeInt { width, .. }) |
+            Some(&Instruction::TypeFloat { width, .. }) => Some(width as usize / 8),
+
+            Some(&Instruction::TypeVector { component_id, count, .. }) => {
+                self.type_size(component_id).and_then(|s| s.checked_mul(count as usize))
+            },
+
+            Some(&Instruction::TypeMatrix { column_type_id, column_count, .. }) => {
+                self.type_size(column_type_id).and_then(|s| s.checked_mul(column_count as usize))
+            },
+
+            Some(&Instruction::TypeArray { type_id, length_id, .. }) => {
+                let len = match self.constant(length_id) {
+                    Some(len) => len as usize,
+                    None => return None,
+                };
+
+                let stride = match self.decoration(ty, Decoration::DecorationArrayStride) {
+                    Some(params) => params[0] as usize,
+                    None => match self.type_size(type_id) {
+                        Some(s) => s,
+                        None => return None,
+                    },
+                };
+
+                stride.checked_mul(len)
+            },
+
+            Some(&Instruction::TypeStruct { ref member_types, .. }) => {
+                let mut size = 0;
+
+                for (num, &member_ty) in member_types.iter().enumerate() {
+                    let offset = match self.member_decoration(ty, num as u32,
+                                                              Decoration::DecorationOffset)
+                    {
+                        Some(params) => params[0] as usize,
+                        None => return None,
+                    };
+
+                    let matrix_stride = self.member_decoration(ty, num as u32,
+                                                               Decoration::DecorationMatrixStride);
+                    let member_size = match (self.definition(member_ty), matrix_stride) {
+                        (Some(&Instruction::TypeMatrix { column_count, .. }), Some(params)) => {
+                            match (params[0] as usize).checked_mul(column_count as usize) {
+                                Some(s) => s,
+                                None => return None,
+                            }
+                        },
+                        _ => match self.type_size(member_ty) {
+                            Some(s) => s,
+                            None => return None,
+                        },
+                    };
+
+                    match offset.checked_add(member_size) {
+                        Some(end) if end > size => size = end,
+                        Some(_) => (),
+                        None => return None,
+                    }
+                }
+
+                Some(size)
+            },
+
+            _ => None,
+        }
+    }
+
+    // Returns the type pointed to by a pointer type.
+    fn pointed_type(&self, pointer: u32) -> Result<u32, SpirvParseError> {
+        match self.definition(pointer) {
+            Some(&Instruction::TypePointer { type_id, .. }) => Ok(type_id),
+            _ => Err(SpirvParseError::UnsupportedType),
+        }
+    }
+
+    // Returns the type of the elements of `ty` if it is an array, or `ty` otherwise.
+    fn strip_arrays(&self, ty: u32) -> u32 {
+        match self.definition(ty) {
+            Some(&Instruction::TypeArray { type_id, .. }) |
+            Some(&Instruction::TypeRuntimeArray { type_id, .. }) => self.strip_arrays(type_id),
+            _ => ty,
+        }
+    }
+
+    // Returns the value of an integer constant.
+    fn constant(&self, id: u32) -> Option<u64> {
+        match self.definition(id) {
+            Some(&Instruction::Constant { ref data, .. }) => {
+                Some(data.iter().rev().fold(0u64, |a, &b| (a << 32) | b as u64))
+            },
+            _ => None,
+        }
+    }
+
+    // Returns the name of an object, if it has one.
+    fn name(&self, id: u32) -> Option<String> {
+        self.instructions.iter().filter_map(|i| {
+            match *i {
+                Instruction::Name { target_id, ref name } if target_id == id => Some(name),
+                _ => None,
+            }
+        }).next().and_then(|name| if name.is_empty() { None } else { Some(name.clone()) })
+    }
+
+    // Returns the parameters of a decoration of an object.
+    //
+    // The parser guarantees that decorations which have a literal parameter aren't empty.
+    fn decoration(&self, target: u32, decoration: Decoration) -> Option<&[u32]> {
+        self.instructions.iter().filter_map(|i| {
+            match *i {
+                Instruction::Decorate { target_id, decoration: d, ref params }
+                    if target_id == target && d == decoration => Some(&params[..]),
+                _ => None,
+            }
+        }).next()
+    }
+
+    // Returns the parameters of a decoration of a member of a struct.
+    fn member_decoration(&self, target: u32, member: u32, decoration: Decoration)
+                         -> Option<&[u32]>
+    {
+        self.instructions.iter().filter_map(|i| {
+            match *i {
+                Instruction::MemberDecorate { target_id, member: m, decoration: d, ref params }
+                    if target_id == target && m == member && d == decoration => Some(&params[..]),
+                _ => None,
+            }
+        }).next()
+    }
+
+    // Returns the instruction that declares the given ID.
+    fn definition(&self, id: u32) -> Option<&Instruction> {
+        self.instructions.iter().find(|i| i.result_id() == Some(id))
+    }
+}
+
+// Multiplies a number of elements by the length of an array, or returns an error on overflow.
+#[inline]
+fn multiply(num: u32, len: u64) -> Result<u32, SpirvParseError> {
+    if len > u32::max_value() as u64 {
+        return Err(SpirvParseError::UnsupportedType);
+    }
+
+    num.checked_mul(len as u32).ok_or(SpirvParseError::UnsupportedType)
+}
+
+#[cfg(test)]
+mod tests {
+    use descriptor::descriptor::DescriptorBufferDesc;
+    use descriptor::descriptor::DescriptorDescTy;
+    use descriptor::pipeline_layout::PipelineLayoutDesc;
+    use pipeline::shader::SpecializationConstants;
+    use pipeline::shader::SpecializationMapEntry;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::pipeline_layout::PushConstantMember;
+    use descriptor::pipeline_layout::PushConstantsDesc;
+    use pipeline::spirv::PushConstantsError;
+    use pipeline::spirv::ShaderReflection;
+    use pipeline::spirv::SpecConstantValue;
+    use pipeline::spirv::SpecializationConstantsError;
+    use pipeline::spirv::SpirvParseError;
+
+    // Compute shader with a local size of 64x1x1, a storage buffer at set 0 binding 1, and an
+    // array of two uniform buffers at set 1 binding 0.
+    fn compute_shader() -> Vec<u32> {
+        vec![
+            0x07230203, 0x00010000, 0, 20, 0,
+            (5 << 16) | 15, 5, 1, 0x6e69616d, 0,                    // OpEntryPoint "main"
+            (6 << 16) | 16, 1, 17, 64, 1, 1,                        // OpExecutionMode LocalSize
+            (3 << 16) | 71, 3, 3,                                   // OpDecorate %3 BufferBlock
+            (5 << 16) | 72, 3, 0, 35, 0,                            // OpMemberDecorate Offset 0
+            (4 << 16) | 71, 5, 34, 0,                               // OpDecorate %5 Set 0
+            (4 << 16) | 71, 5, 33, 1,                               // OpDecorate %5 Binding 1
+            (3 << 16) | 71, 6, 2,                                   // OpDecorate %6 Block
+            (5 << 16) | 72, 6, 0, 35, 0,                            // OpMemberDecorate Offset 0
+            (4 << 16) | 71, 10, 34, 1,                              // OpDecorate %10 Set 1
+            (4 << 16) | 71, 10, 33, 0,                              // OpDecorate %10 Binding 0
+            (3 << 16) | 22, 2, 32,                                  // %2 = OpTypeFloat 32
+            (3 << 16) | 30, 3, 2,                                   // %3 = OpTypeStruct %2
+            (4 << 16) | 32, 4, 2, 3,                                // %4 = OpTypePointer %3
+            (4 << 16) | 59, 4, 5, 2,                                // %5 = OpVariable %4
+            (3 << 16) | 30, 6, 2,                                   // %6 = OpTypeStruct %2
+            (4 << 16) | 21, 11, 32, 0,                              // %11 = OpTypeInt 32 0
+            (4 << 16) | 43, 11, 7, 2,                               // %7 = OpConstant 2
+            (4 << 16) | 28, 8, 6, 7,                                // %8 = OpTypeArray %6 %7
+            (4 << 16) | 32, 9, 2, 8,                                // %9 = OpTypePointer %8
+            (4 << 16) | 59, 9, 10, 2,                               // %10 = OpVariable %9
+        ]
+    }
+
+    #[test]
+    fn compute_descriptors() {
+        let reflection = ShaderReflection::parse(&compute_shader()).unwrap();
+
+        let entry = reflection.entry_point("main").unwrap();
+        assert_eq!(entry.local_size(), Some([64, 1, 1]));
+        assert!(entry.stages().compute);
+
+        let descriptors = reflection.descriptors();
+        assert_eq!(descriptors.len(), 2);
+
+        assert_eq!(descriptors[0].0, 0);
+        assert_eq!(descriptors[0].1.binding, 1);
+        assert_eq!(descriptors[0].1.ty, DescriptorDescTy::Buffer(DescriptorBufferDesc {
+            dynamic: Some(false),
+            storage: true,
+        }));
+
+        assert_eq!(descriptors[1].0, 1);
+        assert_eq!(descriptors[1].1.binding, 0);
+        assert_eq!(descriptors[1].1.array_count, 2);
+
+        let layout = reflection.layout_desc();
+        assert_eq!(layout.descriptors_desc().count(), 2);
+    }
+
+    #[test]
+    fn wrong_header() {
+        let mut code = compute_shader();
+        code[0] = 0;
+        assert_eq!(ShaderReflection::parse(&code).unwrap_err(), SpirvParseError::WrongHeader);
+    }
+
+    // Fragment shader with an input attachment at set 0 binding 0, or with an array of input
+    // attachments of unknown length if `runtime_array` is true.
+    fn input_attachment_shader(runtime_array: bool) -> Vec<u32> {
+        let mut code = vec![
+            0x07230203, 0x00010000, 0, 7, 0,
+            (5 << 16) | 15, 4, 1, 0x6e69616d, 0,                    // OpEntryPoint "main"
+            (4 << 16) | 71, 4, 34, 0,                               // OpDecorate %4 Set 0
+            (4 << 16) | 71, 4, 33, 0,                               // OpDecorate %4 Binding 0
+            (3 << 16) | 22, 2, 32,                                  // %2 = OpTypeFloat 32
+            (9 << 16) | 25, 3, 2, 6, 0, 0, 0, 2, 0,                 // %3 = OpTypeImage SubpassData
+        ];
+
+        if runtime_array {
+            code.extend_from_slice(&[
+                (3 << 16) | 29, 6, 3,                               // %6 = OpTypeRuntimeArray %3
+                (4 << 16) | 32, 5, 0, 6,                            // %5 = OpTypePointer %6
+            ]);
+        } else {
+            code.extend_from_slice(&[(4 << 16) | 32, 5, 0, 3]);     // %5 = OpTypePointer %3
+        }
+
+        code.extend_from_slice(&[(4 << 16) | 59, 5, 4, 0]);         // %4 = OpVariable %5
+        code
+    }
+
+    #[test]
+    fn input_attachment_readonly() {
+        let reflection = ShaderReflection::parse(&input_attachment_shader(false)).unwrap();
+
+        let descriptors = reflection.descriptors();
+        assert_eq!(descriptors.len(), 1);
+        assert!(descriptors[0].1.readonly);
+        match descriptors[0].1.ty {
+            DescriptorDescTy::InputAttachment { multisampled: false, .. } => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn runtime_descriptor_array() {
+        assert_eq!(ShaderReflection::parse(&input_attachment_shader(true)).unwrap_err(),
+                   SpirvParseError::RuntimeDescriptorArray);
+    }
+
+    #[test]
+    fn forward_type_reference() {
+        // %5 = OpTypePointer %5
+        let mut code = input_attachment_shader(false);
+        let len = code.len();
+        code[len - 5] = 5;
+        assert_eq!(ShaderReflection::parse(&code).unwrap_err(),
+                   SpirvParseError::ForwardTypeReference);
+    }
+
+    // Compute shader with three specialization constants: a `bool` named "enabled" with ID 2,
+    // a `float` named "scale" with ID 0, and an `int` without a `SpecId`.
+    fn spec_constants_shader() -> Vec<u32> {
+        vec![
+            0x07230203, 0x00010000, 0, 10, 0,
+            (5 << 16) | 15, 5, 1, 0x6e69616d, 0,                    // OpEntryPoint "main"
+            (4 << 16) | 5, 3, 0x6c616373, 0x65,                     // OpName %3 "scale"
+            (4 << 16) | 5, 4, 0x62616e65, 0x64656c,                 // OpName %4 "enabled"
+            (4 << 16) | 71, 3, 1, 0,                                // OpDecorate %3 SpecId 0
+            (4 << 16) | 71, 4, 1, 2,                                // OpDecorate %4 SpecId 2
+            (3 << 16) | 22, 1, 32,                                  // %1 = OpTypeFloat 32
+            (2 << 16) | 20, 2,                                      // %2 = OpTypeBool
+            (4 << 16) | 21, 6, 32, 1,                               // %6 = OpTypeInt 32 1
+            (4 << 16) | 50, 1, 3, 0x3fc00000,                       // %3 = OpSpecConstant 1.5
+            (3 << 16) | 48, 2, 4,                                   // %4 = OpSpecConstantTrue
+            (4 << 16) | 50, 6, 7, 12,                               // %7 = OpSpecConstant 12
+        ]
+    }
+
+    #[test]
+    fn spec_constants() {
+        let reflection = ShaderReflection::parse(&spec_constants_shader()).unwrap();
+
+        let constants = reflection.specialization_constants();
+        assert_eq!(constants.len(), 2);
+
+        assert_eq!(constants[0].constant_id(), 0);
+        assert_eq!(constants[0].name(), Some("scale"));
+        assert_eq!(constants[0].default_value(), SpecConstantValue::F32(1.5));
+
+        assert_eq!(constants[1].constant_id(), 2);
+        assert_eq!(constants[1].name(), Some("enabled"));
+        assert_eq!(constants[1].default_value(), SpecConstantValue::Bool(true));
+
+        assert_eq!(reflection.specialization_constant("enabled").unwrap().constant_id(), 2);
+        assert!(reflection.specialization_constant("other").is_none());
+    }
+
+    #[test]
+    fn unsupported_spec_constant() {
+        let mut code = spec_constants_shader();
+        code.extend_from_slice(&[
+            (4 << 16) | 71, 9, 1, 1,                                // OpDecorate %9 SpecId 1
+            (4 << 16) | 21, 8, 16, 0,                               // %8 = OpTypeInt 16 0
+            (4 << 16) | 50, 8, 9, 7,                                // %9 = OpSpecConstant 7
+        ]);
+
+        let reflection = ShaderReflection::parse(&code).unwrap();
+        let ids = reflection.specialization_constants().iter().map(|c| c.constant_id())
+                            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![0, 2]);
+    }
+
+    #[test]
+    fn check_spec_constants() {
+        #[allow(dead_code)]
+        struct Valid { scale: f32, enabled: u32 }
+        unsafe impl SpecializationConstants for Valid {
+            fn descriptors() -> &'static [SpecializationMapEntry] {
+                static DESCRIPTORS: [SpecializationMapEntry; 2] = [
+                    SpecializationMapEntry { constant_id: 0, offset: 0, size: 4 },
+                    SpecializationMapEntry { constant_id: 2, offset: 4, size: 4 },
+                ];
+                &DESCRIPTORS
+            }
+        }
+
+        #[allow(dead_code)]
+        struct WrongSize { scale: f64 }
+        unsafe impl SpecializationConstants for WrongSize {
+            fn descriptors() -> &'static [SpecializationMapEntry] {
+                static DESCRIPTORS: [SpecializationMapEntry; 1] = [
+                    SpecializationMapEntry { constant_id: 0, offset: 0, size: 8 },
+                ];
+                &DESCRIPTORS
+            }
+        }
+
+        #[allow(dead_code)]
+        struct Unknown { value: u32 }
+        unsafe impl SpecializationConstants for Unknown {
+            fn descriptors() -> &'static [SpecializationMapEntry] {
+                static DESCRIPTORS: [SpecializationMapEntry; 1] = [
+                    SpecializationMapEntry { constant_id: 1, offset: 0, size: 4 },
+                ];
+                &DESCRIPTORS
+            }
+        }
+
+        let reflection = ShaderReflection::parse(&spec_constants_shader()).unwrap();
+        assert_eq!(reflection.check_specialization_constants::<()>(), Ok(()));
+        assert_eq!(reflection.check_specialization_constants::<Valid>(), Ok(()));
+        assert_eq!(reflection.check_specialization_constants::<WrongSize>(),
+                   Err(SpecializationConstantsError::SizeMismatch {
+                       constant_id: 0,
+                       expected: 4,
+                       obtained: 8,
+                   }));
+        assert_eq!(reflection.check_specialization_constants::<Unknown>(),
+                   Err(SpecializationConstantsError::UnknownConstant { constant_id: 1 }));
+    }
+
+    #[test]
+    fn check_push_constants() {
+        struct Vertex;
+        unsafe impl PushConstantsDesc for Vertex {
+            fn members() -> Vec<PushConstantMember> {
+                vec![PushConstantMember { offset: 0, size: 16, stages: vertex() }]
+            }
+        }
+
+        struct Short;
+        unsafe impl PushConstantsDesc for Short {
+            fn members() -> Vec<PushConstantMember> {
+                vec![PushConstantMember { offset: 0, size: 8, stages: vertex() }]
+            }
+        }
+
+        fn vertex() -> ShaderStages {
+            ShaderStages { vertex: true, .. ShaderStages::none() }
+        }
+
+        let reflection = ShaderReflection {
+            entry_points: Vec::new(),
+            descriptors: Vec::new(),
+            push_constants_size: 16,
+            specialization_constants: Vec::new(),
+            stages: vertex(),
+        };
+
+        assert_eq!(reflection.check_push_constants::<Vertex>(), Ok(()));
+        assert_eq!(reflection.check_push_constants::<Short>(),
+                   Err(PushConstantsError::TooSmall { expected: 16, obtained: 8 }));
+        assert_eq!(reflection.check_push_constants::<()>(),
+                   Err(PushConstantsError::MissingStages));
+
+        let compute = ShaderReflection::parse(&compute_shader()).unwrap();
+        assert_eq!(compute.check_push_constants::<()>(), Ok(()));
+    }
+}
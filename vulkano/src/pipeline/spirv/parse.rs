@@ -0,0 +1,507 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Low-level parsing of SPIR-V code into a list of instructions.
+//!
+//! This is the parser used both by `ShaderReflection` and by the `vulkano-shaders` crate. Only
+//! the instructions that are needed to describe the interface of a module are decoded, the other
+//! ones are returned as `Instruction::Unknown`.
+//!
+//! Malformed code never makes the parser panic. Instead an error is returned if an instruction
+//! doesn't have enough operands for its opcode, or if an operand isn't a valid enumeration
+//! value. Capabilities, decorations and storage classes that are unknown to the parser, for
+//! example because they come from an extension, are not an error and the instructions that use
+//! them are returned as `Instruction::Unknown`.
+
+use pipeline::spirv::MAGIC;
+use pipeline::spirv::SpirvParseError;
+use pipeline::spirv::bytes_to_words;
+use pipeline::spirv::enums::*;
+
+/// Parses SPIR-V code stored as bytes, in either endianness.
+pub fn parse_spirv(data: &[u8]) -> Result<Spirv, SpirvParseError> {
+    if data.len() < 20 || data.len() % 4 != 0 {
+        return Err(SpirvParseError::MissingHeader);
+    }
+
+    parse_words(&bytes_to_words(data))
+}
+
+/// Parses SPIR-V code stored as words in the native endianness.
+pub fn parse_words(i: &[u32]) -> Result<Spirv, SpirvParseError> {
+    if i.len() < 5 {
+        return Err(SpirvParseError::MissingHeader);
+    }
+
+    if i[0] != MAGIC {
+        return Err(SpirvParseError::WrongHeader);
+    }
+
+    let version = (((i[1] & 0x00ff0000) >> 16) as u8, ((i[1] & 0x0000ff00) >> 8) as u8);
+
+    let instructions = {
+        let mut ret = Vec::new();
+        let mut i = &i[5..];
+        while i.len() >= 1 {
+            let (instruction, rest) = try!(parse_instruction(i));
+            ret.push(instruction);
+            i = rest;
+        }
+        ret
+    };
+
+    Ok(Spirv {
+        version: version,
+        bound: i[3],
+        instructions: instructions,
+    })
+}
+
+/// A parsed SPIR-V module.
+#[derive(Debug, Clone)]
+pub struct Spirv {
+    /// Major and minor version of SPIR-V that the module uses.
+    pub version: (u8, u8),
+    /// All the IDs of the module are strictly inferior to this value.
+    pub bound: u32,
+    /// List of the instructions of the module.
+    pub instructions: Vec<Instruction>,
+}
+
+/// A decoded SPIR-V instruction.
+#[derive(Debug, Clone)]
+pub enum Instruction {
+    Unknown(u16, Vec<u32>),
+    Nop,
+    Name { target_id: u32, name: String },
+    MemberName { target_id: u32, member: u32, name: String },
+    ExtInstImport { result_id: u32, name: String },
+    MemoryModel(AddressingModel, MemoryModel),
+    EntryPoint { execution: ExecutionModel, id: u32, name: String, interface: Vec<u32> },
+    ExecutionMode { target_id: u32, mode: ExecutionMode, optional_literals: Vec<u32> },
+    Capability(Capability),
+    TypeVoid { result_id: u32 },
+    TypeBool { result_id: u32 },
+    TypeInt { result_id: u32, width: u32, signedness: bool },
+    TypeFloat { result_id: u32, width: u32 },
+    TypeVector { result_id: u32, component_id: u32, count: u32 },
+    TypeMatrix { result_id: u32, column_type_id: u32, column_count: u32 },
+    TypeImage {
+        result_id: u32,
+        sampled_type_id: u32,
+        dim: Dim,
+        depth: Option<bool>,
+        arrayed: bool,
+        ms: bool,
+        sampled: Option<bool>,
+        format: ImageFormat,
+        access: Option<AccessQualifier>,
+    },
+    TypeSampler { result_id: u32 },
+    TypeSampledImage { result_id: u32, image_type_id: u32 },
+    TypeArray { result_id: u32, type_id: u32, length_id: u32 },
+    TypeRuntimeArray { result_id: u32, type_id: u32 },
+    TypeStruct { result_id: u32, member_types: Vec<u32> },
+    TypeOpaque { result_id: u32, name: String },
+    TypePointer { result_id: u32, storage_class: StorageClass, type_id: u32 },
+    Constant { result_type_id: u32, result_id: u32, data: Vec<u32> },
+    SpecConstantTrue { result_type_id: u32, result_id: u32 },
+    SpecConstantFalse { result_type_id: u32, result_id: u32 },
+    SpecConstant { result_type_id: u32, result_id: u32, data: Vec<u32> },
+    FunctionEnd,
+    Variable {
+        result_type_id: u32,
+        result_id: u32,
+        storage_class: StorageClass,
+        initializer: Option<u32>,
+    },
+    Decorate { target_id: u32, decoration: Decoration, params: Vec<u32> },
+    MemberDecorate { target_id: u32, member: u32, decoration: Decoration, params: Vec<u32> },
+    Label { result_id: u32 },
+    Branch { result_id: u32 },
+    Kill,
+    Return,
+}
+
+impl Instruction {
+    /// Returns the ID of the type, constant or variable that the instruction declares, if any.
+    pub fn result_id(&self) -> Option<u32> {
+        match *self {
+            Instruction::TypeVoid { result_id } |
+            Instruction::TypeBool { result_id } |
+            Instruction::TypeInt { result_id, .. } |
+            Instruction::TypeFloat { result_id, .. } |
+            Instruction::TypeVector { result_id, .. } |
+            Instruction::TypeMatrix { result_id, .. } |
+            Instruction::TypeImage { result_id, .. } |
+            Instruction::TypeSampler { result_id } |
+            Instruction::TypeSampledImage { result_id, .. } |
+            Instruction::TypeArray { result_id, .. } |
+            Instruction::TypeRuntimeArray { result_id, .. } |
+            Instruction::TypeStruct { result_id, .. } |
+            Instruction::TypeOpaque { result_id, .. } |
+            Instruction::TypePointer { result_id, .. } |
+            Instruction::Constant { result_id, .. } |
+            Instruction::SpecConstantTrue { result_id, .. } |
+            Instruction::SpecConstantFalse { result_id, .. } |
+            Instruction::SpecConstant { result_id, .. } |
+            Instruction::Variable { result_id, .. } => Some(result_id),
+            _ => None,
+        }
+    }
+}
+
+fn parse_instruction(i: &[u32]) -> Result<(Instruction, &[u32]), SpirvParseError> {
+    debug_assert!(i.len() >= 1);
+
+    let word_count = (i[0] >> 16) as usize;
+    let opcode = (i[0] & 0xffff) as u16;
+
+    if word_count == 0 || i.len() < word_count {
+        return Err(SpirvParseError::IncompleteInstruction);
+    }
+
+    let opcode = try!(decode_instruction(opcode, &i[1 .. word_count]));
+    Ok((opcode, &i[word_count..]))
+}
+
+fn decode_instruction(opcode: u16, operands: &[u32]) -> Result<Instruction, SpirvParseError> {
+    // Returns the operand at the given index, or an error if the instruction is too short.
+    let op = |index: usize| -> Result<u32, SpirvParseError> {
+        operands.get(index).cloned().ok_or(SpirvParseError::IncompleteInstruction)
+    };
+
+    // Returns the operands starting at the given index.
+    let rest = |index: usize| -> Result<&[u32], SpirvParseError> {
+        if index <= operands.len() {
+            Ok(&operands[index..])
+        } else {
+            Err(SpirvParseError::IncompleteInstruction)
+        }
+    };
+
+    Ok(match opcode {
+        0 => Instruction::Nop,
+        5 => Instruction::Name {
+            target_id: try!(op(0)),
+            name: parse_string(try!(rest(1))).0,
+        },
+        6 => Instruction::MemberName {
+            target_id: try!(op(0)),
+            member: try!(op(1)),
+            name: parse_string(try!(rest(2))).0,
+        },
+        11 => Instruction::ExtInstImport {
+            result_id: try!(op(0)),
+            name: parse_string(try!(rest(1))).0,
+        },
+        14 => Instruction::MemoryModel(try!(AddressingModel::from_num(try!(op(0)))),
+                                       try!(MemoryModel::from_num(try!(op(1))))),
+        15 => {
+            let (n, r) = parse_string(try!(rest(2)));
+            Instruction::EntryPoint {
+                execution: try!(ExecutionModel::from_num(try!(op(0)))),
+                id: try!(op(1)),
+                name: n,
+                interface: r.to_owned(),
+            }
+        },
+        16 => match ExecutionMode::from_num(try!(op(1))) {
+            Ok(mode) => Instruction::ExecutionMode {
+                target_id: try!(op(0)),
+                mode: mode,
+                optional_literals: try!(execution_mode_params(mode, try!(rest(2)))),
+            },
+            Err(_) => Instruction::Unknown(opcode, operands.to_owned()),
+        },
+        17 => match Capability::from_num(try!(op(0))) {
+            Ok(capability) => Instruction::Capability(capability),
+            Err(_) => Instruction::Unknown(opcode, operands.to_owned()),
+        },
+        19 => Instruction::TypeVoid { result_id: try!(op(0)) },
+        20 => Instruction::TypeBool { result_id: try!(op(0)) },
+        21 => Instruction::TypeInt {
+            result_id: try!(op(0)),
+            width: try!(op(1)),
+            signedness: try!(op(2)) != 0,
+        },
+        22 => Instruction::TypeFloat { result_id: try!(op(0)), width: try!(op(1)) },
+        23 => Instruction::TypeVector {
+            result_id: try!(op(0)),
+            component_id: try!(op(1)),
+            count: try!(op(2)),
+        },
+        24 => Instruction::TypeMatrix {
+            result_id: try!(op(0)),
+            column_type_id: try!(op(1)),
+            column_count: try!(op(2)),
+        },
+        25 => Instruction::TypeImage {
+            result_id: try!(op(0)),
+            sampled_type_id: try!(op(1)),
+            dim: try!(Dim::from_num(try!(op(2)))),
+            depth: match try!(op(3)) {
+                0 => Some(false),
+                1 => Some(true),
+                2 => None,
+                n => return Err(SpirvParseError::UnknownConstant("ImageDepth", n)),
+            },
+            arrayed: try!(op(4)) != 0,
+            ms: try!(op(5)) != 0,
+            sampled: match try!(op(6)) {
+                0 => None,
+                1 => Some(true),
+                2 => Some(false),
+                n => return Err(SpirvParseError::UnknownConstant("ImageSampled", n)),
+            },
+            format: try!(ImageFormat::from_num(try!(op(7)))),
+            access: match operands.get(8) {
+                Some(&access) => Some(try!(AccessQualifier::from_num(access))),
+                None => None,
+            },
+        },
+        26 => Instruction::TypeSampler { result_id: try!(op(0)) },
+        27 => Instruction::TypeSampledImage {
+            result_id: try!(op(0)),
+            image_type_id: try!(op(1)),
+        },
+        28 => Instruction::TypeArray {
+            result_id: try!(op(0)),
+            type_id: try!(op(1)),
+            length_id: try!(op(2)),
+        },
+        29 => Instruction::TypeRuntimeArray { result_id: try!(op(0)), type_id: try!(op(1)) },
+        30 => Instruction::TypeStruct {
+            result_id: try!(op(0)),
+            member_types: try!(rest(1)).to_owned(),
+        },
+        31 => Instruction::TypeOpaque {
+            result_id: try!(op(0)),
+            name: parse_string(try!(rest(1))).0,
+        },
+        32 => match StorageClass::from_num(try!(op(1))) {
+            Ok(storage_class) => Instruction::TypePointer {
+                result_id: try!(op(0)),
+                storage_class: storage_class,
+                type_id: try!(op(2)),
+            },
+            Err(_) => Instruction::Unknown(opcode, operands.to_owned()),
+        },
+        43 => Instruction::Constant {
+            result_type_id: try!(op(0)),
+            result_id: try!(op(1)),
+            data: try!(rest(2)).to_owned(),
+        },
+        48 => Instruction::SpecConstantTrue {
+            result_type_id: try!(op(0)),
+            result_id: try!(op(1)),
+        },
+        49 => Instruction::SpecConstantFalse {
+            result_type_id: try!(op(0)),
+            result_id: try!(op(1)),
+        },
+        50 => Instruction::SpecConstant {
+            result_type_id: try!(op(0)),
+            result_id: try!(op(1)),
+            data: try!(rest(2)).to_owned(),
+        },
+        56 => Instruction::FunctionEnd,
+        59 => match StorageClass::from_num(try!(op(2))) {
+            Ok(storage_class) => Instruction::Variable {
+                result_type_id: try!(op(0)),
+                result_id: try!(op(1)),
+                storage_class: storage_class,
+                initializer: operands.get(3).cloned(),
+            },
+            Err(_) => Instruction::Unknown(opcode, operands.to_owned()),
+        },
+        71 => match Decoration::from_num(try!(op(1))) {
+            Ok(decoration) => Instruction::Decorate {
+                target_id: try!(op(0)),
+                decoration: decoration,
+                params: try!(decoration_params(decoration, try!(rest(2)))),
+            },
+            Err(_) => Instruction::Unknown(opcode, operands.to_owned()),
+        },
+        72 => match Decoration::from_num(try!(op(2))) {
+            Ok(decoration) => Instruction::MemberDecorate {
+                target_id: try!(op(0)),
+                member: try!(op(1)),
+                decoration: decoration,
+                params: try!(decoration_params(decoration, try!(rest(3)))),
+            },
+            Err(_) => Instruction::Unknown(opcode, operands.to_owned()),
+        },
+        248 => Instruction::Label { result_id: try!(op(0)) },
+        249 => Instruction::Branch { result_id: try!(op(0)) },
+        252 => Instruction::Kill,
+        253 => Instruction::Return,
+        _ => Instruction::Unknown(opcode, operands.to_owned()),
+    })
+}
+
+// Returns the parameters of a decoration, or an error if the decoration requires a literal that
+// is missing. This allows users of the parser to read the first parameter of these decorations.
+fn decoration_params(decoration: Decoration, params: &[u32])
+                     -> Result<Vec<u32>, SpirvParseError>
+{
+    let has_literal = match decoration {
+        Decoration::DecorationSpecId | Decoration::DecorationArrayStride |
+        Decoration::DecorationMatrixStride | Decoration::DecorationBuiltIn |
+        Decoration::DecorationStream | Decoration::DecorationLocation |
+        Decoration::DecorationComponent | Decoration::DecorationIndex |
+        Decoration::DecorationBinding | Decoration::DecorationDescriptorSet |
+        Decoration::DecorationOffset | Decoration::DecorationXfbBuffer |
+        Decoration::DecorationXfbStride | Decoration::DecorationFuncParamAttr |
+        Decoration::DecorationFPRoundingMode | Decoration::DecorationFPFastMathMode |
+        Decoration::DecorationInputAttachmentIndex | Decoration::DecorationAlignment => true,
+        _ => false,
+    };
+
+    if has_literal && params.is_empty() {
+        return Err(SpirvParseError::IncompleteInstruction);
+    }
+
+    Ok(params.to_owned())
+}
+
+fn execution_mode_params(mode: ExecutionMode, params: &[u32])
+                         -> Result<Vec<u32>, SpirvParseError>
+{
+    let num_literals = match mode {
+        ExecutionMode::ExecutionModeLocalSize | ExecutionMode::ExecutionModeLocalSizeHint => 3,
+        ExecutionMode::ExecutionModeInvocations | ExecutionMode::ExecutionModeOutputVertices |
+        ExecutionMode::ExecutionModeVecTypeHint => 1,
+        _ => 0,
+    };
+
+    if params.len() < num_literals {
+        return Err(SpirvParseError::IncompleteInstruction);
+    }
+
+    Ok(params.to_owned())
+}
+
+/// Parses a nul-terminated string, and returns the string and the words that follow it.
+///
+/// Bytes that aren't valid UTF-8 are replaced with `U+FFFD`.
+pub fn parse_string(data: &[u32]) -> (String, &[u32]) {
+    let bytes = data.iter().flat_map(|&n| {
+        let b1 = (n & 0xff) as u8;
+        let b2 = ((n >> 8) & 0xff) as u8;
+        let b3 = ((n >> 16) & 0xff) as u8;
+        let b4 = ((n >> 24) & 0xff) as u8;
+        vec![b1, b2, b3, b4].into_iter()
+    }).take_while(|&b| b != 0).collect::<Vec<u8>>();
+
+    let r = 1 + bytes.len() / 4;
+    let r = if r > data.len() { data.len() } else { r };
+    let s = String::from_utf8_lossy(&bytes).into_owned();
+
+    (s, &data[r..])
+}
+
+#[cfg(test)]
+mod tests {
+    use pipeline::spirv::SpirvParseError;
+    use pipeline::spirv::parse;
+
+    #[test]
+    fn truncated_operands() {
+        // `OpTypeInt` with only its result ID.
+        let words = [0x07230203, 0x00010000, 0, 8, 0, (2 << 16) | 21, 1];
+        match parse::parse_words(&words) {
+            Err(SpirvParseError::IncompleteInstruction) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn zero_word_count() {
+        let words = [0x07230203, 0x00010000, 0, 8, 0, 0];
+        match parse::parse_words(&words) {
+            Err(SpirvParseError::IncompleteInstruction) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn invalid_image_operand() {
+        // `OpTypeImage` whose `Depth` operand is 3.
+        let words = [0x07230203, 0x00010000, 0, 8, 0, (9 << 16) | 25, 2, 1, 1, 3, 0, 0, 1, 0];
+        match parse::parse_words(&words) {
+            Err(SpirvParseError::UnknownConstant("ImageDepth", 3)) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn missing_decoration_literal() {
+        // `OpDecorate %1 Binding` without the binding number.
+        let words = [0x07230203, 0x00010000, 0, 8, 0, (3 << 16) | 71, 1, 33];
+        match parse::parse_words(&words) {
+            Err(SpirvParseError::IncompleteInstruction) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn unknown_decoration() {
+        let words = [0x07230203, 0x00010000, 0, 8, 0, (3 << 16) | 71, 1, 5300];
+        let doc = parse::parse_words(&words).unwrap();
+        match doc.instructions[0] {
+            parse::Instruction::Unknown(71, _) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn unknown_execution_mode() {
+        // `OpExecutionMode %1 SubgroupsPerWorkgroup 1`, which isn't known by the parser.
+        let words = [0x07230203, 0x00010000, 0, 8, 0, (4 << 16) | 16, 1, 36, 1];
+        let doc = parse::parse_words(&words).unwrap();
+        match doc.instructions[0] {
+            parse::Instruction::Unknown(16, _) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn local_size() {
+        let words = [0x07230203, 0x00010000, 0, 8, 0, (6 << 16) | 16, 1, 17, 64, 2, 1];
+        let doc = parse::parse_words(&words).unwrap();
+        match doc.instructions[0] {
+            parse::Instruction::ExecutionMode { target_id: 1, ref optional_literals, .. } => {
+                assert_eq!(optional_literals, &[64, 2, 1]);
+            },
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn incomplete_local_size() {
+        // `OpExecutionMode %1 LocalSize 64 1` without the Z size.
+        let words = [0x07230203, 0x00010000, 0, 8, 0, (5 << 16) | 16, 1, 17, 64, 1];
+        match parse::parse_words(&words) {
+            Err(SpirvParseError::IncompleteInstruction) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn unterminated_string() {
+        // `OpName` whose string isn't nul-terminated.
+        let words = [0x07230203, 0x00010000, 0, 8, 0, (3 << 16) | 5, 1, 0x41414141];
+        let doc = parse::parse_words(&words).unwrap();
+        match doc.instructions[0] {
+            parse::Instruction::Name { target_id: 1, ref name } => assert_eq!(name, "AAAA"),
+            _ => panic!()
+        }
+    }
+}
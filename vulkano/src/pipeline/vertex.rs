@@ -30,6 +30,14 @@
 //! The `Vertex` trait is unsafe, but can be implemented on a struct with the `impl_vertex!`
 //! macro.
 //!
+//! # Instancing
+//!
+//! The content of a buffer can also be read once per instance instead of once per vertex. To do
+//! so, implement `Vertex` on the content of the buffer with the `impl_instance!` macro instead
+//! of `impl_vertex!`, and pass a `TwoBuffersDefinition` or a `ThreeBuffersDefinition` when you
+//! create the pipeline. The number of instances to draw is then the length of the smallest
+//! instance buffer.
+//!
 //! # Example
 //!
 //! ```ignore       // TODO:
@@ -72,13 +80,16 @@ use std::sync::Arc;
 use std::vec::IntoIter as VecIntoIter;
 
 use buffer::Buffer;
+use buffer::BufferSlice;
 use buffer::TypedBuffer;
 use format::Format;
 use pipeline::shader::ShaderInterfaceDef;
+use Error;
+use VulkanError;
 use vk;
 
 /// How the vertex source should be unrolled.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(u32)]
 pub enum InputRate {
     /// Each element of the source corresponds to a vertex.
@@ -95,6 +106,16 @@ pub enum InputRate {
 pub unsafe trait Vertex: 'static + Send + Sync {
     /// Returns the characteristics of a vertex member by its name.
     fn member(name: &str) -> Option<VertexMemberInfo>;
+
+    /// Returns whether a buffer of this type should be read once per vertex or once per
+    /// instance, when used with a vertex definition that supports both.
+    ///
+    /// The `impl_vertex!` macro returns `Vertex` and the `impl_instance!` macro returns
+    /// `Instance`.
+    #[inline]
+    fn input_rate() -> InputRate {
+        InputRate::Vertex
+    }
 }
 
 unsafe impl Vertex for () {
@@ -214,18 +235,83 @@ impl fmt::Display for IncompatibleVertexDefinitionError {
     }
 }
 
+impl VulkanError for IncompatibleVertexDefinitionError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        None
+    }
+}
+
 
 /// Extension trait of `Definition`. The `L` parameter is an acceptable vertex source for this
 /// vertex definition.
 pub unsafe trait Source<L>: 'static + Send + Sync {
     /// Iterator used by `decode`.
-    type Iter: ExactSizeIterator<Item = Arc<Buffer>>;
+    type Iter: ExactSizeIterator<Item = (Arc<Buffer>, usize)>;
 
     /// Checks and returns the list of buffers, number of vertices and number of instances.
+    ///
+    /// Each buffer is accompanied by the offset in bytes within the buffer where the vertex data
+    /// starts.
     // TODO: return error if problem
     fn decode(&self, L) -> (Self::Iter, usize, usize);
 }
 
+/// Builds the list of attributes of a vertex definition made of multiple buffers.
+///
+/// Each element of `buffers` is the function that returns the members of the content of the
+/// buffer with the corresponding id. When an attribute is found in multiple buffers, the first
+/// buffer wins.
+fn build_attributes<I>(interface: &I, buffers: &[fn(&str) -> Option<VertexMemberInfo>])
+                       -> Result<Vec<(u32, u32, AttributeInfo)>, IncompatibleVertexDefinitionError>
+    where I: ShaderInterfaceDef
+{
+    let mut attribs = Vec::with_capacity(interface.elements().len());
+
+    for e in interface.elements() {
+        let name = e.name.as_ref().unwrap();
+
+        let (infos, buf_offset) = match buffers.iter().enumerate()
+                                               .filter_map(|(n, b)| b(name).map(|i| (i, n)))
+                                               .next()
+        {
+            Some(i) => i,
+            None => return Err(IncompatibleVertexDefinitionError::MissingAttribute {
+                attribute: name.clone().into_owned()
+            }),
+        };
+
+        if !infos.ty.matches(infos.array_size, e.format, e.location.end - e.location.start) {
+            return Err(IncompatibleVertexDefinitionError::FormatMismatch {
+                attribute: name.clone().into_owned(),
+                shader: (e.format, (e.location.end - e.location.start) as usize),
+                definition: (infos.ty, infos.array_size),
+            })
+        }
+
+        let mut offset = infos.offset;
+        for loc in e.location.clone() {
+            attribs.push((loc, buf_offset as u32,
+                          AttributeInfo { offset: offset, format: e.format }));
+            offset += e.format.size().unwrap();
+        }
+    }
+
+    Ok(attribs)
+}
+
+/// Computes the number of vertices and instances to draw from the length and input rate of each
+/// buffer.
+///
+/// The number of vertices (or instances) is the length of the smallest buffer with the
+/// `Vertex` (or `Instance`) input rate, or 1 if there is no such buffer.
+fn vertices_instances_count(buffers: &[(usize, InputRate)]) -> (usize, usize) {
+    let vertices = buffers.iter().filter(|&&(_, r)| r == InputRate::Vertex).map(|&(l, _)| l).min();
+    let instances = buffers.iter().filter(|&&(_, r)| r == InputRate::Instance)
+                                  .map(|&(l, _)| l).min();
+    (vertices.unwrap_or(1), instances.unwrap_or(1))
+}
+
 /// Implementation of `Definition` for a single vertex buffer.
 pub struct SingleBufferDefinition<T>(pub PhantomData<T>);
 
@@ -243,37 +329,7 @@ unsafe impl<T, I> Definition<I> for SingleBufferDefinition<T>
     fn definition(&self, interface: &I) -> Result<(Self::BuffersIter, Self::AttribsIter),
                                                   IncompatibleVertexDefinitionError>
     {
-        let attrib = {
-            let mut attribs = Vec::with_capacity(interface.elements().len());
-            for e in interface.elements() {
-                let name = e.name.as_ref().unwrap();
-
-                let infos = match <T as Vertex>::member(name) {
-                    Some(m) => m,
-                    None => return Err(IncompatibleVertexDefinitionError::MissingAttribute {
-                        attribute: name.clone().into_owned()
-                    })
-                };
-
-                if !infos.ty.matches(infos.array_size, e.format,
-                                     e.location.end - e.location.start)
-                {
-                    return Err(IncompatibleVertexDefinitionError::FormatMismatch {
-                        attribute: name.clone().into_owned(),
-                        shader: (e.format, (e.location.end - e.location.start) as usize),
-                        definition: (infos.ty, infos.array_size),
-                    })
-                }
-
-                let mut offset = infos.offset;
-                for loc in e.location.clone() {
-                    attribs.push((loc, 0, AttributeInfo { offset: offset, format: e.format }));
-                    offset += e.format.size().unwrap();
-                }
-            }
-            attribs
-        }.into_iter();      // TODO: meh
-
+        let attrib = try!(build_attributes(interface, &[<T as Vertex>::member])).into_iter();
         let buffers = Some((0, mem::size_of::<T>(), InputRate::Vertex)).into_iter();
         Ok((buffers, attrib))
     }
@@ -282,16 +338,33 @@ unsafe impl<T, I> Definition<I> for SingleBufferDefinition<T>
 unsafe impl<'a, B, V> Source<&'a Arc<B>> for SingleBufferDefinition<V>
     where B: TypedBuffer<Content = [V]> + 'static, V: Vertex + 'static
 {
-    type Iter = OptionIntoIter<Arc<Buffer>>;
+    type Iter = OptionIntoIter<(Arc<Buffer>, usize)>;
 
     #[inline]
-    fn decode(&self, source: &'a Arc<B>) -> (OptionIntoIter<Arc<Buffer>>, usize, usize) {
-        let iter = Some(source.clone() as Arc<_>).into_iter();
+    fn decode(&self, source: &'a Arc<B>) -> (OptionIntoIter<(Arc<Buffer>, usize)>, usize, usize) {
+        let iter = Some((source.clone() as Arc<_>, 0)).into_iter();
         (iter, source.len(), 1)
     }
 }
 
-/// Unstable.
+unsafe impl<'a, B, V> Source<BufferSlice<'a, [V], B>> for SingleBufferDefinition<V>
+    where B: Buffer + 'static, V: Vertex + 'static
+{
+    type Iter = OptionIntoIter<(Arc<Buffer>, usize)>;
+
+    #[inline]
+    fn decode(&self, source: BufferSlice<'a, [V], B>)
+              -> (OptionIntoIter<(Arc<Buffer>, usize)>, usize, usize)
+    {
+        let iter = Some((source.buffer().clone() as Arc<_>, source.offset())).into_iter();
+        (iter, source.len(), 1)
+    }
+}
+
+/// Implementation of `Definition` for two buffers.
+///
+/// The input rate of each buffer is determined by its content. Use `impl_vertex!` for data that
+/// is read once per vertex and `impl_instance!` for data that is read once per instance.
 // TODO: shouldn't be just `Two` but `Multi`
 pub struct TwoBuffersDefinition<T, U>(pub PhantomData<(T, U)>);
 
@@ -309,43 +382,12 @@ unsafe impl<T, U, I> Definition<I> for TwoBuffersDefinition<T, U>
     fn definition(&self, interface: &I) -> Result<(Self::BuffersIter, Self::AttribsIter),
                                                   IncompatibleVertexDefinitionError>
     {
-        let attrib = {
-            let mut attribs = Vec::with_capacity(interface.elements().len());
-            for e in interface.elements() {
-                let name = e.name.as_ref().unwrap();
-
-                let (infos, buf_offset) = if let Some(infos) = <T as Vertex>::member(name) {
-                    (infos, 0)
-                } else if let Some(infos) = <U as Vertex>::member(name) {
-                    (infos, 1)
-                } else {
-                    return Err(IncompatibleVertexDefinitionError::MissingAttribute {
-                        attribute: name.clone().into_owned()
-                    });
-                };
-
-                if !infos.ty.matches(infos.array_size, e.format,
-                                     e.location.end - e.location.start)
-                {
-                    return Err(IncompatibleVertexDefinitionError::FormatMismatch {
-                        attribute: name.clone().into_owned(),
-                        shader: (e.format, (e.location.end - e.location.start) as usize),
-                        definition: (infos.ty, infos.array_size),
-                    })
-                }
-
-                let mut offset = infos.offset;
-                for loc in e.location.clone() {
-                    attribs.push((loc, buf_offset, AttributeInfo { offset: offset, format: e.format }));
-                    offset += e.format.size().unwrap();
-                }
-            }
-            attribs
-        }.into_iter();      // TODO: meh
+        let attrib = try!(build_attributes(interface, &[<T as Vertex>::member,
+                                                        <U as Vertex>::member])).into_iter();
 
         let buffers = vec![
-            (0, mem::size_of::<T>(), InputRate::Vertex),
-            (1, mem::size_of::<U>(), InputRate::Vertex)
+            (0, mem::size_of::<T>(), <T as Vertex>::input_rate()),
+            (1, mem::size_of::<U>(), <U as Vertex>::input_rate())
         ].into_iter();
 
         Ok((buffers, attrib))
@@ -356,18 +398,129 @@ unsafe impl<'a, T, U, Bt, Bu> Source<(&'a Arc<Bt>, &'a Arc<Bu>)> for TwoBuffersD
     where T: Vertex + 'static, Bt: TypedBuffer<Content = [T]> + 'static, T: 'static,
           U: Vertex + 'static, Bu: TypedBuffer<Content = [U]> + 'static, T: 'static
 {
-    type Iter = VecIntoIter<Arc<Buffer>>;
+    type Iter = VecIntoIter<(Arc<Buffer>, usize)>;
 
     #[inline]
     fn decode(&self, source: (&'a Arc<Bt>, &'a Arc<Bu>))
-              -> (VecIntoIter<Arc<Buffer>>, usize, usize)
+              -> (VecIntoIter<(Arc<Buffer>, usize)>, usize, usize)
+    {
+        let iter = vec![(source.0.clone() as Arc<_>, 0),
+                        (source.1.clone() as Arc<_>, 0)].into_iter();
+        let (vertices, instances) = vertices_instances_count(&[
+            (source.0.len(), <T as Vertex>::input_rate()),
+            (source.1.len(), <U as Vertex>::input_rate()),
+        ]);
+        (iter, vertices, instances)
+    }
+}
+
+unsafe impl<'a, T, U, Bt, Bu> Source<(BufferSlice<'a, [T], Bt>, BufferSlice<'a, [U], Bu>)>
+    for TwoBuffersDefinition<T, U>
+    where T: Vertex + 'static, Bt: Buffer + 'static,
+          U: Vertex + 'static, Bu: Buffer + 'static
+{
+    type Iter = VecIntoIter<(Arc<Buffer>, usize)>;
+
+    #[inline]
+    fn decode(&self, source: (BufferSlice<'a, [T], Bt>, BufferSlice<'a, [U], Bu>))
+              -> (VecIntoIter<(Arc<Buffer>, usize)>, usize, usize)
+    {
+        let iter = vec![(source.0.buffer().clone() as Arc<_>, source.0.offset()),
+                        (source.1.buffer().clone() as Arc<_>, source.1.offset())].into_iter();
+        let (vertices, instances) = vertices_instances_count(&[
+            (source.0.len(), <T as Vertex>::input_rate()),
+            (source.1.len(), <U as Vertex>::input_rate()),
+        ]);
+        (iter, vertices, instances)
+    }
+}
+
+/// Implementation of `Definition` for three buffers.
+///
+/// The input rate of each buffer is determined by its content. See `TwoBuffersDefinition`.
+pub struct ThreeBuffersDefinition<T, U, V>(pub PhantomData<(T, U, V)>);
+
+impl<T, U, V> ThreeBuffersDefinition<T, U, V> {
+    #[inline]
+    pub fn new() -> ThreeBuffersDefinition<T, U, V> { ThreeBuffersDefinition(PhantomData) }
+}
+
+unsafe impl<T, U, V, I> Definition<I> for ThreeBuffersDefinition<T, U, V>
+    where T: Vertex, U: Vertex, V: Vertex, I: ShaderInterfaceDef
+{
+    type BuffersIter = VecIntoIter<(u32, usize, InputRate)>;
+    type AttribsIter = VecIntoIter<(u32, u32, AttributeInfo)>;
+
+    fn definition(&self, interface: &I) -> Result<(Self::BuffersIter, Self::AttribsIter),
+                                                  IncompatibleVertexDefinitionError>
     {
-        let iter = vec![source.0.clone() as Arc<_>, source.1.clone() as Arc<_>].into_iter();
-        (iter, [source.0.len(), source.1.len()].iter().cloned().min().unwrap(), 1)
+        let attrib = try!(build_attributes(interface, &[<T as Vertex>::member,
+                                                        <U as Vertex>::member,
+                                                        <V as Vertex>::member])).into_iter();
+
+        let buffers = vec![
+            (0, mem::size_of::<T>(), <T as Vertex>::input_rate()),
+            (1, mem::size_of::<U>(), <U as Vertex>::input_rate()),
+            (2, mem::size_of::<V>(), <V as Vertex>::input_rate())
+        ].into_iter();
+
+        Ok((buffers, attrib))
+    }
+}
+
+unsafe impl<'a, T, U, V, Bt, Bu, Bv> Source<(&'a Arc<Bt>, &'a Arc<Bu>, &'a Arc<Bv>)>
+    for ThreeBuffersDefinition<T, U, V>
+    where T: Vertex + 'static, Bt: TypedBuffer<Content = [T]> + 'static,
+          U: Vertex + 'static, Bu: TypedBuffer<Content = [U]> + 'static,
+          V: Vertex + 'static, Bv: TypedBuffer<Content = [V]> + 'static
+{
+    type Iter = VecIntoIter<(Arc<Buffer>, usize)>;
+
+    #[inline]
+    fn decode(&self, source: (&'a Arc<Bt>, &'a Arc<Bu>, &'a Arc<Bv>))
+              -> (VecIntoIter<(Arc<Buffer>, usize)>, usize, usize)
+    {
+        let iter = vec![(source.0.clone() as Arc<_>, 0), (source.1.clone() as Arc<_>, 0),
+                        (source.2.clone() as Arc<_>, 0)].into_iter();
+        let (vertices, instances) = vertices_instances_count(&[
+            (source.0.len(), <T as Vertex>::input_rate()),
+            (source.1.len(), <U as Vertex>::input_rate()),
+            (source.2.len(), <V as Vertex>::input_rate()),
+        ]);
+        (iter, vertices, instances)
+    }
+}
+
+unsafe impl<'a, T, U, V, Bt, Bu, Bv>
+    Source<(BufferSlice<'a, [T], Bt>, BufferSlice<'a, [U], Bu>, BufferSlice<'a, [V], Bv>)>
+    for ThreeBuffersDefinition<T, U, V>
+    where T: Vertex + 'static, Bt: Buffer + 'static,
+          U: Vertex + 'static, Bu: Buffer + 'static,
+          V: Vertex + 'static, Bv: Buffer + 'static
+{
+    type Iter = VecIntoIter<(Arc<Buffer>, usize)>;
+
+    #[inline]
+    fn decode(&self, source: (BufferSlice<'a, [T], Bt>, BufferSlice<'a, [U], Bu>,
+                              BufferSlice<'a, [V], Bv>))
+              -> (VecIntoIter<(Arc<Buffer>, usize)>, usize, usize)
+    {
+        let iter = vec![(source.0.buffer().clone() as Arc<_>, source.0.offset()),
+                        (source.1.buffer().clone() as Arc<_>, source.1.offset()),
+                        (source.2.buffer().clone() as Arc<_>, source.2.offset())].into_iter();
+        let (vertices, instances) = vertices_instances_count(&[
+            (source.0.len(), <T as Vertex>::input_rate()),
+            (source.1.len(), <U as Vertex>::input_rate()),
+            (source.2.len(), <V as Vertex>::input_rate()),
+        ]);
+        (iter, vertices, instances)
     }
 }
 
 /// Unstable.
+///
+/// The first buffer is always read per vertex and the second buffer per instance, whatever
+/// their content. Prefer `TwoBuffersDefinition` with a struct that uses `impl_instance!`.
 // TODO: bad way to do things
 pub struct OneVertexOneInstanceDefinition<T, U>(pub PhantomData<(T, U)>);
 
@@ -385,39 +538,8 @@ unsafe impl<T, U, I> Definition<I> for OneVertexOneInstanceDefinition<T, U>
     fn definition(&self, interface: &I) -> Result<(Self::BuffersIter, Self::AttribsIter),
                                                   IncompatibleVertexDefinitionError>
     {
-        let attrib = {
-            let mut attribs = Vec::with_capacity(interface.elements().len());
-            for e in interface.elements() {
-                let name = e.name.as_ref().unwrap();
-
-                let (infos, buf_offset) = if let Some(infos) = <T as Vertex>::member(name) {
-                    (infos, 0)
-                } else if let Some(infos) = <U as Vertex>::member(name) {
-                    (infos, 1)
-                } else {
-                    return Err(IncompatibleVertexDefinitionError::MissingAttribute {
-                        attribute: name.clone().into_owned()
-                    });
-                };
-
-                if !infos.ty.matches(infos.array_size, e.format,
-                                     e.location.end - e.location.start)
-                {
-                    return Err(IncompatibleVertexDefinitionError::FormatMismatch {
-                        attribute: name.clone().into_owned(),
-                        shader: (e.format, (e.location.end - e.location.start) as usize),
-                        definition: (infos.ty, infos.array_size),
-                    })
-                }
-
-                let mut offset = infos.offset;
-                for loc in e.location.clone() {
-                    attribs.push((loc, buf_offset, AttributeInfo { offset: offset, format: e.format }));
-                    offset += e.format.size().unwrap();
-                }
-            }
-            attribs
-        }.into_iter();      // TODO: meh
+        let attrib = try!(build_attributes(interface, &[<T as Vertex>::member,
+                                                        <U as Vertex>::member])).into_iter();
 
         let buffers = vec![
             (0, mem::size_of::<T>(), InputRate::Vertex),
@@ -432,13 +554,14 @@ unsafe impl<'a, T, U, Bt, Bu> Source<(&'a Arc<Bt>, &'a Arc<Bu>)> for OneVertexOn
     where T: Vertex + 'static, Bt: TypedBuffer<Content = [T]> + 'static, T: 'static,
           U: Vertex + 'static, Bu: TypedBuffer<Content = [U]> + 'static, U: 'static
 {
-    type Iter = VecIntoIter<Arc<Buffer>>;
+    type Iter = VecIntoIter<(Arc<Buffer>, usize)>;
 
     #[inline]
     fn decode(&self, source: (&'a Arc<Bt>, &'a Arc<Bu>))
-              -> (VecIntoIter<Arc<Buffer>>, usize, usize)
+              -> (VecIntoIter<(Arc<Buffer>, usize)>, usize, usize)
     {
-        let iter = vec![source.0.clone() as Arc<_>, source.1.clone() as Arc<_>].into_iter();
+        let iter = vec![(source.0.clone() as Arc<_>, 0),
+                        (source.1.clone() as Arc<_>, 0)].into_iter();
         (iter, source.0.len(), source.1.len())
     }
 }
@@ -447,9 +570,14 @@ unsafe impl<'a, T, U, Bt, Bu> Source<(&'a Arc<Bt>, &'a Arc<Bu>)> for OneVertexOn
 // TODO: add example
 #[macro_export]
 macro_rules! impl_vertex {
-    ($out:ident $(, $member:ident)*) => (
+    (__inner__ ($rate:ident) $out:ident $(, $member:ident)*) => (
         #[allow(unsafe_code)]
         unsafe impl $crate::pipeline::vertex::Vertex for $out {
+            #[inline(always)]
+            fn input_rate() -> $crate::pipeline::vertex::InputRate {
+                $crate::pipeline::vertex::InputRate::$rate
+            }
+
             #[inline(always)]
             fn member(name: &str) -> Option<$crate::pipeline::vertex::VertexMemberInfo> {
                 #[allow(unused_imports)]
@@ -483,7 +611,23 @@ macro_rules! impl_vertex {
                 None
             }
         }
-    )
+    );
+
+    ($out:ident $(, $member:ident)*) => (
+        impl_vertex!{__inner__ (Vertex) $out $(, $member)*}
+    );
+}
+
+/// Implements the `Vertex` trait on a struct whose content is read once per instance instead of
+/// once per vertex.
+///
+/// Use this for the content of instance buffers with `TwoBuffersDefinition` or
+/// `ThreeBuffersDefinition`. The syntax is the same as `impl_vertex!`.
+#[macro_export]
+macro_rules! impl_instance {
+    ($out:ident $(, $member:ident)*) => (
+        impl_vertex!{__inner__ (Instance) $out $(, $member)*}
+    );
 }
 
 /// Trait for data types that can be used as vertex members. Used by the `impl_vertex!` macro.
@@ -619,3 +763,57 @@ impl_vm_array!(15);
 impl_vm_array!(16);
 impl_vm_array!(32);
 impl_vm_array!(64);
+
+#[cfg(test)]
+mod tests {
+    use buffer::BufferSlice;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use pipeline::vertex::InputRate;
+    use pipeline::vertex::SingleBufferDefinition;
+    use pipeline::vertex::Source;
+    use pipeline::vertex::Vertex;
+    use pipeline::vertex::vertices_instances_count;
+
+    #[allow(dead_code)]
+    struct PerVertex { position: [f32; 2] }
+    impl_vertex!(PerVertex, position);
+
+    #[allow(dead_code)]
+    struct PerInstance { offset: [f32; 2] }
+    impl_instance!(PerInstance, offset);
+
+    #[test]
+    fn input_rates() {
+        assert_eq!(<PerVertex as Vertex>::input_rate(), InputRate::Vertex);
+        assert_eq!(<PerInstance as Vertex>::input_rate(), InputRate::Instance);
+        assert!(<PerInstance as Vertex>::member("offset").is_some());
+    }
+
+    #[test]
+    fn counts() {
+        let counts = vertices_instances_count(&[(12, InputRate::Vertex), (5, InputRate::Instance),
+                                                (8, InputRate::Vertex)]);
+        assert_eq!(counts, (8, 5));
+
+        let counts = vertices_instances_count(&[(12, InputRate::Vertex)]);
+        assert_eq!(counts, (12, 1));
+    }
+
+    #[test]
+    fn slice_source() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let usage = BufferUsage { vertex_buffer: true, .. BufferUsage::none() };
+        let data = (0 .. 16).map(|_| PerVertex { position: [0.0, 0.0] });
+        let buffer = CpuAccessibleBuffer::from_iter(&device, &usage, Some(queue.family()),
+                                                    data).unwrap();
+
+        let slice = BufferSlice::from(&buffer).slice(4 .. 10).unwrap();
+        let (mut buffers, vertices, instances) = SingleBufferDefinition::<PerVertex>::new()
+                                                                         .decode(slice);
+        assert_eq!((vertices, instances), (6, 1));
+        assert_eq!(buffers.next().unwrap().1, 4 * 8);
+        assert!(buffers.next().is_none());
+    }
+}
@@ -0,0 +1,301 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Measuring the time that the GPU spends on each part of a frame.
+//!
+//! A `GpuProfiler` writes timestamps around named *scopes* of the commands of a frame, and
+//! converts them to durations once the GPU has executed the frame.
+//!
+//! Since the timestamps are only available after the command buffer has been executed, the
+//! profiler owns one timestamp query pool per frame in flight. The queries of a frame are read
+//! back when its pool is reused, which means that the timings returned by `timings()` are those
+//! of a frame that was recorded a few frames ago.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let mut profiler = GpuProfiler::new(&device, queue.family(), 2, 16).unwrap();
+//!
+//! loop {
+//!     let mut cb = AutoCommandBufferBuilder::new(&device, queue.family());
+//!     profiler.begin_frame(&mut cb).unwrap();
+//!
+//!     profiler.begin_scope("shadow pass", &mut cb).unwrap();
+//!     cb.begin_render_pass(&shadow_framebuffer, shadow_clear_values).unwrap();
+//!     /* ... */
+//!     cb.end_render_pass().unwrap();
+//!     profiler.end_scope(&mut cb).unwrap();
+//!
+//!     profiler.scope("main pass", &mut cb, |cb| {
+//!         try!(cb.begin_render_pass(&framebuffer, clear_values));
+//!         /* ... */
+//!         cb.end_render_pass()
+//!     }).unwrap();
+//!
+//!     let submission = command_buffer::submit(&cb.build().unwrap(), &queue).unwrap();
+//!
+//!     for &(ref name, ms) in profiler.timings() {
+//!         println!("{}: {:.3} ms", name, ms);
+//!     }
+//! }
+//! ```
+
+use std::sync::Arc;
+
+use command_buffer::AutoCommandBufferBuilder;
+use command_buffer::AutoCommandBufferBuilderError;
+use command_buffer::pool::CommandPool;
+use device::Device;
+use instance::QueueFamily;
+use query::GetResultsError;
+use query::TimestampQueryPool;
+use sync::PipelineStages;
+
+use OomError;
+
+/// Records timestamps around named scopes of commands and resolves them into durations.
+///
+/// See [the documentation of the module](index.html) for more info.
+pub struct GpuProfiler {
+    device: Arc<Device>,
+    // `timestamp_valid_bits` of the queue family the command buffers are submitted to.
+    valid_bits: u32,
+    max_scopes: u32,
+    frames: Vec<Frame>,
+    // Index within `frames` of the frame being recorded.
+    current: usize,
+    // Indices within the scopes of the current frame of the scopes that haven't been ended.
+    open_scopes: Vec<usize>,
+    // Durations of the scopes of the last frame that has been resolved, in milliseconds.
+    timings: Vec<(String, f64)>,
+}
+
+struct Frame {
+    pool: Arc<TimestampQueryPool>,
+    // Names of the scopes of the frame. The scope at index `n` uses the slots `2 * n` and
+    // `2 * n + 1` of the pool.
+    scopes: Vec<String>,
+    // True if the frame has been recorded and its timestamps haven't been read yet.
+    pending: bool,
+}
+
+impl GpuProfiler {
+    /// Builds a new profiler.
+    ///
+    /// `queue_family` is the family of the queue the command buffers are submitted to.
+    /// `frames_in_flight` is the number of frames that can be executed by the GPU while the
+    /// next one is being recorded, and `max_scopes` is the maximum number of scopes per frame.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `queue_family` doesn't support timestamps.
+    /// - Panics if `frames_in_flight` or `max_scopes` is 0.
+    ///
+    pub fn new(device: &Arc<Device>, queue_family: QueueFamily, frames_in_flight: usize,
+               max_scopes: u32) -> Result<GpuProfiler, OomError>
+    {
+        assert!(queue_family.supports_timestamps(),
+                "The queue family doesn't support timestamps");
+        assert!(frames_in_flight >= 1);
+        assert!(max_scopes >= 1);
+
+        let mut frames = Vec::with_capacity(frames_in_flight);
+        for _ in 0 .. frames_in_flight {
+            frames.push(Frame {
+                pool: Arc::new(try!(TimestampQueryPool::raw(device, max_scopes * 2))),
+                scopes: Vec::new(),
+                pending: false,
+            });
+        }
+
+        Ok(GpuProfiler {
+            device: device.clone(),
+            valid_bits: queue_family.timestamp_valid_bits(),
+            max_scopes: max_scopes,
+            frames: frames,
+            current: frames_in_flight - 1,
+            open_scopes: Vec::new(),
+            timings: Vec::new(),
+        })
+    }
+
+    /// Returns the device this profiler belongs to.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    /// Starts profiling a new frame, whose commands are recorded in `builder`.
+    ///
+    /// The query pool of the frame that was recorded `frames_in_flight` frames ago is reused. If
+    /// the GPU has finished executing that frame, its timings are read back and are then
+    /// returned by `timings()`. Otherwise they are discarded and `timings()` keeps returning the
+    /// previous ones.
+    ///
+    /// Only allowed outside of a render pass.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if a scope of the previous frame hasn't been ended.
+    ///
+    pub fn begin_frame<P>(&mut self, builder: &mut AutoCommandBufferBuilder<P>)
+                          -> Result<(), AutoCommandBufferBuilderError>
+        where P: CommandPool
+    {
+        assert!(self.open_scopes.is_empty(), "A scope of the previous frame hasn't been ended");
+
+        let next = (self.current + 1) % self.frames.len();
+        try!(builder.reset_query_pool(&self.frames[next].pool, 0 .. self.max_scopes * 2));
+
+        try!(self.resolve(next));
+        self.current = next;
+        self.frames[next].scopes.clear();
+        self.frames[next].pending = true;
+        Ok(())
+    }
+
+    /// Writes a timestamp that marks the start of a scope named `name`. The scope ends with the
+    /// next call to `end_scope`. Scopes can be nested.
+    ///
+    /// Allowed both inside and outside of a render pass.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `begin_frame` hasn't been called.
+    /// - Panics if more than `max_scopes` scopes are begun in the same frame.
+    ///
+    pub fn begin_scope<P>(&mut self, name: &str, builder: &mut AutoCommandBufferBuilder<P>)
+                          -> Result<(), AutoCommandBufferBuilderError>
+        where P: CommandPool
+    {
+        let frame = &mut self.frames[self.current];
+        assert!(frame.pending, "begin_frame must be called before recording scopes");
+        assert!((frame.scopes.len() as u32) < self.max_scopes,
+                "Too many scopes in the same frame");
+
+        let index = frame.scopes.len();
+        let stage = PipelineStages { top_of_pipe: true, .. PipelineStages::none() };
+        // Safe because all the slots are reset by `begin_frame`.
+        try!(unsafe { builder.write_timestamp(&frame.pool, stage, index as u32 * 2) });
+
+        frame.scopes.push(name.to_owned());
+        self.open_scopes.push(index);
+        Ok(())
+    }
+
+    /// Writes a timestamp that marks the end of the scope that was begun last.
+    ///
+    /// Allowed both inside and outside of a render pass.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if there is no scope to end.
+    ///
+    pub fn end_scope<P>(&mut self, builder: &mut AutoCommandBufferBuilder<P>)
+                        -> Result<(), AutoCommandBufferBuilderError>
+        where P: CommandPool
+    {
+        let index = self.open_scopes.pop().expect("There is no scope to end");
+        let frame = &self.frames[self.current];
+        let stage = PipelineStages { bottom_of_pipe: true, .. PipelineStages::none() };
+        // Safe because all the slots are reset by `begin_frame`.
+        unsafe { builder.write_timestamp(&frame.pool, stage, index as u32 * 2 + 1) }
+    }
+
+    /// Records the commands of `record` inside of a scope named `name`.
+    ///
+    /// This is equivalent to calling `begin_scope`, `record` and `end_scope`.
+    pub fn scope<P, F>(&mut self, name: &str, builder: &mut AutoCommandBufferBuilder<P>,
+                       record: F) -> Result<(), AutoCommandBufferBuilderError>
+        where P: CommandPool,
+              F: FnOnce(&mut AutoCommandBufferBuilder<P>)
+                        -> Result<(), AutoCommandBufferBuilderError>
+    {
+        try!(self.begin_scope(name, builder));
+        try!(record(builder));
+        self.end_scope(builder)
+    }
+
+    /// Returns the name and the duration in milliseconds of each scope of the last frame whose
+    /// timestamps have been read back, in the order in which the scopes were begun.
+    #[inline]
+    pub fn timings(&self) -> &[(String, f64)] {
+        &self.timings
+    }
+
+    /// Returns the duration in milliseconds of the first scope named `name` in the last frame
+    /// whose timestamps have been read back.
+    #[inline]
+    pub fn timing(&self, name: &str) -> Option<f64> {
+        self.timings.iter().find(|&&(ref n, _)| n == name).map(|&(_, ms)| ms)
+    }
+
+    // Reads back the timestamps of a frame, if it has been recorded and executed.
+    fn resolve(&mut self, frame: usize) -> Result<(), OomError> {
+        let frame = &mut self.frames[frame];
+        if !frame.pending {
+            return Ok(());
+        }
+
+        frame.pending = false;
+        if frame.scopes.is_empty() {
+            self.timings.clear();
+            return Ok(());
+        }
+
+        let values = match frame.pool.try_get_results(0 .. frame.scopes.len() as u32 * 2) {
+            Ok(values) => values,
+            Err(GetResultsError::NotReady) => return Ok(()),
+            Err(GetResultsError::DeviceLost) => return Ok(()),
+            Err(GetResultsError::OomError(err)) => return Err(err),
+        };
+
+        let pool = &frame.pool;
+        let valid_bits = self.valid_bits;
+        self.timings = frame.scopes.drain(..).enumerate().map(|(n, name)| {
+            let ns = pool.elapsed_nanoseconds(values[n * 2], values[n * 2 + 1], valid_bits);
+            (name, ns as f64 / 1_000_000.0)
+        }).collect();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::submit;
+    use profiler::GpuProfiler;
+
+    #[test]
+    fn resolve_previous_frame() {
+        let (device, queue) = gfx_dev_and_queue!();
+        if !queue.family().supports_timestamps() {
+            return;
+        }
+
+        let mut profiler = GpuProfiler::new(&device, queue.family(), 1, 4).unwrap();
+
+        let mut cb = AutoCommandBufferBuilder::new(&device, queue.family());
+        profiler.begin_frame(&mut cb).unwrap();
+        profiler.begin_scope("outer", &mut cb).unwrap();
+        profiler.scope("inner", &mut cb, |_| Ok(())).unwrap();
+        profiler.end_scope(&mut cb).unwrap();
+        let submission = submit(&cb.build().unwrap(), &queue).unwrap();
+        submission.wait(Duration::from_secs(5)).unwrap();
+        assert!(profiler.timings().is_empty());
+
+        let mut cb = AutoCommandBufferBuilder::new(&device, queue.family());
+        profiler.begin_frame(&mut cb).unwrap();
+        assert_eq!(profiler.timings().len(), 2);
+        assert_eq!(profiler.timings()[0].0, "outer");
+        assert!(profiler.timing("outer").unwrap() >= profiler.timing("inner").unwrap());
+    }
+}
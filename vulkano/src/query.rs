@@ -12,10 +12,19 @@
 //! In Vulkan, queries are not created individually. Instead you manipulate **query pools**, which
 //! represent a collection of queries. Whenever you use a query, you have to specify both the query
 //! pool and the slot id within that query pool.
+//!
+//! # Reading the results
+//!
+//! All the query pools read their results the same way. `get_results` returns a `QueryResult`
+//! for each slot of the range, and takes `QueryResultFlags` that indicate whether to wait for
+//! the results and whether to return partial results for the queries that haven't finished.
+//! `try_get_results` is a shortcut that doesn't wait and returns `GetResultsError::NotReady`
+//! unless all the results are available.
 
 use std::error;
 use std::fmt;
 use std::mem;
+use std::ops::Range;
 use std::ptr;
 use std::sync::Arc;
 
@@ -25,13 +34,21 @@ use check_errors;
 use Error;
 use OomError;
 use SafeDeref;
+use VulkanError;
 use VulkanObject;
 use VulkanPointers;
 use vk;
 
+/// Trait for objects that contain a Vulkan query pool.
+pub unsafe trait QueryPool: 'static + Send + Sync {
+    /// Returns the inner query pool.
+    fn inner(&self) -> &UnsafeQueryPool;
+}
+
 pub struct UnsafeQueryPool<P = Arc<Device>> where P: SafeDeref<Target = Device> {
     pool: vk::QueryPool,
     device: P,
+    ty: QueryType,
     num_slots: u32,
 }
 
@@ -72,10 +89,17 @@ impl<P> UnsafeQueryPool<P> where P: SafeDeref<Target = Device> {
         Ok(UnsafeQueryPool {
             pool: pool,
             device: device,
+            ty: ty,
             num_slots: num_slots,
         })
     }
 
+    /// Returns the type of queries of that query pool.
+    #[inline]
+    pub fn ty(&self) -> QueryType {
+        self.ty
+    }
+
     /// Returns the number of slots of that query pool.
     #[inline]
     pub fn num_slots(&self) -> u32 {
@@ -87,15 +111,190 @@ impl<P> UnsafeQueryPool<P> where P: SafeDeref<Target = Device> {
     pub fn device(&self) -> &P {
         &self.device
     }
+
+    // Calls `vkGetQueryPoolResults` with 64-bit results and availability for the slots in
+    // `slots`, and decodes each query with `decode`.
+    //
+    // `decode` receives the `values_per_query` values written for each query.
+    fn get_results_with<T, F>(&self, slots: Range<u32>, values_per_query: usize,
+                              flags: QueryResultFlags, mut decode: F)
+                              -> Result<Vec<QueryResult<T>>, GetResultsError>
+        where F: FnMut(&[u64]) -> T
+    {
+        assert!(slots.start <= slots.end);
+        assert!(slots.end <= self.num_slots);
+
+        if flags.partial {
+            match self.ty {
+                QueryType::Timestamp => panic!("Partial results are not allowed for timestamps"),
+                _ => ()
+            }
+        }
+
+        let num_queries = (slots.end - slots.start) as usize;
+        if num_queries == 0 {
+            return Ok(Vec::new());
+        }
+
+        // The availability of each query is written after its values.
+        let stride = values_per_query + 1;
+        let mut output = vec![0u64; num_queries * stride];
+
+        let vk_flags: vk::QueryResultFlags = flags.into();
+        let vk_flags = vk_flags | vk::QUERY_RESULT_64_BIT | vk::QUERY_RESULT_WITH_AVAILABILITY_BIT;
+
+        unsafe {
+            let vk = self.device.pointers();
+            try!(check_errors({
+                vk.GetQueryPoolResults(self.device.internal_object(), self.pool, slots.start,
+                                       num_queries as u32, output.len() * mem::size_of::<u64>(),
+                                       output.as_mut_ptr() as *mut _,
+                                       (stride * mem::size_of::<u64>()) as vk::DeviceSize,
+                                       vk_flags)
+            }));
+        }
+
+        Ok(output.chunks(stride).map(|query| {
+            let (values, availability) = query.split_at(values_per_query);
+
+            if availability[0] != 0 {
+                QueryResult::Available(decode(values))
+            } else if flags.partial {
+                QueryResult::Unavailable(Some(decode(values)))
+            } else {
+                QueryResult::Unavailable(None)
+            }
+        }).collect())
+    }
+}
+
+// Extracts the values of `results`, or returns `NotReady` if one of them is unavailable.
+fn all_available<T>(results: Vec<QueryResult<T>>) -> Result<Vec<T>, GetResultsError> {
+    results.into_iter().map(|r| r.available().ok_or(GetResultsError::NotReady)).collect()
+}
+
+unsafe impl<P> VulkanObject for UnsafeQueryPool<P> where P: SafeDeref<Target = Device> {
+    type Object = vk::QueryPool;
+
+    #[inline]
+    fn internal_object(&self) -> vk::QueryPool {
+        self.pool
+    }
 }
 
+unsafe impl QueryPool for UnsafeQueryPool {
+    #[inline]
+    fn inner(&self) -> &UnsafeQueryPool {
+        self
+    }
+}
+
+/// Type of the queries of a query pool.
 #[derive(Debug, Copy, Clone)]
 pub enum QueryType {
+    /// Counts the number of samples that pass the depth and stencil tests.
     Occlusion,
+    /// Counts the events that correspond to the given flags.
     PipelineStatistics(QueryPipelineStatisticFlags),
+    /// Records the moment when a command reaches a stage of the pipeline.
     Timestamp,
 }
 
+impl QueryType {
+    /// Returns the number of values that are written for each query of this type, not including
+    /// the availability.
+    #[inline]
+    pub fn result_len(&self) -> usize {
+        match *self {
+            QueryType::Occlusion | QueryType::Timestamp => 1,
+            QueryType::PipelineStatistics(flags) => flags.count(),
+        }
+    }
+}
+
+/// Describes how the results of queries must be retrieved.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct QueryResultFlags {
+    /// If true, waits until the results of all the queries are available.
+    pub wait: bool,
+    /// If true, an intermediate value is returned for the queries whose results are not
+    /// available. Not allowed for timestamp queries.
+    pub partial: bool,
+}
+
+impl QueryResultFlags {
+    /// Builds a `QueryResultFlags` that doesn't wait and doesn't return partial results.
+    #[inline]
+    pub fn none() -> QueryResultFlags {
+        QueryResultFlags {
+            wait: false,
+            partial: false,
+        }
+    }
+
+    /// Builds a `QueryResultFlags` that waits for all the results to be available.
+    #[inline]
+    pub fn wait() -> QueryResultFlags {
+        QueryResultFlags {
+            wait: true,
+            partial: false,
+        }
+    }
+
+    /// Builds a `QueryResultFlags` that doesn't wait and returns partial results.
+    #[inline]
+    pub fn partial() -> QueryResultFlags {
+        QueryResultFlags {
+            wait: false,
+            partial: true,
+        }
+    }
+}
+
+impl Into<vk::QueryResultFlags> for QueryResultFlags {
+    #[inline]
+    fn into(self) -> vk::QueryResultFlags {
+        let mut result = 0;
+        if self.wait {
+            result |= vk::QUERY_RESULT_WAIT_BIT;
+        }
+        if self.partial {
+            result |= vk::QUERY_RESULT_PARTIAL_BIT;
+        }
+        result
+    }
+}
+
+/// Result of a single query.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum QueryResult<T> {
+    /// The query has finished and its result is available.
+    Available(T),
+    /// The query hasn't finished yet. Contains an intermediate result if partial results were
+    /// requested.
+    Unavailable(Option<T>),
+}
+
+impl<T> QueryResult<T> {
+    /// Returns the result if it is available.
+    #[inline]
+    pub fn available(self) -> Option<T> {
+        match self {
+            QueryResult::Available(value) => Some(value),
+            QueryResult::Unavailable(_) => None,
+        }
+    }
+
+    /// Returns true if the result is available.
+    #[inline]
+    pub fn is_available(&self) -> bool {
+        match *self {
+            QueryResult::Available(_) => true,
+            QueryResult::Unavailable(_) => false,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct QueryPipelineStatisticFlags {
     pub input_assembly_vertices: bool,
@@ -112,6 +311,7 @@ pub struct QueryPipelineStatisticFlags {
 }
 
 impl QueryPipelineStatisticFlags {
+    /// Builds a `QueryPipelineStatisticFlags` with all the counters disabled.
     #[inline]
     pub fn none() -> QueryPipelineStatisticFlags {
         QueryPipelineStatisticFlags {
@@ -128,6 +328,34 @@ impl QueryPipelineStatisticFlags {
             compute_shader_invocations: false,
         }
     }
+
+    /// Builds a `QueryPipelineStatisticFlags` with all the counters enabled.
+    #[inline]
+    pub fn all() -> QueryPipelineStatisticFlags {
+        QueryPipelineStatisticFlags {
+            input_assembly_vertices: true,
+            input_assembly_primitives: true,
+            vertex_shader_invocations: true,
+            geometry_shader_invocations: true,
+            geometry_shader_primitives: true,
+            clipping_invocations: true,
+            clipping_primitives: true,
+            fragment_shader_invocations: true,
+            tessellation_control_shader_patches: true,
+            tessellation_evaluation_shader_invocations: true,
+            compute_shader_invocations: true,
+        }
+    }
+
+    /// Returns the number of counters that are enabled.
+    ///
+    /// This corresponds to the number of values written by each query of a pipeline statistics
+    /// pool.
+    #[inline]
+    pub fn count(&self) -> usize {
+        let flags: vk::QueryPipelineStatisticFlags = (*self).into();
+        flags.count_ones() as usize
+    }
 }
 
 impl Into<vk::QueryPipelineStatisticFlags> for QueryPipelineStatisticFlags {
@@ -217,6 +445,16 @@ impl fmt::Display for QueryPoolCreationError {
     }
 }
 
+impl VulkanError for QueryPoolCreationError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            QueryPoolCreationError::OomError(ref err) => err.error_code(),
+            _ => None
+        }
+    }
+}
+
 impl From<OomError> for QueryPoolCreationError {
     #[inline]
     fn from(err: OomError) -> QueryPoolCreationError {
@@ -235,6 +473,16 @@ impl From<Error> for QueryPoolCreationError {
     }
 }
 
+/// A pool of occlusion queries.
+///
+/// Each query counts the number of samples that pass the depth and stencil tests between the
+/// moment it begins and the moment it ends. Queries are started with `begin_query` and stopped
+/// with `end_query` on a command buffer builder, usually around the draw commands of an object.
+///
+/// If the query is not *precise*, it is only guaranteed to return zero if no sample passed and
+/// a non-zero value otherwise. This is enough to determine whether an object is visible, for
+/// example to skip drawing it in the next frame. Precise queries require the
+/// `occlusion_query_precise` feature.
 pub struct OcclusionQueriesPool {
     inner: UnsafeQueryPool,
 }
@@ -265,7 +513,7 @@ impl OcclusionQueriesPool {
     pub fn new(device: &Arc<Device>, num_slots: u32)
                -> Arc<OcclusionQueriesPool>
     {
-       Arc::new(OcclusionQueriesPool::raw(device, num_slots).unwrap())
+        Arc::new(OcclusionQueriesPool::raw(device, num_slots).unwrap())
     }
 
     /// Returns the number of slots of that query pool.
@@ -279,15 +527,385 @@ impl OcclusionQueriesPool {
     pub fn device(&self) -> &Arc<Device> {
         self.inner.device()
     }
+
+    /// Reads the number of samples that passed for each query in `slots`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `slots` is out of range of the pool.
+    ///
+    pub fn get_results(&self, slots: Range<u32>, flags: QueryResultFlags)
+                       -> Result<Vec<QueryResult<u64>>, GetResultsError>
+    {
+        self.inner.get_results_with(slots, 1, flags, |values| values[0])
+    }
+
+    /// Reads the number of samples that passed for each query in `slots`, without waiting.
+    ///
+    /// Returns `GetResultsError::NotReady` if the result of one of the queries isn't available
+    /// yet, so that it can be tried again later instead of stalling.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `slots` is out of range of the pool.
+    ///
+    #[inline]
+    pub fn try_get_results(&self, slots: Range<u32>) -> Result<Vec<u64>, GetResultsError> {
+        all_available(try!(self.get_results(slots, QueryResultFlags::none())))
+    }
+
+    /// Returns true if at least one sample passed during the query of the given slot.
+    ///
+    /// Returns `Ok(None)` if the result isn't available yet and `wait` is false.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `slot` is out of range of the pool.
+    ///
+    pub fn is_visible(&self, slot: u32, wait: bool) -> Result<Option<bool>, GetResultsError> {
+        let flags = if wait { QueryResultFlags::wait() } else { QueryResultFlags::none() };
+        let result = try!(self.get_results(slot .. slot + 1, flags));
+        Ok(result[0].available().map(|samples| samples != 0))
+    }
+}
+
+unsafe impl QueryPool for OcclusionQueriesPool {
+    #[inline]
+    fn inner(&self) -> &UnsafeQueryPool {
+        &self.inner
+    }
+}
+
+/// A pool of timestamp queries.
+///
+/// Timestamps are written with `write_timestamp` on a command buffer builder. Each query
+/// contains the moment when all the previous commands reached the given stage of the pipeline.
+/// Timestamps are expressed in ticks, and the difference between two timestamps can be
+/// converted to nanoseconds with `ticks_to_nanoseconds`.
+///
+/// Only the queue families whose `timestamp_valid_bits` is not 0 can write timestamps.
+pub struct TimestampQueryPool {
+    inner: UnsafeQueryPool,
+}
+
+impl TimestampQueryPool {
+    /// See the docs of new().
+    pub fn raw(device: &Arc<Device>, num_slots: u32) -> Result<TimestampQueryPool, OomError> {
+        Ok(TimestampQueryPool {
+            inner: match UnsafeQueryPool::new(device.clone(), QueryType::Timestamp, num_slots) {
+                Ok(q) => q,
+                Err(QueryPoolCreationError::OomError(err)) => return Err(err),
+                Err(QueryPoolCreationError::PipelineStatisticsQueryFeatureNotEnabled) => {
+                    unreachable!()
+                },
+            }
+        })
+    }
+
+    /// Builds a new query pool.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device or host ran out of memory.
+    ///
+    #[inline]
+    pub fn new(device: &Arc<Device>, num_slots: u32) -> Arc<TimestampQueryPool> {
+        Arc::new(TimestampQueryPool::raw(device, num_slots).unwrap())
+    }
+
+    /// Returns the number of slots of that query pool.
+    #[inline]
+    pub fn num_slots(&self) -> u32 {
+        self.inner.num_slots()
+    }
+
+    /// Returns the device that was used to create this pool.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        self.inner.device()
+    }
+
+    /// Reads the timestamps of the queries in `slots`, in ticks.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `slots` is out of range of the pool.
+    /// - Panics if partial results are requested.
+    ///
+    pub fn get_results(&self, slots: Range<u32>, flags: QueryResultFlags)
+                       -> Result<Vec<QueryResult<u64>>, GetResultsError>
+    {
+        self.inner.get_results_with(slots, 1, flags, |values| values[0])
+    }
+
+    /// Reads the timestamps of the queries in `slots`, in ticks, without waiting.
+    ///
+    /// Returns `GetResultsError::NotReady` if one of the timestamps hasn't been written yet.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `slots` is out of range of the pool.
+    ///
+    #[inline]
+    pub fn try_get_results(&self, slots: Range<u32>) -> Result<Vec<u64>, GetResultsError> {
+        all_available(try!(self.get_results(slots, QueryResultFlags::none())))
+    }
+
+    /// Returns the number of nanoseconds that correspond to `ticks`.
+    ///
+    /// The duration of a tick is given by the `timestamp_period` limit of the physical device.
+    #[inline]
+    pub fn ticks_to_nanoseconds(&self, ticks: u64) -> u64 {
+        let period = self.device().physical_device().limits().timestamp_period();
+        (ticks as f64 * period as f64) as u64
+    }
+
+    /// Returns the number of nanoseconds between two timestamps.
+    ///
+    /// `valid_bits` must be the `timestamp_valid_bits` of the queue family the timestamps were
+    /// written from. It is used to handle the case where the counter wrapped around.
+    #[inline]
+    pub fn elapsed_nanoseconds(&self, start: u64, end: u64, valid_bits: u32) -> u64 {
+        let mask = if valid_bits >= 64 { !0 } else { (1u64 << valid_bits) - 1 };
+        self.ticks_to_nanoseconds(end.wrapping_sub(start) & mask)
+    }
+}
+
+unsafe impl QueryPool for TimestampQueryPool {
+    #[inline]
+    fn inner(&self) -> &UnsafeQueryPool {
+        &self.inner
+    }
+}
+
+/// Error that can happen when retrieving the results of queries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GetResultsError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The connection to the device has been lost.
+    DeviceLost,
+    /// The results of some of the queries are not available yet.
+    NotReady,
+}
+
+impl error::Error for GetResultsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            GetResultsError::OomError(_) => "not enough memory available",
+            GetResultsError::DeviceLost => "the connection to the device has been lost",
+            GetResultsError::NotReady => "the results of some of the queries are not available yet",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            GetResultsError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for GetResultsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for GetResultsError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            GetResultsError::OomError(ref err) => err.error_code(),
+            GetResultsError::DeviceLost => Some(Error::DeviceLost),
+            _ => None
+        }
+    }
+}
+
+impl From<OomError> for GetResultsError {
+    #[inline]
+    fn from(err: OomError) -> GetResultsError {
+        GetResultsError::OomError(err)
+    }
+}
+
+impl From<Error> for GetResultsError {
+    #[inline]
+    fn from(err: Error) -> GetResultsError {
+        match err {
+            err @ Error::OutOfHostMemory => GetResultsError::OomError(OomError::from(err)),
+            err @ Error::OutOfDeviceMemory => GetResultsError::OomError(OomError::from(err)),
+            Error::DeviceLost => GetResultsError::DeviceLost,
+            _ => panic!("unexpected error: {:?}", err)
+        }
+    }
+}
+
+/// A pool of pipeline statistics queries.
+///
+/// Each query counts, between the moment it begins and the moment it ends, the events that
+/// correspond to the counters enabled in the `QueryPipelineStatisticFlags` of the pool.
+///
+/// Creating such a pool requires the `pipeline_statistics_query` feature.
+pub struct PipelineStatisticsQueryPool {
+    inner: UnsafeQueryPool,
+    flags: QueryPipelineStatisticFlags,
+}
+
+impl PipelineStatisticsQueryPool {
+    /// See the docs of new().
+    pub fn raw(device: &Arc<Device>, num_slots: u32, flags: QueryPipelineStatisticFlags)
+               -> Result<PipelineStatisticsQueryPool, QueryPoolCreationError>
+    {
+        let ty = QueryType::PipelineStatistics(flags);
+
+        Ok(PipelineStatisticsQueryPool {
+            inner: try!(UnsafeQueryPool::new(device.clone(), ty, num_slots)),
+            flags: flags,
+        })
+    }
+
+    /// Builds a new query pool whose queries track the counters enabled in `flags`.
+    ///
+    /// Returns an error if the `pipeline_statistics_query` feature isn't enabled on the device.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device or host ran out of memory.
+    ///
+    #[inline]
+    pub fn new(device: &Arc<Device>, num_slots: u32, flags: QueryPipelineStatisticFlags)
+               -> Result<Arc<PipelineStatisticsQueryPool>, QueryPoolCreationError>
+    {
+        match PipelineStatisticsQueryPool::raw(device, num_slots, flags) {
+            Ok(pool) => Ok(Arc::new(pool)),
+            Err(QueryPoolCreationError::OomError(err)) => panic!("{:?}", err),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the counters that are tracked by the queries of this pool.
+    #[inline]
+    pub fn flags(&self) -> QueryPipelineStatisticFlags {
+        self.flags
+    }
+
+    /// Returns the number of slots of that query pool.
+    #[inline]
+    pub fn num_slots(&self) -> u32 {
+        self.inner.num_slots()
+    }
+
+    /// Returns the device that was used to create this pool.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        self.inner.device()
+    }
+
+    /// Reads the results of the queries in `slots`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `slots` is out of range of the pool.
+    ///
+    pub fn get_results(&self, slots: Range<u32>, flags: QueryResultFlags)
+                       -> Result<Vec<QueryResult<PipelineStatistics>>, GetResultsError>
+    {
+        let stats_flags = self.flags;
+        self.inner.get_results_with(slots, stats_flags.count(), flags, |values| {
+            PipelineStatistics::from_values(stats_flags, values)
+        })
+    }
+
+    /// Reads the results of the queries in `slots`, without waiting.
+    ///
+    /// Returns `GetResultsError::NotReady` if the result of one of the queries isn't available
+    /// yet.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `slots` is out of range of the pool.
+    ///
+    #[inline]
+    pub fn try_get_results(&self, slots: Range<u32>)
+                           -> Result<Vec<PipelineStatistics>, GetResultsError>
+    {
+        all_available(try!(self.get_results(slots, QueryResultFlags::none())))
+    }
+}
+
+unsafe impl QueryPool for PipelineStatisticsQueryPool {
+    #[inline]
+    fn inner(&self) -> &UnsafeQueryPool {
+        &self.inner
+    }
+}
+
+/// Results of a pipeline statistics query.
+///
+/// Each counter is `None` if it wasn't enabled in the flags of the pool.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PipelineStatistics {
+    pub input_assembly_vertices: Option<u64>,
+    pub input_assembly_primitives: Option<u64>,
+    pub vertex_shader_invocations: Option<u64>,
+    pub geometry_shader_invocations: Option<u64>,
+    pub geometry_shader_primitives: Option<u64>,
+    pub clipping_invocations: Option<u64>,
+    pub clipping_primitives: Option<u64>,
+    pub fragment_shader_invocations: Option<u64>,
+    pub tessellation_control_shader_patches: Option<u64>,
+    pub tessellation_evaluation_shader_invocations: Option<u64>,
+    pub compute_shader_invocations: Option<u64>,
+}
+
+impl PipelineStatistics {
+    // Builds a `PipelineStatistics` from the values written by Vulkan for a single query.
+    //
+    // Vulkan writes the enabled counters in the order of their bits, which is the order of the
+    // fields of the struct.
+    fn from_values(flags: QueryPipelineStatisticFlags, values: &[u64]) -> PipelineStatistics {
+        debug_assert_eq!(values.len(), flags.count());
+
+        let mut values = values.iter().cloned();
+        let mut next = |enabled: bool| if enabled { values.next() } else { None };
+
+        PipelineStatistics {
+            input_assembly_vertices: next(flags.input_assembly_vertices),
+            input_assembly_primitives: next(flags.input_assembly_primitives),
+            vertex_shader_invocations: next(flags.vertex_shader_invocations),
+            geometry_shader_invocations: next(flags.geometry_shader_invocations),
+            geometry_shader_primitives: next(flags.geometry_shader_primitives),
+            clipping_invocations: next(flags.clipping_invocations),
+            clipping_primitives: next(flags.clipping_primitives),
+            fragment_shader_invocations: next(flags.fragment_shader_invocations),
+            tessellation_control_shader_patches: next(flags.tessellation_control_shader_patches),
+            tessellation_evaluation_shader_invocations:
+                next(flags.tessellation_evaluation_shader_invocations),
+            compute_shader_invocations: next(flags.compute_shader_invocations),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use query::all_available;
+    use query::GetResultsError;
     use query::OcclusionQueriesPool;
+    use query::PipelineStatistics;
+    use query::PipelineStatisticsQueryPool;
     use query::QueryPipelineStatisticFlags;
     use query::QueryPoolCreationError;
+    use query::QueryResult;
+    use query::QueryResultFlags;
     use query::QueryType;
+    use query::TimestampQueryPool;
     use query::UnsafeQueryPool;
+    use vk;
 
     #[test]
     fn occlusion_create() {
@@ -305,4 +923,63 @@ mod tests {
             _ => panic!()
         };
     }
+
+    #[test]
+    fn pipeline_statistics_pool_feature() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        match PipelineStatisticsQueryPool::new(&device, 16, QueryPipelineStatisticFlags::all()) {
+            Err(QueryPoolCreationError::PipelineStatisticsQueryFeatureNotEnabled) => (),
+            _ => panic!()
+        };
+    }
+
+    #[test]
+    fn pipeline_statistics_decode() {
+        let mut flags = QueryPipelineStatisticFlags::none();
+        flags.input_assembly_primitives = true;
+        flags.fragment_shader_invocations = true;
+        assert_eq!(flags.count(), 2);
+        assert_eq!(QueryPipelineStatisticFlags::all().count(), 11);
+
+        let stats = PipelineStatistics::from_values(flags, &[12, 3400]);
+        assert_eq!(stats.input_assembly_vertices, None);
+        assert_eq!(stats.input_assembly_primitives, Some(12));
+        assert_eq!(stats.fragment_shader_invocations, Some(3400));
+        assert_eq!(stats.compute_shader_invocations, None);
+    }
+
+    #[test]
+    fn result_flags() {
+        let none: vk::QueryResultFlags = QueryResultFlags::none().into();
+        let wait: vk::QueryResultFlags = QueryResultFlags::wait().into();
+        let partial: vk::QueryResultFlags = QueryResultFlags::partial().into();
+        assert_eq!(none, 0);
+        assert_eq!(wait, vk::QUERY_RESULT_WAIT_BIT);
+        assert_eq!(partial, vk::QUERY_RESULT_PARTIAL_BIT);
+
+        assert_eq!(QueryResult::Available(5).available(), Some(5));
+        assert_eq!(QueryResult::Unavailable(Some(2)).available(), None);
+        assert!(!QueryResult::Unavailable::<u64>(None).is_available());
+    }
+
+    #[test]
+    fn timestamp_conversion() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pool = TimestampQueryPool::new(&device, 2);
+
+        let period = device.physical_device().limits().timestamp_period() as f64;
+        assert_eq!(pool.ticks_to_nanoseconds(0), 0);
+        assert_eq!(pool.ticks_to_nanoseconds(1000), (1000.0 * period) as u64);
+        assert_eq!(pool.elapsed_nanoseconds(0xfffffffff, 999, 36), (1000.0 * period) as u64);
+    }
+
+    #[test]
+    fn all_available_or_not_ready() {
+        let results = vec![QueryResult::Available(3u64), QueryResult::Available(5)];
+        assert_eq!(all_available(results), Ok(vec![3, 5]));
+
+        let results = vec![QueryResult::Available(3u64), QueryResult::Unavailable(Some(1))];
+        assert_eq!(all_available(results), Err(GetResultsError::NotReady));
+    }
 }
@@ -59,16 +59,38 @@
 //!
 //! Samplers that don't use `ClampToBorder` are not concerned by these restrictions.
 //!
-// FIXME: restrictions aren't checked yet
+//! These restrictions are checked by `Sampler::check_image_view`, which is called when a sampler
+//! and an image view are written together in a descriptor set.
+//!
+//! # YCbCr conversion
+//!
+//! Images with a YCbCr format, for example decoded video frames, can only be sampled through a
+//! `SamplerYcbcrConversion`. The conversion describes how the YCbCr values are converted to RGB,
+//! and must be passed both when creating the sampler with `Sampler::ycbcr` and when creating the
+//! image view with `UnsafeImageView::raw_ycbcr`. The shader then reads RGB values.
+//!
+//! This requires the `khr_sampler_ycbcr_conversion` extension to be enabled on the device.
+//!
+use std::collections::HashMap;
 use std::error;
 use std::fmt;
+use std::hash::BuildHasherDefault;
 use std::mem;
 use std::ptr;
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::Weak;
+use fnv::FnvHasher;
 
 use device::Device;
+use format::Format;
+use format::FormatTy;
+use image::Dimensions;
+use image::ImageAspect;
+use image::traits::ImageView;
 use Error;
 use OomError;
+use VulkanError;
 use VulkanObject;
 use VulkanPointers;
 use check_errors;
@@ -82,13 +104,13 @@ pub struct Sampler {
     device: Arc<Device>,
     compare_mode: bool,
     unnormalized: bool,
+    border_color: Option<BorderColor>,
     usable_with_float_formats: bool,
     usable_with_int_formats: bool,
     usable_with_swizzling: bool,
+    ycbcr_conversion: Option<Arc<SamplerYcbcrConversion>>,
 }
 
-// TODO: what's the story with VK_KHR_mirror_clamp_to_edge? Is it an extension or is it core?
-
 impl Sampler {
     /// Shortcut for creating a sampler with linear sampling, linear mipmaps, and with the repeat
     /// mode for borders.
@@ -122,6 +144,22 @@ impl Sampler {
                      SamplerAddressMode::Repeat, 0.0, 1.0, 0.0, 1.0).unwrap()
     }
 
+    /// Shortcut for creating a compare-mode sampler with linear sampling, that only uses the main
+    /// level of images. Coordinates out of range return a depth of 1.0.
+    ///
+    /// Suitable for sampling shadow maps. `compare` is usually `Compare::LessOrEqual`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if out of memory or the maximum number of samplers has exceeded.
+    ///
+    #[inline]
+    pub fn simple_compare_linear(device: &Arc<Device>, compare: Compare) -> Arc<Sampler> {
+        let address = SamplerAddressMode::ClampToBorder(BorderColor::FloatOpaqueWhite);
+        Sampler::compare(device, Filter::Linear, Filter::Linear, MipmapMode::Nearest, address,
+                         address, address, 0.0, 1.0, 0.0, 1.0, compare).unwrap()
+    }
+
     /// Creates a new `Sampler` with the given behavior.
     ///
     /// `mag_filter` and `min_filter` define how the implementation should sample from the image
@@ -167,8 +205,17 @@ impl Sampler {
     /// indicating how much the reference value (passed by the shader) compares to the value in the
     /// texture.
     ///
+    /// In a shader, such a sampler corresponds to a `sampler2DShadow` or similar type. If the
+    /// filters are `Linear` and the format of the image supports linear filtering, the
+    /// implementation may perform percentage-closer filtering, ie. compare several texels and
+    /// return the average of the results.
+    ///
+    /// `compare` is the operation applied between the reference value and the value of the
+    /// texture. The result is 1.0 if the comparison passes, and 0.0 otherwise.
+    ///
     /// Note that it doesn't make sense to create a compare-mode sampler with an integer border
-    /// color, as such a sampler would be unusable.
+    /// color, as such a sampler would be unusable. See `check_image_view` for the images this
+    /// sampler can be used with.
     ///
     /// # Panic
     ///
@@ -220,6 +267,16 @@ impl Sampler {
             }
         }
 
+        // Check that the extension required by `MirrorClampToEdge` is enabled.
+        if address_u == SamplerAddressMode::MirrorClampToEdge ||
+           address_v == SamplerAddressMode::MirrorClampToEdge ||
+           address_w == SamplerAddressMode::MirrorClampToEdge
+        {
+            if !device.loaded_extensions().khr_sampler_mirror_clamp_to_edge {
+                return Err(SamplerCreationError::SamplerMirrorClampToEdgeExtensionNotEnabled);
+            }
+        }
+
         // Handling border color.
         let border_color = address_u.border_color();
         let border_color = match (border_color, address_v.border_color()) {
@@ -267,37 +324,34 @@ impl Sampler {
             device: device.clone(),
             compare_mode: compare.is_some(),
             unnormalized: false,
-            usable_with_float_formats: match border_color {
-                Some(BorderColor::FloatTransparentBlack) => true,
-                Some(BorderColor::FloatOpaqueBlack) => true,
-                Some(BorderColor::FloatOpaqueWhite) => true,
-                Some(_) => false,
-                None => true,
-            },
-            usable_with_int_formats: compare.is_none() && match border_color {
-                Some(BorderColor::IntTransparentBlack) => true,
-                Some(BorderColor::IntOpaqueBlack) => true,
-                Some(BorderColor::IntOpaqueWhite) => true,
-                Some(_) => false,
-                None => true,
-            },
-            usable_with_swizzling: match border_color {
-                Some(BorderColor::FloatOpaqueBlack) => false,
-                Some(BorderColor::IntOpaqueBlack) => false,
-                _ => true,
-            },
+            border_color: border_color,
+            usable_with_float_formats: border_color.map(|b| b.is_float()).unwrap_or(true),
+            usable_with_int_formats: compare.is_none() &&
+                                     border_color.map(|b| !b.is_float()).unwrap_or(true),
+            usable_with_swizzling: border_color.map(|b| !b.is_opaque_black()).unwrap_or(true),
+            ycbcr_conversion: None,
         }))
     }
 
     /// Creates a sampler with unnormalized coordinates. This means that texture coordinates won't
     /// range between `0.0` and `1.0` but use plain pixel offsets.
     ///
+    /// This is useful for pixel-exact sampling, for example of the glyphs of a font atlas or of
+    /// UI elements.
+    ///
+    /// The Vulkan specs restrict the configuration of such a sampler, which is why this
+    /// constructor takes fewer parameters than `new`: the same filter is used for magnification
+    /// and minification, mipmapping and anisotropic filtering are disabled, the sampler isn't a
+    /// compare-mode sampler, and the address modes are limited to the clamping ones.
+    ///
     /// Using an unnormalized sampler adds a few restrictions:
     ///
     /// - It can only be used with non-array 1D or 2D images.
     /// - It can only be used with images with a single mipmap.
     /// - Projection and offsets can't be used by shaders. Only the first mipmap can be accessed.
     ///
+    /// The first two restrictions are checked by `check_image_view`.
+    ///
     /// # Panic
     ///
     /// - Panics if multiple `ClampToBorder` values are passed and the border color is different.
@@ -326,10 +380,11 @@ impl Sampler {
                 mipmapMode: vk::SAMPLER_MIPMAP_MODE_NEAREST,
                 addressModeU: address_u.to_vk(),
                 addressModeV: address_v.to_vk(),
-                addressModeW: vk::SAMPLER_ADDRESS_MODE_CLAMP_TO_EDGE,       // unused by the impl
+                // The W coordinate is unused, but the address mode must still be a clamping one.
+                addressModeW: vk::SAMPLER_ADDRESS_MODE_CLAMP_TO_EDGE,
                 mipLodBias: 0.0,
                 anisotropyEnable: vk::FALSE,
-                maxAnisotropy: 0.0,
+                maxAnisotropy: 1.0,
                 compareEnable: vk::FALSE,
                 compareOp: vk::COMPARE_OP_NEVER,
                 minLod: 0.0,
@@ -349,28 +404,81 @@ impl Sampler {
             device: device.clone(),
             compare_mode: false,
             unnormalized: true,
-            usable_with_float_formats: match border_color {
-                Some(BorderColor::FloatTransparentBlack) => true,
-                Some(BorderColor::FloatOpaqueBlack) => true,
-                Some(BorderColor::FloatOpaqueWhite) => true,
-                Some(_) => false,
-                None => true,
-            },
-            usable_with_int_formats: match border_color {
-                Some(BorderColor::IntTransparentBlack) => true,
-                Some(BorderColor::IntOpaqueBlack) => true,
-                Some(BorderColor::IntOpaqueWhite) => true,
-                Some(_) => false,
-                None => true,
-            },
-            usable_with_swizzling: match border_color {
-                Some(BorderColor::FloatOpaqueBlack) => false,
-                Some(BorderColor::IntOpaqueBlack) => false,
-                _ => true,
-            },
+            border_color: border_color,
+            usable_with_float_formats: border_color.map(|b| b.is_float()).unwrap_or(true),
+            usable_with_int_formats: border_color.map(|b| !b.is_float()).unwrap_or(true),
+            usable_with_swizzling: border_color.map(|b| !b.is_opaque_black()).unwrap_or(true),
+            ycbcr_conversion: None,
+        }))
+    }
+
+    /// Creates a sampler that converts YCbCr data to RGB with the given conversion.
+    ///
+    /// The Vulkan specs restrict the configuration of such a sampler: the chroma filter of the
+    /// conversion is used for magnification and minification, the address modes are
+    /// `ClampToEdge`, and anisotropic filtering is disabled. The sampler can only be used with
+    /// image views that were created with the same conversion, and must be an immutable sampler
+    /// of the descriptor set layout. See `UnsafeDescriptorSetLayout::raw_with_immutable_samplers`.
+    pub fn ycbcr(device: &Arc<Device>, conversion: &Arc<SamplerYcbcrConversion>)
+                 -> Result<Arc<Sampler>, SamplerCreationError>
+    {
+        assert_eq!(&**device as *const Device, &**conversion.device() as *const Device);
+
+        let vk = device.pointers();
+
+        let sampler = unsafe {
+            let conversion_infos = vk::SamplerYcbcrConversionInfoKHR {
+                sType: vk::STRUCTURE_TYPE_SAMPLER_YCBCR_CONVERSION_INFO_KHR,
+                pNext: ptr::null(),
+                conversion: conversion.internal_object(),
+            };
+
+            let infos = vk::SamplerCreateInfo {
+                sType: vk::STRUCTURE_TYPE_SAMPLER_CREATE_INFO,
+                pNext: &conversion_infos as *const vk::SamplerYcbcrConversionInfoKHR as *const _,
+                flags: 0,   // reserved
+                magFilter: conversion.chroma_filter() as u32,
+                minFilter: conversion.chroma_filter() as u32,
+                mipmapMode: vk::SAMPLER_MIPMAP_MODE_NEAREST,
+                addressModeU: vk::SAMPLER_ADDRESS_MODE_CLAMP_TO_EDGE,
+                addressModeV: vk::SAMPLER_ADDRESS_MODE_CLAMP_TO_EDGE,
+                addressModeW: vk::SAMPLER_ADDRESS_MODE_CLAMP_TO_EDGE,
+                mipLodBias: 0.0,
+                anisotropyEnable: vk::FALSE,
+                maxAnisotropy: 1.0,
+                compareEnable: vk::FALSE,
+                compareOp: vk::COMPARE_OP_NEVER,
+                minLod: 0.0,
+                maxLod: vk::LOD_CLAMP_NONE,
+                borderColor: 0,
+                unnormalizedCoordinates: vk::FALSE,
+            };
+
+            let mut output = mem::uninitialized();
+            try!(check_errors(vk.CreateSampler(device.internal_object(), &infos,
+                                               ptr::null(), &mut output)));
+            output
+        };
+
+        Ok(Arc::new(Sampler {
+            sampler: sampler,
+            device: device.clone(),
+            compare_mode: false,
+            unnormalized: false,
+            border_color: None,
+            usable_with_float_formats: true,
+            usable_with_int_formats: false,
+            usable_with_swizzling: true,
+            ycbcr_conversion: Some(conversion.clone()),
         }))
     }
 
+    /// Returns the device this sampler belongs to.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
     /// Returns true if the sampler is a compare-mode sampler.
     #[inline]
     pub fn compare_mode(&self) -> bool {
@@ -383,6 +491,19 @@ impl Sampler {
         self.unnormalized
     }
 
+    /// Returns the border color used by the `ClampToBorder` address modes, or `None` if the
+    /// sampler doesn't use `ClampToBorder`.
+    #[inline]
+    pub fn border_color(&self) -> Option<BorderColor> {
+        self.border_color
+    }
+
+    /// Returns the YCbCr conversion of the sampler, if it was created with `Sampler::ycbcr`.
+    #[inline]
+    pub fn ycbcr_conversion(&self) -> Option<&Arc<SamplerYcbcrConversion>> {
+        self.ycbcr_conversion.as_ref()
+    }
+
     /// Returns true if the sampler can be used with floating-point image views. See the
     /// documentation of the `sampler` module for more info.
     #[inline]
@@ -403,6 +524,84 @@ impl Sampler {
     pub fn usable_with_swizzling(&self) -> bool {
         self.usable_with_swizzling
     }
+
+    /// Checks whether this sampler can be used to sample from the given image view.
+    ///
+    /// The numeric type of the view is determined by its format, or by its aspect for
+    /// depth-stencil formats: depth is sampled as floating-point and stencil as integer. See
+    /// the documentation of the `sampler` module for more info.
+    ///
+    /// Compare-mode samplers can only be used with views that give access to the depth aspect of
+    /// a depth or depth-stencil image. Unnormalized samplers can only be used with non-array 1D
+    /// or 2D views that have a single mipmap level. Views with a YCbCr format can only be used
+    /// with a sampler that has the same YCbCr conversion as the view.
+    pub fn check_image_view<I: ?Sized>(&self, view: &I) -> Result<(), IncompatibleImageViewError>
+        where I: ImageView
+    {
+        let format = view.format();
+        let aspect = view.inner().aspect();
+
+        let same_conversion = match (self.ycbcr_conversion.as_ref(),
+                                     view.inner().ycbcr_conversion())
+        {
+            (Some(a), Some(b)) => &**a as *const _ == &**b as *const _,
+            (None, None) => !format.is_ycbcr(),
+            _ => false,
+        };
+
+        if !same_conversion {
+            return Err(IncompatibleImageViewError::YcbcrConversionMismatch { format: format });
+        }
+
+        if self.compare_mode {
+            match aspect {
+                ImageAspect::Depth | ImageAspect::DepthStencil => (),
+                _ => return Err(IncompatibleImageViewError::CompareModeWithoutDepthAspect {
+                    format: format,
+                }),
+            }
+        }
+
+        let float = match (format.ty(), aspect) {
+            (FormatTy::Float, _) | (FormatTy::Compressed, _) | (FormatTy::Depth, _) => true,
+            (FormatTy::Uint, _) | (FormatTy::Sint, _) | (FormatTy::Stencil, _) => false,
+            (FormatTy::DepthStencil, ImageAspect::Stencil) => false,
+            (FormatTy::DepthStencil, _) => true,
+        };
+
+        if let Some(border_color) = self.border_color {
+            if float && !border_color.is_float() {
+                return Err(IncompatibleImageViewError::IntBorderColorWithFloatFormat {
+                    border_color: border_color,
+                    format: format,
+                });
+            }
+
+            if !float && border_color.is_float() {
+                return Err(IncompatibleImageViewError::FloatBorderColorWithIntFormat {
+                    border_color: border_color,
+                    format: format,
+                });
+            }
+        }
+
+        if !self.usable_with_swizzling && !view.identity_swizzle() {
+            return Err(IncompatibleImageViewError::OpaqueBlackBorderColorWithSwizzling);
+        }
+
+        if self.unnormalized {
+            match view.dimensions() {
+                Dimensions::Dim1d { .. } | Dimensions::Dim2d { .. } => (),
+                _ => return Err(IncompatibleImageViewError::UnnormalizedWithUnsupportedDimensions),
+            }
+
+            if view.inner().mipmap_levels().len() != 1 {
+                return Err(IncompatibleImageViewError::UnnormalizedWithMultipleMipmaps);
+            }
+        }
+
+        Ok(())
+    }
 }
 
 unsafe impl VulkanObject for Sampler {
@@ -424,6 +623,331 @@ impl Drop for Sampler {
     }
 }
 
+/// Describes how the YCbCr data of an image is converted to RGB when it is sampled.
+///
+/// See the documentation of the module for more info.
+#[derive(Debug)]
+pub struct SamplerYcbcrConversion {
+    conversion: vk::SamplerYcbcrConversionKHR,
+    device: Arc<Device>,
+    format: Format,
+    chroma_filter: Filter,
+}
+
+impl SamplerYcbcrConversion {
+    /// Creates a new conversion for images of the given format.
+    ///
+    /// `model` and `range` describe how the YCbCr values were encoded. `chroma_offset` is the
+    /// location of the chroma samples relative to the luma samples in the X and Y directions, for
+    /// formats whose chroma planes are subsampled. `chroma_filter` is used to reconstruct the
+    /// chroma values at the location of the luma samples.
+    pub fn new(device: &Arc<Device>, format: Format, model: YcbcrModelConversion,
+               range: YcbcrRange, chroma_offset: [ChromaLocation; 2], chroma_filter: Filter)
+               -> Result<Arc<SamplerYcbcrConversion>, SamplerYcbcrConversionCreationError>
+    {
+        if !device.loaded_extensions().khr_sampler_ycbcr_conversion {
+            return Err(SamplerYcbcrConversionCreationError::ExtensionNotEnabled);
+        }
+
+        if !format.is_ycbcr() {
+            return Err(SamplerYcbcrConversionCreationError::FormatNotYcbcr { format: format });
+        }
+
+        let vk = device.pointers();
+
+        let conversion = unsafe {
+            let infos = vk::SamplerYcbcrConversionCreateInfoKHR {
+                sType: vk::STRUCTURE_TYPE_SAMPLER_YCBCR_CONVERSION_CREATE_INFO_KHR,
+                pNext: ptr::null(),
+                format: format as u32,
+                ycbcrModel: model as u32,
+                ycbcrRange: range as u32,
+                components: vk::ComponentMapping {
+                    r: vk::COMPONENT_SWIZZLE_IDENTITY,
+                    g: vk::COMPONENT_SWIZZLE_IDENTITY,
+                    b: vk::COMPONENT_SWIZZLE_IDENTITY,
+                    a: vk::COMPONENT_SWIZZLE_IDENTITY,
+                },
+                xChromaOffset: chroma_offset[0] as u32,
+                yChromaOffset: chroma_offset[1] as u32,
+                chromaFilter: chroma_filter as u32,
+                forceExplicitReconstruction: vk::FALSE,
+            };
+
+            let mut output = mem::uninitialized();
+            try!(check_errors(vk.CreateSamplerYcbcrConversionKHR(device.internal_object(),
+                                                                  &infos, ptr::null(),
+                                                                  &mut output)));
+            output
+        };
+
+        Ok(Arc::new(SamplerYcbcrConversion {
+            conversion: conversion,
+            device: device.clone(),
+            format: format,
+            chroma_filter: chroma_filter,
+        }))
+    }
+
+    /// Returns the device this conversion belongs to.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    /// Returns the format of the images that this conversion applies to.
+    #[inline]
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Returns the filter used to reconstruct the chroma values.
+    #[inline]
+    pub fn chroma_filter(&self) -> Filter {
+        self.chroma_filter
+    }
+}
+
+unsafe impl VulkanObject for SamplerYcbcrConversion {
+    type Object = vk::SamplerYcbcrConversionKHR;
+
+    #[inline]
+    fn internal_object(&self) -> vk::SamplerYcbcrConversionKHR {
+        self.conversion
+    }
+}
+
+impl Drop for SamplerYcbcrConversion {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            let vk = self.device.pointers();
+            vk.DestroySamplerYcbcrConversionKHR(self.device.internal_object(), self.conversion,
+                                                ptr::null());
+        }
+    }
+}
+
+/// Color model of the YCbCr data of an image.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u32)]
+pub enum YcbcrModelConversion {
+    /// The values are already RGB and are passed through.
+    RgbIdentity = vk::SAMPLER_YCBCR_MODEL_CONVERSION_RGB_IDENTITY_KHR,
+    /// The values are YCbCr and are only range-expanded, not converted to RGB.
+    YcbcrIdentity = vk::SAMPLER_YCBCR_MODEL_CONVERSION_YCBCR_IDENTITY_KHR,
+    /// The values are converted with the BT.709 color model, used by HD video.
+    Ycbcr709 = vk::SAMPLER_YCBCR_MODEL_CONVERSION_YCBCR_709_KHR,
+    /// The values are converted with the BT.601 color model, used by SD video.
+    Ycbcr601 = vk::SAMPLER_YCBCR_MODEL_CONVERSION_YCBCR_601_KHR,
+    /// The values are converted with the BT.2020 color model, used by UHD video.
+    Ycbcr2020 = vk::SAMPLER_YCBCR_MODEL_CONVERSION_YCBCR_2020_KHR,
+}
+
+/// Range of the encoded YCbCr values.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u32)]
+pub enum YcbcrRange {
+    /// The values use the full range of the format.
+    ItuFull = vk::SAMPLER_YCBCR_RANGE_ITU_FULL_KHR,
+    /// The values use the restricted range of the ITU standards, for example 16 to 235 for the
+    /// luma of 8 bits formats.
+    ItuNarrow = vk::SAMPLER_YCBCR_RANGE_ITU_NARROW_KHR,
+}
+
+/// Location of the chroma samples relative to the luma samples.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u32)]
+pub enum ChromaLocation {
+    /// The chroma samples are at the same location as the even luma samples.
+    CositedEven = vk::CHROMA_LOCATION_COSITED_EVEN_KHR,
+    /// The chroma samples are between the luma samples.
+    Midpoint = vk::CHROMA_LOCATION_MIDPOINT_KHR,
+}
+
+/// Cache that returns the existing sampler when a sampler with the same parameters is
+/// requested again.
+///
+/// Implementations can have a low limit on the number of samplers that exist at the same time,
+/// as indicated by the `max_sampler_allocation_count` limit, while a program usually only needs
+/// a few different samplers. Each device has its own cache, which is returned by
+/// `Device::sampler_cache()`.
+///
+/// The cache only holds weak references, so that a sampler is destroyed when it isn't used
+/// anymore.
+///
+/// # Example
+///
+/// ```no_run
+/// use vulkano::sampler;
+///
+/// # let device: std::sync::Arc<vulkano::device::Device> = unsafe { ::std::mem::uninitialized() };
+/// let address = sampler::SamplerAddressMode::Repeat;
+/// let a = device.sampler_cache().sampler(&device, sampler::Filter::Linear,
+///                                        sampler::Filter::Linear, sampler::MipmapMode::Linear,
+///                                        address, address, address, 0.0, 1.0, 0.0, 100.0)
+///                                        .unwrap();
+/// let b = device.sampler_cache().sampler(&device, sampler::Filter::Linear,
+///                                        sampler::Filter::Linear, sampler::MipmapMode::Linear,
+///                                        address, address, address, 0.0, 1.0, 0.0, 100.0)
+///                                        .unwrap();
+/// assert!(std::sync::Arc::ptr_eq(&a, &b));
+/// ```
+pub struct SamplerCache {
+    samplers: Mutex<HashMap<SamplerKey, Weak<Sampler>, BuildHasherDefault<FnvHasher>>>,
+}
+
+// Creation parameters of a sampler. Floating-point values are stored as bits.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct SamplerKey {
+    mag_filter: Filter,
+    min_filter: Filter,
+    mipmap_mode: MipmapMode,
+    address: [SamplerAddressMode; 3],
+    mip_lod_bias: u32,
+    max_anisotropy: u32,
+    min_lod: u32,
+    max_lod: u32,
+    compare: Option<Compare>,
+    unnormalized: bool,
+}
+
+impl SamplerCache {
+    #[doc(hidden)]
+    #[inline]
+    pub fn new() -> SamplerCache {
+        SamplerCache {
+            samplers: Mutex::new(Default::default()),
+        }
+    }
+
+    /// Same as `Sampler::new`, but returns an existing sampler if there is one with the same
+    /// parameters.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `device` isn't the device that owns this cache.
+    /// - Same panic reasons as `Sampler::new`.
+    ///
+    pub fn sampler(&self, device: &Arc<Device>, mag_filter: Filter, min_filter: Filter,
+                   mipmap_mode: MipmapMode, address_u: SamplerAddressMode,
+                   address_v: SamplerAddressMode, address_w: SamplerAddressMode,
+                   mip_lod_bias: f32, max_anisotropy: f32, min_lod: f32, max_lod: f32)
+                   -> Result<Arc<Sampler>, SamplerCreationError>
+    {
+        let key = SamplerKey {
+            mag_filter: mag_filter,
+            min_filter: min_filter,
+            mipmap_mode: mipmap_mode,
+            address: [address_u, address_v, address_w],
+            mip_lod_bias: mip_lod_bias.to_bits(),
+            max_anisotropy: max_anisotropy.to_bits(),
+            min_lod: min_lod.to_bits(),
+            max_lod: max_lod.to_bits(),
+            compare: None,
+            unnormalized: false,
+        };
+
+        self.get_or_create(device, key, || {
+            Sampler::new(device, mag_filter, min_filter, mipmap_mode, address_u, address_v,
+                         address_w, mip_lod_bias, max_anisotropy, min_lod, max_lod)
+        })
+    }
+
+    /// Same as `Sampler::compare`, but returns an existing sampler if there is one with the same
+    /// parameters.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `device` isn't the device that owns this cache.
+    /// - Same panic reasons as `Sampler::compare`.
+    ///
+    pub fn compare_sampler(&self, device: &Arc<Device>, mag_filter: Filter, min_filter: Filter,
+                           mipmap_mode: MipmapMode, address_u: SamplerAddressMode,
+                           address_v: SamplerAddressMode, address_w: SamplerAddressMode,
+                           mip_lod_bias: f32, max_anisotropy: f32, min_lod: f32, max_lod: f32,
+                           compare: Compare)
+                           -> Result<Arc<Sampler>, SamplerCreationError>
+    {
+        let key = SamplerKey {
+            mag_filter: mag_filter,
+            min_filter: min_filter,
+            mipmap_mode: mipmap_mode,
+            address: [address_u, address_v, address_w],
+            mip_lod_bias: mip_lod_bias.to_bits(),
+            max_anisotropy: max_anisotropy.to_bits(),
+            min_lod: min_lod.to_bits(),
+            max_lod: max_lod.to_bits(),
+            compare: Some(compare),
+            unnormalized: false,
+        };
+
+        self.get_or_create(device, key, || {
+            Sampler::compare(device, mag_filter, min_filter, mipmap_mode, address_u, address_v,
+                             address_w, mip_lod_bias, max_anisotropy, min_lod, max_lod, compare)
+        })
+    }
+
+    /// Same as `Sampler::unnormalized`, but returns an existing sampler if there is one with the
+    /// same parameters.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `device` isn't the device that owns this cache.
+    /// - Same panic reasons as `Sampler::unnormalized`.
+    ///
+    pub fn unnormalized_sampler(&self, device: &Arc<Device>, filter: Filter,
+                                address_u: UnnormalizedSamplerAddressMode,
+                                address_v: UnnormalizedSamplerAddressMode)
+                                -> Result<Arc<Sampler>, SamplerCreationError>
+    {
+        let key = SamplerKey {
+            mag_filter: filter,
+            min_filter: filter,
+            mipmap_mode: MipmapMode::Nearest,
+            address: [address_u.to_normalized(), address_v.to_normalized(),
+                      SamplerAddressMode::ClampToEdge],
+            mip_lod_bias: 0,
+            max_anisotropy: 0,
+            min_lod: 0,
+            max_lod: 0,
+            compare: None,
+            unnormalized: true,
+        };
+
+        self.get_or_create(device, key, || {
+            Sampler::unnormalized(device, filter, address_u, address_v)
+        })
+    }
+
+    /// Returns the number of samplers of the cache that are still alive.
+    pub fn len(&self) -> usize {
+        self.samplers.lock().unwrap().values().filter(|s| s.upgrade().is_some()).count()
+    }
+
+    // Returns the sampler corresponding to `key`, or creates it with `create`.
+    fn get_or_create<F>(&self, device: &Arc<Device>, key: SamplerKey, create: F)
+                        -> Result<Arc<Sampler>, SamplerCreationError>
+        where F: FnOnce() -> Result<Arc<Sampler>, SamplerCreationError>
+    {
+        assert!(device.sampler_cache() as *const SamplerCache == self as *const SamplerCache,
+                "The device doesn't own this sampler cache");
+
+        let mut samplers = self.samplers.lock().unwrap();
+
+        if let Some(sampler) = samplers.get(&key).and_then(|s| s.upgrade()) {
+            return Ok(sampler);
+        }
+
+        // Removing the samplers that have been destroyed in the meanwhile.
+        samplers.retain(|_, s| s.upgrade().is_some());
+
+        let sampler = try!(create());
+        samplers.insert(key, Arc::downgrade(&sampler));
+        Ok(sampler)
+    }
+}
+
 /// Describes how the color of each pixel should be determined.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[repr(u32)]
@@ -474,6 +998,9 @@ pub enum SamplerAddressMode {
 
     /// Similar to `MirroredRepeat`, except that coordinates are clamped to the range
     /// `[-1.0, 1.0]`.
+    ///
+    /// Requires the `khr_sampler_mirror_clamp_to_edge` extension to be enabled when creating the
+    /// device.
     MirrorClampToEdge,
 }
 
@@ -517,6 +1044,17 @@ pub enum UnnormalizedSamplerAddressMode {
 }
 
 impl UnnormalizedSamplerAddressMode {
+    // Returns the equivalent normalized address mode.
+    #[inline]
+    fn to_normalized(self) -> SamplerAddressMode {
+        match self {
+            UnnormalizedSamplerAddressMode::ClampToEdge => SamplerAddressMode::ClampToEdge,
+            UnnormalizedSamplerAddressMode::ClampToBorder(c) => {
+                SamplerAddressMode::ClampToBorder(c)
+            },
+        }
+    }
+
     #[inline]
     fn to_vk(self) -> vk::SamplerAddressMode {
         match self {
@@ -565,6 +1103,187 @@ pub enum BorderColor {
     IntOpaqueWhite = vk::BORDER_COLOR_INT_OPAQUE_WHITE,
 }
 
+impl BorderColor {
+    /// Returns true if the border color is floating-point, ie. if it can be used with
+    /// floating-point, normalized and depth image views.
+    #[inline]
+    pub fn is_float(&self) -> bool {
+        match *self {
+            BorderColor::FloatTransparentBlack | BorderColor::FloatOpaqueBlack |
+            BorderColor::FloatOpaqueWhite => true,
+            BorderColor::IntTransparentBlack | BorderColor::IntOpaqueBlack |
+            BorderColor::IntOpaqueWhite => false,
+        }
+    }
+
+    /// Returns true if the border color is opaque black, which can't be used with an image view
+    /// that swizzles its components.
+    #[inline]
+    pub fn is_opaque_black(&self) -> bool {
+        match *self {
+            BorderColor::FloatOpaqueBlack | BorderColor::IntOpaqueBlack => true,
+            _ => false,
+        }
+    }
+}
+
+/// Error that can happen when a sampler is used with an image view it isn't compatible with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IncompatibleImageViewError {
+    /// The sampler uses a floating-point border color, but the view has an integer or stencil
+    /// format.
+    FloatBorderColorWithIntFormat {
+        border_color: BorderColor,
+        format: Format,
+    },
+    /// The sampler uses an integer border color, but the view has a floating-point, normalized
+    /// or depth format.
+    IntBorderColorWithFloatFormat {
+        border_color: BorderColor,
+        format: Format,
+    },
+    /// The sampler is a compare-mode sampler, but the view doesn't give access to the depth
+    /// aspect of a depth or depth-stencil image.
+    CompareModeWithoutDepthAspect {
+        format: Format,
+    },
+    /// The sampler uses an opaque black border color, but the view swizzles its components.
+    OpaqueBlackBorderColorWithSwizzling,
+    /// The sampler is unnormalized, but the view isn't a non-array 1D or 2D view.
+    UnnormalizedWithUnsupportedDimensions,
+    /// The sampler is unnormalized, but the view has more than one mipmap level.
+    UnnormalizedWithMultipleMipmaps,
+    /// The sampler and the view don't use the same YCbCr conversion, or the view has a YCbCr
+    /// format and neither of them uses a conversion.
+    YcbcrConversionMismatch {
+        format: Format,
+    },
+}
+
+impl error::Error for IncompatibleImageViewError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            IncompatibleImageViewError::FloatBorderColorWithIntFormat { .. } => {
+                "the sampler uses a floating-point border color but the image view has an \
+                 integer format"
+            },
+            IncompatibleImageViewError::IntBorderColorWithFloatFormat { .. } => {
+                "the sampler uses an integer border color but the image view has a \
+                 floating-point format"
+            },
+            IncompatibleImageViewError::CompareModeWithoutDepthAspect { .. } => {
+                "the sampler is a compare-mode sampler but the image view doesn't have a depth \
+                 aspect"
+            },
+            IncompatibleImageViewError::OpaqueBlackBorderColorWithSwizzling => {
+                "the sampler uses an opaque black border color but the image view uses \
+                 components swizzling"
+            },
+            IncompatibleImageViewError::UnnormalizedWithUnsupportedDimensions => {
+                "the sampler is unnormalized but the image view isn't a non-array 1D or 2D view"
+            },
+            IncompatibleImageViewError::UnnormalizedWithMultipleMipmaps => {
+                "the sampler is unnormalized but the image view has more than one mipmap level"
+            },
+            IncompatibleImageViewError::YcbcrConversionMismatch { .. } => {
+                "the sampler and the image view don't use the same YCbCr conversion"
+            },
+        }
+    }
+}
+
+impl fmt::Display for IncompatibleImageViewError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for IncompatibleImageViewError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        None
+    }
+}
+
+/// Error that can happen when creating a `SamplerYcbcrConversion`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SamplerYcbcrConversionCreationError {
+    /// Not enough memory.
+    OomError(OomError),
+
+    /// The `khr_sampler_ycbcr_conversion` extension isn't enabled on the device.
+    ExtensionNotEnabled,
+
+    /// The format isn't a YCbCr format.
+    FormatNotYcbcr {
+        format: Format,
+    },
+}
+
+impl error::Error for SamplerYcbcrConversionCreationError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            SamplerYcbcrConversionCreationError::OomError(_) => "not enough memory available",
+            SamplerYcbcrConversionCreationError::ExtensionNotEnabled => {
+                "the `khr_sampler_ycbcr_conversion` extension is not enabled"
+            },
+            SamplerYcbcrConversionCreationError::FormatNotYcbcr { .. } => {
+                "the format isn't a YCbCr format"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            SamplerYcbcrConversionCreationError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for SamplerYcbcrConversionCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for SamplerYcbcrConversionCreationError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            SamplerYcbcrConversionCreationError::OomError(ref err) => err.error_code(),
+            _ => None
+        }
+    }
+}
+
+impl From<OomError> for SamplerYcbcrConversionCreationError {
+    #[inline]
+    fn from(err: OomError) -> SamplerYcbcrConversionCreationError {
+        SamplerYcbcrConversionCreationError::OomError(err)
+    }
+}
+
+impl From<Error> for SamplerYcbcrConversionCreationError {
+    #[inline]
+    fn from(err: Error) -> SamplerYcbcrConversionCreationError {
+        match err {
+            err @ Error::OutOfHostMemory => {
+                SamplerYcbcrConversionCreationError::OomError(OomError::from(err))
+            },
+            err @ Error::OutOfDeviceMemory => {
+                SamplerYcbcrConversionCreationError::OomError(OomError::from(err))
+            },
+            _ => panic!("unexpected error: {:?}", err)
+        }
+    }
+}
+
 /// Error that can happen when creating an instance.
 #[derive(Clone, Debug, PartialEq)]
 pub enum SamplerCreationError {
@@ -579,6 +1298,10 @@ pub enum SamplerCreationError {
     /// creating the device.
     SamplerAnisotropyFeatureNotEnabled,
 
+    /// Using `MirrorClampToEdge` requires enabling the `khr_sampler_mirror_clamp_to_edge`
+    /// extension when creating the device.
+    SamplerMirrorClampToEdgeExtensionNotEnabled,
+
     /// The requested anisotropy level exceeds the device's limits.
     AnisotropyLimitExceeded {
         /// The value that was requested.
@@ -604,6 +1327,9 @@ impl error::Error for SamplerCreationError {
             SamplerCreationError::TooManyObjects => "too many simultaneous sampler objects",
             SamplerCreationError::SamplerAnisotropyFeatureNotEnabled => "the `sampler_anisotropy` \
                                                                          feature is not enabled",
+            SamplerCreationError::SamplerMirrorClampToEdgeExtensionNotEnabled => {
+                "the `khr_sampler_mirror_clamp_to_edge` extension is not enabled"
+            },
             SamplerCreationError::AnisotropyLimitExceeded { .. } => "anisotropy limit exceeded",
             SamplerCreationError::MipLodBiasLimitExceeded { .. } => "mip lod bias limit exceeded",
         }
@@ -625,6 +1351,17 @@ impl fmt::Display for SamplerCreationError {
     }
 }
 
+impl VulkanError for SamplerCreationError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            SamplerCreationError::OomError(ref err) => err.error_code(),
+            SamplerCreationError::TooManyObjects => Some(Error::TooManyObjects),
+            _ => None
+        }
+    }
+}
+
 impl From<OomError> for SamplerCreationError {
     #[inline]
     fn from(err: OomError) -> SamplerCreationError {
@@ -646,6 +1383,8 @@ impl From<Error> for SamplerCreationError {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+    use format::Format;
     use sampler;
 
     #[test]
@@ -677,6 +1416,14 @@ mod tests {
         assert!(!s.is_unnormalized());
     }
 
+    #[test]
+    fn simple_compare_linear() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let s = sampler::Sampler::simple_compare_linear(&device, sampler::Compare::LessOrEqual);
+        assert!(s.compare_mode());
+        assert!(!s.usable_with_int_formats());
+    }
+
     #[test]
     fn create_unnormalized() {
         let (device, queue) = gfx_dev_and_queue!();
@@ -688,6 +1435,60 @@ mod tests {
 
         assert!(!s.compare_mode());
         assert!(s.is_unnormalized());
+        assert_eq!(s.border_color(), None);
+    }
+
+    #[test]
+    fn create_unnormalized_border() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let b = sampler::BorderColor::FloatTransparentBlack;
+        let s = sampler::Sampler::unnormalized(&device, sampler::Filter::Nearest,
+                                               sampler::UnnormalizedSamplerAddressMode::ClampToBorder(b),
+                                               sampler::UnnormalizedSamplerAddressMode::ClampToEdge)
+                                               .unwrap();
+
+        assert!(s.is_unnormalized());
+        assert_eq!(s.border_color(), Some(b));
+        assert!(s.usable_with_float_formats());
+        assert!(!s.usable_with_int_formats());
+    }
+
+    #[test]
+    fn ycbcr_conversion_extension() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let err = sampler::SamplerYcbcrConversion::new(&device,
+                                                       Format::G8_B8R8_2Plane420Unorm,
+                                                       sampler::YcbcrModelConversion::Ycbcr709,
+                                                       sampler::YcbcrRange::ItuNarrow,
+                                                       [sampler::ChromaLocation::Midpoint; 2],
+                                                       sampler::Filter::Linear).unwrap_err();
+        assert_eq!(err, sampler::SamplerYcbcrConversionCreationError::ExtensionNotEnabled);
+    }
+
+    #[test]
+    fn sampler_cache() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let cache = device.sampler_cache();
+        let repeat = sampler::SamplerAddressMode::Repeat;
+        let a = cache.sampler(&device, sampler::Filter::Linear, sampler::Filter::Linear,
+                              sampler::MipmapMode::Nearest, repeat, repeat, repeat, 0.0, 1.0,
+                              0.0, 2.0).unwrap();
+        let b = cache.sampler(&device, sampler::Filter::Linear, sampler::Filter::Linear,
+                              sampler::MipmapMode::Nearest, repeat, repeat, repeat, 0.0, 1.0,
+                              0.0, 2.0).unwrap();
+        let c = cache.sampler(&device, sampler::Filter::Nearest, sampler::Filter::Linear,
+                              sampler::MipmapMode::Nearest, repeat, repeat, repeat, 0.0, 1.0,
+                              0.0, 2.0).unwrap();
+
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &c));
+        assert_eq!(cache.len(), 2);
+
+        drop(c);
+        assert_eq!(cache.len(), 1);
     }
 
     #[test]
@@ -741,6 +1542,31 @@ mod tests {
                                       sampler::SamplerAddressMode::Repeat, 1.0, 1.0, 5.0, 2.0);
     }
 
+    #[test]
+    fn border_color_types() {
+        assert!(sampler::BorderColor::FloatOpaqueWhite.is_float());
+        assert!(!sampler::BorderColor::IntTransparentBlack.is_float());
+        assert!(sampler::BorderColor::IntOpaqueBlack.is_opaque_black());
+        assert!(!sampler::BorderColor::FloatTransparentBlack.is_opaque_black());
+    }
+
+    #[test]
+    fn int_border_color() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let b = sampler::BorderColor::IntOpaqueWhite;
+        let s = sampler::Sampler::new(&device, sampler::Filter::Nearest, sampler::Filter::Nearest,
+                                      sampler::MipmapMode::Nearest,
+                                      sampler::SamplerAddressMode::ClampToBorder(b),
+                                      sampler::SamplerAddressMode::ClampToBorder(b),
+                                      sampler::SamplerAddressMode::ClampToBorder(b), 0.0, 1.0,
+                                      0.0, 0.0).unwrap();
+
+        assert_eq!(s.border_color(), Some(b));
+        assert!(s.usable_with_int_formats());
+        assert!(!s.usable_with_float_formats());
+    }
+
     #[test]
     fn anisotropy_feature() {
         let (device, queue) = gfx_dev_and_queue!();
@@ -757,6 +1583,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn mirror_clamp_to_edge_extension() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let r = sampler::Sampler::new(&device, sampler::Filter::Linear, sampler::Filter::Linear,
+                                      sampler::MipmapMode::Nearest,
+                                      sampler::SamplerAddressMode::MirrorClampToEdge,
+                                      sampler::SamplerAddressMode::Repeat,
+                                      sampler::SamplerAddressMode::Repeat, 1.0, 1.0, 0.0, 2.0);
+
+        match r {
+            Err(sampler::SamplerCreationError::SamplerMirrorClampToEdgeExtensionNotEnabled) => (),
+            _ => panic!()
+        }
+    }
+
     #[test]
     fn anisotropy_limit() {
         let (device, queue) = gfx_dev_and_queue!(sampler_anisotropy);
@@ -0,0 +1,340 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use buffer::CpuAccessibleBuffer;
+use buffer::sys::Usage;
+use command_buffer::PrimaryCommandBufferBuilder;
+use command_buffer::Submission;
+use command_buffer::submit;
+use device::Queue;
+use device::QueueError;
+use format::Format;
+use image::traits::ImageContent;
+use memory::DeviceMemoryAllocError;
+use swapchain::OffscreenSwapchain;
+use sync::FenceWaitError;
+
+use Error;
+use OomError;
+use VulkanError;
+
+/// Copy of the content of a swapchain image to a host-visible buffer, for example to take a
+/// screenshot or to compare the output of an application with a reference image in a test.
+///
+/// The image must have been created with the `transfer_source` usage, which must be requested
+/// when creating the swapchain. The layout of the image is transitioned for the copy and back
+/// automatically.
+///
+/// Once an image of a `Swapchain` has been presented it belongs to the presentation engine, and
+/// its content can't be read until it is acquired again, at which point it is undefined.
+/// Therefore the capture must be submitted after the commands that draw on the image and before
+/// the image is presented. The images of an `OffscreenSwapchain` stay readable after they have
+/// been presented, and `capture_last_presented` captures the image that was presented last.
+///
+/// # Example
+///
+/// ```ignore
+/// let (index, _) = swapchain.acquire_next_image(Duration::from_secs(1)).unwrap();
+/// // Draw on `images[index]`.
+/// let capture = ScreenCapture::new(&images[index], &queue).unwrap();
+/// swapchain.present(&queue, index).unwrap();
+///
+/// let pixels = capture.rgba8(Duration::from_secs(1)).unwrap();
+/// assert_eq!(pixels.len(), (capture.dimensions()[0] * capture.dimensions()[1] * 4) as usize);
+/// ```
+pub struct ScreenCapture {
+    // Host-visible buffer that receives the pixels, in the format of the image.
+    buffer: Arc<CpuAccessibleBuffer<[u8]>>,
+    // Submission of the copy.
+    submission: Arc<Submission>,
+    format: Format,
+    dimensions: [u32; 2],
+}
+
+impl ScreenCapture {
+    /// Submits to `queue` a command buffer that copies the first layer of `image` to a new
+    /// host-visible buffer.
+    ///
+    /// The copy is automatically ordered after the commands that were previously submitted and
+    /// that draw on `image`.
+    ///
+    /// Returns an error if the image wasn't created with the `transfer_source` usage or if its
+    /// format can't be converted to RGBA8. See `rgba8` for the list of supported formats.
+    pub fn new<I>(image: &Arc<I>, queue: &Arc<Queue>) -> Result<ScreenCapture, CaptureError>
+        where I: ImageContent<u8> + 'static
+    {
+        if !image.inner().usage_transfer_src() {
+            return Err(CaptureError::MissingTransferSourceUsage);
+        }
+
+        let format = image.format();
+        if !is_supported_format(format) {
+            return Err(CaptureError::UnsupportedFormat);
+        }
+
+        let dimensions = image.dimensions().width_height();
+        let size = dimensions[0] as usize * dimensions[1] as usize *
+                   format.size().expect("Supported formats have a size");
+
+        let usage = Usage {
+            transfer_dest: true,
+            .. Usage::none()
+        };
+
+        let buffer: Arc<CpuAccessibleBuffer<[u8]>> = unsafe {
+            try!(CpuAccessibleBuffer::raw(queue.device(), size, &usage, Some(queue.family())))
+        };
+
+        let cb = PrimaryCommandBufferBuilder::new(queue.device(), queue.family())
+                    .copy_color_image_to_buffer(&buffer, image, 0, 0 .. 1, [0, 0, 0],
+                                                [dimensions[0], dimensions[1], 1])
+                    .build();
+        let submission = try!(submit(&cb, queue));
+
+        Ok(ScreenCapture {
+            buffer: buffer,
+            submission: submission,
+            format: format,
+            dimensions: dimensions,
+        })
+    }
+
+    /// Returns the format of the captured image.
+    #[inline]
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Returns the width and height of the captured image.
+    #[inline]
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.dimensions
+    }
+
+    /// Returns true if the copy is finished, in which case reading the pixels doesn't block.
+    #[inline]
+    pub fn ready(&self) -> bool {
+        self.submission.finished()
+    }
+
+    /// Waits until the copy is finished or until `timeout` has elapsed.
+    #[inline]
+    pub fn wait(&self, timeout: Duration) -> Result<(), FenceWaitError> {
+        self.submission.wait(timeout)
+    }
+
+    /// Returns the pixels of the image as 8-bit RGBA values, row after row, blocking until the
+    /// copy is finished or until `timeout` has elapsed.
+    ///
+    /// The supported formats are the 8-bit RGBA, BGRA and ABGR formats, whose values are
+    /// returned without conversion, and the 10-bit `A2R10G10B10` and `A2B10G10R10` formats,
+    /// whose values are reduced to 8 bits. sRGB values are not converted to linear values.
+    pub fn rgba8(&self, timeout: Duration) -> Result<Vec<u8>, FenceWaitError> {
+        let raw = try!(self.buffer.read(timeout));
+        Ok(to_rgba8(self.format, &raw))
+    }
+
+    /// Returns the host-visible buffer that receives the pixels, in the format of the image.
+    #[inline]
+    pub fn buffer(&self) -> &Arc<CpuAccessibleBuffer<[u8]>> {
+        &self.buffer
+    }
+}
+
+impl<F> OffscreenSwapchain<F> where F: 'static + Send + Sync {
+    /// Captures the image that was presented last. Returns `None` if no image has been presented
+    /// yet.
+    ///
+    /// The capture is ordered after the submissions that were passed when presenting the image.
+    /// See `ScreenCapture` for more info.
+    pub fn capture_last_presented(&self, queue: &Arc<Queue>)
+                                  -> Result<Option<ScreenCapture>, CaptureError>
+    {
+        match self.last_presented() {
+            Some(index) => ScreenCapture::new(self.image(index), queue).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+// Returns true if `to_rgba8` can convert pixels of this format.
+fn is_supported_format(format: Format) -> bool {
+    match format {
+        Format::R8G8B8A8Unorm | Format::R8G8B8A8Srgb | Format::R8G8B8A8Uint |
+        Format::B8G8R8A8Unorm | Format::B8G8R8A8Srgb | Format::B8G8R8A8Uint |
+        Format::A8B8G8R8UnormPack32 | Format::A8B8G8R8SrgbPack32 |
+        Format::A8B8G8R8UintPack32 | Format::A2R10G10B10UnormPack32 |
+        Format::A2B10G10R10UnormPack32 => true,
+        _ => false,
+    }
+}
+
+// Converts tightly-packed pixels of the given format to 8-bit RGBA.
+fn to_rgba8(format: Format, raw: &[u8]) -> Vec<u8> {
+    debug_assert!(is_supported_format(format));
+
+    match format {
+        Format::R8G8B8A8Unorm | Format::R8G8B8A8Srgb | Format::R8G8B8A8Uint |
+        Format::A8B8G8R8UnormPack32 | Format::A8B8G8R8SrgbPack32 |
+        Format::A8B8G8R8UintPack32 => {
+            // The packed formats are stored in little endian, which puts red in the first byte.
+            raw.to_vec()
+        },
+        Format::B8G8R8A8Unorm | Format::B8G8R8A8Srgb | Format::B8G8R8A8Uint => {
+            raw.chunks(4).flat_map(|p| vec![p[2], p[1], p[0], p[3]]).collect()
+        },
+        Format::A2R10G10B10UnormPack32 | Format::A2B10G10R10UnormPack32 => {
+            raw.chunks(4).flat_map(|p| {
+                let v = p[0] as u32 | (p[1] as u32) << 8 | (p[2] as u32) << 16 |
+                        (p[3] as u32) << 24;
+                let low = (v & 0x3ff) >> 2;
+                let mid = ((v >> 10) & 0x3ff) >> 2;
+                let high = ((v >> 20) & 0x3ff) >> 2;
+                let alpha = (v >> 30) * 85;
+
+                if format == Format::A2R10G10B10UnormPack32 {
+                    vec![high as u8, mid as u8, low as u8, alpha as u8]
+                } else {
+                    vec![low as u8, mid as u8, high as u8, alpha as u8]
+                }
+            }).collect()
+        },
+        _ => unreachable!()
+    }
+}
+
+/// Error that can happen when capturing an image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CaptureError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The maximum number of memory allocations of the device has been reached.
+    TooManyObjects,
+    /// The connection to the device has been lost.
+    DeviceLost,
+    /// The image wasn't created with the `transfer_source` usage.
+    MissingTransferSourceUsage,
+    /// The format of the image can't be converted to RGBA8.
+    UnsupportedFormat,
+}
+
+impl error::Error for CaptureError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CaptureError::OomError(_) => "not enough memory",
+            CaptureError::TooManyObjects => {
+                "the maximum number of memory allocations has been reached"
+            },
+            CaptureError::DeviceLost => "the connection to the device has been lost",
+            CaptureError::MissingTransferSourceUsage => {
+                "the image wasn't created with the `transfer_source` usage"
+            },
+            CaptureError::UnsupportedFormat => {
+                "the format of the image can't be converted to RGBA8"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            CaptureError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for CaptureError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for CaptureError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            CaptureError::OomError(ref err) => err.error_code(),
+            CaptureError::TooManyObjects => Some(Error::TooManyObjects),
+            CaptureError::DeviceLost => Some(Error::DeviceLost),
+            _ => None
+        }
+    }
+}
+
+impl From<OomError> for CaptureError {
+    #[inline]
+    fn from(err: OomError) -> CaptureError {
+        CaptureError::OomError(err)
+    }
+}
+
+impl From<QueueError> for CaptureError {
+    #[inline]
+    fn from(err: QueueError) -> CaptureError {
+        match err {
+            QueueError::OomError(err) => CaptureError::OomError(err),
+            QueueError::TooManyObjects => CaptureError::TooManyObjects,
+            QueueError::DeviceLost(_) => CaptureError::DeviceLost,
+        }
+    }
+}
+
+impl From<DeviceMemoryAllocError> for CaptureError {
+    #[inline]
+    fn from(err: DeviceMemoryAllocError) -> CaptureError {
+        match err {
+            DeviceMemoryAllocError::OomError(err) => CaptureError::OomError(err),
+            DeviceMemoryAllocError::TooManyObjects => CaptureError::TooManyObjects,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use format::Format;
+    use swapchain::OffscreenSwapchain;
+    use super::to_rgba8;
+
+    #[test]
+    fn convert_bgra_and_10_bits() {
+        assert_eq!(to_rgba8(Format::B8G8R8A8Unorm, &[1, 2, 3, 4, 5, 6, 7, 8]),
+                   vec![3, 2, 1, 4, 7, 6, 5, 8]);
+        assert_eq!(to_rgba8(Format::R8G8B8A8Srgb, &[1, 2, 3, 4]), vec![1, 2, 3, 4]);
+
+        // Red = 0x3ff, green = 0x200, blue = 0, alpha = 3.
+        let v: u32 = 0x3ff << 20 | 0x200 << 10 | 3 << 30;
+        let raw = [v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8];
+        assert_eq!(to_rgba8(Format::A2R10G10B10UnormPack32, &raw), vec![255, 128, 0, 255]);
+        assert_eq!(to_rgba8(Format::A2B10G10R10UnormPack32, &raw), vec![0, 128, 255, 255]);
+    }
+
+    #[test]
+    fn capture_offscreen() {
+        let (device, queue) = gfx_dev_and_queue!();
+        let (swapchain, _) = OffscreenSwapchain::new(&device, [32, 32], Format::R8G8B8A8Unorm,
+                                                     1).unwrap();
+        assert!(swapchain.capture_last_presented(&queue).unwrap().is_none());
+
+        let index = swapchain.acquire_next_image(Duration::from_secs(1)).unwrap();
+        swapchain.present(index, Vec::new());
+
+        let capture = swapchain.capture_last_presented(&queue).unwrap().unwrap();
+        assert_eq!(capture.dimensions(), [32, 32]);
+        assert_eq!(capture.rgba8(Duration::from_secs(5)).unwrap().len(), 32 * 32 * 4);
+    }
+}
@@ -0,0 +1,362 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::mem;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+use command_buffer::Submission;
+use command_buffer::pool::StandardCommandPool;
+use device::Queue;
+use image::swapchain::SwapchainImage;
+use swapchain::AcquireError;
+use swapchain::PresentError;
+use swapchain::Swapchain;
+use swapchain::SwapchainCreationError;
+use sync::FenceWaitError;
+
+use Error;
+use VulkanError;
+
+/// Limits the number of frames that the CPU can prepare in advance of the GPU.
+///
+/// A `FrameSystem` owns a number of *slots*, one per frame that can be in flight at the same
+/// time. Each slot has its own command pool and keeps the submissions of the frame that was
+/// last rendered with it. `begin_frame()` waits for the submissions of the oldest slot to be
+/// finished by the GPU before acquiring the next image of the swapchain, so that the resources
+/// of this slot can be safely reused. `end_frame()` presents the image and moves on to the next
+/// slot.
+///
+/// The semaphores between the acquisition of an image, the command buffers that draw on it and
+/// the presentation are handled automatically by the swapchain and the submissions.
+///
+/// If a `Frame` is destroyed without being passed to `end_frame`, for example because an error
+/// happened while building its command buffers, its image is presented as it is in order to
+/// give it back to the swapchain, and the next frame can be started.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::sync::Arc;
+/// # use std::time::Duration;
+/// use vulkano::swapchain::FrameSystem;
+///
+/// # let swapchain: Arc<vulkano::swapchain::Swapchain> = unsafe { std::mem::uninitialized() };
+/// # let queue: Arc<vulkano::device::Queue> = unsafe { std::mem::uninitialized() };
+/// let mut frames = FrameSystem::new(&swapchain, &queue, 2);
+///
+/// loop {
+///     let mut frame = frames.begin_frame(Duration::from_secs(1)).unwrap();
+///
+///     // Build command buffers from `frame.command_pool()` that draw on the image whose index is
+///     // `frame.image_num()`, submit them, and pass the submissions to `frame.add_submission()`.
+///
+///     let suboptimal = frames.end_frame(frame).unwrap();
+///     if suboptimal {
+///         frames.recreate_swapchain().unwrap();
+///     }
+/// }
+/// ```
+pub struct FrameSystem {
+    swapchain: Arc<Swapchain>,
+    queue: Arc<Queue>,
+    slots: Vec<Arc<Slot>>,
+    // Index within `slots` of the slot of the next frame.
+    current: usize,
+    // True if a frame has been started and not ended or destroyed yet. Shared with the frame.
+    in_frame: Arc<AtomicBool>,
+}
+
+struct Slot {
+    pool: Arc<StandardCommandPool>,
+    submissions: Mutex<Vec<Arc<Submission>>>,
+}
+
+impl FrameSystem {
+    /// Builds a new `FrameSystem` that allows `frames_in_flight` frames to be prepared or
+    /// executed at the same time.
+    ///
+    /// Two frames in flight is usually a good choice, as the CPU can prepare a frame while the GPU
+    /// renders the previous one.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `frames_in_flight` is 0.
+    /// - Panics if the swapchain and the queue don't belong to the same device.
+    ///
+    pub fn new(swapchain: &Arc<Swapchain>, queue: &Arc<Queue>, frames_in_flight: usize)
+               -> FrameSystem
+    {
+        assert!(frames_in_flight >= 1);
+        assert_eq!(&**swapchain.device() as *const _, &**queue.device() as *const _);
+
+        let slots = (0 .. frames_in_flight).map(|_| {
+            Arc::new(Slot {
+                pool: Arc::new(StandardCommandPool::new(queue.device(), queue.family())),
+                submissions: Mutex::new(Vec::new()),
+            })
+        }).collect();
+
+        FrameSystem {
+            swapchain: swapchain.clone(),
+            queue: queue.clone(),
+            slots: slots,
+            current: 0,
+            in_frame: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Returns the swapchain that frames are presented to.
+    #[inline]
+    pub fn swapchain(&self) -> &Arc<Swapchain> {
+        &self.swapchain
+    }
+
+    /// Returns the queue that frames are presented on.
+    #[inline]
+    pub fn queue(&self) -> &Arc<Queue> {
+        &self.queue
+    }
+
+    /// Returns the maximum number of frames in flight.
+    #[inline]
+    pub fn frames_in_flight(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Waits until the resources of the next frame are no longer in use by the GPU, then acquires
+    /// an image from the swapchain.
+    ///
+    /// `timeout` is used both for waiting for the previous submissions and for acquiring the
+    /// image.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the previous frame is still alive and hasn't been ended with `end_frame`.
+    ///
+    pub fn begin_frame(&mut self, timeout: Duration) -> Result<Frame, BeginFrameError> {
+        assert!(!self.in_frame.load(Ordering::SeqCst), "The previous frame hasn't been ended");
+
+        let slot = self.slots[self.current].clone();
+
+        {
+            let mut submissions = slot.submissions.lock().unwrap();
+            for submission in submissions.iter() {
+                try!(submission.wait(timeout));
+            }
+            submissions.clear();
+        }
+
+        let (image_num, suboptimal) = try!(self.swapchain.acquire_next_image(timeout));
+        self.in_frame.store(true, Ordering::SeqCst);
+        self.current = (self.current + 1) % self.slots.len();
+
+        Ok(Frame {
+            slot: slot,
+            image_num: image_num,
+            suboptimal: suboptimal,
+            swapchain: self.swapchain.clone(),
+            queue: self.queue.clone(),
+            in_frame: self.in_frame.clone(),
+            submissions: Vec::new(),
+            ended: false,
+        })
+    }
+
+    /// Presents the image of the frame and moves on to the next frame.
+    ///
+    /// Returns true if the swapchain is suboptimal, either when the image was acquired or when it
+    /// was presented. In this situation you should call `recreate_swapchain`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `frame` wasn't returned by `begin_frame` of this system.
+    ///
+    pub fn end_frame(&mut self, mut frame: Frame) -> Result<bool, PresentError> {
+        assert!(Arc::ptr_eq(&frame.in_frame, &self.in_frame),
+                "The frame doesn't belong to this frame system");
+
+        let present_suboptimal = try!(frame.present());
+        Ok(frame.suboptimal || present_suboptimal)
+    }
+
+    /// Recreates the swapchain with `Swapchain::recreate` and uses the new swapchain for the next
+    /// frames. Returns the images of the new swapchain.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if a frame has been started and not ended.
+    ///
+    pub fn recreate_swapchain(&mut self)
+                              -> Result<Vec<Arc<SwapchainImage>>, SwapchainCreationError>
+    {
+        assert!(!self.in_frame.load(Ordering::SeqCst),
+                "Can't recreate the swapchain in the middle of a frame");
+        let (swapchain, images) = try!(self.swapchain.recreate());
+        self.swapchain = swapchain;
+        Ok(images)
+    }
+
+    /// Replaces the swapchain that is used for the next frames, for example after recreating it
+    /// with `recreate_with_dimension`. Returns the old swapchain.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if a frame has been started and not ended.
+    /// - Panics if the swapchain doesn't belong to the same device as the queue.
+    ///
+    pub fn set_swapchain(&mut self, swapchain: &Arc<Swapchain>) -> Arc<Swapchain> {
+        assert!(!self.in_frame.load(Ordering::SeqCst),
+                "Can't replace the swapchain in the middle of a frame");
+        assert_eq!(&**swapchain.device() as *const _, &**self.queue.device() as *const _);
+        mem::replace(&mut self.swapchain, swapchain.clone())
+    }
+}
+
+/// A frame that has been started with `FrameSystem::begin_frame`.
+///
+/// Must be passed back to `FrameSystem::end_frame` once all of its command buffers have been
+/// submitted. If it is destroyed instead, its image is presented as it is.
+pub struct Frame {
+    slot: Arc<Slot>,
+    image_num: usize,
+    suboptimal: bool,
+    swapchain: Arc<Swapchain>,
+    queue: Arc<Queue>,
+    in_frame: Arc<AtomicBool>,
+    submissions: Vec<Arc<Submission>>,
+    // True once the image has been presented.
+    ended: bool,
+}
+
+impl Frame {
+    /// Returns the index of the image of the swapchain to draw on.
+    #[inline]
+    pub fn image_num(&self) -> usize {
+        self.image_num
+    }
+
+    /// Returns true if the swapchain was already suboptimal when the image was acquired.
+    #[inline]
+    pub fn suboptimal(&self) -> bool {
+        self.suboptimal
+    }
+
+    /// Returns the command pool of this frame.
+    ///
+    /// Command buffers that are created from this pool are recycled once the frame has been
+    /// executed by the GPU.
+    #[inline]
+    pub fn command_pool(&self) -> &Arc<StandardCommandPool> {
+        &self.slot.pool
+    }
+
+    /// Adds a submission that belongs to this frame. The next frame that uses the same slot waits
+    /// for it to be finished.
+    #[inline]
+    pub fn add_submission(&mut self, submission: Arc<Submission>) {
+        self.submissions.push(submission);
+    }
+
+    // Hands the submissions over to the slot, lets the system start the next frame and presents
+    // the image.
+    fn present(&mut self) -> Result<bool, PresentError> {
+        debug_assert!(!self.ended);
+        self.ended = true;
+
+        *self.slot.submissions.lock().unwrap() = mem::replace(&mut self.submissions, Vec::new());
+        self.in_frame.store(false, Ordering::SeqCst);
+
+        self.swapchain.present(&self.queue, self.image_num)
+    }
+}
+
+impl Drop for Frame {
+    #[inline]
+    fn drop(&mut self) {
+        if !self.ended {
+            if thread::panicking() {
+                let submissions = mem::replace(&mut self.submissions, Vec::new());
+                if let Ok(mut slot_submissions) = self.slot.submissions.lock() {
+                    *slot_submissions = submissions;
+                }
+                self.in_frame.store(false, Ordering::SeqCst);
+            } else {
+                // The image must be given back to the swapchain, otherwise it would be lost.
+                let _ = self.present();
+            }
+        }
+    }
+}
+
+/// Error that can happen when calling `FrameSystem::begin_frame`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BeginFrameError {
+    /// Error while waiting for the submissions of a previous frame.
+    FenceWaitError(FenceWaitError),
+    /// Error while acquiring an image of the swapchain.
+    AcquireError(AcquireError),
+}
+
+impl error::Error for BeginFrameError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            BeginFrameError::FenceWaitError(_) => {
+                "error while waiting for the submissions of a previous frame"
+            },
+            BeginFrameError::AcquireError(_) => "error while acquiring an image of the swapchain",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            BeginFrameError::FenceWaitError(ref err) => Some(err),
+            BeginFrameError::AcquireError(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for BeginFrameError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for BeginFrameError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            BeginFrameError::FenceWaitError(ref err) => err.error_code(),
+            BeginFrameError::AcquireError(ref err) => err.error_code(),
+        }
+    }
+}
+
+impl From<FenceWaitError> for BeginFrameError {
+    #[inline]
+    fn from(err: FenceWaitError) -> BeginFrameError {
+        BeginFrameError::FenceWaitError(err)
+    }
+}
+
+impl From<AcquireError> for BeginFrameError {
+    #[inline]
+    fn from(err: AcquireError) -> BeginFrameError {
+        BeginFrameError::AcquireError(err)
+    }
+}
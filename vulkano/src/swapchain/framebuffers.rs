@@ -0,0 +1,152 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use framebuffer::Framebuffer;
+use framebuffer::FramebufferCreationError;
+use image::swapchain::SwapchainImage;
+
+/// Creates and caches one framebuffer per image of a swapchain.
+///
+/// The framebuffer of an image is built the first time it is requested with `framebuffer()`,
+/// by calling the function that was passed to `new`. After the swapchain has been recreated,
+/// passing the new images to `set_images` discards the old framebuffers, and the new ones are
+/// built when they are requested.
+///
+/// `I` is the type of the images, which is `SwapchainImage` for a real swapchain and can be an
+/// `AttachmentImage` for an `OffscreenSwapchain`.
+///
+/// # Example
+///
+/// ```ignore
+/// let framebuffers = SwapchainFramebuffers::new(&render_pass, images, |render_pass, image| {
+///     let dimensions = image.dimensions();
+///     Framebuffer::new(render_pass, [dimensions[0], dimensions[1], 1],
+///                      render_pass::AList { color: image, depth: &depth_buffer })
+/// });
+///
+/// let mut frame = frames.begin_frame(Duration::from_secs(1)).unwrap();
+/// let framebuffer = framebuffers.framebuffer(frame.image_num()).unwrap();
+/// // Draw on `framebuffer`.
+///
+/// if frames.end_frame(frame).unwrap() {
+///     framebuffers.set_images(frames.recreate_swapchain().unwrap());
+/// }
+/// ```
+pub struct SwapchainFramebuffers<L, I = SwapchainImage> {
+    render_pass: Arc<L>,
+    build: Box<Fn(&Arc<L>, &Arc<I>) -> Result<Arc<Framebuffer<L>>, FramebufferCreationError> +
+               Send + Sync>,
+    // The images of the swapchain, and their framebuffer if it has been built.
+    images: Mutex<Vec<(Arc<I>, Option<Arc<Framebuffer<L>>>)>>,
+}
+
+impl<L, I> SwapchainFramebuffers<L, I> {
+    /// Builds a new `SwapchainFramebuffers` for the given images. No framebuffer is built yet.
+    ///
+    /// `build` is called with the render pass and an image every time the framebuffer of an
+    /// image is needed and hasn't been built yet.
+    pub fn new<F>(render_pass: &Arc<L>, images: Vec<Arc<I>>, build: F)
+                  -> SwapchainFramebuffers<L, I>
+        where F: Fn(&Arc<L>, &Arc<I>) -> Result<Arc<Framebuffer<L>>, FramebufferCreationError> +
+                 Send + Sync + 'static
+    {
+        SwapchainFramebuffers {
+            render_pass: render_pass.clone(),
+            build: Box::new(build),
+            images: Mutex::new(images.into_iter().map(|i| (i, None)).collect()),
+        }
+    }
+
+    /// Returns the render pass of the framebuffers.
+    #[inline]
+    pub fn render_pass(&self) -> &Arc<L> {
+        &self.render_pass
+    }
+
+    /// Returns the number of images.
+    #[inline]
+    pub fn num_images(&self) -> usize {
+        self.images.lock().unwrap().len()
+    }
+
+    /// Returns the framebuffer of the image whose index is `image_num`, for example the index
+    /// returned when acquiring an image of the swapchain. Builds it if necessary.
+    ///
+    /// If the build fails, the error is returned and the build is attempted again the next time.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `image_num` is out of range.
+    ///
+    pub fn framebuffer(&self, image_num: usize)
+                       -> Result<Arc<Framebuffer<L>>, FramebufferCreationError>
+    {
+        let mut images = self.images.lock().unwrap();
+        let &mut (ref image, ref mut framebuffer) = &mut images[image_num];
+
+        if let Some(ref framebuffer) = *framebuffer {
+            return Ok(framebuffer.clone());
+        }
+
+        let new = try!((self.build)(&self.render_pass, image));
+        *framebuffer = Some(new.clone());
+        Ok(new)
+    }
+
+    /// Replaces the images, for example after the swapchain has been recreated. The framebuffers
+    /// of the previous images are discarded.
+    ///
+    /// Command buffers that use the previous framebuffers keep them alive.
+    pub fn set_images(&self, images: Vec<Arc<I>>) {
+        *self.images.lock().unwrap() = images.into_iter().map(|i| (i, None)).collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use format::Format;
+    use framebuffer::EmptySinglePassRenderPass;
+    use framebuffer::Framebuffer;
+    use swapchain::OffscreenSwapchain;
+    use swapchain::SwapchainFramebuffers;
+
+    #[test]
+    fn lazy_creation() {
+        let (device, _) = gfx_dev_and_queue!();
+        let render_pass = EmptySinglePassRenderPass::new(&device);
+        let (_, images) = OffscreenSwapchain::new(&device, [32, 32], Format::R8G8B8A8Unorm,
+                                                  2).unwrap();
+
+        let builds = Arc::new(AtomicUsize::new(0));
+        let builds2 = builds.clone();
+        let framebuffers = SwapchainFramebuffers::new(&render_pass, images.clone(),
+                                                      move |render_pass, _| {
+            builds2.fetch_add(1, Ordering::SeqCst);
+            Framebuffer::new(render_pass, [32, 32, 1], ())
+        });
+
+        assert_eq!(framebuffers.num_images(), 2);
+        assert_eq!(builds.load(Ordering::SeqCst), 0);
+
+        let first = framebuffers.framebuffer(1).unwrap();
+        let second = framebuffers.framebuffer(1).unwrap();
+        assert!(&*first as *const _ == &*second as *const _);
+        assert_eq!(builds.load(Ordering::SeqCst), 1);
+
+        framebuffers.set_images(images);
+        framebuffers.framebuffer(1).unwrap();
+        assert_eq!(builds.load(Ordering::SeqCst), 2);
+    }
+}
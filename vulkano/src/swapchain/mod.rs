@@ -105,6 +105,9 @@
 //!  - How to perform the cycling between images in regard to vsync.
 //!
 //! You can query the supported values of all these properties with `Surface::get_capabilities()`.
+//! The values that can change during the lifetime of the surface, such as its current dimensions,
+//! can be queried again cheaply with `Surface::capabilities()`, for example after the window has
+//! been resized.
 //!
 //! ## Creating a swapchain
 //!
@@ -125,16 +128,30 @@
 //! section), you can draw on it. This is done in three steps:
 //!
 //!  - Call `Swapchain::acquire_next_image`. This function will return the index of the image
-//!    (within the list returned by `Swapchain::new`) that is available to draw.
+//!    (within the list returned by `Swapchain::new`) that is available to draw, and whether the
+//!    swapchain is suboptimal (see below).
 //!  - Draw on that image just like you would draw to any other image (see the documentation of
 //!    the `pipeline` module).
 //!  - Call `Swapchain::present` with the same index in order to tell the implementation that you
 //!    are finished drawing to the image and that it can queue a command to present the image on
 //!    the screen after the draw operations are finished. 
 //!
+//! If you render to multiple windows, `Swapchain::present_multiple` presents one image of each of
+//! their swapchains at once. If only a small part of an image has changed since the previous
+//! frame, `Swapchain::present_with_regions` lets the implementation only update this part of the
+//! surface when the `khr_incremental_present` extension is enabled.
+//!
+//! Waiting for the GPU after each frame is slow, but letting the CPU prepare an unbounded number
+//! of frames in advance increases the latency and the memory usage. The `FrameSystem` helper
+//! limits the number of frames in flight and takes care of acquiring and presenting the images.
+//!
+//! These steps can also be chained with the futures of the `sync` module. `swapchain::acquire`
+//! returns a `SwapchainAcquireFuture`, after which you can call `then_execute` and
+//! `then_swapchain_present`, and vulkano inserts the semaphores between them.
+//!
 //! TODO: add example here
 //! loop {
-//!     let index = swapchain.acquire_next_image(Duration::from_millis(500)).unwrap();
+//!     let (index, _) = swapchain.acquire_next_image(Duration::from_millis(500)).unwrap();
 //!     draw(images[index]);
 //!     swapchain.present(queue, index).unwrap();
 //! }
@@ -147,10 +164,15 @@
 //!
 //! In this situation, acquiring a swapchain image or presenting it will return an error. Rendering
 //! to an image of that swapchain will not produce any error, but may or may not work. To continue
-//! rendering, you will need to *recreate* the swapchain by creating a new swapchain and passing
-//! as last parameter the old swapchain.
+//! rendering, you will need to *recreate* the swapchain by calling `Swapchain::recreate()`, which
+//! creates a new swapchain with the same parameters and the new dimensions of the surface. Use
+//! `recreate_with_dimension` instead if the surface doesn't report its dimensions. Recreating
+//! returns `SwapchainCreationError::UnsupportedDimensions` while the window is minimized.
 //!
-//! TODO: suboptimal stuff
+//! The swapchain can also become *suboptimal*, in which case acquiring and presenting images still
+//! works but the swapchain no longer matches the surface exactly. `acquire_next_image` and
+//! `present` return a boolean that indicates this situation. The frame can be finished normally,
+//! and the swapchain should be recreated before the next one.
 //!
 //! ```no_run
 //! # use std::time::Duration;
@@ -164,14 +186,14 @@
 //!
 //! loop {
 //!     if recreate_swapchain {
-//!         swapchain = swapchain.0.recreate_with_dimension([1024, 768]).unwrap();
+//!         swapchain = swapchain.0.recreate().unwrap();
 //!         recreate_swapchain = false;
 //!     }
 //!
 //!     let (ref swapchain, ref _images) = swapchain;
 //!
 //!     let index = match swapchain.acquire_next_image(Duration::from_millis(500)) {
-//!         Ok(img) => img,
+//!         Ok((img, suboptimal)) => { recreate_swapchain = suboptimal; img },
 //!         Err(AcquireError::OutOfDate) => { recreate_swapchain = true; continue; },
 //!         Err(err) => panic!("{:?}", err)
 //!     };
@@ -179,7 +201,7 @@
 //!     // ...
 //!
 //!     match swapchain.present(&queue, index) {
-//!         Ok(()) => (),
+//!         Ok(suboptimal) => { recreate_swapchain |= suboptimal; },
 //!         Err(PresentError::OutOfDate) => { recreate_swapchain = true; },
 //!         Err(err) => panic!("{:?}", err),
 //!     }
@@ -193,15 +215,37 @@ use vk;
 pub use self::surface::Capabilities;
 pub use self::surface::Surface;
 pub use self::surface::PresentMode;
+pub use self::surface::SurfaceCapabilities;
 pub use self::surface::SurfaceTransform;
 pub use self::surface::CompositeAlpha;
 pub use self::surface::ColorSpace;
 pub use self::surface::SurfaceCreationError;
+pub use self::surface::Window;
+pub use self::surface::WindowHandle;
+pub use self::capture::CaptureError;
+pub use self::capture::ScreenCapture;
+pub use self::frame_system::BeginFrameError;
+pub use self::frame_system::Frame;
+pub use self::frame_system::FrameSystem;
+pub use self::framebuffers::SwapchainFramebuffers;
+pub use self::offscreen::OffscreenSwapchain;
 pub use self::swapchain::Swapchain;
+pub use self::swapchain::SwapchainAcquireFuture;
+pub use self::swapchain::acquire;
 pub use self::swapchain::AcquireError;
+pub use self::swapchain::FullScreenExclusive;
+pub use self::swapchain::FullScreenExclusiveError;
 pub use self::swapchain::PresentError;
+pub use self::swapchain::PresentFuture;
+pub use self::swapchain::RectangleLayer;
+pub use self::swapchain::SwapchainCreationError;
+pub use self::swapchain::Win32Monitor;
 
 pub mod display;
+mod capture;
+mod frame_system;
+mod framebuffers;
+mod offscreen;
 mod surface;
 mod swapchain;
 
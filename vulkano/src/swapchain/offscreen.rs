@@ -0,0 +1,236 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use command_buffer::Submission;
+use device::Device;
+use format::FormatDesc;
+use image::attachment::AttachmentImage;
+use image::sys::ImageCreationError;
+use swapchain::AcquireError;
+use sync::FenceWaitError;
+
+/// Ring of images that mimics a `Swapchain` without a surface.
+///
+/// This can be used to run the same rendering code with or without a window, for example for
+/// rendering tests on a CI server or for generating images on a server.
+///
+/// Images are acquired with `acquire_next_image` and handed back with `present`, along with the
+/// submissions that draw on them. Acquiring an image waits until the submissions of the previous
+/// time it was presented are finished. Presenting doesn't show anything: the content of the
+/// image can be read back by copying it to a buffer in one of the submissions.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// use vulkano::format::Format;
+/// use vulkano::swapchain::OffscreenSwapchain;
+///
+/// # let device: std::sync::Arc<vulkano::device::Device> = unsafe { std::mem::uninitialized() };
+/// let (swapchain, images) = OffscreenSwapchain::new(&device, [1024, 768],
+///                                                   Format::R8G8B8A8Unorm, 2).unwrap();
+///
+/// let index = swapchain.acquire_next_image(Duration::from_secs(1)).unwrap();
+/// // Draw on `images[index]`, copy it to a buffer, and keep the submissions.
+/// swapchain.present(index, Vec::new());
+/// ```
+pub struct OffscreenSwapchain<F> {
+    device: Arc<Device>,
+    dimensions: [u32; 2],
+    images: Vec<Arc<AttachmentImage<F>>>,
+    state: Mutex<State>,
+}
+
+struct State {
+    // Index of the image that the next call to `acquire_next_image` returns.
+    next: usize,
+    // Index of the image that was presented last.
+    last_presented: Option<usize>,
+    images: Vec<ImageState>,
+}
+
+struct ImageState {
+    // True if the image has been acquired and not presented yet.
+    acquired: bool,
+    // Submissions that were passed when the image was last presented.
+    submissions: Vec<Arc<Submission>>,
+}
+
+impl<F> OffscreenSwapchain<F> where F: FormatDesc + Clone {
+    /// Builds a new `OffscreenSwapchain` with `num_images` images of the given dimensions and
+    /// format. Returns the swapchain and its images.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `num_images` is 0.
+    ///
+    pub fn new(device: &Arc<Device>, dimensions: [u32; 2], format: F, num_images: usize)
+               -> Result<(Arc<OffscreenSwapchain<F>>, Vec<Arc<AttachmentImage<F>>>),
+                         ImageCreationError>
+    {
+        assert!(num_images >= 1);
+
+        let mut images = Vec::with_capacity(num_images);
+        for _ in 0 .. num_images {
+            images.push(try!(AttachmentImage::new(device, dimensions, format.clone())));
+        }
+
+        let state = State {
+            next: 0,
+            last_presented: None,
+            images: (0 .. num_images).map(|_| {
+                ImageState {
+                    acquired: false,
+                    submissions: Vec::new(),
+                }
+            }).collect(),
+        };
+
+        let swapchain = Arc::new(OffscreenSwapchain {
+            device: device.clone(),
+            dimensions: dimensions,
+            images: images.clone(),
+            state: Mutex::new(state),
+        });
+
+        Ok((swapchain, images))
+    }
+}
+
+impl<F> OffscreenSwapchain<F> {
+    /// Returns the device this swapchain belongs to.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    /// Returns the dimensions of the images.
+    #[inline]
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.dimensions
+    }
+
+    /// Returns the number of images.
+    #[inline]
+    pub fn num_images(&self) -> usize {
+        self.images.len()
+    }
+
+    /// Returns one of the images.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `index` is out of range.
+    ///
+    #[inline]
+    pub fn image(&self, index: usize) -> &Arc<AttachmentImage<F>> {
+        &self.images[index]
+    }
+
+    /// Returns the index of the image that was presented last, if any.
+    #[inline]
+    pub fn last_presented(&self) -> Option<usize> {
+        self.state.lock().unwrap().last_presented
+    }
+
+    /// Acquires the next image of the ring and returns its index.
+    ///
+    /// Waits until the submissions of the previous time this image was presented are finished.
+    /// Returns `AcquireError::Timeout` if this doesn't happen before `timeout`, or if the image
+    /// is still acquired because it hasn't been presented.
+    pub fn acquire_next_image(&self, timeout: Duration) -> Result<usize, AcquireError> {
+        let mut state = self.state.lock().unwrap();
+        let index = state.next;
+
+        if state.images[index].acquired {
+            return Err(AcquireError::Timeout);
+        }
+
+        for submission in state.images[index].submissions.iter() {
+            match submission.wait(timeout) {
+                Ok(()) => (),
+                Err(FenceWaitError::OomError(err)) => return Err(AcquireError::OomError(err)),
+                Err(FenceWaitError::Timeout) => return Err(AcquireError::Timeout),
+                Err(FenceWaitError::DeviceLost(err)) => return Err(AcquireError::DeviceLost(err)),
+            }
+        }
+
+        state.images[index].submissions.clear();
+        state.images[index].acquired = true;
+        state.next = (index + 1) % state.images.len();
+        Ok(index)
+    }
+
+    /// Hands back an image that was acquired, along with the submissions that use it.
+    ///
+    /// The image can't be acquired again before these submissions are finished.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `index` is out of range.
+    /// - Panics if the image wasn't acquired.
+    ///
+    pub fn present<I>(&self, index: usize, submissions: I)
+        where I: IntoIterator<Item = Arc<Submission>>
+    {
+        let mut state = self.state.lock().unwrap();
+        assert!(state.images[index].acquired, "The image wasn't acquired");
+
+        state.images[index].acquired = false;
+        state.images[index].submissions = submissions.into_iter().collect();
+        state.last_presented = Some(index);
+    }
+
+    /// Waits until the submissions of the image that was presented last are finished, so that
+    /// the results of a readback can be accessed.
+    ///
+    /// Does nothing if no image has been presented yet.
+    pub fn wait_last_presented(&self, timeout: Duration) -> Result<(), FenceWaitError> {
+        let state = self.state.lock().unwrap();
+
+        if let Some(index) = state.last_presented {
+            for submission in state.images[index].submissions.iter() {
+                try!(submission.wait(timeout));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use format::Format;
+    use swapchain::AcquireError;
+    use swapchain::OffscreenSwapchain;
+
+    #[test]
+    fn acquire_present_ring() {
+        let (device, _) = gfx_dev_and_queue!();
+        let (swapchain, images) = OffscreenSwapchain::new(&device, [32, 32],
+                                                          Format::R8G8B8A8Unorm, 2).unwrap();
+        assert_eq!(images.len(), 2);
+        assert_eq!(swapchain.last_presented(), None);
+
+        let timeout = Duration::from_secs(1);
+        assert_eq!(swapchain.acquire_next_image(timeout), Ok(0));
+        assert_eq!(swapchain.acquire_next_image(timeout), Ok(1));
+        assert_eq!(swapchain.acquire_next_image(timeout), Err(AcquireError::Timeout));
+
+        swapchain.present(0, Vec::new());
+        assert_eq!(swapchain.last_presented(), Some(0));
+        assert_eq!(swapchain.acquire_next_image(timeout), Ok(0));
+        swapchain.wait_last_presented(timeout).unwrap();
+    }
+}
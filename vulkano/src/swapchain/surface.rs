@@ -7,10 +7,12 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::any::Any;
 use std::error;
 use std::fmt;
 use std::mem;
 use std::os::raw::c_ulong;
+use std::os::raw::c_void;
 use std::ptr;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
@@ -28,6 +30,7 @@ use swapchain::display::DisplayPlane;
 use check_errors;
 use Error;
 use OomError;
+use VulkanError;
 use VulkanObject;
 use VulkanPointers;
 use vk;
@@ -43,9 +46,103 @@ pub struct Surface {
     // If true, a swapchain has been associated to this surface, and that any new swapchain
     // creation should be forbidden.
     has_swapchain: AtomicBool,
+
+    // The window the surface was created from with `from_window`, kept alive as long as the
+    // surface.
+    window: Option<Arc<Any + Send + Sync>>,
+}
+
+/// Native handle of a window, used to create a `Surface`.
+///
+/// Each variant is only available if the corresponding feature of the crate is enabled. All of
+/// these features are enabled by default.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WindowHandle {
+    /// A Win32 window. Requires the `khr_win32_surface` extension.
+    #[cfg(feature = "win32")]
+    Win32 { hinstance: *const c_void, hwnd: *const c_void },
+    /// An Xlib window. Requires the `khr_xlib_surface` extension.
+    #[cfg(feature = "xlib")]
+    Xlib { display: *const c_void, window: c_ulong },
+    /// An XCB window. Requires the `khr_xcb_surface` extension.
+    #[cfg(feature = "xcb")]
+    Xcb { connection: *const c_void, window: u32 },
+    /// A Wayland surface. Requires the `khr_wayland_surface` extension.
+    #[cfg(feature = "wayland")]
+    Wayland { display: *const c_void, surface: *const c_void },
+    /// A Mir surface. Requires the `khr_mir_surface` extension.
+    #[cfg(feature = "mir")]
+    Mir { connection: *const c_void, surface: *const c_void },
+    /// An Android `ANativeWindow`. Requires the `khr_android_surface` extension.
+    #[cfg(feature = "android")]
+    Android { window: *const c_void },
+}
+
+/// Object that owns a native window and that can be used to create a `Surface` safely.
+///
+/// This is meant to be implemented by windowing libraries, or by applications that create their
+/// windows themselves.
+///
+/// # Safety
+///
+/// The handle returned by `window_handle` must be valid for as long as the object is alive.
+pub unsafe trait Window: Send + Sync + 'static {
+    /// Returns the native handle of the window.
+    fn window_handle(&self) -> WindowHandle;
 }
 
 impl Surface {
+    /// Creates a `Surface` from a window.
+    ///
+    /// The surface keeps the window alive, which makes this function safe contrary to the
+    /// constructors that take raw handles.
+    pub fn from_window<W>(instance: &Arc<Instance>, window: Arc<W>)
+                          -> Result<Arc<Surface>, SurfaceCreationError>
+        where W: Window
+    {
+        let mut surface = unsafe {
+            try!(Surface::from_window_handle(instance, window.window_handle()))
+        };
+
+        // The surface was just created, so there's no other reference to it.
+        Arc::get_mut(&mut surface).unwrap().window = Some(window);
+        Ok(surface)
+    }
+
+    /// Creates a `Surface` from the native handle of a window.
+    ///
+    /// This calls the constructor that corresponds to the platform of the handle, such as
+    /// `from_xlib` or `from_hwnd`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the handle is correct and stays alive for the entire lifetime
+    /// of the surface.
+    pub unsafe fn from_window_handle(instance: &Arc<Instance>, handle: WindowHandle)
+                                     -> Result<Arc<Surface>, SurfaceCreationError>
+    {
+        match handle {
+            #[cfg(feature = "win32")]
+            WindowHandle::Win32 { hinstance, hwnd } => Surface::from_hwnd(instance, hinstance, hwnd),
+            #[cfg(feature = "xlib")]
+            WindowHandle::Xlib { display, window } => Surface::from_xlib(instance, display, window),
+            #[cfg(feature = "xcb")]
+            WindowHandle::Xcb { connection, window } => {
+                Surface::from_xcb(instance, connection, window)
+            },
+            #[cfg(feature = "wayland")]
+            WindowHandle::Wayland { display, surface } => {
+                Surface::from_wayland(instance, display, surface)
+            },
+            #[cfg(feature = "mir")]
+            WindowHandle::Mir { connection, surface } => {
+                Surface::from_mir(instance, connection, surface)
+            },
+            #[cfg(feature = "android")]
+            WindowHandle::Android { window } => Surface::from_anativewindow(instance, window),
+        }
+    }
+
     /// Creates a `Surface` that covers a display mode.
     ///
     /// # Panic
@@ -94,6 +191,7 @@ impl Surface {
             instance: instance.clone(),
             surface: surface,
             has_swapchain: AtomicBool::new(false),
+            window: None,
         }))
     }
 
@@ -105,6 +203,7 @@ impl Surface {
     ///
     /// The caller must ensure that the `hinstance` and the `hwnd` are both correct and stay
     /// alive for the entire lifetime of the surface.
+    #[cfg(feature = "win32")]
     pub unsafe fn from_hwnd<T, U>(instance: &Arc<Instance>, hinstance: *const T, hwnd: *const U)
                                   -> Result<Arc<Surface>, SurfaceCreationError>
     {
@@ -133,6 +232,7 @@ impl Surface {
             instance: instance.clone(),
             surface: surface,
             has_swapchain: AtomicBool::new(false),
+            window: None,
         }))
     }
 
@@ -144,6 +244,7 @@ impl Surface {
     ///
     /// The caller must ensure that the `connection` and the `window` are both correct and stay
     /// alive for the entire lifetime of the surface.
+    #[cfg(feature = "xcb")]
     pub unsafe fn from_xcb<C>(instance: &Arc<Instance>, connection: *const C, window: u32)
                                  -> Result<Arc<Surface>, SurfaceCreationError>
     {
@@ -172,6 +273,7 @@ impl Surface {
             instance: instance.clone(),
             surface: surface,
             has_swapchain: AtomicBool::new(false),
+            window: None,
         }))
     }
 
@@ -183,6 +285,7 @@ impl Surface {
     ///
     /// The caller must ensure that the `display` and the `window` are both correct and stay
     /// alive for the entire lifetime of the surface.
+    #[cfg(feature = "xlib")]
     pub unsafe fn from_xlib<D>(instance: &Arc<Instance>, display: *const D, window: c_ulong)
                                   -> Result<Arc<Surface>, SurfaceCreationError>
     {
@@ -211,6 +314,7 @@ impl Surface {
             instance: instance.clone(),
             surface: surface,
             has_swapchain: AtomicBool::new(false),
+            window: None,
         }))
     }
 
@@ -222,6 +326,7 @@ impl Surface {
     ///
     /// The caller must ensure that the `display` and the `surface` are both correct and stay
     /// alive for the entire lifetime of the surface.
+    #[cfg(feature = "wayland")]
     pub unsafe fn from_wayland<D, S>(instance: &Arc<Instance>, display: *const D, surface: *const S)
                                      -> Result<Arc<Surface>, SurfaceCreationError>
     {
@@ -250,6 +355,7 @@ impl Surface {
             instance: instance.clone(),
             surface: surface,
             has_swapchain: AtomicBool::new(false),
+            window: None,
         }))
     }
 
@@ -262,6 +368,7 @@ impl Surface {
     ///
     /// The caller must ensure that the `connection` and the `surface` are both correct and stay
     /// alive for the entire lifetime of the surface.
+    #[cfg(feature = "mir")]
     pub unsafe fn from_mir<C, S>(instance: &Arc<Instance>, connection: *const C, surface: *const S)
                                  -> Result<Arc<Surface>, SurfaceCreationError>
     {
@@ -290,6 +397,7 @@ impl Surface {
             instance: instance.clone(),
             surface: surface,
             has_swapchain: AtomicBool::new(false),
+            window: None,
         }))
     }
 
@@ -299,6 +407,7 @@ impl Surface {
     ///
     /// The caller must ensure that the `window` is correct and stays alive for the entire
     /// lifetime of the surface.
+    #[cfg(feature = "android")]
     pub unsafe fn from_anativewindow<T>(instance: &Arc<Instance>, window: *const T)
                                         -> Result<Arc<Surface>, SurfaceCreationError>
     {
@@ -326,6 +435,7 @@ impl Surface {
             instance: instance.clone(),
             surface: surface,
             has_swapchain: AtomicBool::new(false),
+            window: None,
         }))
     }
 
@@ -343,26 +453,83 @@ impl Surface {
         }
     }
 
-    /// Retreives the capabilities of a surface when used by a certain device.
+    /// Queries the current capabilities of a surface when used by a certain device.
+    ///
+    /// Contrary to `get_capabilities`, this doesn't query the supported formats and present
+    /// modes, which don't change during the lifetime of the surface. The values returned by this
+    /// function, most notably the current extent and transform, can change for example when the
+    /// window is resized or rotated, and are cheap enough to be queried again every time.
     ///
     /// # Panic
     ///
     /// - Panics if the device and the surface don't belong to the same instance.
     ///
-    pub fn get_capabilities(&self, device: &PhysicalDevice) -> Result<Capabilities, OomError> { // TODO: wrong error type
+    pub fn capabilities(&self, device: &PhysicalDevice)
+                        -> Result<SurfaceCapabilities, OomError>
+    {
         unsafe {
             assert_eq!(&*self.instance as *const _, &**device.instance() as *const _);
 
             let vk = self.instance.pointers();
 
-            let caps = {
-                let mut out: vk::SurfaceCapabilitiesKHR = mem::uninitialized();
-                try!(check_errors(
-                    vk.GetPhysicalDeviceSurfaceCapabilitiesKHR(device.internal_object(),
-                                                               self.surface, &mut out)
-                ));
-                out
-            };
+            let mut caps: vk::SurfaceCapabilitiesKHR = mem::uninitialized();
+            try!(check_errors(
+                vk.GetPhysicalDeviceSurfaceCapabilitiesKHR(device.internal_object(),
+                                                           self.surface, &mut caps)
+            ));
+
+            Ok(SurfaceCapabilities {
+                min_image_count: caps.minImageCount,
+                max_image_count: if caps.maxImageCount == 0 { None }
+                                 else { Some(caps.maxImageCount) },
+                current_extent: if caps.currentExtent.width == 0xffffffff &&
+                                   caps.currentExtent.height == 0xffffffff
+                {
+                    None
+                } else {
+                    Some([caps.currentExtent.width, caps.currentExtent.height])
+                },
+                min_image_extent: [caps.minImageExtent.width, caps.minImageExtent.height],
+                max_image_extent: [caps.maxImageExtent.width, caps.maxImageExtent.height],
+                max_image_array_layers: caps.maxImageArrayLayers,
+                supported_transforms: SupportedSurfaceTransforms::from_bits(caps.supportedTransforms),
+                current_transform: SupportedSurfaceTransforms::from_bits(caps.currentTransform)
+                                        .iter().next().unwrap_or(SurfaceTransform::Identity),
+                supported_composite_alpha: SupportedCompositeAlpha::from_bits(caps.supportedCompositeAlpha),
+                supported_usage_flags: {
+                    let usage = ImageUsage::from_bits(caps.supportedUsageFlags);
+                    debug_assert!(usage.color_attachment);  // specs say that this must be true
+                    usage
+                },
+            })
+        }
+    }
+
+    /// Returns the current dimensions of the surface, or `None` if the dimensions of the surface
+    /// are determined by the dimensions of the swapchain that uses it.
+    ///
+    /// This is a shortcut for the `current_extent` of `capabilities`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device and the surface don't belong to the same instance.
+    ///
+    #[inline]
+    pub fn current_extent(&self, device: &PhysicalDevice) -> Result<Option<[u32; 2]>, OomError> {
+        Ok(try!(self.capabilities(device)).current_extent)
+    }
+
+    /// Retreives the capabilities of a surface when used by a certain device.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device and the surface don't belong to the same instance.
+    ///
+    pub fn get_capabilities(&self, device: &PhysicalDevice) -> Result<Capabilities, OomError> { // TODO: wrong error type
+        let caps = try!(self.capabilities(device));
+
+        unsafe {
+            let vk = self.instance.pointers();
 
             let formats = {
                 let mut num = 0;
@@ -405,34 +572,57 @@ impl Surface {
             };
 
             Ok(Capabilities {
-                min_image_count: caps.minImageCount,
-                max_image_count: if caps.maxImageCount == 0 { None }
-                                 else { Some(caps.maxImageCount) },
-                current_extent: if caps.currentExtent.width == 0xffffffff &&
-                                   caps.currentExtent.height == 0xffffffff
-                {
-                    None
-                } else {
-                    Some([caps.currentExtent.width, caps.currentExtent.height])
-                },
-                min_image_extent: [caps.minImageExtent.width, caps.minImageExtent.height],
-                max_image_extent: [caps.maxImageExtent.width, caps.maxImageExtent.height],
-                max_image_array_layers: caps.maxImageArrayLayers,
-                supported_transforms: SupportedSurfaceTransforms::from_bits(caps.supportedTransforms),
-                current_transform: SupportedSurfaceTransforms::from_bits(caps.supportedTransforms).iter().next().unwrap(),        // TODO:
-                supported_composite_alpha: SupportedCompositeAlpha::from_bits(caps.supportedCompositeAlpha),
-                supported_usage_flags: {
-                    let usage = ImageUsage::from_bits(caps.supportedUsageFlags);
-                    debug_assert!(usage.color_attachment);  // specs say that this must be true
-                    usage
-                },
-                supported_formats: formats.into_iter().map(|f| {
-                    (Format::from_num(f.format).unwrap(), ColorSpace::from_num(f.colorSpace))
+                min_image_count: caps.min_image_count,
+                max_image_count: caps.max_image_count,
+                current_extent: caps.current_extent,
+                min_image_extent: caps.min_image_extent,
+                max_image_extent: caps.max_image_extent,
+                max_image_array_layers: caps.max_image_array_layers,
+                supported_transforms: caps.supported_transforms,
+                current_transform: caps.current_transform,
+                supported_composite_alpha: caps.supported_composite_alpha,
+                supported_usage_flags: caps.supported_usage_flags,
+                supported_formats: formats.into_iter().filter_map(|f| {
+                    // Color spaces of extensions that vulkano doesn't know about are skipped.
+                    ColorSpace::from_num(f.colorSpace).map(|cs| {
+                        (Format::from_num(f.format).unwrap(), cs)
+                    })
                 }).collect(),
                 present_modes: modes,
             })
         }
     }
+
+    /// Chooses a format and a present mode for a swapchain that uses this surface.
+    ///
+    /// The formats and the present modes are passed by order of preference. See
+    /// `Capabilities::choose_format` and `Capabilities::choose_present_mode` for what happens if
+    /// none of them is supported.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use vulkano::swapchain::PresentMode;
+    ///
+    /// # let surface: std::sync::Arc<vulkano::swapchain::Surface> = unsafe { std::mem::uninitialized() };
+    /// # let physical: vulkano::instance::PhysicalDevice = unsafe { std::mem::uninitialized() };
+    /// let (format, _color_space, present_mode) = surface.choose_format_and_present_mode(
+    ///     &physical, &[], &[PresentMode::Mailbox, PresentMode::Immediate]).unwrap();
+    /// ```
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the device and the surface don't belong to the same instance.
+    ///
+    pub fn choose_format_and_present_mode(&self, device: &PhysicalDevice, formats: &[Format],
+                                          present_modes: &[PresentMode])
+                                          -> Result<(Format, ColorSpace, PresentMode), OomError>
+    {
+        let capabilities = try!(self.get_capabilities(device));
+        let (format, color_space) = capabilities.choose_format(formats);
+        let present_mode = capabilities.choose_present_mode(present_modes);
+        Ok((format, color_space, present_mode))
+    }
 }
 
 unsafe impl SurfaceSwapchainLock for Surface {
@@ -500,6 +690,16 @@ impl fmt::Display for SurfaceCreationError {
     }
 }
 
+impl VulkanError for SurfaceCreationError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            SurfaceCreationError::OomError(ref err) => err.error_code(),
+            _ => None
+        }
+    }
+}
+
 impl From<OomError> for SurfaceCreationError {
     #[inline]
     fn from(err: OomError) -> SurfaceCreationError {
@@ -518,6 +718,46 @@ impl From<Error> for SurfaceCreationError {
     }
 }
 
+/// The capabilities of a surface that can change during its lifetime, for example when the window
+/// is resized.
+///
+/// Returned by `Surface::capabilities`.
+#[derive(Clone, Debug)]
+pub struct SurfaceCapabilities {
+    /// Minimum number of images that must be present in the swapchain.
+    pub min_image_count: u32,
+
+    /// Maximum number of images that must be present in the swapchain, or `None` if there is no
+    /// maximum value.
+    pub max_image_count: Option<u32>,
+
+    /// The current dimensions of the surface. `None` means that the surface's dimensions will
+    /// depend on the dimensions of the swapchain that you are going to create.
+    pub current_extent: Option<[u32; 2]>,
+
+    /// Minimum width and height of a swapchain that uses this surface.
+    pub min_image_extent: [u32; 2],
+
+    /// Maximum width and height of a swapchain that uses this surface.
+    pub max_image_extent: [u32; 2],
+
+    /// Maximum number of image layers if you create an image array. The minimum is 1.
+    pub max_image_array_layers: u32,
+
+    /// List of transforms supported for the swapchain.
+    pub supported_transforms: SupportedSurfaceTransforms,
+
+    /// Current transform used by the surface.
+    pub current_transform: SurfaceTransform,
+
+    /// List of composite alpha modes supports for the swapchain.
+    pub supported_composite_alpha: SupportedCompositeAlpha,
+
+    /// List of image usages that are supported for images of the swapchain. Only
+    /// the `color_attachment` usage is guaranteed to be supported.
+    pub supported_usage_flags: ImageUsage,
+}
+
 /// The capabilities of a surface when used by a physical device.
 ///
 /// You have to match these capabilities when you create a swapchain.
@@ -564,6 +804,43 @@ pub struct Capabilities {
     pub present_modes: SupportedPresentModes,
 }
 
+impl Capabilities {
+    /// Returns the first format of `preferred` that is supported, with its color space.
+    ///
+    /// If none of them is supported, returns the first supported format that is sRGB, or the
+    /// first supported format if there is none.
+    ///
+    /// Only formats in the `SrgbNonLinear` color space are considered, unless the surface doesn't
+    /// support this color space at all. To output to an HDR or a wide gamut display, look for the
+    /// color space you want in `supported_formats` instead.
+    pub fn choose_format(&self, preferred: &[Format]) -> (Format, ColorSpace) {
+        let srgb_only = self.supported_formats.iter()
+                            .any(|&(_, cs)| cs == ColorSpace::SrgbNonLinear);
+        let candidates = || {
+            self.supported_formats.iter().cloned()
+                .filter(move |&(_, cs)| !srgb_only || cs == ColorSpace::SrgbNonLinear)
+        };
+
+        for &format in preferred {
+            if let Some(f) = candidates().find(|&(f, _)| f == format) {
+                return f;
+            }
+        }
+
+        candidates().find(|&(f, _)| f.is_srgb())
+            .or(candidates().next())
+            .expect("the surface doesn't support any format")
+    }
+
+    /// Returns the first present mode of `preferred` that is supported.
+    ///
+    /// If none of them is supported, returns `Fifo`, which is always supported.
+    pub fn choose_present_mode(&self, preferred: &[PresentMode]) -> PresentMode {
+        preferred.iter().cloned().find(|&m| self.present_modes.supports(m))
+                 .unwrap_or(PresentMode::Fifo)
+    }
+}
+
 /// The way presenting a swapchain is accomplished.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(u32)]
@@ -776,27 +1053,141 @@ impl Iterator for SupportedCompositeAlphaIter {
 }
 
 /// How the presentation engine should interpret the data.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+///
+/// All the color spaces other than `SrgbNonLinear` require the `ext_swapchain_colorspace`
+/// instance extension.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[repr(u32)]
 pub enum ColorSpace {
     /// Interpret it as sRGB.
-    SrgbNonLinear,
+    SrgbNonLinear = vk::COLOR_SPACE_SRGB_NONLINEAR_KHR,
+    /// Display-P3 primaries with the sRGB transfer function.
+    DisplayP3NonLinear = vk::COLOR_SPACE_DISPLAY_P3_NONLINEAR_EXT,
+    /// sRGB primaries with a linear transfer function. Values can go outside of the `[0, 1]`
+    /// range, which is what is usually called scRGB.
+    ExtendedSrgbLinear = vk::COLOR_SPACE_EXTENDED_SRGB_LINEAR_EXT,
+    /// Same as `ExtendedSrgbLinear`, but with the sRGB transfer function.
+    ExtendedSrgbNonLinear = vk::COLOR_SPACE_EXTENDED_SRGB_NONLINEAR_EXT,
+    /// Display-P3 primaries with a linear transfer function.
+    DisplayP3Linear = vk::COLOR_SPACE_DISPLAY_P3_LINEAR_EXT,
+    /// DCI-P3 primaries with the DCI-P3 transfer function.
+    DciP3NonLinear = vk::COLOR_SPACE_DCI_P3_NONLINEAR_EXT,
+    /// BT.709 primaries with a linear transfer function.
+    Bt709Linear = vk::COLOR_SPACE_BT709_LINEAR_EXT,
+    /// BT.709 primaries with the BT.709 transfer function.
+    Bt709NonLinear = vk::COLOR_SPACE_BT709_NONLINEAR_EXT,
+    /// BT.2020 primaries with a linear transfer function.
+    Bt2020Linear = vk::COLOR_SPACE_BT2020_LINEAR_EXT,
+    /// BT.2020 primaries with the SMPTE ST 2084 (PQ) transfer function. This is HDR10.
+    Hdr10St2084 = vk::COLOR_SPACE_HDR10_ST2084_EXT,
+    /// Dolby Vision, with BT.2020 primaries and the SMPTE ST 2084 transfer function.
+    DolbyVision = vk::COLOR_SPACE_DOLBYVISION_EXT,
+    /// BT.2020 primaries with the hybrid log-gamma transfer function.
+    Hdr10Hlg = vk::COLOR_SPACE_HDR10_HLG_EXT,
+    /// Adobe RGB primaries with a linear transfer function.
+    AdobeRgbLinear = vk::COLOR_SPACE_ADOBERGB_LINEAR_EXT,
+    /// Adobe RGB primaries with the gamma 2.2 transfer function.
+    AdobeRgbNonLinear = vk::COLOR_SPACE_ADOBERGB_NONLINEAR_EXT,
+    /// The values are passed to the display without any color space conversion.
+    PassThrough = vk::COLOR_SPACE_PASS_THROUGH_EXT,
 }
 
 impl ColorSpace {
     #[inline]
-    fn from_num(val: u32) -> ColorSpace {
-        assert_eq!(val, vk::COLOR_SPACE_SRGB_NONLINEAR_KHR);
-        ColorSpace::SrgbNonLinear
+    fn from_num(val: u32) -> Option<ColorSpace> {
+        Some(match val {
+            vk::COLOR_SPACE_SRGB_NONLINEAR_KHR => ColorSpace::SrgbNonLinear,
+            vk::COLOR_SPACE_DISPLAY_P3_NONLINEAR_EXT => ColorSpace::DisplayP3NonLinear,
+            vk::COLOR_SPACE_EXTENDED_SRGB_LINEAR_EXT => ColorSpace::ExtendedSrgbLinear,
+            vk::COLOR_SPACE_EXTENDED_SRGB_NONLINEAR_EXT => ColorSpace::ExtendedSrgbNonLinear,
+            vk::COLOR_SPACE_DISPLAY_P3_LINEAR_EXT => ColorSpace::DisplayP3Linear,
+            vk::COLOR_SPACE_DCI_P3_NONLINEAR_EXT => ColorSpace::DciP3NonLinear,
+            vk::COLOR_SPACE_BT709_LINEAR_EXT => ColorSpace::Bt709Linear,
+            vk::COLOR_SPACE_BT709_NONLINEAR_EXT => ColorSpace::Bt709NonLinear,
+            vk::COLOR_SPACE_BT2020_LINEAR_EXT => ColorSpace::Bt2020Linear,
+            vk::COLOR_SPACE_HDR10_ST2084_EXT => ColorSpace::Hdr10St2084,
+            vk::COLOR_SPACE_DOLBYVISION_EXT => ColorSpace::DolbyVision,
+            vk::COLOR_SPACE_HDR10_HLG_EXT => ColorSpace::Hdr10Hlg,
+            vk::COLOR_SPACE_ADOBERGB_LINEAR_EXT => ColorSpace::AdobeRgbLinear,
+            vk::COLOR_SPACE_ADOBERGB_NONLINEAR_EXT => ColorSpace::AdobeRgbNonLinear,
+            vk::COLOR_SPACE_PASS_THROUGH_EXT => ColorSpace::PassThrough,
+            _ => return None,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use std::ptr;
+    use std::sync::Arc;
+    use format::Format;
+    use image::Usage as ImageUsage;
+    use swapchain::Capabilities;
+    use swapchain::ColorSpace;
+    use swapchain::PresentMode;
+    use swapchain::SupportedSurfaceTransforms;
     use swapchain::Surface;
     use swapchain::SurfaceCreationError;
+    use swapchain::SurfaceTransform;
+    use swapchain::Window;
+    use swapchain::WindowHandle;
+    use super::SupportedCompositeAlpha;
+    use super::SupportedPresentModes;
+
+    fn capabilities(formats: Vec<Format>, modes: SupportedPresentModes) -> Capabilities {
+        Capabilities {
+            min_image_count: 2,
+            max_image_count: None,
+            current_extent: None,
+            min_image_extent: [1, 1],
+            max_image_extent: [4096, 4096],
+            max_image_array_layers: 1,
+            supported_transforms: SupportedSurfaceTransforms::none(),
+            current_transform: SurfaceTransform::Identity,
+            supported_composite_alpha: SupportedCompositeAlpha::none(),
+            supported_usage_flags: ImageUsage { color_attachment: true, .. ImageUsage::none() },
+            supported_formats: formats.into_iter().map(|f| (f, ColorSpace::SrgbNonLinear))
+                                      .collect(),
+            present_modes: modes,
+        }
+    }
+
+    #[test]
+    fn choose_format() {
+        let caps = capabilities(vec![Format::B8G8R8A8Unorm, Format::B8G8R8A8Srgb],
+                                SupportedPresentModes::none());
+
+        assert_eq!(caps.choose_format(&[Format::R8G8B8A8Unorm, Format::B8G8R8A8Unorm]).0,
+                   Format::B8G8R8A8Unorm);
+        assert_eq!(caps.choose_format(&[]).0, Format::B8G8R8A8Srgb);
+
+        let caps = capabilities(vec![Format::B8G8R8A8Unorm], SupportedPresentModes::none());
+        assert_eq!(caps.choose_format(&[Format::R8G8B8A8Srgb]).0, Format::B8G8R8A8Unorm);
+    }
 
     #[test]
+    fn choose_format_ignores_hdr() {
+        let mut caps = capabilities(vec![Format::B8G8R8A8Unorm], SupportedPresentModes::none());
+        caps.supported_formats.insert(0, (Format::A2B10G10R10UnormPack32,
+                                          ColorSpace::Hdr10St2084));
+
+        assert_eq!(caps.choose_format(&[Format::A2B10G10R10UnormPack32]),
+                   (Format::B8G8R8A8Unorm, ColorSpace::SrgbNonLinear));
+    }
+
+    #[test]
+    fn choose_present_mode() {
+        let modes = SupportedPresentModes { immediate: true, fifo: true,
+                                            .. SupportedPresentModes::none() };
+        let caps = capabilities(vec![Format::B8G8R8A8Srgb], modes);
+
+        let preferred = [PresentMode::Mailbox, PresentMode::Immediate, PresentMode::Fifo];
+        assert_eq!(caps.choose_present_mode(&preferred), PresentMode::Immediate);
+        assert_eq!(caps.choose_present_mode(&[PresentMode::Relaxed]), PresentMode::Fifo);
+    }
+
+    #[test]
+    #[cfg(feature = "win32")]
     fn khr_win32_surface_ext_missing() {
         let instance = instance!();
         match unsafe { Surface::from_hwnd(&instance, ptr::null::<u8>(), ptr::null::<u8>()) } {
@@ -806,6 +1197,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "xcb")]
     fn khr_xcb_surface_ext_missing() {
         let instance = instance!();
         match unsafe { Surface::from_xcb(&instance, ptr::null::<u8>(), 0) } {
@@ -815,6 +1207,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "xlib")]
     fn khr_xlib_surface_ext_missing() {
         let instance = instance!();
         match unsafe { Surface::from_xlib(&instance, ptr::null::<u8>(), 0) } {
@@ -824,6 +1217,24 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "xlib")]
+    fn from_window_ext_missing() {
+        struct DummyWindow;
+        unsafe impl Window for DummyWindow {
+            fn window_handle(&self) -> WindowHandle {
+                WindowHandle::Xlib { display: ptr::null(), window: 0 }
+            }
+        }
+
+        let instance = instance!();
+        match Surface::from_window(&instance, Arc::new(DummyWindow)) {
+            Err(SurfaceCreationError::MissingExtension { .. }) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "wayland")]
     fn khr_wayland_surface_ext_missing() {
         let instance = instance!();
         match unsafe { Surface::from_wayland(&instance, ptr::null::<u8>(), ptr::null::<u8>()) } {
@@ -833,6 +1244,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "mir")]
     fn khr_mir_surface_ext_missing() {
         let instance = instance!();
         match unsafe { Surface::from_mir(&instance, ptr::null::<u8>(), ptr::null::<u8>()) } {
@@ -842,6 +1254,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "android")]
     fn khr_android_surface_ext_missing() {
         let instance = instance!();
         match unsafe { Surface::from_anativewindow(&instance, ptr::null::<u8>()) } {
@@ -13,10 +13,13 @@ use std::mem;
 use std::ptr;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::time::Duration;
+use smallvec::SmallVec;
 
 use device::Device;
+use device::DeviceLostError;
 use device::Queue;
 use format::Format;
 use format::FormatDesc;
@@ -24,11 +27,13 @@ use image::ImageDimensions;
 use image::sys::UnsafeImage;
 use image::sys::Usage as ImageUsage;
 use image::swapchain::SwapchainImage;
+use swapchain::ColorSpace;
 use swapchain::CompositeAlpha;
 use swapchain::PresentMode;
 use swapchain::Surface;
 use swapchain::SurfaceTransform;
 use swapchain::SurfaceSwapchainLock;
+use sync::GpuFuture;
 use sync::Semaphore;
 use sync::SharingMode;
 
@@ -37,6 +42,7 @@ use Error;
 use OomError;
 use Success;
 use SynchronizedVulkanObject;
+use VulkanError;
 use VulkanObject;
 use VulkanPointers;
 use vk;
@@ -57,6 +63,11 @@ pub struct Swapchain {
 
     images_semaphores: Mutex<Vec<Option<Arc<Semaphore>>>>,
 
+    // Semaphores that the latest presentation of each image waits upon. There is no way to know
+    // when a presentation has finished waiting, so they are kept alive until the image is
+    // acquired again, which can't happen before the presentation is over.
+    present_semaphores: Mutex<Vec<Option<PresentSemaphores>>>,
+
     // If true, that means we have used this swapchain to recreate a new swapchain. The current
     // swapchain can no longer be used for anything except presenting already-acquired images.
     //
@@ -67,6 +78,7 @@ pub struct Swapchain {
     // Parameters passed to the constructor.
     num_images: u32,
     format: Format,
+    color_space: ColorSpace,
     dimensions: [u32; 2],
     layers: u32,
     usage: ImageUsage,
@@ -75,6 +87,10 @@ pub struct Swapchain {
     alpha: CompositeAlpha,
     mode: PresentMode,
     clipped: bool,
+    full_screen_exclusive: FullScreenExclusive,
+    // True if full-screen exclusive mode has been acquired with
+    // `acquire_full_screen_exclusive`.
+    full_screen_exclusive_held: AtomicBool,
 }
 
 impl Swapchain {
@@ -89,6 +105,15 @@ impl Swapchain {
     /// important to take into account if your fragment shader has side-effects or if you want to
     /// read back the content of the image afterwards.
     ///
+    /// The `color_space` parameter indicates how the presentation engine interprets the content
+    /// of the images. The combination of `format` and `color_space` must be in the
+    /// `supported_formats` of the capabilities. Pass `ColorSpace::SrgbNonLinear` unless you want
+    /// to output HDR or wide gamut content.
+    ///
+    /// The `full_screen_exclusive` parameter controls whether the implementation can give the
+    /// swapchain exclusive access to the display. See `FullScreenExclusive`. Pass
+    /// `FullScreenExclusive::Default` if you don't care.
+    ///
     /// This function returns the swapchain plus a list of the images that belong to the
     /// swapchain. The order in which the images are returned is important for the
     /// `acquire_next_image` and `present` functions.
@@ -97,36 +122,88 @@ impl Swapchain {
     ///
     /// - Panics if the device and the surface don't belong to the same instance.
     /// - Panics if `color_attachment` is false in `usage`.
+    /// - Panics if `color_space` isn't `SrgbNonLinear` and the `ext_swapchain_colorspace`
+    ///   extension isn't enabled on the instance.
+    /// - Panics if `full_screen_exclusive` isn't `Default` and the `ext_full_screen_exclusive`
+    ///   extension isn't enabled on the device.
     ///
     // TODO: remove `old_swapchain` parameter and add another function `with_old_swapchain`.
     #[inline]
     pub fn new<F, S>(device: &Arc<Device>, surface: &Arc<Surface>, num_images: u32, format: F,
-                     dimensions: [u32; 2], layers: u32, usage: &ImageUsage, sharing: S,
-                     transform: SurfaceTransform, alpha: CompositeAlpha, mode: PresentMode,
-                     clipped: bool, old_swapchain: Option<&Arc<Swapchain>>)
+                     color_space: ColorSpace, dimensions: [u32; 2], layers: u32,
+                     usage: &ImageUsage, sharing: S, transform: SurfaceTransform,
+                     alpha: CompositeAlpha, mode: PresentMode, clipped: bool,
+                     full_screen_exclusive: FullScreenExclusive,
+                     old_swapchain: Option<&Arc<Swapchain>>)
                      -> Result<(Arc<Swapchain>, Vec<Arc<SwapchainImage>>), OomError>
         where F: FormatDesc, S: Into<SharingMode>
     {
-        Swapchain::new_inner(device, surface, num_images, format.format(), dimensions, layers,
-                             usage, sharing.into(), transform, alpha, mode, clipped,
-                             old_swapchain.map(|s| &**s))
+        Swapchain::new_inner(device, surface, num_images, format.format(), color_space,
+                             dimensions, layers, usage, sharing.into(), transform, alpha, mode,
+                             clipped, full_screen_exclusive, old_swapchain.map(|s| &**s))
+    }
+
+    /// Recreates the swapchain with the current dimensions of the surface.
+    ///
+    /// This is what you usually want to call after `acquire_next_image` or `present` returned
+    /// `OutOfDate`, for example because the window has been resized. The new swapchain has the
+    /// same format, present mode, usage and other parameters as this one, and is created with
+    /// this one as its old swapchain. This swapchain can no longer be acquired afterwards.
+    ///
+    /// If the surface doesn't report its dimensions, the dimensions of this swapchain are kept.
+    /// Use `recreate_with_dimension` in this situation.
+    ///
+    /// Returns `UnsupportedDimensions` if the surface currently can't hold a swapchain, for
+    /// example because the window is minimized. Try again later in this situation.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the other parameters of this swapchain are no longer supported by the surface.
+    ///
+    pub fn recreate(&self)
+                    -> Result<(Arc<Swapchain>, Vec<Arc<SwapchainImage>>), SwapchainCreationError>
+    {
+        let current_extent = try!(self.surface.current_extent(&self.device.physical_device()));
+        let dimensions = current_extent.unwrap_or(self.dimensions);
+        self.recreate_with_dimension(dimensions)
     }
 
-     /// Recreates the swapchain with new dimensions.
+    /// Recreates the swapchain with new dimensions.
+    ///
+    /// Same as `recreate`, except that the dimensions of the new swapchain are `dimensions`.
+    ///
+    /// Returns `UnsupportedDimensions` if `dimensions` is not supported by the surface.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the other parameters of this swapchain are no longer supported by the surface.
+    ///
     pub fn recreate_with_dimension(&self, dimensions: [u32; 2])
-                                   -> Result<(Arc<Swapchain>, Vec<Arc<SwapchainImage>>), OomError>
+                                   -> Result<(Arc<Swapchain>, Vec<Arc<SwapchainImage>>),
+                                             SwapchainCreationError>
     {
-        Swapchain::new_inner(&self.device, &self.surface, self.num_images, self.format,
-                             dimensions, self.layers, &self.usage, self.sharing.clone(),
-                             self.transform, self.alpha, self.mode, self.clipped, Some(self))
+        let capabilities = try!(self.surface.get_capabilities(&self.device.physical_device()));
+        if dimensions[0] < capabilities.min_image_extent[0] ||
+           dimensions[1] < capabilities.min_image_extent[1] ||
+           dimensions[0] > capabilities.max_image_extent[0] ||
+           dimensions[1] > capabilities.max_image_extent[1]
+        {
+            return Err(SwapchainCreationError::UnsupportedDimensions);
+        }
+
+        Ok(try!(Swapchain::new_inner(&self.device, &self.surface, self.num_images, self.format,
+                                     self.color_space, dimensions, self.layers, &self.usage,
+                                     self.sharing.clone(), self.transform, self.alpha, self.mode,
+                                     self.clipped, self.full_screen_exclusive, Some(self))))
     }
 
     // TODO: images layouts should always be set to "PRESENT", since we have no way to switch the
     //       layout at present time
     fn new_inner(device: &Arc<Device>, surface: &Arc<Surface>, num_images: u32, format: Format,
-                 dimensions: [u32; 2], layers: u32, usage: &ImageUsage, sharing: SharingMode,
+                 color_space: ColorSpace, dimensions: [u32; 2], layers: u32, usage: &ImageUsage, sharing: SharingMode,
                  transform: SurfaceTransform, alpha: CompositeAlpha, mode: PresentMode,
-                 clipped: bool, old_swapchain: Option<&Swapchain>)
+                 clipped: bool, full_screen_exclusive: FullScreenExclusive,
+                 old_swapchain: Option<&Swapchain>)
                  -> Result<(Arc<Swapchain>, Vec<Arc<SwapchainImage>>), OomError>
     {
         // Checking that the requested parameters match the capabilities.
@@ -134,7 +211,11 @@ impl Swapchain {
         // TODO: return errors instead
         assert!(num_images >= capabilities.min_image_count);
         if let Some(c) = capabilities.max_image_count { assert!(num_images <= c) };
-        assert!(capabilities.supported_formats.iter().find(|&&(f, _)| f == format).is_some());
+        assert!(capabilities.supported_formats.iter()
+                            .find(|&&(f, cs)| f == format && cs == color_space).is_some());
+        if color_space != ColorSpace::SrgbNonLinear {
+            assert!(device.instance().loaded_extensions().ext_swapchain_colorspace);
+        }
         assert!(dimensions[0] >= capabilities.min_image_extent[0]);
         assert!(dimensions[1] >= capabilities.min_image_extent[1]);
         assert!(dimensions[0] <= capabilities.max_image_extent[0]);
@@ -144,6 +225,9 @@ impl Swapchain {
         assert!(capabilities.supported_transforms.supports(transform));
         assert!(capabilities.supported_composite_alpha.supports(alpha));
         assert!(capabilities.present_modes.supports(mode));
+        if full_screen_exclusive != FullScreenExclusive::Default {
+            assert!(device.loaded_extensions().ext_full_screen_exclusive);
+        }
 
         // If we recreate a swapchain, make sure that the surface is the same.
         if let Some(sc) = old_swapchain {
@@ -164,8 +248,10 @@ impl Swapchain {
 
         assert!(usage.color_attachment);
 
+        // The old swapchain is retired by the creation of the new one, and acquiring its images
+        // is no longer possible.
         if let Some(ref old_swapchain) = old_swapchain {
-            *old_swapchain.stale.lock().unwrap() = false;
+            *old_swapchain.stale.lock().unwrap() = true;
         }
 
         let swapchain = unsafe {
@@ -175,14 +261,39 @@ impl Swapchain {
                                                      ids.as_ptr()),
             };
 
+            let win32_monitor = match full_screen_exclusive {
+                FullScreenExclusive::AppControlled(Some(monitor)) => {
+                    Some(vk::SurfaceFullScreenExclusiveWin32InfoEXT {
+                        sType: vk::STRUCTURE_TYPE_SURFACE_FULL_SCREEN_EXCLUSIVE_WIN32_INFO_EXT,
+                        pNext: ptr::null(),
+                        hmonitor: monitor.0 as *mut _,
+                    })
+                },
+                _ => None,
+            };
+
+            let full_screen_exclusive_info = if full_screen_exclusive ==
+                                                    FullScreenExclusive::Default
+            {
+                None
+            } else {
+                Some(vk::SurfaceFullScreenExclusiveInfoEXT {
+                    sType: vk::STRUCTURE_TYPE_SURFACE_FULL_SCREEN_EXCLUSIVE_INFO_EXT,
+                    pNext: win32_monitor.as_ref().map(|m| m as *const _ as *mut _)
+                                        .unwrap_or(ptr::null_mut()),
+                    fullScreenExclusive: full_screen_exclusive.to_vk(),
+                })
+            };
+
             let infos = vk::SwapchainCreateInfoKHR {
                 sType: vk::STRUCTURE_TYPE_SWAPCHAIN_CREATE_INFO_KHR,
-                pNext: ptr::null(),
+                pNext: full_screen_exclusive_info.as_ref().map(|i| i as *const _ as *const _)
+                                                 .unwrap_or(ptr::null()),
                 flags: 0,   // reserved
                 surface: surface.internal_object(),
                 minImageCount: num_images,
                 imageFormat: format as u32,
-                imageColorSpace: vk::COLOR_SPACE_SRGB_NONLINEAR_KHR,     // only available value
+                imageColorSpace: color_space as u32,
                 imageExtent: vk::Extent2D { width: dimensions[0], height: dimensions[1] },
                 imageArrayLayers: layers,
                 imageUsage: usage.to_usage_bits(),
@@ -212,9 +323,11 @@ impl Swapchain {
             swapchain: swapchain,
             semaphores_pool: Mutex::new(Vec::new()),
             images_semaphores: Mutex::new(Vec::new()),
+            present_semaphores: Mutex::new(Vec::new()),
             stale: Mutex::new(false),
             num_images: num_images,
             format: format,
+            color_space: color_space,
             dimensions: dimensions,
             layers: layers,
             usage: usage.clone(),
@@ -223,6 +336,8 @@ impl Swapchain {
             alpha: alpha,
             mode: mode,
             clipped: clipped,
+            full_screen_exclusive: full_screen_exclusive,
+            full_screen_exclusive_held: AtomicBool::new(false),
         });
 
         let images = unsafe {
@@ -247,8 +362,10 @@ impl Swapchain {
 
         {
             let mut semaphores = swapchain.images_semaphores.lock().unwrap();
+            let mut present_semaphores = swapchain.present_semaphores.lock().unwrap();
             for _ in 0 .. images.len() {
                 semaphores.push(None);
+                present_semaphores.push(None);
             }
         }
 
@@ -264,17 +381,25 @@ impl Swapchain {
     /// Tries to take ownership of an image in order to draw on it.
     ///
     /// The function returns the index of the image in the array of images that was returned
-    /// when creating the swapchain.
+    /// when creating the swapchain, and a boolean that is true if the swapchain is *suboptimal*.
+    ///
+    /// A suboptimal swapchain can still be used to present images to the surface, but no longer
+    /// matches its properties exactly, for example because the window has been resized. You
+    /// should draw and present the image as usual, and then recreate the swapchain.
     ///
     /// If you try to draw on an image without acquiring it first, the execution will block. (TODO
     /// behavior may change).
-    pub fn acquire_next_image(&self, timeout: Duration) -> Result<usize, AcquireError> {
+    pub fn acquire_next_image(&self, timeout: Duration) -> Result<(usize, bool), AcquireError> {
         unsafe {
             let stale = self.stale.lock().unwrap();
             if *stale {
                 return Err(AcquireError::OutOfDate);
             }
 
+            if self.device.is_lost() {
+                return Err(AcquireError::DeviceLost(DeviceLostError));
+            }
+
             let vk = self.device.pointers();
 
             let semaphore = self.semaphores_pool.lock().unwrap().remove(0);
@@ -283,23 +408,39 @@ impl Swapchain {
                                               .saturating_add(timeout.subsec_nanos() as u64);
 
             let mut out = mem::uninitialized();
-            let r = try!(check_errors(vk.AcquireNextImageKHR(self.device.internal_object(),
-                                                             self.swapchain, timeout_ns,
-                                                             semaphore.internal_object(), 0,     // TODO: timeout
-                                                             &mut out)));
-
-            let id = match r {
-                Success::Success => out as usize,
-                Success::Suboptimal => out as usize,        // TODO: give that info to the user
-                Success::NotReady => return Err(AcquireError::Timeout),
-                Success::Timeout => return Err(AcquireError::Timeout),
+            let r = self.device.check_errors(vk.AcquireNextImageKHR(self.device.internal_object(),
+                                                                    self.swapchain, timeout_ns,
+                                                                    semaphore.internal_object(),
+                                                                    0, &mut out));
+            // The semaphore isn't signaled if no image is acquired, so it goes back to the pool.
+            let r = match r {
+                Ok(r) => r,
+                Err(err) => {
+                    self.semaphores_pool.lock().unwrap().push(semaphore);
+                    return Err(self.on_error(err));
+                },
+            };
+
+            let (id, suboptimal) = match r {
+                Success::Success => (out as usize, false),
+                Success::Suboptimal => (out as usize, true),
+                Success::NotReady | Success::Timeout => {
+                    self.semaphores_pool.lock().unwrap().push(semaphore);
+                    return Err(AcquireError::Timeout);
+                },
                 s => panic!("unexpected success value: {:?}", s)
             };
 
+            // The previous presentation of this image is over, therefore the semaphores it was
+            // waiting upon can be reused or destroyed.
+            if let Some(previous) = self.present_semaphores.lock().unwrap()[id].take() {
+                self.semaphores_pool.lock().unwrap().push(previous.image);
+            }
+
             let mut images_semaphores = self.images_semaphores.lock().unwrap();
             images_semaphores[id] = Some(semaphore);
 
-            Ok(id)
+            Ok((id, suboptimal))
         }
     }
 
@@ -310,42 +451,272 @@ impl Swapchain {
     ///
     /// The actual behavior depends on the present mode that you passed when creating the
     /// swapchain.
-    pub fn present(&self, queue: &Arc<Queue>, index: usize) -> Result<(), PresentError> {
-        let vk = self.device.pointers();
+    ///
+    /// Returns true if the swapchain is suboptimal, in which case the image has been presented
+    /// but the swapchain should be recreated. See `acquire_next_image`.
+    pub fn present(&self, queue: &Arc<Queue>, index: usize) -> Result<bool, PresentError> {
+        let mut results = try!(Swapchain::present_impl(queue, Some((self, index, &[][..])), &[]));
+        results.remove(0)
+    }
 
-        let wait_semaphore = {
-            let mut images_semaphores = self.images_semaphores.lock().unwrap();
+    /// Same as `present`, but indicates that only the content of the image inside of `regions`
+    /// has changed since the last image that was presented.
+    ///
+    /// The implementation can use this information to only update these regions of the surface,
+    /// which saves power for applications that only redraw a small part of their window. The
+    /// content outside of the regions must be the same as in the previously presented image.
+    ///
+    /// If the `khr_incremental_present` extension isn't enabled on the device, the regions are
+    /// ignored and this function is the same as `present`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if a region is outside of the images of the swapchain, once transformed
+    ///   according to the transform of the swapchain, or if its layer is out of range.
+    ///
+    #[inline]
+    pub fn present_with_regions(&self, queue: &Arc<Queue>, index: usize,
+                                regions: &[RectangleLayer]) -> Result<bool, PresentError>
+    {
+        let mut results = try!(Swapchain::present_impl(queue, Some((self, index, regions)), &[]));
+        results.remove(0)
+    }
+
+    /// Presents images of multiple swapchains at once.
+    ///
+    /// Each element is a swapchain and the index of the image to present, as for `present`. This
+    /// is done with a single call to Vulkan, which lets the implementation present to multiple
+    /// windows or monitors in a synchronized way.
+    ///
+    /// Returns one result per swapchain, in the same order. The outer error is only returned for
+    /// errors that concern the whole queue, like `OomError` or `DeviceLost`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the list is empty.
+    /// - Panics if the same swapchain appears twice in the list.
+    /// - Panics if a swapchain doesn't belong to the same device as the queue.
+    /// - Panics if one of the images wasn't acquired.
+    ///
+    #[inline]
+    pub fn present_multiple<'a, I>(queue: &Arc<Queue>, swapchains: I)
+                                   -> Result<Vec<Result<bool, PresentError>>, PresentError>
+        where I: IntoIterator<Item = (&'a Swapchain, usize)>
+    {
+        Swapchain::present_impl(queue, swapchains.into_iter().map(|(sc, i)| (sc, i, &[][..])), &[])
+    }
+
+    /// Same as `present_multiple`, but also passes the regions of each image that have changed.
+    /// See `present_with_regions`.
+    ///
+    /// # Panic
+    ///
+    /// - Same panic reasons as `present_multiple` and `present_with_regions`.
+    ///
+    #[inline]
+    pub fn present_multiple_with_regions<'a, I>(queue: &Arc<Queue>, swapchains: I)
+                                                -> Result<Vec<Result<bool, PresentError>>,
+                                                          PresentError>
+        where I: IntoIterator<Item = (&'a Swapchain, usize, &'a [RectangleLayer])>
+    {
+        Swapchain::present_impl(queue, swapchains, &[])
+    }
+
+    // Presents images of multiple swapchains. An empty list of regions means that the whole image
+    // has changed. The presentation also waits upon `extra_wait_semaphores`, which are kept alive
+    // by the swapchains until the presented images are acquired again.
+    fn present_impl<'a, I>(queue: &Arc<Queue>, swapchains: I,
+                           extra_wait_semaphores: &[Arc<Semaphore>])
+                           -> Result<Vec<Result<bool, PresentError>>, PresentError>
+        where I: IntoIterator<Item = (&'a Swapchain, usize, &'a [RectangleLayer])>
+    {
+        let swapchains: SmallVec<[(&Swapchain, usize, &[RectangleLayer]); 4]> =
+            swapchains.into_iter().collect();
+        assert!(!swapchains.is_empty());
+
+        for (i, &(sc, _, regions)) in swapchains.iter().enumerate() {
+            assert!(swapchains[.. i].iter().all(|&(other, _, _)| {
+                        other as *const Swapchain != sc as *const Swapchain
+                    }), "The same swapchain can't be presented twice in one call");
+
+            for region in regions {
+                check_present_region(region, sc.dimensions, sc.layers, sc.transform);
+            }
+        }
+
+        let device = queue.device();
+        let vk = device.pointers();
+
+        if device.is_lost() {
+            return Err(PresentError::DeviceLost(DeviceLostError));
+        }
+
+        let wait_semaphores = swapchains.iter().map(|&(sc, index, _)| {
+            assert_eq!(&*sc.device as *const Device, &**device as *const Device);
+            let mut images_semaphores = sc.images_semaphores.lock().unwrap();
             images_semaphores[index].take().expect("Trying to present an image that was \
                                                     not acquired")
+        }).collect::<SmallVec<[_; 4]>>();
+
+        let raw_semaphores = wait_semaphores.iter().chain(extra_wait_semaphores.iter())
+                                            .map(|s| s.internal_object())
+                                            .collect::<SmallVec<[_; 4]>>();
+        let raw_swapchains = swapchains.iter().map(|&(sc, _, _)| sc.swapchain)
+                                       .collect::<SmallVec<[_; 4]>>();
+        let indices = swapchains.iter().map(|&(_, index, _)| index as u32)
+                                .collect::<SmallVec<[_; 4]>>();
+
+        // The regions are only a hint, and are ignored if the extension isn't enabled.
+        let use_regions = device.loaded_extensions().khr_incremental_present &&
+                          swapchains.iter().any(|&(_, _, r)| !r.is_empty());
+        let rectangles = if use_regions {
+            swapchains.iter().map(|&(sc, _, regions)| {
+                regions.iter().map(|r| {
+                    vk::RectLayerKHR {
+                        offset: vk::Offset2D { x: r.offset[0], y: r.offset[1] },
+                        extent: vk::Extent2D { width: r.extent[0], height: r.extent[1] },
+                        layer: r.layer,
+                    }
+                }).collect::<Vec<_>>()
+            }).collect::<SmallVec<[_; 4]>>()
+        } else {
+            SmallVec::new()
+        };
+        let present_regions = rectangles.iter().map(|rects| {
+            vk::PresentRegionKHR {
+                rectangleCount: rects.len() as u32,
+                pRectangles: if rects.is_empty() { ptr::null() } else { rects.as_ptr() },
+            }
+        }).collect::<SmallVec<[_; 4]>>();
+        let regions_infos = vk::PresentRegionsKHR {
+            sType: vk::STRUCTURE_TYPE_PRESENT_REGIONS_KHR,
+            pNext: ptr::null(),
+            swapchainCount: present_regions.len() as u32,
+            pRegions: present_regions.as_ptr(),
         };
 
-        // FIXME: the semaphore may be destroyed ; need to return it
-
-        unsafe {
-            let mut result = mem::uninitialized();
+        // Some drivers don't write the results, so they are initialized to success.
+        let mut results: SmallVec<[vk::Result; 4]> = swapchains.iter().map(|_| vk::SUCCESS)
+                                                                .collect();
 
+        let result = unsafe {
             let queue = queue.internal_object_guard();
-            let index = index as u32;
 
             let infos = vk::PresentInfoKHR {
                 sType: vk::STRUCTURE_TYPE_PRESENT_INFO_KHR,
-                pNext: ptr::null(),
-                waitSemaphoreCount: 1,
-                pWaitSemaphores: &wait_semaphore.internal_object(),
-                swapchainCount: 1,
-                pSwapchains: &self.swapchain,
-                pImageIndices: &index,
-                pResults: &mut result,
+                pNext: if use_regions { &regions_infos as *const _ as *const _ }
+                       else { ptr::null() },
+                waitSemaphoreCount: raw_semaphores.len() as u32,
+                pWaitSemaphores: raw_semaphores.as_ptr(),
+                swapchainCount: raw_swapchains.len() as u32,
+                pSwapchains: raw_swapchains.as_ptr(),
+                pImageIndices: indices.as_ptr(),
+                pResults: results.as_mut_ptr(),
             };
 
-            try!(check_errors(vk.QueuePresentKHR(*queue, &infos)));
-            //try!(check_errors(result));       // TODO: AMD driver doesn't seem to write the result
+            vk.QueuePresentKHR(*queue, &infos)
+        };
+
+        for (&(sc, index, _), semaphore) in swapchains.iter().zip(wait_semaphores.into_iter()) {
+            let mut present_semaphores = sc.present_semaphores.lock().unwrap();
+            debug_assert!(present_semaphores[index].is_none());
+            present_semaphores[index] = Some(PresentSemaphores {
+                image: semaphore,
+                extra: extra_wait_semaphores.iter().cloned().collect(),
+            });
+        }
+
+        match device.check_errors(result) {
+            Err(err @ Error::OutOfHostMemory) | Err(err @ Error::OutOfDeviceMemory) |
+            Err(err @ Error::DeviceLost) => return Err(PresentError::from(err)),
+            _ => ()
+        };
+
+        // With a single swapchain, the global result is the result of this swapchain.
+        if results.len() == 1 {
+            results[0] = result;
+        }
+
+        Ok(results.into_iter().zip(swapchains.iter()).map(|(r, &(sc, _, _))| {
+            match check_errors(r) {
+                Ok(Success::Suboptimal) => Ok(true),
+                Ok(_) => Ok(false),
+                Err(err) => Err(sc.on_error(err)),
+            }
+        }).collect())
+    }
+
+    // Updates the state of the swapchain after an error has been returned by Vulkan.
+    fn on_error<E>(&self, err: Error) -> E where E: From<Error> {
+        if let Error::FullScreenExclusiveLost = err {
+            self.full_screen_exclusive_held.store(false, Ordering::SeqCst);
+        }
+
+        E::from(err)
+    }
+
+    /// Acquires full-screen exclusive mode for the swapchain.
+    ///
+    /// The implementation can then present the images directly to the display, which usually
+    /// reduces the latency. The mode can be lost at any time, for example when the window loses
+    /// focus, in which case `acquire_next_image` and `present` return `FullScreenExclusiveLost`
+    /// and the mode must be acquired again.
+    ///
+    /// The swapchain must have been created with `FullScreenExclusive::AppControlled`.
+    pub fn acquire_full_screen_exclusive(&self) -> Result<(), FullScreenExclusiveError> {
+        match self.full_screen_exclusive {
+            FullScreenExclusive::AppControlled(_) => (),
+            _ => return Err(FullScreenExclusiveError::NotAppControlled),
+        };
+
+        if self.full_screen_exclusive_held.swap(true, Ordering::SeqCst) {
+            return Err(FullScreenExclusiveError::DoubleAcquire);
+        }
+
+        unsafe {
+            let vk = self.device.pointers();
+            let r = check_errors(vk.AcquireFullScreenExclusiveModeEXT(self.device.internal_object(),
+                                                                      self.swapchain));
+            if let Err(err) = r {
+                self.full_screen_exclusive_held.store(false, Ordering::SeqCst);
+                return Err(FullScreenExclusiveError::from(err));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Releases full-screen exclusive mode, after it has been acquired with
+    /// `acquire_full_screen_exclusive`.
+    pub fn release_full_screen_exclusive(&self) -> Result<(), FullScreenExclusiveError> {
+        match self.full_screen_exclusive {
+            FullScreenExclusive::AppControlled(_) => (),
+            _ => return Err(FullScreenExclusiveError::NotAppControlled),
+        };
+
+        if !self.full_screen_exclusive_held.swap(false, Ordering::SeqCst) {
+            return Err(FullScreenExclusiveError::NotAcquired);
+        }
+
+        unsafe {
+            let vk = self.device.pointers();
+            try!(check_errors(vk.ReleaseFullScreenExclusiveModeEXT(self.device.internal_object(),
+                                                                   self.swapchain)));
         }
 
-        self.semaphores_pool.lock().unwrap().push(wait_semaphore);
         Ok(())
     }
 
+    /// Returns true if full-screen exclusive mode is currently held by the swapchain.
+    ///
+    /// This only tracks the calls to `acquire_full_screen_exclusive` and
+    /// `release_full_screen_exclusive`. A loss of the mode is only noticed when
+    /// `acquire_next_image` or `present` returns `FullScreenExclusiveLost`.
+    #[inline]
+    pub fn is_full_screen_exclusive(&self) -> bool {
+        self.full_screen_exclusive_held.load(Ordering::SeqCst)
+    }
+
     /// Returns the number of images of the swapchain.
     ///
     /// See the documentation of `Swapchain::new`. 
@@ -354,6 +725,18 @@ impl Swapchain {
         self.num_images
     }
 
+    /// Returns the device this swapchain was created with.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    /// Returns the surface this swapchain was created with.
+    #[inline]
+    pub fn surface(&self) -> &Arc<Surface> {
+        &self.surface
+    }
+
     /// Returns the format of the images of the swapchain.
     ///
     /// See the documentation of `Swapchain::new`. 
@@ -362,6 +745,14 @@ impl Swapchain {
         self.format
     }
 
+    /// Returns the color space of the images of the swapchain.
+    ///
+    /// See the documentation of `Swapchain::new`.
+    #[inline]
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
     /// Returns the dimensions of the images of the swapchain.
     ///
     /// See the documentation of `Swapchain::new`. 
@@ -410,6 +801,14 @@ impl Swapchain {
         self.clipped
     }
 
+    /// Returns the full-screen exclusive mode that the swapchain was created with.
+    ///
+    /// See the documentation of `Swapchain::new`.
+    #[inline]
+    pub fn full_screen_exclusive(&self) -> FullScreenExclusive {
+        self.full_screen_exclusive
+    }
+
     /*/// Returns the semaphore that is going to be signalled when the image is going to be ready
     /// to be drawn upon.
     ///
@@ -433,6 +832,13 @@ impl Drop for Swapchain {
     #[inline]
     fn drop(&mut self) {
         unsafe {
+            // Presentations may still be waiting upon the semaphores that are destroyed with the
+            // swapchain. Errors are ignored, as there's nothing more we can do about them.
+            let pending = self.present_semaphores.lock().unwrap().iter().any(|s| s.is_some());
+            if pending {
+                let _ = self.device.wait_raw();
+            }
+
             let vk = self.device.pointers();
             vk.DestroySwapchainKHR(self.device.internal_object(), self.swapchain, ptr::null());
             self.surface.flag().store(false, Ordering::Release);
@@ -440,6 +846,142 @@ impl Drop for Swapchain {
     }
 }
 
+// Semaphores that a presentation of an image waits upon.
+struct PresentSemaphores {
+    // Semaphore of the image, which goes back to the pool of the swapchain once the presentation
+    // is over.
+    image: Arc<Semaphore>,
+    // Additional semaphores passed by the caller.
+    extra: SmallVec<[Arc<Semaphore>; 4]>,
+}
+
+/// Acquires the next image of a swapchain, and returns a future that represents the moment when
+/// the image is available.
+///
+/// Returns the index of the image, a boolean that is true if the swapchain is suboptimal, and the
+/// future. See `Swapchain::acquire_next_image`.
+///
+/// The future can be used to chain the commands that draw on the image and the presentation
+/// with the `GpuFuture` trait.
+#[inline]
+pub fn acquire(swapchain: &Arc<Swapchain>, timeout: Duration)
+               -> Result<(usize, bool, SwapchainAcquireFuture), AcquireError>
+{
+    let (image_num, suboptimal) = try!(swapchain.acquire_next_image(timeout));
+
+    let future = SwapchainAcquireFuture {
+        swapchain: swapchain.clone(),
+        image_num: image_num,
+    };
+
+    Ok((image_num, suboptimal, future))
+}
+
+/// A future that represents the acquisition of an image of a swapchain. See `acquire`.
+pub struct SwapchainAcquireFuture {
+    swapchain: Arc<Swapchain>,
+    image_num: usize,
+}
+
+impl SwapchainAcquireFuture {
+    /// Returns the index of the image that has been acquired.
+    #[inline]
+    pub fn image_num(&self) -> usize {
+        self.image_num
+    }
+
+    /// Returns the swapchain the image belongs to.
+    #[inline]
+    pub fn swapchain(&self) -> &Arc<Swapchain> {
+        &self.swapchain
+    }
+}
+
+unsafe impl GpuFuture for SwapchainAcquireFuture {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.swapchain.device
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<&Arc<Queue>> {
+        None
+    }
+
+    #[inline]
+    unsafe fn take_semaphores(&self) -> SmallVec<[Arc<Semaphore>; 4]> {
+        // The semaphore that is signaled by the acquisition is stored in the swapchain, and is
+        // automatically waited upon by the first command buffer that uses the image or by the
+        // presentation.
+        SmallVec::new()
+    }
+}
+
+/// A future that represents the presentation of an image of a swapchain after another future.
+/// See `GpuFuture::then_swapchain_present`.
+pub struct PresentFuture<F> {
+    previous: F,
+    queue: Arc<Queue>,
+    suboptimal: bool,
+}
+
+impl<F> PresentFuture<F> where F: GpuFuture {
+    // Only meant to be called by `GpuFuture::then_swapchain_present`, which is the public way to
+    // build a `PresentFuture`.
+    #[doc(hidden)]
+    pub fn new(previous: F, queue: &Arc<Queue>, swapchain: &Arc<Swapchain>, image_num: usize)
+               -> Result<PresentFuture<F>, PresentError>
+    {
+        assert_eq!(&**previous.device() as *const Device, &**queue.device() as *const Device);
+
+        // The swapchain keeps the semaphores alive until the presentation is over.
+        let semaphores = unsafe { previous.take_semaphores() };
+        let mut results = try!(Swapchain::present_impl(queue,
+                                                       Some((&**swapchain, image_num, &[][..])),
+                                                       &semaphores));
+        let suboptimal = try!(results.remove(0));
+
+        Ok(PresentFuture {
+            previous: previous,
+            queue: queue.clone(),
+            suboptimal: suboptimal,
+        })
+    }
+}
+
+impl<F> PresentFuture<F> {
+    /// Returns true if the swapchain was suboptimal when presenting. See `Swapchain::present`.
+    #[inline]
+    pub fn suboptimal(&self) -> bool {
+        self.suboptimal
+    }
+
+    /// Returns the future this one was built from.
+    #[inline]
+    pub fn previous(&self) -> &F {
+        &self.previous
+    }
+}
+
+unsafe impl<F> GpuFuture for PresentFuture<F> where F: GpuFuture {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.queue.device()
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<&Arc<Queue>> {
+        Some(&self.queue)
+    }
+
+    #[inline]
+    unsafe fn take_semaphores(&self) -> SmallVec<[Arc<Semaphore>; 4]> {
+        // Presenting can't signal semaphores. Operations submitted afterwards to the same queue
+        // start after the presentation.
+        SmallVec::new()
+    }
+}
+
 /// Error that can happen when calling `acquire_next_image`.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(u32)]
@@ -448,7 +990,7 @@ pub enum AcquireError {
     OomError(OomError),
 
     /// The connection to the device has been lost.
-    DeviceLost,
+    DeviceLost(DeviceLostError),
 
     /// The timeout of the function has been reached before an image was available.
     Timeout,
@@ -459,6 +1001,9 @@ pub enum AcquireError {
     /// The surface has changed in a way that makes the swapchain unusable. You must query the
     /// surface's new properties and recreate a new swapchain if you want to continue drawing.
     OutOfDate,
+
+    /// The swapchain has lost full-screen exclusive mode, which must be acquired again.
+    FullScreenExclusiveLost,
 }
 
 impl error::Error for AcquireError {
@@ -466,10 +1011,13 @@ impl error::Error for AcquireError {
     fn description(&self) -> &str {
         match *self {
             AcquireError::OomError(_) => "not enough memory",
-            AcquireError::DeviceLost => "the connection to the device has been lost",
+            AcquireError::DeviceLost(_) => "the connection to the device has been lost",
             AcquireError::Timeout => "no image is available for acquiring yet",
             AcquireError::SurfaceLost => "the surface of this swapchain is no longer valid",
             AcquireError::OutOfDate => "the swapchain needs to be recreated",
+            AcquireError::FullScreenExclusiveLost => {
+                "the swapchain has lost full-screen exclusive mode"
+            },
         }
     }
 
@@ -477,6 +1025,7 @@ impl error::Error for AcquireError {
     fn cause(&self) -> Option<&error::Error> {
         match *self {
             AcquireError::OomError(ref err) => Some(err),
+            AcquireError::DeviceLost(ref err) => Some(err),
             _ => None
         }
     }
@@ -489,15 +1038,30 @@ impl fmt::Display for AcquireError {
     }
 }
 
+impl VulkanError for AcquireError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            AcquireError::OomError(ref err) => err.error_code(),
+            AcquireError::DeviceLost(ref err) => err.error_code(),
+            AcquireError::SurfaceLost => Some(Error::SurfaceLost),
+            AcquireError::OutOfDate => Some(Error::OutOfDate),
+            AcquireError::FullScreenExclusiveLost => Some(Error::FullScreenExclusiveLost),
+            _ => None
+        }
+    }
+}
+
 impl From<Error> for AcquireError {
     #[inline]
     fn from(err: Error) -> AcquireError {
         match err {
             err @ Error::OutOfHostMemory => AcquireError::OomError(OomError::from(err)),
             err @ Error::OutOfDeviceMemory => AcquireError::OomError(OomError::from(err)),
-            Error::DeviceLost => AcquireError::DeviceLost,
+            Error::DeviceLost => AcquireError::DeviceLost(DeviceLostError),
             Error::SurfaceLost => AcquireError::SurfaceLost,
             Error::OutOfDate => AcquireError::OutOfDate,
+            Error::FullScreenExclusiveLost => AcquireError::FullScreenExclusiveLost,
             _ => panic!("unexpected error: {:?}", err)
         }
     }
@@ -511,7 +1075,7 @@ pub enum PresentError {
     OomError(OomError),
 
     /// The connection to the device has been lost.
-    DeviceLost,
+    DeviceLost(DeviceLostError),
 
     /// The surface is no longer accessible and must be recreated.
     SurfaceLost,
@@ -519,6 +1083,9 @@ pub enum PresentError {
     /// The surface has changed in a way that makes the swapchain unusable. You must query the
     /// surface's new properties and recreate a new swapchain if you want to continue drawing.
     OutOfDate,
+
+    /// The swapchain has lost full-screen exclusive mode, which must be acquired again.
+    FullScreenExclusiveLost,
 }
 
 impl error::Error for PresentError {
@@ -526,9 +1093,12 @@ impl error::Error for PresentError {
     fn description(&self) -> &str {
         match *self {
             PresentError::OomError(_) => "not enough memory",
-            PresentError::DeviceLost => "the connection to the device has been lost",
+            PresentError::DeviceLost(_) => "the connection to the device has been lost",
             PresentError::SurfaceLost => "the surface of this swapchain is no longer valid",
             PresentError::OutOfDate => "the swapchain needs to be recreated",
+            PresentError::FullScreenExclusiveLost => {
+                "the swapchain has lost full-screen exclusive mode"
+            },
         }
     }
 
@@ -536,6 +1106,7 @@ impl error::Error for PresentError {
     fn cause(&self) -> Option<&error::Error> {
         match *self {
             PresentError::OomError(ref err) => Some(err),
+            PresentError::DeviceLost(ref err) => Some(err),
             _ => None
         }
     }
@@ -548,16 +1119,301 @@ impl fmt::Display for PresentError {
     }
 }
 
+impl VulkanError for PresentError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            PresentError::OomError(ref err) => err.error_code(),
+            PresentError::DeviceLost(ref err) => err.error_code(),
+            PresentError::SurfaceLost => Some(Error::SurfaceLost),
+            PresentError::OutOfDate => Some(Error::OutOfDate),
+            PresentError::FullScreenExclusiveLost => Some(Error::FullScreenExclusiveLost),
+        }
+    }
+}
+
 impl From<Error> for PresentError {
     #[inline]
     fn from(err: Error) -> PresentError {
         match err {
             err @ Error::OutOfHostMemory => PresentError::OomError(OomError::from(err)),
             err @ Error::OutOfDeviceMemory => PresentError::OomError(OomError::from(err)),
-            Error::DeviceLost => PresentError::DeviceLost,
+            Error::DeviceLost => PresentError::DeviceLost(DeviceLostError),
             Error::SurfaceLost => PresentError::SurfaceLost,
             Error::OutOfDate => PresentError::OutOfDate,
+            Error::FullScreenExclusiveLost => PresentError::FullScreenExclusiveLost,
+            _ => panic!("unexpected error: {:?}", err)
+        }
+    }
+}
+
+// Panics if `region` isn't inside the images of a swapchain with the given dimensions, number
+// of layers and transform.
+fn check_present_region(region: &RectangleLayer, dimensions: [u32; 2], layers: u32,
+                        transform: SurfaceTransform)
+{
+    assert!(region.layer < layers,
+            "The layer {} of a present region is out of range of the swapchain", region.layer);
+
+    // The rectangle must be inside the images once it is transformed like them.
+    let extent = match transform {
+        SurfaceTransform::Rotate90 | SurfaceTransform::Rotate270 |
+        SurfaceTransform::HorizontalMirrorRotate90 |
+        SurfaceTransform::HorizontalMirrorRotate270 => [dimensions[1], dimensions[0]],
+        _ => dimensions,
+    };
+
+    for i in 0 .. 2 {
+        assert!(region.offset[i] >= 0 &&
+                region.offset[i] as u64 + region.extent[i] as u64 <= extent[i] as u64,
+                "The present region {:?} is outside of the images of the swapchain", region);
+    }
+}
+
+/// Rectangle of an image of a swapchain whose content has changed. Used with
+/// `Swapchain::present_with_regions`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RectangleLayer {
+    /// Coordinates in pixels of the top-left corner of the rectangle.
+    pub offset: [i32; 2],
+    /// Dimensions in pixels of the rectangle.
+    pub extent: [u32; 2],
+    /// Array layer of the image that the rectangle is in. Always 0 unless the swapchain has
+    /// multiple layers.
+    pub layer: u32,
+}
+
+/// Whether the swapchain can get exclusive access to the display.
+///
+/// All the values other than `Default` require the `ext_full_screen_exclusive` extension.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FullScreenExclusive {
+    /// The implementation decides whether to use full-screen exclusive mode.
+    Default,
+    /// The implementation can use full-screen exclusive mode when it thinks it's appropriate.
+    Allowed,
+    /// The implementation must never use full-screen exclusive mode.
+    Disallowed,
+    /// Full-screen exclusive mode is only used between calls to
+    /// `Swapchain::acquire_full_screen_exclusive` and `Swapchain::release_full_screen_exclusive`.
+    ///
+    /// If the surface was created from a Win32 window, the monitor on which the window will be
+    /// full-screen must be passed.
+    AppControlled(Option<Win32Monitor>),
+}
+
+impl FullScreenExclusive {
+    #[inline]
+    fn to_vk(&self) -> vk::FullScreenExclusiveEXT {
+        match *self {
+            FullScreenExclusive::Default => vk::FULL_SCREEN_EXCLUSIVE_DEFAULT_EXT,
+            FullScreenExclusive::Allowed => vk::FULL_SCREEN_EXCLUSIVE_ALLOWED_EXT,
+            FullScreenExclusive::Disallowed => vk::FULL_SCREEN_EXCLUSIVE_DISALLOWED_EXT,
+            FullScreenExclusive::AppControlled(_) => {
+                vk::FULL_SCREEN_EXCLUSIVE_APPLICATION_CONTROLLED_EXT
+            },
+        }
+    }
+}
+
+/// Handle to a Win32 monitor (an `HMONITOR`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Win32Monitor(usize);
+
+impl Win32Monitor {
+    /// Wraps a raw `HMONITOR`.
+    ///
+    /// # Safety
+    ///
+    /// - `hmonitor` must be a valid monitor handle for as long as the swapchains that are
+    ///   created with it are alive.
+    ///
+    #[inline]
+    pub unsafe fn new<T>(hmonitor: *const T) -> Win32Monitor {
+        Win32Monitor(hmonitor as usize)
+    }
+}
+
+/// Error that can happen when recreating a swapchain.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SwapchainCreationError {
+    /// Not enough memory.
+    OomError(OomError),
+
+    /// The dimensions are not supported by the surface, for example because its window is
+    /// minimized.
+    UnsupportedDimensions,
+}
+
+impl error::Error for SwapchainCreationError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            SwapchainCreationError::OomError(_) => "not enough memory",
+            SwapchainCreationError::UnsupportedDimensions => {
+                "the dimensions are not supported by the surface"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            SwapchainCreationError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for SwapchainCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for SwapchainCreationError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            SwapchainCreationError::OomError(ref err) => err.error_code(),
+            _ => None
+        }
+    }
+}
+
+impl From<OomError> for SwapchainCreationError {
+    #[inline]
+    fn from(err: OomError) -> SwapchainCreationError {
+        SwapchainCreationError::OomError(err)
+    }
+}
+
+/// Error that can happen when acquiring or releasing full-screen exclusive mode.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FullScreenExclusiveError {
+    /// Not enough memory.
+    OomError(OomError),
+
+    /// The surface is no longer accessible and must be recreated.
+    SurfaceLost,
+
+    /// Full-screen exclusive mode couldn't be acquired for an implementation-specific reason.
+    InitializationFailed,
+
+    /// The swapchain wasn't created with `FullScreenExclusive::AppControlled`.
+    NotAppControlled,
+
+    /// Full-screen exclusive mode is already held by the swapchain.
+    DoubleAcquire,
+
+    /// Full-screen exclusive mode isn't held by the swapchain.
+    NotAcquired,
+}
+
+impl error::Error for FullScreenExclusiveError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            FullScreenExclusiveError::OomError(_) => "not enough memory",
+            FullScreenExclusiveError::SurfaceLost => {
+                "the surface of this swapchain is no longer valid"
+            },
+            FullScreenExclusiveError::InitializationFailed => {
+                "full-screen exclusive mode couldn't be acquired"
+            },
+            FullScreenExclusiveError::NotAppControlled => {
+                "the swapchain wasn't created with application-controlled full-screen exclusive \
+                 mode"
+            },
+            FullScreenExclusiveError::DoubleAcquire => {
+                "full-screen exclusive mode is already held by the swapchain"
+            },
+            FullScreenExclusiveError::NotAcquired => {
+                "full-screen exclusive mode isn't held by the swapchain"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            FullScreenExclusiveError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for FullScreenExclusiveError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for FullScreenExclusiveError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            FullScreenExclusiveError::OomError(ref err) => err.error_code(),
+            FullScreenExclusiveError::SurfaceLost => Some(Error::SurfaceLost),
+            FullScreenExclusiveError::InitializationFailed => Some(Error::InitializationFailed),
+            _ => None
+        }
+    }
+}
+
+impl From<Error> for FullScreenExclusiveError {
+    #[inline]
+    fn from(err: Error) -> FullScreenExclusiveError {
+        match err {
+            err @ Error::OutOfHostMemory => FullScreenExclusiveError::OomError(OomError::from(err)),
+            err @ Error::OutOfDeviceMemory => {
+                FullScreenExclusiveError::OomError(OomError::from(err))
+            },
+            Error::SurfaceLost => FullScreenExclusiveError::SurfaceLost,
+            Error::InitializationFailed => FullScreenExclusiveError::InitializationFailed,
             _ => panic!("unexpected error: {:?}", err)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use swapchain::SurfaceTransform;
+    use super::RectangleLayer;
+    use super::check_present_region;
+
+    #[test]
+    fn present_region_inside() {
+        let region = RectangleLayer { offset: [16, 0], extent: [48, 32], layer: 1 };
+        check_present_region(&region, [64, 32], 2, SurfaceTransform::Identity);
+    }
+
+    #[test]
+    #[should_panic = "is outside of the images of the swapchain"]
+    fn present_region_outside() {
+        let region = RectangleLayer { offset: [16, 0], extent: [49, 32], layer: 0 };
+        check_present_region(&region, [64, 32], 1, SurfaceTransform::Identity);
+    }
+
+    #[test]
+    #[should_panic = "is outside of the images of the swapchain"]
+    fn present_region_negative_offset() {
+        let region = RectangleLayer { offset: [-1, 0], extent: [8, 8], layer: 0 };
+        check_present_region(&region, [64, 32], 1, SurfaceTransform::Identity);
+    }
+
+    #[test]
+    fn present_region_rotated() {
+        let region = RectangleLayer { offset: [0, 0], extent: [32, 64], layer: 0 };
+        check_present_region(&region, [64, 32], 1, SurfaceTransform::Rotate90);
+    }
+
+    #[test]
+    #[should_panic = "out of range of the swapchain"]
+    fn present_region_layer_out_of_range() {
+        let region = RectangleLayer { offset: [0, 0], extent: [8, 8], layer: 1 };
+        check_present_region(&region, [64, 32], 1, SurfaceTransform::Identity);
+    }
+}
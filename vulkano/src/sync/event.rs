@@ -10,6 +10,7 @@
 use std::mem;
 use std::ptr;
 use std::sync::Arc;
+use std::sync::Mutex;
 
 use device::Device;
 use OomError;
@@ -21,6 +22,10 @@ use vk;
 
 /// Used to block the GPU execution until an event on the CPU occurs.
 ///
+/// A command buffer can wait for an event with `UnsafeCommandBufferBuilder::wait_events`. The
+/// CPU can then unblock it at any time by calling `set`, without having to submit anything to a
+/// queue. The event can be shared with an `Arc`, as all the methods take `&self`.
+///
 /// Note that Vulkan implementations may have limits on how long a command buffer will wait for an
 /// event to be signaled, in order to avoid interfering with progress of other clients of the GPU.
 /// If the event isn't signaled within these limits, results are undefined and may include
@@ -31,6 +36,8 @@ pub struct Event {
     event: vk::Event,
     // The device.
     device: Arc<Device>,
+    // Setting and resetting an event from the host must be externally synchronized.
+    host_lock: Mutex<()>,
 }
 
 impl Event {
@@ -56,6 +63,7 @@ impl Event {
         Ok(Event {
             device: device.clone(),
             event: event,
+            host_lock: Mutex::new(()),
         })
     }
     
@@ -70,6 +78,12 @@ impl Event {
         Arc::new(Event::raw(device).unwrap())
     }
 
+    /// Returns the device that owns this event.
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
     /// Returns true if the event is signaled.
     #[inline]
     pub fn signaled(&self) -> Result<bool, OomError> {
@@ -87,8 +101,9 @@ impl Event {
 
     /// See the docs of set().
     #[inline]
-    pub fn set_raw(&mut self) -> Result<(), OomError> {
+    pub fn set_raw(&self) -> Result<(), OomError> {
         unsafe {
+            let _lock = self.host_lock.lock().unwrap();
             let vk = self.device.pointers();
             try!(check_errors(vk.SetEvent(self.device.internal_object(), self.event)));
             Ok(())
@@ -104,14 +119,15 @@ impl Event {
     /// - Panics if the device or host ran out of memory.
     ///
     #[inline]
-    pub fn set(&mut self) {
+    pub fn set(&self) {
         self.set_raw().unwrap();
     }
 
     /// See the docs of reset().
     #[inline]
-    pub fn reset_raw(&mut self) -> Result<(), OomError> {
+    pub fn reset_raw(&self) -> Result<(), OomError> {
         unsafe {
+            let _lock = self.host_lock.lock().unwrap();
             let vk = self.device.pointers();
             try!(check_errors(vk.ResetEvent(self.device.internal_object(), self.event)));
             Ok(())
@@ -125,7 +141,7 @@ impl Event {
     /// - Panics if the device or host ran out of memory.
     ///
     #[inline]
-    pub fn reset(&mut self) {
+    pub fn reset(&self) {
         self.reset_raw().unwrap();
     }
 }
@@ -182,4 +198,17 @@ mod tests {
         Arc::get_mut(&mut event).unwrap().reset();
         assert!(!event.signaled().unwrap());
     }
+
+    #[test]
+    fn event_set_shared() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let event = Event::new(&device);
+        let shared = event.clone();
+        shared.set();
+        assert!(event.signaled().unwrap());
+
+        shared.reset();
+        assert!(!event.signaled().unwrap());
+    }
 }
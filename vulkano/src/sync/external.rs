@@ -0,0 +1,184 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use instance::DeviceExtensions;
+use Error;
+use OomError;
+use VulkanError;
+use vk;
+
+/// Type of handle that is used to share a semaphore with other APIs or other processes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ExternalSemaphoreHandleType {
+    /// POSIX file descriptor that is only meaningful to Vulkan implementations and to other
+    /// APIs that use the same driver. Refers to the semaphore itself.
+    ///
+    /// Requires the `khr_external_semaphore_fd` extension.
+    OpaqueFd,
+    /// Linux sync file descriptor. Refers to the payload of the semaphore at the time it was
+    /// exported, and can only be imported temporarily.
+    ///
+    /// Requires the `khr_external_semaphore_fd` extension.
+    SyncFd,
+}
+
+impl ExternalSemaphoreHandleType {
+    /// Returns true if importing a handle of this type can only replace the payload of the
+    /// semaphore temporarily.
+    #[inline]
+    pub fn requires_temporary_import(&self) -> bool {
+        match *self {
+            ExternalSemaphoreHandleType::OpaqueFd => false,
+            ExternalSemaphoreHandleType::SyncFd => true,
+        }
+    }
+
+    /// Returns true if the extensions required to use this handle type are in the list.
+    #[inline]
+    pub fn is_supported_by(&self, extensions: &DeviceExtensions) -> bool {
+        extensions.khr_external_semaphore && extensions.khr_external_semaphore_fd
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn to_bits(&self) -> vk::ExternalSemaphoreHandleTypeFlagBitsKHR {
+        match *self {
+            ExternalSemaphoreHandleType::OpaqueFd => {
+                vk::EXTERNAL_SEMAPHORE_HANDLE_TYPE_OPAQUE_FD_BIT_KHR
+            },
+            ExternalSemaphoreHandleType::SyncFd => {
+                vk::EXTERNAL_SEMAPHORE_HANDLE_TYPE_SYNC_FD_BIT_KHR
+            },
+        }
+    }
+}
+
+/// Type of handle that is used to share a fence with other APIs or other processes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ExternalFenceHandleType {
+    /// POSIX file descriptor that is only meaningful to Vulkan implementations and to other
+    /// APIs that use the same driver. Refers to the fence itself.
+    ///
+    /// Requires the `khr_external_fence_fd` extension.
+    OpaqueFd,
+    /// Linux sync file descriptor. Refers to the payload of the fence at the time it was
+    /// exported, and can only be imported temporarily.
+    ///
+    /// Requires the `khr_external_fence_fd` extension.
+    SyncFd,
+}
+
+impl ExternalFenceHandleType {
+    /// Returns true if importing a handle of this type can only replace the payload of the
+    /// fence temporarily.
+    #[inline]
+    pub fn requires_temporary_import(&self) -> bool {
+        match *self {
+            ExternalFenceHandleType::OpaqueFd => false,
+            ExternalFenceHandleType::SyncFd => true,
+        }
+    }
+
+    /// Returns true if the extensions required to use this handle type are in the list.
+    #[inline]
+    pub fn is_supported_by(&self, extensions: &DeviceExtensions) -> bool {
+        extensions.khr_external_fence && extensions.khr_external_fence_fd
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn to_bits(&self) -> vk::ExternalFenceHandleTypeFlagBitsKHR {
+        match *self {
+            ExternalFenceHandleType::OpaqueFd => vk::EXTERNAL_FENCE_HANDLE_TYPE_OPAQUE_FD_BIT_KHR,
+            ExternalFenceHandleType::SyncFd => vk::EXTERNAL_FENCE_HANDLE_TYPE_SYNC_FD_BIT_KHR,
+        }
+    }
+}
+
+/// Error that can happen when importing or exporting a semaphore or a fence.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExternalSyncError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The extensions required by the handle type are not enabled on the device.
+    MissingExtension,
+    /// The object wasn't created as exportable with this handle type.
+    NotExportable,
+    /// The handle is invalid or isn't compatible with the object.
+    InvalidExternalHandle,
+    /// The implementation can't create more handles of this type.
+    TooManyObjects,
+}
+
+impl error::Error for ExternalSyncError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            ExternalSyncError::OomError(_) => "not enough memory available",
+            ExternalSyncError::MissingExtension => {
+                "the extensions required by the handle type are not enabled"
+            },
+            ExternalSyncError::NotExportable => {
+                "the object can't be exported with this handle type"
+            },
+            ExternalSyncError::InvalidExternalHandle => "the external handle is invalid",
+            ExternalSyncError::TooManyObjects => "too many handles of this type have been created",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ExternalSyncError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for ExternalSyncError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for ExternalSyncError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            ExternalSyncError::OomError(ref err) => err.error_code(),
+            ExternalSyncError::InvalidExternalHandle => Some(Error::InvalidExternalHandle),
+            ExternalSyncError::TooManyObjects => Some(Error::TooManyObjects),
+            _ => None
+        }
+    }
+}
+
+impl From<OomError> for ExternalSyncError {
+    #[inline]
+    fn from(err: OomError) -> ExternalSyncError {
+        ExternalSyncError::OomError(err)
+    }
+}
+
+impl From<Error> for ExternalSyncError {
+    #[inline]
+    fn from(err: Error) -> ExternalSyncError {
+        match err {
+            err @ Error::OutOfHostMemory => ExternalSyncError::OomError(OomError::from(err)),
+            err @ Error::OutOfDeviceMemory => ExternalSyncError::OomError(OomError::from(err)),
+            Error::InvalidExternalHandle => ExternalSyncError::InvalidExternalHandle,
+            Error::TooManyObjects => ExternalSyncError::TooManyObjects,
+            _ => panic!("unexpected error: {:?}", err)
+        }
+    }
+}
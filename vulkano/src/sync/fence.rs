@@ -10,6 +10,7 @@
 use std::error;
 use std::fmt;
 use std::mem;
+use std::os::raw::c_int;
 use std::ptr;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
@@ -18,10 +19,14 @@ use std::time::Duration;
 use smallvec::SmallVec;
 
 use device::Device;
+use device::DeviceLostError;
+use sync::ExternalFenceHandleType;
+use sync::ExternalSyncError;
 use Error;
 use OomError;
 use SafeDeref;
 use Success;
+use VulkanError;
 use VulkanObject;
 use VulkanPointers;
 use check_errors;
@@ -42,13 +47,16 @@ pub struct Fence<D = Arc<Device>> where D: SafeDeref<Target = Device> {
     // This variable exists so that we don't need to call `vkGetFenceStatus` or `vkWaitForFences`
     // multiple times.
     signaled: AtomicBool,
+
+    // Handle types the fence can be exported to.
+    export_handle_types: vk::ExternalFenceHandleTypeFlagsKHR,
 }
 
 impl<D> Fence<D> where D: SafeDeref<Target = Device> {
     /// See the docs of new().
     #[inline]
     pub fn raw(device: D) -> Result<Fence<D>, OomError> {
-        Fence::new_impl(device, false)
+        Fence::new_impl(device, false, None)
     }
 
     /// Builds a new fence.
@@ -65,7 +73,7 @@ impl<D> Fence<D> where D: SafeDeref<Target = Device> {
     /// See the docs of signaled().
     #[inline]
     pub fn signaled_raw(device: D) -> Result<Fence<D>, OomError> {
-        Fence::new_impl(device, true)
+        Fence::new_impl(device, true, None)
     }
 
     /// Builds a new fence already in the "signaled" state.
@@ -79,11 +87,38 @@ impl<D> Fence<D> where D: SafeDeref<Target = Device> {
         Arc::new(Fence::signaled_raw(device).unwrap())
     }
 
-    fn new_impl(device: D, signaled: bool) -> Result<Fence<D>, OomError> {
+    /// Builds a new fence that can later be exported with `export_fd` in order to be shared
+    /// with another API or another process.
+    #[inline]
+    pub fn exportable(device: D, signaled: bool, handle_type: ExternalFenceHandleType)
+                      -> Result<Fence<D>, ExternalSyncError>
+    {
+        if !handle_type.is_supported_by(device.loaded_extensions()) {
+            return Err(ExternalSyncError::MissingExtension);
+        }
+
+        Ok(try!(Fence::new_impl(device, signaled, Some(handle_type))))
+    }
+
+    fn new_impl(device: D, signaled: bool, export: Option<ExternalFenceHandleType>)
+                -> Result<Fence<D>, OomError>
+    {
+        let export_handle_types = export.map(|ty| ty.to_bits()).unwrap_or(0);
+
         let fence = unsafe {
+            let export_infos = vk::ExportFenceCreateInfoKHR {
+                sType: vk::STRUCTURE_TYPE_EXPORT_FENCE_CREATE_INFO_KHR,
+                pNext: ptr::null(),
+                handleTypes: export_handle_types,
+            };
+
             let infos = vk::FenceCreateInfo {
                 sType: vk::STRUCTURE_TYPE_FENCE_CREATE_INFO,
-                pNext: ptr::null(),
+                pNext: if export.is_some() {
+                    &export_infos as *const vk::ExportFenceCreateInfoKHR as *const _
+                } else {
+                    ptr::null()
+                },
                 flags: if signaled { vk::FENCE_CREATE_SIGNALED_BIT } else { 0 },
             };
 
@@ -98,12 +133,104 @@ impl<D> Fence<D> where D: SafeDeref<Target = Device> {
             fence: fence,
             device: device,
             signaled: AtomicBool::new(signaled),
+            export_handle_types: export_handle_types,
         })
     }
 
-    /// Returns true if the fence is signaled.
+    /// Exports the fence as a file descriptor, in order to share it with another API or another
+    /// process.
+    ///
+    /// The fence must have been created with `exportable` with the same handle type. Each call
+    /// returns a new file descriptor, which you are responsible for closing.
+    ///
+    /// Exporting a `SyncFd` handle resets the fence.
+    ///
+    /// # Safety
+    ///
+    /// - If `handle_type` is `SyncFd`, the fence must be signaled or have a pending signal
+    ///   operation.
+    ///
+    pub unsafe fn export_fd(&self, handle_type: ExternalFenceHandleType)
+                            -> Result<c_int, ExternalSyncError>
+    {
+        if (self.export_handle_types & handle_type.to_bits()) == 0 {
+            return Err(ExternalSyncError::NotExportable);
+        }
+
+        let vk = self.device.pointers();
+
+        let infos = vk::FenceGetFdInfoKHR {
+            sType: vk::STRUCTURE_TYPE_FENCE_GET_FD_INFO_KHR,
+            pNext: ptr::null(),
+            fence: self.fence,
+            handleType: handle_type.to_bits(),
+        };
+
+        let mut output = mem::uninitialized();
+        try!(check_errors(vk.GetFenceFdKHR(self.device.internal_object(), &infos,
+                                           &mut output)));
+
+        if handle_type == ExternalFenceHandleType::SyncFd {
+            self.signaled.store(false, Ordering::Relaxed);
+        }
+
+        Ok(output)
+    }
+
+    /// Replaces the payload of the fence with the one of a file descriptor that was exported by
+    /// another API or another process.
+    ///
+    /// If `temporary` is true, the imported payload is only used until the fence is reset, after
+    /// which the fence goes back to its original payload.
+    ///
+    /// On success, the ownership of the file descriptor is transferred to the Vulkan
+    /// implementation and you must not use or close it anymore.
+    ///
+    /// # Safety
+    ///
+    /// - The fence must not be in use by the GPU.
+    /// - The file descriptor must have been exported with the same handle type from a
+    ///   compatible implementation.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `temporary` is false and the handle type requires a temporary import.
+    ///
+    pub unsafe fn import_fd(&self, handle_type: ExternalFenceHandleType, fd: c_int,
+                            temporary: bool) -> Result<(), ExternalSyncError>
+    {
+        assert!(temporary || !handle_type.requires_temporary_import());
+
+        if !handle_type.is_supported_by(self.device.loaded_extensions()) {
+            return Err(ExternalSyncError::MissingExtension);
+        }
+
+        let infos = vk::ImportFenceFdInfoKHR {
+            sType: vk::STRUCTURE_TYPE_IMPORT_FENCE_FD_INFO_KHR,
+            pNext: ptr::null(),
+            fence: self.fence,
+            flags: if temporary { vk::FENCE_IMPORT_TEMPORARY_BIT_KHR } else { 0 },
+            handleType: handle_type.to_bits(),
+            fd: fd,
+        };
+
+        let vk = self.device.pointers();
+        try!(check_errors(vk.ImportFenceFdKHR(self.device.internal_object(), &infos)));
+
+        // The state of the fence is now the one of the imported payload.
+        self.signaled.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Returns true if the fence is signaled. Same as `is_signaled`.
     #[inline]
     pub fn ready(&self) -> Result<bool, OomError> {
+        self.is_signaled()
+    }
+
+    /// Returns true if the fence is signaled, without blocking.
+    #[inline]
+    pub fn is_signaled(&self) -> Result<bool, OomError> {
         unsafe {
             if self.signaled.load(Ordering::Relaxed) { return Ok(true); }
 
@@ -128,13 +255,15 @@ impl<D> Fence<D> where D: SafeDeref<Target = Device> {
     pub fn wait(&self, timeout: Duration) -> Result<(), FenceWaitError> {
         unsafe {
             if self.signaled.load(Ordering::Relaxed) { return Ok(()); }
+            if self.device.is_lost() { return Err(FenceWaitError::DeviceLost(DeviceLostError)); }
 
             let timeout_ns = timeout.as_secs().saturating_mul(1_000_000_000)
                                               .saturating_add(timeout.subsec_nanos() as u64);
 
             let vk = self.device.pointers();
-            let r = try!(check_errors(vk.WaitForFences(self.device.internal_object(), 1,
-                                                       &self.fence, vk::TRUE, timeout_ns)));
+            let r = try!(self.device.check_errors(vk.WaitForFences(self.device.internal_object(),
+                                                                   1, &self.fence, vk::TRUE,
+                                                                   timeout_ns)));
 
             match r {
                 Success::Success => {
@@ -149,6 +278,19 @@ impl<D> Fence<D> where D: SafeDeref<Target = Device> {
         }
     }
 
+    /// Waits until the fence is signaled, or until the timeout has elapsed.
+    ///
+    /// Contrary to `wait`, reaching the timeout isn't considered as an error and is returned as
+    /// `WaitStatus::Timeout`.
+    #[inline]
+    pub fn wait_timeout(&self, timeout: Duration) -> Result<WaitStatus, FenceWaitError> {
+        match self.wait(timeout) {
+            Ok(()) => Ok(WaitStatus::Signaled),
+            Err(FenceWaitError::Timeout) => Ok(WaitStatus::Timeout),
+            Err(err) => Err(err),
+        }
+    }
+
     /// Waits for multiple fences at once.
     ///
     /// # Panic
@@ -156,10 +298,36 @@ impl<D> Fence<D> where D: SafeDeref<Target = Device> {
     /// Panics if not all fences belong to the same device.
     pub fn multi_wait<'a, I>(iter: I, timeout: Duration) -> Result<(), FenceWaitError>
         where I: IntoIterator<Item = &'a Fence<D>>, D: 'a
+    {
+        match try!(Fence::wait_impl(iter, true, timeout)) {
+            WaitStatus::Signaled => Ok(()),
+            WaitStatus::Timeout => Err(FenceWaitError::Timeout),
+        }
+    }
+
+    /// Waits until all the fences are signaled if `wait_all` is true, or until at least one of
+    /// them is signaled if `wait_all` is false. Also returns if the timeout has elapsed.
+    ///
+    /// If the list is empty, returns `WaitStatus::Signaled` immediately.
+    ///
+    /// # Panic
+    ///
+    /// Panics if not all fences belong to the same device.
+    #[inline]
+    pub fn wait_multiple(fences: &[&Fence<D>], wait_all: bool, timeout: Duration)
+                         -> Result<WaitStatus, FenceWaitError>
+    {
+        Fence::wait_impl(fences.iter().cloned(), wait_all, timeout)
+    }
+
+    fn wait_impl<'a, I>(iter: I, wait_all: bool, timeout: Duration)
+                        -> Result<WaitStatus, FenceWaitError>
+        where I: IntoIterator<Item = &'a Fence<D>>, D: 'a
     {
         let mut device: Option<&Device> = None;
+        let mut any_signaled = false;
 
-        let fences: SmallVec<[vk::Fence; 8]> = iter.into_iter().filter_map(|fence| {
+        let fences: SmallVec<[&Fence<D>; 8]> = iter.into_iter().filter_map(|fence| {
             match &mut device {
                 dev @ &mut None => *dev = Some(&*fence.device),
                 &mut Some(ref dev) if &**dev as *const Device == &*fence.device as *const Device => {},
@@ -168,28 +336,43 @@ impl<D> Fence<D> where D: SafeDeref<Target = Device> {
             };
 
             if fence.signaled.load(Ordering::Relaxed) {
+                any_signaled = true;
                 None
             } else {
-                Some(fence.fence)
+                Some(fence)
             }
         }).collect();
 
+        if fences.is_empty() || (any_signaled && !wait_all) {
+            return Ok(WaitStatus::Signaled);
+        }
+
+        let raw_fences: SmallVec<[vk::Fence; 8]> = fences.iter().map(|f| f.fence).collect();
+
         let timeout_ns = timeout.as_secs().saturating_mul(1_000_000_000)
                                           .saturating_add(timeout.subsec_nanos() as u64);
 
-        let r = if let Some(device) = device {
-            unsafe {
-                let vk = device.pointers();
-                try!(check_errors(vk.WaitForFences(device.internal_object(), fences.len() as u32,
-                                                   fences.as_ptr(), vk::TRUE, timeout_ns)))
-            }
-        } else {
-            return Ok(());
+        let r = unsafe {
+            let device = device.unwrap();
+            let vk = device.pointers();
+            try!(device.check_errors(vk.WaitForFences(device.internal_object(),
+                                                      raw_fences.len() as u32,
+                                                      raw_fences.as_ptr(),
+                                                      if wait_all { vk::TRUE } else { vk::FALSE },
+                                                      timeout_ns)))
         };
 
         match r {
-            Success::Success => Ok(()),
-            Success::Timeout => Err(FenceWaitError::Timeout),
+            Success::Success => {
+                // When waiting for any fence, we don't know which ones are signaled.
+                if wait_all {
+                    for fence in fences.iter() {
+                        fence.signaled.store(true, Ordering::Relaxed);
+                    }
+                }
+                Ok(WaitStatus::Signaled)
+            },
+            Success::Timeout => Ok(WaitStatus::Timeout),
             _ => unreachable!()
         }
     }
@@ -256,6 +439,15 @@ impl<D> Drop for Fence<D> where D: SafeDeref<Target = Device> {
     }
 }
 
+/// Result of waiting on one or multiple fences.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WaitStatus {
+    /// The fences are signaled.
+    Signaled,
+    /// The timeout has elapsed before the fences were signaled.
+    Timeout,
+}
+
 /// Error that can be returned when waiting on a fence.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum FenceWaitError {
@@ -265,8 +457,8 @@ pub enum FenceWaitError {
     /// The specified timeout wasn't long enough.
     Timeout,
 
-    /// The device has been lost.
-    DeviceLostError,
+    /// The connection to the device has been lost.
+    DeviceLost(DeviceLostError),
 }
 
 impl error::Error for FenceWaitError {
@@ -275,7 +467,7 @@ impl error::Error for FenceWaitError {
         match *self {
             FenceWaitError::OomError(_) => "no memory available",
             FenceWaitError::Timeout => "the timeout has been reached",
-            FenceWaitError::DeviceLostError => "the device was lost",
+            FenceWaitError::DeviceLost(_) => "the connection to the device has been lost",
         }
     }
 
@@ -283,6 +475,7 @@ impl error::Error for FenceWaitError {
     fn cause(&self) -> Option<&error::Error> {
         match *self {
             FenceWaitError::OomError(ref err) => Some(err),
+            FenceWaitError::DeviceLost(ref err) => Some(err),
             _ => None
         }
     }
@@ -295,13 +488,24 @@ impl fmt::Display for FenceWaitError {
     }
 }
 
+impl VulkanError for FenceWaitError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            FenceWaitError::OomError(ref err) => err.error_code(),
+            FenceWaitError::DeviceLost(ref err) => err.error_code(),
+            _ => None
+        }
+    }
+}
+
 impl From<Error> for FenceWaitError {
     #[inline]
     fn from(err: Error) -> FenceWaitError {
         match err {
             Error::OutOfHostMemory => FenceWaitError::OomError(From::from(err)),
             Error::OutOfDeviceMemory => FenceWaitError::OomError(From::from(err)),
-            Error::DeviceLost => FenceWaitError::DeviceLostError,
+            Error::DeviceLost => FenceWaitError::DeviceLost(DeviceLostError),
             _ => panic!("Unexpected error value: {}", err as i32)
         }
     }
@@ -311,7 +515,10 @@ impl From<Error> for FenceWaitError {
 mod tests {
     use std::sync::Arc;
     use std::time::Duration;
+    use sync::ExternalFenceHandleType;
+    use sync::ExternalSyncError;
     use sync::Fence;
+    use sync::WaitStatus;
 
     #[test]
     fn fence_create() {
@@ -337,6 +544,53 @@ mod tests {
         fence.wait(Duration::new(0, 10)).unwrap();
     }
 
+    #[test]
+    fn fence_wait_timeout() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let fence = Fence::new(device.clone());
+        assert_eq!(fence.wait_timeout(Duration::new(0, 10)).unwrap(), WaitStatus::Timeout);
+        assert!(!fence.is_signaled().unwrap());
+
+        let fence = Fence::signaled(device.clone());
+        assert_eq!(fence.wait_timeout(Duration::new(0, 10)).unwrap(), WaitStatus::Signaled);
+    }
+
+    #[test]
+    fn wait_multiple_any_all() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let signaled = Fence::signaled(device.clone());
+        let unsignaled = Fence::new(device.clone());
+        let fences = [&*signaled, &*unsignaled];
+
+        assert_eq!(Fence::wait_multiple(&fences, false, Duration::new(0, 10)).unwrap(),
+                   WaitStatus::Signaled);
+        assert_eq!(Fence::wait_multiple(&fences, true, Duration::new(0, 10)).unwrap(),
+                   WaitStatus::Timeout);
+    }
+
+    #[test]
+    fn export_not_exportable() {
+        let (device, _) = gfx_dev_and_queue!();
+        let fence = Fence::new(device.clone());
+
+        match unsafe { fence.export_fd(ExternalFenceHandleType::OpaqueFd) } {
+            Err(ExternalSyncError::NotExportable) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn exportable_missing_extension() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        match Fence::exportable(device.clone(), false, ExternalFenceHandleType::OpaqueFd) {
+            Err(ExternalSyncError::MissingExtension) => (),
+            _ => panic!()
+        }
+    }
+
     #[test]
     fn fence_reset() {
         let (device, _) = gfx_dev_and_queue!();
@@ -0,0 +1,446 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::ptr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use smallvec::SmallVec;
+
+use command_buffer::PrimaryCommandBuffer;
+use command_buffer::Submission;
+use command_buffer::pool::CommandPool;
+use command_buffer::submit_with_semaphores;
+use device::Device;
+use device::Queue;
+use device::QueueError;
+use swapchain::PresentError;
+use swapchain::PresentFuture;
+use swapchain::Swapchain;
+use sync::Fence;
+use sync::FenceWaitError;
+use sync::PipelineStages;
+use sync::Semaphore;
+
+use OomError;
+use SynchronizedVulkanObject;
+use VulkanObject;
+use VulkanPointers;
+use vk;
+
+/// Represents an event that will happen on the GPU in the future.
+///
+/// Futures are chained with the `then_*` methods. Each operation of the chain is submitted as
+/// soon as it is added, and vulkano inserts the semaphores that are needed for an operation to
+/// start only after the previous one has finished, including when they are executed on different
+/// queues. A fence can be added at the end of the chain with `then_signal_fence` in order to wait
+/// for the whole chain from the CPU.
+///
+/// A future keeps alive the resources that are used by its operations and by the operations of
+/// the futures it was built from. Destroying a future that contains command buffer submissions
+/// blocks until the GPU has finished executing them.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::sync::Arc;
+/// # use std::time::Duration;
+/// use vulkano::swapchain;
+/// use vulkano::sync::GpuFuture;
+///
+/// # let swapchain: Arc<vulkano::swapchain::Swapchain> = unsafe { std::mem::uninitialized() };
+/// # let queue: Arc<vulkano::device::Queue> = unsafe { std::mem::uninitialized() };
+/// # let command_buffer: Arc<vulkano::command_buffer::PrimaryCommandBuffer> = unsafe { std::mem::uninitialized() };
+/// let (image_num, _suboptimal, acquire) = swapchain::acquire(&swapchain, Duration::from_secs(1))
+///                                             .unwrap();
+///
+/// let future = acquire.then_execute(&queue, &command_buffer).unwrap()
+///                     .then_swapchain_present(&queue, &swapchain, image_num).unwrap()
+///                     .then_signal_fence().unwrap();
+///
+/// future.wait(Duration::from_secs(1)).unwrap();
+/// ```
+pub unsafe trait GpuFuture {
+    /// Returns the device that the operations of this future are executed on.
+    fn device(&self) -> &Arc<Device>;
+
+    /// Returns the queue that executes the last operation of this future, or `None` if the
+    /// future doesn't contain any operation submitted to a queue.
+    fn queue(&self) -> Option<&Arc<Queue>>;
+
+    /// Takes the semaphores that an operation must wait upon in order to start after this
+    /// future has finished.
+    ///
+    /// This function is supposed to be called only by vulkano's internals. It is recommended
+    /// that you never call it.
+    ///
+    /// # Safety
+    ///
+    /// Each semaphore is returned only once. The caller must make sure that the semaphores are
+    /// waited upon by an operation that is submitted to a queue, and that they are kept alive
+    /// until this operation has finished.
+    unsafe fn take_semaphores(&self) -> SmallVec<[Arc<Semaphore>; 4]>;
+
+    /// Submits a command buffer that starts executing after this future.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the queue doesn't belong to the same device as the future.
+    /// - Panics if the queue doesn't belong to the family the command buffer was created with.
+    ///
+    fn then_execute<P>(self, queue: &Arc<Queue>, command_buffer: &Arc<PrimaryCommandBuffer<P>>)
+                       -> Result<CommandBufferExecFuture<Self>, QueueError>
+        where Self: Sized,
+              P: CommandPool + 'static,
+              P::Finished: Send + Sync + 'static
+    {
+        assert_eq!(&**self.device() as *const Device, &**queue.device() as *const Device);
+
+        let semaphore = Arc::new(try!(Semaphore::raw(queue.device().clone())));
+        let stages = PipelineStages { all_commands: true, .. PipelineStages::none() };
+        // The semaphores taken from the previous future are signaled by operations that have
+        // already been submitted, and nothing else waits upon them.
+        let submission = unsafe {
+            let wait = self.take_semaphores().into_iter().map(|s| (s, stages))
+                           .collect::<SmallVec<[_; 4]>>();
+            try!(submit_with_semaphores(command_buffer, queue, &wait, &[semaphore.clone()]))
+        };
+
+        Ok(CommandBufferExecFuture {
+            previous: self,
+            queue: queue.clone(),
+            submission: submission,
+            semaphore: Mutex::new(Some(semaphore)),
+        })
+    }
+
+    /// Presents an image of a swapchain after this future.
+    ///
+    /// The image must have been acquired first, for example with `swapchain::acquire`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the swapchain or the queue don't belong to the same device as the future.
+    /// - Panics if the image wasn't acquired.
+    ///
+    #[inline]
+    fn then_swapchain_present(self, queue: &Arc<Queue>, swapchain: &Arc<Swapchain>,
+                              image_num: usize) -> Result<PresentFuture<Self>, PresentError>
+        where Self: Sized
+    {
+        PresentFuture::new(self, queue, swapchain, image_num)
+    }
+
+    /// Signals a fence after this future. The returned object can be used to wait for the
+    /// operations of the future to be finished from the CPU.
+    ///
+    /// If the future doesn't contain any operation submitted to a queue, the fence is already
+    /// signaled.
+    fn then_signal_fence(self) -> Result<FenceSignalFuture<Self>, QueueError>
+        where Self: Sized
+    {
+        let queue = self.queue().cloned();
+        let wait = unsafe { self.take_semaphores() };
+
+        let queue = match queue {
+            Some(queue) => queue,
+            None => {
+                debug_assert!(wait.is_empty());
+                let fence = Arc::new(try!(Fence::signaled_raw(self.device().clone())));
+                return Ok(FenceSignalFuture {
+                    previous: self,
+                    queue: None,
+                    fence: fence,
+                    semaphores: Mutex::new(SmallVec::new()),
+                    keep_alive: wait,
+                });
+            },
+        };
+
+        try!(queue.device().check_lost());
+        let fence = Arc::new(try!(Fence::raw(queue.device().clone())));
+        let semaphore = Arc::new(try!(Semaphore::raw(queue.device().clone())));
+
+        unsafe {
+            let vk = queue.device().pointers();
+
+            let raw_wait = wait.iter().map(|s| s.internal_object())
+                               .collect::<SmallVec<[_; 4]>>();
+            let stages = wait.iter().map(|_| vk::PIPELINE_STAGE_ALL_COMMANDS_BIT)
+                             .collect::<SmallVec<[_; 4]>>();
+            let raw_signal = semaphore.internal_object();
+
+            let infos = vk::SubmitInfo {
+                sType: vk::STRUCTURE_TYPE_SUBMIT_INFO,
+                pNext: ptr::null(),
+                waitSemaphoreCount: raw_wait.len() as u32,
+                pWaitSemaphores: raw_wait.as_ptr(),
+                pWaitDstStageMask: stages.as_ptr(),
+                commandBufferCount: 0,
+                pCommandBuffers: ptr::null(),
+                signalSemaphoreCount: 1,
+                pSignalSemaphores: &raw_signal,
+            };
+
+            try!(queue.device().check_errors(vk.QueueSubmit(*queue.internal_object_guard(), 1,
+                                                            &infos, fence.internal_object())));
+        }
+
+        queue.record_submission(fence.clone());
+
+        Ok(FenceSignalFuture {
+            previous: self,
+            queue: Some(queue),
+            fence: fence,
+            semaphores: Mutex::new({ let mut v = SmallVec::new(); v.push(semaphore); v }),
+            keep_alive: wait,
+        })
+    }
+
+    /// Joins this future with another one. The operations that are chained after the returned
+    /// future start only after both futures have finished.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the two futures don't belong to the same device.
+    ///
+    #[inline]
+    fn join<F>(self, other: F) -> JoinFuture<Self, F>
+        where Self: Sized, F: GpuFuture
+    {
+        assert_eq!(&**self.device() as *const Device, &**other.device() as *const Device);
+        JoinFuture { first: self, second: other }
+    }
+}
+
+unsafe impl<F: ?Sized> GpuFuture for Box<F> where F: GpuFuture {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        (**self).device()
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<&Arc<Queue>> {
+        (**self).queue()
+    }
+
+    #[inline]
+    unsafe fn take_semaphores(&self) -> SmallVec<[Arc<Semaphore>; 4]> {
+        (**self).take_semaphores()
+    }
+}
+
+/// Builds a future that represents "now". It can be used as the start of a chain.
+#[inline]
+pub fn now(device: &Arc<Device>) -> NowFuture {
+    NowFuture { device: device.clone() }
+}
+
+/// A future that represents "now". See `now`.
+#[derive(Debug, Clone)]
+pub struct NowFuture {
+    device: Arc<Device>,
+}
+
+unsafe impl GpuFuture for NowFuture {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<&Arc<Queue>> {
+        None
+    }
+
+    #[inline]
+    unsafe fn take_semaphores(&self) -> SmallVec<[Arc<Semaphore>; 4]> {
+        SmallVec::new()
+    }
+}
+
+/// A future that represents the execution of a command buffer after another future. See
+/// `GpuFuture::then_execute`.
+pub struct CommandBufferExecFuture<F> {
+    previous: F,
+    queue: Arc<Queue>,
+    submission: Arc<Submission>,
+    // Semaphore signaled by the submission, or `None` if it has already been taken.
+    semaphore: Mutex<Option<Arc<Semaphore>>>,
+}
+
+impl<F> CommandBufferExecFuture<F> {
+    /// Returns the submission of the command buffer.
+    #[inline]
+    pub fn submission(&self) -> &Arc<Submission> {
+        &self.submission
+    }
+
+    /// Returns the future this one was built from.
+    #[inline]
+    pub fn previous(&self) -> &F {
+        &self.previous
+    }
+}
+
+unsafe impl<F> GpuFuture for CommandBufferExecFuture<F> where F: GpuFuture {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.queue.device()
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<&Arc<Queue>> {
+        Some(&self.queue)
+    }
+
+    #[inline]
+    unsafe fn take_semaphores(&self) -> SmallVec<[Arc<Semaphore>; 4]> {
+        self.semaphore.lock().unwrap().take().into_iter().collect()
+    }
+}
+
+/// A future that signals a fence after another future. See `GpuFuture::then_signal_fence`.
+///
+/// Destroying this object blocks until the fence is signaled.
+pub struct FenceSignalFuture<F> {
+    previous: F,
+    queue: Option<Arc<Queue>>,
+    fence: Arc<Fence>,
+    // Semaphores that are signaled at the same time as the fence and that haven't been taken.
+    semaphores: Mutex<SmallVec<[Arc<Semaphore>; 4]>>,
+    // Semaphores of the previous future that are waited upon before signaling the fence.
+    keep_alive: SmallVec<[Arc<Semaphore>; 4]>,
+}
+
+impl<F> FenceSignalFuture<F> {
+    /// Returns the fence that is signaled when the future has finished.
+    #[inline]
+    pub fn fence(&self) -> &Arc<Fence> {
+        &self.fence
+    }
+
+    /// Returns true if the GPU has finished executing the operations of the future.
+    #[inline]
+    pub fn is_signaled(&self) -> Result<bool, OomError> {
+        self.fence.ready()
+    }
+
+    /// Waits until the GPU has finished executing the operations of the future.
+    #[inline]
+    pub fn wait(&self, timeout: Duration) -> Result<(), FenceWaitError> {
+        self.fence.wait(timeout)
+    }
+
+    /// Returns the future this one was built from.
+    #[inline]
+    pub fn previous(&self) -> &F {
+        &self.previous
+    }
+}
+
+unsafe impl<F> GpuFuture for FenceSignalFuture<F> where F: GpuFuture {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.previous.device()
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<&Arc<Queue>> {
+        self.queue.as_ref()
+    }
+
+    #[inline]
+    unsafe fn take_semaphores(&self) -> SmallVec<[Arc<Semaphore>; 4]> {
+        let mut semaphores = self.semaphores.lock().unwrap();
+        ::std::mem::replace(&mut *semaphores, SmallVec::new())
+    }
+}
+
+impl<F> Drop for FenceSignalFuture<F> {
+    fn drop(&mut self) {
+        // The semaphores can only be destroyed once the fence is signaled.
+        if self.queue.is_none() {
+            return;
+        }
+
+        let timeout = Duration::new(u64::max_value() / 1_000_000_000,
+                                    (u64::max_value() % 1_000_000_000) as u32);
+        match self.fence.wait(timeout) {
+            Ok(_) => (),
+            Err(FenceWaitError::DeviceLost(_)) => (),
+            Err(FenceWaitError::Timeout) => panic!(),       // The driver has some sort of problem.
+            Err(FenceWaitError::OomError(_)) => panic!(),   // What else to do here?
+        }
+    }
+}
+
+/// Two futures joined together. See `GpuFuture::join`.
+#[derive(Debug)]
+pub struct JoinFuture<A, B> {
+    first: A,
+    second: B,
+}
+
+unsafe impl<A, B> GpuFuture for JoinFuture<A, B> where A: GpuFuture, B: GpuFuture {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.first.device()
+    }
+
+    #[inline]
+    fn queue(&self) -> Option<&Arc<Queue>> {
+        self.second.queue().or(self.first.queue())
+    }
+
+    #[inline]
+    unsafe fn take_semaphores(&self) -> SmallVec<[Arc<Semaphore>; 4]> {
+        let mut semaphores = self.first.take_semaphores();
+        semaphores.extend(self.second.take_semaphores().into_iter());
+        semaphores
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use command_buffer::PrimaryCommandBufferBuilder;
+    use sync::GpuFuture;
+    use sync::now;
+
+    #[test]
+    fn now_fence_signaled() {
+        let (device, _) = gfx_dev_and_queue!();
+        let future = now(&device).then_signal_fence().unwrap();
+        assert!(future.is_signaled().unwrap());
+        future.wait(Duration::from_secs(0)).unwrap();
+    }
+
+    #[test]
+    fn execute_then_fence() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let cb1 = PrimaryCommandBufferBuilder::new(&device, queue.family()).build();
+        let cb2 = PrimaryCommandBufferBuilder::new(&device, queue.family()).build();
+
+        let future = now(&device).then_execute(&queue, &cb1).unwrap()
+                                 .then_execute(&queue, &cb2).unwrap()
+                                 .then_signal_fence().unwrap();
+        future.wait(Duration::from_secs(10)).unwrap();
+        assert!(future.previous().submission().finished());
+    }
+
+    #[test]
+    fn join_now() {
+        let (device, _) = gfx_dev_and_queue!();
+        let future = now(&device).join(now(&device));
+        assert!(future.queue().is_none());
+    }
+}
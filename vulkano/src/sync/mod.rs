@@ -18,20 +18,43 @@
 //! But don't worry ; this is automatically enforced by this library (as long as you don't use
 //! any unsafe function). See the `memory` module for more info.
 //!
+//! # Futures
+//!
+//! Operations on the GPU, such as acquiring an image of a swapchain, executing a command buffer
+//! and presenting, can be chained with the `GpuFuture` trait. Vulkano then inserts the semaphores
+//! and fences that are needed between them. A chain usually starts with `now()` or with
+//! `swapchain::acquire()`.
+//!
 
 use std::ops;
 use std::sync::Arc;
 use device::Queue;
+use instance::QueueFamily;
 use vk;
 
 pub use self::event::Event;
+pub use self::external::ExternalFenceHandleType;
+pub use self::external::ExternalSemaphoreHandleType;
+pub use self::external::ExternalSyncError;
 pub use self::fence::Fence;
 pub use self::fence::FenceWaitError;
+pub use self::fence::WaitStatus;
+pub use self::future::CommandBufferExecFuture;
+pub use self::future::FenceSignalFuture;
+pub use self::future::GpuFuture;
+pub use self::future::JoinFuture;
+pub use self::future::NowFuture;
+pub use self::future::now;
 pub use self::semaphore::Semaphore;
+pub use self::sparse::SparseBindBuilder;
+pub use self::sparse::SparseBindError;
 
 mod event;
+mod external;
 mod fence;
+mod future;
 mod semaphore;
+mod sparse;
 
 /// Base trait for objects that can be used as resources and must be synchronized.
 // TODO: remove
@@ -168,6 +191,30 @@ pipeline_stages!{
     all_commands => vk::PIPELINE_STAGE_ALL_COMMANDS_BIT,
 }
 
+impl PipelineStages {
+    /// Returns true if all the stages can be executed by queues of the given family.
+    ///
+    /// The graphics stages require a family that supports graphics operations, and the compute
+    /// shader stage requires a family that supports compute operations.
+    pub fn supported_by_queue_family(&self, family: QueueFamily) -> bool {
+        let graphics = self.vertex_input || self.vertex_shader ||
+                       self.tessellation_control_shader || self.tessellation_evaluation_shader ||
+                       self.geometry_shader || self.fragment_shader ||
+                       self.early_fragment_tests || self.late_fragment_tests ||
+                       self.color_attachment_output || self.all_graphics;
+
+        if graphics && !family.supports_graphics() {
+            return false;
+        }
+
+        if self.compute_shader && !family.supports_compute() {
+            return false;
+        }
+
+        true
+    }
+}
+
 macro_rules! access_flags {
     ($($elem:ident => $val:expr,)+) => (
         #[derive(Debug, Copy, Clone)]
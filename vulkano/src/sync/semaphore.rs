@@ -8,10 +8,13 @@
 // according to those terms.
 
 use std::mem;
+use std::os::raw::c_int;
 use std::ptr;
 use std::sync::Arc;
 
 use device::Device;
+use sync::ExternalSemaphoreHandleType;
+use sync::ExternalSyncError;
 use OomError;
 use SafeDeref;
 use VulkanObject;
@@ -27,6 +30,8 @@ use vk;
 pub struct Semaphore<D = Arc<Device>> where D: SafeDeref<Target = Device> {
     semaphore: vk::Semaphore,
     device: D,
+    // Handle types the semaphore can be exported to.
+    export_handle_types: vk::ExternalSemaphoreHandleTypeFlagsKHR,
 }
 
 impl<D> Semaphore<D> where D: SafeDeref<Target = Device> {
@@ -51,6 +56,7 @@ impl<D> Semaphore<D> where D: SafeDeref<Target = Device> {
         Ok(Semaphore {
             device: device,
             semaphore: semaphore,
+            export_handle_types: 0,
         })
     }
 
@@ -64,6 +70,119 @@ impl<D> Semaphore<D> where D: SafeDeref<Target = Device> {
     pub fn new(device: D) -> Arc<Semaphore<D>> {
         Arc::new(Semaphore::raw(device).unwrap())
     }
+
+    /// Builds a new semaphore that can later be exported with `export_fd` in order to be shared
+    /// with another API or another process.
+    pub fn exportable(device: D, handle_type: ExternalSemaphoreHandleType)
+                      -> Result<Semaphore<D>, ExternalSyncError>
+    {
+        if !handle_type.is_supported_by(device.loaded_extensions()) {
+            return Err(ExternalSyncError::MissingExtension);
+        }
+
+        let semaphore = unsafe {
+            let export_infos = vk::ExportSemaphoreCreateInfoKHR {
+                sType: vk::STRUCTURE_TYPE_EXPORT_SEMAPHORE_CREATE_INFO_KHR,
+                pNext: ptr::null(),
+                handleTypes: handle_type.to_bits(),
+            };
+
+            let infos = vk::SemaphoreCreateInfo {
+                sType: vk::STRUCTURE_TYPE_SEMAPHORE_CREATE_INFO,
+                pNext: &export_infos as *const vk::ExportSemaphoreCreateInfoKHR as *const _,
+                flags: 0,   // reserved
+            };
+
+            let vk = device.pointers();
+            let mut output = mem::uninitialized();
+            try!(check_errors(vk.CreateSemaphore(device.internal_object(), &infos,
+                                                 ptr::null(), &mut output)));
+            output
+        };
+
+        Ok(Semaphore {
+            device: device,
+            semaphore: semaphore,
+            export_handle_types: handle_type.to_bits(),
+        })
+    }
+
+    /// Exports the semaphore as a file descriptor, in order to share it with another API or
+    /// another process.
+    ///
+    /// The semaphore must have been created with `exportable` with the same handle type. Each
+    /// call returns a new file descriptor, which you are responsible for closing.
+    ///
+    /// Exporting a `SyncFd` handle unsignals the semaphore.
+    ///
+    /// # Safety
+    ///
+    /// - If `handle_type` is `SyncFd`, the semaphore must be signaled or have a pending signal
+    ///   operation.
+    ///
+    pub unsafe fn export_fd(&self, handle_type: ExternalSemaphoreHandleType)
+                            -> Result<c_int, ExternalSyncError>
+    {
+        if (self.export_handle_types & handle_type.to_bits()) == 0 {
+            return Err(ExternalSyncError::NotExportable);
+        }
+
+        let vk = self.device.pointers();
+
+        let infos = vk::SemaphoreGetFdInfoKHR {
+            sType: vk::STRUCTURE_TYPE_SEMAPHORE_GET_FD_INFO_KHR,
+            pNext: ptr::null(),
+            semaphore: self.semaphore,
+            handleType: handle_type.to_bits(),
+        };
+
+        let mut output = mem::uninitialized();
+        try!(check_errors(vk.GetSemaphoreFdKHR(self.device.internal_object(), &infos,
+                                               &mut output)));
+        Ok(output)
+    }
+
+    /// Replaces the payload of the semaphore with the one of a file descriptor that was exported
+    /// by another API or another process.
+    ///
+    /// If `temporary` is true, the imported payload is only used until the semaphore is waited
+    /// upon, after which the semaphore goes back to its original payload.
+    ///
+    /// On success, the ownership of the file descriptor is transferred to the Vulkan
+    /// implementation and you must not use or close it anymore.
+    ///
+    /// # Safety
+    ///
+    /// - The semaphore must not be in use by the GPU.
+    /// - The file descriptor must have been exported with the same handle type from a
+    ///   compatible implementation.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `temporary` is false and the handle type requires a temporary import.
+    ///
+    pub unsafe fn import_fd(&self, handle_type: ExternalSemaphoreHandleType, fd: c_int,
+                            temporary: bool) -> Result<(), ExternalSyncError>
+    {
+        assert!(temporary || !handle_type.requires_temporary_import());
+
+        if !handle_type.is_supported_by(self.device.loaded_extensions()) {
+            return Err(ExternalSyncError::MissingExtension);
+        }
+
+        let infos = vk::ImportSemaphoreFdInfoKHR {
+            sType: vk::STRUCTURE_TYPE_IMPORT_SEMAPHORE_FD_INFO_KHR,
+            pNext: ptr::null(),
+            semaphore: self.semaphore,
+            flags: if temporary { vk::SEMAPHORE_IMPORT_TEMPORARY_BIT_KHR } else { 0 },
+            handleType: handle_type.to_bits(),
+            fd: fd,
+        };
+
+        let vk = self.device.pointers();
+        try!(check_errors(vk.ImportSemaphoreFdKHR(self.device.internal_object(), &infos)));
+        Ok(())
+    }
 }
 
 unsafe impl<D> VulkanObject for Semaphore<D> where D: SafeDeref<Target = Device> {
@@ -87,6 +206,8 @@ impl<D> Drop for Semaphore<D> where D: SafeDeref<Target = Device> {
 
 #[cfg(test)]
 mod tests {
+    use sync::ExternalSemaphoreHandleType;
+    use sync::ExternalSyncError;
     use sync::Semaphore;
 
     #[test]
@@ -94,4 +215,25 @@ mod tests {
         let (device, _) = gfx_dev_and_queue!();
         let _ = Semaphore::new(device.clone());
     }
+
+    #[test]
+    fn export_not_exportable() {
+        let (device, _) = gfx_dev_and_queue!();
+        let semaphore = Semaphore::new(device.clone());
+
+        match unsafe { semaphore.export_fd(ExternalSemaphoreHandleType::OpaqueFd) } {
+            Err(ExternalSyncError::NotExportable) => (),
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn exportable_missing_extension() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        match Semaphore::exportable(device.clone(), ExternalSemaphoreHandleType::OpaqueFd) {
+            Err(ExternalSyncError::MissingExtension) => (),
+            _ => panic!()
+        }
+    }
 }
@@ -0,0 +1,623 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Binding memory to sparse resources.
+//!
+//! A buffer created with the `sparse` flag of `SparseLevel` doesn't get its memory from
+//! `bind_memory`. Instead, ranges of the buffer are bound to ranges of memory by submitting a
+//! sparse binding operation to a queue whose family supports sparse binding. This allows very
+//! large buffers to be only partially backed by memory, if the `sparse_residency` flag is set as
+//! well.
+//!
+//! Images created with `UnsafeImage::new_sparse` work the same way. Their memory can be bound
+//! either with opaque bindings, which treat the image as a linear range of bytes like a buffer,
+//! or, if the image was created with the `sparse_residency` flag, with bindings of individual
+//! regions of a mipmap level and an array layer. The latter allows keeping in memory only the
+//! parts of a very large texture that are actually used.
+//!
+//! Use a `SparseBindBuilder` to describe the operation. The parameters of each binding are
+//! checked when it is added to the builder.
+
+use std::error;
+use std::fmt;
+use std::ptr;
+use std::sync::Arc;
+use smallvec::SmallVec;
+
+use buffer::sys::UnsafeBuffer;
+use device::Device;
+use device::Queue;
+use format::FormatTy;
+use image::sys::UnsafeImage;
+use memory::DeviceMemory;
+use memory::MemoryRequirements;
+use sync::Fence;
+use sync::Semaphore;
+
+use Error;
+use OomError;
+use SynchronizedVulkanObject;
+use VulkanError;
+use VulkanObject;
+use VulkanPointers;
+use vk;
+
+/// Prototype of a sparse binding operation.
+///
+/// The builder borrows the buffers, images, memory and semaphores that are used by the operation
+/// until it is submitted.
+pub struct SparseBindBuilder<'a> {
+    // List of buffers whose bindings are modified, and the list of bindings of each buffer.
+    buffers: SmallVec<[(&'a UnsafeBuffer, SmallVec<[vk::SparseMemoryBind; 4]>); 4]>,
+    // List of images whose opaque bindings are modified.
+    images_opaque: SmallVec<[(&'a UnsafeImage, SmallVec<[vk::SparseMemoryBind; 4]>); 4]>,
+    // List of images whose bindings of regions are modified.
+    images: SmallVec<[(&'a UnsafeImage, SmallVec<[vk::SparseImageMemoryBind; 4]>); 4]>,
+    wait_semaphores: SmallVec<[vk::Semaphore; 4]>,
+    signal_semaphores: SmallVec<[vk::Semaphore; 4]>,
+}
+
+impl<'a> SparseBindBuilder<'a> {
+    /// Builds a new empty operation.
+    #[inline]
+    pub fn new() -> SparseBindBuilder<'a> {
+        SparseBindBuilder {
+            buffers: SmallVec::new(),
+            images_opaque: SmallVec::new(),
+            images: SmallVec::new(),
+            wait_semaphores: SmallVec::new(),
+            signal_semaphores: SmallVec::new(),
+        }
+    }
+
+    /// Adds a binding of the range `offset .. offset + size` of `buffer` to the memory starting
+    /// at `memory_offset` within `memory`.
+    ///
+    /// `offset`, `memory_offset` and `size` must be multiples of the alignment of the memory
+    /// requirements of the buffer. `size` doesn't need to be a multiple of the alignment if the
+    /// range ends at the end of the buffer.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `buffer` and `memory` were not created with the same device.
+    ///
+    pub fn bind_buffer(&mut self, buffer: &'a UnsafeBuffer, offset: usize,
+                       memory: &'a DeviceMemory, memory_offset: usize, size: usize)
+                       -> Result<&mut SparseBindBuilder<'a>, SparseBindError>
+    {
+        assert_eq!(&**buffer.device() as *const Device, memory.device() as *const Device);
+
+        try!(check_buffer_range(buffer, offset, size));
+        try!(check_memory_range(&buffer.memory_requirements(), memory, memory_offset, size));
+
+        push_bind(&mut self.buffers, buffer, vk::SparseMemoryBind {
+            resourceOffset: offset as vk::DeviceSize,
+            size: size as vk::DeviceSize,
+            memory: memory.internal_object(),
+            memoryOffset: memory_offset as vk::DeviceSize,
+            flags: 0,
+        });
+
+        Ok(self)
+    }
+
+    /// Adds an operation that removes the memory bound to the range `offset .. offset + size` of
+    /// `buffer`.
+    ///
+    /// The same alignment rules as `bind_buffer` apply.
+    pub fn unbind_buffer(&mut self, buffer: &'a UnsafeBuffer, offset: usize, size: usize)
+                         -> Result<&mut SparseBindBuilder<'a>, SparseBindError>
+    {
+        try!(check_buffer_range(buffer, offset, size));
+
+        push_bind(&mut self.buffers, buffer, vk::SparseMemoryBind {
+            resourceOffset: offset as vk::DeviceSize,
+            size: size as vk::DeviceSize,
+            memory: 0,
+            memoryOffset: 0,
+            flags: 0,
+        });
+
+        Ok(self)
+    }
+
+    /// Adds an opaque binding of the range `offset .. offset + size` of the memory of `image` to
+    /// the memory starting at `memory_offset` within `memory`.
+    ///
+    /// The opaque memory range of an image has the size of its memory requirements. Opaque
+    /// bindings must be used to bind the whole image if it wasn't created with the
+    /// `sparse_residency` flag, and to bind the mip tail and the metadata otherwise.
+    ///
+    /// The same alignment rules as `bind_buffer` apply.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `image` and `memory` were not created with the same device.
+    ///
+    pub fn bind_image_opaque(&mut self, image: &'a UnsafeImage, offset: usize,
+                             memory: &'a DeviceMemory, memory_offset: usize, size: usize)
+                             -> Result<&mut SparseBindBuilder<'a>, SparseBindError>
+    {
+        assert_eq!(&**image.device() as *const Device, memory.device() as *const Device);
+
+        try!(check_image_opaque_range(image, offset, size));
+        try!(check_memory_range(&image.memory_requirements(), memory, memory_offset, size));
+
+        push_bind(&mut self.images_opaque, image, vk::SparseMemoryBind {
+            resourceOffset: offset as vk::DeviceSize,
+            size: size as vk::DeviceSize,
+            memory: memory.internal_object(),
+            memoryOffset: memory_offset as vk::DeviceSize,
+            flags: 0,
+        });
+
+        Ok(self)
+    }
+
+    /// Adds an operation that removes the memory bound to the range `offset .. offset + size` of
+    /// the opaque memory range of `image`.
+    ///
+    /// The same alignment rules as `bind_buffer` apply.
+    pub fn unbind_image_opaque(&mut self, image: &'a UnsafeImage, offset: usize, size: usize)
+                               -> Result<&mut SparseBindBuilder<'a>, SparseBindError>
+    {
+        try!(check_image_opaque_range(image, offset, size));
+
+        push_bind(&mut self.images_opaque, image, vk::SparseMemoryBind {
+            resourceOffset: offset as vk::DeviceSize,
+            size: size as vk::DeviceSize,
+            memory: 0,
+            memoryOffset: 0,
+            flags: 0,
+        });
+
+        Ok(self)
+    }
+
+    /// Adds a binding of a region of a mipmap level and an array layer of `image` to the memory
+    /// starting at `memory_offset` within `memory`.
+    ///
+    /// `offset` and `extent` must be multiples of the granularity returned by
+    /// `sparse_memory_requirements`. `extent` doesn't need to be a multiple of the granularity
+    /// if the region ends at the edge of the mipmap level. The region uses one sparse block of
+    /// memory, whose size is the alignment of the memory requirements, for each block of
+    /// granularity.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `image` and `memory` were not created with the same device.
+    /// - Panics if `image` doesn't have a color format.
+    ///
+    pub fn bind_image(&mut self, image: &'a UnsafeImage, mip_level: u32, array_layer: u32,
+                      offset: [u32; 3], extent: [u32; 3], memory: &'a DeviceMemory,
+                      memory_offset: usize)
+                      -> Result<&mut SparseBindBuilder<'a>, SparseBindError>
+    {
+        assert_eq!(&**image.device() as *const Device, memory.device() as *const Device);
+
+        let size = try!(check_image_region(image, mip_level, array_layer, offset, extent));
+        try!(check_memory_range(&image.memory_requirements(), memory, memory_offset, size));
+
+        push_bind(&mut self.images, image,
+                  image_memory_bind(mip_level, array_layer, offset, extent,
+                                    memory.internal_object(), memory_offset));
+        Ok(self)
+    }
+
+    /// Adds an operation that removes the memory bound to a region of a mipmap level and an
+    /// array layer of `image`.
+    ///
+    /// The same alignment rules as `bind_image` apply.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `image` doesn't have a color format.
+    ///
+    pub fn unbind_image(&mut self, image: &'a UnsafeImage, mip_level: u32, array_layer: u32,
+                        offset: [u32; 3], extent: [u32; 3])
+                        -> Result<&mut SparseBindBuilder<'a>, SparseBindError>
+    {
+        try!(check_image_region(image, mip_level, array_layer, offset, extent));
+
+        push_bind(&mut self.images, image,
+                  image_memory_bind(mip_level, array_layer, offset, extent, 0, 0));
+        Ok(self)
+    }
+
+    /// Adds a semaphore that the operation waits upon before starting.
+    #[inline]
+    pub fn wait_semaphore(&mut self, semaphore: &'a Semaphore) -> &mut SparseBindBuilder<'a> {
+        self.wait_semaphores.push(semaphore.internal_object());
+        self
+    }
+
+    /// Adds a semaphore that is signaled once the operation is finished.
+    #[inline]
+    pub fn signal_semaphore(&mut self, semaphore: &'a Semaphore) -> &mut SparseBindBuilder<'a> {
+        self.signal_semaphores.push(semaphore.internal_object());
+        self
+    }
+
+    /// Returns true if the operation doesn't bind or unbind anything.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty() && self.images_opaque.is_empty() && self.images.is_empty()
+    }
+
+    /// Submits the operation to a queue, and optionally signals `fence` once it is finished.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if one of the resources or memories was not created with the device of the
+    ///   queue.
+    ///
+    /// # Safety
+    ///
+    /// - The ranges of the resources that are modified must not be in use by the GPU while the
+    ///   operation is executed. Use the semaphores to order the operation with command buffers.
+    /// - The memory must outlive the bindings that refer to it, and must not be used by anything
+    ///   else than the resources it is bound to, unless the resources were created with the
+    ///   `sparse_aliased` flag.
+    /// - If `fence` is `Some`, it must not be in use by another submission.
+    ///
+    pub unsafe fn submit(self, queue: &Arc<Queue>, fence: Option<&Fence>)
+                         -> Result<(), SparseBindError>
+    {
+        if !queue.family().supports_sparse_binding() {
+            return Err(SparseBindError::SparseBindingNotSupported);
+        }
+
+        for &(buffer, _) in self.buffers.iter() {
+            assert_eq!(&**buffer.device() as *const Device, &**queue.device() as *const Device);
+        }
+        let images = self.images_opaque.iter().map(|e| e.0).chain(self.images.iter().map(|e| e.0));
+        for image in images {
+            assert_eq!(&**image.device() as *const Device, &**queue.device() as *const Device);
+        }
+
+        let buffer_binds = self.buffers.iter().map(|&(buffer, ref binds)| {
+            vk::SparseBufferMemoryBindInfo {
+                buffer: buffer.internal_object(),
+                bindCount: binds.len() as u32,
+                pBinds: binds.as_ptr(),
+            }
+        }).collect::<SmallVec<[_; 4]>>();
+
+        let image_opaque_binds = self.images_opaque.iter().map(|&(image, ref binds)| {
+            vk::SparseImageOpaqueMemoryBindInfo {
+                image: image.internal_object(),
+                bindCount: binds.len() as u32,
+                pBinds: binds.as_ptr(),
+            }
+        }).collect::<SmallVec<[_; 4]>>();
+
+        let image_binds = self.images.iter().map(|&(image, ref binds)| {
+            vk::SparseImageMemoryBindInfo {
+                image: image.internal_object(),
+                bindCount: binds.len() as u32,
+                pBinds: binds.as_ptr(),
+            }
+        }).collect::<SmallVec<[_; 4]>>();
+
+        let infos = vk::BindSparseInfo {
+            sType: vk::STRUCTURE_TYPE_BIND_SPARSE_INFO,
+            pNext: ptr::null(),
+            waitSemaphoreCount: self.wait_semaphores.len() as u32,
+            pWaitSemaphores: self.wait_semaphores.as_ptr(),
+            bufferBindCount: buffer_binds.len() as u32,
+            pBufferBinds: buffer_binds.as_ptr(),
+            imageOpaqueBindCount: image_opaque_binds.len() as u32,
+            pImageOpaqueBinds: image_opaque_binds.as_ptr(),
+            imageBindCount: image_binds.len() as u32,
+            pImageBinds: image_binds.as_ptr(),
+            signalSemaphoreCount: self.signal_semaphores.len() as u32,
+            pSignalSemaphores: self.signal_semaphores.as_ptr(),
+        };
+
+        let fence = fence.map(|f| f.internal_object()).unwrap_or(0);
+
+        let vk = queue.device().pointers();
+        try!(queue.device().check_errors(vk.QueueBindSparse(*queue.internal_object_guard(), 1,
+                                                            &infos, fence)));
+        Ok(())
+    }
+}
+
+// Adds a binding to the list of bindings of `resource`.
+fn push_bind<'a, R, B>(list: &mut SmallVec<[(&'a R, SmallVec<[B; 4]>); 4]>, resource: &'a R,
+                       bind: B)
+    where R: VulkanObject, R::Object: PartialEq
+{
+    let obj = resource.internal_object();
+    if let Some(entry) = list.iter_mut().find(|e| e.0.internal_object() == obj) {
+        entry.1.push(bind);
+        return;
+    }
+
+    let mut binds = SmallVec::new();
+    binds.push(bind);
+    list.push((resource, binds));
+}
+
+// Builds the description of a binding of a region of a color image.
+fn image_memory_bind(mip_level: u32, array_layer: u32, offset: [u32; 3], extent: [u32; 3],
+                     memory: vk::DeviceMemory, memory_offset: usize) -> vk::SparseImageMemoryBind
+{
+    vk::SparseImageMemoryBind {
+        subresource: vk::ImageSubresource {
+            aspectMask: vk::IMAGE_ASPECT_COLOR_BIT,
+            mipLevel: mip_level,
+            arrayLayer: array_layer,
+        },
+        offset: vk::Offset3D { x: offset[0] as i32, y: offset[1] as i32, z: offset[2] as i32 },
+        extent: vk::Extent3D { width: extent[0], height: extent[1], depth: extent[2] },
+        memory: memory,
+        memoryOffset: memory_offset as vk::DeviceSize,
+        flags: 0,
+    }
+}
+
+// Checks that `offset .. offset + size` is a valid range of a sparse buffer.
+fn check_buffer_range(buffer: &UnsafeBuffer, offset: usize, size: usize)
+                      -> Result<(), SparseBindError>
+{
+    if !buffer.sparse_level().sparse {
+        return Err(SparseBindError::NotSparse);
+    }
+
+    if size == 0 || offset.checked_add(size).map_or(true, |end| end > buffer.size()) {
+        return Err(SparseBindError::OutOfRange);
+    }
+
+    let alignment = buffer.memory_requirements().alignment;
+    if offset % alignment != 0 || (size % alignment != 0 && offset + size != buffer.size()) {
+        return Err(SparseBindError::WrongAlignment);
+    }
+
+    Ok(())
+}
+
+// Checks that `offset .. offset + size` is a valid range of the opaque memory of a sparse image.
+fn check_image_opaque_range(image: &UnsafeImage, offset: usize, size: usize)
+                            -> Result<(), SparseBindError>
+{
+    if !image.sparse_level().sparse {
+        return Err(SparseBindError::NotSparse);
+    }
+
+    let reqs = image.memory_requirements();
+    if size == 0 || offset.checked_add(size).map_or(true, |end| end > reqs.size) {
+        return Err(SparseBindError::OutOfRange);
+    }
+
+    if offset % reqs.alignment != 0 || (size % reqs.alignment != 0 && offset + size != reqs.size) {
+        return Err(SparseBindError::WrongAlignment);
+    }
+
+    Ok(())
+}
+
+// Checks that the region is valid for a binding of a sparse image, and returns the size in bytes
+// of the memory that the region uses.
+fn check_image_region(image: &UnsafeImage, mip_level: u32, array_layer: u32, offset: [u32; 3],
+                      extent: [u32; 3]) -> Result<usize, SparseBindError>
+{
+    match image.format().ty() {
+        FormatTy::Float | FormatTy::Uint | FormatTy::Sint | FormatTy::Compressed => (),
+        _ => panic!("binding regions of sparse images is only supported for color images")
+    };
+
+    if !image.sparse_level().sparse_residency {
+        return Err(SparseBindError::NotResident);
+    }
+
+    if mip_level >= image.mipmap_levels() ||
+       array_layer >= image.dimensions().array_layers()
+    {
+        return Err(SparseBindError::OutOfRange);
+    }
+
+    let reqs = match image.sparse_memory_requirements().iter().cloned().find(|r| r.color) {
+        Some(r) => r,
+        None => return Err(SparseBindError::NotResident),
+    };
+
+    // The mip tail can only be bound with opaque bindings.
+    if mip_level >= reqs.mip_tail_first_lod {
+        return Err(SparseBindError::OutOfRange);
+    }
+
+    let mip_extent = image.dimensions().mipmap_extent(mip_level);
+    let mut num_blocks = 1;
+    for i in 0 .. 3 {
+        if extent[i] == 0 || offset[i] as u64 + extent[i] as u64 > mip_extent[i] as u64 {
+            return Err(SparseBindError::OutOfRange);
+        }
+
+        let granularity = reqs.granularity[i];
+        if offset[i] % granularity != 0 ||
+           (extent[i] % granularity != 0 && offset[i] + extent[i] != mip_extent[i])
+        {
+            return Err(SparseBindError::WrongAlignment);
+        }
+
+        num_blocks *= ((extent[i] + granularity - 1) / granularity) as usize;
+    }
+
+    Ok(num_blocks * image.memory_requirements().alignment)
+}
+
+// Checks that `memory_offset .. memory_offset + size` is a valid range of `memory` for a
+// resource with the given requirements.
+fn check_memory_range(reqs: &MemoryRequirements, memory: &DeviceMemory, memory_offset: usize,
+                      size: usize) -> Result<(), SparseBindError>
+{
+    if memory_offset % reqs.alignment != 0 {
+        return Err(SparseBindError::WrongAlignment);
+    }
+    if memory_offset.checked_add(size).map_or(true, |end| end > memory.size()) {
+        return Err(SparseBindError::OutOfRange);
+    }
+    if reqs.memory_type_bits & (1 << memory.memory_type().id()) == 0 {
+        return Err(SparseBindError::WrongMemoryType);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when building or submitting a sparse binding operation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SparseBindError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The buffer or image was not created with the `sparse` flag.
+    NotSparse,
+    /// A region of an image was bound but the image was not created with the `sparse_residency`
+    /// flag.
+    NotResident,
+    /// The range is empty or exceeds the size of the resource or of the memory.
+    OutOfRange,
+    /// An offset or a size is not a multiple of the alignment of the memory requirements of the
+    /// resource, or of the granularity of the sparse image.
+    WrongAlignment,
+    /// The memory type of the memory can't be used with the resource.
+    WrongMemoryType,
+    /// The queue family of the queue doesn't support sparse binding.
+    SparseBindingNotSupported,
+    /// The connection to the device has been lost.
+    DeviceLostError,
+}
+
+impl error::Error for SparseBindError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            SparseBindError::OomError(_) => "not enough memory",
+            SparseBindError::NotSparse => "the resource was not created with the sparse flag",
+            SparseBindError::NotResident => {
+                "the image was not created with the sparse residency flag"
+            },
+            SparseBindError::OutOfRange => {
+                "the range is empty or exceeds the size of the resource or of the memory"
+            },
+            SparseBindError::WrongAlignment => {
+                "an offset or a size is not a multiple of the required alignment"
+            },
+            SparseBindError::WrongMemoryType => {
+                "the memory type of the memory can't be used with the resource"
+            },
+            SparseBindError::SparseBindingNotSupported => {
+                "the queue family doesn't support sparse binding"
+            },
+            SparseBindError::DeviceLostError => "the connection to the device has been lost",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            SparseBindError::OomError(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for SparseBindError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl VulkanError for SparseBindError {
+    #[inline]
+    fn error_code(&self) -> Option<Error> {
+        match *self {
+            SparseBindError::OomError(ref err) => err.error_code(),
+            SparseBindError::DeviceLostError => Some(Error::DeviceLost),
+            _ => None
+        }
+    }
+}
+
+impl From<OomError> for SparseBindError {
+    #[inline]
+    fn from(err: OomError) -> SparseBindError {
+        SparseBindError::OomError(err)
+    }
+}
+
+impl From<Error> for SparseBindError {
+    #[inline]
+    fn from(err: Error) -> SparseBindError {
+        match err {
+            Error::OutOfHostMemory => SparseBindError::OomError(From::from(err)),
+            Error::OutOfDeviceMemory => SparseBindError::OomError(From::from(err)),
+            Error::DeviceLost => SparseBindError::DeviceLostError,
+            _ => panic!("Unexpected error value: {}", err as i32)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+
+    use buffer::BufferUsage;
+    use buffer::sys::SparseLevel;
+    use buffer::sys::UnsafeBuffer;
+    use format::Format;
+    use image::ImageDimensions;
+    use image::Usage as ImageUsage;
+    use image::sys::UnsafeImage;
+    use sync::Sharing;
+    use sync::SparseBindBuilder;
+    use sync::SparseBindError;
+
+    #[test]
+    fn not_sparse() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let (buffer, _) = unsafe {
+            UnsafeBuffer::new(&device, 128, &BufferUsage::all(),
+                              Sharing::Exclusive::<iter::Empty<u32>>, SparseLevel::none()).unwrap()
+        };
+
+        let mut builder = SparseBindBuilder::new();
+        match builder.unbind_buffer(&buffer, 0, 128) {
+            Err(SparseBindError::NotSparse) => (),
+            _ => panic!()
+        }
+        assert!(builder.is_empty());
+    }
+
+    #[test]
+    fn image_not_sparse() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let usage = ImageUsage { sampled: true, .. ImageUsage::none() };
+        let (image, _) = unsafe {
+            UnsafeImage::new(&device, &usage, Format::R8G8B8A8Unorm,
+                             ImageDimensions::Dim2d { width: 32, height: 32, array_layers: 1,
+                                                      cubemap_compatible: false }, 1, 1,
+                             Sharing::Exclusive::<iter::Empty<u32>>, false, false).unwrap()
+        };
+
+        let mut builder = SparseBindBuilder::new();
+        match builder.unbind_image_opaque(&image, 0, 128) {
+            Err(SparseBindError::NotSparse) => (),
+            _ => panic!()
+        }
+        match builder.unbind_image(&image, 0, 0, [0, 0, 0], [32, 32, 1]) {
+            Err(SparseBindError::NotResident) => (),
+            _ => panic!()
+        }
+        assert!(builder.is_empty());
+    }
+}